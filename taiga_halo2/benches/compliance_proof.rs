@@ -1,20 +1,17 @@
 use criterion::{criterion_group, criterion_main, Criterion};
-use halo2_proofs::{
-    arithmetic::Field,
-    plonk::{create_proof, verify_proof, SingleVerifier},
-    transcript::{Blake2bRead, Blake2bWrite},
-};
-use pasta_curves::{pallas, vesta};
+use halo2_proofs::arithmetic::Field;
+use pasta_curves::pallas;
 use rand::rngs::OsRng;
 use rand::Rng;
 use taiga_halo2::{
     compliance::ComplianceInfo,
     constant::{
         COMPLIANCE_CIRCUIT_PARAMS_SIZE, COMPLIANCE_PROVING_KEY, COMPLIANCE_VERIFYING_KEY,
-        SETUP_PARAMS_MAP, TAIGA_COMMITMENT_TREE_DEPTH,
+        DEFAULT_PARAMS_STORE, TAIGA_COMMITMENT_TREE_DEPTH,
     },
     merkle_tree::MerklePath,
     nullifier::{Nullifier, NullifierKeyContainer},
+    proof::Proof,
     resource::{Resource, ResourceKind},
 };
 
@@ -30,7 +27,7 @@ fn bench_compliance_proof(name: &str, c: &mut Criterion) {
                 ResourceKind::new(logic, label)
             };
             let value = pallas::Base::random(&mut rng);
-            let quantity: u64 = rng.gen();
+            let quantity: u128 = rng.gen();
             let rseed = pallas::Base::random(&mut rng);
             Resource {
                 kind,
@@ -38,6 +35,7 @@ fn bench_compliance_proof(name: &str, c: &mut Criterion) {
                 quantity,
                 nk_container: nk,
                 is_ephemeral: false,
+                is_transparent_kind: false,
                 nonce,
                 rseed,
             }
@@ -51,7 +49,7 @@ fn bench_compliance_proof(name: &str, c: &mut Criterion) {
                 ResourceKind::new(logic, label)
             };
             let value = pallas::Base::random(&mut rng);
-            let quantity: u64 = rng.gen();
+            let quantity: u128 = rng.gen();
             let rseed = pallas::Base::random(&mut rng);
             Resource {
                 kind,
@@ -59,6 +57,7 @@ fn bench_compliance_proof(name: &str, c: &mut Criterion) {
                 quantity,
                 nk_container: npk,
                 is_ephemeral: false,
+                is_transparent_kind: false,
                 nonce,
                 rseed,
             }
@@ -73,57 +72,46 @@ fn bench_compliance_proof(name: &str, c: &mut Criterion) {
         )
     };
     let (compliance, compliance_circuit) = compliance_info.build();
-    let params = SETUP_PARAMS_MAP
-        .get(&COMPLIANCE_CIRCUIT_PARAMS_SIZE)
+    let params = DEFAULT_PARAMS_STORE
+        .get(COMPLIANCE_CIRCUIT_PARAMS_SIZE)
         .unwrap();
 
     // Prover bench
     let prover_name = name.to_string() + "-prover";
     c.bench_function(&prover_name, |b| {
         b.iter(|| {
-            let mut transcript = Blake2bWrite::<_, vesta::Affine, _>::init(vec![]);
-            create_proof(
-                params,
+            Proof::create(
                 &COMPLIANCE_PROVING_KEY,
-                &[compliance_circuit.clone()],
-                &[&[&compliance.to_instance()]],
+                &params,
+                compliance_circuit.clone(),
+                &[&compliance.to_instance()],
                 &mut rng,
-                &mut transcript,
             )
             .unwrap();
-            let _proof = transcript.finalize();
         })
     });
 
     // Verifier bench
     // Create a proof for verifier
-    let proof = {
-        let mut transcript = Blake2bWrite::<_, vesta::Affine, _>::init(vec![]);
-        create_proof(
-            params,
-            &COMPLIANCE_PROVING_KEY,
-            &[compliance_circuit],
-            &[&[&compliance.to_instance()]],
-            &mut rng,
-            &mut transcript,
-        )
-        .unwrap();
-        transcript.finalize()
-    };
+    let proof = Proof::create(
+        &COMPLIANCE_PROVING_KEY,
+        &params,
+        compliance_circuit,
+        &[&compliance.to_instance()],
+        &mut rng,
+    )
+    .unwrap();
 
     let verifier_name = name.to_string() + "-verifier";
     c.bench_function(&verifier_name, |b| {
         b.iter(|| {
-            let strategy = SingleVerifier::new(params);
-            let mut transcript = Blake2bRead::init(&proof[..]);
-            assert!(verify_proof(
-                params,
-                &COMPLIANCE_VERIFYING_KEY,
-                strategy,
-                &[&[&compliance.to_instance()]],
-                &mut transcript
-            )
-            .is_ok());
+            assert!(proof
+                .verify(
+                    &COMPLIANCE_VERIFYING_KEY,
+                    &params,
+                    &[&compliance.to_instance()]
+                )
+                .is_ok());
         })
     });
 }
@@ -10,7 +10,7 @@ use taiga_halo2::{
         resource_logic_circuit::ResourceLogicCircuit,
         resource_logic_examples::TrivialResourceLogicCircuit,
     },
-    constant::{NUM_RESOURCE, RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE, SETUP_PARAMS_MAP},
+    constant::{DEFAULT_PARAMS_STORE, NUM_RESOURCE, RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE},
     nullifier::{Nullifier, NullifierKeyContainer},
     proof::Proof,
     resource::{Resource, ResourceKind},
@@ -29,7 +29,7 @@ fn bench_resource_logic_proof(name: &str, c: &mut Criterion) {
                 ResourceKind::new(logic, label)
             };
             let value = pallas::Base::random(&mut rng);
-            let quantity: u64 = rng.gen();
+            let quantity: u128 = rng.gen();
             let rseed = pallas::Base::random(&mut rng);
             Resource {
                 kind,
@@ -37,6 +37,7 @@ fn bench_resource_logic_proof(name: &str, c: &mut Criterion) {
                 quantity,
                 nk_container: nk,
                 is_ephemeral: false,
+                is_transparent_kind: false,
                 nonce,
                 rseed,
             }
@@ -52,7 +53,7 @@ fn bench_resource_logic_proof(name: &str, c: &mut Criterion) {
                     ResourceKind::new(logic, label)
                 };
                 let value = pallas::Base::random(&mut rng);
-                let quantity: u64 = rng.gen();
+                let quantity: u128 = rng.gen();
                 let rseed = pallas::Base::random(&mut rng);
                 Resource {
                     kind,
@@ -60,24 +61,25 @@ fn bench_resource_logic_proof(name: &str, c: &mut Criterion) {
                     quantity,
                     nk_container: npk,
                     is_ephemeral: false,
+                    is_transparent_kind: false,
                     nonce,
                     rseed,
                 }
             })
             .collect::<Vec<_>>();
-        let owned_resource_id = input_resources[0].get_nf().unwrap().inner();
+        let owned_resource_id = input_resources[0].get_nf().unwrap().into();
         TrivialResourceLogicCircuit::new(
             owned_resource_id,
             input_resources,
             output_resources.try_into().unwrap(),
         )
     };
-    let params = SETUP_PARAMS_MAP
-        .get(&RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE)
+    let params = DEFAULT_PARAMS_STORE
+        .get(RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE)
         .unwrap();
     let empty_circuit: TrivialResourceLogicCircuit = Default::default();
-    let vk = keygen_vk(params, &empty_circuit).expect("keygen_vk should not fail");
-    let pk = keygen_pk(params, vk, &empty_circuit).expect("keygen_pk should not fail");
+    let vk = keygen_vk(&params, &empty_circuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &empty_circuit).expect("keygen_pk should not fail");
     let public_inputs = resource_logic_circuit.get_public_inputs(&mut rng);
 
     // Prover bench
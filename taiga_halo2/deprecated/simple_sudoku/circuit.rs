@@ -386,10 +386,10 @@ mod tests {
     use halo2_proofs::{arithmetic::FieldExt, dev::MockProver};
     use rand::rngs::OsRng;
 
-    use halo2_proofs::{plonk, poly::commitment::Params};
-    use pasta_curves::{pallas, vesta};
+    use halo2_proofs::plonk;
+    use pasta_curves::pallas;
     use std::time::Instant;
-    use taiga_halo2::proof::Proof;
+    use taiga_halo2::proof::{setup_params, Proof};
     #[test]
     fn test_sudoku() {
         let sudoku = [
@@ -464,7 +464,7 @@ mod tests {
 
         println!("Success!");
         let time = Instant::now();
-        let params = Params::new(K);
+        let params = setup_params(K);
 
         let vk = plonk::keygen_vk(&params, &circuit).unwrap();
         let pk = plonk::keygen_pk(&params, vk.clone(), &circuit).unwrap();
@@ -506,7 +506,7 @@ mod tests {
         const K: u32 = 13;
 
         let circuit = SudokuCircuit { sudoku };
-        let params: Params<vesta::Affine> = Params::new(K);
+        let params = setup_params(K);
 
         let _vk = plonk::keygen_vk(&params, &circuit).unwrap(); // this would fail on this specific puzzle with the old implementation of synthesize
     }
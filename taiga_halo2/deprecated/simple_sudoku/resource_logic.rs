@@ -14,7 +14,7 @@ use taiga_halo2::{
             ResourceLogicVerifyingInfo,
         },
     },
-    constant::{NUM_RESOURCE, SETUP_PARAMS_MAP},
+    constant::{DEFAULT_PARAMS_STORE, NUM_RESOURCE},
     resource::{Resource, RandomSeed},
     proof::Proof,
     resource_logic_circuit_impl,
@@ -94,6 +94,7 @@ mod tests {
         constant::NUM_RESOURCE,
         resource::{Resource, RandomSeed},
         nullifier::{Nullifier, NullifierKeyContainer},
+        proof::setup_params,
         resource_logic_vk::ResourceLogicVerifyingKey,
     };
 
@@ -101,7 +102,7 @@ mod tests {
     use pasta_curves::pallas;
     use rand::rngs::OsRng;
 
-    use halo2_proofs::{plonk, poly::commitment::Params};
+    use halo2_proofs::plonk;
 
     use crate::{circuit::SudokuCircuit, resource_logic::SudokuResourceLogic};
 
@@ -125,7 +126,7 @@ mod tests {
                 [3, 2, 5, 1, 9, 7, 8, 4, 6],
             ],
         };
-        let params = Params::new(K);
+        let params = setup_params(K);
 
         let vk = plonk::keygen_vk(&params, &sudoku).unwrap();
 
@@ -1,4 +1,4 @@
-use ff::{Field, PrimeField};
+use ff::Field;
 use halo2_proofs::{
     circuit::{floor_planner, AssignedCell, Layouter, Value},
     plonk::{keygen_pk, keygen_vk, Advice, Circuit, Column, ConstraintSystem, Error},
@@ -23,10 +23,10 @@ use taiga_halo2::{
             ResourceLogicInfo, ResourceLogicPublicInputs, ResourceLogicVerifyingInfo,
         },
     },
-    constant::{NUM_RESOURCE, SETUP_PARAMS_MAP},
+    constant::{DEFAULT_PARAMS_STORE, NUM_RESOURCE},
     resource::{Resource, RandomSeed},
     proof::Proof,
-    utils::poseidon_hash,
+    utils::{hash_to_field, poseidon_hash, HashToFieldMethod},
     resource_logic_circuit_impl,
     resource_logic_vk::ResourceLogicVerifyingKey,
 };
@@ -35,6 +35,9 @@ use crate::gadgets::{
     state_check::SudokuStateCheckConfig, state_update::StateUpdateConfig,
     value_check::ValueCheckConfig,
 };
+
+const SUDOKU_STATE_PERSONALIZATION: &[u8; 16] = b"Taiga_SudokuStat";
+
 #[derive(Clone, Debug)]
 pub struct SudokuState {
     pub state: [[u8; 9]; 9],
@@ -43,47 +46,11 @@ pub struct SudokuState {
 impl SudokuState {
     pub fn encode(&self) -> pallas::Base {
         // TODO: add the rho of resource to make the app_data_static unique.
-
-        let sudoku = self.state.concat();
-        let s1 = &sudoku[..sudoku.len() / 2]; // s1 contains 40 elements
-        let s2 = &sudoku[sudoku.len() / 2..]; // s2 contains 41 elements
-        let u: Vec<u8> = s1
-            .iter()
-            .zip(s2.iter()) // zip contains 40 elements
-            .map(|(b1, b2)| {
-                // Two entries of the sudoku can be seen as [b0,b1,b2,b3] and [c0,c1,c2,c3]
-                // We store [b0,b1,b2,b3,c0,c1,c2,c3] here.
-                assert!(b1 + 16 * b2 < 255);
-                b1 + 16 * b2
-            })
-            .chain(s2.last().copied()) // there's 41st element in s2, so we add it here
-            .collect();
-
-        // fill u with zeros.
-        // The length of u is 41 bytes, or 328 bits, since we are allocating 4 bits
-        // per the first 40 integers and let the last sudoku digit takes an entire byte.
-        // We still need to add 184 bits (i.e. 23 bytes) to reach 2*256=512 bits in total.
-        // let u2 = [u, vec![0; 23]].concat(); // this is not working with all puzzles
-        // For some reason, not _any_ byte array can be transformed into a 256-bit field element.
-        // Preliminary investigation shows that `pallas::Base::from_repr` fails on a 32 byte array
-        // if the first bit of every 8-byte (== u64) chunk is set to '1'. For now, we just add a zero
-        // byte every 7 bytes, which is not ideal but works. Further investigation is needed.
-        let mut u2 = [0u8; 64];
-        let mut i = 0;
-        let mut j = 0;
-        while j != u.len() {
-            if (i + 1) % 8 != 0 {
-                u2[i] = u[j];
-                j += 1;
-            }
-            i += 1;
-        }
-        let u_first: [u8; 32] = u2[0..32].try_into().unwrap();
-        let u_last: [u8; 32] = u2[32..].try_into().unwrap();
-
-        let x = pallas::Base::from_repr(u_first).unwrap();
-        let y = pallas::Base::from_repr(u_last).unwrap();
-        poseidon_hash(x, y)
+        hash_to_field(
+            HashToFieldMethod::Poseidon,
+            SUDOKU_STATE_PERSONALIZATION,
+            &self.state.concat(),
+        )
     }
 }
 
@@ -27,7 +27,7 @@ use taiga_halo2::{
             ResourceLogicPublicInputs, ResourceLogicVerifyingInfo,
         },
     },
-    constant::{NUM_RESOURCE, SETUP_PARAMS_MAP},
+    constant::{DEFAULT_PARAMS_STORE, NUM_RESOURCE},
     resource::{Resource, RandomSeed},
     proof::Proof,
     utils::poseidon_hash,
@@ -389,10 +389,10 @@ fn test_halo2_dealer_intent_resource_logic_circuit() {
             .unwrap();
     assert_eq!(prover.verify(), Ok(()));
 
-    let params = SETUP_PARAMS_MAP.get(&12).unwrap();
-    let vk = keygen_vk(params, &circuit).expect("keygen_vk should not fail");
-    let pk = keygen_pk(params, vk.clone(), &circuit).expect("keygen_pk should not fail");
-    let proof = Proof::create(&pk, params, circuit, &[public_inputs.inner()], &mut rng).unwrap();
+    let params = DEFAULT_PARAMS_STORE.get(12).unwrap();
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk.clone(), &circuit).expect("keygen_pk should not fail");
+    let proof = Proof::create(&pk, &params, circuit, &[public_inputs.inner()], &mut rng).unwrap();
 
-    proof.verify(&vk, params, &[public_inputs.inner()]).unwrap();
+    proof.verify(&vk, &params, &[public_inputs.inner()]).unwrap();
 }
@@ -0,0 +1,213 @@
+/// The delegated proving client: Alice, Bob and Carol swap tokens in a ring as in the token swap
+/// example, but each of them only builds the witnesses of the partial transaction and delegates
+/// the proving to the server. The client assembles the proved partial transactions into the
+/// transaction and verifies it.
+/// cargo run --release --example delegated_proving_client --features example-token -- 127.0.0.1:7878
+///
+mod protocol;
+
+use group::Group;
+use halo2_proofs::arithmetic::Field;
+use pasta_curves::{group::Curve, pallas};
+use protocol::{request_proving, ProvingRequest};
+use rand::{rngs::OsRng, RngCore};
+use std::net::TcpStream;
+use taiga_halo2::{
+    circuit::{
+        resource_logic_bytecode::ApplicationByteCode,
+        resource_logic_examples::{
+            signature_verification::COMPRESSED_TOKEN_AUTH_VK,
+            token::{Token, TokenAuthorization},
+        },
+    },
+    compliance::ComplianceInfo,
+    constant::TAIGA_COMMITMENT_TREE_DEPTH,
+    merkle_tree::{Anchor, MerklePath},
+    nullifier::NullifierKeyContainer,
+    resource::Resource,
+    shielded_ptx::ShieldedPartialTransaction,
+    taiga_api::create_transaction,
+    transaction::Transaction,
+};
+
+// Exports the witnesses of a token swap partial transaction, no proof is generated here.
+#[allow(clippy::too_many_arguments)]
+pub fn create_token_swap_proving_request<R: RngCore>(
+    mut rng: R,
+    input_token: Token,
+    input_auth_sk: pallas::Scalar,
+    input_nk: pallas::Base,
+    output_token: Token,
+    output_auth_pk: pallas::Point,
+    output_npk: pallas::Base,
+) -> ProvingRequest {
+    let input_auth = TokenAuthorization::from_sk_vk(&input_auth_sk, &COMPRESSED_TOKEN_AUTH_VK);
+
+    // input resource
+    let input_resource =
+        input_token.create_random_input_token_resource(&mut rng, input_nk, &input_auth);
+
+    // output resource
+    let output_auth = TokenAuthorization::new(output_auth_pk, *COMPRESSED_TOKEN_AUTH_VK);
+    let mut output_resource =
+        output_token.create_random_output_token_resource(&mut rng, output_npk, &output_auth);
+
+    // padding the zero resources
+    let padding_input_resource = Resource::random_padding_resource(&mut rng);
+    let mut padding_output_resource = Resource::random_padding_resource(&mut rng);
+
+    let merkle_path = MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH);
+
+    // Create compliance pairs
+    let compliances = {
+        let compliance_1 = ComplianceInfo::new(
+            *input_resource.resource(),
+            merkle_path.clone(),
+            None,
+            &mut output_resource.resource,
+            &mut rng,
+        );
+
+        // Fetch a valid anchor for padding input resources
+        let anchor = Anchor::from(pallas::Base::random(&mut rng));
+        let compliance_2 = ComplianceInfo::new(
+            padding_input_resource,
+            merkle_path,
+            Some(anchor),
+            &mut padding_output_resource,
+            &mut rng,
+        );
+        vec![compliance_1, compliance_2]
+    };
+
+    // Create applications
+    let input_resources = [*input_resource.resource(), padding_input_resource];
+    let output_resources = [*output_resource.resource(), padding_output_resource];
+    let input_token_app = input_resource.generate_input_token_application(
+        &mut rng,
+        input_auth,
+        input_auth_sk,
+        input_resources,
+        output_resources,
+    );
+    let output_token_app = output_resource.generate_output_token_application(
+        &mut rng,
+        output_auth,
+        input_resources,
+        output_resources,
+    );
+    let padding_input_app = ApplicationByteCode::create_input_padding_resource_application(
+        &padding_input_resource,
+        input_resources,
+        output_resources,
+    );
+    let padding_output_app = ApplicationByteCode::create_output_padding_resource_application(
+        &padding_output_resource,
+        input_resources,
+        output_resources,
+    );
+
+    ProvingRequest {
+        compliances,
+        input_resource_apps: vec![input_token_app, padding_input_app],
+        output_resource_apps: vec![output_token_app, padding_output_app],
+        hints: vec![],
+    }
+}
+
+// Each party exports its witnesses, the server proves the partial transactions.
+pub fn create_delegated_token_swap_transaction(
+    prove: impl Fn(&ProvingRequest) -> std::io::Result<ShieldedPartialTransaction>,
+) -> std::io::Result<Transaction> {
+    let mut rng = OsRng;
+    let generator = pallas::Point::generator().to_affine();
+
+    let btc_token = Token::new("btc".to_string(), 5);
+    let eth_token = Token::new("eth".to_string(), 10);
+    let xan_token = Token::new("xan".to_string(), 15);
+
+    let alice_auth_sk = pallas::Scalar::random(&mut rng);
+    let alice_auth_pk = generator * alice_auth_sk;
+    let alice_nk = NullifierKeyContainer::random_key(&mut rng);
+    let bob_auth_sk = pallas::Scalar::random(&mut rng);
+    let bob_auth_pk = generator * bob_auth_sk;
+    let bob_nk = NullifierKeyContainer::random_key(&mut rng);
+    let carol_auth_sk = pallas::Scalar::random(&mut rng);
+    let carol_auth_pk = generator * carol_auth_sk;
+    let carol_nk = NullifierKeyContainer::random_key(&mut rng);
+
+    // Alice has 5 BTC and wants 10 ETH
+    let alice_request = create_token_swap_proving_request(
+        &mut rng,
+        btc_token.clone(),
+        alice_auth_sk,
+        alice_nk.get_nk().unwrap(),
+        eth_token.clone(),
+        alice_auth_pk,
+        alice_nk.get_npk(),
+    );
+    // Bob has 10 ETH and wants 15 XAN
+    let bob_request = create_token_swap_proving_request(
+        &mut rng,
+        eth_token,
+        bob_auth_sk,
+        bob_nk.get_nk().unwrap(),
+        xan_token.clone(),
+        bob_auth_pk,
+        bob_nk.get_npk(),
+    );
+    // Carol has 15 XAN and wants 5 BTC
+    let carol_request = create_token_swap_proving_request(
+        &mut rng,
+        xan_token,
+        carol_auth_sk,
+        carol_nk.get_nk().unwrap(),
+        btc_token,
+        carol_auth_pk,
+        carol_nk.get_npk(),
+    );
+
+    let ptxs = [alice_request, bob_request, carol_request]
+        .iter()
+        .map(prove)
+        .collect::<Result<Vec<_>, _>>()?;
+    create_transaction(ptxs, &mut rng).map_err(|e| std::io::Error::other(e.to_string()))
+}
+
+fn main() -> std::io::Result<()> {
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:7878".to_string());
+
+    // One connection per proving request
+    let tx = create_delegated_token_swap_transaction(|request| {
+        let mut stream = TcpStream::connect(&addr)?;
+        request_proving(&mut stream, request)
+    })?;
+    tx.execute()
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    println!("the delegated token swap transaction is valid");
+    Ok(())
+}
+
+#[test]
+fn test_delegated_proving() {
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = std::thread::spawn(move || {
+        for _ in 0..3 {
+            let (mut stream, _) = listener.accept().unwrap();
+            protocol::serve(&mut stream).unwrap();
+        }
+    });
+
+    let tx = create_delegated_token_swap_transaction(|request| {
+        let mut stream = TcpStream::connect(addr)?;
+        request_proving(&mut stream, request)
+    })
+    .unwrap();
+    server.join().unwrap();
+    tx.execute().unwrap();
+}
@@ -0,0 +1,77 @@
+// Shared by the client and the server, each uses a part of it
+#![allow(dead_code)]
+
+/// The wire protocol between the delegated proving client and server.
+///
+/// The client exports the witnesses of a shielded partial transaction as a `ProvingRequest`:
+/// the compliance infos and the resource logic bytecodes. The server generates the proofs and
+/// returns the partial transaction. Every message is a borsh encoding prefixed with its length
+/// as a little endian u32.
+use borsh::{BorshDeserialize, BorshSerialize};
+use rand::rngs::OsRng;
+use std::io::{self, Read, Write};
+use taiga_halo2::{
+    circuit::resource_logic_bytecode::ApplicationByteCode, compliance::ComplianceInfo,
+    shielded_ptx::ShieldedPartialTransaction, taiga_api::create_shielded_partial_transaction,
+};
+
+// Bounds the allocation for a message from the other party
+const MAX_MESSAGE_LEN: usize = 1 << 24;
+
+/// The witnesses of a shielded partial transaction to prove.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct ProvingRequest {
+    pub compliances: Vec<ComplianceInfo>,
+    pub input_resource_apps: Vec<ApplicationByteCode>,
+    pub output_resource_apps: Vec<ApplicationByteCode>,
+    pub hints: Vec<u8>,
+}
+
+/// The proved partial transaction, or the reason the server failed to prove it.
+pub type ProvingResponse = Result<ShieldedPartialTransaction, String>;
+
+pub fn write_message<W: Write, T: BorshSerialize>(writer: &mut W, message: &T) -> io::Result<()> {
+    let bytes = borsh::to_vec(message)?;
+    writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    writer.write_all(&bytes)?;
+    writer.flush()
+}
+
+pub fn read_message<R: Read, T: BorshDeserialize>(reader: &mut R) -> io::Result<T> {
+    let mut len_bytes = [0u8; 4];
+    reader.read_exact(&mut len_bytes)?;
+    let len = u32::from_le_bytes(len_bytes) as usize;
+    if len > MAX_MESSAGE_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("message of {len} bytes is too long"),
+        ));
+    }
+    let mut bytes = vec![0u8; len];
+    reader.read_exact(&mut bytes)?;
+    T::try_from_slice(&bytes)
+}
+
+/// Serves one proving request on the stream.
+pub fn serve<S: Read + Write>(stream: &mut S) -> io::Result<()> {
+    let request: ProvingRequest = read_message(stream)?;
+    let response: ProvingResponse = create_shielded_partial_transaction(
+        request.compliances,
+        request.input_resource_apps,
+        request.output_resource_apps,
+        request.hints,
+        OsRng,
+    )
+    .map_err(|e| e.to_string());
+    write_message(stream, &response)
+}
+
+/// Sends the proving request and waits for the proved partial transaction.
+pub fn request_proving<S: Read + Write>(
+    stream: &mut S,
+    request: &ProvingRequest,
+) -> io::Result<ShieldedPartialTransaction> {
+    write_message(stream, request)?;
+    let response: ProvingResponse = read_message(stream)?;
+    response.map_err(io::Error::other)
+}
@@ -0,0 +1,25 @@
+/// The delegated proving server: proves the partial transactions of the clients.
+/// cargo run --release --example delegated_proving_server --features example-token -- 127.0.0.1:7878
+///
+mod protocol;
+
+use std::net::TcpListener;
+
+fn main() -> std::io::Result<()> {
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:7878".to_string());
+    let listener = TcpListener::bind(&addr)?;
+    println!("delegated proving server listening on {addr}");
+
+    for stream in listener.incoming() {
+        let mut stream = stream?;
+        let peer = stream.peer_addr()?;
+        // A failed request only drops its connection
+        match protocol::serve(&mut stream) {
+            Ok(()) => println!("served a proving request of {peer}"),
+            Err(e) => println!("failed to serve {peer}: {e}"),
+        }
+    }
+    Ok(())
+}
@@ -114,13 +114,13 @@ pub fn create_transaction<R: RngCore + CryptoRng>(mut rng: R) -> Transaction {
             // Create resource logics for the intent
             let intent_resource_logics = {
                 let intent_resource_logic = CascadeIntentResourceLogicCircuit {
-                    owned_resource_id: cascade_intent_resource.commitment().inner(),
+                    owned_resource_id: cascade_intent_resource.commitment().into(),
                     input_resources,
                     output_resources,
                     cascade_resource_cm: cascade_intent_resource.get_label(),
                 };
 
-                ResourceLogics::new(Box::new(intent_resource_logic), vec![])
+                ResourceLogics::from_application(Box::new(intent_resource_logic))
             };
 
             (
@@ -175,13 +175,13 @@ pub fn create_transaction<R: RngCore + CryptoRng>(mut rng: R) -> Transaction {
             // Create resource_logics for the intent
             let intent_resource_logics = {
                 let intent_resource_logic = CascadeIntentResourceLogicCircuit {
-                    owned_resource_id: cascade_intent_resource.get_nf().unwrap().inner(),
+                    owned_resource_id: cascade_intent_resource.get_nf().unwrap().into(),
                     input_resources,
                     output_resources,
                     cascade_resource_cm: cascade_intent_resource.get_label(),
                 };
 
-                ResourceLogics::new(Box::new(intent_resource_logic), vec![])
+                ResourceLogics::from_application(Box::new(intent_resource_logic))
             };
 
             // Create resource logics for the input resource_3
@@ -0,0 +1,487 @@
+/// The golden path of the public API, end to end against the in-memory ledger simulator:
+/// 1. Genesis: the issuer holds the supplies of 5 "BTC" and 1 "DOLPHIN". The token resource
+///    logic rejects ephemeral token resources, so the supplies are created at genesis.
+/// 2. Block 1: the issuer mints 5 BTC to Alice and 1 DOLPHIN to Bob in a transparent ptx.
+/// 3. Block 2: Alice shields her BTC, and finds the shielded BTC by scanning the block.
+/// 4. Block 3: Alice creates an intent for 1 DOLPHIN or 2 "MONKEY" with her BTC and Bob offers
+///    his DOLPHIN for 5 BTC. The solver matches the intent with the offer and settles it.
+/// 5. Alice scans block 3 as the receiver and finds the DOLPHIN.
+/// 6. Block 4: Alice spends the DOLPHIN, paying it to Bob. Replaying the spend is rejected.
+///
+use crate::ledger::{Ledger, LedgerError};
+use ff::PrimeField;
+use halo2_proofs::arithmetic::Field;
+use pasta_curves::pallas;
+use rand::{CryptoRng, RngCore};
+use taiga_halo2::{
+    circuit::resource_logic_examples::{
+        or_relation_intent::{create_intent_resource, OrRelationIntentResourceLogicCircuit},
+        signature_verification::COMPRESSED_TOKEN_AUTH_VK,
+        token::{Token, TokenAuthorization, TokenResource},
+    },
+    compliance::ComplianceInfo,
+    merkle_tree::{MerklePath, WitnessTracker},
+    nullifier::{Nullifier, NullifierKeyContainer},
+    resource::{Resource, ResourceLogics},
+    shielded_ptx::{ShieldedPartialTransaction, ShieldedPartialTransactionBuilder},
+    solver::{IntentKind, OpenIntent, PlannedPtx, Planner, Volume},
+    transaction::{ShieldedPartialTxBundle, Transaction, TransparentPartialTxBundle},
+    transparent_ptx::TransparentPartialTransaction,
+};
+
+/// The keys and the resources of a user.
+pub struct Wallet {
+    // The receiving key, the resources paid to the user are encrypted to its public key
+    sk: pallas::Base,
+    nk: pallas::Base,
+    resources: Vec<TokenResource>,
+}
+
+impl Wallet {
+    pub fn random<R: RngCore>(mut rng: R) -> Self {
+        Self {
+            sk: pallas::Base::random(&mut rng),
+            nk: pallas::Base::random(&mut rng),
+            resources: vec![],
+        }
+    }
+
+    // The receiving key doubles as the signing key, see ResourceLogicPublicInputs::decrypt
+    pub fn auth_sk(&self) -> pallas::Scalar {
+        pallas::Scalar::from_repr(self.sk.to_repr()).unwrap()
+    }
+
+    pub fn auth(&self) -> TokenAuthorization {
+        TokenAuthorization::from_sk_vk(&self.auth_sk(), &COMPRESSED_TOKEN_AUTH_VK)
+    }
+
+    pub fn npk(&self) -> pallas::Base {
+        NullifierKeyContainer::from_key(self.nk).get_npk()
+    }
+
+    pub fn balance(&self, token: &Token) -> u128 {
+        self.resources
+            .iter()
+            .filter(|resource| resource.get_label() == token.encode_label())
+            .map(|resource| resource.quantity)
+            .sum()
+    }
+
+    /// Removes a resource of the token to spend it.
+    pub fn take(&mut self, token: &Token) -> TokenResource {
+        let index = self
+            .resources
+            .iter()
+            .position(|resource| resource.get_label() == token.encode_label())
+            .expect("no resource of the token");
+        self.resources.remove(index)
+    }
+
+    /// Finds the resources paid to the wallet in the transactions: the transparent resources are
+    /// public, the shielded ones are trial-decrypted from the resource logic public inputs.
+    pub fn scan(&mut self, txs: &[Transaction], tokens: &[Token]) {
+        for tx in txs {
+            for ptx in tx.get_transparent_ptx_bundle().partial_txs() {
+                for compliance in ptx.get_compliances() {
+                    let resource = compliance.get_output_resource();
+                    if resource.get_npk() == self.npk() && !resource.is_ephemeral {
+                        self.receive(*resource, tokens);
+                    }
+                }
+            }
+
+            let shielded_ptx_bundle = tx.get_shielded_ptx_bundle();
+            let output_cms = shielded_ptx_bundle.get_output_cms();
+            for ptx in shielded_ptx_bundle.partial_txs() {
+                for public_inputs in ptx.get_output_resource_logic_public_inputs() {
+                    let Some(resource) = public_inputs
+                        .decrypt(self.sk)
+                        .and_then(|plaintext| self.open_resource(&plaintext))
+                    else {
+                        continue;
+                    };
+                    // The commitment binds the decrypted resource to the ptx
+                    if output_cms.contains(&resource.commitment()) {
+                        self.receive(resource, tokens);
+                    }
+                }
+            }
+        }
+    }
+
+    // The plaintext is the resource encrypted by the receiver resource logic
+    fn open_resource(&self, plaintext: &[pallas::Base]) -> Option<Resource> {
+        let quantity = {
+            let repr = plaintext[3].to_repr();
+            if repr[16..].iter().any(|byte| *byte != 0) {
+                return None;
+            }
+            u128::from_le_bytes(repr[..16].try_into().unwrap())
+        };
+        if plaintext[5] != self.npk() {
+            return None;
+        }
+        Some(Resource::from_full(
+            plaintext[0],
+            plaintext[1],
+            plaintext[2],
+            quantity,
+            NullifierKeyContainer::from_key(self.nk),
+            Nullifier::from(plaintext[4]),
+            plaintext[6] == pallas::Base::one(),
+            plaintext[7],
+        ))
+    }
+
+    fn receive(&mut self, mut resource: Resource, tokens: &[Token]) {
+        let Some(token) = tokens
+            .iter()
+            .find(|token| token.encode_label() == resource.get_label())
+        else {
+            return;
+        };
+        if self
+            .resources
+            .iter()
+            .any(|owned| owned.commitment() == resource.commitment())
+        {
+            return;
+        }
+        // The nullifier key is needed to spend the resource
+        resource.nk_container = NullifierKeyContainer::from_key(self.nk);
+        self.resources.push(TokenResource {
+            token_name: token.name().clone(),
+            auth_policy: *token.auth_policy(),
+            resource,
+        });
+    }
+}
+
+/// A shielded ptx spending `input` of `owner` and paying `output_token` to the receiver.
+fn create_transfer_ptx<R: RngCore>(
+    mut rng: R,
+    ledger: &Ledger,
+    owner: &Wallet,
+    input: TokenResource,
+    output_token: &Token,
+    receiver: &Wallet,
+) -> ShieldedPartialTransaction {
+    let input_auth = owner.auth();
+    let input_auth_sk = owner.auth_sk();
+    let merkle_path = ledger.merkle_path(&input.commitment()).unwrap();
+    let output_auth = receiver.auth();
+    let output =
+        output_token.create_random_output_token_resource(&mut rng, receiver.npk(), &output_auth);
+
+    ShieldedPartialTransactionBuilder::new()
+        .add_input(
+            *input.resource(),
+            merkle_path,
+            move |rng, _, input_resources, output_resources| {
+                input.generate_input_token_resource_logics(
+                    rng,
+                    input_auth,
+                    input_auth_sk,
+                    input_resources,
+                    output_resources,
+                )
+            },
+        )
+        .add_output(
+            *output.resource(),
+            move |rng, resource, input_resources, output_resources| {
+                // The nonce of the output resource is set in the compliance pair
+                TokenResource {
+                    resource: *resource,
+                    ..output
+                }
+                .generate_output_token_resource_logics(
+                    rng,
+                    output_auth,
+                    input_resources,
+                    output_resources,
+                )
+            },
+        )
+        .build(&mut rng)
+        .unwrap()
+}
+
+/// The issuer pays the genesis resources to the receivers in a transparent ptx.
+fn create_mint_ptx<R: RngCore>(
+    mut rng: R,
+    ledger: &Ledger,
+    issuer: &Wallet,
+    mints: [(TokenResource, &Token, &Wallet); 2],
+) -> TransparentPartialTransaction {
+    let mut compliances = vec![];
+    let mut inputs = vec![];
+    let mut outputs = vec![];
+    for (input, output_token, receiver) in mints {
+        let merkle_path = ledger.merkle_path(&input.commitment()).unwrap();
+        let mut output = output_token.create_random_output_token_resource(
+            &mut rng,
+            receiver.npk(),
+            &receiver.auth(),
+        );
+        compliances.push(ComplianceInfo::new(
+            *input.resource(),
+            merkle_path,
+            None,
+            &mut output.resource,
+            &mut rng,
+        ));
+        inputs.push(input);
+        outputs.push((output, receiver.auth()));
+    }
+
+    let input_resources = [*inputs[0].resource(), *inputs[1].resource()];
+    let output_resources = [*outputs[0].0.resource(), *outputs[1].0.resource()];
+    let input_apps = inputs
+        .iter()
+        .map(|input| {
+            input.generate_input_token_application(
+                &mut rng,
+                issuer.auth(),
+                issuer.auth_sk(),
+                input_resources,
+                output_resources,
+            )
+        })
+        .collect();
+    let output_apps = outputs
+        .iter()
+        .map(|(output, auth)| {
+            output.generate_output_token_application(
+                &mut rng,
+                *auth,
+                input_resources,
+                output_resources,
+            )
+        })
+        .collect();
+    TransparentPartialTransaction::new(compliances, input_apps, output_apps, vec![])
+}
+
+pub fn run_golden_path<R: RngCore + CryptoRng>(mut rng: R) {
+    let btc = Token::new("btc".to_string(), 5u64);
+    let dolphin = Token::new("dolphin".to_string(), 1u64);
+    let monkey = Token::new("monkey".to_string(), 2u64);
+    let tokens = [btc.clone(), dolphin.clone(), monkey.clone()];
+
+    // Generate keys
+    let issuer = Wallet::random(&mut rng);
+    let mut alice = Wallet::random(&mut rng);
+    let mut bob = Wallet::random(&mut rng);
+
+    // Genesis
+    let issuer_btc = btc.create_random_input_token_resource(&mut rng, issuer.nk, &issuer.auth());
+    let issuer_dolphin =
+        dolphin.create_random_input_token_resource(&mut rng, issuer.nk, &issuer.auth());
+    let mut ledger = Ledger::genesis(&[issuer_btc.commitment(), issuer_dolphin.commitment()]);
+
+    // Block 1: mint transparently
+    let mint_ptx = create_mint_ptx(
+        &mut rng,
+        &ledger,
+        &issuer,
+        [(issuer_btc, &btc, &alice), (issuer_dolphin, &dolphin, &bob)],
+    );
+    let tx = Transaction::build(
+        &mut rng,
+        ShieldedPartialTxBundle::default(),
+        TransparentPartialTxBundle::new(vec![mint_ptx]),
+    )
+    .unwrap();
+    ledger.submit_block(vec![tx]).unwrap();
+    alice.scan(ledger.block(1), &tokens);
+    bob.scan(ledger.block(1), &tokens);
+    assert_eq!(alice.balance(&btc), 5);
+    assert_eq!(bob.balance(&dolphin), 1);
+
+    // Block 2: Alice shields her BTC
+    let alice_btc = alice.take(&btc);
+    let shield_ptx = create_transfer_ptx(&mut rng, &ledger, &alice, alice_btc, &btc, &alice);
+    let tx = Transaction::build(
+        &mut rng,
+        ShieldedPartialTxBundle::new(vec![shield_ptx]),
+        TransparentPartialTxBundle::default(),
+    )
+    .unwrap();
+    ledger.submit_block(vec![tx]).unwrap();
+    alice.scan(ledger.block(2), &tokens);
+    assert_eq!(alice.balance(&btc), 5);
+
+    // Block 3: Alice creates the intent, Bob creates the offer
+    let intent_nk = pallas::Base::random(&mut rng);
+    let alice_btc = alice.take(&btc);
+    let alice_intent_ptx = {
+        let input_auth = alice.auth();
+        let input_auth_sk = alice.auth_sk();
+        let merkle_path = ledger.merkle_path(&alice_btc.commitment()).unwrap();
+        let intent_resource = create_intent_resource(
+            &mut rng,
+            &dolphin,
+            &monkey,
+            alice.npk(),
+            input_auth.to_value(),
+            intent_nk,
+        );
+        let (token_1, token_2, receiver_npk) = (dolphin.clone(), monkey.clone(), alice.npk());
+        ShieldedPartialTransactionBuilder::new()
+            .add_input(
+                *alice_btc.resource(),
+                merkle_path,
+                move |rng, _, input_resources, output_resources| {
+                    alice_btc.generate_input_token_resource_logics(
+                        rng,
+                        input_auth,
+                        input_auth_sk,
+                        input_resources,
+                        output_resources,
+                    )
+                },
+            )
+            .add_output(
+                intent_resource,
+                move |_, resource, input_resources, output_resources| {
+                    let intent_resource_logic = OrRelationIntentResourceLogicCircuit {
+                        owned_resource_id: resource.commitment().into(),
+                        input_resources,
+                        output_resources,
+                        token_1,
+                        token_2,
+                        receiver_npk,
+                        receiver_value: input_auth.to_value(),
+                    };
+                    ResourceLogics::from_application(Box::new(intent_resource_logic))
+                },
+            )
+            .build(&mut rng)
+            .unwrap()
+    };
+    let bob_dolphin = bob.take(&dolphin);
+    let bob_offer_ptx = create_transfer_ptx(&mut rng, &ledger, &bob, bob_dolphin, &btc, &bob);
+
+    // The solver plans the settlement of the intent and the offer
+    let open_intents = [
+        OpenIntent::new(IntentKind::OrRelation {
+            sell: btc.clone(),
+            token_1: dolphin.clone(),
+            token_2: monkey.clone(),
+        }),
+        OpenIntent::new(IntentKind::OrRelation {
+            sell: dolphin.clone(),
+            token_1: btc.clone(),
+            token_2: btc.clone(),
+        }),
+    ];
+    let plan = Planner::new(Volume).plan(&open_intents);
+    assert!(plan.unmatched(&open_intents).is_empty());
+    // Bob's offer pays him the BTC already, only Alice's intent needs a solver ptx
+    let alice_output = plan
+        .ptxs(&open_intents)
+        .into_iter()
+        .find_map(|planned| match planned {
+            PlannedPtx::ConsumeOrRelationIntent { intent: 0, output } => Some(output),
+            _ => None,
+        })
+        .unwrap();
+    let solver_ptx = {
+        let intent_resource = create_intent_resource(
+            &mut rng,
+            &dolphin,
+            &monkey,
+            alice.npk(),
+            alice.auth().to_value(),
+            intent_nk,
+        );
+        let output_auth = alice.auth();
+        let output =
+            alice_output.create_random_output_token_resource(&mut rng, alice.npk(), &output_auth);
+        let (token_1, token_2, receiver_npk, receiver_value) = (
+            dolphin.clone(),
+            monkey.clone(),
+            alice.npk(),
+            alice.auth().to_value(),
+        );
+        // The intent resource is ephemeral, its anchor is the padding anchor
+        ShieldedPartialTransactionBuilder::new()
+            .with_padding_anchor(ledger.latest_anchor())
+            .add_input(
+                intent_resource,
+                MerklePath::default(),
+                move |_, resource, input_resources, output_resources| {
+                    let intent_resource_logic = OrRelationIntentResourceLogicCircuit {
+                        owned_resource_id: resource.get_nf().unwrap().into(),
+                        input_resources,
+                        output_resources,
+                        token_1,
+                        token_2,
+                        receiver_npk,
+                        receiver_value,
+                    };
+                    ResourceLogics::from_application(Box::new(intent_resource_logic))
+                },
+            )
+            .add_output(
+                *output.resource(),
+                move |rng, resource, input_resources, output_resources| {
+                    TokenResource {
+                        resource: *resource,
+                        ..output
+                    }
+                    .generate_output_token_resource_logics(
+                        rng,
+                        output_auth,
+                        input_resources,
+                        output_resources,
+                    )
+                },
+            )
+            .build(&mut rng)
+            .unwrap()
+    };
+    let tx = Transaction::build(
+        &mut rng,
+        ShieldedPartialTxBundle::new(vec![alice_intent_ptx, bob_offer_ptx, solver_ptx]),
+        TransparentPartialTxBundle::default(),
+    )
+    .unwrap();
+    ledger.submit_block(vec![tx]).unwrap();
+
+    // Alice and Bob scan as the receivers
+    alice.scan(ledger.block(3), &tokens);
+    bob.scan(ledger.block(3), &tokens);
+    assert_eq!(alice.balance(&btc), 0);
+    assert_eq!(alice.balance(&dolphin), 1);
+    assert_eq!(bob.balance(&btc), 5);
+    assert_eq!(bob.balance(&dolphin), 0);
+
+    // Block 4: Alice spends the received DOLPHIN
+    let alice_dolphin = alice.take(&dolphin);
+    let spend_ptx = create_transfer_ptx(&mut rng, &ledger, &alice, alice_dolphin, &dolphin, &bob);
+    let tx = Transaction::build(
+        &mut rng,
+        ShieldedPartialTxBundle::new(vec![spend_ptx]),
+        TransparentPartialTxBundle::default(),
+    )
+    .unwrap();
+    ledger.submit_block(vec![tx.clone()]).unwrap();
+    bob.scan(ledger.block(4), &tokens);
+    assert_eq!(bob.balance(&dolphin), 1);
+
+    // The DOLPHIN can't be spent twice
+    assert!(matches!(
+        ledger.submit_block(vec![tx]),
+        Err(LedgerError::DoubleSpend(_))
+    ));
+    assert_eq!(ledger.height(), 4);
+}
+
+#[test]
+fn test_golden_path() {
+    use rand::rngs::OsRng;
+
+    run_golden_path(OsRng);
+}
@@ -0,0 +1,179 @@
+/// The example shows how to migrate a resource to the new version of its application logic.
+/// Alice holds a resource of the version 1 kind, the application approves the upgrade to the
+/// version 2 kind and Alice migrates her resource with a migration ticket.
+///
+/// The trivial resource logic stands in for both versions of the application logic, the label of
+/// the version 2 kind commits to the approved version 1 kind. A real version 2 logic accepts the
+/// mint with `check_migration_mint`, which checks the approval in the circuit.
+use halo2_proofs::arithmetic::Field;
+use pasta_curves::pallas;
+use rand::{CryptoRng, RngCore};
+use taiga_halo2::{
+    circuit::resource_logic_examples::{
+        migration::{ApprovedUpgrades, KindUpgrade, MigrationResourceLogicCircuit},
+        TrivialResourceLogicCircuit, COMPRESSED_TRIVIAL_RESOURCE_LOGIC_VK,
+    },
+    constant::{NUM_RESOURCE, TAIGA_COMMITMENT_TREE_DEPTH},
+    merkle_tree::MerklePath,
+    nullifier::Nullifier,
+    resource::{OwnedResourceId, Resource, ResourceKind, ResourceLogics},
+    shielded_ptx::ShieldedPartialTransactionBuilder,
+    transaction::{ShieldedPartialTxBundle, Transaction, TransparentPartialTxBundle},
+    utils::poseidon_hash_n,
+};
+
+fn version_1_kind(app_label: pallas::Base) -> ResourceKind {
+    ResourceKind::new(
+        *COMPRESSED_TRIVIAL_RESOURCE_LOGIC_VK,
+        poseidon_hash_n([app_label, pallas::Base::one()]),
+    )
+}
+
+fn trivial_resource_logics(
+    owned_resource_id: OwnedResourceId,
+    input_resources: [Resource; NUM_RESOURCE],
+    output_resources: [Resource; NUM_RESOURCE],
+) -> ResourceLogics {
+    let resource_logic =
+        TrivialResourceLogicCircuit::new(owned_resource_id, input_resources, output_resources);
+    ResourceLogics::from_application(Box::new(resource_logic))
+}
+
+fn migration_resource_logics(
+    owned_resource_id: OwnedResourceId,
+    input_resources: [Resource; NUM_RESOURCE],
+    output_resources: [Resource; NUM_RESOURCE],
+    upgrade: KindUpgrade,
+) -> ResourceLogics {
+    let resource_logic = MigrationResourceLogicCircuit {
+        owned_resource_id,
+        input_resources,
+        output_resources,
+        upgrade,
+    };
+    ResourceLogics::from_application(Box::new(resource_logic))
+}
+
+/// Returns the migration transaction and the upgrades approved by the application.
+pub fn create_migration_transaction<R: RngCore + CryptoRng>(
+    mut rng: R,
+) -> (Transaction, ApprovedUpgrades) {
+    let app_label = pallas::Base::random(&mut rng);
+    let mut approved_upgrades =
+        ApprovedUpgrades::new(*COMPRESSED_TRIVIAL_RESOURCE_LOGIC_VK, app_label);
+    approved_upgrades
+        .approve(version_1_kind(app_label))
+        .unwrap();
+    let upgrade = approved_upgrades.upgrade(version_1_kind(app_label));
+
+    let alice_nk = pallas::Base::random(&mut rng);
+    let old_resource = Resource::new_input_resource(
+        upgrade.old_kind.logic,
+        upgrade.old_kind.label,
+        pallas::Base::zero(),
+        5u128,
+        alice_nk,
+        Nullifier::random(&mut rng),
+        false,
+        pallas::Base::random(&mut rng),
+    );
+    let merkle_path = MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH);
+
+    // The first partial transaction:
+    // Alice burns the version 1 resource and creates the migration ticket.
+    let ptx_1 = ShieldedPartialTransactionBuilder::new()
+        .add_input(
+            old_resource,
+            merkle_path,
+            |_, resource, input_resources, output_resources| {
+                trivial_resource_logics(
+                    resource.get_nf().unwrap().into(),
+                    input_resources,
+                    output_resources,
+                )
+            },
+        )
+        .add_output(
+            upgrade.create_burned_resource(&mut rng, &old_resource),
+            |_, resource, input_resources, output_resources| {
+                trivial_resource_logics(
+                    resource.commitment().into(),
+                    input_resources,
+                    output_resources,
+                )
+            },
+        )
+        .add_output(
+            upgrade.create_ticket_resource(&mut rng, old_resource.quantity, alice_nk),
+            move |_, resource, input_resources, output_resources| {
+                migration_resource_logics(
+                    resource.commitment().into(),
+                    input_resources,
+                    output_resources,
+                    upgrade,
+                )
+            },
+        )
+        .build(&mut rng)
+        .unwrap();
+
+    // The second partial transaction:
+    // Alice consumes the ticket and mints the version 2 resource.
+    let ptx_2 = ShieldedPartialTransactionBuilder::new()
+        .add_input(
+            upgrade.create_ticket_resource(&mut rng, old_resource.quantity, alice_nk),
+            MerklePath::default(),
+            move |_, resource, input_resources, output_resources| {
+                migration_resource_logics(
+                    resource.get_nf().unwrap().into(),
+                    input_resources,
+                    output_resources,
+                    upgrade,
+                )
+            },
+        )
+        .add_input(
+            upgrade.create_mint_resource(&mut rng, old_resource.quantity, alice_nk),
+            MerklePath::default(),
+            |_, resource, input_resources, output_resources| {
+                trivial_resource_logics(
+                    resource.get_nf().unwrap().into(),
+                    input_resources,
+                    output_resources,
+                )
+            },
+        )
+        .add_output(
+            upgrade.create_migrated_resource(&mut rng, &old_resource),
+            |_, resource, input_resources, output_resources| {
+                trivial_resource_logics(
+                    resource.commitment().into(),
+                    input_resources,
+                    output_resources,
+                )
+            },
+        )
+        .build(&mut rng)
+        .unwrap();
+
+    let shielded_tx_bundle = ShieldedPartialTxBundle::new(vec![ptx_1, ptx_2]);
+    let transparent_ptx_bundle = TransparentPartialTxBundle::default();
+    let tx = Transaction::build(&mut rng, shielded_tx_bundle, transparent_ptx_bundle).unwrap();
+    (tx, approved_upgrades)
+}
+
+#[test]
+fn test_kind_migration() {
+    use rand::rngs::OsRng;
+
+    let mut rng = OsRng;
+    let (tx, approved_upgrades) = create_migration_transaction(&mut rng);
+    tx.execute().unwrap();
+
+    // The ticket is the second output of the first ptx
+    let ptx_1 = &tx.get_shielded_ptx_bundle().partial_txs()[0];
+    let ticket_public_inputs = ptx_1.get_output_resource_logic_public_inputs()[1];
+    approved_upgrades
+        .check_public_inputs(ticket_public_inputs)
+        .unwrap();
+}
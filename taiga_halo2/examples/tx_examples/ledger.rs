@@ -0,0 +1,135 @@
+/// An in-memory ledger simulator: the append-only commitment tree, the nullifier set and the
+/// recent anchors, updated block by block.
+///
+/// A block is accepted if all its transactions execute, their anchors are recent roots and their
+/// nullifiers are not spent. The output commitments are appended to the tree in the order of the
+/// transactions and the new root becomes the latest anchor. The ledger keeps a witness of all
+/// the commitments, so it serves as the wallets' `WitnessTracker` as well.
+use std::{collections::HashSet, fmt};
+use taiga_halo2::{
+    error::TransactionError,
+    merkle_tree::{
+        Anchor, AnchorHistory, CommitmentTree, IncrementalWitness, MerklePath, WitnessTracker,
+    },
+    nullifier::Nullifier,
+    resource::ResourceCommitment,
+    transaction::Transaction,
+};
+
+const ANCHOR_HISTORY_CAPACITY: usize = 16;
+
+#[derive(Debug)]
+pub enum LedgerError {
+    /// The transaction failed to execute.
+    Transaction(TransactionError),
+    /// The anchor is not a recent root of the commitment tree.
+    UnknownAnchor(Anchor),
+    /// The nullifier is already spent, in a previous block or earlier in the block.
+    DoubleSpend(Nullifier),
+}
+
+impl fmt::Display for LedgerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LedgerError::Transaction(e) => write!(f, "Invalid transaction: {e}"),
+            LedgerError::UnknownAnchor(anchor) => write!(f, "Unknown anchor: {:?}", anchor.inner()),
+            LedgerError::DoubleSpend(nf) => {
+                write!(f, "Double spend of nullifier: {:?}", nf.inner())
+            }
+        }
+    }
+}
+
+impl From<TransactionError> for LedgerError {
+    fn from(e: TransactionError) -> Self {
+        LedgerError::Transaction(e)
+    }
+}
+
+pub struct Ledger {
+    tree: CommitmentTree,
+    witnesses: Vec<(ResourceCommitment, IncrementalWitness)>,
+    nullifiers: HashSet<Nullifier>,
+    anchors: AnchorHistory,
+    blocks: Vec<Vec<Transaction>>,
+}
+
+impl Ledger {
+    /// The ledger with the commitments of the genesis block, e.g. the initial token supplies.
+    pub fn genesis(cms: &[ResourceCommitment]) -> Self {
+        let mut ledger = Self {
+            tree: CommitmentTree::new(),
+            witnesses: vec![],
+            nullifiers: HashSet::new(),
+            anchors: AnchorHistory::new(ANCHOR_HISTORY_CAPACITY),
+            blocks: vec![],
+        };
+        for cm in cms {
+            ledger.append(*cm).unwrap();
+        }
+        ledger.anchors.push(ledger.tree.root());
+        ledger
+    }
+
+    pub fn latest_anchor(&self) -> Anchor {
+        self.anchors.latest().unwrap()
+    }
+
+    /// The height of the latest block, the genesis block is at height 0.
+    pub fn height(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// The transactions of the block at `height`, starting from 1.
+    pub fn block(&self, height: usize) -> &[Transaction] {
+        &self.blocks[height - 1]
+    }
+
+    /// Appends the block if all its transactions are valid, otherwise the ledger is unchanged.
+    pub fn submit_block(&mut self, txs: Vec<Transaction>) -> Result<(), LedgerError> {
+        let mut spent = HashSet::new();
+        let mut cms = vec![];
+        for tx in txs.iter() {
+            let result = tx.execute()?;
+            if let Some(anchor) = result
+                .anchors
+                .iter()
+                .find(|anchor| !self.anchors.contains(anchor))
+            {
+                return Err(LedgerError::UnknownAnchor(*anchor));
+            }
+            for nf in result.nullifiers {
+                if self.nullifiers.contains(&nf) || !spent.insert(nf) {
+                    return Err(LedgerError::DoubleSpend(nf));
+                }
+            }
+            cms.extend(result.output_cms);
+        }
+
+        self.nullifiers.extend(spent);
+        for cm in cms {
+            self.append(cm)?;
+        }
+        self.anchors.push(self.tree.root());
+        self.blocks.push(txs);
+        Ok(())
+    }
+
+    fn append(&mut self, cm: ResourceCommitment) -> Result<(), TransactionError> {
+        for (_, witness) in self.witnesses.iter_mut() {
+            witness.append(cm)?;
+        }
+        self.tree.append(cm)?;
+        self.witnesses.push((cm, self.tree.witness().unwrap()));
+        Ok(())
+    }
+}
+
+impl WitnessTracker for Ledger {
+    fn merkle_path(&self, cm: &ResourceCommitment) -> Option<MerklePath> {
+        self.witnesses
+            .iter()
+            .find(|(witnessed, _)| witnessed == cm)
+            .map(|(_, witness)| witness.path())
+    }
+}
@@ -1,10 +1,26 @@
 mod cascaded_partial_transactions;
+mod golden_path;
+mod kind_migration;
+mod ledger;
 mod partial_fulfillment_token_swap;
+mod pure_intent;
+mod ring_trade_with_intent;
 mod token;
 mod token_swap_with_intent;
 mod token_swap_without_intent;
+mod transparent_token_swap;
 fn main() {
     use rand::rngs::OsRng;
+    use std::time::Instant;
+
+    // Set TAIGA_CIRCUIT_REPORT to a path, with the circuit-report feature, to write the rows,
+    // the timings and the sizes of every proved circuit there as JSON.
+    #[cfg(feature = "circuit-report")]
+    let circuit_report_path = std::env::var_os("TAIGA_CIRCUIT_REPORT");
+    #[cfg(feature = "circuit-report")]
+    if circuit_report_path.is_some() {
+        taiga_halo2::circuit_report::start_recording();
+    }
 
     let rng = OsRng;
     let tx = token_swap_without_intent::create_token_swap_transaction(rng);
@@ -18,4 +34,36 @@ fn main() {
 
     let tx = cascaded_partial_transactions::create_transaction(rng);
     tx.execute().unwrap();
+
+    let (intent_tx, settlement_tx) = pure_intent::create_pure_intent_transactions(rng);
+    intent_tx.execute().unwrap();
+    settlement_tx.execute().unwrap();
+
+    let tx = transparent_token_swap::create_transparent_token_swap_transaction(rng);
+    tx.execute().unwrap();
+
+    golden_path::run_golden_path(rng);
+
+    let (tx, _) = kind_migration::create_migration_transaction(rng);
+    tx.execute().unwrap();
+
+    // The ring trade bundles six ptxs, report the timings as a data point for larger bundles.
+    let start = Instant::now();
+    let tx = ring_trade_with_intent::create_ring_trade_transaction(rng);
+    let proving_time = start.elapsed();
+    let start = Instant::now();
+    tx.execute().unwrap();
+    let verifying_time = start.elapsed();
+    println!("ring trade with 6 ptxs: proving {proving_time:?}, verifying {verifying_time:?}");
+
+    #[cfg(feature = "circuit-report")]
+    if let Some(path) = circuit_report_path {
+        let report = taiga_halo2::circuit_report::finish_recording().unwrap();
+        report.write_json(&path).unwrap();
+        println!(
+            "circuit report of {} proofs written to {}",
+            report.circuits.len(),
+            path.to_string_lossy()
+        );
+    }
 }
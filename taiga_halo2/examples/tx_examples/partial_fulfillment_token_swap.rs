@@ -76,13 +76,13 @@ pub fn create_token_intent_ptx<R: RngCore>(
         // Create resource_logics for the intent
         let intent_resource_logics = {
             let intent_resource_logic = PartialFulfillmentIntentResourceLogicCircuit {
-                owned_resource_id: intent_resource.commitment().inner(),
+                owned_resource_id: intent_resource.commitment().into(),
                 input_resources,
                 output_resources,
                 swap: swap.clone(),
             };
 
-            ResourceLogics::new(Box::new(intent_resource_logic), vec![])
+            ResourceLogics::from_application(Box::new(intent_resource_logic))
         };
 
         // Create resource_logics for the padding input
@@ -165,18 +165,19 @@ pub fn consume_token_intent_ptx<R: RngCore>(
         // Create resource_logics for the intent
         let intent_resource_logics = {
             let intent_resource_logic = PartialFulfillmentIntentResourceLogicCircuit {
-                owned_resource_id: intent_resource.get_nf().unwrap().inner(),
+                owned_resource_id: intent_resource.get_nf().unwrap().into(),
                 input_resources,
                 output_resources,
                 swap: swap.clone(),
             };
 
-            ResourceLogics::new(Box::new(intent_resource_logic), vec![])
+            ResourceLogics::from_application(Box::new(intent_resource_logic))
         };
 
         // Create resource_logics for the bought_resource
         let bought_resource_resource_logics = TokenResource {
             token_name: swap.buy.name().clone(),
+            auth_policy: *swap.buy.auth_policy(),
             resource: bought_resource,
         }
         .generate_output_token_resource_logics(
@@ -197,6 +198,7 @@ pub fn consume_token_intent_ptx<R: RngCore>(
         // Create resource_logics for the returned_resource
         let returned_resource_resource_logics = TokenResource {
             token_name: swap.sell.token_name().clone(),
+            auth_policy: swap.sell.auth_policy,
             resource: returned_resource,
         }
         .generate_output_token_resource_logics(
@@ -0,0 +1,227 @@
+/// Token swap example with a pure intent resource
+/// Alice wants 1 "DOLPHIN" or 2 "Monkeys" and publishes a pure intent for it before committing
+/// any funds: the intent is of quantity zero and no real resource is consumed to create it.
+/// Bob has 1 "DOLPHIN" and wants 5 "BTC". Bob matches the intent, and Alice commits her 5 "BTC"
+/// in the settlement tx.
+///
+use crate::token::create_token_swap_ptx;
+use group::Group;
+use halo2_proofs::arithmetic::Field;
+use pasta_curves::{group::Curve, pallas};
+use rand::{CryptoRng, RngCore};
+use taiga_halo2::{
+    circuit::resource_logic_examples::{
+        or_relation_intent::{create_pure_intent_resource, OrRelationIntentResourceLogicCircuit},
+        signature_verification::COMPRESSED_TOKEN_AUTH_VK,
+        token::{Token, TokenAuthorization, TokenResource},
+    },
+    constant::TAIGA_COMMITMENT_TREE_DEPTH,
+    merkle_tree::MerklePath,
+    nullifier::NullifierKeyContainer,
+    resource::ResourceLogics,
+    shielded_ptx::{ShieldedPartialTransaction, ShieldedPartialTransactionBuilder},
+    transaction::{ShieldedPartialTxBundle, Transaction, TransparentPartialTxBundle},
+};
+
+pub fn create_pure_intent_ptx<R: RngCore>(
+    mut rng: R,
+    token_1: Token,
+    token_2: Token,
+    receiver_npk: pallas::Base,
+    receiver_value: pallas::Base,
+    intent_nk: pallas::Base,
+) -> ShieldedPartialTransaction {
+    let intent_resource = create_pure_intent_resource(
+        &mut rng,
+        &token_1,
+        &token_2,
+        receiver_npk,
+        receiver_value,
+        intent_nk,
+    );
+
+    // The intent is paired with a padding input resource
+    ShieldedPartialTransactionBuilder::new()
+        .add_intent(
+            intent_resource,
+            |_, resource, input_resources, output_resources| {
+                let intent_resource_logic = OrRelationIntentResourceLogicCircuit {
+                    owned_resource_id: resource.commitment().into(),
+                    input_resources,
+                    output_resources,
+                    token_1,
+                    token_2,
+                    receiver_npk,
+                    receiver_value,
+                };
+                ResourceLogics::from_application(Box::new(intent_resource_logic))
+            },
+        )
+        .build(&mut rng)
+        .unwrap()
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn consume_pure_intent_ptx<R: RngCore>(
+    mut rng: R,
+    token_1: Token,
+    token_2: Token,
+    receiver_npk: pallas::Base,
+    receiver_value: pallas::Base,
+    intent_nk: pallas::Base,
+    input_token: Token,
+    input_auth_sk: pallas::Scalar,
+    input_nk: pallas::Base,
+    receiver_auth_pk: pallas::Point,
+) -> ShieldedPartialTransaction {
+    // input intent resource
+    let intent_resource = create_pure_intent_resource(
+        &mut rng,
+        &token_1,
+        &token_2,
+        receiver_npk,
+        receiver_value,
+        intent_nk,
+    );
+
+    // input token resource
+    let input_auth = TokenAuthorization::from_sk_vk(&input_auth_sk, &COMPRESSED_TOKEN_AUTH_VK);
+    let input_resource =
+        input_token.create_random_input_token_resource(&mut rng, input_nk, &input_auth);
+
+    // output token resource, the intent checks it's the first output resource
+    let output_auth = TokenAuthorization::new(receiver_auth_pk, *COMPRESSED_TOKEN_AUTH_VK);
+    let output_resource =
+        input_token.create_random_output_token_resource(&mut rng, receiver_npk, &output_auth);
+
+    // The intent is ephemeral, its merkle path is not checked
+    let intent_merkle_path = MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH);
+    let merkle_path = MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH);
+    ShieldedPartialTransactionBuilder::new()
+        .add_input(
+            intent_resource,
+            intent_merkle_path,
+            |_, resource, input_resources, output_resources| {
+                let intent_resource_logic = OrRelationIntentResourceLogicCircuit {
+                    owned_resource_id: resource.get_nf().unwrap().into(),
+                    input_resources,
+                    output_resources,
+                    token_1,
+                    token_2,
+                    receiver_npk,
+                    receiver_value,
+                };
+                ResourceLogics::from_application(Box::new(intent_resource_logic))
+            },
+        )
+        .add_input(
+            *input_resource.resource(),
+            merkle_path,
+            |rng, _, input_resources, output_resources| {
+                input_resource.generate_input_token_resource_logics(
+                    rng,
+                    input_auth,
+                    input_auth_sk,
+                    input_resources,
+                    output_resources,
+                )
+            },
+        )
+        .add_output(
+            *output_resource.resource(),
+            |rng, resource, input_resources, output_resources| {
+                // The nonce of the output resource is set in the compliance pair
+                TokenResource {
+                    resource: *resource,
+                    ..output_resource.clone()
+                }
+                .generate_output_token_resource_logics(
+                    rng,
+                    output_auth,
+                    input_resources,
+                    output_resources,
+                )
+            },
+        )
+        .build(&mut rng)
+        .unwrap()
+}
+
+// Returns the tx publishing the intent and the tx settling it
+pub fn create_pure_intent_transactions<R: RngCore + CryptoRng>(
+    mut rng: R,
+) -> (Transaction, Transaction) {
+    let generator = pallas::Point::generator().to_affine();
+
+    // Alice publishes the pure intent, no funds are committed yet
+    let alice_auth_sk = pallas::Scalar::random(&mut rng);
+    let alice_auth_pk = generator * alice_auth_sk;
+    let alice_nk = NullifierKeyContainer::random_key(&mut rng);
+    let alice_auth = TokenAuthorization::from_sk_vk(&alice_auth_sk, &COMPRESSED_TOKEN_AUTH_VK);
+    let receiver_npk = alice_nk.get_npk();
+    let receiver_value = alice_auth.to_value();
+    let token_1 = Token::new("dolphin".to_string(), 1u64);
+    let token_2 = Token::new("monkey".to_string(), 2u64);
+    let intent_ptx = create_pure_intent_ptx(
+        &mut rng,
+        token_1.clone(),
+        token_2.clone(),
+        receiver_npk,
+        receiver_value,
+        alice_nk.get_nk().unwrap(),
+    );
+    // The intent ptx is balanced on its own
+    let intent_tx = Transaction::build(
+        &mut rng,
+        ShieldedPartialTxBundle::new(vec![intent_ptx]),
+        TransparentPartialTxBundle::default(),
+    )
+    .unwrap();
+
+    // Bob consumes the intent with his 1 DOLPHIN, sent to Alice
+    let bob_auth_sk = pallas::Scalar::random(&mut rng);
+    let bob_auth_pk = generator * bob_auth_sk;
+    let bob_nk = NullifierKeyContainer::random_key(&mut rng);
+    let bob_ptx = consume_pure_intent_ptx(
+        &mut rng,
+        token_1.clone(),
+        token_2,
+        receiver_npk,
+        receiver_value,
+        alice_nk.get_nk().unwrap(),
+        token_1,
+        bob_auth_sk,
+        bob_nk.get_nk().unwrap(),
+        alice_auth_pk,
+    );
+
+    // Alice commits her 5 BTC to Bob
+    let btc_token = Token::new("btc".to_string(), 5u64);
+    let alice_ptx = create_token_swap_ptx(
+        &mut rng,
+        btc_token.clone(),
+        alice_auth_sk,
+        alice_nk.get_nk().unwrap(),
+        btc_token,
+        bob_auth_pk,
+        bob_nk.get_npk(),
+    );
+
+    let settlement_tx = Transaction::build(
+        &mut rng,
+        ShieldedPartialTxBundle::new(vec![bob_ptx, alice_ptx]),
+        TransparentPartialTxBundle::default(),
+    )
+    .unwrap();
+    (intent_tx, settlement_tx)
+}
+
+#[test]
+fn test_pure_intent_tx() {
+    use rand::rngs::OsRng;
+
+    let mut rng = OsRng;
+    let (intent_tx, settlement_tx) = create_pure_intent_transactions(&mut rng);
+    intent_tx.execute().unwrap();
+    settlement_tx.execute().unwrap();
+}
@@ -0,0 +1,126 @@
+/// Three-party ring trade with or-relation intent resources
+/// Alice has 5 "BTC" and wants 10 "ETH" or 1 "DOLPHIN".
+/// Bob has 10 "ETH" and wants 15 "XAN" or 2 "MONKEY".
+/// Carol has 15 "XAN" and wants 5 "BTC" or 3 "PENGUIN".
+/// No pair of them can swap, but the Solver settles the ring in one transaction: it consumes
+/// the three intents and sends each of them the token they want. The transaction bundles six
+/// partial transactions, three intent ptxs and three solver ptxs.
+///
+use crate::token_swap_with_intent::{consume_token_intent_ptx, create_token_intent_ptx};
+use group::Group;
+use halo2_proofs::arithmetic::Field;
+use pasta_curves::{group::Curve, pallas};
+use rand::{CryptoRng, RngCore};
+use taiga_halo2::{
+    circuit::resource_logic_examples::token::Token,
+    transaction::{ShieldedPartialTxBundle, Transaction, TransparentPartialTxBundle},
+};
+
+pub fn create_ring_trade_transaction<R: RngCore + CryptoRng>(mut rng: R) -> Transaction {
+    let generator = pallas::Point::generator().to_affine();
+
+    let btc_token = Token::new("btc".to_string(), 5u64);
+    let eth_token = Token::new("eth".to_string(), 10u64);
+    let xan_token = Token::new("xan".to_string(), 15u64);
+
+    // Alice creates the partial transaction with 5 BTC input and intent output
+    let alice_auth_sk = pallas::Scalar::random(&mut rng);
+    let alice_auth_pk = generator * alice_auth_sk;
+    let alice_nk = pallas::Base::random(&mut rng);
+    let alice_alternative = Token::new("dolphin".to_string(), 1u64);
+    let (alice_ptx, alice_intent_nk, alice_receiver_npk, alice_receiver_value) =
+        create_token_intent_ptx(
+            &mut rng,
+            eth_token.clone(),
+            alice_alternative.clone(),
+            btc_token.clone(),
+            alice_auth_sk,
+            alice_nk,
+        );
+
+    // Bob creates the partial transaction with 10 ETH input and intent output
+    let bob_auth_sk = pallas::Scalar::random(&mut rng);
+    let bob_auth_pk = generator * bob_auth_sk;
+    let bob_nk = pallas::Base::random(&mut rng);
+    let bob_alternative = Token::new("monkey".to_string(), 2u64);
+    let (bob_ptx, bob_intent_nk, bob_receiver_npk, bob_receiver_value) = create_token_intent_ptx(
+        &mut rng,
+        xan_token.clone(),
+        bob_alternative.clone(),
+        eth_token.clone(),
+        bob_auth_sk,
+        bob_nk,
+    );
+
+    // Carol creates the partial transaction with 15 XAN input and intent output
+    let carol_auth_sk = pallas::Scalar::random(&mut rng);
+    let carol_auth_pk = generator * carol_auth_sk;
+    let carol_nk = pallas::Base::random(&mut rng);
+    let carol_alternative = Token::new("penguin".to_string(), 3u64);
+    let (carol_ptx, carol_intent_nk, carol_receiver_npk, carol_receiver_value) =
+        create_token_intent_ptx(
+            &mut rng,
+            btc_token.clone(),
+            carol_alternative.clone(),
+            xan_token.clone(),
+            carol_auth_sk,
+            carol_nk,
+        );
+
+    // Solver consumes Alice's intent and sends her the ETH from Bob
+    let solver_alice_ptx = consume_token_intent_ptx(
+        &mut rng,
+        eth_token.clone(),
+        alice_alternative,
+        alice_intent_nk,
+        alice_receiver_npk,
+        alice_receiver_value,
+        eth_token,
+        alice_auth_pk,
+    );
+
+    // Solver consumes Bob's intent and sends him the XAN from Carol
+    let solver_bob_ptx = consume_token_intent_ptx(
+        &mut rng,
+        xan_token.clone(),
+        bob_alternative,
+        bob_intent_nk,
+        bob_receiver_npk,
+        bob_receiver_value,
+        xan_token,
+        bob_auth_pk,
+    );
+
+    // Solver consumes Carol's intent and sends her the BTC from Alice
+    let solver_carol_ptx = consume_token_intent_ptx(
+        &mut rng,
+        btc_token.clone(),
+        carol_alternative,
+        carol_intent_nk,
+        carol_receiver_npk,
+        carol_receiver_value,
+        btc_token,
+        carol_auth_pk,
+    );
+
+    // Solver creates the final transaction, the ring is only balanced as a whole
+    let shielded_tx_bundle = ShieldedPartialTxBundle::new(vec![
+        alice_ptx,
+        bob_ptx,
+        carol_ptx,
+        solver_alice_ptx,
+        solver_bob_ptx,
+        solver_carol_ptx,
+    ]);
+    let transparent_ptx_bundle = TransparentPartialTxBundle::default();
+    Transaction::build(&mut rng, shielded_tx_bundle, transparent_ptx_bundle).unwrap()
+}
+
+#[test]
+fn test_ring_trade_tx() {
+    use rand::rngs::OsRng;
+
+    let mut rng = OsRng;
+    let tx = create_ring_trade_transaction(&mut rng);
+    tx.execute().unwrap();
+}
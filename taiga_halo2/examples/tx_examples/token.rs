@@ -1,22 +1,47 @@
-use halo2_proofs::arithmetic::Field;
-
 use pasta_curves::pallas;
 use rand::RngCore;
 
 use taiga_halo2::{
-    circuit::resource_logic_examples::{
-        signature_verification::COMPRESSED_TOKEN_AUTH_VK,
-        token::{Token, TokenAuthorization},
+    circuit::{
+        resource_logic_bytecode::ApplicationByteCode,
+        resource_logic_examples::{
+            signature_verification::COMPRESSED_TOKEN_AUTH_VK,
+            token::{Token, TokenAuthorization, TokenResource},
+        },
     },
     compliance::ComplianceInfo,
     constant::TAIGA_COMMITMENT_TREE_DEPTH,
-    merkle_tree::{Anchor, MerklePath},
-    resource::{Resource, ResourceLogics},
-    shielded_ptx::ShieldedPartialTransaction,
+    merkle_tree::MerklePath,
+    prover_options::ProverOptions,
+    resource::Resource,
+    shielded_ptx::{ShieldedPartialTransaction, ShieldedPartialTransactionBuilder},
+    transparent_ptx::TransparentPartialTransaction,
 };
 
 #[allow(clippy::too_many_arguments)]
 pub fn create_token_swap_ptx<R: RngCore>(
+    rng: R,
+    input_token: Token,
+    input_auth_sk: pallas::Scalar,
+    input_nk: pallas::Base,
+    output_token: Token,
+    output_auth_pk: pallas::Point,
+    output_npk: pallas::Base,
+) -> ShieldedPartialTransaction {
+    create_token_swap_ptx_with_options(
+        rng,
+        input_token,
+        input_auth_sk,
+        input_nk,
+        output_token,
+        output_auth_pk,
+        output_npk,
+        &ProverOptions::default(),
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_token_swap_ptx_with_options<R: RngCore>(
     mut rng: R,
     input_token: Token,
     input_auth_sk: pallas::Scalar,
@@ -24,9 +49,69 @@ pub fn create_token_swap_ptx<R: RngCore>(
     output_token: Token,
     output_auth_pk: pallas::Point,
     output_npk: pallas::Base,
+    options: &ProverOptions,
 ) -> ShieldedPartialTransaction {
     let input_auth = TokenAuthorization::from_sk_vk(&input_auth_sk, &COMPRESSED_TOKEN_AUTH_VK);
 
+    // input resource
+    let input_resource =
+        input_token.create_random_input_token_resource(&mut rng, input_nk, &input_auth);
+
+    // output resource
+    let output_auth = TokenAuthorization::new(output_auth_pk, *COMPRESSED_TOKEN_AUTH_VK);
+    let output_resource =
+        output_token.create_random_output_token_resource(&mut rng, output_npk, &output_auth);
+
+    // The builder pads the free slots with padding resources and creates the compliance pairs
+    let merkle_path = MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH);
+    ShieldedPartialTransactionBuilder::new()
+        .add_input(
+            *input_resource.resource(),
+            merkle_path,
+            |rng, _, input_resources, output_resources| {
+                input_resource.generate_input_token_resource_logics(
+                    rng,
+                    input_auth,
+                    input_auth_sk,
+                    input_resources,
+                    output_resources,
+                )
+            },
+        )
+        .add_output(
+            *output_resource.resource(),
+            |rng, resource, input_resources, output_resources| {
+                // The nonce of the output resource is set in the compliance pair
+                TokenResource {
+                    resource: *resource,
+                    ..output_resource.clone()
+                }
+                .generate_output_token_resource_logics(
+                    rng,
+                    output_auth,
+                    input_resources,
+                    output_resources,
+                )
+            },
+        )
+        .build_with_options(options, &mut rng)
+        .unwrap()
+}
+
+// The transparent counterpart of create_token_swap_ptx: the resources are public and the
+// resource logics are executed instead of proved.
+#[allow(clippy::too_many_arguments)]
+pub fn create_transparent_token_swap_ptx<R: RngCore>(
+    mut rng: R,
+    input_token: Token,
+    input_auth_sk: pallas::Scalar,
+    input_nk: pallas::Base,
+    output_token: Token,
+    output_auth_pk: pallas::Point,
+    output_npk: pallas::Base,
+) -> TransparentPartialTransaction {
+    let input_auth = TokenAuthorization::from_sk_vk(&input_auth_sk, &COMPRESSED_TOKEN_AUTH_VK);
+
     // input resource
     let input_resource =
         input_token.create_random_input_token_resource(&mut rng, input_nk, &input_auth);
@@ -40,7 +125,7 @@ pub fn create_token_swap_ptx<R: RngCore>(
     let padding_input_resource = Resource::random_padding_resource(&mut rng);
     let mut padding_output_resource = Resource::random_padding_resource(&mut rng);
 
-    // Generate proving info
+    // The anchors are recalculated from the merkle paths when executing transparently.
     let merkle_path = MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH);
 
     // Create compliance pairs
@@ -53,24 +138,22 @@ pub fn create_token_swap_ptx<R: RngCore>(
             &mut rng,
         );
 
-        // Fetch a valid anchor for padding input resources
-        let anchor = Anchor::from(pallas::Base::random(&mut rng));
         let compliance_2 = ComplianceInfo::new(
             padding_input_resource,
             merkle_path,
-            Some(anchor),
+            None,
             &mut padding_output_resource,
             &mut rng,
         );
         vec![compliance_1, compliance_2]
     };
 
-    // Create resource logics
-    let (input_resource_logics, output_resource_logics) = {
+    // Create applications
+    let (input_resource_apps, output_resource_apps) = {
         let input_resources = [*input_resource.resource(), padding_input_resource];
         let output_resources = [*output_resource.resource(), padding_output_resource];
-        // Create resource_logics for the input token
-        let input_token_resource_logics = input_resource.generate_input_token_resource_logics(
+        // Create the application for the input token
+        let input_token_app = input_resource.generate_input_token_application(
             &mut rng,
             input_auth,
             input_auth_sk,
@@ -78,43 +161,39 @@ pub fn create_token_swap_ptx<R: RngCore>(
             output_resources,
         );
 
-        // Create resource logics for the output token
-        let output_token_resource_logics = output_resource.generate_output_token_resource_logics(
+        // Create the application for the output token
+        let output_token_app = output_resource.generate_output_token_application(
             &mut rng,
             output_auth,
             input_resources,
             output_resources,
         );
 
-        // Create resource logics for the padding input
-        let padding_input_resource_logics =
-            ResourceLogics::create_input_padding_resource_resource_logics(
-                &padding_input_resource,
-                input_resources,
-                output_resources,
-            );
-
-        // Create resource logics for the padding output
-        let padding_output_resource_logics =
-            ResourceLogics::create_output_padding_resource_resource_logics(
-                &padding_output_resource,
-                input_resources,
-                output_resources,
-            );
+        // Create the application for the padding input
+        let padding_input_app = ApplicationByteCode::create_input_padding_resource_application(
+            &padding_input_resource,
+            input_resources,
+            output_resources,
+        );
+
+        // Create the application for the padding output
+        let padding_output_app = ApplicationByteCode::create_output_padding_resource_application(
+            &padding_output_resource,
+            input_resources,
+            output_resources,
+        );
 
         (
-            vec![input_token_resource_logics, padding_input_resource_logics],
-            vec![output_token_resource_logics, padding_output_resource_logics],
+            vec![input_token_app, padding_input_app],
+            vec![output_token_app, padding_output_app],
         )
     };
 
-    // Create shielded partial tx
-    ShieldedPartialTransaction::build(
+    // Create transparent partial tx
+    TransparentPartialTransaction::new(
         compliances,
-        input_resource_logics,
-        output_resource_logics,
+        input_resource_apps,
+        output_resource_apps,
         vec![],
-        &mut rng,
     )
-    .unwrap()
 }
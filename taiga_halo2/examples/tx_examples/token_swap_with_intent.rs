@@ -97,7 +97,7 @@ pub fn create_token_intent_ptx<R: RngCore>(
         // Create resource logics for the intent resource
         let intent_resource_resource_logics = {
             let intent_resource_logic = OrRelationIntentResourceLogicCircuit {
-                owned_resource_id: intent_resource.commitment().inner(),
+                owned_resource_id: intent_resource.commitment().into(),
                 input_resources,
                 output_resources,
                 token_1,
@@ -106,7 +106,7 @@ pub fn create_token_intent_ptx<R: RngCore>(
                 receiver_value: input_resource.value,
             };
 
-            ResourceLogics::new(Box::new(intent_resource_logic), vec![])
+            ResourceLogics::from_application(Box::new(intent_resource_logic))
         };
 
         // Create resource logics for the padding input
@@ -214,7 +214,7 @@ pub fn consume_token_intent_ptx<R: RngCore>(
         // Create resource_logics for the intent
         let intent_resource_logics = {
             let intent_resource_logic = OrRelationIntentResourceLogicCircuit {
-                owned_resource_id: input_resource_nf.inner(),
+                owned_resource_id: input_resource_nf.into(),
                 input_resources,
                 output_resources,
                 token_1,
@@ -223,7 +223,7 @@ pub fn consume_token_intent_ptx<R: RngCore>(
                 receiver_value,
             };
 
-            ResourceLogics::new(Box::new(intent_resource_logic), vec![])
+            ResourceLogics::from_application(Box::new(intent_resource_logic))
         };
 
         // Create resource logics for the output token resource
@@ -3,7 +3,7 @@
 /// Bob has 10 "ETH" and wants 15 "XAN"
 /// Carol has 15 "XAN" and wants 5 BTC""
 ///
-use crate::token::create_token_swap_ptx;
+use crate::token::create_token_swap_ptx_with_options;
 use group::Group;
 use halo2_proofs::arithmetic::Field;
 use pasta_curves::{group::Curve, pallas};
@@ -11,10 +11,18 @@ use rand::{CryptoRng, RngCore};
 use taiga_halo2::{
     circuit::resource_logic_examples::token::Token,
     nullifier::NullifierKeyContainer,
+    prover_options::ProverOptions,
     transaction::{ShieldedPartialTxBundle, Transaction, TransparentPartialTxBundle},
 };
 
-pub fn create_token_swap_transaction<R: RngCore + CryptoRng>(mut rng: R) -> Transaction {
+pub fn create_token_swap_transaction<R: RngCore + CryptoRng>(rng: R) -> Transaction {
+    create_token_swap_transaction_with_options(rng, &ProverOptions::default())
+}
+
+pub fn create_token_swap_transaction_with_options<R: RngCore + CryptoRng>(
+    mut rng: R,
+    options: &ProverOptions,
+) -> Transaction {
     let generator = pallas::Point::generator().to_affine();
 
     let btc_token = Token::new("btc".to_string(), 5);
@@ -26,7 +34,7 @@ pub fn create_token_swap_transaction<R: RngCore + CryptoRng>(mut rng: R) -> Tran
     let alice_auth_pk = generator * alice_auth_sk;
     let alice_nk = NullifierKeyContainer::random_key(&mut rng);
 
-    let alice_ptx = create_token_swap_ptx(
+    let alice_ptx = create_token_swap_ptx_with_options(
         &mut rng,
         btc_token.clone(),
         alice_auth_sk,
@@ -34,6 +42,7 @@ pub fn create_token_swap_transaction<R: RngCore + CryptoRng>(mut rng: R) -> Tran
         eth_token.clone(),
         alice_auth_pk,
         alice_nk.get_npk(),
+        options,
     );
 
     // Bob creates the partial transaction
@@ -41,7 +50,7 @@ pub fn create_token_swap_transaction<R: RngCore + CryptoRng>(mut rng: R) -> Tran
     let bob_auth_pk = generator * bob_auth_sk;
     let bob_nk = NullifierKeyContainer::random_key(&mut rng);
 
-    let bob_ptx = create_token_swap_ptx(
+    let bob_ptx = create_token_swap_ptx_with_options(
         &mut rng,
         eth_token,
         bob_auth_sk,
@@ -49,6 +58,7 @@ pub fn create_token_swap_transaction<R: RngCore + CryptoRng>(mut rng: R) -> Tran
         xan_token.clone(),
         bob_auth_pk,
         bob_nk.get_npk(),
+        options,
     );
 
     // Carol creates the partial transaction
@@ -56,7 +66,7 @@ pub fn create_token_swap_transaction<R: RngCore + CryptoRng>(mut rng: R) -> Tran
     let carol_auth_pk = generator * carol_auth_sk;
     let carol_nk = NullifierKeyContainer::random_key(&mut rng);
 
-    let carol_ptx = create_token_swap_ptx(
+    let carol_ptx = create_token_swap_ptx_with_options(
         &mut rng,
         xan_token,
         carol_auth_sk,
@@ -64,6 +74,7 @@ pub fn create_token_swap_transaction<R: RngCore + CryptoRng>(mut rng: R) -> Tran
         btc_token,
         carol_auth_pk,
         carol_nk.get_npk(),
+        options,
     );
 
     // Solver creates the final transaction
@@ -80,3 +91,35 @@ fn test_basic_swap_tx() {
     let tx = create_token_swap_transaction(&mut rng);
     tx.execute().unwrap();
 }
+
+// Reports the peak resident memory of proving the full swap transaction, e.g.
+// TAIGA_LOW_MEMORY=1 TAIGA_PARAMS_FILE=params/params_15 cargo test --release --example tx_examples \
+//     --features example-intents,example-migration profile_token_swap_peak_memory -- --ignored --nocapture
+// The low-memory mode must stay within the 8GB of the machines it targets.
+#[ignore]
+#[test]
+fn profile_token_swap_peak_memory() {
+    use rand::rngs::OsRng;
+
+    let mut options = ProverOptions::default();
+    if std::env::var_os("TAIGA_LOW_MEMORY").is_some() {
+        options = ProverOptions::low_memory();
+        if let Some(params_file) = std::env::var_os("TAIGA_PARAMS_FILE") {
+            options = options.with_mapped_params(params_file);
+        }
+    }
+    let tx = create_token_swap_transaction_with_options(OsRng, &options);
+    tx.execute().unwrap();
+
+    // VmHWM is the peak resident set size on linux, in kB
+    let status = std::fs::read_to_string("/proc/self/status").unwrap();
+    let peak_kb: u64 = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))
+        .and_then(|peak| peak.trim().trim_end_matches("kB").trim().parse().ok())
+        .expect("VmHWM should be reported");
+    println!("{:?}: peak resident memory {} MB", options, peak_kb / 1024);
+    if !options.use_cached_proving_keys {
+        assert!(peak_kb < 8 * 1024 * 1024);
+    }
+}
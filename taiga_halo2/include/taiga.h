@@ -0,0 +1,167 @@
+#ifndef TAIGA_H
+#define TAIGA_H
+
+/* Generated with cbindgen from src/ffi.rs, don't edit it by hand. */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+typedef enum TaigaStatus {
+  TAIGA_STATUS_OK = 0,
+  TAIGA_STATUS_NULL_POINTER = 1,
+  // A 32-byte input is not a canonical field element
+  TAIGA_STATUS_INVALID_FIELD = 2,
+  // A byte buffer is not a valid borsh encoding
+  TAIGA_STATUS_DECODING_FAILED = 3,
+  // Building or verifying the partial transaction or transaction failed
+  TAIGA_STATUS_TRANSACTION_FAILED = 4,
+  TAIGA_STATUS_PANIC = 5,
+} TaigaStatus;
+
+typedef struct TaigaPartialTransaction TaigaPartialTransaction;
+
+typedef struct TaigaResource TaigaResource;
+
+typedef struct TaigaTransaction TaigaTransaction;
+
+// A byte buffer allocated by the library, released with `taiga_buffer_free`.
+typedef struct TaigaBuffer {
+  uint8_t *data;
+  uintptr_t len;
+} TaigaBuffer;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// The message of the last failure on the calling thread, or null. The string is owned by the
+// library and valid until the next failing call on the thread.
+const char *taiga_last_error_message(void);
+
+// # Safety
+// The buffer must have been returned by the library and not released yet.
+void taiga_buffer_free(struct TaigaBuffer buffer);
+
+// Creates an input resource with a random nonce and rseed, see
+// `taiga_api::create_input_resource`.
+//
+// # Safety
+// The field pointers must point to 32 readable bytes, `quantity_le` to 16 and `out` must be
+// writable.
+enum TaigaStatus taiga_resource_create_input(const uint8_t (*logic)[32],
+                                             const uint8_t (*label)[32],
+                                             const uint8_t (*value)[32],
+                                             const uint8_t (*quantity_le)[16],
+                                             const uint8_t (*nk)[32],
+                                             bool is_ephemeral,
+                                             struct TaigaResource **out);
+
+// Creates an output resource owned by the npk with a random rseed, see
+// `taiga_api::create_output_resource`.
+//
+// # Safety
+// The field pointers must point to 32 readable bytes, `quantity_le` to 16 and `out` must be
+// writable.
+enum TaigaStatus taiga_resource_create_output(const uint8_t (*logic)[32],
+                                              const uint8_t (*label)[32],
+                                              const uint8_t (*value)[32],
+                                              const uint8_t (*quantity_le)[16],
+                                              const uint8_t (*npk)[32],
+                                              bool is_ephemeral,
+                                              struct TaigaResource **out);
+
+// # Safety
+// `data` must point to `len` readable bytes and `out` must be writable.
+enum TaigaStatus taiga_resource_from_bytes(const uint8_t *data,
+                                           uintptr_t len,
+                                           struct TaigaResource **out);
+
+// # Safety
+// `resource` must be a live handle and `out` must be writable.
+enum TaigaStatus taiga_resource_to_bytes(const struct TaigaResource *resource,
+                                         struct TaigaBuffer *out);
+
+// Writes the commitment of the resource.
+//
+// # Safety
+// `resource` must be a live handle and `out` must point to 32 writable bytes.
+enum TaigaStatus taiga_resource_commitment(const struct TaigaResource *resource,
+                                           uint8_t (*out)[32]);
+
+// # Safety
+// `resource` must be null or a live handle, it is invalid afterwards.
+void taiga_resource_free(struct TaigaResource *resource);
+
+// Builds a shielded partial transaction from the borsh encoded compliance infos
+// (`Vec<ComplianceInfo>`) and applications of the input and output resources
+// (`Vec<ApplicationByteCode>`), generating all the proofs.
+//
+// # Safety
+// Every buffer must point to its length of readable bytes and `out` must be writable.
+enum TaigaStatus taiga_ptx_from_bytecode(const uint8_t *compliances,
+                                         uintptr_t compliances_len,
+                                         const uint8_t *input_resource_apps,
+                                         uintptr_t input_resource_apps_len,
+                                         const uint8_t *output_resource_apps,
+                                         uintptr_t output_resource_apps_len,
+                                         const uint8_t *hints,
+                                         uintptr_t hints_len,
+                                         struct TaigaPartialTransaction **out);
+
+// # Safety
+// `data` must point to `len` readable bytes and `out` must be writable.
+enum TaigaStatus taiga_ptx_from_bytes(const uint8_t *data,
+                                      uintptr_t len,
+                                      struct TaigaPartialTransaction **out);
+
+// # Safety
+// `ptx` must be a live handle and `out` must be writable.
+enum TaigaStatus taiga_ptx_to_bytes(const struct TaigaPartialTransaction *ptx,
+                                    struct TaigaBuffer *out);
+
+// Verifies the proofs of the partial transaction.
+//
+// # Safety
+// `ptx` must be a live handle.
+enum TaigaStatus taiga_ptx_verify(const struct TaigaPartialTransaction *ptx);
+
+// # Safety
+// `ptx` must be null or a live handle, it is invalid afterwards.
+void taiga_ptx_free(struct TaigaPartialTransaction *ptx);
+
+// Builds a transaction from the shielded partial transactions, the handles stay valid.
+//
+// # Safety
+// `ptxs` must point to `len` live handles and `out` must be writable.
+enum TaigaStatus taiga_transaction_build(const struct TaigaPartialTransaction *const *ptxs,
+                                         uintptr_t len,
+                                         struct TaigaTransaction **out);
+
+// # Safety
+// `data` must point to `len` readable bytes and `out` must be writable.
+enum TaigaStatus taiga_transaction_from_bytes(const uint8_t *data,
+                                              uintptr_t len,
+                                              struct TaigaTransaction **out);
+
+// # Safety
+// `tx` must be a live handle and `out` must be writable.
+enum TaigaStatus taiga_transaction_to_bytes(const struct TaigaTransaction *tx,
+                                            struct TaigaBuffer *out);
+
+// Verifies the transaction and writes the borsh encoded `TransactionResult`.
+//
+// # Safety
+// `tx` must be a live handle and `out` must be writable.
+enum TaigaStatus taiga_transaction_execute(const struct TaigaTransaction *tx,
+                                           struct TaigaBuffer *out);
+
+// # Safety
+// `tx` must be null or a live handle, it is invalid afterwards.
+void taiga_transaction_free(struct TaigaTransaction *tx);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* TAIGA_H */
@@ -0,0 +1,266 @@
+/// Resource ciphertexts under a standard AEAD, for deployments that want hardware AES or have
+/// specific compliance requirements.
+///
+/// The encryption verified in the receiver resource logic stays the Poseidon sponge of
+/// `ResourceCiphertext`. The AEAD ciphertexts are meant for the off-circuit delivery of
+/// resources, and carry a ciphersuite byte in their header so receivers can decrypt any of the
+/// supported ciphers:
+///   ciphersuite (1) || nonce (12) || AEAD ciphertext and tag
+/// The header is authenticated as associated data.
+use crate::{
+    constant::{AEAD_KEY_PERSONALIZATION, AEAD_NONCE_LEN, RESOURCE_ENCRYPTION_PLAINTEXT_NUM},
+    resource_encryption::{ResourcePlaintext, SecretKey},
+};
+use aes_gcm::Aes256Gcm;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305,
+};
+use ff::PrimeField;
+use pasta_curves::pallas;
+use rand::RngCore;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ciphersuite {
+    ChaCha20Poly1305 = 1,
+    Aes256Gcm = 2,
+}
+
+impl TryFrom<u8> for Ciphersuite {
+    type Error = ();
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
+        match byte {
+            1 => Ok(Ciphersuite::ChaCha20Poly1305),
+            2 => Ok(Ciphersuite::Aes256Gcm),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The symmetric encryption of a ciphersuite.
+pub trait ResourceAead {
+    const CIPHERSUITE: Ciphersuite;
+
+    fn seal(key: &[u8; 32], nonce: &[u8; AEAD_NONCE_LEN], aad: &[u8], plaintext: &[u8]) -> Vec<u8>;
+
+    /// Returns None if the authentication fails.
+    fn open(
+        key: &[u8; 32],
+        nonce: &[u8; AEAD_NONCE_LEN],
+        aad: &[u8],
+        ciphertext: &[u8],
+    ) -> Option<Vec<u8>>;
+}
+
+pub struct ChaCha20Poly1305Aead;
+
+pub struct Aes256GcmAead;
+
+impl ResourceAead for ChaCha20Poly1305Aead {
+    const CIPHERSUITE: Ciphersuite = Ciphersuite::ChaCha20Poly1305;
+
+    fn seal(key: &[u8; 32], nonce: &[u8; AEAD_NONCE_LEN], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        ChaCha20Poly1305::new(key.into())
+            .encrypt(
+                nonce.into(),
+                Payload {
+                    msg: plaintext,
+                    aad,
+                },
+            )
+            .expect("the plaintext fits in a ChaCha20Poly1305 message")
+    }
+
+    fn open(
+        key: &[u8; 32],
+        nonce: &[u8; AEAD_NONCE_LEN],
+        aad: &[u8],
+        ciphertext: &[u8],
+    ) -> Option<Vec<u8>> {
+        ChaCha20Poly1305::new(key.into())
+            .decrypt(
+                nonce.into(),
+                Payload {
+                    msg: ciphertext,
+                    aad,
+                },
+            )
+            .ok()
+    }
+}
+
+impl ResourceAead for Aes256GcmAead {
+    const CIPHERSUITE: Ciphersuite = Ciphersuite::Aes256Gcm;
+
+    fn seal(key: &[u8; 32], nonce: &[u8; AEAD_NONCE_LEN], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        Aes256Gcm::new(key.into())
+            .encrypt(
+                nonce.into(),
+                Payload {
+                    msg: plaintext,
+                    aad,
+                },
+            )
+            .expect("the plaintext fits in an AES-GCM message")
+    }
+
+    fn open(
+        key: &[u8; 32],
+        nonce: &[u8; AEAD_NONCE_LEN],
+        aad: &[u8],
+        ciphertext: &[u8],
+    ) -> Option<Vec<u8>> {
+        Aes256Gcm::new(key.into())
+            .decrypt(
+                nonce.into(),
+                Payload {
+                    msg: ciphertext,
+                    aad,
+                },
+            )
+            .ok()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AeadResourceCiphertext {
+    ciphersuite: Ciphersuite,
+    nonce: [u8; AEAD_NONCE_LEN],
+    ciphertext: Vec<u8>,
+}
+
+impl AeadResourceCiphertext {
+    pub fn encrypt<A: ResourceAead, R: RngCore>(
+        message: &ResourcePlaintext,
+        secret_key: &SecretKey,
+        mut rng: R,
+    ) -> Self {
+        let mut nonce = [0u8; AEAD_NONCE_LEN];
+        rng.fill_bytes(&mut nonce);
+        let plaintext: Vec<u8> = message
+            .inner()
+            .iter()
+            .flat_map(|element| element.to_repr())
+            .collect();
+        let ciphertext = A::seal(
+            &secret_key.kdf(AEAD_KEY_PERSONALIZATION),
+            &nonce,
+            &Self::header(A::CIPHERSUITE, &nonce),
+            &plaintext,
+        );
+        Self {
+            ciphersuite: A::CIPHERSUITE,
+            nonce,
+            ciphertext,
+        }
+    }
+
+    /// Returns None if the authentication fails or the plaintext is not a resource plaintext.
+    pub fn decrypt(&self, secret_key: &SecretKey) -> Option<ResourcePlaintext> {
+        let key = secret_key.kdf(AEAD_KEY_PERSONALIZATION);
+        let aad = Self::header(self.ciphersuite, &self.nonce);
+        let plaintext = match self.ciphersuite {
+            Ciphersuite::ChaCha20Poly1305 => {
+                ChaCha20Poly1305Aead::open(&key, &self.nonce, &aad, &self.ciphertext)
+            }
+            Ciphersuite::Aes256Gcm => {
+                Aes256GcmAead::open(&key, &self.nonce, &aad, &self.ciphertext)
+            }
+        }?;
+        if plaintext.len() != RESOURCE_ENCRYPTION_PLAINTEXT_NUM * 32 {
+            return None;
+        }
+        let message = plaintext
+            .chunks(32)
+            .map(|chunk| Option::from(pallas::Base::from_repr(chunk.try_into().unwrap())))
+            .collect::<Option<Vec<_>>>()?;
+        Some(message.into())
+    }
+
+    pub fn ciphersuite(&self) -> Ciphersuite {
+        self.ciphersuite
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Self::header(self.ciphersuite, &self.nonce);
+        bytes.extend_from_slice(&self.ciphertext);
+        bytes
+    }
+
+    /// Returns None if the ciphersuite is unknown or the bytes are too short.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 1 + AEAD_NONCE_LEN {
+            return None;
+        }
+        let ciphersuite = Ciphersuite::try_from(bytes[0]).ok()?;
+        Some(Self {
+            ciphersuite,
+            nonce: bytes[1..1 + AEAD_NONCE_LEN].try_into().unwrap(),
+            ciphertext: bytes[1 + AEAD_NONCE_LEN..].to_vec(),
+        })
+    }
+
+    fn header(ciphersuite: Ciphersuite, nonce: &[u8; AEAD_NONCE_LEN]) -> Vec<u8> {
+        let mut header = vec![ciphersuite as u8];
+        header.extend_from_slice(nonce);
+        header
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use group::Group;
+    use halo2_proofs::arithmetic::Field;
+    use rand::rngs::OsRng;
+
+    fn aead_round_trip<A: ResourceAead>() {
+        let mut rng = OsRng;
+        let key = SecretKey::from_dh_exchange(
+            &pallas::Point::random(&mut rng),
+            &pallas::Scalar::random(&mut rng),
+        );
+        let message: Vec<_> = (0..8).map(|_| pallas::Base::random(&mut rng)).collect();
+        let plaintext = ResourcePlaintext::padding(&message);
+
+        let cipher = AeadResourceCiphertext::encrypt::<A, _>(&plaintext, &key, &mut rng);
+        assert_eq!(cipher.ciphersuite(), A::CIPHERSUITE);
+        let cipher = AeadResourceCiphertext::from_bytes(&cipher.to_bytes()).unwrap();
+        assert_eq!(cipher.decrypt(&key).unwrap().to_vec(), plaintext.to_vec());
+
+        // Other keys can't decrypt
+        let other_key = SecretKey::from_dh_exchange(
+            &pallas::Point::random(&mut rng),
+            &pallas::Scalar::random(&mut rng),
+        );
+        assert!(cipher.decrypt(&other_key).is_none());
+
+        // The header is authenticated
+        let mut bytes = cipher.to_bytes();
+        bytes[0] = if bytes[0] == 1 { 2 } else { 1 };
+        let tampered = AeadResourceCiphertext::from_bytes(&bytes).unwrap();
+        assert!(tampered.decrypt(&key).is_none());
+
+        let mut bytes = cipher.to_bytes();
+        bytes[1] ^= 1;
+        let tampered = AeadResourceCiphertext::from_bytes(&bytes).unwrap();
+        assert!(tampered.decrypt(&key).is_none());
+    }
+
+    #[test]
+    fn chacha20poly1305_resource_encryption_test() {
+        aead_round_trip::<ChaCha20Poly1305Aead>();
+    }
+
+    #[test]
+    fn aes256gcm_resource_encryption_test() {
+        aead_round_trip::<Aes256GcmAead>();
+    }
+
+    #[test]
+    fn unknown_ciphersuite_test() {
+        assert!(AeadResourceCiphertext::from_bytes(&[0u8; 64]).is_none());
+        assert!(AeadResourceCiphertext::from_bytes(&[1u8; 12]).is_none());
+    }
+}
@@ -0,0 +1,154 @@
+/// Async wrappers around proof generation.
+///
+/// Proving is CPU bound, so the proofs are generated on the tokio blocking pool and the returned
+/// `ProvingTask` can be awaited from async code. The functions must be called within a tokio
+/// runtime. The rng of a task is moved to the blocking pool with it.
+use crate::{
+    circuit::resource_logic_circuit::{ResourceLogic, ResourceLogicVerifyingInfo},
+    compliance::ComplianceInfo,
+    constant::DEFAULT_PARAMS_STORE,
+    error::TransactionError,
+    params_store::ParamsStore,
+    resource::ResourceLogics,
+    shielded_ptx::{ComplianceVerifyingInfo, ShieldedPartialTransaction},
+};
+use rand::RngCore;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::task::{Context, Poll};
+use tokio::task::JoinHandle;
+
+/// A proof generation running on the blocking pool.
+///
+/// Cancelling a task that has not started yet skips the proving. A proof already in progress
+/// can't be interrupted, it runs to completion and the result is discarded.
+pub struct ProvingTask<T> {
+    handle: JoinHandle<Result<T, TransactionError>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl<T: Send + 'static> ProvingTask<T> {
+    pub fn spawn<F>(prove: F) -> Self
+    where
+        F: FnOnce() -> Result<T, TransactionError> + Send + 'static,
+    {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let flag = cancelled.clone();
+        let handle = tokio::task::spawn_blocking(move || {
+            if flag.load(Ordering::Acquire) {
+                return Err(TransactionError::ProvingCancelled);
+            }
+            prove()
+        });
+        Self { handle, cancelled }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+        self.handle.abort();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+}
+
+impl<T> Future for ProvingTask<T> {
+    type Output = Result<T, TransactionError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.cancelled.load(Ordering::Acquire) {
+            return Poll::Ready(Err(TransactionError::ProvingCancelled));
+        }
+        match Pin::new(&mut self.handle).poll(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Ok(result)) => Poll::Ready(result),
+            Poll::Ready(Err(e)) if e.is_cancelled() => {
+                Poll::Ready(Err(TransactionError::ProvingCancelled))
+            }
+            Poll::Ready(Err(e)) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+}
+
+/// Generates the resource logic proof with the default params.
+pub fn prove_async<R: RngCore + Send + 'static>(
+    resource_logic: Box<ResourceLogic>,
+    mut rng: R,
+) -> ProvingTask<ResourceLogicVerifyingInfo> {
+    ProvingTask::spawn(move || {
+        resource_logic.get_verifying_info_with_params_store(&DEFAULT_PARAMS_STORE, &mut rng)
+    })
+}
+
+/// Generates the resource logic proof with the params from the given store.
+pub fn prove_async_with_params_store<R: RngCore + Send + 'static>(
+    resource_logic: Box<ResourceLogic>,
+    params_store: Arc<ParamsStore>,
+    mut rng: R,
+) -> ProvingTask<ResourceLogicVerifyingInfo> {
+    ProvingTask::spawn(move || {
+        resource_logic.get_verifying_info_with_params_store(&params_store, &mut rng)
+    })
+}
+
+/// Generates the compliance proof with the default params.
+pub fn prove_compliance_async<R: RngCore + Send + 'static>(
+    compliance_info: ComplianceInfo,
+    rng: R,
+) -> ProvingTask<ComplianceVerifyingInfo> {
+    ProvingTask::spawn(move || {
+        ComplianceVerifyingInfo::create(&compliance_info, rng).map_err(TransactionError::Proof)
+    })
+}
+
+/// Builds a shielded partial transaction, generating all of its proofs.
+pub fn build_shielded_ptx_async<R: RngCore + Send + 'static>(
+    compliances: Vec<ComplianceInfo>,
+    input_resource_logics: Vec<ResourceLogics>,
+    output_resource_logics: Vec<ResourceLogics>,
+    hints: Vec<u8>,
+    rng: R,
+) -> ProvingTask<ShieldedPartialTransaction> {
+    ProvingTask::spawn(move || {
+        ShieldedPartialTransaction::build(
+            compliances,
+            input_resource_logics,
+            output_resource_logics,
+            hints,
+            rng,
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{prove_async, ProvingTask};
+    use crate::circuit::resource_logic_examples::tests::random_trivial_resource_logic_circuit;
+    use crate::error::TransactionError;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_prove_async() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let circuit = random_trivial_resource_logic_circuit(OsRng);
+            let verifying_info = prove_async(Box::new(circuit), OsRng).await.unwrap();
+            verifying_info.verify().unwrap();
+
+            let task = ProvingTask::spawn(|| Ok(()));
+            task.cancel();
+            assert!(task.is_cancelled());
+            assert!(matches!(
+                task.await,
+                Err(TransactionError::ProvingCancelled)
+            ));
+        });
+    }
+}
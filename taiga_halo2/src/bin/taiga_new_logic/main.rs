@@ -0,0 +1,331 @@
+//! Scaffolds a new resource logic circuit.
+//!
+//! Usage: taiga-new-logic <LogicName> [--crate-root <path>] [--no-register]
+//!
+//! Writes `src/circuit/resource_logic_examples/<logic_name>.rs` with the circuit struct,
+//! label encoding, tagged encoding and a MockProver test, and registers it in
+//! `resource_logic_examples.rs`, `ResourceLogicRepresentation` and the vk registry behind its
+//! own `example-<logic-name>` feature unless `--no-register` is given.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::exit;
+
+const TEMPLATE: &str = include_str!("resource_logic.rs.template");
+const MANIFEST: &str = "Cargo.toml";
+const EXAMPLES_MOD: &str = "src/circuit/resource_logic_examples.rs";
+const EXAMPLES_DIR: &str = "src/circuit/resource_logic_examples";
+const BYTECODE: &str = "src/circuit/resource_logic_bytecode.rs";
+const VK_REGISTRY: &str = "src/resource_logic_vk_registry.rs";
+
+struct LogicName {
+    // e.g. MyLogic
+    camel: String,
+    // e.g. my_logic
+    snake: String,
+}
+
+impl LogicName {
+    fn parse(name: &str) -> Result<Self, String> {
+        if name.is_empty()
+            || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            || !name.starts_with(|c: char| c.is_ascii_alphabetic())
+        {
+            return Err(format!("invalid logic name: {}", name));
+        }
+
+        let words: Vec<String> = if name.contains('_') || name.chars().all(|c| !c.is_uppercase()) {
+            name.split('_')
+                .filter(|w| !w.is_empty())
+                .map(|w| w.to_lowercase())
+                .collect()
+        } else {
+            let mut words = vec![];
+            let mut word = String::new();
+            for c in name.chars() {
+                if c.is_uppercase() && !word.is_empty() {
+                    words.push(word);
+                    word = String::new();
+                }
+                word.push(c.to_ascii_lowercase());
+            }
+            words.push(word);
+            words
+        };
+
+        let camel: String = words
+            .iter()
+            .map(|w| {
+                let mut chars = w.chars();
+                match chars.next() {
+                    Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                    None => String::new(),
+                }
+            })
+            .collect();
+        let snake = words.join("_");
+        Ok(Self { camel, snake })
+    }
+
+    // e.g. example-my-logic
+    fn feature(&self) -> String {
+        format!("example-{}", self.snake.replace('_', "-"))
+    }
+
+    fn render(&self, template: &str) -> String {
+        template
+            .replace("{{Name}}", &self.camel)
+            .replace("{{name}}", &self.snake)
+            .replace("{{NAME}}", &self.snake.to_uppercase())
+    }
+}
+
+fn insert_before(source: &str, marker: &str, snippet: &str, nth: usize) -> Result<String, String> {
+    let index = source
+        .match_indices(marker)
+        .nth(nth)
+        .map(|(i, _)| i)
+        .ok_or_else(|| format!("marker not found: {}", marker.trim()))?;
+    // Insert at the beginning of the marker line to keep the indentation
+    let line_start = source[..index].rfind('\n').map_or(0, |i| i + 1);
+    Ok(format!(
+        "{}{}{}",
+        &source[..line_start],
+        snippet,
+        &source[line_start..]
+    ))
+}
+
+fn register(crate_root: &Path, name: &LogicName) -> Result<(), String> {
+    let feature = name.feature();
+    let manifest = crate_root.join(MANIFEST);
+    let source = fs::read_to_string(&manifest).map_err(|e| e.to_string())?;
+    // Add the feature to the examples feature, and define it after the list
+    let source = insert_before(
+        &source,
+        "]\nexample-signature-verification = ",
+        &format!("    \"{}\",\n", feature),
+        0,
+    )?;
+    let source = insert_before(
+        &source,
+        "example-signature-verification = ",
+        &format!("{} = [\"borsh\", \"prover\"]\n", feature),
+        0,
+    )?;
+    fs::write(&manifest, source).map_err(|e| e.to_string())?;
+
+    let examples_mod = crate_root.join(EXAMPLES_MOD);
+    let source = fs::read_to_string(&examples_mod).map_err(|e| e.to_string())?;
+    let source = insert_before(
+        &source,
+        "\nlazy_static! {",
+        &format!(
+            "#[cfg(feature = \"{}\")]\npub mod {};\n",
+            feature, name.snake
+        ),
+        0,
+    )?;
+    fs::write(&examples_mod, source).map_err(|e| e.to_string())?;
+
+    let bytecode = crate_root.join(BYTECODE);
+    let source = fs::read_to_string(&bytecode).map_err(|e| e.to_string())?;
+    let source = insert_before(
+        &source,
+        "#[cfg(feature = \"borsh\")]\nuse crate::circuit::resource_logic_examples::TrivialResourceLogicCircuit;",
+        &format!(
+            "#[cfg(feature = \"{}\")]\nuse crate::circuit::resource_logic_examples::{}::{}ResourceLogicCircuit;\n",
+            feature, name.snake, name.camel
+        ),
+        0,
+    )?;
+    let source = insert_before(
+        &source,
+        "// Add other native resource_logic types here if needed",
+        &format!("    {},\n", name.camel),
+        0,
+    )?;
+    // name
+    let source = insert_before(
+        &source,
+        "        }\n    }\n\n    /// The schema of the inputs.",
+        &format!(
+            "            ResourceLogicRepresentation::{camel} => \"{camel}\",\n",
+            camel = name.camel
+        ),
+        0,
+    )?;
+    let arm = |body: &str| {
+        format!(
+            "            #[cfg(feature = \"{feature}\")]\n            ResourceLogicRepresentation::{camel} => {{\n                let resource_logic =\n                    self.decode_native_inputs::<{camel}ResourceLogicCircuit>()?;\n                {body}\n            }}\n",
+            feature = feature,
+            camel = name.camel,
+            body = body
+        )
+    };
+    // generate_proof
+    let source = insert_before(
+        &source,
+        "#[allow(unreachable_patterns)]",
+        &arm(
+            "resource_logic.get_verifying_info_with_params_store(&DEFAULT_PARAMS_STORE, &mut rng)",
+        ),
+        0,
+    )?;
+    // verify_transparently
+    let source = insert_before(
+        &source,
+        "#[allow(unreachable_patterns)]",
+        &arm("resource_logic.verify_transparently()?"),
+        1,
+    )?;
+    fs::write(&bytecode, source).map_err(|e| e.to_string())?;
+
+    let vk_registry = crate_root.join(VK_REGISTRY);
+    let source = fs::read_to_string(&vk_registry).map_err(|e| e.to_string())?;
+    let source = insert_before(
+        &source,
+        "        registry\n    };",
+        &format!(
+            "        #[cfg(feature = \"{feature}\")]\n        {{\n            use crate::circuit::resource_logic_examples::{snake}::COMPRESSED_{upper}_VK;\n            registry.register(\"{camel}\", 1, *COMPRESSED_{upper}_VK);\n        }}\n",
+            feature = feature,
+            snake = name.snake,
+            upper = name.snake.to_uppercase(),
+            camel = name.camel
+        ),
+        0,
+    )?;
+    fs::write(&vk_registry, source).map_err(|e| e.to_string())
+}
+
+fn run(args: &[String]) -> Result<PathBuf, String> {
+    let mut name = None;
+    let mut crate_root = PathBuf::from(".");
+    let mut no_register = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--crate-root" => {
+                crate_root = iter
+                    .next()
+                    .map(PathBuf::from)
+                    .ok_or("--crate-root needs a path")?
+            }
+            "--no-register" => no_register = true,
+            _ if name.is_none() => name = Some(LogicName::parse(arg)?),
+            _ => return Err(format!("unexpected argument: {}", arg)),
+        }
+    }
+    let name = name.ok_or("missing logic name")?;
+
+    let path = crate_root
+        .join(EXAMPLES_DIR)
+        .join(format!("{}.rs", name.snake));
+    if path.exists() {
+        return Err(format!("{} already exists", path.display()));
+    }
+    fs::write(&path, name.render(TEMPLATE)).map_err(|e| e.to_string())?;
+
+    if !no_register {
+        register(&crate_root, &name)?;
+    }
+
+    Ok(path)
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(path) => println!("created {}", path.display()),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            eprintln!("usage: taiga-new-logic <LogicName> [--crate-root <path>] [--no-register]");
+            exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            if entry.file_name() == "target" {
+                continue;
+            }
+            let to = to.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                copy_dir(&entry.path(), &to)?;
+            } else {
+                fs::copy(entry.path(), to)?;
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_logic_name() {
+        let name = LogicName::parse("MyLogic").unwrap();
+        assert_eq!(
+            (name.camel.as_str(), name.snake.as_str()),
+            ("MyLogic", "my_logic")
+        );
+        let name = LogicName::parse("my_logic").unwrap();
+        assert_eq!(
+            (name.camel.as_str(), name.snake.as_str()),
+            ("MyLogic", "my_logic")
+        );
+        assert_eq!(name.feature(), "example-my-logic");
+        assert!(LogicName::parse("1logic").is_err());
+        assert!(LogicName::parse("my-logic").is_err());
+    }
+
+    // Generates a logic into a copy of the workspace and checks that the copy, including the
+    // test of the generated logic, still compiles: the markers and the template have to follow
+    // the code they are inserted in. Slow, it builds the crate from scratch.
+    #[test]
+    #[ignore]
+    fn test_generated_logic_compiles() {
+        let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let workspace_dir = crate_dir.parent().unwrap();
+        let temp_workspace =
+            std::env::temp_dir().join(format!("taiga-new-logic-{}", std::process::id()));
+        let temp_crate = temp_workspace.join(crate_dir.file_name().unwrap());
+        fs::create_dir_all(&temp_workspace).unwrap();
+        for file in ["Cargo.toml", "Cargo.lock", "rust-toolchain"] {
+            if workspace_dir.join(file).exists() {
+                fs::copy(workspace_dir.join(file), temp_workspace.join(file)).unwrap();
+            }
+        }
+        copy_dir(crate_dir, &temp_crate).unwrap();
+
+        let args = [
+            "GeneratedCheck".to_string(),
+            "--crate-root".to_string(),
+            temp_crate.display().to_string(),
+        ];
+        run(&args).unwrap();
+
+        let status = Command::new(env!("CARGO"))
+            .args([
+                "check",
+                "--lib",
+                "--tests",
+                "--no-default-features",
+                "--features",
+                "example-generated-check",
+            ])
+            .current_dir(&temp_crate)
+            .env(
+                "CARGO_TARGET_DIR",
+                workspace_dir.join("target/taiga-new-logic"),
+            )
+            .status()
+            .unwrap();
+        fs::remove_dir_all(&temp_workspace).unwrap();
+        assert!(status.success());
+    }
+}
@@ -0,0 +1,148 @@
+/// The burn address convention.
+///
+/// A resource is burned by creating it with the canonical unspendable npk `BURN_NPK`. Its
+/// nullifier needs the nk behind the npk, which nobody knows, so the resource can never be
+/// consumed. Applications burning resources (e.g. token burns for bridging) reveal the opening of
+/// the burned resource with `prove_burned`, and third parties check with `BurnProof::verify` that
+/// the resource is in the commitment tree and sent to the burn address.
+use crate::{
+    constant::BURN_NPK,
+    error::TransactionError,
+    merkle_tree::{Anchor, MerklePath},
+    nullifier::NullifierKeyContainer,
+    resource::{Resource, ResourceCommitment},
+};
+use pasta_curves::pallas;
+
+#[cfg(feature = "borsh")]
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// The nullifier key container of the burn address.
+pub fn burn_nk_container() -> NullifierKeyContainer {
+    NullifierKeyContainer::from_npk(*BURN_NPK)
+}
+
+pub fn is_burned(resource: &Resource) -> bool {
+    resource.get_npk() == *BURN_NPK
+}
+
+impl Resource {
+    /// Creates an output resource sent to the burn address.
+    pub fn new_burn_resource(
+        logic: pallas::Base,
+        label: pallas::Base,
+        value: pallas::Base,
+        quantity: u128,
+        is_ephemeral: bool,
+        rseed: pallas::Base,
+    ) -> Self {
+        Resource::new_output_resource(
+            logic,
+            label,
+            value,
+            quantity,
+            *BURN_NPK,
+            is_ephemeral,
+            rseed,
+        )
+    }
+}
+
+/// The opening of a burned resource and its position in the commitment tree.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+pub struct BurnProof {
+    resource: Resource,
+    merkle_path: MerklePath,
+}
+
+/// Proves that the resource, created at the position of the merkle path, is burned.
+pub fn prove_burned(
+    resource: &Resource,
+    merkle_path: MerklePath,
+) -> Result<BurnProof, TransactionError> {
+    if !is_burned(resource) {
+        return Err(TransactionError::NotBurnResource);
+    }
+    Ok(BurnProof {
+        resource: *resource,
+        merkle_path,
+    })
+}
+
+impl BurnProof {
+    pub fn resource(&self) -> &Resource {
+        &self.resource
+    }
+
+    pub fn commitment(&self) -> ResourceCommitment {
+        self.resource.commitment()
+    }
+
+    /// Checks the resource is sent to the burn address and is in the commitment tree of the anchor.
+    pub fn verify(&self, anchor: &Anchor) -> Result<(), TransactionError> {
+        if !is_burned(&self.resource) {
+            return Err(TransactionError::NotBurnResource);
+        }
+        if self.resource.calculate_root(&self.merkle_path) != *anchor {
+            return Err(TransactionError::InvalidBurnProof);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{constant::TAIGA_COMMITMENT_TREE_DEPTH, resource::tests::random_resource};
+    use halo2_proofs::arithmetic::Field;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn burn_proof_test() {
+        let mut rng = OsRng;
+        let mut resource = random_resource(&mut rng);
+        resource.nk_container = burn_nk_container();
+        let merkle_path = MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH);
+        let anchor = resource.calculate_root(&merkle_path);
+
+        let proof = prove_burned(&resource, merkle_path.clone()).unwrap();
+        assert!(proof.verify(&anchor).is_ok());
+        assert_eq!(proof.commitment(), resource.commitment());
+
+        // The burned resource has no known nk, so it has no nullifier
+        assert!(resource.get_nf().is_none());
+
+        // Wrong anchor
+        let other_anchor = Anchor::from(pallas::Base::random(&mut rng));
+        assert!(matches!(
+            proof.verify(&other_anchor),
+            Err(TransactionError::InvalidBurnProof)
+        ));
+
+        // A tampered opening doesn't match the commitment in the tree
+        let mut tampered = proof.clone();
+        tampered.resource.quantity += 1;
+        assert!(matches!(
+            tampered.verify(&anchor),
+            Err(TransactionError::InvalidBurnProof)
+        ));
+
+        // Resources not sent to the burn address can't be proved burned
+        let spendable = random_resource(&mut rng);
+        assert!(matches!(
+            prove_burned(&spendable, merkle_path),
+            Err(TransactionError::NotBurnResource)
+        ));
+
+        let burn_resource = Resource::new_burn_resource(
+            resource.get_logic(),
+            resource.get_label(),
+            resource.value,
+            resource.quantity,
+            false,
+            resource.rseed,
+        );
+        assert!(is_burned(&burn_resource));
+    }
+}
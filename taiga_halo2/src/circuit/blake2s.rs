@@ -1,11 +1,7 @@
 use super::gadgets::assign_free_advice;
 use crate::circuit::gadgets::assign_free_constant;
 use crate::constant::{
-    RESOURCE_LOGIC_CIRCUIT_FIRST_DYNAMIC_RESOURCE_LOGIC_CM_1,
-    RESOURCE_LOGIC_CIRCUIT_FIRST_DYNAMIC_RESOURCE_LOGIC_CM_2,
-    RESOURCE_LOGIC_CIRCUIT_SECOND_DYNAMIC_RESOURCE_LOGIC_CM_1,
-    RESOURCE_LOGIC_CIRCUIT_SECOND_DYNAMIC_RESOURCE_LOGIC_CM_2,
-    RESOURCE_LOGIC_COMMITMENT_PERSONALIZATION,
+    RESOURCE_LOGIC_CIRCUIT_DYNAMIC_RESOURCE_LOGIC_CMS, RESOURCE_LOGIC_COMMITMENT_PERSONALIZATION,
 };
 use crate::resource_logic_commitment::ResourceLogicCommitment;
 use byteorder::{ByteOrder, LittleEndian};
@@ -51,26 +47,11 @@ pub fn publicize_default_dynamic_resource_logic_commitments<F: PrimeField>(
         Value::known(resource_logic_cm_fields[1]),
     )?;
 
-    layouter.constrain_instance(
-        resource_logic_cm_1.cell(),
-        instances,
-        RESOURCE_LOGIC_CIRCUIT_FIRST_DYNAMIC_RESOURCE_LOGIC_CM_1,
-    )?;
-    layouter.constrain_instance(
-        resource_logic_cm_2.cell(),
-        instances,
-        RESOURCE_LOGIC_CIRCUIT_FIRST_DYNAMIC_RESOURCE_LOGIC_CM_2,
-    )?;
-    layouter.constrain_instance(
-        resource_logic_cm_1.cell(),
-        instances,
-        RESOURCE_LOGIC_CIRCUIT_SECOND_DYNAMIC_RESOURCE_LOGIC_CM_1,
-    )?;
-    layouter.constrain_instance(
-        resource_logic_cm_2.cell(),
-        instances,
-        RESOURCE_LOGIC_CIRCUIT_SECOND_DYNAMIC_RESOURCE_LOGIC_CM_2,
-    )?;
+    // Fill every dynamic resource logic slot of the layout
+    for [cm_1_idx, cm_2_idx] in RESOURCE_LOGIC_CIRCUIT_DYNAMIC_RESOURCE_LOGIC_CMS {
+        layouter.constrain_instance(resource_logic_cm_1.cell(), instances, cm_1_idx)?;
+        layouter.constrain_instance(resource_logic_cm_2.cell(), instances, cm_2_idx)?;
+    }
 
     Ok(())
 }
@@ -1,19 +1,23 @@
 use crate::circuit::blake2s::{resource_logic_commitment_gadget, Blake2sChip, Blake2sConfig};
 use crate::circuit::gadgets::assign_free_advice;
+use crate::circuit::gadgets::merkle_path::{
+    merkle_poseidon_gadget, MerklePoseidonChip, MerklePoseidonConfig,
+};
 use crate::circuit::hash_to_curve::HashToCurveConfig;
 use crate::circuit::integrity::{
     check_input_resource, check_output_resource, compute_delta_commitment,
 };
-use crate::circuit::merkle_circuit::{
-    merkle_poseidon_gadget, MerklePoseidonChip, MerklePoseidonConfig,
-};
+use crate::circuit::resource_logic_circuit::ResourceVariables;
 use crate::constant::{
     TaigaFixedBases, COMPLIANCE_ANCHOR_PUBLIC_INPUT_ROW_IDX,
     COMPLIANCE_DELTA_CM_X_PUBLIC_INPUT_ROW_IDX, COMPLIANCE_DELTA_CM_Y_PUBLIC_INPUT_ROW_IDX,
     COMPLIANCE_INPUT_RESOURCE_LOGIC_CM_1_ROW_IDX, COMPLIANCE_INPUT_RESOURCE_LOGIC_CM_2_ROW_IDX,
-    COMPLIANCE_NF_PUBLIC_INPUT_ROW_IDX, COMPLIANCE_OUTPUT_CM_PUBLIC_INPUT_ROW_IDX,
-    COMPLIANCE_OUTPUT_RESOURCE_LOGIC_CM_1_ROW_IDX, COMPLIANCE_OUTPUT_RESOURCE_LOGIC_CM_2_ROW_IDX,
-    TAIGA_COMMITMENT_TREE_DEPTH,
+    COMPLIANCE_INPUT_TRANSPARENT_FLAG_ROW_IDX, COMPLIANCE_INPUT_TRANSPARENT_LABEL_ROW_IDX,
+    COMPLIANCE_INPUT_TRANSPARENT_LOGIC_ROW_IDX, COMPLIANCE_NF_PUBLIC_INPUT_ROW_IDX,
+    COMPLIANCE_OUTPUT_CM_PUBLIC_INPUT_ROW_IDX, COMPLIANCE_OUTPUT_RESOURCE_LOGIC_CM_1_ROW_IDX,
+    COMPLIANCE_OUTPUT_RESOURCE_LOGIC_CM_2_ROW_IDX, COMPLIANCE_OUTPUT_TRANSPARENT_FLAG_ROW_IDX,
+    COMPLIANCE_OUTPUT_TRANSPARENT_LABEL_ROW_IDX, COMPLIANCE_OUTPUT_TRANSPARENT_LOGIC_ROW_IDX,
+    RESOURCE_KIND_DOMAIN, TAIGA_COMMITMENT_TREE_DEPTH, TRANSPARENT_RESOURCE_KIND_DOMAIN,
 };
 use crate::merkle_tree::LR;
 use crate::resource::Resource;
@@ -24,7 +28,7 @@ use halo2_gadgets::{
     utilities::lookup_range_check::LookupRangeCheckConfig,
 };
 use halo2_proofs::{
-    circuit::{floor_planner, Layouter, Value},
+    circuit::{floor_planner, AssignedCell, Layouter, Value},
     plonk::{
         Advice, Circuit, Column, ConstraintSystem, Constraints, Error, Expression, Instance,
         Selector, TableColumn,
@@ -44,6 +48,8 @@ pub struct ComplianceConfig {
     poseidon_config: PoseidonConfig<pallas::Base, 3, 2>,
     merkle_config: MerklePoseidonConfig,
     merkle_path_selector: Selector,
+    transparent_kind_selector: Selector,
+    kind_domain_selector: Selector,
     hash_to_curve_config: HashToCurveConfig,
     blake2s_config: Blake2sConfig<pallas::Base>,
     resource_commit_config: ResourceCommitConfig,
@@ -139,6 +145,66 @@ impl Circuit<pallas::Base> for ComplianceCircuit {
             )
         });
 
+        let transparent_kind_selector = meta.selector();
+        meta.create_gate("transparent kind", |meta| {
+            let transparent_kind_selector = meta.query_selector(transparent_kind_selector);
+            let is_transparent_kind = meta.query_advice(advices[0], Rotation::cur());
+            let logic = meta.query_advice(advices[1], Rotation::cur());
+            let label = meta.query_advice(advices[2], Rotation::cur());
+            let public_logic = meta.query_advice(advices[3], Rotation::cur());
+            let public_label = meta.query_advice(advices[4], Rotation::cur());
+
+            Constraints::with_selector(
+                transparent_kind_selector,
+                [
+                    (
+                        "public_logic = is_transparent_kind * logic",
+                        public_logic - is_transparent_kind.clone() * logic,
+                    ),
+                    (
+                        "public_label = is_transparent_kind * label",
+                        public_label - is_transparent_kind * label,
+                    ),
+                ],
+            )
+        });
+
+        // The kind of a transparent-kind resource is derived in its own domain, so the prover
+        // can't balance it against an output of the kind without the flag
+        let kind_domain_selector = meta.selector();
+        meta.create_gate("kind domain", |meta| {
+            let kind_domain_selector = meta.query_selector(kind_domain_selector);
+            let is_transparent_kind = meta.query_advice(advices[0], Rotation::cur());
+            let u_0_postfix = meta.query_advice(advices[1], Rotation::cur());
+            let u_1_postfix = meta.query_advice(advices[2], Rotation::cur());
+            let postfix = |opaque: pallas::Base, transparent: pallas::Base| {
+                Expression::Constant(opaque)
+                    + is_transparent_kind.clone() * Expression::Constant(transparent - opaque)
+            };
+
+            Constraints::with_selector(
+                kind_domain_selector,
+                [
+                    (
+                        "u_0_postfix of the domain picked by is_transparent_kind",
+                        u_0_postfix
+                            - postfix(
+                                RESOURCE_KIND_DOMAIN.u_0_postfix(),
+                                TRANSPARENT_RESOURCE_KIND_DOMAIN.u_0_postfix(),
+                            ),
+                    ),
+                    (
+                        "u_1_postfix of the domain picked by is_transparent_kind",
+                        u_1_postfix
+                            - postfix(
+                                RESOURCE_KIND_DOMAIN.u_1_postfix(),
+                                TRANSPARENT_RESOURCE_KIND_DOMAIN.u_1_postfix(),
+                            ),
+                    ),
+                ],
+            )
+        });
+
         let merkle_config = MerklePoseidonChip::configure(
             meta,
             advices[..5].try_into().unwrap(),
@@ -152,7 +218,7 @@ impl Circuit<pallas::Base> for ComplianceCircuit {
 
         let resource_commit_config = ResourceCommitChip::configure(
             meta,
-            advices[0..3].try_into().unwrap(),
+            advices[0..4].try_into().unwrap(),
             poseidon_config.clone(),
             range_check,
         );
@@ -165,6 +231,8 @@ impl Circuit<pallas::Base> for ComplianceCircuit {
             poseidon_config,
             merkle_config,
             merkle_path_selector,
+            transparent_kind_selector,
+            kind_domain_selector,
             hash_to_curve_config,
             blake2s_config,
             resource_commit_config,
@@ -217,7 +285,7 @@ impl Circuit<pallas::Base> for ComplianceCircuit {
         // Check the merkle tree path validity and public the root
         let root = merkle_poseidon_gadget(
             layouter.namespace(|| "poseidon merkle"),
-            merkle_chip,
+            &merkle_chip,
             input_resource_variables.cm,
             &self.merkle_path,
         )?;
@@ -234,16 +302,24 @@ impl Circuit<pallas::Base> for ComplianceCircuit {
         )?;
 
         // compute and public delta commitment(input_value_commitment - output_value_commitment)
+        let input_kind_domain_postfixes = assign_kind_domain_postfixes(
+            layouter.namespace(|| "input kind domain"),
+            &config,
+            &input_resource_variables.resource_variables,
+        )?;
+        let output_kind_domain_postfixes = assign_kind_domain_postfixes(
+            layouter.namespace(|| "output kind domain"),
+            &config,
+            &output_resource_vars.resource_variables,
+        )?;
         let delta = compute_delta_commitment(
             layouter.namespace(|| "delta commitment"),
             ecc_chip,
             config.hash_to_curve_config.clone(),
-            input_resource_variables.resource_variables.logic.clone(),
-            input_resource_variables.resource_variables.label.clone(),
-            input_resource_variables.resource_variables.quantity.clone(),
-            output_resource_vars.resource_variables.logic.clone(),
-            output_resource_vars.resource_variables.label.clone(),
-            output_resource_vars.resource_variables.quantity,
+            &input_resource_variables.resource_variables,
+            input_kind_domain_postfixes,
+            &output_resource_vars.resource_variables,
+            output_kind_domain_postfixes,
             self.rcv,
         )?;
         layouter.constrain_instance(
@@ -277,6 +353,24 @@ impl Circuit<pallas::Base> for ComplianceCircuit {
             },
         )?;
 
+        // Publish the kinds of the transparent-kind resources
+        publish_transparent_kind(
+            layouter.namespace(|| "input transparent kind"),
+            &config,
+            &input_resource_variables.resource_variables,
+            COMPLIANCE_INPUT_TRANSPARENT_FLAG_ROW_IDX,
+            COMPLIANCE_INPUT_TRANSPARENT_LOGIC_ROW_IDX,
+            COMPLIANCE_INPUT_TRANSPARENT_LABEL_ROW_IDX,
+        )?;
+        publish_transparent_kind(
+            layouter.namespace(|| "output transparent kind"),
+            &config,
+            &output_resource_vars.resource_variables,
+            COMPLIANCE_OUTPUT_TRANSPARENT_FLAG_ROW_IDX,
+            COMPLIANCE_OUTPUT_TRANSPARENT_LOGIC_ROW_IDX,
+            COMPLIANCE_OUTPUT_TRANSPARENT_LABEL_ROW_IDX,
+        )?;
+
         // Input resource logic commitment
         let input_resource_logic_cm_r = assign_free_advice(
             layouter.namespace(|| "witness input_resource_logic_cm_r"),
@@ -327,12 +421,110 @@ impl Circuit<pallas::Base> for ComplianceCircuit {
     }
 }
 
+// Assigns the postfixes of the domain the kind of the resource is derived in, the
+// TRANSPARENT_RESOURCE_KIND_DOMAIN if it's a transparent-kind resource and the
+// RESOURCE_KIND_DOMAIN otherwise.
+fn assign_kind_domain_postfixes(
+    mut layouter: impl Layouter<pallas::Base>,
+    config: &ComplianceConfig,
+    resource_variables: &ResourceVariables,
+) -> Result<[AssignedCell<pallas::Base, pallas::Base>; 2], Error> {
+    layouter.assign_region(
+        || "kind domain",
+        |mut region| {
+            config.kind_domain_selector.enable(&mut region, 0)?;
+            let is_transparent_kind = resource_variables.is_transparent_kind.copy_advice(
+                || "is_transparent_kind",
+                &mut region,
+                config.advices[0],
+                0,
+            )?;
+            let postfixes = is_transparent_kind.value().map(|&is_transparent_kind| {
+                if is_transparent_kind == pallas::Base::one() {
+                    *TRANSPARENT_RESOURCE_KIND_DOMAIN
+                } else {
+                    *RESOURCE_KIND_DOMAIN
+                }
+            });
+            let u_0_postfix = region.assign_advice(
+                || "u_0_postfix",
+                config.advices[1],
+                0,
+                || postfixes.map(|domain| domain.u_0_postfix()),
+            )?;
+            let u_1_postfix = region.assign_advice(
+                || "u_1_postfix",
+                config.advices[2],
+                0,
+                || postfixes.map(|domain| domain.u_1_postfix()),
+            )?;
+            Ok([u_0_postfix, u_1_postfix])
+        },
+    )
+}
+
+// Publishes the is_transparent_kind flag of the resource, and its logic and label if it's a
+// transparent-kind resource, zeros otherwise.
+fn publish_transparent_kind(
+    mut layouter: impl Layouter<pallas::Base>,
+    config: &ComplianceConfig,
+    resource_variables: &ResourceVariables,
+    flag_row_idx: usize,
+    logic_row_idx: usize,
+    label_row_idx: usize,
+) -> Result<(), Error> {
+    layouter.constrain_instance(
+        resource_variables.is_transparent_kind.cell(),
+        config.instances,
+        flag_row_idx,
+    )?;
+    let (public_logic, public_label) = layouter.assign_region(
+        || "transparent kind",
+        |mut region| {
+            config.transparent_kind_selector.enable(&mut region, 0)?;
+            let is_transparent_kind = resource_variables.is_transparent_kind.copy_advice(
+                || "is_transparent_kind",
+                &mut region,
+                config.advices[0],
+                0,
+            )?;
+            let logic = resource_variables.logic.copy_advice(
+                || "logic",
+                &mut region,
+                config.advices[1],
+                0,
+            )?;
+            let label = resource_variables.label.copy_advice(
+                || "label",
+                &mut region,
+                config.advices[2],
+                0,
+            )?;
+            let public_logic = region.assign_advice(
+                || "public logic",
+                config.advices[3],
+                0,
+                || is_transparent_kind.value() * logic.value(),
+            )?;
+            let public_label = region.assign_advice(
+                || "public label",
+                config.advices[4],
+                0,
+                || is_transparent_kind.value() * label.value(),
+            )?;
+            Ok((public_logic, public_label))
+        },
+    )?;
+    layouter.constrain_instance(public_logic.cell(), config.instances, logic_row_idx)?;
+    layouter.constrain_instance(public_label.cell(), config.instances, label_row_idx)
+}
+
 #[test]
 fn test_halo2_compliance_circuit() {
     use crate::compliance::tests::random_compliance_info;
     use crate::constant::{
         COMPLIANCE_CIRCUIT_PARAMS_SIZE, COMPLIANCE_PROVING_KEY, COMPLIANCE_VERIFYING_KEY,
-        SETUP_PARAMS_MAP,
+        DEFAULT_PARAMS_STORE,
     };
     use crate::proof::Proof;
     use halo2_proofs::dev::MockProver;
@@ -352,12 +544,12 @@ fn test_halo2_compliance_circuit() {
     assert_eq!(prover.verify(), Ok(()));
 
     // Create compliance proof
-    let params = SETUP_PARAMS_MAP
-        .get(&COMPLIANCE_CIRCUIT_PARAMS_SIZE)
+    let params = DEFAULT_PARAMS_STORE
+        .get(COMPLIANCE_CIRCUIT_PARAMS_SIZE)
         .unwrap();
     let proof = Proof::create(
         &COMPLIANCE_PROVING_KEY,
-        params,
+        &params,
         compliance_circuit,
         &[&compliance.to_instance()],
         &mut rng,
@@ -367,8 +559,146 @@ fn test_halo2_compliance_circuit() {
     assert!(proof
         .verify(
             &COMPLIANCE_VERIFYING_KEY,
-            params,
+            &params,
             &[&compliance.to_instance()]
         )
         .is_ok());
 }
+
+#[test]
+fn test_compliance_circuit_transparent_kind() {
+    use crate::compliance::ComplianceInfo;
+    use crate::constant::{COMPLIANCE_CIRCUIT_PARAMS_SIZE, TAIGA_COMMITMENT_TREE_DEPTH};
+    use crate::merkle_tree::MerklePath;
+    use crate::resource::{tests::random_resource, ResourceKind};
+    use halo2_proofs::dev::MockProver;
+    use rand::rngs::OsRng;
+
+    let mut rng = OsRng;
+    let mut input_resource = random_resource(&mut rng);
+    input_resource.is_transparent_kind = true;
+    let mut output_resource = random_resource(&mut rng);
+    let input_merkle_path = MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH);
+    let compliance_info = ComplianceInfo::new(
+        input_resource,
+        input_merkle_path,
+        None,
+        &mut output_resource,
+        &mut rng,
+    );
+    let (mut compliance, compliance_circuit) = compliance_info.build();
+    assert_eq!(compliance.input_transparent_kind, Some(input_resource.kind));
+    assert_eq!(compliance.output_transparent_kind, None);
+    let prover = MockProver::<pallas::Base>::run(
+        COMPLIANCE_CIRCUIT_PARAMS_SIZE,
+        &compliance_circuit,
+        vec![compliance.to_instance()],
+    )
+    .unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // The kind of a transparent-kind resource can't be hidden
+    compliance.input_transparent_kind = None;
+    let prover = MockProver::<pallas::Base>::run(
+        COMPLIANCE_CIRCUIT_PARAMS_SIZE,
+        &compliance_circuit,
+        vec![compliance.to_instance()],
+    )
+    .unwrap();
+    assert!(prover.verify().is_err());
+
+    // Nor can the kind of another resource be disclosed
+    compliance.input_transparent_kind = Some(input_resource.kind);
+    compliance.output_transparent_kind = Some(output_resource.kind);
+    let prover = MockProver::<pallas::Base>::run(
+        COMPLIANCE_CIRCUIT_PARAMS_SIZE,
+        &compliance_circuit,
+        vec![compliance.to_instance()],
+    )
+    .unwrap();
+    assert!(prover.verify().is_err());
+
+    // Nor can another resource claim to disclose the zero kind it publishes
+    compliance.output_transparent_kind = Some(ResourceKind::default());
+    let prover = MockProver::<pallas::Base>::run(
+        COMPLIANCE_CIRCUIT_PARAMS_SIZE,
+        &compliance_circuit,
+        vec![compliance.to_instance()],
+    )
+    .unwrap();
+    assert!(prover.verify().is_err());
+}
+
+#[test]
+fn test_compliance_circuit_transparent_kind_flag_flip() {
+    use crate::compliance::ComplianceInfo;
+    use crate::constant::{
+        COMPLIANCE_CIRCUIT_PARAMS_SIZE, RESOURCE_COMMITMENT_R_GENERATOR,
+        TAIGA_COMMITMENT_TREE_DEPTH,
+    };
+    use crate::delta_commitment::DeltaCommitment;
+    use crate::merkle_tree::MerklePath;
+    use crate::resource::tests::random_resource;
+    use halo2_proofs::dev::MockProver;
+    use pasta_curves::group::{cofactor::CofactorCurveAffine, GroupEncoding};
+    use rand::rngs::OsRng;
+
+    let mut rng = OsRng;
+    let mut input_resource = random_resource(&mut rng);
+    input_resource.is_transparent_kind = true;
+    // The output spends the transparent asset into a resource of the same kind and quantity
+    // hiding its kind
+    let mut output_resource = random_resource(&mut rng);
+    output_resource.kind = input_resource.kind;
+    output_resource.quantity = input_resource.quantity;
+    output_resource.is_transparent_kind = false;
+    let input_merkle_path = MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH);
+    let compliance_info = ComplianceInfo::new(
+        input_resource,
+        input_merkle_path,
+        None,
+        &mut output_resource,
+        &mut rng,
+    );
+    let rcv = compliance_info.get_rcv();
+    let (mut compliance, compliance_circuit) = compliance_info.build();
+    let prover = MockProver::<pallas::Base>::run(
+        COMPLIANCE_CIRCUIT_PARAMS_SIZE,
+        &compliance_circuit,
+        vec![compliance.to_instance()],
+    )
+    .unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // The kinds are derived in different domains, so the delta doesn't balance: it isn't a
+    // commitment to zero a binding signature could be made for
+    let balanced = RESOURCE_COMMITMENT_R_GENERATOR.to_curve() * rcv;
+    assert_ne!(compliance.delta.inner(), balanced);
+
+    // Nor can the prover claim the balanced delta
+    compliance.delta = DeltaCommitment::from_bytes(balanced.to_bytes()).unwrap();
+    let prover = MockProver::<pallas::Base>::run(
+        COMPLIANCE_CIRCUIT_PARAMS_SIZE,
+        &compliance_circuit,
+        vec![compliance.to_instance()],
+    )
+    .unwrap();
+    assert!(prover.verify().is_err());
+
+    // With the flag kept, the output balances the input
+    let mut output_resource = input_resource;
+    output_resource.nk_container = random_resource(&mut rng).nk_container;
+    let compliance_info = ComplianceInfo::new(
+        input_resource,
+        MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH),
+        None,
+        &mut output_resource,
+        &mut rng,
+    );
+    let rcv = compliance_info.get_rcv();
+    let (compliance, _) = compliance_info.build();
+    assert_eq!(
+        compliance.delta.inner(),
+        RESOURCE_COMMITMENT_R_GENERATOR.to_curve() * rcv
+    );
+}
@@ -0,0 +1,481 @@
+/// Human-readable reports of the constraint system and the region structure of the circuits.
+///
+/// The reports of the built-in circuits are pinned in `params/constraint_reports`, so a change
+/// of the constraints or the layout of a circuit shows up as a diff of the report in review
+/// instead of having to read the synthesize code. Regenerate them with
+/// `export_constraint_reports` and render the layouts with `render_circuit_layouts`.
+use halo2_proofs::{
+    circuit::Value,
+    plonk::{
+        Advice, Any, Assigned, Assignment, Circuit, Column, ConstraintSystem, Error, Fixed,
+        FloorPlanner, Instance, Selector,
+    },
+};
+use pasta_curves::pallas;
+use std::fmt::Write;
+
+/// The cells a region (or the same region repeated) uses.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct RegionStats {
+    rows: usize,
+    advice_cells: usize,
+    fixed_cells: usize,
+    selectors: usize,
+    copies: usize,
+}
+
+#[derive(Debug)]
+struct ActiveRegion {
+    name: String,
+    min_row: Option<usize>,
+    max_row: usize,
+    stats: RegionStats,
+}
+
+impl ActiveRegion {
+    fn touch(&mut self, row: usize) {
+        self.min_row = Some(self.min_row.map_or(row, |min| min.min(row)));
+        self.max_row = self.max_row.max(row);
+    }
+}
+
+/// Records the regions of a synthesis without evaluating any witness.
+#[derive(Debug, Default)]
+struct RegionRecorder {
+    namespaces: Vec<String>,
+    region: Option<ActiveRegion>,
+    // (region name, number of occurrences, stats), in the order of their first occurrence
+    regions: Vec<(String, usize, RegionStats)>,
+    // the fixed cells assigned outside of regions, e.g. lookup tables
+    table_cells: usize,
+    // one past the last assigned row, in or outside of regions
+    rows_used: usize,
+}
+
+impl RegionRecorder {
+    fn touch(&mut self, row: usize) {
+        self.rows_used = self.rows_used.max(row + 1);
+        if let Some(region) = self.region.as_mut() {
+            region.touch(row);
+        }
+    }
+}
+
+impl Assignment<pallas::Base> for RegionRecorder {
+    fn enter_region<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        let mut name = self.namespaces.join("/");
+        if !name.is_empty() {
+            name.push('/');
+        }
+        name.push_str(&name_fn().into());
+        self.region = Some(ActiveRegion {
+            name,
+            min_row: None,
+            max_row: 0,
+            stats: RegionStats::default(),
+        });
+    }
+
+    fn exit_region(&mut self) {
+        let region = self.region.take().expect("exit a region that was entered");
+        let mut stats = region.stats;
+        stats.rows = region.min_row.map_or(0, |min| region.max_row - min + 1);
+        match self
+            .regions
+            .iter_mut()
+            .find(|(name, _, recorded)| *name == region.name && *recorded == stats)
+        {
+            Some((_, count, _)) => *count += 1,
+            None => self.regions.push((region.name, 1, stats)),
+        }
+    }
+
+    fn enable_selector<A, AR>(
+        &mut self,
+        _annotation: A,
+        _selector: &Selector,
+        row: usize,
+    ) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.touch(row);
+        if let Some(region) = self.region.as_mut() {
+            region.stats.selectors += 1;
+        }
+        Ok(())
+    }
+
+    fn query_instance(
+        &self,
+        _column: Column<Instance>,
+        _row: usize,
+    ) -> Result<Value<pallas::Base>, Error> {
+        Ok(Value::unknown())
+    }
+
+    fn assign_advice<V, VR, A, AR>(
+        &mut self,
+        _annotation: A,
+        _column: Column<Advice>,
+        row: usize,
+        _to: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<pallas::Base>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.touch(row);
+        if let Some(region) = self.region.as_mut() {
+            region.stats.advice_cells += 1;
+        }
+        Ok(())
+    }
+
+    fn assign_fixed<V, VR, A, AR>(
+        &mut self,
+        _annotation: A,
+        _column: Column<Fixed>,
+        row: usize,
+        _to: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<pallas::Base>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.touch(row);
+        match self.region.as_mut() {
+            Some(region) => region.stats.fixed_cells += 1,
+            None => self.table_cells += 1,
+        }
+        Ok(())
+    }
+
+    fn copy(
+        &mut self,
+        _left_column: Column<Any>,
+        _left_row: usize,
+        _right_column: Column<Any>,
+        _right_row: usize,
+    ) -> Result<(), Error> {
+        if let Some(region) = self.region.as_mut() {
+            region.stats.copies += 1;
+        }
+        Ok(())
+    }
+
+    fn fill_from_row(
+        &mut self,
+        _column: Column<Fixed>,
+        _row: usize,
+        _to: Value<Assigned<pallas::Base>>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.namespaces.push(name_fn().into());
+    }
+
+    fn pop_namespace(&mut self, _gadget_name: Option<String>) {
+        self.namespaces.pop();
+    }
+}
+
+/// The rows a circuit assigns, lookup tables included, against the usable rows at `2^k`: the
+/// rows above the blinding rows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RowUtilization {
+    pub k: u32,
+    pub rows_used: usize,
+    pub rows_available: usize,
+}
+
+/// Measures the rows of the circuit at `2^k` without evaluating the witnesses, like
+/// `constraint_report`.
+pub fn row_utilization<C: Circuit<pallas::Base>>(
+    circuit: &C,
+    k: u32,
+) -> Result<RowUtilization, Error> {
+    let mut cs = ConstraintSystem::default();
+    let config = C::configure(&mut cs);
+    let mut recorder = RegionRecorder::default();
+    C::FloorPlanner::synthesize(&mut recorder, circuit, config, cs.constants().clone())?;
+    Ok(RowUtilization {
+        k,
+        rows_used: recorder.rows_used,
+        rows_available: (1usize << k).saturating_sub(cs.blinding_factors() + 1),
+    })
+}
+
+/// Renders the constraint system and the regions of the circuit. The witnesses of the circuit
+/// are not evaluated, so the default circuit can be reported.
+pub fn constraint_report<C: Circuit<pallas::Base>>(
+    name: &str,
+    circuit: &C,
+) -> Result<String, Error> {
+    let mut cs = ConstraintSystem::default();
+    let config = C::configure(&mut cs);
+    let mut recorder = RegionRecorder::default();
+    C::FloorPlanner::synthesize(&mut recorder, circuit, config, cs.constants().clone())?;
+
+    let mut report = String::new();
+    // Writing to a String never fails
+    let _ = writeln!(report, "# {name}");
+    let _ = writeln!(
+        report,
+        "columns: advice {}, fixed {}, instance {}, selectors {}",
+        cs.num_advice_columns(),
+        cs.num_fixed_columns(),
+        cs.num_instance_columns(),
+        cs.num_selectors()
+    );
+    let _ = writeln!(report, "degree: {}", cs.degree());
+    let _ = writeln!(report, "lookups: {}", cs.lookups().len());
+    let _ = writeln!(
+        report,
+        "permutation columns: {}",
+        cs.permutation().get_columns().len()
+    );
+    let _ = writeln!(report, "gates: {}", cs.gates().len());
+    for gate in cs.gates() {
+        let max_degree = gate
+            .polynomials()
+            .iter()
+            .map(|poly| poly.degree())
+            .max()
+            .unwrap_or(0);
+        let _ = writeln!(
+            report,
+            "  {}: {} constraints, degree {}",
+            gate.name(),
+            gate.polynomials().len(),
+            max_degree
+        );
+    }
+    let _ = writeln!(report, "table cells: {}", recorder.table_cells);
+    let _ = writeln!(report, "regions: {}", recorder.regions.len());
+    for (name, count, stats) in recorder.regions.iter() {
+        let _ = writeln!(
+            report,
+            "  {count} x {name}: rows {}, advice {}, fixed {}, selectors {}, copies {}",
+            stats.rows, stats.advice_cells, stats.fixed_cells, stats.selectors, stats.copies
+        );
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+const CONSTRAINT_REPORTS_PATH: &str = "./params/constraint_reports";
+
+// The built-in circuits and their reports.
+#[cfg(test)]
+fn builtin_constraint_reports() -> Vec<(&'static str, String)> {
+    use crate::circuit::{
+        compliance_circuit::ComplianceCircuit, resource_logic_examples::TrivialResourceLogicCircuit,
+    };
+
+    let mut reports = vec![
+        (
+            "Compliance",
+            constraint_report("Compliance", &ComplianceCircuit::default()).unwrap(),
+        ),
+        (
+            "Trivial",
+            constraint_report("Trivial", &TrivialResourceLogicCircuit::default()).unwrap(),
+        ),
+    ];
+    #[cfg(feature = "example-token")]
+    {
+        use crate::circuit::resource_logic_examples::token::TokenResourceLogicCircuit;
+        reports.push((
+            "Token",
+            constraint_report("Token", &TokenResourceLogicCircuit::default()).unwrap(),
+        ));
+    }
+    #[cfg(feature = "example-intents")]
+    {
+        use crate::circuit::resource_logic_examples::{
+            cascade_intent::CascadeIntentResourceLogicCircuit,
+            or_relation_intent::OrRelationIntentResourceLogicCircuit,
+            partial_fulfillment_intent::PartialFulfillmentIntentResourceLogicCircuit,
+        };
+        reports.push((
+            "PartialFulfillmentIntent",
+            constraint_report(
+                "PartialFulfillmentIntent",
+                &PartialFulfillmentIntentResourceLogicCircuit::default(),
+            )
+            .unwrap(),
+        ));
+        reports.push((
+            "OrRelationIntent",
+            constraint_report(
+                "OrRelationIntent",
+                &OrRelationIntentResourceLogicCircuit::default(),
+            )
+            .unwrap(),
+        ));
+        reports.push((
+            "CascadeIntent",
+            constraint_report(
+                "CascadeIntent",
+                &CascadeIntentResourceLogicCircuit::default(),
+            )
+            .unwrap(),
+        ));
+    }
+    #[cfg(feature = "example-bridge")]
+    {
+        use crate::circuit::resource_logic_examples::bridge::BridgeResourceLogicCircuit;
+        reports.push((
+            "Bridge",
+            constraint_report("Bridge", &BridgeResourceLogicCircuit::default()).unwrap(),
+        ));
+    }
+    #[cfg(feature = "example-payment-channel")]
+    {
+        use crate::circuit::resource_logic_examples::payment_channel::PaymentChannelResourceLogicCircuit;
+        reports.push((
+            "PaymentChannel",
+            constraint_report(
+                "PaymentChannel",
+                &PaymentChannelResourceLogicCircuit::default(),
+            )
+            .unwrap(),
+        ));
+    }
+    #[cfg(feature = "example-randomness-beacon")]
+    {
+        use crate::circuit::resource_logic_examples::randomness_beacon::RandomnessBeaconResourceLogicCircuit;
+        reports.push((
+            "RandomnessBeacon",
+            constraint_report(
+                "RandomnessBeacon",
+                &RandomnessBeaconResourceLogicCircuit::default(),
+            )
+            .unwrap(),
+        ));
+    }
+    #[cfg(feature = "example-migration")]
+    {
+        use crate::circuit::resource_logic_examples::migration::MigrationResourceLogicCircuit;
+        reports.push((
+            "Migration",
+            constraint_report("Migration", &MigrationResourceLogicCircuit::default()).unwrap(),
+        ));
+    }
+    #[cfg(feature = "example-subscription")]
+    {
+        use crate::circuit::resource_logic_examples::subscription::SubscriptionResourceLogicCircuit;
+        reports.push((
+            "Subscription",
+            constraint_report("Subscription", &SubscriptionResourceLogicCircuit::default())
+                .unwrap(),
+        ));
+    }
+    reports
+}
+
+#[test]
+fn test_constraint_reports_are_pinned() {
+    for (name, report) in builtin_constraint_reports() {
+        let path = format!("{CONSTRAINT_REPORTS_PATH}/{name}.txt");
+        let pinned = std::fs::read_to_string(&path).unwrap_or_else(|err| {
+            panic!("cannot read {path} with {err}, run export_constraint_reports to create it")
+        });
+        assert_eq!(
+            pinned, report,
+            "the constraints of {name} changed, review the diff and run export_constraint_reports"
+        );
+    }
+}
+
+#[test]
+fn test_row_utilization() {
+    use crate::circuit::{
+        compliance_circuit::ComplianceCircuit, resource_logic_examples::TrivialResourceLogicCircuit,
+    };
+    use crate::constant::{COMPLIANCE_CIRCUIT_PARAMS_SIZE, RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE};
+
+    let compliance = row_utilization(
+        &ComplianceCircuit::default(),
+        COMPLIANCE_CIRCUIT_PARAMS_SIZE,
+    )
+    .unwrap();
+    let trivial = row_utilization(
+        &TrivialResourceLogicCircuit::default(),
+        RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+    )
+    .unwrap();
+    for utilization in [compliance, trivial] {
+        assert!(utilization.rows_used > 0);
+        assert!(utilization.rows_used <= utilization.rows_available);
+        assert!(utilization.rows_available < 1 << utilization.k);
+    }
+
+    // The rows don't fit at a smaller k
+    let small = row_utilization(&TrivialResourceLogicCircuit::default(), 8).unwrap();
+    assert_eq!(small.rows_used, trivial.rows_used);
+    assert!(small.rows_used > small.rows_available);
+}
+
+#[ignore]
+#[test]
+fn export_constraint_reports() {
+    std::fs::create_dir_all(CONSTRAINT_REPORTS_PATH)
+        .unwrap_or_else(|err| panic!("cannot create constraint_reports with {}", err));
+    for (name, report) in builtin_constraint_reports() {
+        std::fs::write(format!("{CONSTRAINT_REPORTS_PATH}/{name}.txt"), report)
+            .unwrap_or_else(|err| panic!("cannot write the report of {name} with {}", err));
+    }
+}
+
+// Renders the region layouts of the compliance and the trivial resource logic circuits with
+// halo2's CircuitLayout, to ./target/circuit_layouts.
+#[ignore]
+#[test]
+fn render_circuit_layouts() {
+    use crate::circuit::{
+        compliance_circuit::ComplianceCircuit, resource_logic_examples::TrivialResourceLogicCircuit,
+    };
+    use crate::constant::{COMPLIANCE_CIRCUIT_PARAMS_SIZE, RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE};
+    use halo2_proofs::dev::CircuitLayout;
+    use plotters::prelude::*;
+
+    std::fs::create_dir_all("./target/circuit_layouts").unwrap();
+    let root = BitMapBackend::new("./target/circuit_layouts/Compliance.png", (1024, 3096))
+        .into_drawing_area();
+    root.fill(&WHITE).unwrap();
+    CircuitLayout::default()
+        .render(
+            COMPLIANCE_CIRCUIT_PARAMS_SIZE,
+            &ComplianceCircuit::default(),
+            &root,
+        )
+        .unwrap();
+
+    let root = BitMapBackend::new("./target/circuit_layouts/Trivial.png", (1024, 3096))
+        .into_drawing_area();
+    root.fill(&WHITE).unwrap();
+    CircuitLayout::default()
+        .render(
+            RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+            &TrivialResourceLogicCircuit::default(),
+            &root,
+        )
+        .unwrap();
+}
@@ -5,14 +5,29 @@ use halo2_proofs::{
 };
 
 pub mod add;
+pub mod bit_decomposition;
+pub mod boolean;
+pub mod bytes_encoding;
+pub mod comparison;
 pub mod conditional_equal;
 pub mod conditional_select;
 pub mod extended_or_relation;
+pub mod fixed_point;
+pub mod hash_accumulator;
+pub mod hash_to_field;
+pub mod header_chain;
+pub mod kind_quantity;
+pub mod merkle_path;
 pub mod mul;
 pub mod poseidon_hash;
+pub mod range_check;
+pub mod resource_template;
+pub mod schnorr;
 pub mod sub;
 pub mod target_resource_variable;
 pub mod triple_mul;
+pub mod u128;
+pub mod vk_policy;
 
 pub fn assign_free_advice<F: arithmetic::Field, V: Copy>(
     mut layouter: impl Layouter<F>,
@@ -0,0 +1,247 @@
+/// Decomposition of a cell into constrained bits, and the packing of bits into a cell. The bits
+/// are bool-checked and recomposed with Horner's rule, `acc = 2 * acc + bit` from the most
+/// significant bit. At most `MAX_BITS` bits are supported, so that the recomposition can't wrap
+/// in the field and the decomposition is unique.
+///
+/// The chip is built from the add and mul configs, it adds no gate to the circuits.
+use crate::circuit::gadgets::{
+    add::{AddChip, AddConfig, AddInstructions},
+    assign_free_constant,
+    boolean::AssignedBool,
+    mul::{MulChip, MulConfig, MulInstructions},
+};
+use group::ff::PrimeField;
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter},
+    plonk::{Advice, Column, Error},
+};
+use pasta_curves::pallas;
+
+/// The maximum number of bits, 2^254 is below the modulus.
+pub const MAX_BITS: usize = 254;
+
+/// The order of the bits.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Endianness {
+    /// The least significant bit first.
+    Little,
+    /// The most significant bit first.
+    Big,
+}
+
+#[derive(Clone, Debug)]
+pub struct BitDecompositionChip {
+    advice: Column<Advice>,
+    add_chip: AddChip<pallas::Base>,
+    mul_chip: MulChip<pallas::Base>,
+}
+
+impl BitDecompositionChip {
+    pub fn construct(advice: Column<Advice>, add_config: AddConfig, mul_config: MulConfig) -> Self {
+        Self {
+            advice,
+            add_chip: AddChip::construct(add_config, ()),
+            mul_chip: MulChip::construct(mul_config),
+        }
+    }
+
+    /// Decomposes the value into `num_bits` bits in the given order. The constraints are
+    /// unsatisfiable if the value doesn't fit in `num_bits` bits, so it's a range check too.
+    pub fn decompose(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        value: &AssignedCell<pallas::Base, pallas::Base>,
+        num_bits: usize,
+        endianness: Endianness,
+    ) -> Result<Vec<AssignedBool>, Error> {
+        if num_bits > MAX_BITS {
+            return Err(Error::Synthesis);
+        }
+
+        let mut bits = (0..num_bits)
+            .map(|i| {
+                AssignedBool::witness(
+                    &self.mul_chip,
+                    layouter.namespace(|| format!("witness bit {i}")),
+                    self.advice,
+                    value
+                        .value()
+                        .map(|v| (v.to_repr()[i / 8] >> (i % 8)) & 1 == 1),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let composed = self.compose(
+            layouter.namespace(|| "compose bits"),
+            &bits,
+            Endianness::Little,
+        )?;
+        layouter.assign_region(
+            || "composed bits == value",
+            |mut region| region.constrain_equal(composed.cell(), value.cell()),
+        )?;
+
+        if endianness == Endianness::Big {
+            bits.reverse();
+        }
+        Ok(bits)
+    }
+
+    /// Packs the bits in the given order into a cell.
+    pub fn compose(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        bits: &[AssignedBool],
+        endianness: Endianness,
+    ) -> Result<AssignedCell<pallas::Base, pallas::Base>, Error> {
+        if bits.len() > MAX_BITS {
+            return Err(Error::Synthesis);
+        }
+
+        // From the most significant bit
+        let msb_first: Vec<&AssignedBool> = match endianness {
+            Endianness::Little => bits.iter().rev().collect(),
+            Endianness::Big => bits.iter().collect(),
+        };
+        let mut acc = assign_free_constant(
+            layouter.namespace(|| "zero"),
+            self.advice,
+            pallas::Base::zero(),
+        )?;
+        if msb_first.is_empty() {
+            return Ok(acc);
+        }
+        let two = assign_free_constant(
+            layouter.namespace(|| "two"),
+            self.advice,
+            pallas::Base::from(2),
+        )?;
+        for bit in msb_first {
+            let doubled =
+                MulInstructions::mul(&self.mul_chip, layouter.namespace(|| "2 * acc"), &acc, &two)?;
+            acc = AddInstructions::add(
+                &self.add_chip,
+                layouter.namespace(|| "2 * acc + bit"),
+                &doubled,
+                bit,
+            )?;
+        }
+        Ok(acc)
+    }
+}
+
+#[test]
+fn test_halo2_bit_decomposition() {
+    use crate::circuit::gadgets::assign_free_advice;
+    use halo2_proofs::{
+        circuit::{SimpleFloorPlanner, Value},
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem, Instance},
+    };
+
+    // Decomposes the value, publicizes the bits and their repacking
+    #[derive(Default)]
+    struct MyCircuit {
+        value: pallas::Base,
+        num_bits: usize,
+        endianness: Option<Endianness>,
+    }
+
+    impl Circuit<pallas::Base> for MyCircuit {
+        type Config = ([Column<Advice>; 2], Column<Instance>, AddConfig, MulConfig);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                value: pallas::Base::zero(),
+                num_bits: self.num_bits,
+                endianness: self.endianness,
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            let advices = [meta.advice_column(), meta.advice_column()];
+            for advice in advices.iter() {
+                meta.enable_equality(*advice);
+            }
+            let instances = meta.instance_column();
+            meta.enable_equality(instances);
+            let constants = meta.fixed_column();
+            meta.enable_constant(constants);
+            (
+                advices,
+                instances,
+                AddChip::configure(meta, advices),
+                MulChip::configure(meta, advices),
+            )
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), Error> {
+            let (advices, instances, add_config, mul_config) = config;
+            let chip = BitDecompositionChip::construct(advices[0], add_config, mul_config);
+            let value = assign_free_advice(
+                layouter.namespace(|| "witness value"),
+                advices[0],
+                Value::known(self.value),
+            )?;
+            let endianness = self.endianness.unwrap();
+            let bits = chip.decompose(
+                layouter.namespace(|| "decompose"),
+                &value,
+                self.num_bits,
+                endianness,
+            )?;
+            for (i, bit) in bits.iter().enumerate() {
+                layouter.constrain_instance(bit.cell(), instances, i)?;
+            }
+            let repacked = chip.compose(layouter.namespace(|| "compose"), &bits, endianness)?;
+            layouter.constrain_instance(repacked.cell(), instances, self.num_bits)
+        }
+    }
+
+    let bits = |value: u64, num_bits: usize, endianness: Endianness| {
+        let mut bits: Vec<pallas::Base> = (0..num_bits)
+            .map(|i| pallas::Base::from((value >> i) & 1))
+            .collect();
+        if endianness == Endianness::Big {
+            bits.reverse();
+        }
+        bits.push(pallas::Base::from(value));
+        bits
+    };
+
+    for endianness in [Endianness::Little, Endianness::Big] {
+        for (value, num_bits) in [(0b1011, 4), (0b1011, 8), (0, 1), (u64::MAX, 64)] {
+            let circuit = MyCircuit {
+                value: pallas::Base::from(value),
+                num_bits,
+                endianness: Some(endianness),
+            };
+            let prover =
+                MockProver::run(11, &circuit, vec![bits(value, num_bits, endianness)]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+
+        // The value doesn't fit in the bits
+        let circuit = MyCircuit {
+            value: pallas::Base::from(0b10110),
+            num_bits: 4,
+            endianness: Some(endianness),
+        };
+        let prover = MockProver::run(11, &circuit, vec![bits(0b0110, 4, endianness)]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    // The bit order is constrained
+    let circuit = MyCircuit {
+        value: pallas::Base::from(0b1011),
+        num_bits: 4,
+        endianness: Some(Endianness::Little),
+    };
+    let prover = MockProver::run(11, &circuit, vec![bits(0b1011, 4, Endianness::Big)]).unwrap();
+    assert!(prover.verify().is_err());
+}
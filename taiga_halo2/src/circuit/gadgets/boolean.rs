@@ -0,0 +1,309 @@
+/// Boolean circuit variables. Gadgets whose soundness relies on a 0/1 flag, like the
+/// conditional equal gadget, take an `AssignedBool` instead of a bare cell, so a flag can only
+/// come from a gadget that constrains it.
+///
+/// The combinators are built from the add, sub and mul chips of the resource logic circuits, so
+/// the resource logics don't need to hand-roll boolean arithmetic.
+use crate::circuit::gadgets::{
+    add::{AddChip, AddInstructions},
+    assign_free_advice, assign_free_constant,
+    mul::{MulChip, MulInstructions},
+    sub::{SubChip, SubInstructions},
+};
+use halo2_proofs::{
+    arithmetic::Field,
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::{Advice, Column, Error},
+};
+use pasta_curves::pallas;
+use std::ops::Deref;
+
+/// A cell constrained to be 0 or 1.
+#[derive(Clone, Debug)]
+pub struct AssignedBool(AssignedCell<pallas::Base, pallas::Base>);
+
+impl AssignedBool {
+    /// Wraps a cell that is already bool-checked by the gate assigning it.
+    pub(crate) fn from_bool_checked(cell: AssignedCell<pallas::Base, pallas::Base>) -> Self {
+        Self(cell)
+    }
+
+    /// Constrains `cell * cell = cell`.
+    pub fn assert_bool(
+        mul_chip: &MulChip<pallas::Base>,
+        mut layouter: impl Layouter<pallas::Base>,
+        cell: &AssignedCell<pallas::Base, pallas::Base>,
+    ) -> Result<Self, Error> {
+        let square =
+            MulInstructions::mul(mul_chip, layouter.namespace(|| "cell * cell"), cell, cell)?;
+        layouter.assign_region(
+            || "bool check: cell * cell == cell",
+            |mut region| region.constrain_equal(square.cell(), cell.cell()),
+        )?;
+        Ok(Self(cell.clone()))
+    }
+
+    /// Witnesses a boolean and constrains it to be 0 or 1.
+    pub fn witness(
+        mul_chip: &MulChip<pallas::Base>,
+        mut layouter: impl Layouter<pallas::Base>,
+        column: Column<Advice>,
+        value: Value<bool>,
+    ) -> Result<Self, Error> {
+        let cell = assign_free_advice(
+            layouter.namespace(|| "witness bool"),
+            column,
+            value.map(pallas::Base::from),
+        )?;
+        Self::assert_bool(mul_chip, layouter.namespace(|| "bool check"), &cell)
+    }
+
+    /// Returns `1 - self`.
+    pub fn not(
+        &self,
+        sub_chip: &SubChip<pallas::Base>,
+        mut layouter: impl Layouter<pallas::Base>,
+        column: Column<Advice>,
+    ) -> Result<Self, Error> {
+        let one = assign_free_constant(layouter.namespace(|| "one"), column, pallas::Base::one())?;
+        let not = SubInstructions::sub(sub_chip, layouter.namespace(|| "1 - flag"), &one, &self.0)?;
+        Ok(Self(not))
+    }
+
+    /// Returns `self * other`.
+    pub fn and(
+        &self,
+        mul_chip: &MulChip<pallas::Base>,
+        layouter: impl Layouter<pallas::Base>,
+        other: &Self,
+    ) -> Result<Self, Error> {
+        let and = MulInstructions::mul(mul_chip, layouter, &self.0, &other.0)?;
+        Ok(Self(and))
+    }
+
+    /// Returns `self + other - self * other`.
+    pub fn or(
+        &self,
+        add_chip: &AddChip<pallas::Base>,
+        sub_chip: &SubChip<pallas::Base>,
+        mul_chip: &MulChip<pallas::Base>,
+        mut layouter: impl Layouter<pallas::Base>,
+        other: &Self,
+    ) -> Result<Self, Error> {
+        let sum =
+            AddInstructions::add(add_chip, layouter.namespace(|| "a + b"), &self.0, &other.0)?;
+        let and = self.and(mul_chip, layouter.namespace(|| "a * b"), other)?;
+        let or =
+            SubInstructions::sub(sub_chip, layouter.namespace(|| "a + b - a * b"), &sum, &and)?;
+        Ok(Self(or))
+    }
+
+    /// Returns `self + other - 2 * self * other`.
+    pub fn xor(
+        &self,
+        add_chip: &AddChip<pallas::Base>,
+        sub_chip: &SubChip<pallas::Base>,
+        mul_chip: &MulChip<pallas::Base>,
+        mut layouter: impl Layouter<pallas::Base>,
+        other: &Self,
+    ) -> Result<Self, Error> {
+        let or = self.or(
+            add_chip,
+            sub_chip,
+            mul_chip,
+            layouter.namespace(|| "a or b"),
+            other,
+        )?;
+        let and = self.and(mul_chip, layouter.namespace(|| "a * b"), other)?;
+        let xor = SubInstructions::sub(
+            sub_chip,
+            layouter.namespace(|| "(a or b) - a * b"),
+            &or,
+            &and,
+        )?;
+        Ok(Self(xor))
+    }
+
+    /// Returns 1 if `x != 0`, otherwise 0.
+    /// Witnesses `inv = x^-1` (0 when x = 0) and constrains `flag = x * inv` and `x * (1 - flag) = 0`.
+    pub fn is_nonzero(
+        mul_chip: &MulChip<pallas::Base>,
+        sub_chip: &SubChip<pallas::Base>,
+        mut layouter: impl Layouter<pallas::Base>,
+        column: Column<Advice>,
+        x: &AssignedCell<pallas::Base, pallas::Base>,
+    ) -> Result<Self, Error> {
+        let inv = assign_free_advice(
+            layouter.namespace(|| "witness x^-1"),
+            column,
+            x.value()
+                .map(|x| x.invert().unwrap_or(pallas::Base::zero())),
+        )?;
+        let flag = MulInstructions::mul(mul_chip, layouter.namespace(|| "x * inv"), x, &inv)?;
+        let one = assign_free_constant(layouter.namespace(|| "one"), column, pallas::Base::one())?;
+        let not_flag =
+            SubInstructions::sub(sub_chip, layouter.namespace(|| "1 - flag"), &one, &flag)?;
+        let check = MulInstructions::mul(
+            mul_chip,
+            layouter.namespace(|| "x * (1 - flag)"),
+            x,
+            &not_flag,
+        )?;
+        let zero =
+            assign_free_constant(layouter.namespace(|| "zero"), column, pallas::Base::zero())?;
+        layouter.assign_region(
+            || "x * (1 - flag) == 0",
+            |mut region| region.constrain_equal(check.cell(), zero.cell()),
+        )?;
+        Ok(Self(flag))
+    }
+}
+
+impl Deref for AssignedBool {
+    type Target = AssignedCell<pallas::Base, pallas::Base>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[test]
+fn test_halo2_boolean_gadget() {
+    use crate::circuit::gadgets::{add::AddConfig, mul::MulConfig, sub::SubConfig};
+    use halo2_proofs::{
+        circuit::SimpleFloorPlanner,
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem},
+    };
+
+    #[derive(Default)]
+    struct MyCircuit {
+        a: pallas::Base,
+        b: pallas::Base,
+        x: pallas::Base,
+        // expected (not a, a and b, x != 0, a or b, a xor b)
+        expected: [pallas::Base; 5],
+    }
+
+    impl Circuit<pallas::Base> for MyCircuit {
+        type Config = ([Column<Advice>; 2], AddConfig, MulConfig, SubConfig);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            let advices = [meta.advice_column(), meta.advice_column()];
+            for advice in advices.iter() {
+                meta.enable_equality(*advice);
+            }
+            let constants = meta.fixed_column();
+            meta.enable_constant(constants);
+            let add_config = AddChip::configure(meta, advices);
+            let mul_config = MulChip::configure(meta, advices);
+            let sub_config = SubChip::configure(meta, advices);
+            (advices, add_config, mul_config, sub_config)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), Error> {
+            let (advices, add_config, mul_config, sub_config) = config;
+            let add_chip = AddChip::construct(add_config, ());
+            let mul_chip = MulChip::construct(mul_config);
+            let sub_chip = SubChip::construct(sub_config, ());
+
+            let mut witness_bool = |name: &'static str, value: pallas::Base| {
+                let cell = assign_free_advice(
+                    layouter.namespace(|| name),
+                    advices[0],
+                    Value::known(value),
+                )?;
+                AssignedBool::assert_bool(&mul_chip, layouter.namespace(|| name), &cell)
+            };
+            let a = witness_bool("a", self.a)?;
+            let b = witness_bool("b", self.b)?;
+            let x = assign_free_advice(
+                layouter.namespace(|| "witness x"),
+                advices[0],
+                Value::known(self.x),
+            )?;
+
+            let not_a = a.not(&sub_chip, layouter.namespace(|| "not a"), advices[0])?;
+            let a_and_b = a.and(&mul_chip, layouter.namespace(|| "a and b"), &b)?;
+            let x_is_nonzero = AssignedBool::is_nonzero(
+                &mul_chip,
+                &sub_chip,
+                layouter.namespace(|| "x != 0"),
+                advices[0],
+                &x,
+            )?;
+
+            let a_or_b = a.or(
+                &add_chip,
+                &sub_chip,
+                &mul_chip,
+                layouter.namespace(|| "a or b"),
+                &b,
+            )?;
+            let a_xor_b = a.xor(
+                &add_chip,
+                &sub_chip,
+                &mul_chip,
+                layouter.namespace(|| "a xor b"),
+                &b,
+            )?;
+            let witnessed_true = AssignedBool::witness(
+                &mul_chip,
+                layouter.namespace(|| "witness true"),
+                advices[0],
+                Value::known(true),
+            )?;
+            let one = pallas::Base::one();
+
+            for (name, flag, expected) in [
+                ("not a", not_a, self.expected[0]),
+                ("a and b", a_and_b, self.expected[1]),
+                ("x != 0", x_is_nonzero, self.expected[2]),
+                ("a or b", a_or_b, self.expected[3]),
+                ("a xor b", a_xor_b, self.expected[4]),
+                ("true", witnessed_true, one),
+            ] {
+                let expected =
+                    assign_free_constant(layouter.namespace(|| name), advices[1], expected)?;
+                layouter.assign_region(
+                    || name,
+                    |mut region| region.constrain_equal(flag.cell(), expected.cell()),
+                )?;
+            }
+            Ok(())
+        }
+    }
+
+    let zero = pallas::Base::zero();
+    let one = pallas::Base::one();
+    let two = pallas::Base::from(2u64);
+    // (a, b, x, expected, satisfied)
+    let cases = [
+        (zero, zero, zero, [one, zero, zero, zero, zero], true),
+        (one, zero, two, [zero, zero, one, one, one], true),
+        (one, one, -one, [zero, one, one, one, zero], true),
+        (zero, one, two, [one, zero, one, one, one], true),
+        // Wrong results
+        (one, one, zero, [zero, one, one, one, zero], false),
+        (one, zero, two, [zero, one, one, one, one], false),
+        (one, one, -one, [zero, one, one, one, one], false),
+        (zero, zero, zero, [one, zero, zero, one, zero], false),
+        // Non-boolean flags are rejected by assert_bool
+        (two, zero, zero, [-one, zero, zero, two, two], false),
+        (one, two, zero, [zero, two, zero, one, -one], false),
+    ];
+    for (a, b, x, expected, satisfied) in cases {
+        let circuit = MyCircuit { a, b, x, expected };
+        let prover = MockProver::<pallas::Base>::run(8, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify().is_ok(), satisfied);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::circuit::gadgets::{
+    assign_free_advice, assign_free_constant, poseidon_hash::poseidon_hash_gadget,
+};
+use crate::utils::{encode_bytes_to_fields, BYTES_PER_FIELD};
+use halo2_gadgets::poseidon::Pow5Config as PoseidonConfig;
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::{Advice, Column, Error},
+};
+use pasta_curves::pallas;
+
+/// Assigns the `utils::encode_bytes_to_fields` encoding of a byte string: the 31-byte chunks
+/// as witnesses, followed by the length as a constant. The length must be fixed by the
+/// circuit, so a proof for one length can't be reused for another.
+pub fn assign_encoded_bytes(
+    mut layouter: impl Layouter<pallas::Base>,
+    advice: Column<Advice>,
+    bytes: Value<&[u8]>,
+    len: usize,
+) -> Result<Vec<AssignedCell<pallas::Base, pallas::Base>>, Error> {
+    let fields = bytes.map(|bytes| {
+        assert_eq!(bytes.len(), len, "unexpected byte string length");
+        encode_bytes_to_fields(bytes)
+    });
+    let mut cells = (0..len.div_ceil(BYTES_PER_FIELD))
+        .map(|i| {
+            assign_free_advice(
+                layouter.namespace(|| "bytes chunk"),
+                advice,
+                fields.as_ref().map(|f| f[i]),
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    cells.push(assign_free_constant(
+        layouter.namespace(|| "bytes length"),
+        advice,
+        pallas::Base::from(len as u64),
+    )?);
+    Ok(cells)
+}
+
+/// The in-circuit counterpart of `utils::poseidon_hash_bytes`.
+pub fn hash_encoded_bytes_gadget(
+    poseidon_config: PoseidonConfig<pallas::Base, 3, 2>,
+    mut layouter: impl Layouter<pallas::Base>,
+    advice: Column<Advice>,
+    encoded_bytes: &[AssignedCell<pallas::Base, pallas::Base>],
+) -> Result<AssignedCell<pallas::Base, pallas::Base>, Error> {
+    let init = assign_free_constant(layouter.namespace(|| "zero"), advice, pallas::Base::zero())?;
+    encoded_bytes.iter().try_fold(init, |acc, field| {
+        poseidon_hash_gadget(
+            poseidon_config.clone(),
+            layouter.namespace(|| "hash encoded bytes"),
+            [acc, field.clone()],
+        )
+    })
+}
+
+#[test]
+fn test_halo2_bytes_encoding_gadget() {
+    use crate::circuit::resource_logic_circuit::ResourceLogicConfig;
+    use crate::constant::RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE;
+    use crate::utils::poseidon_hash_bytes;
+    use halo2_proofs::{
+        circuit::SimpleFloorPlanner,
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem},
+    };
+
+    const LEN: usize = 40;
+
+    #[derive(Default)]
+    struct MyCircuit {
+        bytes: Vec<u8>,
+    }
+
+    impl Circuit<pallas::Base> for MyCircuit {
+        type Config = ResourceLogicConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            ResourceLogicConfig::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), Error> {
+            let encoded_bytes = assign_encoded_bytes(
+                layouter.namespace(|| "assign encoded bytes"),
+                config.advices[0],
+                Value::known(self.bytes.as_slice()),
+                LEN,
+            )?;
+            let hash = hash_encoded_bytes_gadget(
+                config.poseidon_config,
+                layouter.namespace(|| "hash encoded bytes"),
+                config.advices[0],
+                &encoded_bytes,
+            )?;
+            layouter.constrain_instance(hash.cell(), config.instances, 0)
+        }
+    }
+
+    let bytes: Vec<u8> = (0..LEN as u8).collect();
+    let circuit = MyCircuit {
+        bytes: bytes.clone(),
+    };
+    let prover = MockProver::<pallas::Base>::run(
+        RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+        &circuit,
+        vec![vec![poseidon_hash_bytes(&bytes)]],
+    )
+    .unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // The hash of another byte string is rejected
+    let prover = MockProver::<pallas::Base>::run(
+        RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+        &circuit,
+        vec![vec![poseidon_hash_bytes(&bytes[..LEN - 1])]],
+    )
+    .unwrap();
+    assert!(prover.verify().is_err());
+}
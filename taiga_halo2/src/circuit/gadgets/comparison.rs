@@ -0,0 +1,233 @@
+/// Comparison of u64 values: `a < b` iff `a - b + 2^64` is below 2^64, so the chip witnesses the
+/// flag `lt` and range checks `c = a + lt * 2^64 - b`. Both operands are range checked to u64 by
+/// the chip, the field subtraction can't wrap into the range otherwise.
+///
+/// The chip is built from the add, sub, mul and lookup range check configs, it adds no gate to
+/// the circuits.
+use crate::circuit::gadgets::{
+    add::{AddChip, AddConfig, AddInstructions},
+    assign_free_constant,
+    boolean::AssignedBool,
+    mul::{MulChip, MulConfig, MulInstructions},
+    range_check::RangeCheckChip,
+    sub::{SubChip, SubConfig, SubInstructions},
+    u128::base_to_u128,
+};
+use group::ff::PrimeField;
+use halo2_gadgets::utilities::lookup_range_check::LookupRangeCheckConfig;
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter},
+    plonk::{Advice, Column, Error},
+};
+use pasta_curves::pallas;
+
+#[derive(Clone, Debug)]
+pub struct ComparisonChip {
+    advice: Column<Advice>,
+    add_chip: AddChip<pallas::Base>,
+    sub_chip: SubChip<pallas::Base>,
+    mul_chip: MulChip<pallas::Base>,
+    range_check: RangeCheckChip,
+}
+
+impl ComparisonChip {
+    pub fn construct(
+        advice: Column<Advice>,
+        add_config: AddConfig,
+        sub_config: SubConfig,
+        mul_config: MulConfig,
+        range_check_config: LookupRangeCheckConfig<pallas::Base, 10>,
+    ) -> Self {
+        Self {
+            advice,
+            add_chip: AddChip::construct(add_config, ()),
+            sub_chip: SubChip::construct(sub_config, ()),
+            mul_chip: MulChip::construct(mul_config),
+            range_check: RangeCheckChip::construct(range_check_config),
+        }
+    }
+
+    /// Returns 1 if `a < b`, otherwise 0.
+    pub fn is_less_than(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        a: &AssignedCell<pallas::Base, pallas::Base>,
+        b: &AssignedCell<pallas::Base, pallas::Base>,
+    ) -> Result<AssignedBool, Error> {
+        self.range_check
+            .range_check_u64(layouter.namespace(|| "range check a"), a)?;
+        self.range_check
+            .range_check_u64(layouter.namespace(|| "range check b"), b)?;
+
+        let lt = AssignedBool::witness(
+            &self.mul_chip,
+            layouter.namespace(|| "witness a < b"),
+            self.advice,
+            a.value()
+                .zip(b.value())
+                .map(|(a, b)| base_to_u128(a) < base_to_u128(b)),
+        )?;
+
+        // c = a + lt * 2^64 - b
+        let two_pow_64 = assign_free_constant(
+            layouter.namespace(|| "2^64"),
+            self.advice,
+            pallas::Base::from_u128(1 << 64),
+        )?;
+        let shift = MulInstructions::mul(
+            &self.mul_chip,
+            layouter.namespace(|| "lt * 2^64"),
+            &lt,
+            &two_pow_64,
+        )?;
+        let shifted_a = AddInstructions::add(
+            &self.add_chip,
+            layouter.namespace(|| "a + lt * 2^64"),
+            a,
+            &shift,
+        )?;
+        let c = SubInstructions::sub(
+            &self.sub_chip,
+            layouter.namespace(|| "a + lt * 2^64 - b"),
+            &shifted_a,
+            b,
+        )?;
+        self.range_check
+            .range_check_u64(layouter.namespace(|| "range check c"), &c)?;
+
+        Ok(lt)
+    }
+
+    /// Returns 1 if `a <= b`, otherwise 0.
+    pub fn is_leq(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        a: &AssignedCell<pallas::Base, pallas::Base>,
+        b: &AssignedCell<pallas::Base, pallas::Base>,
+    ) -> Result<AssignedBool, Error> {
+        let gt = self.is_less_than(layouter.namespace(|| "b < a"), b, a)?;
+        gt.not(
+            &self.sub_chip,
+            layouter.namespace(|| "!(b < a)"),
+            self.advice,
+        )
+    }
+}
+
+#[test]
+fn test_halo2_comparison() {
+    use crate::circuit::gadgets::assign_free_advice;
+    use halo2_proofs::{
+        circuit::{SimpleFloorPlanner, Value},
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem, Instance},
+    };
+
+    #[derive(Default)]
+    struct MyCircuit {
+        a: pallas::Base,
+        b: pallas::Base,
+    }
+
+    impl Circuit<pallas::Base> for MyCircuit {
+        type Config = (
+            [Column<Advice>; 2],
+            Column<Instance>,
+            AddConfig,
+            SubConfig,
+            MulConfig,
+            LookupRangeCheckConfig<pallas::Base, 10>,
+        );
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            let advices = [meta.advice_column(), meta.advice_column()];
+            for advice in advices.iter() {
+                meta.enable_equality(*advice);
+            }
+            let instances = meta.instance_column();
+            meta.enable_equality(instances);
+            let constants = meta.fixed_column();
+            meta.enable_constant(constants);
+            let table_idx = meta.lookup_table_column();
+            let range_check = LookupRangeCheckConfig::configure(meta, advices[0], table_idx);
+            (
+                advices,
+                instances,
+                AddChip::configure(meta, advices),
+                SubChip::configure(meta, advices),
+                MulChip::configure(meta, advices),
+                range_check,
+            )
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), Error> {
+            let (advices, instances, add_config, sub_config, mul_config, range_check) = config;
+            range_check.load(&mut layouter)?;
+            let chip = ComparisonChip::construct(
+                advices[0],
+                add_config,
+                sub_config,
+                mul_config,
+                range_check,
+            );
+            let a = assign_free_advice(
+                layouter.namespace(|| "witness a"),
+                advices[0],
+                Value::known(self.a),
+            )?;
+            let b = assign_free_advice(
+                layouter.namespace(|| "witness b"),
+                advices[0],
+                Value::known(self.b),
+            )?;
+            let lt = chip.is_less_than(layouter.namespace(|| "a < b"), &a, &b)?;
+            layouter.constrain_instance(lt.cell(), instances, 0)?;
+            let leq = chip.is_leq(layouter.namespace(|| "a <= b"), &a, &b)?;
+            layouter.constrain_instance(leq.cell(), instances, 1)
+        }
+    }
+
+    let max = u64::MAX;
+    for (a, b) in [
+        (0, 0),
+        (0, 1),
+        (1, 0),
+        (5, 5),
+        (3, 7),
+        (7, 3),
+        (0, max),
+        (max, 0),
+        (max, max),
+    ] {
+        let circuit = MyCircuit {
+            a: pallas::Base::from(a),
+            b: pallas::Base::from(b),
+        };
+        let instances = vec![pallas::Base::from(a < b), pallas::Base::from(a <= b)];
+        let prover = MockProver::run(11, &circuit, vec![instances]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // The flags can't be flipped
+        let instances = vec![pallas::Base::from(a >= b), pallas::Base::from(a <= b)];
+        let prover = MockProver::run(11, &circuit, vec![instances]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    // The operands must be u64
+    let circuit = MyCircuit {
+        a: pallas::Base::from(3) - pallas::Base::from(5),
+        b: pallas::Base::from(1),
+    };
+    let instances = vec![pallas::Base::zero(), pallas::Base::zero()];
+    let prover = MockProver::run(11, &circuit, vec![instances]).unwrap();
+    assert!(prover.verify().is_err());
+}
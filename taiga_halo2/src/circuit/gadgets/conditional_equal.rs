@@ -1,4 +1,7 @@
 /// Constrain flag * (lhs - rhs) = 0
+/// The gate only enforces lhs == rhs when the flag is set, so it is as sound as the flag. The
+/// flag is an `AssignedBool` and must come from a gadget constraining it to 0 or 1.
+use crate::circuit::gadgets::boolean::AssignedBool;
 use halo2_proofs::{
     circuit::{AssignedCell, Region},
     plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Selector},
@@ -44,7 +47,7 @@ impl ConditionalEqualConfig {
 
     pub fn assign_region(
         &self,
-        flag: &AssignedCell<pallas::Base, pallas::Base>,
+        flag: &AssignedBool,
         lhs: &AssignedCell<pallas::Base, pallas::Base>,
         rhs: &AssignedCell<pallas::Base, pallas::Base>,
         offset: usize,
@@ -60,3 +63,105 @@ impl ConditionalEqualConfig {
         Ok(())
     }
 }
+
+#[test]
+fn test_halo2_conditional_equal_gadget() {
+    use crate::circuit::gadgets::{
+        assign_free_advice,
+        mul::{MulChip, MulConfig},
+    };
+    use halo2_proofs::{
+        arithmetic::Field,
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        plonk::Circuit,
+    };
+
+    #[derive(Default)]
+    struct MyCircuit {
+        flag: pallas::Base,
+        lhs: pallas::Base,
+        rhs: pallas::Base,
+    }
+
+    impl Circuit<pallas::Base> for MyCircuit {
+        type Config = ([Column<Advice>; 3], ConditionalEqualConfig, MulConfig);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            let advices = [
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+            ];
+            for advice in advices.iter() {
+                meta.enable_equality(*advice);
+            }
+            let conditional_equal_config = ConditionalEqualConfig::configure(meta, advices);
+            let mul_config = MulChip::configure(meta, [advices[0], advices[1]]);
+            (advices, conditional_equal_config, mul_config)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), Error> {
+            let (advices, conditional_equal_config, mul_config) = config;
+            let mul_chip = MulChip::construct(mul_config);
+            let flag = assign_free_advice(
+                layouter.namespace(|| "witness flag"),
+                advices[0],
+                Value::known(self.flag),
+            )?;
+            let flag = AssignedBool::assert_bool(
+                &mul_chip,
+                layouter.namespace(|| "bool check flag"),
+                &flag,
+            )?;
+            let lhs = assign_free_advice(
+                layouter.namespace(|| "witness lhs"),
+                advices[1],
+                Value::known(self.lhs),
+            )?;
+            let rhs = assign_free_advice(
+                layouter.namespace(|| "witness rhs"),
+                advices[2],
+                Value::known(self.rhs),
+            )?;
+
+            layouter.assign_region(
+                || "conditional equal",
+                |mut region| {
+                    conditional_equal_config.assign_region(&flag, &lhs, &rhs, 0, &mut region)
+                },
+            )
+        }
+    }
+
+    let zero = pallas::Base::zero();
+    let one = pallas::Base::one();
+    let max = -pallas::Base::one();
+    // (flag, lhs, rhs, satisfied)
+    let cases = [
+        (one, zero, zero, true),
+        (one, max, max, true),
+        (one, max, zero, false),
+        (one, one, pallas::Base::from(2u64), false),
+        // The check is skipped when the flag is off
+        (zero, max, zero, true),
+        (zero, zero, zero, true),
+        // Non-boolean flags are rejected
+        (pallas::Base::from(2u64), max, max, false),
+        (max, zero, zero, false),
+    ];
+    for (flag, lhs, rhs, satisfied) in cases {
+        let circuit = MyCircuit { flag, lhs, rhs };
+        let prover = MockProver::<pallas::Base>::run(6, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify().is_ok(), satisfied);
+    }
+}
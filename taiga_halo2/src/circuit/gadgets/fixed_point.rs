@@ -0,0 +1,329 @@
+/// Fixed-point multiplication and division with explicit rounding:
+/// q = round(a * b / denominator), where a * b + offset = q * denominator + r and 0 <= r < denominator.
+/// The offset is 0 for Rounding::Down, denominator - 1 for Rounding::Up and
+/// denominator / 2 for Rounding::HalfUp.
+///
+/// E.g. a 0.3% fee on `quantity` is `mul_div(quantity, 30, 10_000, Rounding::Up)`.
+use crate::circuit::gadgets::{range_check::RangeCheckChip, u128::base_to_u128};
+use group::ff::PrimeField;
+use halo2_gadgets::utilities::lookup_range_check::LookupRangeCheckConfig;
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Expression, Selector},
+    poly::Rotation,
+};
+use pasta_curves::pallas;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Rounding {
+    /// Round towards zero.
+    #[default]
+    Down,
+    /// Round away from zero.
+    Up,
+    /// Round to the nearest integer, ties are rounded up.
+    HalfUp,
+}
+
+impl Rounding {
+    fn offset(&self, denominator: u64) -> u64 {
+        match self {
+            Rounding::Down => 0,
+            Rounding::Up => denominator - 1,
+            Rounding::HalfUp => denominator / 2,
+        }
+    }
+}
+
+/// The native counterpart of `FixedPointConfig::mul_div`.
+pub fn mul_div(a: u64, b: u64, denominator: u64, rounding: Rounding) -> u128 {
+    assert!(denominator > 0);
+    let (q, _) = mul_div_rem(a as u128, b as u128, denominator, rounding);
+    q
+}
+
+fn mul_div_rem(a: u128, b: u128, denominator: u64, rounding: Rounding) -> (u128, u128) {
+    let numerator = a * b + rounding.offset(denominator) as u128;
+    let denominator = denominator as u128;
+    (numerator / denominator, numerator % denominator)
+}
+
+#[derive(Clone, Debug)]
+pub struct FixedPointConfig {
+    q_mul_div: Selector,
+    advice: [Column<Advice>; 4],
+    range_check: RangeCheckChip,
+}
+
+impl FixedPointConfig {
+    pub fn configure(
+        meta: &mut ConstraintSystem<pallas::Base>,
+        advice: [Column<Advice>; 4],
+        range_check: LookupRangeCheckConfig<pallas::Base, 10>,
+    ) -> Self {
+        let config = Self {
+            q_mul_div: meta.selector(),
+            advice,
+            range_check: RangeCheckChip::construct(range_check),
+        };
+
+        config.create_gate(meta);
+
+        config
+    }
+
+    fn create_gate(&self, meta: &mut ConstraintSystem<pallas::Base>) {
+        // | a | b           | offset | slack |
+        // | q | denominator | r      |       |
+        meta.create_gate("fixed point mul div", |meta| {
+            let q_mul_div = meta.query_selector(self.q_mul_div);
+            let a = meta.query_advice(self.advice[0], Rotation::cur());
+            let b = meta.query_advice(self.advice[1], Rotation::cur());
+            let offset = meta.query_advice(self.advice[2], Rotation::cur());
+            let slack = meta.query_advice(self.advice[3], Rotation::cur());
+            let q = meta.query_advice(self.advice[0], Rotation::next());
+            let denominator = meta.query_advice(self.advice[1], Rotation::next());
+            let r = meta.query_advice(self.advice[2], Rotation::next());
+            let one = Expression::Constant(pallas::Base::one());
+
+            Constraints::with_selector(
+                q_mul_div,
+                [
+                    (
+                        "a * b + offset = q * denominator + r",
+                        a * b + offset - q * denominator.clone() - r.clone(),
+                    ),
+                    (
+                        "slack = denominator - 1 - r",
+                        slack - (denominator - one - r),
+                    ),
+                ],
+            )
+        });
+    }
+
+    /// Returns round(a * b / denominator). `a` and `b` must be range-checked u64 values,
+    /// the denominator is a circuit constant.
+    pub fn mul_div(
+        &self,
+        layouter: impl Layouter<pallas::Base>,
+        a: &AssignedCell<pallas::Base, pallas::Base>,
+        b: &AssignedCell<pallas::Base, pallas::Base>,
+        denominator: u64,
+        rounding: Rounding,
+    ) -> Result<AssignedCell<pallas::Base, pallas::Base>, Error> {
+        assert!(denominator > 0);
+        let q_and_r = a.value().zip(b.value()).map(|(a, b)| {
+            let (q, r) = mul_div_rem(base_to_u128(a), base_to_u128(b), denominator, rounding);
+            (pallas::Base::from_u128(q), pallas::Base::from_u128(r))
+        });
+        self.assign_mul_div(layouter, a, b, denominator, rounding, q_and_r)
+    }
+
+    // q < 2^128, denominator < 2^64 and r < 2^64, so q * denominator + r can't wrap and the
+    // quotient is unique.
+    fn assign_mul_div(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        a: &AssignedCell<pallas::Base, pallas::Base>,
+        b: &AssignedCell<pallas::Base, pallas::Base>,
+        denominator: u64,
+        rounding: Rounding,
+        q_and_r: Value<(pallas::Base, pallas::Base)>,
+    ) -> Result<AssignedCell<pallas::Base, pallas::Base>, Error> {
+        let (q, r, slack) = layouter.assign_region(
+            || "fixed point mul div",
+            |mut region| {
+                self.q_mul_div.enable(&mut region, 0)?;
+                a.copy_advice(|| "a", &mut region, self.advice[0], 0)?;
+                b.copy_advice(|| "b", &mut region, self.advice[1], 0)?;
+                region.assign_advice_from_constant(
+                    || "offset",
+                    self.advice[2],
+                    0,
+                    pallas::Base::from(rounding.offset(denominator)),
+                )?;
+                let slack = region.assign_advice(
+                    || "slack",
+                    self.advice[3],
+                    0,
+                    || q_and_r.map(|(_, r)| pallas::Base::from(denominator - 1) - r),
+                )?;
+                let q =
+                    region.assign_advice(|| "q", self.advice[0], 1, || q_and_r.map(|(q, _)| q))?;
+                region.assign_advice_from_constant(
+                    || "denominator",
+                    self.advice[1],
+                    1,
+                    pallas::Base::from(denominator),
+                )?;
+                let r =
+                    region.assign_advice(|| "r", self.advice[2], 1, || q_and_r.map(|(_, r)| r))?;
+                Ok((q, r, slack))
+            },
+        )?;
+
+        // 0 <= r < denominator
+        self.range_check
+            .range_check_u64(layouter.namespace(|| "range check r"), &r)?;
+        self.range_check
+            .range_check_u64(layouter.namespace(|| "range check slack"), &slack)?;
+        // a * b + offset < 2^128, and so is the honest quotient
+        self.range_check
+            .range_check_u128(layouter.namespace(|| "range check q"), &q)?;
+
+        Ok(q)
+    }
+
+    /// Returns round(a / denominator).
+    pub fn div(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        a: &AssignedCell<pallas::Base, pallas::Base>,
+        denominator: u64,
+        rounding: Rounding,
+    ) -> Result<AssignedCell<pallas::Base, pallas::Base>, Error> {
+        let one = layouter.assign_region(
+            || "constant one",
+            |mut region| {
+                region.assign_advice_from_constant(|| "one", self.advice[1], 0, pallas::Base::one())
+            },
+        )?;
+        self.mul_div(layouter, a, &one, denominator, rounding)
+    }
+}
+
+#[test]
+fn test_halo2_fixed_point_mul_div() {
+    use crate::circuit::gadgets::assign_free_advice;
+    use group::ff::Field;
+    use halo2_proofs::{circuit::SimpleFloorPlanner, dev::MockProver, plonk::Circuit};
+
+    #[derive(Default)]
+    struct MyCircuit {
+        a: u64,
+        b: u64,
+        denominator: u64,
+        rounding: Rounding,
+        // Overrides the witnessed (q, r)
+        forged: Option<(pallas::Base, pallas::Base)>,
+    }
+
+    impl Circuit<pallas::Base> for MyCircuit {
+        type Config = (FixedPointConfig, LookupRangeCheckConfig<pallas::Base, 10>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            let advice = [
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+            ];
+            for column in advice {
+                meta.enable_equality(column);
+            }
+            let table_idx = meta.lookup_table_column();
+            let constants = meta.fixed_column();
+            meta.enable_constant(constants);
+            let range_check = LookupRangeCheckConfig::configure(meta, advice[0], table_idx);
+            (
+                FixedPointConfig::configure(meta, advice, range_check),
+                range_check,
+            )
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), Error> {
+            let (config, range_check) = config;
+            range_check.load(&mut layouter)?;
+            let a = assign_free_advice(
+                layouter.namespace(|| "witness a"),
+                config.advice[0],
+                Value::known(pallas::Base::from(self.a)),
+            )?;
+            let b = assign_free_advice(
+                layouter.namespace(|| "witness b"),
+                config.advice[1],
+                Value::known(pallas::Base::from(self.b)),
+            )?;
+            let q = match self.forged {
+                Some(q_and_r) => config.assign_mul_div(
+                    layouter.namespace(|| "forged mul div"),
+                    &a,
+                    &b,
+                    self.denominator,
+                    self.rounding,
+                    Value::known(q_and_r),
+                )?,
+                None => config.mul_div(
+                    layouter.namespace(|| "mul div"),
+                    &a,
+                    &b,
+                    self.denominator,
+                    self.rounding,
+                )?,
+            };
+            q.value().assert_if_known(|q| {
+                self.forged.is_some()
+                    || **q
+                        == pallas::Base::from_u128(mul_div(
+                            self.a,
+                            self.b,
+                            self.denominator,
+                            self.rounding,
+                        ))
+            });
+            Ok(())
+        }
+    }
+
+    for (a, b, denominator, rounding) in [
+        (1000, 30, 10_000, Rounding::Up),
+        (1000, 30, 10_000, Rounding::Down),
+        (1005, 1, 10, Rounding::HalfUp),
+        (u64::MAX, u64::MAX, 1, Rounding::Down),
+        (u64::MAX, u64::MAX, u64::MAX, Rounding::Up),
+    ] {
+        let circuit = MyCircuit {
+            a,
+            b,
+            denominator,
+            rounding,
+            forged: None,
+        };
+        let prover = MockProver::run(11, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    // 1000 * 30 + 9999 = 3 * 10_000 + 9999. With r = 9998 the field quotient
+    // 30001 / 10_000 satisfies the gate but wraps, and is rejected by the q range check.
+    let denominator = pallas::Base::from(10_000);
+    let r = pallas::Base::from(9998);
+    let q = (pallas::Base::from(1000 * 30 + 9999) - r) * denominator.invert().unwrap();
+    assert_eq!(q * denominator + r, pallas::Base::from(39_999));
+    let circuit = MyCircuit {
+        a: 1000,
+        b: 30,
+        denominator: 10_000,
+        rounding: Rounding::Up,
+        forged: Some((q, r)),
+    };
+    let prover = MockProver::run(11, &circuit, vec![]).unwrap();
+    assert!(prover.verify().is_err());
+
+    // The honest (q, r) passes through the same path
+    let circuit = MyCircuit {
+        forged: Some((pallas::Base::from(3), pallas::Base::from(9999))),
+        ..circuit
+    };
+    let prover = MockProver::run(11, &circuit, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+}
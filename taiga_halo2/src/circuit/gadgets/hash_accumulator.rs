@@ -0,0 +1,375 @@
+/// A running Poseidon hash committing to a variable-length list of field elements.
+///
+///   state_0 = domain
+///   state_{i+1} = poseidon_hash(state_i || item_i)
+///   commitment = poseidon_hash(state_n || n)
+///
+/// The domain separates the lists of different applications (dynamic vk sets, intent options,
+/// cascade lists, ...) and the length is bound in the final hash. The vk policies of the token
+/// and receiver resource logics are committed to with it. A circuit with room for a
+/// fixed number of items commits to a shorter list by absorbing the padding slots with a zero
+/// flag in `absorb_if`, which leaves the state and the length unchanged. The padding slots must
+/// come last: the flags are constrained to be a prefix, `flag_{i+1} * (1 - flag_i) = 0`, so a
+/// prover can't skip items in the middle of the slots. `HashAccumulator` is the native
+/// counterpart, the commitments of both agree for the same domain and items.
+use crate::{
+    circuit::gadgets::{
+        add::{AddChip, AddInstructions},
+        assign_free_constant,
+        boolean::AssignedBool,
+        conditional_select::ConditionalSelectConfig,
+        mul::MulChip,
+        poseidon_hash::poseidon_hash_gadget,
+    },
+    utils::poseidon_hash,
+};
+use halo2_gadgets::poseidon::Pow5Config as PoseidonConfig;
+use halo2_proofs::{
+    arithmetic::Field,
+    circuit::{AssignedCell, Layouter},
+    plonk::{Advice, Column, Error},
+};
+use pasta_curves::pallas;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HashAccumulator {
+    state: pallas::Base,
+    len: u64,
+    // Cleared by the first padding slot
+    open: bool,
+}
+
+impl HashAccumulator {
+    pub fn start(domain: pallas::Base) -> Self {
+        Self {
+            state: domain,
+            len: 0,
+            open: true,
+        }
+    }
+
+    /// Panics after a padding slot, the circuit is not satisfiable either.
+    pub fn absorb(&mut self, item: pallas::Base) {
+        assert!(self.open, "an item is absorbed after a padding slot");
+        self.state = poseidon_hash(self.state, item);
+        self.len += 1;
+    }
+
+    /// Absorbs the item only if `flag` is set, as the padding slots of the circuit.
+    pub fn absorb_if(&mut self, flag: bool, item: pallas::Base) {
+        if flag {
+            self.absorb(item);
+        } else {
+            self.open = false;
+        }
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn finalize(&self) -> pallas::Base {
+        poseidon_hash(self.state, pallas::Base::from(self.len))
+    }
+}
+
+/// The commitment to `items` in the domain.
+pub fn accumulate(domain: pallas::Base, items: &[pallas::Base]) -> pallas::Base {
+    let mut accumulator = HashAccumulator::start(domain);
+    items.iter().for_each(|item| accumulator.absorb(*item));
+    accumulator.finalize()
+}
+
+#[derive(Clone, Debug)]
+pub struct AssignedHashAccumulator {
+    pub state: AssignedCell<pallas::Base, pallas::Base>,
+    pub len: AssignedCell<pallas::Base, pallas::Base>,
+    // The flag of the last slot, 1 at the start
+    pub open: AssignedBool,
+}
+
+#[derive(Clone, Debug)]
+pub struct HashAccumulatorChip {
+    poseidon_config: PoseidonConfig<pallas::Base, 3, 2>,
+    conditional_select_config: ConditionalSelectConfig,
+    add_chip: AddChip<pallas::Base>,
+    mul_chip: MulChip<pallas::Base>,
+    advice: Column<Advice>,
+}
+
+impl HashAccumulatorChip {
+    pub fn construct(
+        poseidon_config: PoseidonConfig<pallas::Base, 3, 2>,
+        conditional_select_config: ConditionalSelectConfig,
+        add_chip: AddChip<pallas::Base>,
+        mul_chip: MulChip<pallas::Base>,
+        advice: Column<Advice>,
+    ) -> Self {
+        Self {
+            poseidon_config,
+            conditional_select_config,
+            add_chip,
+            mul_chip,
+            advice,
+        }
+    }
+
+    // Constrains flag * (1 - open) = 0, i.e. flag * open = flag: no item after a padding slot
+    fn check_prefix(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        open: &AssignedBool,
+        flag: &AssignedBool,
+    ) -> Result<(), Error> {
+        let product = flag.and(&self.mul_chip, layouter.namespace(|| "flag * open"), open)?;
+        layouter.assign_region(
+            || "flag * open == flag",
+            |mut region| region.constrain_equal(product.cell(), flag.cell()),
+        )
+    }
+
+    /// Starts an empty accumulator. The domain is a fixed constant of the circuit.
+    pub fn start(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        domain: pallas::Base,
+    ) -> Result<AssignedHashAccumulator, Error> {
+        let state = assign_free_constant(layouter.namespace(|| "domain"), self.advice, domain)?;
+        let len = assign_free_constant(
+            layouter.namespace(|| "zero length"),
+            self.advice,
+            pallas::Base::zero(),
+        )?;
+        let open = AssignedBool::from_bool_checked(assign_free_constant(
+            layouter.namespace(|| "open"),
+            self.advice,
+            pallas::Base::one(),
+        )?);
+        Ok(AssignedHashAccumulator { state, len, open })
+    }
+
+    pub fn absorb(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        accumulator: &AssignedHashAccumulator,
+        item: &AssignedCell<pallas::Base, pallas::Base>,
+    ) -> Result<AssignedHashAccumulator, Error> {
+        let state = poseidon_hash_gadget(
+            self.poseidon_config.clone(),
+            layouter.namespace(|| "state || item"),
+            [accumulator.state.clone(), item.clone()],
+        )?;
+        let one = assign_free_constant(
+            layouter.namespace(|| "one"),
+            self.advice,
+            pallas::Base::one(),
+        )?;
+        self.check_prefix(
+            layouter.namespace(|| "no item after a padding slot"),
+            &accumulator.open,
+            &AssignedBool::from_bool_checked(one.clone()),
+        )?;
+        let len = self
+            .add_chip
+            .add(layouter.namespace(|| "len + 1"), &accumulator.len, &one)?;
+        Ok(AssignedHashAccumulator {
+            state,
+            len,
+            open: accumulator.open.clone(),
+        })
+    }
+
+    /// Absorbs the item if `flag == 1`, otherwise returns the accumulator unchanged. The flag
+    /// must be 0 if a previous flag was 0.
+    pub fn absorb_if(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        accumulator: &AssignedHashAccumulator,
+        flag: &AssignedBool,
+        item: &AssignedCell<pallas::Base, pallas::Base>,
+    ) -> Result<AssignedHashAccumulator, Error> {
+        let absorbed = poseidon_hash_gadget(
+            self.poseidon_config.clone(),
+            layouter.namespace(|| "state || item"),
+            [accumulator.state.clone(), item.clone()],
+        )?;
+        let state = layouter.assign_region(
+            || "conditional select: accumulator state",
+            |mut region| {
+                self.conditional_select_config.assign_region(
+                    flag,
+                    &absorbed,
+                    &accumulator.state,
+                    0,
+                    &mut region,
+                )
+            },
+        )?;
+        self.check_prefix(
+            layouter.namespace(|| "no item after a padding slot"),
+            &accumulator.open,
+            flag,
+        )?;
+        let len = self
+            .add_chip
+            .add(layouter.namespace(|| "len + flag"), &accumulator.len, flag)?;
+        Ok(AssignedHashAccumulator {
+            state,
+            len,
+            open: flag.clone(),
+        })
+    }
+
+    pub fn finalize(
+        &self,
+        layouter: impl Layouter<pallas::Base>,
+        accumulator: &AssignedHashAccumulator,
+    ) -> Result<AssignedCell<pallas::Base, pallas::Base>, Error> {
+        poseidon_hash_gadget(
+            self.poseidon_config.clone(),
+            layouter,
+            [accumulator.state.clone(), accumulator.len.clone()],
+        )
+    }
+}
+
+#[test]
+fn test_halo2_hash_accumulator_gadget() {
+    use crate::circuit::{
+        gadgets::assign_free_advice, resource_logic_circuit::ResourceLogicConfig,
+    };
+    use crate::constant::RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE;
+    use halo2_proofs::{
+        circuit::{SimpleFloorPlanner, Value},
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem},
+    };
+    use rand::rngs::OsRng;
+
+    const DOMAIN: u64 = 7;
+    const CAPACITY: usize = 4;
+
+    #[derive(Default)]
+    struct MyCircuit {
+        // The list, padded with zeros up to CAPACITY
+        items: [pallas::Base; CAPACITY],
+        flags: [bool; CAPACITY],
+        expected: pallas::Base,
+    }
+
+    impl Circuit<pallas::Base> for MyCircuit {
+        type Config = ResourceLogicConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            ResourceLogicConfig::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), Error> {
+            let mul_chip = MulChip::construct(config.mul_config.clone());
+            let chip = HashAccumulatorChip::construct(
+                config.poseidon_config.clone(),
+                config.conditional_select_config,
+                AddChip::construct(config.add_config.clone(), ()),
+                mul_chip.clone(),
+                config.advices[0],
+            );
+
+            let mut accumulator =
+                chip.start(layouter.namespace(|| "start"), pallas::Base::from(DOMAIN))?;
+            for (item, flag) in self.items.iter().zip(self.flags) {
+                let item = assign_free_advice(
+                    layouter.namespace(|| "witness item"),
+                    config.advices[0],
+                    Value::known(*item),
+                )?;
+                let flag = AssignedBool::witness(
+                    &mul_chip,
+                    layouter.namespace(|| "witness flag"),
+                    config.advices[0],
+                    Value::known(flag),
+                )?;
+                accumulator =
+                    chip.absorb_if(layouter.namespace(|| "absorb"), &accumulator, &flag, &item)?;
+            }
+            let commitment = chip.finalize(layouter.namespace(|| "finalize"), &accumulator)?;
+
+            let expected = assign_free_constant(
+                layouter.namespace(|| "expected commitment"),
+                config.advices[0],
+                self.expected,
+            )?;
+            layouter.assign_region(
+                || "check commitment",
+                |mut region| region.constrain_equal(commitment.cell(), expected.cell()),
+            )
+        }
+    }
+
+    let mut rng = OsRng;
+    let list: Vec<_> = (0..3).map(|_| pallas::Base::random(&mut rng)).collect();
+    let expected = accumulate(pallas::Base::from(DOMAIN), &list);
+
+    // The padding slots don't change the commitment
+    let mut padded = HashAccumulator::start(pallas::Base::from(DOMAIN));
+    for (i, item) in list.iter().chain([pallas::Base::zero()].iter()).enumerate() {
+        padded.absorb_if(i < list.len(), *item);
+    }
+    assert_eq!(padded.len(), list.len() as u64);
+    assert_eq!(padded.finalize(), expected);
+    // The length and the domain are bound
+    assert_ne!(
+        accumulate(
+            pallas::Base::from(DOMAIN),
+            &[list.clone(), vec![pallas::Base::zero()]].concat()
+        ),
+        expected
+    );
+    assert_ne!(accumulate(pallas::Base::from(DOMAIN + 1), &list), expected);
+
+    let mut items = [pallas::Base::zero(); CAPACITY];
+    items[..list.len()].copy_from_slice(&list);
+    let circuit = MyCircuit {
+        items,
+        flags: [true, true, true, false],
+        expected,
+    };
+    let prover =
+        MockProver::<pallas::Base>::run(RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE, &circuit, vec![vec![]])
+            .unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // Absorbing a padding slot changes the commitment
+    let circuit = MyCircuit {
+        items,
+        flags: [true; CAPACITY],
+        expected,
+    };
+    let prover =
+        MockProver::<pallas::Base>::run(RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE, &circuit, vec![vec![]])
+            .unwrap();
+    assert!(prover.verify().is_err());
+
+    // Skipping an item in the middle of the list is rejected, even with the matching commitment
+    let circuit = MyCircuit {
+        items,
+        flags: [true, false, true, false],
+        expected: accumulate(pallas::Base::from(DOMAIN), &[items[0], items[2]]),
+    };
+    let prover =
+        MockProver::<pallas::Base>::run(RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE, &circuit, vec![vec![]])
+            .unwrap();
+    assert!(prover.verify().is_err());
+}
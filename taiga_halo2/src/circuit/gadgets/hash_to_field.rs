@@ -0,0 +1,159 @@
+use crate::circuit::gadgets::{
+    assign_free_advice, assign_free_constant, poseidon_hash::poseidon_hash_gadget,
+};
+use crate::utils::to_field_elements;
+use halo2_gadgets::poseidon::Pow5Config as PoseidonConfig;
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::{Advice, Column, Error},
+};
+use pasta_curves::pallas;
+
+/// Assigns a byte string as 31-byte chunks, the layout expected by `hash_to_field_gadget`.
+/// The number of chunks must be fixed by the circuit, so the message length is a circuit
+/// parameter rather than a witness.
+pub fn assign_message_chunks(
+    mut layouter: impl Layouter<pallas::Base>,
+    advice: Column<Advice>,
+    message: Value<&[u8]>,
+    num_chunks: usize,
+) -> Result<Vec<AssignedCell<pallas::Base, pallas::Base>>, Error> {
+    let chunks = message.map(|m| {
+        let chunks = to_field_elements(m);
+        assert_eq!(chunks.len(), num_chunks, "unexpected message length");
+        chunks
+    });
+    (0..num_chunks)
+        .map(|i| {
+            assign_free_advice(
+                layouter.namespace(|| "message chunk"),
+                advice,
+                chunks.as_ref().map(|c| c[i]),
+            )
+        })
+        .collect()
+}
+
+/// The in-circuit counterpart of `utils::hash_to_field` with `HashToFieldMethod::Poseidon`.
+pub fn hash_to_field_gadget(
+    poseidon_config: PoseidonConfig<pallas::Base, 3, 2>,
+    mut layouter: impl Layouter<pallas::Base>,
+    advice: Column<Advice>,
+    personalization: &[u8; 16],
+    message_len: AssignedCell<pallas::Base, pallas::Base>,
+    message_chunks: &[AssignedCell<pallas::Base, pallas::Base>],
+) -> Result<AssignedCell<pallas::Base, pallas::Base>, Error> {
+    let personalization = assign_free_constant(
+        layouter.namespace(|| "personalization"),
+        advice,
+        to_field_elements(personalization)[0],
+    )?;
+    let init = poseidon_hash_gadget(
+        poseidon_config.clone(),
+        layouter.namespace(|| "hash personalization and message length"),
+        [personalization, message_len],
+    )?;
+    message_chunks.iter().try_fold(init, |acc, chunk| {
+        poseidon_hash_gadget(
+            poseidon_config.clone(),
+            layouter.namespace(|| "hash message chunk"),
+            [acc, chunk.clone()],
+        )
+    })
+}
+
+#[test]
+fn test_halo2_hash_to_field_gadget() {
+    use crate::circuit::resource_logic_circuit::ResourceLogicConfig;
+    use crate::constant::RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE;
+    use crate::utils::{hash_to_field, HashToFieldMethod};
+    use halo2_proofs::{
+        circuit::SimpleFloorPlanner,
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem},
+    };
+
+    // Two chunks, the second one partial
+    const MESSAGE_LEN: usize = 40;
+    const NUM_CHUNKS: usize = 2;
+
+    #[derive(Default)]
+    struct MyCircuit {
+        personalization: [u8; 16],
+        message: Vec<u8>,
+        expected: pallas::Base,
+    }
+
+    impl Circuit<pallas::Base> for MyCircuit {
+        type Config = ResourceLogicConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            ResourceLogicConfig::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), Error> {
+            let message_chunks = assign_message_chunks(
+                layouter.namespace(|| "message chunks"),
+                config.advices[0],
+                Value::known(self.message.as_slice()),
+                NUM_CHUNKS,
+            )?;
+            let message_len = assign_free_advice(
+                layouter.namespace(|| "message length"),
+                config.advices[0],
+                Value::known(pallas::Base::from(self.message.len() as u64)),
+            )?;
+            let hash = hash_to_field_gadget(
+                config.poseidon_config,
+                layouter.namespace(|| "hash to field"),
+                config.advices[0],
+                &self.personalization,
+                message_len,
+                &message_chunks,
+            )?;
+
+            let expected = assign_free_constant(
+                layouter.namespace(|| "expected hash"),
+                config.advices[0],
+                self.expected,
+            )?;
+            layouter.assign_region(
+                || "check hash",
+                |mut region| region.constrain_equal(hash.cell(), expected.cell()),
+            )
+        }
+    }
+
+    let personalization = *b"MyApp_TokenName_";
+    let message: Vec<u8> = (0..MESSAGE_LEN as u8).collect();
+    let expected = hash_to_field(HashToFieldMethod::Poseidon, &personalization, &message);
+    let circuit = MyCircuit {
+        personalization,
+        message: message.clone(),
+        expected,
+    };
+    let prover =
+        MockProver::<pallas::Base>::run(RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE, &circuit, vec![vec![]])
+            .unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // The personalization is a constant of the circuit, another one gives another hash
+    let circuit = MyCircuit {
+        personalization: *b"MyApp_TokenName2",
+        message,
+        expected,
+    };
+    let prover =
+        MockProver::<pallas::Base>::run(RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE, &circuit, vec![vec![]])
+            .unwrap();
+    assert!(prover.verify().is_err());
+}
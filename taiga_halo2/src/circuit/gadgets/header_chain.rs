@@ -0,0 +1,368 @@
+/// Light-client gadgets over the block headers of an external chain.
+///
+/// A header chain is verified from a trusted header hash: every header links to the hash of its
+/// parent and increments the height. The header hash is pluggable through `HeaderHashChip`, a
+/// Poseidon implementation is provided. The tip of the chain can additionally be authenticated
+/// with a Schnorr signature of the authority of the external chain, see `gadgets::schnorr`. The
+/// state roots of the verified headers can then be used in the circuit, e.g. as the external
+/// root of the bridge example.
+use crate::{
+    circuit::gadgets::{
+        add::{AddChip, AddInstructions},
+        assign_free_advice, assign_free_constant,
+        poseidon_hash::poseidon_hash_gadget,
+        schnorr::Signature,
+    },
+    utils::poseidon_hash_n,
+};
+use halo2_gadgets::poseidon::Pow5Config as PoseidonConfig;
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::{Advice, Column, Error},
+};
+use pasta_curves::pallas;
+
+/// The header fields a light client needs, encoded as field elements.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExternalHeader {
+    pub parent_hash: pallas::Base,
+    pub height: u64,
+    pub state_root: pallas::Base,
+    // The commitment to the other fields of the header
+    pub extra: pallas::Base,
+}
+
+#[derive(Clone, Debug)]
+pub struct AssignedHeader {
+    pub parent_hash: AssignedCell<pallas::Base, pallas::Base>,
+    pub height: AssignedCell<pallas::Base, pallas::Base>,
+    pub state_root: AssignedCell<pallas::Base, pallas::Base>,
+    pub extra: AssignedCell<pallas::Base, pallas::Base>,
+}
+
+impl ExternalHeader {
+    pub fn assign(
+        &self,
+        column: Column<Advice>,
+        mut layouter: impl Layouter<pallas::Base>,
+    ) -> Result<AssignedHeader, Error> {
+        let parent_hash = assign_free_advice(
+            layouter.namespace(|| "witness parent_hash"),
+            column,
+            Value::known(self.parent_hash),
+        )?;
+        let height = assign_free_advice(
+            layouter.namespace(|| "witness height"),
+            column,
+            Value::known(pallas::Base::from(self.height)),
+        )?;
+        let state_root = assign_free_advice(
+            layouter.namespace(|| "witness state_root"),
+            column,
+            Value::known(self.state_root),
+        )?;
+        let extra = assign_free_advice(
+            layouter.namespace(|| "witness extra"),
+            column,
+            Value::known(self.extra),
+        )?;
+        Ok(AssignedHeader {
+            parent_hash,
+            height,
+            state_root,
+            extra,
+        })
+    }
+}
+
+/// The header hash of the external chain, natively and in circuit.
+pub trait HeaderHashChip {
+    fn hash_native(header: &ExternalHeader) -> pallas::Base;
+
+    fn hash(
+        &self,
+        layouter: impl Layouter<pallas::Base>,
+        header: &AssignedHeader,
+    ) -> Result<AssignedCell<pallas::Base, pallas::Base>, Error>;
+}
+
+/// header_hash = poseidon_hash(parent_hash || height || state_root || extra)
+#[derive(Clone, Debug)]
+pub struct PoseidonHeaderHashChip {
+    config: PoseidonConfig<pallas::Base, 3, 2>,
+}
+
+impl PoseidonHeaderHashChip {
+    pub fn construct(config: PoseidonConfig<pallas::Base, 3, 2>) -> Self {
+        Self { config }
+    }
+}
+
+impl HeaderHashChip for PoseidonHeaderHashChip {
+    fn hash_native(header: &ExternalHeader) -> pallas::Base {
+        poseidon_hash_n([
+            header.parent_hash,
+            pallas::Base::from(header.height),
+            header.state_root,
+            header.extra,
+        ])
+    }
+
+    fn hash(
+        &self,
+        layouter: impl Layouter<pallas::Base>,
+        header: &AssignedHeader,
+    ) -> Result<AssignedCell<pallas::Base, pallas::Base>, Error> {
+        poseidon_hash_gadget(
+            self.config.clone(),
+            layouter,
+            [
+                header.parent_hash.clone(),
+                header.height.clone(),
+                header.state_root.clone(),
+                header.extra.clone(),
+            ],
+        )
+    }
+}
+
+/// Returns the hash of the tip if the headers form a chain from the trusted hash.
+pub fn verify_header_chain_native<H: HeaderHashChip>(
+    trusted_hash: pallas::Base,
+    headers: &[ExternalHeader],
+) -> Option<pallas::Base> {
+    let mut hash = trusted_hash;
+    let mut height = None;
+    for header in headers {
+        if header.parent_hash != hash || height.is_some_and(|h: u64| header.height != h + 1) {
+            return None;
+        }
+        hash = H::hash_native(header);
+        height = Some(header.height);
+    }
+    Some(hash)
+}
+
+/// Constrains the headers to form a chain from the trusted hash, and returns the assigned
+/// headers and the hash of the tip.
+#[allow(clippy::type_complexity)]
+pub fn verify_header_chain<H: HeaderHashChip>(
+    hash_chip: &H,
+    add_chip: &AddChip<pallas::Base>,
+    mut layouter: impl Layouter<pallas::Base>,
+    column: Column<Advice>,
+    trusted_hash: &AssignedCell<pallas::Base, pallas::Base>,
+    headers: &[ExternalHeader],
+) -> Result<
+    (
+        Vec<AssignedHeader>,
+        AssignedCell<pallas::Base, pallas::Base>,
+    ),
+    Error,
+> {
+    let one = assign_free_constant(layouter.namespace(|| "one"), column, pallas::Base::one())?;
+    let mut hash = trusted_hash.clone();
+    let mut assigned_headers: Vec<AssignedHeader> = Vec::with_capacity(headers.len());
+    for header in headers {
+        let assigned = header.assign(column, layouter.namespace(|| "assign header"))?;
+
+        // parent_hash = hash(parent)
+        layouter.assign_region(
+            || "check parent hash",
+            |mut region| region.constrain_equal(assigned.parent_hash.cell(), hash.cell()),
+        )?;
+
+        // height = parent height + 1
+        if let Some(parent) = assigned_headers.last() {
+            let height = add_chip.add(
+                layouter.namespace(|| "parent height + 1"),
+                &parent.height,
+                &one,
+            )?;
+            layouter.assign_region(
+                || "check height",
+                |mut region| region.constrain_equal(assigned.height.cell(), height.cell()),
+            )?;
+        }
+
+        hash = hash_chip.hash(layouter.namespace(|| "header hash"), &assigned)?;
+        assigned_headers.push(assigned);
+    }
+    Ok((assigned_headers, hash))
+}
+
+/// A Schnorr signature of a header hash by the authority of the external chain, it's verified
+/// with `SchnorrChip`.
+pub type HeaderSignature = Signature;
+
+#[test]
+fn test_halo2_header_chain_gadget() {
+    use crate::circuit::{
+        gadgets::schnorr::SchnorrChip, resource_logic_circuit::ResourceLogicConfig,
+    };
+    use crate::constant::RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE;
+    use halo2_gadgets::ecc::{chip::EccChip, NonIdentityPoint};
+    use halo2_proofs::{
+        arithmetic::Field,
+        circuit::SimpleFloorPlanner,
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem},
+    };
+    use pasta_curves::group::{Curve, Group};
+    use rand::rngs::OsRng;
+
+    struct MyCircuit {
+        trusted_hash: pallas::Base,
+        headers: Vec<ExternalHeader>,
+        pk: pallas::Point,
+        signature: HeaderSignature,
+    }
+
+    impl Default for MyCircuit {
+        fn default() -> Self {
+            Self {
+                trusted_hash: pallas::Base::zero(),
+                headers: vec![],
+                pk: pallas::Point::generator(),
+                signature: HeaderSignature::default(),
+            }
+        }
+    }
+
+    impl Circuit<pallas::Base> for MyCircuit {
+        type Config = ResourceLogicConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            ResourceLogicConfig::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), Error> {
+            layouter.assign_table(
+                || "table_idx",
+                |mut table| {
+                    for index in 0..(1 << 10) {
+                        table.assign_cell(
+                            || "table_idx",
+                            config.table_idx,
+                            index,
+                            || Value::known(pallas::Base::from(index as u64)),
+                        )?;
+                    }
+                    Ok(())
+                },
+            )?;
+
+            let hash_chip = PoseidonHeaderHashChip::construct(config.poseidon_config.clone());
+            let add_chip = AddChip::construct(config.add_config.clone(), ());
+            let trusted_hash = assign_free_advice(
+                layouter.namespace(|| "witness trusted hash"),
+                config.advices[0],
+                Value::known(self.trusted_hash),
+            )?;
+            let (_, tip_hash) = verify_header_chain(
+                &hash_chip,
+                &add_chip,
+                layouter.namespace(|| "verify header chain"),
+                config.advices[0],
+                &trusted_hash,
+                &self.headers,
+            )?;
+
+            let pk = NonIdentityPoint::new(
+                EccChip::construct(config.ecc_config.clone()),
+                layouter.namespace(|| "witness pk"),
+                Value::known(self.pk.to_affine()),
+            )?;
+            SchnorrChip::from_resource_logic_config(&config).verify(
+                layouter.namespace(|| "verify tip signature"),
+                &pk,
+                &tip_hash,
+                &self.signature,
+            )
+        }
+    }
+
+    let mut rng = OsRng;
+    let trusted_hash = pallas::Base::random(&mut rng);
+    let mut headers = vec![];
+    let mut parent_hash = trusted_hash;
+    for height in 100..104 {
+        let header = ExternalHeader {
+            parent_hash,
+            height,
+            state_root: pallas::Base::random(&mut rng),
+            extra: pallas::Base::random(&mut rng),
+        };
+        parent_hash = PoseidonHeaderHashChip::hash_native(&header);
+        headers.push(header);
+    }
+    let tip_hash =
+        verify_header_chain_native::<PoseidonHeaderHashChip>(trusted_hash, &headers).unwrap();
+    assert_eq!(tip_hash, parent_hash);
+
+    let sk = pallas::Scalar::random(&mut rng);
+    let pk = pallas::Point::generator() * sk;
+    let signature = HeaderSignature::sign(&mut rng, sk, tip_hash);
+    assert!(signature.verify(&pk, tip_hash));
+
+    let circuit = MyCircuit {
+        trusted_hash,
+        headers: headers.clone(),
+        pk,
+        signature,
+    };
+    let prover =
+        MockProver::<pallas::Base>::run(RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE, &circuit, vec![vec![]])
+            .unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // A broken link is rejected
+    {
+        let mut headers = headers.clone();
+        headers[2].state_root = pallas::Base::random(&mut rng);
+        assert!(
+            verify_header_chain_native::<PoseidonHeaderHashChip>(trusted_hash, &headers).is_none()
+        );
+        let circuit = MyCircuit {
+            trusted_hash,
+            headers,
+            pk,
+            signature,
+        };
+        let prover = MockProver::<pallas::Base>::run(
+            RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+            &circuit,
+            vec![vec![]],
+        )
+        .unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    // A signature of another authority is rejected
+    {
+        let other_pk = pallas::Point::random(&mut rng);
+        assert!(!signature.verify(&other_pk, tip_hash));
+        let circuit = MyCircuit {
+            trusted_hash,
+            headers,
+            pk: other_pk,
+            signature,
+        };
+        let prover = MockProver::<pallas::Base>::run(
+            RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+            &circuit,
+            vec![vec![]],
+        )
+        .unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -0,0 +1,91 @@
+/// The total quantity of a resource kind created by the output resources of a ptx. Custom logics
+/// must not sum the quantity cells naively: the quantities are only range checked to u128 one by
+/// one, and a sum of field elements can exceed the range or wrap the field. The gadget adds the
+/// quantities with the u128 arithmetic and constrains the total to u64.
+use crate::circuit::{
+    gadgets::{
+        boolean::AssignedBool,
+        mul::{MulChip, MulInstructions},
+        sub::{SubChip, SubInstructions},
+        u128::{U128Cell, U128Config},
+    },
+    resource_logic_circuit::BasicResourceLogicVariables,
+};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter},
+    plonk::{Advice, Column, Error},
+};
+use pasta_curves::pallas;
+
+/// Returns the total quantity of the output resources of the kind (logic, label), the
+/// constraints are unsatisfiable if it doesn't fit in u64.
+#[allow(clippy::too_many_arguments)]
+pub fn output_kind_quantity_gadget(
+    mut layouter: impl Layouter<pallas::Base>,
+    advice: Column<Advice>,
+    mul_chip: &MulChip<pallas::Base>,
+    sub_chip: &SubChip<pallas::Base>,
+    u128_config: &U128Config,
+    logic: &AssignedCell<pallas::Base, pallas::Base>,
+    label: &AssignedCell<pallas::Base, pallas::Base>,
+    basic_variables: &BasicResourceLogicVariables,
+) -> Result<U128Cell, Error> {
+    let mut total: Option<U128Cell> = None;
+    for output in basic_variables.output_resource_variables.iter() {
+        let variables = &output.resource_variables;
+
+        // same_kind = (output logic == logic) and (output label == label)
+        let logic_diff = sub_chip.sub(
+            layouter.namespace(|| "output logic - logic"),
+            &variables.logic,
+            logic,
+        )?;
+        let label_diff = sub_chip.sub(
+            layouter.namespace(|| "output label - label"),
+            &variables.label,
+            label,
+        )?;
+        let same_logic = AssignedBool::is_nonzero(
+            mul_chip,
+            sub_chip,
+            layouter.namespace(|| "logic differs"),
+            advice,
+            &logic_diff,
+        )?
+        .not(sub_chip, layouter.namespace(|| "same logic"), advice)?;
+        let same_label = AssignedBool::is_nonzero(
+            mul_chip,
+            sub_chip,
+            layouter.namespace(|| "label differs"),
+            advice,
+            &label_diff,
+        )?
+        .not(sub_chip, layouter.namespace(|| "same label"), advice)?;
+        let same_kind =
+            same_logic.and(mul_chip, layouter.namespace(|| "same kind"), &same_label)?;
+
+        // Only the quantities of the kind count, the others are masked to zero
+        let quantity = mul_chip.mul(
+            layouter.namespace(|| "same_kind * quantity"),
+            &same_kind,
+            &variables.quantity,
+        )?;
+        let quantity =
+            u128_config.decompose(layouter.namespace(|| "decompose quantity"), &quantity)?;
+        total = Some(match total {
+            None => quantity,
+            Some(total) => u128_config.add(
+                layouter.namespace(|| "total quantity + quantity"),
+                &total,
+                &quantity,
+            )?,
+        });
+    }
+
+    let total = total.expect("there are output resources");
+    layouter.assign_region(
+        || "check total quantity is u64",
+        |mut region| region.constrain_constant(total.hi.cell(), pallas::Base::zero()),
+    )?;
+    Ok(total)
+}
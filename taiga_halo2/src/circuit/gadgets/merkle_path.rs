@@ -0,0 +1,438 @@
+/// Poseidon merkle path gadgets, to verify the membership of a leaf in a tree such as the
+/// resource commitment tree or a whitelist maintained by an application.
+///
+/// Two chips implement `MerkleInstructions`. `MerklePoseidonChip` orders the nodes with the cond
+/// swap gate and is meant for the circuits configuring it, e.g. the compliance circuit.
+/// `MerkleSelectChip` only uses conditional selects and the mul gate, so a resource logic can
+/// build it from its `ResourceLogicConfig` without adding gates. The roots computed in-circuit
+/// match `MerklePath::root`.
+use crate::circuit::gadgets::{
+    assign_free_advice,
+    boolean::AssignedBool,
+    conditional_select::ConditionalSelectConfig,
+    mul::{MulChip, MulConfig},
+    poseidon_hash::poseidon_hash_gadget,
+};
+use crate::circuit::resource_logic_circuit::ResourceLogicConfig;
+use crate::merkle_tree::{is_left, MerklePath, LR};
+use halo2_gadgets::{
+    poseidon::Pow5Config as PoseidonConfig,
+    utilities::cond_swap::{CondSwapChip, CondSwapConfig, CondSwapInstructions},
+};
+use halo2_proofs::{
+    circuit::{AssignedCell, Chip, Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error},
+};
+use pasta_curves::pallas;
+
+/// The instructions of a level of a merkle path: the current node and its sibling are ordered as
+/// the (left, right) inputs of the parent poseidon hash.
+pub trait MerkleInstructions {
+    fn poseidon_config(&self) -> PoseidonConfig<pallas::Base, 3, 2>;
+
+    /// `lr` is the position of the sibling, as in `MerklePath`.
+    #[allow(clippy::type_complexity)]
+    fn swap(
+        &self,
+        layouter: impl Layouter<pallas::Base>,
+        node: AssignedCell<pallas::Base, pallas::Base>,
+        sibling: pallas::Base,
+        lr: LR,
+    ) -> Result<
+        (
+            AssignedCell<pallas::Base, pallas::Base>,
+            AssignedCell<pallas::Base, pallas::Base>,
+        ),
+        Error,
+    >;
+}
+
+/// MerkleTreeChip based on poseidon hash.
+#[derive(Clone, Debug)]
+pub struct MerklePoseidonConfig {
+    advices: [Column<Advice>; 5],
+    cond_swap_config: CondSwapConfig,
+    poseidon_config: PoseidonConfig<pallas::Base, 3, 2>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MerklePoseidonChip {
+    config: MerklePoseidonConfig,
+}
+
+impl Chip<pallas::Base> for MerklePoseidonChip {
+    type Config = MerklePoseidonConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl MerklePoseidonChip {
+    pub fn configure(
+        meta: &mut ConstraintSystem<pallas::Base>,
+        advices: [Column<Advice>; 5],
+        poseidon_config: PoseidonConfig<pallas::Base, 3, 2>,
+    ) -> MerklePoseidonConfig {
+        let cond_swap_config = CondSwapChip::configure(meta, advices);
+
+        MerklePoseidonConfig {
+            advices,
+            cond_swap_config,
+            poseidon_config,
+        }
+    }
+
+    pub fn construct(config: MerklePoseidonConfig) -> Self {
+        MerklePoseidonChip { config }
+    }
+}
+
+impl MerkleInstructions for MerklePoseidonChip {
+    fn poseidon_config(&self) -> PoseidonConfig<pallas::Base, 3, 2> {
+        self.config.poseidon_config.clone()
+    }
+
+    fn swap(
+        &self,
+        layouter: impl Layouter<pallas::Base>,
+        node: AssignedCell<pallas::Base, pallas::Base>,
+        sibling: pallas::Base,
+        lr: LR,
+    ) -> Result<
+        (
+            AssignedCell<pallas::Base, pallas::Base>,
+            AssignedCell<pallas::Base, pallas::Base>,
+        ),
+        Error,
+    > {
+        let chip = CondSwapChip::<pallas::Base>::construct(self.config.cond_swap_config.clone());
+        chip.swap(
+            layouter,
+            (node, Value::known(sibling)),
+            Value::known(is_left(lr)),
+        )
+    }
+}
+
+/// The merkle path instructions built from conditional selects, for the circuits without the
+/// cond swap gate such as the resource logic circuits.
+#[derive(Clone, Debug)]
+pub struct MerkleSelectChip {
+    advice: Column<Advice>,
+    conditional_select_config: ConditionalSelectConfig,
+    mul_config: MulConfig,
+    poseidon_config: PoseidonConfig<pallas::Base, 3, 2>,
+}
+
+impl MerkleSelectChip {
+    pub fn construct(
+        advice: Column<Advice>,
+        conditional_select_config: ConditionalSelectConfig,
+        mul_config: MulConfig,
+        poseidon_config: PoseidonConfig<pallas::Base, 3, 2>,
+    ) -> Self {
+        Self {
+            advice,
+            conditional_select_config,
+            mul_config,
+            poseidon_config,
+        }
+    }
+
+    /// Builds the chip from the columns and gates of a resource logic circuit.
+    pub fn from_resource_logic_config(config: &ResourceLogicConfig) -> Self {
+        Self::construct(
+            config.advices[0],
+            config.conditional_select_config,
+            config.mul_config.clone(),
+            config.poseidon_config.clone(),
+        )
+    }
+}
+
+impl MerkleInstructions for MerkleSelectChip {
+    fn poseidon_config(&self) -> PoseidonConfig<pallas::Base, 3, 2> {
+        self.poseidon_config.clone()
+    }
+
+    fn swap(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        node: AssignedCell<pallas::Base, pallas::Base>,
+        sibling: pallas::Base,
+        lr: LR,
+    ) -> Result<
+        (
+            AssignedCell<pallas::Base, pallas::Base>,
+            AssignedCell<pallas::Base, pallas::Base>,
+        ),
+        Error,
+    > {
+        let sibling = assign_free_advice(
+            layouter.namespace(|| "witness sibling"),
+            self.advice,
+            Value::known(sibling),
+        )?;
+        let is_left_sibling = assign_free_advice(
+            layouter.namespace(|| "witness is_left"),
+            self.advice,
+            Value::known(pallas::Base::from(is_left(lr))),
+        )?;
+        let is_left_sibling = AssignedBool::assert_bool(
+            &MulChip::construct(self.mul_config.clone()),
+            layouter.namespace(|| "is_left bool check"),
+            &is_left_sibling,
+        )?;
+        let left = layouter.assign_region(
+            || "conditional select: left",
+            |mut region| {
+                self.conditional_select_config.assign_region(
+                    &is_left_sibling,
+                    &sibling,
+                    &node,
+                    0,
+                    &mut region,
+                )
+            },
+        )?;
+        let right = layouter.assign_region(
+            || "conditional select: right",
+            |mut region| {
+                self.conditional_select_config.assign_region(
+                    &is_left_sibling,
+                    &node,
+                    &sibling,
+                    0,
+                    &mut region,
+                )
+            },
+        )?;
+        Ok((left, right))
+    }
+}
+
+/// Computes the root of a merkle path of `DEPTH` levels from the leaf, shared by the compliance
+/// circuit and the resource logic circuits. The root matches `MerklePath::root`.
+pub fn merkle_poseidon_gadget<const DEPTH: usize>(
+    mut layouter: impl Layouter<pallas::Base>,
+    chip: &impl MerkleInstructions,
+    leaf: AssignedCell<pallas::Base, pallas::Base>,
+    merkle_path: &[(pallas::Base, LR); DEPTH],
+) -> Result<AssignedCell<pallas::Base, pallas::Base>, Error> {
+    let mut cur = leaf;
+    for (sibling, lr) in merkle_path.iter() {
+        let (left, right) = chip.swap(layouter.namespace(|| "merkle swap"), cur, *sibling, *lr)?;
+        cur = poseidon_hash_gadget(
+            chip.poseidon_config(),
+            layouter.namespace(|| "merkle poseidon hash"),
+            [left, right],
+        )?;
+    }
+
+    Ok(cur)
+}
+
+/// Constrains the leaf to be in the tree of `root`, the merkle path has `DEPTH` levels.
+///
+/// The depth is fixed by the circuit, a path of another depth is a synthesis error rather than a
+/// circuit of another shape.
+pub fn merkle_membership_gadget<const DEPTH: usize>(
+    mut layouter: impl Layouter<pallas::Base>,
+    chip: &impl MerkleInstructions,
+    leaf: AssignedCell<pallas::Base, pallas::Base>,
+    merkle_path: &MerklePath,
+    root: &AssignedCell<pallas::Base, pallas::Base>,
+) -> Result<(), Error> {
+    let merkle_path = fixed_depth_path::<DEPTH>(merkle_path)?;
+    let computed_root = merkle_poseidon_gadget(
+        layouter.namespace(|| "merkle root"),
+        chip,
+        leaf,
+        &merkle_path,
+    )?;
+    layouter.assign_region(
+        || "merkle root check",
+        |mut region| region.constrain_equal(computed_root.cell(), root.cell()),
+    )
+}
+
+/// The siblings of a merkle path of `DEPTH` levels, the input of `merkle_poseidon_gadget`.
+pub fn fixed_depth_path<const DEPTH: usize>(
+    merkle_path: &MerklePath,
+) -> Result<[(pallas::Base, LR); DEPTH], Error> {
+    merkle_path
+        .get_path()
+        .try_into()
+        .map_err(|_| Error::Synthesis)
+}
+
+#[test]
+fn test_halo2_merkle_circuit() {
+    use crate::constant::TAIGA_COMMITMENT_TREE_DEPTH;
+    use crate::merkle_tree::Node;
+    use halo2_gadgets::poseidon::{primitives as poseidon, Pow5Chip as PoseidonChip};
+    use halo2_proofs::{
+        arithmetic::Field,
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem, Error, Instance},
+    };
+    use rand::rngs::OsRng;
+
+    // Computes the root with both merkle chips and constrains them to the native root
+    #[derive(Default)]
+    struct MyCircuit<const DEPTH: usize> {
+        leaf: pallas::Base,
+        merkle_path: MerklePath,
+    }
+
+    impl<const DEPTH: usize> Circuit<pallas::Base> for MyCircuit<DEPTH> {
+        type Config = (
+            MerklePoseidonConfig,
+            ConditionalSelectConfig,
+            MulConfig,
+            Column<Instance>,
+        );
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            let instances = meta.instance_column();
+            meta.enable_equality(instances);
+            let advices = [
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+            ];
+            for advice in advices.iter() {
+                meta.enable_equality(*advice);
+            }
+
+            let state = (0..3).map(|_| meta.advice_column()).collect::<Vec<_>>();
+            let partial_sbox = meta.advice_column();
+            let rc_a = (0..3).map(|_| meta.fixed_column()).collect::<Vec<_>>();
+            let rc_b = (0..3).map(|_| meta.fixed_column()).collect::<Vec<_>>();
+            meta.enable_constant(rc_b[0]);
+            let poseidon_config = PoseidonChip::configure::<poseidon::P128Pow5T3>(
+                meta,
+                state.try_into().unwrap(),
+                partial_sbox,
+                rc_a.try_into().unwrap(),
+                rc_b.try_into().unwrap(),
+            );
+
+            let merkle_config = MerklePoseidonChip::configure(meta, advices, poseidon_config);
+            let conditional_select_config =
+                ConditionalSelectConfig::configure(meta, [advices[0], advices[1]]);
+            let mul_config = MulChip::configure(meta, [advices[0], advices[1]]);
+            (
+                merkle_config,
+                conditional_select_config,
+                mul_config,
+                instances,
+            )
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), Error> {
+            let (merkle_config, conditional_select_config, mul_config, instances) = config;
+            let merkle_path = fixed_depth_path::<DEPTH>(&self.merkle_path)?;
+
+            // Witness leaf
+            let leaf = assign_free_advice(
+                layouter.namespace(|| "witness leaf"),
+                merkle_config.advices[0],
+                Value::known(self.leaf),
+            )?;
+
+            let select_chip = MerkleSelectChip::construct(
+                merkle_config.advices[0],
+                conditional_select_config,
+                mul_config,
+                merkle_config.poseidon_config.clone(),
+            );
+            let swap_chip = MerklePoseidonChip::construct(merkle_config);
+
+            let swap_root = merkle_poseidon_gadget(
+                layouter.namespace(|| "poseidon merkle with cond swap"),
+                &swap_chip,
+                leaf.clone(),
+                &merkle_path,
+            )?;
+            layouter.constrain_instance(swap_root.cell(), instances, 0)?;
+
+            let select_root = merkle_poseidon_gadget(
+                layouter.namespace(|| "poseidon merkle with conditional select"),
+                &select_chip,
+                leaf.clone(),
+                &merkle_path,
+            )?;
+            layouter.constrain_instance(select_root.cell(), instances, 0)?;
+
+            // The membership of the leaf in the tree of the native root
+            let root = assign_free_advice(
+                layouter.namespace(|| "witness root"),
+                select_chip.advice,
+                Value::known(self.merkle_path.root(Node::from(self.leaf)).inner()),
+            )?;
+            merkle_membership_gadget::<DEPTH>(
+                layouter.namespace(|| "merkle membership"),
+                &select_chip,
+                leaf,
+                &self.merkle_path,
+                &root,
+            )
+        }
+    }
+
+    fn check_depth<const DEPTH: usize>() {
+        let mut rng = OsRng;
+        let leaf = pallas::Base::random(rng);
+        let merkle_path = MerklePath::random(&mut rng, DEPTH);
+        let root = merkle_path.root(Node::from(leaf)).inner();
+        let circuit = MyCircuit::<DEPTH> { leaf, merkle_path };
+
+        let prover = MockProver::run(13, &circuit, vec![vec![root]]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // A wrong root is rejected
+        let prover = MockProver::run(13, &circuit, vec![vec![root + pallas::Base::one()]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    // A leaf out of the tree is rejected
+    let mut rng = OsRng;
+    let merkle_path = MerklePath::random(&mut rng, 4);
+    let root = merkle_path.root(Node::from(pallas::Base::one())).inner();
+    let circuit = MyCircuit::<4> {
+        leaf: pallas::Base::zero(),
+        merkle_path,
+    };
+    let prover = MockProver::run(13, &circuit, vec![vec![root]]).unwrap();
+    assert!(prover.verify().is_err());
+
+    // A path of another depth is a synthesis error
+    let circuit = MyCircuit::<4> {
+        leaf: pallas::Base::one(),
+        merkle_path: MerklePath::random(&mut rng, 3),
+    };
+    assert!(MockProver::run(13, &circuit, vec![vec![root]]).is_err());
+
+    check_depth::<1>();
+    check_depth::<4>();
+    check_depth::<TAIGA_COMMITMENT_TREE_DEPTH>();
+}
@@ -99,3 +99,96 @@ impl<F: Field> MulInstructions<F> for MulChip<F> {
         )
     }
 }
+
+#[test]
+fn test_halo2_mul_gadget() {
+    use crate::circuit::gadgets::assign_free_advice;
+    use halo2_proofs::{
+        circuit::{SimpleFloorPlanner, Value},
+        dev::MockProver,
+        plonk::Circuit,
+    };
+    use pasta_curves::pallas;
+
+    #[derive(Default)]
+    struct MyCircuit {
+        a: pallas::Base,
+        b: pallas::Base,
+        expected: pallas::Base,
+    }
+
+    impl Circuit<pallas::Base> for MyCircuit {
+        type Config = ([Column<Advice>; 2], MulConfig);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            let advices = [meta.advice_column(), meta.advice_column()];
+            for advice in advices.iter() {
+                meta.enable_equality(*advice);
+            }
+            let mul_config = MulChip::configure(meta, advices);
+            (advices, mul_config)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), Error> {
+            let (advices, mul_config) = config;
+            let a = assign_free_advice(
+                layouter.namespace(|| "witness a"),
+                advices[0],
+                Value::known(self.a),
+            )?;
+            let b = assign_free_advice(
+                layouter.namespace(|| "witness b"),
+                advices[1],
+                Value::known(self.b),
+            )?;
+            let expected = assign_free_advice(
+                layouter.namespace(|| "witness expected"),
+                advices[0],
+                Value::known(self.expected),
+            )?;
+
+            let mul_chip = MulChip::<pallas::Base>::construct(mul_config);
+            let c = mul_chip.mul(layouter.namespace(|| "a * b"), &a, &b)?;
+
+            layouter.assign_region(
+                || "constrain result",
+                |mut region| region.constrain_equal(c.cell(), expected.cell()),
+            )
+        }
+    }
+
+    let max = -pallas::Base::one();
+    let cases = [
+        (pallas::Base::zero(), pallas::Base::zero()),
+        (pallas::Base::zero(), max),
+        (pallas::Base::one(), max),
+        (pallas::Base::from(6u64), pallas::Base::from(7u64)),
+        (max, max),
+    ];
+    for (a, b) in cases {
+        let circuit = MyCircuit {
+            a,
+            b,
+            expected: a * b,
+        };
+        let prover = MockProver::<pallas::Base>::run(6, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    let circuit = MyCircuit {
+        a: pallas::Base::from(6u64),
+        b: pallas::Base::from(7u64),
+        expected: pallas::Base::from(13u64),
+    };
+    let prover = MockProver::<pallas::Base>::run(6, &circuit, vec![]).unwrap();
+    assert!(prover.verify().is_err());
+}
@@ -0,0 +1,153 @@
+/// Range checks with the 10-bit lookup table: the cell is decomposed into 10-bit limbs and the
+/// top limb is short-checked, the constraints are unsatisfiable if the value doesn't fit. Custom
+/// logics must range check the quantities before doing arithmetic on them, or the arithmetic can
+/// silently wrap in the field.
+use halo2_gadgets::utilities::lookup_range_check::LookupRangeCheckConfig;
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::Error,
+};
+use pasta_curves::pallas;
+
+#[derive(Clone, Debug)]
+pub struct RangeCheckChip {
+    config: LookupRangeCheckConfig<pallas::Base, 10>,
+}
+
+impl RangeCheckChip {
+    pub fn construct(config: LookupRangeCheckConfig<pallas::Base, 10>) -> Self {
+        Self { config }
+    }
+
+    /// Witness a value and constrain it below 2^64.
+    pub fn witness_u64(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        value: Value<pallas::Base>,
+    ) -> Result<AssignedCell<pallas::Base, pallas::Base>, Error> {
+        let zs = self.config.witness_check(
+            layouter.namespace(|| "6 * K(10) bits range check"),
+            value,
+            6,
+            false,
+        )?;
+        self.config.copy_short_check(
+            layouter.namespace(|| "4 bits range check"),
+            zs[6].clone(),
+            4,
+        )?;
+        Ok(zs[0].clone())
+    }
+
+    /// Constrain an assigned cell below 2^64.
+    pub fn range_check_u64(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        value: &AssignedCell<pallas::Base, pallas::Base>,
+    ) -> Result<(), Error> {
+        let zs = self.config.copy_check(
+            layouter.namespace(|| "6 * K(10) bits range check"),
+            value.clone(),
+            6,
+            false,
+        )?;
+        self.config.copy_short_check(
+            layouter.namespace(|| "4 bits range check"),
+            zs[6].clone(),
+            4,
+        )
+    }
+
+    /// Constrain an assigned cell below 2^128.
+    pub fn range_check_u128(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        value: &AssignedCell<pallas::Base, pallas::Base>,
+    ) -> Result<(), Error> {
+        let zs = self.config.copy_check(
+            layouter.namespace(|| "12 * K(10) bits range check"),
+            value.clone(),
+            12,
+            false,
+        )?;
+        self.config.copy_short_check(
+            layouter.namespace(|| "8 bits range check"),
+            zs[12].clone(),
+            8,
+        )
+    }
+}
+
+#[test]
+fn test_halo2_range_check_u64() {
+    use crate::circuit::gadgets::assign_free_advice;
+    use halo2_proofs::{
+        circuit::SimpleFloorPlanner,
+        dev::MockProver,
+        plonk::{Advice, Circuit, Column, ConstraintSystem},
+    };
+
+    #[derive(Default)]
+    struct MyCircuit {
+        value: pallas::Base,
+    }
+
+    impl Circuit<pallas::Base> for MyCircuit {
+        type Config = (Column<Advice>, LookupRangeCheckConfig<pallas::Base, 10>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            let advice = meta.advice_column();
+            meta.enable_equality(advice);
+            let table_idx = meta.lookup_table_column();
+            let constants = meta.fixed_column();
+            meta.enable_constant(constants);
+            let range_check = LookupRangeCheckConfig::configure(meta, advice, table_idx);
+            (advice, range_check)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), Error> {
+            let (advice, range_check) = config;
+            range_check.load(&mut layouter)?;
+            let value = assign_free_advice(
+                layouter.namespace(|| "witness value"),
+                advice,
+                Value::known(self.value),
+            )?;
+            let chip = RangeCheckChip::construct(range_check);
+            chip.range_check_u64(layouter.namespace(|| "range check"), &value)?;
+            chip.witness_u64(
+                layouter.namespace(|| "witness u64"),
+                Value::known(self.value),
+            )?;
+            Ok(())
+        }
+    }
+
+    for value in [0, 1, u64::MAX as u128] {
+        let circuit = MyCircuit {
+            value: pallas::Base::from_u128(value),
+        };
+        let prover = MockProver::run(11, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    // The values wrapping from a subtraction are rejected too
+    for value in [
+        pallas::Base::from_u128(1 << 64),
+        -pallas::Base::one(),
+        pallas::Base::from(3) - pallas::Base::from(5),
+    ] {
+        let circuit = MyCircuit { value };
+        let prover = MockProver::run(11, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -0,0 +1,425 @@
+/// The convention for passing structured data from one ptx to the next via an intent resource.
+/// The label of the intent resource commits to a resource template:
+///   label = poseidon(logic, label, quantity, value, npk, is_ephemeral)
+/// When the intent resource is consumed, its resource logic checks that the ptx creates an
+/// output resource with exactly these fields. The cascade intent is the special case where the
+/// label carries the commitment of a resource instead of its fields.
+use crate::{
+    circuit::{
+        gadgets::{
+            boolean::AssignedBool, conditional_equal::ConditionalEqualConfig,
+            poseidon_hash::poseidon_hash_gadget,
+        },
+        resource_logic_circuit::ResourceVariables,
+    },
+    nullifier::Nullifier,
+    resource::Resource,
+    utils::poseidon_hash_n,
+};
+use group::ff::PrimeField;
+use halo2_gadgets::poseidon::Pow5Config as PoseidonConfig;
+use halo2_proofs::{
+    arithmetic::Field,
+    circuit::{AssignedCell, Layouter},
+    plonk::Error,
+};
+use pasta_curves::pallas;
+use rand::RngCore;
+
+/// The fields of the resource the next ptx must create. The nonce and rseed are left to the
+/// creator of the resource.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ResourceTemplate {
+    pub logic: pallas::Base,
+    pub label: pallas::Base,
+    pub quantity: u128,
+    pub value: pallas::Base,
+    pub npk: pallas::Base,
+    pub is_ephemeral: bool,
+}
+
+impl ResourceTemplate {
+    pub fn from_resource(resource: &Resource) -> Self {
+        Self {
+            logic: resource.get_logic(),
+            label: resource.get_label(),
+            quantity: resource.quantity,
+            value: resource.value,
+            npk: resource.get_npk(),
+            is_ephemeral: resource.is_ephemeral,
+        }
+    }
+
+    /// The native counterpart of `check_resource_template`, used as the label of the intent resource.
+    pub fn encode(&self) -> pallas::Base {
+        poseidon_hash_n([
+            self.logic,
+            self.label,
+            pallas::Base::from_u128(self.quantity),
+            self.value,
+            self.npk,
+            pallas::Base::from(self.is_ephemeral),
+        ])
+    }
+
+    pub fn is_satisfied_by(&self, resource: &Resource) -> bool {
+        *self == Self::from_resource(resource)
+    }
+
+    /// Creates an ephemeral intent resource carrying the template in its label.
+    pub fn create_intent_resource<R: RngCore>(
+        &self,
+        mut rng: R,
+        intent_logic: pallas::Base,
+        nk: pallas::Base,
+    ) -> Resource {
+        let rseed = pallas::Base::random(&mut rng);
+        let nonce = Nullifier::random(&mut rng);
+        Resource::new_input_resource(
+            intent_logic,
+            self.encode(),
+            pallas::Base::zero(),
+            1u128,
+            nk,
+            nonce,
+            true,
+            rseed,
+        )
+    }
+}
+
+/// Constrains `flag * (encoded_template - encode(resource)) = 0`, i.e. the resource matches the
+/// template carried by the intent label when the flag is set. The flag is typically the
+/// is_input_resource flag of the intent resource.
+pub fn check_resource_template(
+    poseidon_config: PoseidonConfig<pallas::Base, 3, 2>,
+    conditional_equal_config: &ConditionalEqualConfig,
+    mut layouter: impl Layouter<pallas::Base>,
+    flag: &AssignedBool,
+    encoded_template: &AssignedCell<pallas::Base, pallas::Base>,
+    resource: &ResourceVariables,
+) -> Result<(), Error> {
+    let encoded_resource = poseidon_hash_gadget(
+        poseidon_config,
+        layouter.namespace(|| "resource template encoding"),
+        [
+            resource.logic.clone(),
+            resource.label.clone(),
+            resource.quantity.clone(),
+            resource.value.clone(),
+            resource.npk.clone(),
+            resource.is_ephemeral.clone(),
+        ],
+    )?;
+
+    layouter.assign_region(
+        || "conditional equal: check resource template",
+        |mut region| {
+            conditional_equal_config.assign_region(
+                flag,
+                encoded_template,
+                &encoded_resource,
+                0,
+                &mut region,
+            )
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_resource_template, ResourceTemplate};
+    use crate::{
+        circuit::{
+            blake2s::publicize_default_dynamic_resource_logic_commitments,
+            gadgets::target_resource_variable::{
+                get_is_input_resource_flag, get_owned_resource_variable,
+            },
+            resource_logic_circuit::{
+                BasicResourceLogicVariables, ResourceLogicCircuit, ResourceLogicConfig,
+                ResourceLogicPublicInputs, ResourceLogicVerifyingInfoTrait,
+            },
+            resource_logic_examples::{
+                TrivialResourceLogicCircuit, COMPRESSED_TRIVIAL_RESOURCE_LOGIC_VK,
+            },
+        },
+        compliance::ComplianceInfo,
+        constant::{DEFAULT_PARAMS_STORE, NUM_RESOURCE, TAIGA_COMMITMENT_TREE_DEPTH},
+        error::TransactionError,
+        merkle_tree::{Anchor, MerklePath},
+        resource::{tests::random_resource, OwnedResourceId, RandomSeed, Resource, ResourceLogics},
+        resource_logic_commitment::ResourceLogicCommitment,
+        resource_logic_vk::ResourceLogicVerifyingKey,
+        shielded_ptx::ShieldedPartialTransaction,
+        transaction::{ShieldedPartialTxBundle, Transaction, TransparentPartialTxBundle},
+    };
+    use halo2_proofs::{
+        arithmetic::Field,
+        circuit::{floor_planner, Layouter},
+        plonk::{Circuit, ConstraintSystem, Error},
+    };
+    use pasta_curves::pallas;
+    use rand::{rngs::OsRng, RngCore};
+
+    // An intent whose label is a resource template: the ptx consuming the intent must create
+    // the templated resource as its first output
+    #[derive(Clone, Debug, Default)]
+    struct TemplateIntentResourceLogicCircuit {
+        owned_resource_id: OwnedResourceId,
+        input_resources: [Resource; NUM_RESOURCE],
+        output_resources: [Resource; NUM_RESOURCE],
+    }
+
+    impl ResourceLogicCircuit for TemplateIntentResourceLogicCircuit {
+        fn custom_constraints(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+            basic_variables: BasicResourceLogicVariables,
+        ) -> Result<(), Error> {
+            let owned_resource_id = basic_variables.get_owned_resource_id();
+            let is_input_resource = get_is_input_resource_flag(
+                config.get_is_input_resource_flag_config,
+                layouter.namespace(|| "get is_input_resource_flag"),
+                &owned_resource_id,
+                &basic_variables.get_input_resource_nfs(),
+                &basic_variables.get_output_resource_cms(),
+            )?;
+            let encoded_template = get_owned_resource_variable(
+                config.get_owned_resource_variable_config,
+                layouter.namespace(|| "get owned resource label"),
+                &owned_resource_id,
+                &basic_variables.get_label_searchable_pairs(),
+            )?;
+            check_resource_template(
+                config.poseidon_config.clone(),
+                &config.conditional_equal_config,
+                layouter.namespace(|| "check resource template"),
+                &is_input_resource,
+                &encoded_template,
+                &basic_variables.output_resource_variables[0].resource_variables,
+            )?;
+            publicize_default_dynamic_resource_logic_commitments(
+                &mut layouter,
+                config.advices[0],
+                config.instances,
+            )
+        }
+
+        fn get_input_resources(&self) -> &[Resource; NUM_RESOURCE] {
+            &self.input_resources
+        }
+
+        fn get_output_resources(&self) -> &[Resource; NUM_RESOURCE] {
+            &self.output_resources
+        }
+
+        fn get_public_inputs(&self, mut rng: impl RngCore) -> ResourceLogicPublicInputs {
+            let mut public_inputs = self.get_mandatory_public_inputs();
+            let default_resource_logic_cm: [pallas::Base; 2] =
+                ResourceLogicCommitment::default().to_public_inputs();
+            public_inputs.extend(default_resource_logic_cm);
+            public_inputs.extend(default_resource_logic_cm);
+            let padding = ResourceLogicPublicInputs::get_public_input_padding(
+                public_inputs.len(),
+                &RandomSeed::random(&mut rng),
+            );
+            public_inputs.extend(padding);
+            public_inputs.into()
+        }
+
+        fn get_owned_resource_id(&self) -> OwnedResourceId {
+            self.owned_resource_id
+        }
+    }
+
+    crate::resource_logic_circuit_impl!(TemplateIntentResourceLogicCircuit);
+    crate::resource_logic_verifying_info_impl!(TemplateIntentResourceLogicCircuit);
+
+    fn random_trivial_resource<R: RngCore>(rng: R) -> Resource {
+        let mut resource = random_resource(rng);
+        resource.kind.logic = *COMPRESSED_TRIVIAL_RESOURCE_LOGIC_VK;
+        resource
+    }
+
+    fn trivial_resource_logics(
+        owned_resource_id: OwnedResourceId,
+        input_resources: [Resource; NUM_RESOURCE],
+        output_resources: [Resource; NUM_RESOURCE],
+    ) -> ResourceLogics {
+        ResourceLogics::from_application(Box::new(TrivialResourceLogicCircuit::new(
+            owned_resource_id,
+            input_resources,
+            output_resources,
+        )))
+    }
+
+    fn intent_resource_logics(
+        owned_resource_id: OwnedResourceId,
+        input_resources: [Resource; NUM_RESOURCE],
+        output_resources: [Resource; NUM_RESOURCE],
+    ) -> ResourceLogics {
+        ResourceLogics::from_application(Box::new(TemplateIntentResourceLogicCircuit {
+            owned_resource_id,
+            input_resources,
+            output_resources,
+        }))
+    }
+
+    #[test]
+    fn test_resource_template_across_ptxs() {
+        let mut rng = OsRng;
+        let merkle_path = MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH);
+        let intent_logic = TemplateIntentResourceLogicCircuit::default()
+            .get_resource_logic_vk()
+            .unwrap()
+            .get_compressed();
+
+        // The first ptx consumes the resource to pass on, the intent carries its template to
+        // the second ptx, which recreates it for the receiver
+        let passed_resource = random_trivial_resource(&mut rng);
+        let template = ResourceTemplate {
+            value: pallas::Base::random(&mut rng),
+            npk: pallas::Base::random(&mut rng),
+            ..ResourceTemplate::from_resource(&passed_resource)
+        };
+        let mut intent_resource =
+            template.create_intent_resource(&mut rng, intent_logic, pallas::Base::random(&mut rng));
+
+        let ptx_1 = {
+            let other_input_resource = random_trivial_resource(&mut rng);
+            let mut other_output_resource = random_trivial_resource(&mut rng);
+            other_output_resource.kind = other_input_resource.kind;
+            other_output_resource.quantity = other_input_resource.quantity;
+            let compliances = vec![
+                ComplianceInfo::new(
+                    passed_resource,
+                    merkle_path.clone(),
+                    None,
+                    &mut intent_resource,
+                    &mut rng,
+                ),
+                ComplianceInfo::new(
+                    other_input_resource,
+                    merkle_path.clone(),
+                    None,
+                    &mut other_output_resource,
+                    &mut rng,
+                ),
+            ];
+            let input_resources = [passed_resource, other_input_resource];
+            let output_resources = [intent_resource, other_output_resource];
+            let input_resource_logics = input_resources
+                .iter()
+                .map(|resource| {
+                    trivial_resource_logics(
+                        resource.get_nf().unwrap().into(),
+                        input_resources,
+                        output_resources,
+                    )
+                })
+                .collect();
+            let output_resource_logics = vec![
+                intent_resource_logics(
+                    intent_resource.commitment().into(),
+                    input_resources,
+                    output_resources,
+                ),
+                trivial_resource_logics(
+                    other_output_resource.commitment().into(),
+                    input_resources,
+                    output_resources,
+                ),
+            ];
+            ShieldedPartialTransaction::build(
+                compliances,
+                input_resource_logics,
+                output_resource_logics,
+                vec![],
+                &mut rng,
+            )
+            .unwrap()
+        };
+
+        let mut templated_resource = Resource::new_output_resource(
+            template.logic,
+            template.label,
+            template.value,
+            template.quantity,
+            template.npk,
+            template.is_ephemeral,
+            pallas::Base::random(&mut rng),
+        );
+        let (ptx_2, intent_circuit) = {
+            let other_input_resource = random_trivial_resource(&mut rng);
+            let mut other_output_resource = random_trivial_resource(&mut rng);
+            other_output_resource.kind = other_input_resource.kind;
+            other_output_resource.quantity = other_input_resource.quantity;
+            // The intent is ephemeral, its anchor isn't checked
+            let compliances = vec![
+                ComplianceInfo::new(
+                    intent_resource,
+                    merkle_path.clone(),
+                    Some(Anchor::from(pallas::Base::random(&mut rng))),
+                    &mut templated_resource,
+                    &mut rng,
+                ),
+                ComplianceInfo::new(
+                    other_input_resource,
+                    merkle_path.clone(),
+                    None,
+                    &mut other_output_resource,
+                    &mut rng,
+                ),
+            ];
+            assert!(template.is_satisfied_by(&templated_resource));
+            let input_resources = [intent_resource, other_input_resource];
+            let output_resources = [templated_resource, other_output_resource];
+            let intent_circuit = TemplateIntentResourceLogicCircuit {
+                owned_resource_id: intent_resource.get_nf().unwrap().into(),
+                input_resources,
+                output_resources,
+            };
+            let input_resource_logics = vec![
+                ResourceLogics::from_application(Box::new(intent_circuit.clone())),
+                trivial_resource_logics(
+                    other_input_resource.get_nf().unwrap().into(),
+                    input_resources,
+                    output_resources,
+                ),
+            ];
+            let output_resource_logics = output_resources
+                .iter()
+                .map(|resource| {
+                    trivial_resource_logics(
+                        resource.commitment().into(),
+                        input_resources,
+                        output_resources,
+                    )
+                })
+                .collect();
+            let ptx = ShieldedPartialTransaction::build(
+                compliances,
+                input_resource_logics,
+                output_resource_logics,
+                vec![],
+                &mut rng,
+            )
+            .unwrap();
+            (ptx, intent_circuit)
+        };
+
+        let tx = Transaction::build(
+            &mut rng,
+            ShieldedPartialTxBundle::new(vec![ptx_1, ptx_2]),
+            TransparentPartialTxBundle::default(),
+        )
+        .unwrap();
+        tx.execute().unwrap();
+
+        // The consuming ptx can't create another resource than the templated one
+        let mut mismatching_circuit = intent_circuit;
+        mismatching_circuit.output_resources[0].value = pallas::Base::random(&mut rng);
+        assert!(mismatching_circuit.verify_transparently().is_err());
+    }
+}
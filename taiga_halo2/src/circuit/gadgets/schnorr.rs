@@ -0,0 +1,347 @@
+/// Schnorr signatures over Pallas and their verification in circuits.
+///
+/// A signature (R, s) of a message hash m by the key P = sk * G satisfies
+///   s * G = R + poseidon_hash(R || P || m) * P
+/// The message hash is any assigned cell, e.g. the Poseidon hash of the nullifiers and the
+/// commitments of a ptx or of a header. `SchnorrChip` only uses the ecc and Poseidon configs of
+/// the circuit, so the resource logics sharing it don't add gates.
+use crate::{
+    circuit::{
+        gadgets::{
+            boolean::AssignedBool, conditional_equal::ConditionalEqualConfig,
+            poseidon_hash::poseidon_hash_gadget,
+        },
+        resource_logic_circuit::ResourceLogicConfig,
+    },
+    constant::{TaigaFixedBases, TaigaFixedBasesFull},
+    utils::{mod_r_p, poseidon_hash_n},
+};
+use halo2_gadgets::{
+    ecc::{
+        chip::{EccChip, EccConfig},
+        FixedPoint, NonIdentityPoint, Point, ScalarFixed, ScalarVar,
+    },
+    poseidon::Pow5Config as PoseidonConfig,
+};
+use halo2_proofs::{
+    arithmetic::Field,
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::Error,
+};
+use pasta_curves::{
+    arithmetic::CurveAffine,
+    group::{Curve, Group},
+    pallas,
+};
+use rand::RngCore;
+
+/// A Schnorr signature of a message hash: s * G = R + poseidon_hash(R || P || m) * P
+#[derive(Clone, Copy, Debug)]
+pub struct Signature {
+    pub(crate) r: pallas::Point,
+    pub(crate) s: pallas::Scalar,
+}
+
+impl Default for Signature {
+    fn default() -> Self {
+        Self {
+            r: pallas::Point::generator(),
+            s: pallas::Scalar::one(),
+        }
+    }
+}
+
+impl Signature {
+    pub fn sign<R: RngCore>(mut rng: R, sk: pallas::Scalar, message_hash: pallas::Base) -> Self {
+        let generator = pallas::Point::generator();
+        let pk = generator * sk;
+        let z = pallas::Scalar::random(&mut rng);
+        let r = generator * z;
+        let s = z + Self::challenge(&r, &pk, message_hash) * sk;
+        Self { r, s }
+    }
+
+    /// Returns false for an identity R or key, which have no coordinates to derive the challenge
+    /// from and which the circuits don't witness.
+    pub fn verify(&self, pk: &pallas::Point, message_hash: pallas::Base) -> bool {
+        if bool::from(self.r.is_identity() | pk.is_identity()) {
+            return false;
+        }
+        pallas::Point::generator() * self.s
+            == self.r + *pk * Self::challenge(&self.r, pk, message_hash)
+    }
+
+    pub fn r(&self) -> pallas::Point {
+        self.r
+    }
+
+    pub fn s(&self) -> pallas::Scalar {
+        self.s
+    }
+
+    fn challenge(
+        r: &pallas::Point,
+        pk: &pallas::Point,
+        message_hash: pallas::Base,
+    ) -> pallas::Scalar {
+        let r_coord = r.to_affine().coordinates().unwrap();
+        let pk_coord = pk.to_affine().coordinates().unwrap();
+        mod_r_p(poseidon_hash_n([
+            *r_coord.x(),
+            *r_coord.y(),
+            *pk_coord.x(),
+            *pk_coord.y(),
+            message_hash,
+        ]))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SchnorrChip {
+    ecc_chip: EccChip<TaigaFixedBases>,
+    poseidon_config: PoseidonConfig<pallas::Base, 3, 2>,
+}
+
+impl SchnorrChip {
+    pub fn construct(
+        ecc_config: EccConfig<TaigaFixedBases>,
+        poseidon_config: PoseidonConfig<pallas::Base, 3, 2>,
+    ) -> Self {
+        Self {
+            ecc_chip: EccChip::construct(ecc_config),
+            poseidon_config,
+        }
+    }
+
+    /// Builds the chip from the configs of a resource logic circuit.
+    pub fn from_resource_logic_config(config: &ResourceLogicConfig) -> Self {
+        Self::construct(config.ecc_config.clone(), config.poseidon_config.clone())
+    }
+
+    /// Constrains the signature of the message hash by the key. The caller is responsible for
+    /// binding the key, e.g. to the value of the owned resource or to a constant.
+    pub fn verify(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        pk: &NonIdentityPoint<pallas::Affine, EccChip<TaigaFixedBases>>,
+        message_hash: &AssignedCell<pallas::Base, pallas::Base>,
+        signature: &Signature,
+    ) -> Result<(), Error> {
+        let (s_g, rhs) = self.signature_equation(
+            layouter.namespace(|| "signature equation"),
+            pk,
+            message_hash,
+            signature,
+        )?;
+        s_g.constrain_equal(layouter.namespace(|| "s*G = R + hP"), &rhs)
+    }
+
+    /// Constrains the signature only when the flag is set, for the logics that require a
+    /// signature on some of their paths.
+    pub fn conditional_verify(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        conditional_equal_config: &ConditionalEqualConfig,
+        flag: &AssignedBool,
+        pk: &NonIdentityPoint<pallas::Affine, EccChip<TaigaFixedBases>>,
+        message_hash: &AssignedCell<pallas::Base, pallas::Base>,
+        signature: &Signature,
+    ) -> Result<(), Error> {
+        let (s_g, rhs) = self.signature_equation(
+            layouter.namespace(|| "signature equation"),
+            pk,
+            message_hash,
+            signature,
+        )?;
+        for (lhs, rhs) in [
+            (s_g.inner().x(), rhs.inner().x()),
+            (s_g.inner().y(), rhs.inner().y()),
+        ] {
+            layouter.assign_region(
+                || "conditional equal: s*G = R + hP",
+                |mut region| {
+                    conditional_equal_config.assign_region(flag, &lhs, &rhs, 0, &mut region)
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Returns both sides of the signature equation s*G = R + poseidon_hash(R || P || m) * P.
+    pub fn signature_equation(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        pk: &NonIdentityPoint<pallas::Affine, EccChip<TaigaFixedBases>>,
+        message_hash: &AssignedCell<pallas::Base, pallas::Base>,
+        signature: &Signature,
+    ) -> Result<
+        (
+            Point<pallas::Affine, EccChip<TaigaFixedBases>>,
+            Point<pallas::Affine, EccChip<TaigaFixedBases>>,
+        ),
+        Error,
+    > {
+        let r = NonIdentityPoint::new(
+            self.ecc_chip.clone(),
+            layouter.namespace(|| "witness r"),
+            Value::known(signature.r.to_affine()),
+        )?;
+        let s_scalar = ScalarFixed::new(
+            self.ecc_chip.clone(),
+            layouter.namespace(|| "witness s"),
+            Value::known(signature.s),
+        )?;
+
+        // s*G
+        let generator =
+            FixedPoint::from_inner(self.ecc_chip.clone(), TaigaFixedBasesFull::BaseGenerator);
+        let (s_g, _) = generator.mul(layouter.namespace(|| "s_scalar * generator"), &s_scalar)?;
+
+        // Hash(R||P||m)
+        let h = poseidon_hash_gadget(
+            self.poseidon_config.clone(),
+            layouter.namespace(|| "Poseidon_hash(R, P, m)"),
+            [
+                r.inner().x(),
+                r.inner().y(),
+                pk.inner().x(),
+                pk.inner().y(),
+                message_hash.clone(),
+            ],
+        )?;
+        let h_scalar = ScalarVar::from_base(
+            self.ecc_chip.clone(),
+            layouter.namespace(|| "ScalarVar from_base"),
+            &h,
+        )?;
+
+        // R + Hash(R||P||m)*P
+        let (h_p, _) = pk.mul(layouter.namespace(|| "hP"), h_scalar)?;
+        let rhs = r.add(layouter.namespace(|| "R + hP"), &h_p)?;
+        Ok((s_g, rhs))
+    }
+}
+
+#[test]
+fn test_halo2_schnorr_chip() {
+    use crate::circuit::gadgets::{assign_free_advice, mul::MulChip};
+    use crate::constant::RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE;
+    use halo2_proofs::{
+        circuit::SimpleFloorPlanner,
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem},
+    };
+    use rand::rngs::OsRng;
+
+    struct MyCircuit {
+        pk: pallas::Point,
+        message_hash: pallas::Base,
+        signature: Signature,
+        // Only check the second signature when set
+        flag: bool,
+        other_signature: Signature,
+    }
+
+    impl Default for MyCircuit {
+        fn default() -> Self {
+            Self {
+                pk: pallas::Point::generator(),
+                message_hash: pallas::Base::zero(),
+                signature: Signature::default(),
+                flag: false,
+                other_signature: Signature::default(),
+            }
+        }
+    }
+
+    impl Circuit<pallas::Base> for MyCircuit {
+        type Config = ResourceLogicConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            ResourceLogicConfig::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), Error> {
+            let chip = SchnorrChip::from_resource_logic_config(&config);
+            let pk = NonIdentityPoint::new(
+                EccChip::construct(config.ecc_config.clone()),
+                layouter.namespace(|| "witness pk"),
+                Value::known(self.pk.to_affine()),
+            )?;
+            let message_hash = assign_free_advice(
+                layouter.namespace(|| "witness message hash"),
+                config.advices[0],
+                Value::known(self.message_hash),
+            )?;
+            chip.verify(
+                layouter.namespace(|| "verify signature"),
+                &pk,
+                &message_hash,
+                &self.signature,
+            )?;
+
+            let flag = AssignedBool::witness(
+                &MulChip::construct(config.mul_config.clone()),
+                layouter.namespace(|| "witness flag"),
+                config.advices[0],
+                Value::known(self.flag),
+            )?;
+            chip.conditional_verify(
+                layouter.namespace(|| "conditionally verify signature"),
+                &config.conditional_equal_config,
+                &flag,
+                &pk,
+                &message_hash,
+                &self.other_signature,
+            )
+        }
+    }
+
+    let mut rng = OsRng;
+    let sk = pallas::Scalar::random(&mut rng);
+    let pk = pallas::Point::generator() * sk;
+    let message_hash = pallas::Base::random(&mut rng);
+    let signature = Signature::sign(&mut rng, sk, message_hash);
+    assert!(signature.verify(&pk, message_hash));
+    assert!(!signature.verify(&pk, message_hash + pallas::Base::one()));
+    let forged = Signature::sign(&mut rng, pallas::Scalar::random(&mut rng), message_hash);
+    assert!(!forged.verify(&pk, message_hash));
+    assert!(!signature.verify(&pallas::Point::identity(), message_hash));
+    let identity_r = Signature {
+        r: pallas::Point::identity(),
+        s: pallas::Scalar::zero(),
+    };
+    assert!(!identity_r.verify(&pk, message_hash));
+
+    let check = |circuit: MyCircuit| {
+        MockProver::<pallas::Base>::run(RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE, &circuit, vec![vec![]])
+            .unwrap()
+            .verify()
+    };
+    let circuit = |message_hash, flag, other_signature| MyCircuit {
+        pk,
+        message_hash,
+        signature,
+        flag,
+        other_signature,
+    };
+    assert_eq!(check(circuit(message_hash, true, signature)), Ok(()));
+    // The unchecked signature may be anything
+    assert_eq!(check(circuit(message_hash, false, forged)), Ok(()));
+    assert!(check(circuit(message_hash, true, forged)).is_err());
+    assert!(check(circuit(
+        message_hash + pallas::Base::one(),
+        false,
+        signature
+    ))
+    .is_err());
+}
@@ -108,3 +108,98 @@ impl<F: Field> SubInstructions<F> for SubChip<F> {
         )
     }
 }
+
+#[test]
+fn test_halo2_sub_gadget() {
+    use crate::circuit::gadgets::assign_free_advice;
+    use halo2_proofs::{
+        circuit::{SimpleFloorPlanner, Value},
+        dev::MockProver,
+        plonk::Circuit,
+    };
+    use pasta_curves::pallas;
+
+    #[derive(Default)]
+    struct MyCircuit {
+        a: pallas::Base,
+        b: pallas::Base,
+        expected: pallas::Base,
+    }
+
+    impl Circuit<pallas::Base> for MyCircuit {
+        type Config = ([Column<Advice>; 2], SubConfig);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            let advices = [meta.advice_column(), meta.advice_column()];
+            for advice in advices.iter() {
+                meta.enable_equality(*advice);
+            }
+            let sub_config = SubChip::configure(meta, advices);
+            (advices, sub_config)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), Error> {
+            let (advices, sub_config) = config;
+            let a = assign_free_advice(
+                layouter.namespace(|| "witness a"),
+                advices[0],
+                Value::known(self.a),
+            )?;
+            let b = assign_free_advice(
+                layouter.namespace(|| "witness b"),
+                advices[1],
+                Value::known(self.b),
+            )?;
+            let expected = assign_free_advice(
+                layouter.namespace(|| "witness expected"),
+                advices[0],
+                Value::known(self.expected),
+            )?;
+
+            let sub_chip = SubChip::<pallas::Base>::construct(sub_config, ());
+            let c = sub_chip.sub(layouter.namespace(|| "a - b"), &a, &b)?;
+
+            layouter.assign_region(
+                || "constrain result",
+                |mut region| region.constrain_equal(c.cell(), expected.cell()),
+            )
+        }
+    }
+
+    let max = -pallas::Base::one();
+    let cases = [
+        (pallas::Base::zero(), pallas::Base::zero()),
+        (pallas::Base::from(5u64), pallas::Base::from(3u64)),
+        // Wraps around the field modulus
+        (pallas::Base::from(3u64), pallas::Base::from(5u64)),
+        (pallas::Base::zero(), max),
+        (max, pallas::Base::one()),
+        (max, max),
+    ];
+    for (a, b) in cases {
+        let circuit = MyCircuit {
+            a,
+            b,
+            expected: a - b,
+        };
+        let prover = MockProver::<pallas::Base>::run(6, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    let circuit = MyCircuit {
+        a: pallas::Base::from(3u64),
+        b: pallas::Base::from(5u64),
+        expected: pallas::Base::from(2u64),
+    };
+    let prover = MockProver::<pallas::Base>::run(6, &circuit, vec![]).unwrap();
+    assert!(prover.verify().is_err());
+}
@@ -1,3 +1,4 @@
+use crate::circuit::gadgets::boolean::AssignedBool;
 use crate::circuit::resource_logic_circuit::ResourceSearchableVariablePair;
 use crate::constant::NUM_RESOURCE;
 use halo2_gadgets::utilities::bool_check;
@@ -34,19 +35,22 @@ pub fn get_is_input_resource_flag(
     owned_resource_id: &AssignedCell<pallas::Base, pallas::Base>,
     input_resource_nfs: &[AssignedCell<pallas::Base, pallas::Base>; NUM_RESOURCE],
     output_resource_cms: &[AssignedCell<pallas::Base, pallas::Base>; NUM_RESOURCE],
-) -> Result<AssignedCell<pallas::Base, pallas::Base>, Error> {
-    layouter.assign_region(
-        || "get is_input_resource_flag",
-        |mut region| {
-            config.assign_region(
-                owned_resource_id,
-                input_resource_nfs,
-                output_resource_cms,
-                0,
-                &mut region,
-            )
-        },
-    )
+) -> Result<AssignedBool, Error> {
+    // The flag is bool-checked in the gate
+    layouter
+        .assign_region(
+            || "get is_input_resource_flag",
+            |mut region| {
+                config.assign_region(
+                    owned_resource_id,
+                    input_resource_nfs,
+                    output_resource_cms,
+                    0,
+                    &mut region,
+                )
+            },
+        )
+        .map(AssignedBool::from_bool_checked)
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -348,3 +352,188 @@ impl GetIsInputResourceFlagConfig {
         )
     }
 }
+
+#[test]
+fn test_halo2_target_resource_variable_gadgets() {
+    use crate::circuit::gadgets::assign_free_advice;
+    use halo2_proofs::{circuit::SimpleFloorPlanner, dev::MockProver, plonk::Circuit};
+
+    #[derive(Default)]
+    struct MyCircuit {
+        owned_resource_id: pallas::Base,
+        // NUM_RESOURCE input nfs followed by NUM_RESOURCE output cms
+        nfs_and_cms: [pallas::Base; NUM_RESOURCE * 2],
+        targets: [pallas::Base; NUM_RESOURCE * 2],
+        expected_is_input_resource_flag: pallas::Base,
+        expected_target: pallas::Base,
+    }
+
+    impl Circuit<pallas::Base> for MyCircuit {
+        type Config = (
+            [Column<Advice>; 5],
+            GetIsInputResourceFlagConfig,
+            GetOwnedResourceVariableConfig,
+        );
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            let advices = [
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+            ];
+            for advice in advices.iter() {
+                meta.enable_equality(*advice);
+            }
+            let get_is_input_resource_flag_config =
+                GetIsInputResourceFlagConfig::configure(meta, advices[0], advices[1], advices[2]);
+            let get_owned_resource_variable_config = GetOwnedResourceVariableConfig::configure(
+                meta,
+                advices[0],
+                [advices[1], advices[2], advices[3], advices[4]],
+            );
+            (
+                advices,
+                get_is_input_resource_flag_config,
+                get_owned_resource_variable_config,
+            )
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), Error> {
+            let (advices, get_is_input_resource_flag_config, get_owned_resource_variable_config) =
+                config;
+            let owned_resource_id = assign_free_advice(
+                layouter.namespace(|| "witness owned_resource_id"),
+                advices[0],
+                Value::known(self.owned_resource_id),
+            )?;
+            let pairs = self
+                .nfs_and_cms
+                .iter()
+                .zip(self.targets.iter())
+                .map(|(nf_or_cm, target)| {
+                    Ok(ResourceSearchableVariablePair {
+                        src_variable: assign_free_advice(
+                            layouter.namespace(|| "witness nf or cm"),
+                            advices[1],
+                            Value::known(*nf_or_cm),
+                        )?,
+                        target_variable: assign_free_advice(
+                            layouter.namespace(|| "witness target"),
+                            advices[2],
+                            Value::known(*target),
+                        )?,
+                    })
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            let input_resource_nfs = [pairs[0].src_variable.clone(), pairs[1].src_variable.clone()];
+            let output_resource_cms =
+                [pairs[2].src_variable.clone(), pairs[3].src_variable.clone()];
+
+            let is_input_resource_flag = get_is_input_resource_flag(
+                get_is_input_resource_flag_config,
+                layouter.namespace(|| "get is_input_resource_flag"),
+                &owned_resource_id,
+                &input_resource_nfs,
+                &output_resource_cms,
+            )?;
+            let target = get_owned_resource_variable(
+                get_owned_resource_variable_config,
+                layouter.namespace(|| "get owned resource variable"),
+                &owned_resource_id,
+                &pairs.try_into().unwrap(),
+            )?;
+
+            let expected_is_input_resource_flag = assign_free_advice(
+                layouter.namespace(|| "witness expected is_input_resource_flag"),
+                advices[0],
+                Value::known(self.expected_is_input_resource_flag),
+            )?;
+            let expected_target = assign_free_advice(
+                layouter.namespace(|| "witness expected target"),
+                advices[0],
+                Value::known(self.expected_target),
+            )?;
+            layouter.assign_region(
+                || "constrain results",
+                |mut region| {
+                    region.constrain_equal(
+                        is_input_resource_flag.cell(),
+                        expected_is_input_resource_flag.cell(),
+                    )?;
+                    region.constrain_equal(target.cell(), expected_target.cell())
+                },
+            )
+        }
+    }
+
+    let nfs_and_cms = [1u64, 2, 3, 4].map(pallas::Base::from);
+    let targets = [10u64, 20, 30, 40].map(pallas::Base::from);
+    let run = |circuit: MyCircuit| {
+        MockProver::<pallas::Base>::run(6, &circuit, vec![])
+            .unwrap()
+            .verify()
+    };
+
+    // The owned resource is each of the input and output resources
+    for (idx, (owned_resource_id, target)) in nfs_and_cms.iter().zip(targets).enumerate() {
+        let circuit = MyCircuit {
+            owned_resource_id: *owned_resource_id,
+            nfs_and_cms,
+            targets,
+            expected_is_input_resource_flag: pallas::Base::from(idx < NUM_RESOURCE),
+            expected_target: target,
+        };
+        assert_eq!(run(circuit), Ok(()));
+    }
+
+    // Equal input nullifiers with equal targets
+    let circuit = MyCircuit {
+        owned_resource_id: nfs_and_cms[0],
+        nfs_and_cms: [
+            nfs_and_cms[0],
+            nfs_and_cms[0],
+            nfs_and_cms[2],
+            nfs_and_cms[3],
+        ],
+        targets: [targets[0], targets[0], targets[2], targets[3]],
+        expected_is_input_resource_flag: pallas::Base::one(),
+        expected_target: targets[0],
+    };
+    assert_eq!(run(circuit), Ok(()));
+
+    // Equal input nullifiers with different targets
+    let circuit = MyCircuit {
+        owned_resource_id: nfs_and_cms[0],
+        nfs_and_cms: [
+            nfs_and_cms[0],
+            nfs_and_cms[0],
+            nfs_and_cms[2],
+            nfs_and_cms[3],
+        ],
+        targets,
+        expected_is_input_resource_flag: pallas::Base::one(),
+        expected_target: targets[0],
+    };
+    assert!(run(circuit).is_err());
+
+    // The owned resource id is not in the resources
+    let circuit = MyCircuit {
+        owned_resource_id: -pallas::Base::one(),
+        nfs_and_cms,
+        targets,
+        expected_is_input_resource_flag: pallas::Base::zero(),
+        expected_target: pallas::Base::zero(),
+    };
+    assert!(run(circuit).is_err());
+}
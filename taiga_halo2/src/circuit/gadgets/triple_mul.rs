@@ -59,3 +59,117 @@ impl TripleMulConfig {
         region.assign_advice(|| "out", self.advice[0], 1, || value)
     }
 }
+
+#[test]
+fn test_halo2_triple_mul_gadget() {
+    use crate::circuit::gadgets::assign_free_advice;
+    use halo2_proofs::{
+        arithmetic::Field,
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        plonk::Circuit,
+    };
+
+    #[derive(Default)]
+    struct MyCircuit {
+        inputs: [pallas::Base; 3],
+        expected: pallas::Base,
+    }
+
+    impl Circuit<pallas::Base> for MyCircuit {
+        type Config = ([Column<Advice>; 3], TripleMulConfig);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            let advices = [
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+            ];
+            for advice in advices.iter() {
+                meta.enable_equality(*advice);
+            }
+            let triple_mul_config = TripleMulConfig::configure(meta, advices);
+            (advices, triple_mul_config)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), Error> {
+            let (advices, triple_mul_config) = config;
+            let inputs = self
+                .inputs
+                .iter()
+                .zip(advices)
+                .map(|(input, advice)| {
+                    assign_free_advice(
+                        layouter.namespace(|| "witness input"),
+                        advice,
+                        Value::known(*input),
+                    )
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            let expected = assign_free_advice(
+                layouter.namespace(|| "witness expected"),
+                advices[0],
+                Value::known(self.expected),
+            )?;
+
+            let out = layouter.assign_region(
+                || "triple mul",
+                |mut region| {
+                    triple_mul_config.assign_region(
+                        &inputs[0],
+                        &inputs[1],
+                        &inputs[2],
+                        0,
+                        &mut region,
+                    )
+                },
+            )?;
+
+            layouter.assign_region(
+                || "constrain result",
+                |mut region| region.constrain_equal(out.cell(), expected.cell()),
+            )
+        }
+    }
+
+    let max = -pallas::Base::one();
+    let cases = [
+        [pallas::Base::zero(); 3],
+        [max, pallas::Base::zero(), max],
+        [pallas::Base::one(); 3],
+        [max; 3],
+        [
+            pallas::Base::from(2u64),
+            pallas::Base::from(3u64),
+            pallas::Base::from(7u64),
+        ],
+    ];
+    for inputs in cases {
+        let circuit = MyCircuit {
+            inputs,
+            expected: inputs[0] * inputs[1] * inputs[2],
+        };
+        let prover = MockProver::<pallas::Base>::run(6, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    let circuit = MyCircuit {
+        inputs: [
+            pallas::Base::from(2u64),
+            pallas::Base::from(3u64),
+            pallas::Base::from(7u64),
+        ],
+        expected: pallas::Base::from(41u64),
+    };
+    let prover = MockProver::<pallas::Base>::run(6, &circuit, vec![]).unwrap();
+    assert!(prover.verify().is_err());
+}
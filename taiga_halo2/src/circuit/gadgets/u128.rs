@@ -0,0 +1,440 @@
+/// Two-limb u128 arithmetic: value = lo + 2^64 * hi, with both limbs range-checked to 64 bits.
+use crate::circuit::gadgets::range_check::RangeCheckChip;
+use group::ff::PrimeField;
+use halo2_gadgets::utilities::{bool_check, lookup_range_check::LookupRangeCheckConfig};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Error, Selector},
+    poly::Rotation,
+};
+use pasta_curves::pallas;
+
+#[derive(Clone, Debug)]
+pub struct U128Cell {
+    pub lo: AssignedCell<pallas::Base, pallas::Base>,
+    pub hi: AssignedCell<pallas::Base, pallas::Base>,
+}
+
+#[derive(Clone, Debug)]
+pub struct U128Config {
+    q_compose: Selector,
+    q_add: Selector,
+    q_sub: Selector,
+    // [a, b, c, carry_in, carry_out]
+    advice: [Column<Advice>; 5],
+    range_check: RangeCheckChip,
+}
+
+impl U128Config {
+    pub fn configure(
+        meta: &mut ConstraintSystem<pallas::Base>,
+        advice: [Column<Advice>; 5],
+        range_check: LookupRangeCheckConfig<pallas::Base, 10>,
+    ) -> Self {
+        let config = Self {
+            q_compose: meta.selector(),
+            q_add: meta.selector(),
+            q_sub: meta.selector(),
+            advice,
+            range_check: RangeCheckChip::construct(range_check),
+        };
+
+        config.create_gate(meta);
+
+        config
+    }
+
+    fn create_gate(&self, meta: &mut ConstraintSystem<pallas::Base>) {
+        let two_pow_64 = pallas::Base::from_u128(1 << 64);
+
+        meta.create_gate("u128 compose", |meta| {
+            let q_compose = meta.query_selector(self.q_compose);
+            let lo = meta.query_advice(self.advice[0], Rotation::cur());
+            let hi = meta.query_advice(self.advice[1], Rotation::cur());
+            let value = meta.query_advice(self.advice[2], Rotation::cur());
+
+            Constraints::with_selector(
+                q_compose,
+                [("value = lo + 2^64 * hi", value - (lo + hi * two_pow_64))],
+            )
+        });
+
+        meta.create_gate("u128 limb add", |meta| {
+            let q_add = meta.query_selector(self.q_add);
+            let a = meta.query_advice(self.advice[0], Rotation::cur());
+            let b = meta.query_advice(self.advice[1], Rotation::cur());
+            let c = meta.query_advice(self.advice[2], Rotation::cur());
+            let carry_in = meta.query_advice(self.advice[3], Rotation::cur());
+            let carry_out = meta.query_advice(self.advice[4], Rotation::cur());
+
+            Constraints::with_selector(
+                q_add,
+                [
+                    ("bool_check carry_out", bool_check(carry_out.clone())),
+                    (
+                        "a + b + carry_in = c + 2^64 * carry_out",
+                        a + b + carry_in - c - carry_out * two_pow_64,
+                    ),
+                ],
+            )
+        });
+
+        meta.create_gate("u128 limb sub", |meta| {
+            let q_sub = meta.query_selector(self.q_sub);
+            let a = meta.query_advice(self.advice[0], Rotation::cur());
+            let b = meta.query_advice(self.advice[1], Rotation::cur());
+            let c = meta.query_advice(self.advice[2], Rotation::cur());
+            let borrow_in = meta.query_advice(self.advice[3], Rotation::cur());
+            let borrow_out = meta.query_advice(self.advice[4], Rotation::cur());
+
+            Constraints::with_selector(
+                q_sub,
+                [
+                    ("bool_check borrow_out", bool_check(borrow_out.clone())),
+                    (
+                        "a - b - borrow_in + 2^64 * borrow_out = c",
+                        a - b - borrow_in + borrow_out * two_pow_64 - c,
+                    ),
+                ],
+            )
+        });
+    }
+
+    /// Witness a u128 and range check both limbs.
+    pub fn witness(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        value: Value<u128>,
+    ) -> Result<U128Cell, Error> {
+        let lo = self.range_check.witness_u64(
+            layouter.namespace(|| "witness lo"),
+            value.map(|v| pallas::Base::from(v as u64)),
+        )?;
+        let hi = self.range_check.witness_u64(
+            layouter.namespace(|| "witness hi"),
+            value.map(|v| pallas::Base::from((v >> 64) as u64)),
+        )?;
+        Ok(U128Cell { lo, hi })
+    }
+
+    /// Decompose a field element into range-checked limbs. The constraints are unsatisfiable
+    /// if the value does not fit in 128 bits.
+    pub fn decompose(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        value: &AssignedCell<pallas::Base, pallas::Base>,
+    ) -> Result<U128Cell, Error> {
+        let limbs = value.value().map(|v| {
+            let bytes = v.to_repr();
+            (
+                u64::from_le_bytes(bytes[0..8].try_into().unwrap()),
+                u64::from_le_bytes(bytes[8..16].try_into().unwrap()),
+            )
+        });
+        let lo = self.range_check.witness_u64(
+            layouter.namespace(|| "witness lo"),
+            limbs.map(|(lo, _)| pallas::Base::from(lo)),
+        )?;
+        let hi = self.range_check.witness_u64(
+            layouter.namespace(|| "witness hi"),
+            limbs.map(|(_, hi)| pallas::Base::from(hi)),
+        )?;
+        layouter.assign_region(
+            || "u128 decompose",
+            |mut region| {
+                self.q_compose.enable(&mut region, 0)?;
+                lo.copy_advice(|| "lo", &mut region, self.advice[0], 0)?;
+                hi.copy_advice(|| "hi", &mut region, self.advice[1], 0)?;
+                value.copy_advice(|| "value", &mut region, self.advice[2], 0)?;
+                Ok(())
+            },
+        )?;
+        Ok(U128Cell { lo, hi })
+    }
+
+    /// Compose the limbs back into a single field element.
+    pub fn compose(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        x: &U128Cell,
+    ) -> Result<AssignedCell<pallas::Base, pallas::Base>, Error> {
+        layouter.assign_region(
+            || "u128 compose",
+            |mut region| {
+                self.q_compose.enable(&mut region, 0)?;
+                x.lo.copy_advice(|| "lo", &mut region, self.advice[0], 0)?;
+                x.hi.copy_advice(|| "hi", &mut region, self.advice[1], 0)?;
+                let value =
+                    x.lo.value()
+                        .zip(x.hi.value())
+                        .map(|(lo, hi)| lo + hi * pallas::Base::from_u128(1 << 64));
+                region.assign_advice(|| "value", self.advice[2], 0, || value)
+            },
+        )
+    }
+
+    /// a + b, the constraints are unsatisfiable on overflow.
+    pub fn add(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        a: &U128Cell,
+        b: &U128Cell,
+    ) -> Result<U128Cell, Error> {
+        let (c, carry) = self.limb_chain(layouter.namespace(|| "u128 add"), a, b, true)?;
+        layouter.assign_region(
+            || "no overflow",
+            |mut region| region.constrain_constant(carry.cell(), pallas::Base::zero()),
+        )?;
+        Ok(c)
+    }
+
+    /// a - b, the constraints are unsatisfiable on underflow.
+    pub fn sub(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        a: &U128Cell,
+        b: &U128Cell,
+    ) -> Result<U128Cell, Error> {
+        let (c, borrow) = self.limb_chain(layouter.namespace(|| "u128 sub"), a, b, false)?;
+        layouter.assign_region(
+            || "no underflow",
+            |mut region| region.constrain_constant(borrow.cell(), pallas::Base::zero()),
+        )?;
+        Ok(c)
+    }
+
+    /// Returns the boolean flag a < b.
+    pub fn less_than(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        a: &U128Cell,
+        b: &U128Cell,
+    ) -> Result<AssignedCell<pallas::Base, pallas::Base>, Error> {
+        let (_, borrow) = self.limb_chain(layouter.namespace(|| "u128 compare"), a, b, false)?;
+        Ok(borrow)
+    }
+
+    // Add (or subtract) the limbs with carry (or borrow) propagation and return the result
+    // together with the final carry (or borrow).
+    fn limb_chain(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        a: &U128Cell,
+        b: &U128Cell,
+        is_add: bool,
+    ) -> Result<(U128Cell, AssignedCell<pallas::Base, pallas::Base>), Error> {
+        let two_pow_64 = 1u128 << 64;
+        let (lo, hi, carry) = layouter.assign_region(
+            || "u128 limb chain",
+            |mut region| {
+                let mut carry_in = region.assign_advice_from_constant(
+                    || "carry_in",
+                    self.advice[3],
+                    0,
+                    pallas::Base::zero(),
+                )?;
+                let mut outputs = vec![];
+                for (offset, (a, b)) in [(&a.lo, &b.lo), (&a.hi, &b.hi)].into_iter().enumerate() {
+                    if is_add {
+                        self.q_add.enable(&mut region, offset)?;
+                    } else {
+                        self.q_sub.enable(&mut region, offset)?;
+                    }
+                    a.copy_advice(|| "a", &mut region, self.advice[0], offset)?;
+                    b.copy_advice(|| "b", &mut region, self.advice[1], offset)?;
+                    if offset > 0 {
+                        carry_in = carry_in.copy_advice(
+                            || "carry_in",
+                            &mut region,
+                            self.advice[3],
+                            offset,
+                        )?;
+                    }
+                    let c_and_carry_out =
+                        a.value()
+                            .zip(b.value())
+                            .zip(carry_in.value())
+                            .map(|((a, b), carry_in)| {
+                                let a = base_to_u128(a);
+                                let b = base_to_u128(b);
+                                let carry_in = base_to_u128(carry_in);
+                                if is_add {
+                                    let sum = a + b + carry_in;
+                                    (sum % two_pow_64, sum / two_pow_64)
+                                } else if a >= b + carry_in {
+                                    (a - b - carry_in, 0)
+                                } else {
+                                    (a + two_pow_64 - b - carry_in, 1)
+                                }
+                            });
+                    let c = region.assign_advice(
+                        || "c",
+                        self.advice[2],
+                        offset,
+                        || c_and_carry_out.map(|(c, _)| pallas::Base::from_u128(c)),
+                    )?;
+                    carry_in = region.assign_advice(
+                        || "carry_out",
+                        self.advice[4],
+                        offset,
+                        || c_and_carry_out.map(|(_, carry)| pallas::Base::from_u128(carry)),
+                    )?;
+                    outputs.push(c);
+                }
+                let hi = outputs.pop().unwrap();
+                let lo = outputs.pop().unwrap();
+                Ok((lo, hi, carry_in))
+            },
+        )?;
+
+        self.range_check
+            .range_check_u64(layouter.namespace(|| "range check lo"), &lo)?;
+        self.range_check
+            .range_check_u64(layouter.namespace(|| "range check hi"), &hi)?;
+
+        Ok((U128Cell { lo, hi }, carry))
+    }
+}
+
+// Only used on the limbs and carries, which fit in 128 bits.
+pub(crate) fn base_to_u128(x: &pallas::Base) -> u128 {
+    u128::from_le_bytes(x.to_repr()[..16].try_into().unwrap())
+}
+
+/// Split a u128 into (lo, hi) u64 limbs.
+pub fn split_u128(value: u128) -> (u64, u64) {
+    (value as u64, (value >> 64) as u64)
+}
+
+#[test]
+fn test_halo2_u128_gadget() {
+    use crate::circuit::gadgets::{assign_free_advice, assign_free_constant};
+    use halo2_proofs::{
+        circuit::SimpleFloorPlanner,
+        dev::MockProver,
+        plonk::{Circuit, Fixed},
+    };
+
+    #[derive(Clone, Copy, Debug, Default)]
+    enum Op {
+        #[default]
+        Add,
+        Sub,
+        LessThan,
+        // Decomposes a, not necessarily a u128
+        Decompose(pallas::Base),
+    }
+
+    #[derive(Default)]
+    struct MyCircuit {
+        op: Op,
+        a: u128,
+        b: u128,
+        // The sum, the difference or the less than flag
+        expected: u128,
+    }
+
+    impl Circuit<pallas::Base> for MyCircuit {
+        type Config = (U128Config, LookupRangeCheckConfig<pallas::Base, 10>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            let advices = [(); 5].map(|_| meta.advice_column());
+            advices
+                .iter()
+                .for_each(|advice| meta.enable_equality(*advice));
+            let table_idx = meta.lookup_table_column();
+            let constants: Column<Fixed> = meta.fixed_column();
+            meta.enable_constant(constants);
+            let range_check = LookupRangeCheckConfig::configure(meta, advices[0], table_idx);
+            (
+                U128Config::configure(meta, advices, range_check),
+                range_check,
+            )
+        }
+
+        fn synthesize(
+            &self,
+            (config, range_check): Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), Error> {
+            range_check.load(&mut layouter)?;
+            let result = match self.op {
+                Op::Decompose(value) => {
+                    let value = assign_free_advice(
+                        layouter.namespace(|| "witness value"),
+                        config.advice[0],
+                        Value::known(value),
+                    )?;
+                    config.decompose(layouter.namespace(|| "decompose"), &value)?;
+                    return Ok(());
+                }
+                op => {
+                    let a =
+                        config.witness(layouter.namespace(|| "witness a"), Value::known(self.a))?;
+                    let b =
+                        config.witness(layouter.namespace(|| "witness b"), Value::known(self.b))?;
+                    match op {
+                        Op::Add => {
+                            let c = config.add(layouter.namespace(|| "a + b"), &a, &b)?;
+                            config.compose(layouter.namespace(|| "compose"), &c)?
+                        }
+                        Op::Sub => {
+                            let c = config.sub(layouter.namespace(|| "a - b"), &a, &b)?;
+                            config.compose(layouter.namespace(|| "compose"), &c)?
+                        }
+                        _ => config.less_than(layouter.namespace(|| "a < b"), &a, &b)?,
+                    }
+                }
+            };
+            let expected = assign_free_constant(
+                layouter.namespace(|| "expected"),
+                config.advice[0],
+                pallas::Base::from_u128(self.expected),
+            )?;
+            layouter.assign_region(
+                || "check result",
+                |mut region| region.constrain_equal(result.cell(), expected.cell()),
+            )
+        }
+    }
+
+    let verify = |op: Op, a: u128, b: u128, expected: u128| {
+        let circuit = MyCircuit { op, a, b, expected };
+        MockProver::<pallas::Base>::run(11, &circuit, vec![])
+            .unwrap()
+            .verify()
+    };
+    let two_pow_64 = 1u128 << 64;
+
+    // The carries and the borrows propagate across the limbs
+    assert_eq!(verify(Op::Add, two_pow_64 - 1, 1, two_pow_64), Ok(()));
+    assert_eq!(verify(Op::Add, u128::MAX - 1, 1, u128::MAX), Ok(()));
+    assert_eq!(verify(Op::Sub, two_pow_64, 1, two_pow_64 - 1), Ok(()));
+    assert_eq!(verify(Op::Sub, 5, 5, 0), Ok(()));
+    assert_eq!(verify(Op::LessThan, 1, 2, 1), Ok(()));
+    assert_eq!(verify(Op::LessThan, two_pow_64, two_pow_64 - 1, 0), Ok(()));
+    assert_eq!(verify(Op::LessThan, 2, 2, 0), Ok(()));
+
+    // The overflow and the underflows are rejected, even with the wrapped result expected
+    assert!(verify(Op::Add, u128::MAX, 1, 0).is_err());
+    assert!(verify(Op::Sub, 0, 1, u128::MAX).is_err());
+    assert!(verify(Op::Sub, two_pow_64, two_pow_64 + 1, u128::MAX).is_err());
+    assert!(verify(Op::Sub, two_pow_64 - 1, two_pow_64, u128::MAX).is_err());
+
+    // The values up to 2^128 - 1 decompose, the larger ones don't
+    for value in [two_pow_64 - 1, two_pow_64, u128::MAX] {
+        let op = Op::Decompose(pallas::Base::from_u128(value));
+        assert_eq!(verify(op, 0, 0, 0), Ok(()));
+    }
+    for value in [
+        pallas::Base::from_u128(u128::MAX) + pallas::Base::one(),
+        -pallas::Base::one(),
+    ] {
+        assert!(verify(Op::Decompose(value), 0, 0, 0).is_err());
+    }
+}
@@ -0,0 +1,66 @@
+/// A vk policy is a fixed-size set of resource logic vks committed to by the hash accumulator of
+/// the vks, in the domain of the policy. The gadget checks a vk is a member of the policy: it is a
+/// root of the product of (vk - policy_vk). The receiver policy and the token auth policy are vk
+/// policies.
+use crate::circuit::gadgets::{
+    assign_free_advice, assign_free_constant,
+    hash_accumulator::{accumulate, HashAccumulatorChip},
+    mul::{MulChip, MulInstructions},
+    sub::{SubChip, SubInstructions},
+};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::{Advice, Column, Error},
+};
+use pasta_curves::pallas;
+
+/// The commitment to the policy, the native counterpart of `vk_policy_gadget`.
+pub fn vk_policy_commitment(domain: pallas::Base, policy_vks: &[pallas::Base]) -> pallas::Base {
+    accumulate(domain, policy_vks)
+}
+
+/// Checks `vk` is a member of the policy, and returns the commitment to the policy.
+#[allow(clippy::too_many_arguments)]
+pub fn vk_policy_gadget<const N: usize>(
+    mut layouter: impl Layouter<pallas::Base>,
+    advice: Column<Advice>,
+    accumulator_chip: &HashAccumulatorChip,
+    domain: pallas::Base,
+    sub_chip: &SubChip<pallas::Base>,
+    mul_chip: &MulChip<pallas::Base>,
+    policy_vks: &[pallas::Base; N],
+    vk: &AssignedCell<pallas::Base, pallas::Base>,
+) -> Result<AssignedCell<pallas::Base, pallas::Base>, Error> {
+    let policy_vks = policy_vks
+        .iter()
+        .map(|policy_vk| {
+            assign_free_advice(
+                layouter.namespace(|| "witness policy vk"),
+                advice,
+                Value::known(*policy_vk),
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut product = sub_chip.sub(layouter.namespace(|| "vk - policy vk"), vk, &policy_vks[0])?;
+    for policy_vk in policy_vks.iter().skip(1) {
+        let diff = sub_chip.sub(layouter.namespace(|| "vk - policy vk"), vk, policy_vk)?;
+        product = mul_chip.mul(layouter.namespace(|| "policy product"), &product, &diff)?;
+    }
+    let constant_zero =
+        assign_free_constant(layouter.namespace(|| "zero"), advice, pallas::Base::zero())?;
+    layouter.assign_region(
+        || "check policy membership",
+        |mut region| region.constrain_equal(product.cell(), constant_zero.cell()),
+    )?;
+
+    let mut accumulator = accumulator_chip.start(layouter.namespace(|| "policy domain"), domain)?;
+    for policy_vk in policy_vks.iter() {
+        accumulator = accumulator_chip.absorb(
+            layouter.namespace(|| "absorb policy vk"),
+            &accumulator,
+            policy_vk,
+        )?;
+    }
+    accumulator_chip.finalize(layouter.namespace(|| "policy commitment"), &accumulator)
+}
@@ -1,6 +1,6 @@
-use crate::constant::{
-    TaigaFixedBases, POSEIDON_TO_CURVE_INPUT_LEN, POSEIDON_TO_FIELD_U_0_POSTFIX,
-    POSEIDON_TO_FIELD_U_1_POSTFIX,
+use crate::{
+    constant::{TaigaFixedBases, POSEIDON_TO_CURVE_INPUT_LEN},
+    utils::HashToCurveDomain,
 };
 use halo2_gadgets::{
     ecc::{chip::EccChip, Point},
@@ -57,75 +57,70 @@ use crate::circuit::gadgets::poseidon_hash::poseidon_hash_gadget;
 //     }
 // }
 
-pub fn hash_to_curve_circuit(
+/// The gadget of `utils::hash_to_curve`: hashes the two messages to a pallas point in the domain.
+/// The postfixes of the domain are loaded as constants.
+pub fn hash_to_curve_gadget(
     mut layouter: impl Layouter<pallas::Base>,
     config: HashToCurveConfig,
     ecc_chip: EccChip<TaigaFixedBases>,
-    messages: &[AssignedCell<pallas::Base, pallas::Base>],
+    domain: &HashToCurveDomain,
+    messages: [AssignedCell<pallas::Base, pallas::Base>; 2],
 ) -> Result<Point<pallas::Affine, EccChip<TaigaFixedBases>>, Error> {
+    let u_0_postfix = layouter.assign_region(
+        || "load constant",
+        |mut region| {
+            region.assign_advice_from_constant(
+                || "constant value",
+                config.advices[0],
+                0,
+                domain.u_0_postfix(),
+            )
+        },
+    )?;
+    let u_1_postfix = layouter.assign_region(
+        || "load constant",
+        |mut region| {
+            region.assign_advice_from_constant(
+                || "constant value",
+                config.advices[1],
+                0,
+                domain.u_1_postfix(),
+            )
+        },
+    )?;
+    hash_to_curve_with_postfixes_gadget(
+        layouter,
+        config,
+        ecc_chip,
+        [u_0_postfix, u_1_postfix],
+        messages,
+    )
+}
+
+/// The gadget of `utils::hash_to_curve` with the postfixes of the domain assigned by the caller,
+/// for a domain picked in the circuit. The caller constrains the postfixes.
+pub fn hash_to_curve_with_postfixes_gadget(
+    mut layouter: impl Layouter<pallas::Base>,
+    config: HashToCurveConfig,
+    ecc_chip: EccChip<TaigaFixedBases>,
+    postfixes: [AssignedCell<pallas::Base, pallas::Base>; 2],
+    messages: [AssignedCell<pallas::Base, pallas::Base>; 2],
+) -> Result<Point<pallas::Affine, EccChip<TaigaFixedBases>>, Error> {
+    let [u_0_postfix, u_1_postfix] = postfixes;
+
     // hash to u_0
-    let u_0 = {
-        let u_0_postfix: Vec<AssignedCell<pallas::Base, pallas::Base>> =
-            POSEIDON_TO_FIELD_U_0_POSTFIX
-                .iter()
-                .map(|&v| {
-                    layouter
-                        .assign_region(
-                            || "load constant",
-                            |mut region| {
-                                region.assign_advice_from_constant(
-                                    || "constant value",
-                                    config.advices[0],
-                                    0,
-                                    v,
-                                )
-                            },
-                        )
-                        .unwrap()
-                })
-                .collect();
-        let poseidon_msg = [messages, &u_0_postfix]
-            .concat()
-            .try_into()
-            .expect("slice with incorrect length");
-        poseidon_hash_gadget::<POSEIDON_TO_CURVE_INPUT_LEN>(
-            config.poseidon_config.clone(),
-            layouter.namespace(|| "compute u_0"),
-            poseidon_msg,
-        )?
-    };
+    let u_0 = poseidon_hash_gadget::<POSEIDON_TO_CURVE_INPUT_LEN>(
+        config.poseidon_config.clone(),
+        layouter.namespace(|| "compute u_0"),
+        [messages[0].clone(), messages[1].clone(), u_0_postfix],
+    )?;
 
     // hash to u_1
-    let u_1 = {
-        let u_1_postfix: Vec<AssignedCell<pallas::Base, pallas::Base>> =
-            POSEIDON_TO_FIELD_U_1_POSTFIX
-                .iter()
-                .map(|&v| {
-                    layouter
-                        .assign_region(
-                            || "load constant",
-                            |mut region| {
-                                region.assign_advice_from_constant(
-                                    || "constant value",
-                                    config.advices[1],
-                                    0,
-                                    v,
-                                )
-                            },
-                        )
-                        .unwrap()
-                })
-                .collect();
-        let poseidon_msg = [messages, &u_1_postfix]
-            .concat()
-            .try_into()
-            .expect("slice with incorrect length");
-        poseidon_hash_gadget::<POSEIDON_TO_CURVE_INPUT_LEN>(
-            config.poseidon_config.clone(),
-            layouter.namespace(|| "compute u_1"),
-            poseidon_msg,
-        )?
-    };
+    let u_1 = poseidon_hash_gadget::<POSEIDON_TO_CURVE_INPUT_LEN>(
+        config.poseidon_config.clone(),
+        layouter.namespace(|| "compute u_1"),
+        [messages[0].clone(), messages[1].clone(), u_1_postfix],
+    )?;
 
     // Use messages as u_0 and u_1
     let q_0 = layouter.assign_region(
@@ -237,14 +232,21 @@ fn test_hash_to_curve_circuit() {
     use pasta_curves::group::Curve;
 
     use crate::circuit::gadgets::assign_free_advice;
-    use crate::utils::poseidon_to_curve;
+    use crate::utils::hash_to_curve;
+    use ff::Field;
     use halo2_proofs::{
         circuit::{Layouter, SimpleFloorPlanner, Value},
         dev::MockProver,
         plonk::{Advice, Circuit, Column, ConstraintSystem, Error},
     };
+    use rand::rngs::OsRng;
+
+    // The gadget point is constrained to the native one
     #[derive(Default)]
-    struct MyCircuit {}
+    struct MyCircuit {
+        domain: &'static str,
+        messages: [pallas::Base; 2],
+    }
 
     impl Circuit<pallas::Base> for MyCircuit {
         type Config = (
@@ -311,22 +313,24 @@ fn test_hash_to_curve_circuit() {
             let (advices, hash_to_curve_config, ecc_config) = config;
             let ecc_chip = EccChip::construct(ecc_config);
 
-            let messages = [pallas::Base::zero(); POSEIDON_TO_CURVE_INPUT_LEN - 1];
-            let messages_vars = messages
-                .into_iter()
-                .map(|v| {
-                    assign_free_advice(layouter.namespace(|| "u_0"), advices[0], Value::known(v))
-                        .unwrap()
-                })
-                .collect::<Vec<_>>();
-            let ret = hash_to_curve_circuit(
+            let domain = HashToCurveDomain::new(self.domain);
+            let messages_vars = self.messages.map(|v| {
+                assign_free_advice(
+                    layouter.namespace(|| "message"),
+                    advices[0],
+                    Value::known(v),
+                )
+                .unwrap()
+            });
+            let ret = hash_to_curve_gadget(
                 layouter.namespace(|| "hash to curve"),
                 hash_to_curve_config,
                 ecc_chip.clone(),
-                &messages_vars,
+                &domain,
+                messages_vars,
             )?;
             let expect_ret = {
-                let expect_point = poseidon_to_curve::<POSEIDON_TO_CURVE_INPUT_LEN>(&messages);
+                let expect_point = hash_to_curve(&domain, self.messages);
                 Point::new(
                     ecc_chip,
                     layouter.namespace(|| "expect_point"),
@@ -337,10 +341,22 @@ fn test_hash_to_curve_circuit() {
         }
     }
 
-    let circuit = MyCircuit {};
-
-    let prover = MockProver::run(11, &circuit, vec![]).unwrap();
-    assert_eq!(prover.verify(), Ok(()));
+    for (domain, messages) in [
+        ("Taiga-NoteType", [pallas::Base::zero(); 2]),
+        (
+            "Taiga-NoteType",
+            [pallas::Base::random(OsRng), pallas::Base::random(OsRng)],
+        ),
+        (
+            "MyApp-Kinds",
+            [pallas::Base::random(OsRng), pallas::Base::random(OsRng)],
+        ),
+        ("", [pallas::Base::one(), pallas::Base::zero()]),
+    ] {
+        let circuit = MyCircuit { domain, messages };
+        let prover = MockProver::run(11, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
 
     // TODO: there is still space to improve the performance. keep the test
     // {
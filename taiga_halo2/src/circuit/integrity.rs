@@ -1,15 +1,15 @@
 use crate::circuit::{
     gadgets::{assign_free_advice, assign_free_constant, poseidon_hash::poseidon_hash_gadget},
-    hash_to_curve::{hash_to_curve_circuit, HashToCurveConfig},
+    hash_to_curve::{hash_to_curve_with_postfixes_gadget, HashToCurveConfig},
     resource_commitment::{resource_commit, ResourceCommitChip},
     resource_logic_circuit::{InputResourceVariables, OutputResourceVariables, ResourceVariables},
 };
 use crate::constant::{
-    TaigaFixedBases, TaigaFixedBasesFull, POSEIDON_TO_CURVE_INPUT_LEN,
-    PRF_EXPAND_PERSONALIZATION_TO_FIELD, PRF_EXPAND_PSI, PRF_EXPAND_RCM,
+    TaigaFixedBases, TaigaFixedBasesFull, PRF_EXPAND_PERSONALIZATION_TO_FIELD, PRF_EXPAND_PSI,
+    PRF_EXPAND_RCM, RESOURCE_KIND_DOMAIN, TRANSPARENT_RESOURCE_KIND_DOMAIN,
 };
 use crate::resource::Resource;
-use crate::utils::poseidon_to_curve;
+use crate::utils::hash_to_curve;
 use halo2_gadgets::{
     ecc::{chip::EccChip, FixedPoint, NonIdentityPoint, Point, ScalarFixed, ScalarVar},
     poseidon::Pow5Config as PoseidonConfig,
@@ -19,7 +19,7 @@ use halo2_proofs::{
     circuit::{AssignedCell, Layouter, Value},
     plonk::{Advice, Column, Error, Instance},
 };
-use pasta_curves::group::Curve;
+use pasta_curves::group::{ff::PrimeField, Curve};
 use pasta_curves::pallas;
 use std::ops::Neg;
 
@@ -93,7 +93,7 @@ pub fn check_input_resource(
         Value::known(input_resource.get_label()),
     )?;
 
-    // Witness and range check the quantity(u64)
+    // Witness and range check the quantity(u128)
     let quantity = quantity_range_check(
         layouter.namespace(|| "quantity range check"),
         resource_commit_chip.get_lookup_config(),
@@ -138,6 +138,14 @@ pub fn check_input_resource(
         Value::known(pallas::Base::from(input_resource.is_ephemeral)),
     )?;
 
+    // Witness is_transparent_kind
+    // is_transparent_kind will be boolean-constrained in the resource_commit.
+    let is_transparent_kind = assign_free_advice(
+        layouter.namespace(|| "witness is_transparent_kind"),
+        advices[0],
+        Value::known(pallas::Base::from(input_resource.is_transparent_kind)),
+    )?;
+
     // Check resource commitment
     let cm = resource_commit(
         layouter.namespace(|| "resource commitment"),
@@ -150,6 +158,7 @@ pub fn check_input_resource(
         psi.clone(),
         quantity.clone(),
         is_ephemeral.clone(),
+        is_transparent_kind.clone(),
         rcm.clone(),
     )?;
 
@@ -171,6 +180,7 @@ pub fn check_input_resource(
         quantity,
         label,
         is_ephemeral,
+        is_transparent_kind,
         value,
         nonce,
         npk,
@@ -222,7 +232,7 @@ pub fn check_output_resource(
         Value::known(output_resource.get_label()),
     )?;
 
-    // Witness and range check the quantity(u64)
+    // Witness and range check the quantity(u128)
     let quantity = quantity_range_check(
         layouter.namespace(|| "quantity range check"),
         resource_commit_chip.get_lookup_config(),
@@ -289,6 +299,14 @@ pub fn check_output_resource(
         Value::known(pallas::Base::from(output_resource.is_ephemeral)),
     )?;
 
+    // Witness is_transparent_kind
+    // is_transparent_kind will be boolean-constrained in the resource_commit.
+    let is_transparent_kind = assign_free_advice(
+        layouter.namespace(|| "witness is_transparent_kind"),
+        advices[0],
+        Value::known(pallas::Base::from(output_resource.is_transparent_kind)),
+    )?;
+
     // Check resource commitment
     let cm = resource_commit(
         layouter.namespace(|| "resource commitment"),
@@ -301,6 +319,7 @@ pub fn check_output_resource(
         psi.clone(),
         quantity.clone(),
         is_ephemeral.clone(),
+        is_transparent_kind.clone(),
         rcm.clone(),
     )?;
 
@@ -312,6 +331,7 @@ pub fn check_output_resource(
         label,
         quantity,
         is_ephemeral,
+        is_transparent_kind,
         value,
         nonce: old_nf,
         npk,
@@ -324,25 +344,40 @@ pub fn check_output_resource(
     })
 }
 
+/// Derives the kind point of the resource, in the `TRANSPARENT_RESOURCE_KIND_DOMAIN` if
+/// is_transparent_kind is set and in the `RESOURCE_KIND_DOMAIN` otherwise. The caller constrains
+/// the postfixes to the ones of the domain picked by is_transparent_kind.
 pub fn derive_kind(
     mut layouter: impl Layouter<pallas::Base>,
     hash_to_curve_config: HashToCurveConfig,
     ecc_chip: EccChip<TaigaFixedBases>,
     logic: AssignedCell<pallas::Base, pallas::Base>,
     label: AssignedCell<pallas::Base, pallas::Base>,
+    is_transparent_kind: &AssignedCell<pallas::Base, pallas::Base>,
+    kind_domain_postfixes: [AssignedCell<pallas::Base, pallas::Base>; 2],
 ) -> Result<NonIdentityPoint<pallas::Affine, EccChip<TaigaFixedBases>>, Error> {
-    let point = hash_to_curve_circuit(
+    let point = hash_to_curve_with_postfixes_gadget(
         layouter.namespace(|| "hash to curve"),
         hash_to_curve_config,
         ecc_chip.clone(),
-        &[logic.clone(), label.clone()],
+        kind_domain_postfixes,
+        [logic.clone(), label.clone()],
     )?;
 
     // Assign a new `NonIdentityPoint` and constran equal to hash_to_curve point since `Point` doesn't have mul operation
     // IndentityPoint is an invalid resource kind and it returns an error.
-    let non_identity_point = logic.value().zip(label.value()).map(|(&vk, &data)| {
-        poseidon_to_curve::<POSEIDON_TO_CURVE_INPUT_LEN>(&[vk, data]).to_affine()
-    });
+    let non_identity_point = logic
+        .value()
+        .zip(label.value())
+        .zip(is_transparent_kind.value())
+        .map(|((&vk, &data), &is_transparent_kind)| {
+            let domain = if is_transparent_kind == pallas::Base::one() {
+                &*TRANSPARENT_RESOURCE_KIND_DOMAIN
+            } else {
+                &*RESOURCE_KIND_DOMAIN
+            };
+            hash_to_curve(domain, [vk, data]).to_affine()
+        });
     let non_identity_point_var = NonIdentityPoint::new(
         ecc_chip,
         layouter.namespace(|| "non-identity resource kind"),
@@ -360,12 +395,10 @@ pub fn compute_delta_commitment(
     mut layouter: impl Layouter<pallas::Base>,
     ecc_chip: EccChip<TaigaFixedBases>,
     hash_to_curve_config: HashToCurveConfig,
-    input_logic: AssignedCell<pallas::Base, pallas::Base>,
-    input_label: AssignedCell<pallas::Base, pallas::Base>,
-    input_quantity: AssignedCell<pallas::Base, pallas::Base>,
-    output_logic: AssignedCell<pallas::Base, pallas::Base>,
-    output_label: AssignedCell<pallas::Base, pallas::Base>,
-    output_quantity: AssignedCell<pallas::Base, pallas::Base>,
+    input_resource: &ResourceVariables,
+    input_kind_domain_postfixes: [AssignedCell<pallas::Base, pallas::Base>; 2],
+    output_resource: &ResourceVariables,
+    output_kind_domain_postfixes: [AssignedCell<pallas::Base, pallas::Base>; 2],
     rcv: pallas::Scalar,
 ) -> Result<Point<pallas::Affine, EccChip<TaigaFixedBases>>, Error> {
     // input value base point
@@ -373,13 +406,15 @@ pub fn compute_delta_commitment(
         layouter.namespace(|| "derive input resource kind"),
         hash_to_curve_config.clone(),
         ecc_chip.clone(),
-        input_logic,
-        input_label,
+        input_resource.logic.clone(),
+        input_resource.label.clone(),
+        &input_resource.is_transparent_kind,
+        input_kind_domain_postfixes,
     )?;
     let v_input_scalar = ScalarVar::from_base(
         ecc_chip.clone(),
         layouter.namespace(|| "ScalarVar from_base"),
-        &input_quantity,
+        &input_resource.quantity,
     )?;
     let (value_point_input, _) =
         input_kind.mul(layouter.namespace(|| "input value point"), v_input_scalar)?;
@@ -389,13 +424,15 @@ pub fn compute_delta_commitment(
         layouter.namespace(|| "derive output resource kind"),
         hash_to_curve_config,
         ecc_chip.clone(),
-        output_logic,
-        output_label,
+        output_resource.logic.clone(),
+        output_resource.label.clone(),
+        &output_resource.is_transparent_kind,
+        output_kind_domain_postfixes,
     )?;
     let v_output_scalar = ScalarVar::from_base(
         ecc_chip.clone(),
         layouter.namespace(|| "ScalarVar from_base"),
-        &output_quantity,
+        &output_resource.quantity,
     )?;
     let (value_point_output, _) =
         output_kind.mul(layouter.namespace(|| "output value point"), v_output_scalar)?;
@@ -442,22 +479,25 @@ pub fn compute_delta_commitment(
     commitment_v.add(layouter.namespace(|| "delta commitment"), &blind)
 }
 
+// Decompose the quantity into 12 10-bit words and an 8-bit word.
+// u64 quantities are a special case, applications that rely on them (e.g. to multiply
+// quantities without wrapping) need to range check the owned resource quantity themselves.
 fn quantity_range_check<const K: usize>(
     mut layouter: impl Layouter<pallas::Base>,
     lookup_config: &LookupRangeCheckConfig<pallas::Base, K>,
-    quantity: u64,
+    quantity: u128,
 ) -> Result<AssignedCell<pallas::Base, pallas::Base>, Error> {
     let zs = lookup_config.witness_check(
-        layouter.namespace(|| "6 * K(10) bits range check"),
-        Value::known(pallas::Base::from(quantity)),
-        6,
+        layouter.namespace(|| "12 * K(10) bits range check"),
+        Value::known(pallas::Base::from_u128(quantity)),
+        12,
         false,
     )?;
 
     lookup_config.copy_short_check(
-        layouter.namespace(|| "4 bits range check"),
-        zs[6].clone(),
-        4,
+        layouter.namespace(|| "8 bits range check"),
+        zs[12].clone(),
+        8,
     )?;
 
     Ok(zs[0].clone())
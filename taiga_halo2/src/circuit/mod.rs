@@ -1,14 +1,27 @@
+// The chips, gadgets and in-circuit primitives are only documented with the circuit-internals
+// feature, resource logics are written against resource_logic_circuit.
+#[cfg_attr(not(feature = "circuit-internals"), doc(hidden))]
 pub mod compliance_circuit;
+#[cfg_attr(not(feature = "circuit-internals"), doc(hidden))]
 pub mod gadgets;
+#[cfg_attr(not(feature = "circuit-internals"), doc(hidden))]
 pub mod integrity;
-pub mod merkle_circuit;
 #[macro_use]
 pub mod resource_logic_circuit;
+#[cfg_attr(not(feature = "circuit-internals"), doc(hidden))]
 pub mod blake2s;
+pub mod constraint_report;
+#[cfg_attr(not(feature = "circuit-internals"), doc(hidden))]
 pub mod curve;
+#[cfg_attr(not(feature = "circuit-internals"), doc(hidden))]
 pub mod hash_to_curve;
+#[cfg_attr(not(feature = "circuit-internals"), doc(hidden))]
 pub mod resource_commitment;
+#[cfg_attr(not(feature = "circuit-internals"), doc(hidden))]
 pub mod resource_encryption_circuit;
 pub mod resource_logic_bytecode;
 pub mod resource_logic_examples;
+// The named input parsing is only used by the vamp-ir circuits of the prover
+#[cfg_attr(not(feature = "prover"), allow(dead_code))]
 mod vamp_ir_utils;
+pub mod verify_failure;
@@ -11,13 +11,14 @@ use halo2_proofs::{
 };
 use pasta_curves::pallas;
 
-/// compose = is_ephemeral(bool) * 2^128 + quantity(64 bits)
+/// compose = is_transparent_kind(bool) * 2^129 + is_ephemeral(bool) * 2^128 + quantity(128 bits)
 #[derive(Clone, Debug)]
 struct ComposeIsEphemeralQuantity {
     q_compose: Selector,
     col_l: Column<Advice>,
     col_m: Column<Advice>,
     col_r: Column<Advice>,
+    col_flag: Column<Advice>,
 }
 
 impl ComposeIsEphemeralQuantity {
@@ -26,6 +27,7 @@ impl ComposeIsEphemeralQuantity {
         col_l: Column<Advice>,
         col_m: Column<Advice>,
         col_r: Column<Advice>,
+        col_flag: Column<Advice>,
         two_pow_128: pallas::Base,
     ) -> Self {
         let q_compose = meta.selector();
@@ -36,15 +38,22 @@ impl ComposeIsEphemeralQuantity {
             let compose_is_ephemeral_and_quantity = meta.query_advice(col_l, Rotation::cur());
             let is_ephemeral = meta.query_advice(col_m, Rotation::cur());
             let quantity = meta.query_advice(col_r, Rotation::cur());
+            let is_transparent_kind = meta.query_advice(col_flag, Rotation::cur());
 
-            // e = quantity + (2^128) * is_ephemeral
-            let composition_check =
-                compose_is_ephemeral_and_quantity - (quantity + is_ephemeral.clone() * two_pow_128);
+            // e = quantity + (2^128) * is_ephemeral + (2^129) * is_transparent_kind
+            let composition_check = compose_is_ephemeral_and_quantity
+                - (quantity
+                    + is_ephemeral.clone() * two_pow_128
+                    + is_transparent_kind.clone() * two_pow_128.double());
 
             Constraints::with_selector(
                 q_compose,
                 [
                     ("bool_check is_ephemeral", bool_check(is_ephemeral)),
+                    (
+                        "bool_check is_transparent_kind",
+                        bool_check(is_transparent_kind),
+                    ),
                     ("composition", composition_check),
                 ],
             )
@@ -55,6 +64,7 @@ impl ComposeIsEphemeralQuantity {
             col_l,
             col_m,
             col_r,
+            col_flag,
         }
     }
 
@@ -63,21 +73,31 @@ impl ComposeIsEphemeralQuantity {
         layouter: &mut impl Layouter<pallas::Base>,
         is_ephemeral: &AssignedCell<pallas::Base, pallas::Base>,
         quantity: &AssignedCell<pallas::Base, pallas::Base>,
+        is_transparent_kind: &AssignedCell<pallas::Base, pallas::Base>,
     ) -> Result<AssignedCell<pallas::Base, pallas::Base>, Error> {
         layouter.assign_region(
             || "Compose is_ephemeral and quantity",
             |mut region| {
                 self.q_compose.enable(&mut region, 0)?;
 
-                let compose =
-                    is_ephemeral
-                        .value()
-                        .zip(quantity.value())
-                        .map(|(is_ephemeral, quantity)| {
-                            quantity + is_ephemeral * pallas::Base::from_u128(1 << 64).square()
-                        });
+                let two_pow_128 = pallas::Base::from_u128(1 << 64).square();
+                let compose = is_ephemeral
+                    .value()
+                    .zip(quantity.value())
+                    .zip(is_transparent_kind.value())
+                    .map(|((is_ephemeral, quantity), is_transparent_kind)| {
+                        quantity
+                            + is_ephemeral * two_pow_128
+                            + is_transparent_kind * two_pow_128.double()
+                    });
                 is_ephemeral.copy_advice(|| "is_ephemeral", &mut region, self.col_m, 0)?;
                 quantity.copy_advice(|| "quantity", &mut region, self.col_r, 0)?;
+                is_transparent_kind.copy_advice(
+                    || "is_transparent_kind",
+                    &mut region,
+                    self.col_flag,
+                    0,
+                )?;
 
                 region.assign_advice(|| "compose", self.col_l, 0, || compose)
             },
@@ -100,7 +120,7 @@ pub struct ResourceCommitChip {
 impl ResourceCommitChip {
     pub fn configure(
         meta: &mut ConstraintSystem<pallas::Base>,
-        advices: [Column<Advice>; 3],
+        advices: [Column<Advice>; 4],
         poseidon_config: PoseidonConfig<pallas::Base, 3, 2>,
         lookup_config: LookupRangeCheckConfig<pallas::Base, 10>,
     ) -> ResourceCommitConfig {
@@ -110,6 +130,7 @@ impl ResourceCommitChip {
             advices[0],
             advices[1],
             advices[2],
+            advices[3],
             two_pow_128,
         );
 
@@ -145,13 +166,16 @@ pub fn resource_commit(
     psi: AssignedCell<pallas::Base, pallas::Base>,
     quantity: AssignedCell<pallas::Base, pallas::Base>,
     is_ephemeral: AssignedCell<pallas::Base, pallas::Base>,
+    is_transparent_kind: AssignedCell<pallas::Base, pallas::Base>,
     rcm: AssignedCell<pallas::Base, pallas::Base>,
 ) -> Result<AssignedCell<pallas::Base, pallas::Base>, Error> {
-    // Compose the quantity and is_ephemeral to one field in order to save one poseidon absorb
-    let compose_is_ephemeral_and_quantity =
-        chip.config
-            .compose_config
-            .assign(&mut layouter, &is_ephemeral, &quantity)?;
+    // Compose the quantity and the flags to one field in order to save poseidon absorbs
+    let compose_is_ephemeral_and_quantity = chip.config.compose_config.assign(
+        &mut layouter,
+        &is_ephemeral,
+        &quantity,
+        &is_transparent_kind,
+    )?;
 
     // resource commitment
     let poseidon_message = [
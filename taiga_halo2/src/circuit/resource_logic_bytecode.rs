@@ -1,36 +1,63 @@
+#[cfg(feature = "example-bridge")]
+use crate::circuit::resource_logic_examples::bridge::BridgeResourceLogicCircuit;
+#[cfg(feature = "example-migration")]
+use crate::circuit::resource_logic_examples::migration::MigrationResourceLogicCircuit;
+#[cfg(feature = "example-payment-channel")]
+use crate::circuit::resource_logic_examples::payment_channel::PaymentChannelResourceLogicCircuit;
+#[cfg(feature = "example-randomness-beacon")]
+use crate::circuit::resource_logic_examples::randomness_beacon::RandomnessBeaconResourceLogicCircuit;
+#[cfg(feature = "example-receiver")]
+use crate::circuit::resource_logic_examples::receiver_resource_logic::ReceiverResourceLogicCircuit;
+#[cfg(feature = "example-signature-verification")]
+use crate::circuit::resource_logic_examples::signature_verification::SignatureVerificationResourceLogicCircuit;
+#[cfg(feature = "example-subscription")]
+use crate::circuit::resource_logic_examples::subscription::SubscriptionResourceLogicCircuit;
+#[cfg(feature = "example-token")]
+use crate::circuit::resource_logic_examples::token::TokenResourceLogicCircuit;
 #[cfg(feature = "borsh")]
 use crate::circuit::resource_logic_examples::TrivialResourceLogicCircuit;
-#[cfg(feature = "examples")]
+#[cfg(feature = "example-intents")]
 use crate::circuit::resource_logic_examples::{
     cascade_intent::CascadeIntentResourceLogicCircuit,
     or_relation_intent::OrRelationIntentResourceLogicCircuit,
     partial_fulfillment_intent::PartialFulfillmentIntentResourceLogicCircuit,
-    receiver_resource_logic::ReceiverResourceLogicCircuit,
-    signature_verification::SignatureVerificationResourceLogicCircuit,
-    token::TokenResourceLogicCircuit,
 };
 use crate::error::TransactionError;
-use crate::shielded_ptx::ResourceLogicVerifyingInfoSet;
+#[cfg(feature = "borsh")]
+use crate::resource::Resource;
 use crate::{
-    circuit::resource_logic_circuit::{
-        ResourceLogicVerifyingInfo, ResourceLogicVerifyingInfoTrait, VampIRResourceLogicCircuit,
-    },
+    circuit::resource_logic_circuit::{ResourceLogicPublicInputs, ResourceLogicVerifyingInfoTrait},
+    circuit::vamp_ir_utils::encode_named_assignments,
     constant::{
+        MAX_DYNAMIC_RESOURCE_LOGIC_NUM, NUM_RESOURCE,
         RESOURCE_LOGIC_CIRCUIT_NULLIFIER_ONE_PUBLIC_INPUT_IDX,
         RESOURCE_LOGIC_CIRCUIT_NULLIFIER_TWO_PUBLIC_INPUT_IDX,
         RESOURCE_LOGIC_CIRCUIT_OUTPUT_CM_ONE_PUBLIC_INPUT_IDX,
         RESOURCE_LOGIC_CIRCUIT_OUTPUT_CM_TWO_PUBLIC_INPUT_IDX,
-        RESOURCE_LOGIC_CIRCUIT_OWNED_RESOURCE_ID_PUBLIC_INPUT_IDX,
     },
     nullifier::Nullifier,
-    resource::ResourceCommitment,
+    resource::{OwnedResourceId, ResourceCommitment},
+};
+#[cfg(feature = "prover")]
+use crate::{
+    circuit::resource_logic_circuit::{
+        ResourceLogicVerifyingInfo, VampIRCircuitError, VampIRResourceLogicCircuit,
+    },
+    circuit::vamp_ir_utils::decode_named_assignments,
+    constant::DEFAULT_PARAMS_STORE,
+    shielded_ptx::ResourceLogicVerifyingInfoSet,
 };
 
 #[cfg(feature = "borsh")]
 use borsh::{BorshDeserialize, BorshSerialize};
 use pasta_curves::pallas;
+#[cfg(feature = "prover")]
+use rand::RngCore;
 #[cfg(feature = "serde")]
 use serde;
+use std::collections::HashMap;
+use std::fmt;
+#[cfg(feature = "prover")]
 use std::path::PathBuf;
 
 #[derive(Clone, Debug)]
@@ -40,6 +67,8 @@ pub enum ResourceLogicRepresentation {
     // vampir has a unified circuit representation.
     VampIR(Vec<u8>),
     // Native halo2 circuits don't have a unified representatioin, enumerate the resource_logic circuit examples for the moment.
+    // The variants are not feature gated to keep the serialization stable, a variant whose
+    // example feature is disabled is rejected with InvalidResourceLogicRepresentation.
     // TODO: figure out if we can have a unified circuit presentation. In theory, it's possible to separate the circuit system and proving system.
     Trivial,
     Token,
@@ -48,9 +77,109 @@ pub enum ResourceLogicRepresentation {
     PartialFulfillmentIntent,
     OrRelationIntent,
     CascadeIntent,
+    Bridge,
+    PaymentChannel,
+    RandomnessBeacon,
+    Migration,
+    Subscription,
     // Add other native resource_logic types here if needed
 }
 
+// The version of the native resource logic input encoding, the first byte of the inputs. Version
+// 2 tags the fields, see `resource_logic_circuit_encoding_impl`, version 3 encodes the owned
+// resource id with its kind.
+pub const NATIVE_RESOURCE_LOGIC_INPUTS_VERSION: u8 = 3;
+// The borsh encoding of a native circuit starts with the tagged owned resource id (its kind and
+// 32 bytes) and input and output resources (210 bytes each)
+pub const MIN_NATIVE_RESOURCE_LOGIC_INPUTS_LEN: usize = 4 + 32 + 2 * NUM_RESOURCE * 210;
+// Bounds the variable parts of the native circuits, e.g. the token names
+pub const MAX_NATIVE_RESOURCE_LOGIC_INPUTS_LEN: usize = 1 << 14;
+
+/// The expected shape of the inputs of a native resource logic representation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResourceLogicInputSchema {
+    pub version: u8,
+    pub min_len: usize,
+    pub max_len: usize,
+}
+
+/// The inputs of a resource logic bytecode don't match the schema of its representation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MalformedLogicInputsError {
+    /// The inputs are empty, the version byte is missing.
+    MissingVersion,
+    /// The inputs are encoded with an unsupported version.
+    UnsupportedVersion(u8),
+    /// The length of the encoding is out of the bounds of the schema.
+    InvalidLength {
+        len: usize,
+        min_len: usize,
+        max_len: usize,
+    },
+    /// The encoding doesn't decode to the circuit of the representation.
+    InvalidEncoding(String),
+}
+
+impl fmt::Display for MalformedLogicInputsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MalformedLogicInputsError::MissingVersion => write!(f, "the version is missing"),
+            MalformedLogicInputsError::UnsupportedVersion(version) => {
+                write!(f, "version {version} is not supported")
+            }
+            MalformedLogicInputsError::InvalidLength {
+                len,
+                min_len,
+                max_len,
+            } => write!(
+                f,
+                "length {len} is out of the bounds [{min_len}, {max_len}]"
+            ),
+            MalformedLogicInputsError::InvalidEncoding(e) => write!(f, "invalid encoding: {e}"),
+        }
+    }
+}
+
+impl ResourceLogicRepresentation {
+    /// The name of the logic, as registered in the vk registry for the native logics.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ResourceLogicRepresentation::VampIR(_) => "VampIR",
+            ResourceLogicRepresentation::Trivial => "Trivial",
+            ResourceLogicRepresentation::Token => "Token",
+            ResourceLogicRepresentation::SignatureVerification => "SignatureVerification",
+            ResourceLogicRepresentation::Receiver => "Receiver",
+            ResourceLogicRepresentation::PartialFulfillmentIntent => "PartialFulfillmentIntent",
+            ResourceLogicRepresentation::OrRelationIntent => "OrRelationIntent",
+            ResourceLogicRepresentation::CascadeIntent => "CascadeIntent",
+            ResourceLogicRepresentation::Bridge => "Bridge",
+            ResourceLogicRepresentation::PaymentChannel => "PaymentChannel",
+            ResourceLogicRepresentation::RandomnessBeacon => "RandomnessBeacon",
+            ResourceLogicRepresentation::Migration => "Migration",
+            ResourceLogicRepresentation::Subscription => "Subscription",
+        }
+    }
+
+    /// The schema of the inputs. None for VampIR, whose named assignments are checked against
+    /// the circuit when decoded.
+    pub fn input_schema(&self) -> Option<ResourceLogicInputSchema> {
+        match self {
+            ResourceLogicRepresentation::VampIR(_) => None,
+            // The trivial circuit is exactly the common prefix
+            ResourceLogicRepresentation::Trivial => Some(ResourceLogicInputSchema {
+                version: NATIVE_RESOURCE_LOGIC_INPUTS_VERSION,
+                min_len: MIN_NATIVE_RESOURCE_LOGIC_INPUTS_LEN,
+                max_len: MIN_NATIVE_RESOURCE_LOGIC_INPUTS_LEN,
+            }),
+            _ => Some(ResourceLogicInputSchema {
+                version: NATIVE_RESOURCE_LOGIC_INPUTS_VERSION,
+                min_len: MIN_NATIVE_RESOURCE_LOGIC_INPUTS_LEN,
+                max_len: MAX_NATIVE_RESOURCE_LOGIC_INPUTS_LEN,
+            }),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -68,127 +197,273 @@ pub struct ApplicationByteCode {
 }
 
 impl ResourceLogicByteCode {
+    /// The inputs are the borsh encoding of a native circuit, prefixed with the version of its
+    /// schema here, or the encoded named assignments of a VampIR circuit.
     pub fn new(circuit: ResourceLogicRepresentation, inputs: Vec<u8>) -> Self {
+        let inputs = match circuit.input_schema() {
+            Some(schema) => [vec![schema.version], inputs].concat(),
+            None => inputs,
+        };
         Self { circuit, inputs }
     }
 
-    pub fn generate_proof(self) -> Result<ResourceLogicVerifyingInfo, TransactionError> {
+    /// Decodes the inputs of a native circuit, validating them against the schema of the
+    /// representation first: the inputs may be attacker-supplied.
+    #[cfg(feature = "borsh")]
+    fn decode_native_inputs<C: BorshDeserialize>(&self) -> Result<C, TransactionError> {
+        let schema = self
+            .circuit
+            .input_schema()
+            .ok_or(TransactionError::InvalidResourceLogicRepresentation)?;
+        let (version, encoding) =
+            self.inputs
+                .split_first()
+                .ok_or(TransactionError::MalformedLogicInputs(
+                    MalformedLogicInputsError::MissingVersion,
+                ))?;
+        if *version != schema.version {
+            return Err(TransactionError::MalformedLogicInputs(
+                MalformedLogicInputsError::UnsupportedVersion(*version),
+            ));
+        }
+        if encoding.len() < schema.min_len || encoding.len() > schema.max_len {
+            return Err(TransactionError::MalformedLogicInputs(
+                MalformedLogicInputsError::InvalidLength {
+                    len: encoding.len(),
+                    min_len: schema.min_len,
+                    max_len: schema.max_len,
+                },
+            ));
+        }
+        // try_from_slice also rejects trailing bytes
+        C::try_from_slice(encoding).map_err(|e| {
+            TransactionError::MalformedLogicInputs(MalformedLogicInputsError::InvalidEncoding(
+                e.to_string(),
+            ))
+        })
+    }
+
+    /// A VampIR resource logic from the circuit file and the named assignments of its wires.
+    pub fn new_vamp_ir(
+        circuit_file: &str,
+        named_assignments: &HashMap<String, pallas::Base>,
+    ) -> Self {
+        Self {
+            circuit: ResourceLogicRepresentation::VampIR(circuit_file.as_bytes().to_vec()),
+            inputs: encode_named_assignments(named_assignments),
+        }
+    }
+
+    #[cfg(feature = "prover")]
+    fn vamp_ir_circuit(
+        circuit: &[u8],
+        inputs: &[u8],
+    ) -> Result<VampIRResourceLogicCircuit, TransactionError> {
+        // TDDO: use the file_name api atm,
+        // request vamp_ir to provide a api to generate circuit from bytes.
+        let vamp_ir_circuit_file = PathBuf::from(String::from_utf8_lossy(circuit).to_string());
+        let named_assignments = decode_named_assignments(inputs).map_err(|report| {
+            TransactionError::VampIR(VampIRCircuitError::InvalidWitness(report))
+        })?;
+        VampIRResourceLogicCircuit::from_vamp_ir_file_with_assignments(
+            &vamp_ir_circuit_file,
+            named_assignments,
+        )
+        .map_err(TransactionError::VampIR)
+    }
+
+    /// Proves the resource logic. The bytecode may come from untrusted input, so malformed
+    /// inputs, e.g. an owned resource id matching no resource, are returned as errors.
+    #[cfg(feature = "prover")]
+    pub fn generate_proof<R: RngCore>(
+        self,
+        mut rng: R,
+    ) -> Result<ResourceLogicVerifyingInfo, TransactionError> {
         match self.circuit {
             ResourceLogicRepresentation::VampIR(circuit) => {
-                // TDDO: use the file_name api atm,
-                // request vamp_ir to provide a api to generate circuit from bytes.
-                let vamp_ir_circuit_file =
-                    PathBuf::from(String::from_utf8_lossy(&circuit).to_string());
-                let inputs_file = PathBuf::from(String::from_utf8_lossy(&self.inputs).to_string());
-                let resource_logic_circuit = VampIRResourceLogicCircuit::from_vamp_ir_file(
-                    &vamp_ir_circuit_file,
-                    &inputs_file,
-                );
-                Ok(resource_logic_circuit.get_verifying_info())
+                let resource_logic_circuit = Self::vamp_ir_circuit(&circuit, &self.inputs)?;
+                resource_logic_circuit
+                    .create_verifying_info(&resource_logic_circuit.params, &mut rng)
             }
             #[cfg(feature = "borsh")]
             ResourceLogicRepresentation::Trivial => {
-                let resource_logic = TrivialResourceLogicCircuit::from_bytes(&self.inputs);
-                Ok(resource_logic.get_verifying_info())
+                let resource_logic = self.decode_native_inputs::<TrivialResourceLogicCircuit>()?;
+                resource_logic.get_verifying_info_with_params_store(&DEFAULT_PARAMS_STORE, &mut rng)
             }
-            #[cfg(feature = "examples")]
+            #[cfg(feature = "example-token")]
             ResourceLogicRepresentation::Token => {
-                let resource_logic = TokenResourceLogicCircuit::from_bytes(&self.inputs);
-                Ok(resource_logic.get_verifying_info())
+                let resource_logic = self.decode_native_inputs::<TokenResourceLogicCircuit>()?;
+                resource_logic.get_verifying_info_with_params_store(&DEFAULT_PARAMS_STORE, &mut rng)
             }
-            #[cfg(feature = "examples")]
+            #[cfg(feature = "example-signature-verification")]
             ResourceLogicRepresentation::SignatureVerification => {
                 let resource_logic =
-                    SignatureVerificationResourceLogicCircuit::from_bytes(&self.inputs);
-                Ok(resource_logic.get_verifying_info())
+                    self.decode_native_inputs::<SignatureVerificationResourceLogicCircuit>()?;
+                resource_logic.get_verifying_info_with_params_store(&DEFAULT_PARAMS_STORE, &mut rng)
             }
-            #[cfg(feature = "examples")]
+            #[cfg(feature = "example-receiver")]
             ResourceLogicRepresentation::Receiver => {
-                let resource_logic = ReceiverResourceLogicCircuit::from_bytes(&self.inputs);
-                Ok(resource_logic.get_verifying_info())
+                let resource_logic = self.decode_native_inputs::<ReceiverResourceLogicCircuit>()?;
+                resource_logic.get_verifying_info_with_params_store(&DEFAULT_PARAMS_STORE, &mut rng)
             }
-            #[cfg(feature = "examples")]
+            #[cfg(feature = "example-intents")]
             ResourceLogicRepresentation::PartialFulfillmentIntent => {
                 let resource_logic =
-                    PartialFulfillmentIntentResourceLogicCircuit::from_bytes(&self.inputs);
-                Ok(resource_logic.get_verifying_info())
+                    self.decode_native_inputs::<PartialFulfillmentIntentResourceLogicCircuit>()?;
+                resource_logic.get_verifying_info_with_params_store(&DEFAULT_PARAMS_STORE, &mut rng)
             }
-            #[cfg(feature = "examples")]
+            #[cfg(feature = "example-intents")]
             ResourceLogicRepresentation::OrRelationIntent => {
-                let resource_logic = OrRelationIntentResourceLogicCircuit::from_bytes(&self.inputs);
-                Ok(resource_logic.get_verifying_info())
+                let resource_logic =
+                    self.decode_native_inputs::<OrRelationIntentResourceLogicCircuit>()?;
+                resource_logic.get_verifying_info_with_params_store(&DEFAULT_PARAMS_STORE, &mut rng)
             }
-            #[cfg(feature = "examples")]
+            #[cfg(feature = "example-intents")]
             ResourceLogicRepresentation::CascadeIntent => {
-                let resource_logic = CascadeIntentResourceLogicCircuit::from_bytes(&self.inputs);
-                Ok(resource_logic.get_verifying_info())
+                let resource_logic =
+                    self.decode_native_inputs::<CascadeIntentResourceLogicCircuit>()?;
+                resource_logic.get_verifying_info_with_params_store(&DEFAULT_PARAMS_STORE, &mut rng)
+            }
+            #[cfg(feature = "example-bridge")]
+            ResourceLogicRepresentation::Bridge => {
+                let resource_logic = self.decode_native_inputs::<BridgeResourceLogicCircuit>()?;
+                resource_logic.get_verifying_info_with_params_store(&DEFAULT_PARAMS_STORE, &mut rng)
+            }
+            #[cfg(feature = "example-payment-channel")]
+            ResourceLogicRepresentation::PaymentChannel => {
+                let resource_logic =
+                    self.decode_native_inputs::<PaymentChannelResourceLogicCircuit>()?;
+                resource_logic.get_verifying_info_with_params_store(&DEFAULT_PARAMS_STORE, &mut rng)
+            }
+            #[cfg(feature = "example-randomness-beacon")]
+            ResourceLogicRepresentation::RandomnessBeacon => {
+                let resource_logic =
+                    self.decode_native_inputs::<RandomnessBeaconResourceLogicCircuit>()?;
+                resource_logic.get_verifying_info_with_params_store(&DEFAULT_PARAMS_STORE, &mut rng)
+            }
+            #[cfg(feature = "example-migration")]
+            ResourceLogicRepresentation::Migration => {
+                let resource_logic =
+                    self.decode_native_inputs::<MigrationResourceLogicCircuit>()?;
+                resource_logic.get_verifying_info_with_params_store(&DEFAULT_PARAMS_STORE, &mut rng)
+            }
+            #[cfg(feature = "example-subscription")]
+            ResourceLogicRepresentation::Subscription => {
+                let resource_logic =
+                    self.decode_native_inputs::<SubscriptionResourceLogicCircuit>()?;
+                resource_logic.get_verifying_info_with_params_store(&DEFAULT_PARAMS_STORE, &mut rng)
             }
             #[allow(unreachable_patterns)]
             _ => Err(TransactionError::InvalidResourceLogicRepresentation),
         }
     }
 
+    pub fn representation(&self) -> &ResourceLogicRepresentation {
+        &self.circuit
+    }
+
     // Verify resource_logic circuit transparently and return owned resource PubID for further checking
     pub fn verify_transparently(
         &self,
         compliance_nfs: &[Nullifier],
         compliance_cms: &[ResourceCommitment],
-    ) -> Result<pallas::Base, TransactionError> {
-        // check resource logic transparently
+    ) -> Result<OwnedResourceId, TransactionError> {
+        let public_inputs = self.transparent_public_inputs()?;
+        Self::check_transparent_public_inputs(&public_inputs, compliance_nfs, compliance_cms)
+    }
+
+    // Check the resource logic constraints transparently and return its public inputs
+    pub(crate) fn transparent_public_inputs(
+        &self,
+    ) -> Result<ResourceLogicPublicInputs, TransactionError> {
         let public_inputs = match &self.circuit {
+            // The vamp-ir circuits are compiled with the prover
+            #[cfg(feature = "prover")]
             ResourceLogicRepresentation::VampIR(circuit) => {
-                // TDDO: use the file_name api atm,
-                // request vamp_ir to provide a api to generate circuit from bytes.
-                let vamp_ir_circuit_file =
-                    PathBuf::from(String::from_utf8_lossy(circuit).to_string());
-                let inputs_file = PathBuf::from(String::from_utf8_lossy(&self.inputs).to_string());
-                let resource_logic_circuit = VampIRResourceLogicCircuit::from_vamp_ir_file(
-                    &vamp_ir_circuit_file,
-                    &inputs_file,
-                );
+                let resource_logic_circuit = Self::vamp_ir_circuit(circuit, &self.inputs)?;
                 resource_logic_circuit.verify_transparently()?
             }
             #[cfg(feature = "borsh")]
             ResourceLogicRepresentation::Trivial => {
-                let resource_logic = TrivialResourceLogicCircuit::from_bytes(&self.inputs);
+                let resource_logic = self.decode_native_inputs::<TrivialResourceLogicCircuit>()?;
                 resource_logic.verify_transparently()?
             }
-            #[cfg(feature = "examples")]
+            #[cfg(feature = "example-token")]
             ResourceLogicRepresentation::Token => {
-                let resource_logic = TokenResourceLogicCircuit::from_bytes(&self.inputs);
+                let resource_logic = self.decode_native_inputs::<TokenResourceLogicCircuit>()?;
                 resource_logic.verify_transparently()?
             }
-            #[cfg(feature = "examples")]
+            #[cfg(feature = "example-signature-verification")]
             ResourceLogicRepresentation::SignatureVerification => {
                 let resource_logic =
-                    SignatureVerificationResourceLogicCircuit::from_bytes(&self.inputs);
+                    self.decode_native_inputs::<SignatureVerificationResourceLogicCircuit>()?;
                 resource_logic.verify_transparently()?
             }
-            #[cfg(feature = "examples")]
+            #[cfg(feature = "example-receiver")]
             ResourceLogicRepresentation::Receiver => {
-                let resource_logic = ReceiverResourceLogicCircuit::from_bytes(&self.inputs);
+                let resource_logic = self.decode_native_inputs::<ReceiverResourceLogicCircuit>()?;
                 resource_logic.verify_transparently()?
             }
-            #[cfg(feature = "examples")]
+            #[cfg(feature = "example-intents")]
             ResourceLogicRepresentation::PartialFulfillmentIntent => {
                 let resource_logic =
-                    PartialFulfillmentIntentResourceLogicCircuit::from_bytes(&self.inputs);
+                    self.decode_native_inputs::<PartialFulfillmentIntentResourceLogicCircuit>()?;
                 resource_logic.verify_transparently()?
             }
-            #[cfg(feature = "examples")]
+            #[cfg(feature = "example-intents")]
             ResourceLogicRepresentation::OrRelationIntent => {
-                let resource_logic = OrRelationIntentResourceLogicCircuit::from_bytes(&self.inputs);
+                let resource_logic =
+                    self.decode_native_inputs::<OrRelationIntentResourceLogicCircuit>()?;
                 resource_logic.verify_transparently()?
             }
-            #[cfg(feature = "examples")]
+            #[cfg(feature = "example-intents")]
             ResourceLogicRepresentation::CascadeIntent => {
-                let resource_logic = CascadeIntentResourceLogicCircuit::from_bytes(&self.inputs);
+                let resource_logic =
+                    self.decode_native_inputs::<CascadeIntentResourceLogicCircuit>()?;
+                resource_logic.verify_transparently()?
+            }
+            #[cfg(feature = "example-bridge")]
+            ResourceLogicRepresentation::Bridge => {
+                let resource_logic = self.decode_native_inputs::<BridgeResourceLogicCircuit>()?;
+                resource_logic.verify_transparently()?
+            }
+            #[cfg(feature = "example-payment-channel")]
+            ResourceLogicRepresentation::PaymentChannel => {
+                let resource_logic =
+                    self.decode_native_inputs::<PaymentChannelResourceLogicCircuit>()?;
+                resource_logic.verify_transparently()?
+            }
+            #[cfg(feature = "example-randomness-beacon")]
+            ResourceLogicRepresentation::RandomnessBeacon => {
+                let resource_logic =
+                    self.decode_native_inputs::<RandomnessBeaconResourceLogicCircuit>()?;
+                resource_logic.verify_transparently()?
+            }
+            #[cfg(feature = "example-migration")]
+            ResourceLogicRepresentation::Migration => {
+                let resource_logic =
+                    self.decode_native_inputs::<MigrationResourceLogicCircuit>()?;
+                resource_logic.verify_transparently()?
+            }
+            #[cfg(feature = "example-subscription")]
+            ResourceLogicRepresentation::Subscription => {
+                let resource_logic =
+                    self.decode_native_inputs::<SubscriptionResourceLogicCircuit>()?;
                 resource_logic.verify_transparently()?
             }
             #[allow(unreachable_patterns)]
             _ => return Err(TransactionError::InvalidResourceLogicRepresentation),
         };
+        Ok(public_inputs)
+    }
 
+    // Check the resource logic uses the resources of the compliances, and return the owned
+    // resource id
+    pub(crate) fn check_transparent_public_inputs(
+        public_inputs: &ResourceLogicPublicInputs,
+        compliance_nfs: &[Nullifier],
+        compliance_cms: &[ResourceCommitment],
+    ) -> Result<OwnedResourceId, TransactionError> {
         // check nullifiers
         // Check the resource_logic actually uses the input resources from compliance circuits.
         let resource_logic_nfs = [
@@ -218,7 +493,7 @@ impl ResourceLogicByteCode {
             return Err(TransactionError::InconsistentOutputResourceCommitment);
         }
 
-        Ok(public_inputs.get_from_index(RESOURCE_LOGIC_CIRCUIT_OWNED_RESOURCE_ID_PUBLIC_INPUT_IDX))
+        Ok(public_inputs.get_owned_resource_id())
     }
 }
 
@@ -226,26 +501,84 @@ impl ApplicationByteCode {
     pub fn new(
         app_resource_logic_bytecode: ResourceLogicByteCode,
         dynamic_resource_logic_bytecode: Vec<ResourceLogicByteCode>,
-    ) -> Self {
-        Self {
+    ) -> Result<Self, TransactionError> {
+        let application = Self {
             app_resource_logic_bytecode,
             dynamic_resource_logic_bytecode,
+        };
+        application.check_dynamic_resource_logic_num()?;
+        Ok(application)
+    }
+
+    /// The application of a resource without dynamic resource logics.
+    pub fn from_application(app_resource_logic_bytecode: ResourceLogicByteCode) -> Self {
+        Self {
+            app_resource_logic_bytecode,
+            dynamic_resource_logic_bytecode: vec![],
         }
     }
 
-    pub fn generate_proofs(self) -> Result<ResourceLogicVerifyingInfoSet, TransactionError> {
+    // Create the application of an input padding resource
+    #[cfg(feature = "borsh")]
+    pub fn create_input_padding_resource_application(
+        resource: &Resource,
+        input_resources: [Resource; NUM_RESOURCE],
+        output_resources: [Resource; NUM_RESOURCE],
+    ) -> Self {
+        let owned_resource_id = resource.get_nf().unwrap().into();
+        let application_resource_logic =
+            TrivialResourceLogicCircuit::new(owned_resource_id, input_resources, output_resources);
+        Self::from_application(application_resource_logic.to_bytecode())
+    }
+
+    // Create the application of an output padding resource
+    #[cfg(feature = "borsh")]
+    pub fn create_output_padding_resource_application(
+        resource: &Resource,
+        input_resources: [Resource; NUM_RESOURCE],
+        output_resources: [Resource; NUM_RESOURCE],
+    ) -> Self {
+        let owned_resource_id = resource.commitment().into();
+        let application_resource_logic =
+            TrivialResourceLogicCircuit::new(owned_resource_id, input_resources, output_resources);
+        Self::from_application(application_resource_logic.to_bytecode())
+    }
+
+    pub fn app_resource_logic_bytecode(&self) -> &ResourceLogicByteCode {
+        &self.app_resource_logic_bytecode
+    }
+
+    pub fn dynamic_resource_logic_bytecode(&self) -> &[ResourceLogicByteCode] {
+        &self.dynamic_resource_logic_bytecode
+    }
+
+    // The bytecode may come from untrusted parties, check the cap before the heavy work
+    pub(crate) fn check_dynamic_resource_logic_num(&self) -> Result<(), TransactionError> {
+        let num = self.dynamic_resource_logic_bytecode.len();
+        if num > MAX_DYNAMIC_RESOURCE_LOGIC_NUM {
+            return Err(TransactionError::TooManyDynamicResourceLogics(num));
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "prover")]
+    pub fn generate_proofs<R: RngCore>(
+        self,
+        mut rng: R,
+    ) -> Result<ResourceLogicVerifyingInfoSet, TransactionError> {
+        self.check_dynamic_resource_logic_num()?;
         let app_resource_logic_verifying_info =
-            self.app_resource_logic_bytecode.generate_proof()?;
+            self.app_resource_logic_bytecode.generate_proof(&mut rng)?;
 
         let app_dynamic_resource_logic_verifying_info: Result<Vec<_>, _> = self
             .dynamic_resource_logic_bytecode
             .into_iter()
-            .map(|bytecode| bytecode.generate_proof())
+            .map(|bytecode| bytecode.generate_proof(&mut rng))
             .collect();
-        Ok(ResourceLogicVerifyingInfoSet::new(
+        ResourceLogicVerifyingInfoSet::new(
             app_resource_logic_verifying_info,
             app_dynamic_resource_logic_verifying_info?,
-        ))
+        )
     }
 
     // Verify resource_logic circuits transparently and return owned resource PubID for further checking
@@ -253,7 +586,8 @@ impl ApplicationByteCode {
         &self,
         compliance_nfs: &[Nullifier],
         compliance_cms: &[ResourceCommitment],
-    ) -> Result<pallas::Base, TransactionError> {
+    ) -> Result<OwnedResourceId, TransactionError> {
+        self.check_dynamic_resource_logic_num()?;
         let owned_resource_id = self
             .app_resource_logic_bytecode
             .verify_transparently(compliance_nfs, compliance_cms)?;
@@ -267,3 +601,93 @@ impl ApplicationByteCode {
         Ok(owned_resource_id)
     }
 }
+
+#[cfg(test)]
+#[cfg(feature = "borsh")]
+mod tests {
+    use super::*;
+    use crate::resource::tests::random_resource;
+    use rand::rngs::OsRng;
+
+    #[cfg(feature = "prover")]
+    #[test]
+    fn test_generate_proof_of_malformed_witness() {
+        use crate::circuit::resource_logic_circuit::ResourceLogicPrecheckError;
+
+        let mut rng = OsRng;
+        let input_resources = [(); NUM_RESOURCE].map(|_| random_resource(&mut rng));
+        let output_resources = [(); NUM_RESOURCE].map(|_| random_resource(&mut rng));
+        // The owned resource id is none of the resources, the proving is rejected with an error
+        let circuit = TrivialResourceLogicCircuit::new(
+            OwnedResourceId::Cm(ResourceCommitment::from(pallas::Base::one())),
+            input_resources,
+            output_resources,
+        );
+        assert!(matches!(
+            circuit.to_bytecode().generate_proof(&mut rng),
+            Err(TransactionError::ResourceLogicPrecheck(
+                ResourceLogicPrecheckError::UnknownOwnedResourceId
+            ))
+        ));
+    }
+    #[test]
+    fn test_native_inputs_schema() {
+        let mut rng = OsRng;
+        let input_resources = [(); NUM_RESOURCE].map(|_| random_resource(&mut rng));
+        let output_resources = [(); NUM_RESOURCE].map(|_| random_resource(&mut rng));
+        let owned_resource_id = output_resources[0].commitment().into();
+        let circuit =
+            TrivialResourceLogicCircuit::new(owned_resource_id, input_resources, output_resources);
+        let bytecode = circuit.to_bytecode();
+        assert_eq!(bytecode.inputs[0], NATIVE_RESOURCE_LOGIC_INPUTS_VERSION);
+        assert_eq!(
+            bytecode.inputs.len(),
+            MIN_NATIVE_RESOURCE_LOGIC_INPUTS_LEN + 1
+        );
+        let decoded = bytecode
+            .decode_native_inputs::<TrivialResourceLogicCircuit>()
+            .unwrap();
+        assert_eq!(decoded.to_bytes(), circuit.to_bytes());
+
+        let malformed = |inputs: Vec<u8>| {
+            let bytecode = ResourceLogicByteCode {
+                circuit: ResourceLogicRepresentation::Trivial,
+                inputs,
+            };
+            match bytecode.decode_native_inputs::<TrivialResourceLogicCircuit>() {
+                Err(TransactionError::MalformedLogicInputs(e)) => e,
+                _ => panic!("the malformed inputs are decoded"),
+            }
+        };
+        assert_eq!(malformed(vec![]), MalformedLogicInputsError::MissingVersion);
+
+        let mut inputs = bytecode.inputs.clone();
+        inputs[0] = NATIVE_RESOURCE_LOGIC_INPUTS_VERSION + 1;
+        assert_eq!(
+            malformed(inputs),
+            MalformedLogicInputsError::UnsupportedVersion(NATIVE_RESOURCE_LOGIC_INPUTS_VERSION + 1)
+        );
+
+        let truncated = bytecode.inputs[..bytecode.inputs.len() - 1].to_vec();
+        assert!(matches!(
+            malformed(truncated),
+            MalformedLogicInputsError::InvalidLength { .. }
+        ));
+
+        // A non-canonical field element in the owned resource id
+        let mut inputs = bytecode.inputs.clone();
+        inputs[2..34].copy_from_slice(&[0xff; 32]);
+        assert!(matches!(
+            malformed(inputs),
+            MalformedLogicInputsError::InvalidEncoding(_)
+        ));
+
+        // An unexpected field tag
+        let mut inputs = bytecode.inputs.clone();
+        inputs[1] = 2;
+        assert!(matches!(
+            malformed(inputs),
+            MalformedLogicInputsError::InvalidEncoding(_)
+        ));
+    }
+}
@@ -2,24 +2,27 @@ use crate::{
     circuit::{
         blake2s::publicize_default_dynamic_resource_logic_commitments,
         blake2s::Blake2sConfig,
+        constraint_report::RowUtilization,
         gadgets::{
             add::{AddChip, AddConfig},
             assign_free_advice,
             conditional_equal::ConditionalEqualConfig,
             conditional_select::ConditionalSelectConfig,
             extended_or_relation::ExtendedOrRelationConfig,
+            fixed_point::FixedPointConfig,
             mul::{MulChip, MulConfig},
             sub::{SubChip, SubConfig},
             target_resource_variable::{
                 GetIsInputResourceFlagConfig, GetOwnedResourceVariableConfig,
             },
+            u128::U128Config,
         },
         integrity::{check_input_resource, check_output_resource},
         resource_commitment::{ResourceCommitChip, ResourceCommitConfig},
-        vamp_ir_utils::{get_circuit_assignments, parse, VariableAssignmentError},
     },
     constant::{
-        TaigaFixedBases, NUM_RESOURCE, RESOURCE_ENCRYPTION_CIPHERTEXT_NUM,
+        TaigaFixedBases, DEFAULT_PARAMS_STORE, MAX_RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+        NUM_RESOURCE, RESOURCE_ENCRYPTION_CIPHERTEXT_NUM,
         RESOURCE_LOGIC_CIRCUIT_NULLIFIER_ONE_PUBLIC_INPUT_IDX,
         RESOURCE_LOGIC_CIRCUIT_NULLIFIER_TWO_PUBLIC_INPUT_IDX,
         RESOURCE_LOGIC_CIRCUIT_OUTPUT_CM_ONE_PUBLIC_INPUT_IDX,
@@ -28,17 +31,19 @@ use crate::{
         RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE, RESOURCE_LOGIC_CIRCUIT_PUBLIC_INPUT_NUM,
         RESOURCE_LOGIC_CIRCUIT_RESOURCE_ENCRYPTION_PK_X_IDX,
         RESOURCE_LOGIC_CIRCUIT_RESOURCE_ENCRYPTION_PK_Y_IDX,
-        RESOURCE_LOGIC_CIRCUIT_RESOURCE_ENCRYPTION_PUBLIC_INPUT_BEGIN_IDX, SETUP_PARAMS_MAP,
+        RESOURCE_LOGIC_CIRCUIT_RESOURCE_ENCRYPTION_PUBLIC_INPUT_BEGIN_IDX,
     },
     error::TransactionError,
-    proof::Proof,
-    resource::{RandomSeed, Resource, ResourceCommitment},
-    resource_encryption::{ResourceCiphertext, SecretKey},
+    ka::EphemeralPublicKey,
+    params_store::ParamsStore,
+    proof::{verifying_key_k, BatchVerifier, Params, Proof, VerifyingKey},
+    resource::{OwnedResourceId, RandomSeed, Resource, ResourceCommitment},
+    resource_encryption::ResourceCiphertext,
     resource_logic_vk::ResourceLogicVerifyingKey,
+    resource_logic_vk_registry::RESOURCE_LOGIC_VK_REGISTRY,
     utils::mod_r_p,
 };
 use dyn_clone::{clone_trait_object, DynClone};
-use group::cofactor::CofactorCurveAffine;
 use halo2_gadgets::{
     ecc::chip::EccChip,
     ecc::chip::EccConfig,
@@ -46,29 +51,40 @@ use halo2_gadgets::{
     utilities::lookup_range_check::LookupRangeCheckConfig,
 };
 use halo2_proofs::{
-    arithmetic::CurveAffine,
     circuit::{AssignedCell, Layouter, Value},
-    plonk::{
-        keygen_pk, keygen_vk, Advice, Circuit, Column, ConstraintSystem, Error, Instance,
-        TableColumn, VerifyingKey,
-    },
-    poly::commitment::Params,
+    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Instance, TableColumn},
+};
+use pasta_curves::pallas;
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+// The vamp-ir circuits are only compiled and proved with the prover
+#[cfg(feature = "prover")]
+use crate::{
+    circuit::vamp_ir_utils::{get_circuit_assignments, parse, parse_named_inputs},
+    proof::setup_params,
+};
+#[cfg(feature = "prover")]
+use halo2_proofs::plonk::{keygen_pk, keygen_vk};
+#[cfg(feature = "prover")]
+use pasta_curves::Fp;
+#[cfg(feature = "prover")]
+use std::{collections::HashMap, fs, path::PathBuf, rc::Rc};
+#[cfg(feature = "prover")]
+use vamp_ir::{
+    ast::{Module, VariableId},
+    halo2::synth::{Halo2Module, PrimeFieldOps},
+    transform::compile,
+    util::Config,
 };
-use pasta_curves::{pallas, vesta, EqAffine, Fp};
-use rand::{rngs::OsRng, RngCore};
-use std::collections::HashMap;
-use std::fs;
-//use std::io;
-use std::path::PathBuf;
-use std::rc::Rc;
-use vamp_ir::ast::Module;
-use vamp_ir::halo2::synth::{make_constant, Halo2Module, PrimeFieldOps};
-use vamp_ir::transform::compile;
-use vamp_ir::util::{read_inputs_from_file, Config};
 
 #[cfg(feature = "serde")]
 use serde;
 
+pub use crate::circuit::vamp_ir_utils::WitnessReport;
+
 #[cfg(feature = "borsh")]
 use borsh::{BorshDeserialize, BorshSerialize};
 
@@ -82,6 +98,7 @@ pub type ResourceLogic = dyn ResourceLogicVerifyingInfoTrait;
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ResourceLogicVerifyingInfo {
+    /// The vk is shared by the proofs of the same logic, e.g. the ones of a decoded ptx.
     #[cfg_attr(
         feature = "serde",
         serde(
@@ -89,9 +106,72 @@ pub struct ResourceLogicVerifyingInfo {
             deserialize_with = "serde_deserialize_verifying_key"
         )
     )]
-    pub vk: VerifyingKey<vesta::Affine>,
+    pub vk: Arc<VerifyingKey>,
     pub proof: Proof,
     pub public_inputs: ResourceLogicPublicInputs,
+    pub circuit_size: ResourceLogicCircuitSize,
+}
+
+/// The declared size of a resource logic circuit. It prices the verification of the proof in
+/// the transaction weight, and lets the verifiers reject too large circuits before verifying
+/// their proofs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+pub struct ResourceLogicCircuitSize {
+    pub k: u32,
+}
+
+impl ResourceLogicCircuitSize {
+    pub fn from_vk(vk: &VerifyingKey) -> Self {
+        Self {
+            k: verifying_key_k(vk),
+        }
+    }
+
+    /// The circuit has 2^k rows.
+    pub fn rows(&self) -> u64 {
+        1 << self.k
+    }
+}
+
+// The rows halo2 keeps at the end of the columns for the blinding factors, an upper bound for
+// the resource logic configs.
+const LOOKUP_TABLE_RESERVED_ROWS: u64 = 16;
+
+/// The lookup tables a resource logic circuit type loads besides the range table of
+/// `ResourceLogicConfig`, e.g. a 16-bit range or a Keccak table. The circuit is proved with the
+/// smallest params holding its largest table.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LookupTableBudget {
+    // The rows of the tables by name, the tables are loaded in their own columns
+    tables: BTreeMap<&'static str, u64>,
+}
+
+impl LookupTableBudget {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares a table of the given rows, a table declared twice keeps its largest size.
+    pub fn with_table(mut self, name: &'static str, rows: u64) -> Self {
+        let entry = self.tables.entry(name).or_default();
+        *entry = (*entry).max(rows);
+        self
+    }
+
+    /// The rows of the largest table.
+    pub fn rows(&self) -> u64 {
+        self.tables.values().copied().max().unwrap_or_default()
+    }
+
+    /// The size k of the params the circuit is proved with: the default resource logic size,
+    /// or the smallest one holding the largest table and the reserved rows.
+    pub fn params_size(&self) -> u32 {
+        let rows = self.rows() + LOOKUP_TABLE_RESERVED_ROWS;
+        let k = u64::BITS - (rows - 1).leading_zeros();
+        k.max(RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE)
+    }
 }
 
 #[cfg(feature = "nif")]
@@ -102,7 +182,7 @@ impl Encoder for ResourceLogicVerifyingInfo {
     fn encode<'a>(&self, env: Env<'a>) -> Term<'a> {
         (
             verifying_info().encode(env),
-            self.vk.to_bytes().encode(env),
+            vk_to_bytes(&self.vk).encode(env),
             self.proof.encode(env),
             self.public_inputs.encode(env),
         )
@@ -120,17 +200,8 @@ impl<'a> Decoder<'a> for ResourceLogicVerifyingInfo {
             ResourceLogicPublicInputs,
         ) = term.decode()?;
         if term == verifying_info() {
-            use crate::circuit::resource_logic_examples::TrivialResourceLogicCircuit;
-            let params = SETUP_PARAMS_MAP
-                .get(&RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE)
-                .unwrap();
-            let vk = VerifyingKey::from_bytes::<TrivialResourceLogicCircuit>(&vk, params)
-                .map_err(|_e| rustler::Error::Atom("failure to decode"))?;
-            Ok(ResourceLogicVerifyingInfo {
-                vk,
-                proof,
-                public_inputs,
-            })
+            let vk = vk_from_bytes(&vk).map_err(|_e| rustler::Error::Atom("failure to decode"))?;
+            Ok(ResourceLogicVerifyingInfo::new(vk, proof, public_inputs))
         } else {
             Err(rustler::Error::BadArg)
         }
@@ -157,14 +228,90 @@ impl<'a> Decoder<'a> for ResourceLogicPublicInputs {
 }
 
 impl ResourceLogicVerifyingInfo {
+    pub fn new(vk: VerifyingKey, proof: Proof, public_inputs: ResourceLogicPublicInputs) -> Self {
+        let circuit_size = ResourceLogicCircuitSize::from_vk(&vk);
+        Self {
+            vk: Arc::new(vk),
+            proof,
+            public_inputs,
+            circuit_size,
+        }
+    }
+
     pub fn verify(&self) -> Result<(), Error> {
-        let params = SETUP_PARAMS_MAP
-            .get(&RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE)
-            .unwrap();
+        let params = DEFAULT_PARAMS_STORE.get(self.circuit_size.k).unwrap();
+        self.verify_with_params(&params)
+    }
+
+    pub fn verify_with_params(&self, params: &Params) -> Result<(), Error> {
         self.proof
             .verify(&self.vk, params, &[self.public_inputs.inner()])
     }
 
+    /// Adds the proof to the batch instead of verifying it.
+    pub fn add_to_batch<'a>(&'a self, batch: &mut BatchVerifier<'a>) {
+        batch.add_proof(
+            self.circuit_size.k,
+            &self.vk,
+            &self.proof,
+            &[self.public_inputs.inner()],
+        );
+    }
+
+    /// Verifies the proof, the error names the logic the proof claims.
+    pub fn verify_with_logic_name(&self, params: &Params) -> Result<(), TransactionError> {
+        self.verify_with_params(params)
+            .map_err(|e| TransactionError::InvalidResourceLogicProof(self.get_logic_name(), e))
+    }
+
+    /// Verifies the proof like `verify_with_logic_name`, with the params of the circuit size
+    /// from the store.
+    pub fn verify_with_params_store(
+        &self,
+        params_store: &ParamsStore,
+    ) -> Result<(), TransactionError> {
+        let params = params_store.get(self.circuit_size.k)?;
+        self.verify_with_logic_name(&params)
+    }
+
+    /// Checks the declared circuit size is acceptable and matches the vk. The cheap size bound
+    /// is checked first.
+    pub fn check_circuit_size(&self) -> Result<(), TransactionError> {
+        if self.circuit_size.k > MAX_RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE {
+            return Err(TransactionError::ResourceLogicCircuitTooLarge(
+                self.circuit_size.k,
+            ));
+        }
+        if self.circuit_size != ResourceLogicCircuitSize::from_vk(&self.vk) {
+            return Err(TransactionError::InconsistentResourceLogicCircuitSize(
+                self.get_logic_name(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The verification weight of the proof, the rows of the circuit.
+    pub fn weight(&self) -> u64 {
+        self.circuit_size.rows()
+    }
+
+    /// The registered name of the logic, e.g. "TokenV1".
+    pub fn get_logic_name(&self) -> String {
+        let compressed_vk = ResourceLogicVerifyingKey::from_vk((*self.vk).clone()).get_compressed();
+        RESOURCE_LOGIC_VK_REGISTRY.describe(&compressed_vk)
+    }
+
+    /// A human readable summary of the verifying info.
+    pub fn inspect(&self) -> String {
+        format!(
+            "logic: {}, owned resource id: {:?}, nullifiers: {:?}, output commitments: {:?}",
+            self.get_logic_name(),
+            self.get_owned_resource_id(),
+            self.get_nullifiers(),
+            self.get_resource_commitments().map(|cm| cm.inner()),
+        )
+    }
+
     pub fn get_nullifiers(&self) -> [pallas::Base; NUM_RESOURCE] {
         [
             self.public_inputs
@@ -185,41 +332,52 @@ impl ResourceLogicVerifyingInfo {
         ]
     }
 
-    pub fn get_owned_resource_id(&self) -> pallas::Base {
-        self.public_inputs
-            .get_from_index(RESOURCE_LOGIC_CIRCUIT_OWNED_RESOURCE_ID_PUBLIC_INPUT_IDX)
+    pub fn get_owned_resource_id(&self) -> OwnedResourceId {
+        self.public_inputs.get_owned_resource_id()
     }
 }
 
 #[cfg(feature = "borsh")]
-impl BorshSerialize for ResourceLogicVerifyingInfo {
-    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+impl ResourceLogicVerifyingInfo {
+    /// Writes the circuit size and the vk, see `write_vk`. The vk is read with the params of the
+    /// circuit size.
+    pub(crate) fn serialize_vk<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.circuit_size.serialize(writer)?;
+        write_vk(&self.vk, writer)
+    }
+
+    /// Reads the circuit size and the vk, the too large circuits are rejected before reading
+    /// the vk. Reading the vk rebuilds the constraint system of its circuit, the vk is shared
+    /// rather than read again for every proof of the logic.
+    pub(crate) fn deserialize_vk<R: std::io::Read>(
+        reader: &mut R,
+    ) -> std::io::Result<(ResourceLogicCircuitSize, Arc<VerifyingKey>)> {
+        let circuit_size = ResourceLogicCircuitSize::deserialize_reader(reader)?;
+        let vk = read_vk(reader, circuit_size.k)?;
+        Ok((circuit_size, Arc::new(vk)))
+    }
+
+    /// Writes the proof and the public inputs, without the vk.
+    pub(crate) fn serialize_without_vk<W: std::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> std::io::Result<()> {
         use ff::PrimeField;
-        // Write vk
-        self.vk.write(writer)?;
-        // Write proof
         self.proof.serialize(writer)?;
-        // Write public inputs
         for ele in self.public_inputs.inner().iter() {
             writer.write_all(&ele.to_repr())?;
         }
         Ok(())
     }
-}
 
-#[cfg(feature = "borsh")]
-impl BorshDeserialize for ResourceLogicVerifyingInfo {
-    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
-        // Read vk
-        use crate::circuit::resource_logic_examples::TrivialResourceLogicCircuit;
+    /// Reads the proof and the public inputs of a proof of the given vk.
+    pub(crate) fn deserialize_with_vk<R: std::io::Read>(
+        reader: &mut R,
+        circuit_size: ResourceLogicCircuitSize,
+        vk: Arc<VerifyingKey>,
+    ) -> std::io::Result<Self> {
         use crate::utils::read_base_field;
-        let params = SETUP_PARAMS_MAP
-            .get(&RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE)
-            .unwrap();
-        let vk = VerifyingKey::read::<_, TrivialResourceLogicCircuit>(reader, params)?;
-        // Read proof
         let proof = Proof::deserialize_reader(reader)?;
-        // Read public inputs
         let public_inputs: Vec<_> = (0..RESOURCE_LOGIC_CIRCUIT_PUBLIC_INPUT_NUM)
             .map(|_| read_base_field(reader))
             .collect::<Result<_, _>>()?;
@@ -227,38 +385,97 @@ impl BorshDeserialize for ResourceLogicVerifyingInfo {
             vk,
             proof,
             public_inputs: public_inputs.into(),
+            circuit_size,
         })
     }
 }
 
+#[cfg(feature = "borsh")]
+impl BorshSerialize for ResourceLogicVerifyingInfo {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.serialize_vk(writer)?;
+        self.serialize_without_vk(writer)
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl BorshDeserialize for ResourceLogicVerifyingInfo {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let (circuit_size, vk) = Self::deserialize_vk(reader)?;
+        Self::deserialize_with_vk(reader, circuit_size, vk)
+    }
+}
+
+/// Writes the compressed vk and the vk. Reading a vk rebuilds the constraint system of its
+/// circuit type, the compressed vk resolves the type, see `register_circuit_type`.
+#[cfg(any(feature = "borsh", feature = "serde", feature = "nif"))]
+fn write_vk<W: std::io::Write>(vk: &VerifyingKey, writer: &mut W) -> std::io::Result<()> {
+    use ff::PrimeField;
+    let compressed_vk = ResourceLogicVerifyingKey::from_vk(vk.clone()).get_compressed();
+    writer.write_all(&compressed_vk.to_repr())?;
+    vk.write(writer)
+}
+
+/// Reads a vk written by `write_vk` with the params of size k. A vk that doesn't match its
+/// compressed vk is rejected. The params are only taken from the default store, which never
+/// generates them for the size an untrusted encoding declares.
+#[cfg(any(feature = "borsh", feature = "serde", feature = "nif"))]
+fn read_vk<R: std::io::Read>(reader: &mut R, k: u32) -> std::io::Result<VerifyingKey> {
+    use crate::{resource_logic_vk_registry::circuit_type_vk_reader, utils::read_base_field};
+    let invalid_data = |e: String| std::io::Error::new(std::io::ErrorKind::InvalidData, e);
+    if k > MAX_RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE {
+        return Err(invalid_data(
+            TransactionError::ResourceLogicCircuitTooLarge(k).to_string(),
+        ));
+    }
+    let params = DEFAULT_PARAMS_STORE
+        .get(k)
+        .map_err(|e| invalid_data(e.to_string()))?;
+    let compressed_vk = read_base_field(reader)?;
+    let vk = circuit_type_vk_reader(&compressed_vk)(reader, &params)?;
+    if ResourceLogicVerifyingKey::from_vk(vk.clone()).get_compressed() != compressed_vk {
+        return Err(invalid_data(
+            "the vk doesn't match its compressed vk, its circuit type may be unregistered"
+                .to_string(),
+        ));
+    }
+    Ok(vk)
+}
+
+// The encodings without the circuit size, serde and nif, prefix the vk with its size k.
+#[cfg(any(feature = "serde", feature = "nif"))]
+fn vk_to_bytes(vk: &VerifyingKey) -> Vec<u8> {
+    let mut bytes = verifying_key_k(vk).to_le_bytes().to_vec();
+    write_vk(vk, &mut bytes).expect("writing into a Vec never fails");
+    bytes
+}
+
+#[cfg(any(feature = "serde", feature = "nif"))]
+fn vk_from_bytes(mut bytes: &[u8]) -> std::io::Result<VerifyingKey> {
+    use std::io::Read;
+    let mut k = [0u8; 4];
+    bytes.read_exact(&mut k)?;
+    read_vk(&mut bytes, u32::from_le_bytes(k))
+}
+
 #[cfg(feature = "serde")]
-fn serde_serialize_verifying_key<S>(
-    x: &VerifyingKey<vesta::Affine>,
-    s: S,
-) -> Result<S::Ok, S::Error>
+fn serde_serialize_verifying_key<S>(x: &Arc<VerifyingKey>, s: S) -> Result<S::Ok, S::Error>
 where
     S: serde::Serializer,
 {
-    let mut buf = Vec::new();
-    x.write(&mut buf).unwrap();
-    s.serialize_bytes(&buf)
+    crate::utils::serde_hex::serialize(vk_to_bytes(x), s)
 }
 
 #[cfg(feature = "serde")]
-fn serde_deserialize_verifying_key<'de, D>(d: D) -> Result<VerifyingKey<vesta::Affine>, D::Error>
+fn serde_deserialize_verifying_key<'de, D>(d: D) -> Result<Arc<VerifyingKey>, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
     use serde::de::Error;
-    let buf: Vec<u8> = serde::Deserialize::deserialize(d)?;
-
-    use crate::circuit::resource_logic_examples::TrivialResourceLogicCircuit;
-    let params = SETUP_PARAMS_MAP
-        .get(&RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE)
-        .unwrap();
-    let vk = VerifyingKey::read::<_, TrivialResourceLogicCircuit>(&mut buf.as_slice(), params)
-        .map_err(|e| Error::custom(format!("Error reading VerifyingKey: {}", e)))?;
-    Ok(vk)
+    let buf = crate::utils::serde_hex::deserialize(d)?;
+    vk_from_bytes(&buf)
+        .map(Arc::new)
+        .map_err(|e| Error::custom(format!("Error reading VerifyingKey: {}", e)))
 }
 
 impl ResourceLogicPublicInputs {
@@ -291,20 +508,38 @@ impl ResourceLogicPublicInputs {
         self.0.to_vec()
     }
 
-    pub fn decrypt(&self, sk: pallas::Base) -> Option<Vec<pallas::Base>> {
-        let cipher: ResourceCiphertext = self.0
-            [RESOURCE_LOGIC_CIRCUIT_RESOURCE_ENCRYPTION_PUBLIC_INPUT_BEGIN_IDX
-                ..RESOURCE_LOGIC_CIRCUIT_RESOURCE_ENCRYPTION_PUBLIC_INPUT_BEGIN_IDX
-                    + RESOURCE_ENCRYPTION_CIPHERTEXT_NUM]
+    pub fn get_owned_resource_id(&self) -> OwnedResourceId {
+        OwnedResourceId::from_field(
+            self.get_from_index(RESOURCE_LOGIC_CIRCUIT_OWNED_RESOURCE_ID_PUBLIC_INPUT_IDX),
+            &[
+                self.get_from_index(RESOURCE_LOGIC_CIRCUIT_NULLIFIER_ONE_PUBLIC_INPUT_IDX),
+                self.get_from_index(RESOURCE_LOGIC_CIRCUIT_NULLIFIER_TWO_PUBLIC_INPUT_IDX),
+            ],
+        )
+    }
+
+    pub fn get_resource_ciphertext(&self) -> ResourceCiphertext {
+        self.0[RESOURCE_LOGIC_CIRCUIT_RESOURCE_ENCRYPTION_PUBLIC_INPUT_BEGIN_IDX
+            ..RESOURCE_LOGIC_CIRCUIT_RESOURCE_ENCRYPTION_PUBLIC_INPUT_BEGIN_IDX
+                + RESOURCE_ENCRYPTION_CIPHERTEXT_NUM]
             .to_vec()
-            .into();
-        let sender_pk = pallas::Affine::from_xy(
+            .into()
+    }
+
+    /// The resource ciphertext and the ephemeral public key of the sender. Returns None if the
+    /// published key is not on the curve.
+    pub fn get_resource_encryption(&self) -> Option<(ResourceCiphertext, EphemeralPublicKey)> {
+        let cipher = self.get_resource_ciphertext();
+        let sender_pk = EphemeralPublicKey::from_coordinates(
             self.get_from_index(RESOURCE_LOGIC_CIRCUIT_RESOURCE_ENCRYPTION_PK_X_IDX),
             self.get_from_index(RESOURCE_LOGIC_CIRCUIT_RESOURCE_ENCRYPTION_PK_Y_IDX),
-        )
-        .unwrap()
-        .to_curve();
-        let key = SecretKey::from_dh_exchange(&sender_pk, &mod_r_p(sk));
+        )?;
+        Some((cipher, sender_pk))
+    }
+
+    pub fn decrypt(&self, sk: pallas::Base) -> Option<Vec<pallas::Base>> {
+        let (cipher, sender_pk) = self.get_resource_encryption()?;
+        let key = sender_pk.agree(&mod_r_p(sk));
         cipher.decrypt(&key)
     }
 }
@@ -334,6 +569,9 @@ pub struct ResourceLogicConfig {
     pub add_config: AddConfig,
     pub sub_config: SubConfig,
     pub mul_config: MulConfig,
+    pub u128_config: U128Config,
+    pub fixed_point_config: FixedPointConfig,
+    pub range_check_config: LookupRangeCheckConfig<pallas::Base, 10>,
     pub blake2s_config: Blake2sConfig<pallas::Base>,
     pub resource_commit_config: ResourceCommitConfig,
 }
@@ -404,13 +642,17 @@ impl ResourceLogicConfig {
         let add_config = AddChip::configure(meta, [advices[0], advices[1]]);
         let sub_config = SubChip::configure(meta, [advices[0], advices[1]]);
         let mul_config = MulChip::configure(meta, [advices[0], advices[1]]);
+        let u128_config =
+            U128Config::configure(meta, advices[0..5].try_into().unwrap(), range_check);
+        let fixed_point_config =
+            FixedPointConfig::configure(meta, advices[0..4].try_into().unwrap(), range_check);
 
         let extended_or_relation_config =
             ExtendedOrRelationConfig::configure(meta, [advices[0], advices[1], advices[2]]);
         let blake2s_config = Blake2sConfig::configure(meta, advices);
         let resource_commit_config = ResourceCommitChip::configure(
             meta,
-            advices[0..3].try_into().unwrap(),
+            advices[0..4].try_into().unwrap(),
             poseidon_config.clone(),
             range_check,
         );
@@ -428,20 +670,78 @@ impl ResourceLogicConfig {
             add_config,
             sub_config,
             mul_config,
+            u128_config,
+            fixed_point_config,
+            range_check_config: range_check,
             blake2s_config,
             resource_commit_config,
         }
     }
 }
 
-pub trait ResourceLogicVerifyingInfoTrait: DynClone {
-    fn get_verifying_info(&self) -> ResourceLogicVerifyingInfo;
+// Send + Sync so that resource logics can be shared across threads by the services.
+/// The randomness of the proofs and of the public input padding is passed in by the caller, the
+/// trait is object safe so it takes a `&mut dyn RngCore`.
+pub trait ResourceLogicVerifyingInfoTrait: DynClone + Send + Sync {
+    /// Proves the circuit with the params of the default store. The params of a circuit larger
+    /// than PARAMS_SIZE, see `ResourceLogicCircuit::params_size`, have to be preloaded in
+    /// `DEFAULT_PARAMS_STORE`, or it's a `MissingParams` error.
+    #[cfg(feature = "prover")]
+    fn get_verifying_info(
+        &self,
+        rng: &mut dyn RngCore,
+    ) -> Result<ResourceLogicVerifyingInfo, TransactionError>;
+    #[cfg(feature = "prover")]
+    fn get_verifying_info_with_params_store(
+        &self,
+        params_store: &ParamsStore,
+        rng: &mut dyn RngCore,
+    ) -> Result<ResourceLogicVerifyingInfo, TransactionError>;
     fn verify_transparently(&self) -> Result<ResourceLogicPublicInputs, TransactionError>;
-    fn get_resource_logic_vk(&self) -> ResourceLogicVerifyingKey;
+    /// The vk of the circuit with the params of the default store, a `MissingParams` error for
+    /// the sizes not preloaded like in `get_verifying_info`.
+    fn get_resource_logic_vk(&self) -> Result<ResourceLogicVerifyingKey, TransactionError>;
+    /// The rows the circuit uses against the usable rows of its params, for the circuit
+    /// reports. The logics that don't measure their rows report None.
+    fn row_utilization(&self) -> Option<RowUtilization> {
+        None
+    }
+}
+
+/// The rng of the public input padding in the transparent verification. The padding of a
+/// transparently checked resource logic hides nothing, a fixed seed keeps the check
+/// deterministic.
+pub fn transparent_verification_rng() -> StdRng {
+    StdRng::seed_from_u64(0)
 }
 
 clone_trait_object!(ResourceLogicVerifyingInfoTrait);
 
+/// The mandatory public inputs of a resource logic, shared by all the circuits: the input
+/// resource nullifiers and the output resource commitments, interleaved, followed by the owned
+/// resource id. Verifiers and solvers recompute the expected instances with it without
+/// constructing the circuits.
+pub fn mandatory_public_inputs(
+    input_resources: &[Resource; NUM_RESOURCE],
+    output_resources: &[Resource; NUM_RESOURCE],
+    owned_resource_id: OwnedResourceId,
+) -> Result<Vec<pallas::Base>, ResourceLogicPrecheckError> {
+    let mut public_inputs = vec![];
+    for (index, (input_resource, output_resource)) in input_resources
+        .iter()
+        .zip(output_resources.iter())
+        .enumerate()
+    {
+        let nf = input_resource
+            .get_nf()
+            .ok_or(ResourceLogicPrecheckError::MissingNullifierKey(index))?;
+        public_inputs.push(nf.inner());
+        public_inputs.push(output_resource.commitment().inner());
+    }
+    public_inputs.push(owned_resource_id.inner());
+    Ok(public_inputs)
+}
+
 pub trait ResourceLogicCircuit: Circuit<pallas::Base> + ResourceLogicVerifyingInfoTrait {
     // Default implementation, constrains the resources integrity.
     // TODO: how to enforce the constraints in resource_logic circuit?
@@ -504,7 +804,7 @@ pub trait ResourceLogicCircuit: Circuit<pallas::Base> + ResourceLogicVerifyingIn
         let owned_resource_id = assign_free_advice(
             layouter.namespace(|| "owned_resource_id"),
             config.advices[0],
-            Value::known(self.get_owned_resource_id()),
+            Value::known(self.get_owned_resource_id().inner()),
         )?;
         layouter.constrain_instance(
             owned_resource_id.cell(),
@@ -541,18 +841,12 @@ pub trait ResourceLogicCircuit: Circuit<pallas::Base> + ResourceLogicVerifyingIn
     }
 
     fn get_mandatory_public_inputs(&self) -> Vec<pallas::Base> {
-        let mut public_inputs = vec![];
-        self.get_input_resources()
-            .iter()
-            .zip(self.get_output_resources().iter())
-            .for_each(|(input_resource, output_resource)| {
-                let nf = input_resource.get_nf().unwrap().inner();
-                public_inputs.push(nf);
-                let cm = output_resource.commitment();
-                public_inputs.push(cm.inner());
-            });
-        public_inputs.push(self.get_owned_resource_id());
-        public_inputs
+        mandatory_public_inputs(
+            self.get_input_resources(),
+            self.get_output_resources(),
+            self.get_owned_resource_id(),
+        )
+        .unwrap()
     }
     fn get_input_resources(&self) -> &[Resource; NUM_RESOURCE];
     fn get_output_resources(&self) -> &[Resource; NUM_RESOURCE];
@@ -560,7 +854,68 @@ pub trait ResourceLogicCircuit: Circuit<pallas::Base> + ResourceLogicVerifyingIn
     // The owned_resource_id is the input_resource_nf or the output_resource_cm_x
     // The owned_resource_id is the key to look up the target variables and
     // help determine whether the owned resource is the input resource or not in resource logic circuit.
-    fn get_owned_resource_id(&self) -> pallas::Base;
+    fn get_owned_resource_id(&self) -> OwnedResourceId;
+
+    // The input resource with the owned_resource_id as nullifier, or the output resource with it
+    // as commitment.
+    fn get_owned_resource(&self) -> Option<&Resource> {
+        match self.get_owned_resource_id() {
+            OwnedResourceId::Nf(nf) => self
+                .get_input_resources()
+                .iter()
+                .find(|resource| resource.get_nf() == Some(nf)),
+            OwnedResourceId::Cm(cm) => self
+                .get_output_resources()
+                .iter()
+                .find(|resource| resource.commitment() == cm),
+        }
+    }
+
+    // Checks the witnesses before the synthesis, so that inconsistent witnesses are reported
+    // before the keygen and the proving instead of as unsatisfied constraints deep in proving.
+    fn precheck(&self) -> Result<(), ResourceLogicPrecheckError> {
+        self.basic_precheck()?;
+        self.custom_precheck()
+    }
+
+    // Default implementation, checks the witnesses of the basic constraints.
+    fn basic_precheck(&self) -> Result<(), ResourceLogicPrecheckError> {
+        if let Some(index) = self
+            .get_input_resources()
+            .iter()
+            .position(|resource| resource.get_nf().is_none())
+        {
+            return Err(ResourceLogicPrecheckError::MissingNullifierKey(index));
+        }
+        if self.get_owned_resource().is_none() {
+            return Err(ResourceLogicPrecheckError::UnknownOwnedResourceId);
+        }
+        Ok(())
+    }
+
+    // Add checks of the witnesses of the custom constraints.
+    fn custom_precheck(&self) -> Result<(), ResourceLogicPrecheckError> {
+        Ok(())
+    }
+
+    // Declare the lookup tables the circuit type loads in its `configure`, the default circuits
+    // load none. The tables are a property of the type like the constraint system, so the keys
+    // of a type are the same for all its circuits.
+    fn lookup_table_budget() -> LookupTableBudget {
+        LookupTableBudget::default()
+    }
+
+    // The size k of the params the circuit is proved and verified with.
+    fn params_size(&self) -> u32 {
+        Self::lookup_table_budget().params_size()
+    }
+
+    // Tells apart the circuits of the type with different keys, e.g. the ones of a mode fixed in
+    // the circuit: the key cache keeps the keys per type and cache key. The circuits of the
+    // default types have the same keys.
+    fn cache_key(&self) -> u64 {
+        0
+    }
 }
 
 /// BasicResourceLogicVariables are generally constrained in ResourceLogicCircuit::basic_constraints
@@ -578,6 +933,7 @@ pub struct ResourceVariables {
     pub label: AssignedCell<pallas::Base, pallas::Base>,
     pub quantity: AssignedCell<pallas::Base, pallas::Base>,
     pub is_ephemeral: AssignedCell<pallas::Base, pallas::Base>,
+    pub is_transparent_kind: AssignedCell<pallas::Base, pallas::Base>,
     pub value: AssignedCell<pallas::Base, pallas::Base>,
     pub nonce: AssignedCell<pallas::Base, pallas::Base>,
     pub npk: AssignedCell<pallas::Base, pallas::Base>,
@@ -692,6 +1048,15 @@ impl BasicResourceLogicVariables {
         )
     }
 
+    pub fn get_is_transparent_kind_searchable_pairs(
+        &self,
+    ) -> [ResourceSearchableVariablePair; NUM_RESOURCE * 2] {
+        self.get_variable_searchable_pairs(
+            |variables| variables.resource_variables.is_transparent_kind.clone(),
+            |variables| variables.resource_variables.is_transparent_kind.clone(),
+        )
+    }
+
     pub fn get_value_searchable_pairs(&self) -> [ResourceSearchableVariablePair; NUM_RESOURCE * 2] {
         self.get_variable_searchable_pairs(
             |variables| variables.resource_variables.value.clone(),
@@ -762,73 +1127,308 @@ macro_rules! resource_logic_circuit_impl {
 macro_rules! resource_logic_verifying_info_impl {
     ($name:ident) => {
         impl ResourceLogicVerifyingInfoTrait for $name {
-            fn get_verifying_info(&self) -> ResourceLogicVerifyingInfo {
-                let mut rng = OsRng;
-                let params = SETUP_PARAMS_MAP.get(&15).unwrap();
-                let vk = keygen_vk(params, self).expect("keygen_vk should not fail");
-                let pk = keygen_pk(params, vk.clone(), self).expect("keygen_pk should not fail");
+            #[cfg(feature = "prover")]
+            fn get_verifying_info(
+                &self,
+                rng: &mut dyn rand::RngCore,
+            ) -> Result<
+                $crate::circuit::resource_logic_circuit::ResourceLogicVerifyingInfo,
+                TransactionError,
+            > {
+                self.get_verifying_info_with_params_store(&DEFAULT_PARAMS_STORE, rng)
+            }
+
+            #[cfg(feature = "prover")]
+            fn get_verifying_info_with_params_store(
+                &self,
+                params_store: &$crate::params_store::ParamsStore,
+                mut rng: &mut dyn rand::RngCore,
+            ) -> Result<
+                $crate::circuit::resource_logic_circuit::ResourceLogicVerifyingInfo,
+                TransactionError,
+            > {
+                self.precheck()
+                    .map_err(TransactionError::ResourceLogicPrecheck)?;
+                let k = self.params_size();
+                if k > $crate::constant::MAX_RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE {
+                    return Err(TransactionError::ResourceLogicCircuitTooLarge(k));
+                }
+                let params = params_store.get(k)?;
+                let pk = $crate::keys::KEY_CACHE.proving_key(&params, self, self.cache_key())?;
+                let vk = pk.get_vk().clone();
                 let public_inputs = self.get_public_inputs(&mut rng);
-                let proof = Proof::create(
+                let proof = $crate::proof::Proof::create(
                     &pk,
-                    params,
+                    &params,
                     self.clone(),
                     &[public_inputs.inner()],
                     &mut rng,
+                )?;
+                Ok(
+                    $crate::circuit::resource_logic_circuit::ResourceLogicVerifyingInfo::new(
+                        vk,
+                        proof,
+                        public_inputs,
+                    ),
                 )
-                .unwrap();
-                ResourceLogicVerifyingInfo {
-                    vk,
-                    proof,
-                    public_inputs,
-                }
             }
 
             fn verify_transparently(&self) -> Result<ResourceLogicPublicInputs, TransactionError> {
                 use halo2_proofs::dev::MockProver;
-                let mut rng = OsRng;
-                let public_inputs = self.get_public_inputs(&mut rng);
-                let prover =
-                    MockProver::<pallas::Base>::run(15, self, vec![public_inputs.to_vec()])
-                        .unwrap();
-                prover.verify().unwrap();
+                self.precheck()
+                    .map_err(TransactionError::ResourceLogicPrecheck)?;
+                let public_inputs = self.get_public_inputs(
+                    $crate::circuit::resource_logic_circuit::transparent_verification_rng(),
+                );
+                let prover = MockProver::<pallas::Base>::run(
+                    self.params_size(),
+                    self,
+                    vec![public_inputs.to_vec()],
+                )
+                .unwrap();
+                crate::circuit::verify_failure::verify_with_explanations(&prover)
+                    .map_err(TransactionError::ResourceLogicConstraintsNotSatisfied)?;
                 Ok(public_inputs)
             }
 
-            fn get_resource_logic_vk(&self) -> ResourceLogicVerifyingKey {
-                let params = SETUP_PARAMS_MAP.get(&15).unwrap();
-                let vk = keygen_vk(params, self).expect("keygen_vk should not fail");
-                ResourceLogicVerifyingKey::from_vk(vk)
+            fn get_resource_logic_vk(&self) -> Result<ResourceLogicVerifyingKey, TransactionError> {
+                let params = DEFAULT_PARAMS_STORE.get(self.params_size())?;
+                let vk = $crate::keys::KEY_CACHE.verifying_key(&params, self, self.cache_key())?;
+                Ok(ResourceLogicVerifyingKey::from_vk(vk))
             }
+
+            fn row_utilization(
+                &self,
+            ) -> Option<$crate::circuit::constraint_report::RowUtilization> {
+                $crate::circuit::constraint_report::row_utilization(self, self.params_size()).ok()
+            }
+        }
+    };
+}
+
+/// The encoding of a field of a native resource logic circuit, see
+/// `resource_logic_circuit_encoding_impl`.
+#[cfg(feature = "borsh")]
+pub trait CircuitFieldEncoding: Sized {
+    fn encode<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()>;
+    fn decode<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self>;
+}
+
+#[cfg(feature = "borsh")]
+impl CircuitFieldEncoding for pallas::Base {
+    fn encode<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        use ff::PrimeField;
+        writer.write_all(&self.to_repr())
+    }
+
+    fn decode<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        crate::utils::read_base_field(reader)
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl CircuitFieldEncoding for pallas::Point {
+    fn encode<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        use group::GroupEncoding;
+        writer.write_all(&self.to_bytes())
+    }
+
+    fn decode<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        crate::utils::read_point(reader)
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl CircuitFieldEncoding for [Resource; NUM_RESOURCE] {
+    fn encode<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        for resource in self.iter() {
+            resource.serialize(writer)?;
         }
+        Ok(())
+    }
+
+    fn decode<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let resources: Vec<_> = (0..NUM_RESOURCE)
+            .map(|_| Resource::deserialize_reader(reader))
+            .collect::<Result<_, _>>()?;
+        Ok(resources.try_into().unwrap())
+    }
+}
+
+/// Implements `CircuitFieldEncoding` with the borsh encoding of the types.
+#[macro_export]
+macro_rules! circuit_field_encoding_from_borsh {
+    ($($name:ty),* $(,)?) => {
+        $(
+            impl $crate::circuit::resource_logic_circuit::CircuitFieldEncoding for $name {
+                fn encode<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+                    borsh::BorshSerialize::serialize(self, writer)
+                }
+
+                fn decode<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+                    borsh::BorshDeserialize::deserialize_reader(reader)
+                }
+            }
+        )*
     };
 }
 
+#[cfg(feature = "borsh")]
+circuit_field_encoding_from_borsh!(
+    bool,
+    u64,
+    RandomSeed,
+    OwnedResourceId,
+    crate::merkle_tree::MerklePath
+);
+
+/// The canonical encoding of a native resource logic circuit: each field, in the listed order,
+/// prefixed with its tag. The tags are checked on decoding, so an encoding of another layout is
+/// rejected instead of silently decoded into the wrong fields. The encoding is versioned by
+/// `NATIVE_RESOURCE_LOGIC_INPUTS_VERSION` in the bytecode, bump it on a layout change.
+///
+/// Implements `BorshSerialize`, `BorshDeserialize`, `to_bytes` and `from_bytes`. The optional
+/// check validates the decoded circuit beyond the encodings of its fields.
+#[macro_export]
+macro_rules! resource_logic_circuit_encoding_impl {
+    ($name:ident { $($tag:literal => $field:ident),* $(,)? }) => {
+        $crate::resource_logic_circuit_encoding_impl!($name { $($tag => $field),* }, |_| Ok(()));
+    };
+    ($name:ident { $($tag:literal => $field:ident),* $(,)? }, $check:expr) => {
+        // The tags are strictly increasing
+        const _: () = {
+            let tags: &[u8] = &[$($tag),*];
+            let mut i = 1;
+            while i < tags.len() {
+                assert!(tags[i - 1] < tags[i], "the field tags must be increasing");
+                i += 1;
+            }
+        };
+
+        impl $name {
+            pub fn to_bytes(&self) -> Vec<u8> {
+                borsh::to_vec(&self).unwrap()
+            }
+
+            pub fn from_bytes(bytes: &Vec<u8>) -> Self {
+                borsh::BorshDeserialize::deserialize(&mut bytes.as_ref()).unwrap()
+            }
+        }
+
+        impl borsh::BorshSerialize for $name {
+            fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+                use $crate::circuit::resource_logic_circuit::CircuitFieldEncoding;
+                $(
+                    writer.write_all(&[$tag])?;
+                    self.$field.encode(writer)?;
+                )*
+                Ok(())
+            }
+        }
+
+        impl borsh::BorshDeserialize for $name {
+            fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+                use $crate::circuit::resource_logic_circuit::CircuitFieldEncoding;
+                $(
+                    let mut tag = [0u8; 1];
+                    reader.read_exact(&mut tag)?;
+                    if tag[0] != $tag {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            format!(
+                                "unexpected field tag {}, expected {} ({})",
+                                tag[0],
+                                $tag,
+                                stringify!($field)
+                            ),
+                        ));
+                    }
+                    let $field = CircuitFieldEncoding::decode(reader)?;
+                )*
+                let circuit = Self { $($field),* };
+                let check: fn(&$name) -> std::io::Result<()> = $check;
+                check(&circuit)?;
+                Ok(circuit)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "prover")]
 #[derive(Clone)]
 pub struct VampIRResourceLogicCircuit {
     // TODO: vamp_ir doesn't support to set the params size manually, add the params here temporarily.
     // remove the params once we can set it as RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE in vamp_ir.
-    pub params: Params<vesta::Affine>,
-    pub circuit: Halo2Module<pallas::Base>,
+    pub params: Params,
+    // Halo2Module holds the module in an Rc, so keep the compiled module and the assignments
+    // here and build the Halo2Module on use. This keeps the circuit Send + Sync.
+    module: Arc<Module>,
+    assignments: HashMap<VariableId, pallas::Base>,
     pub public_inputs: Vec<pallas::Base>,
 }
 
 #[derive(Debug)]
 pub enum VampIRCircuitError {
-    MissingAssignment(String),
+    /// The named inputs don't match the circuit, see the report.
+    InvalidWitness(WitnessReport),
     SourceParsingError(String),
+    IoError(String),
+}
+
+impl fmt::Display for VampIRCircuitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VampIRCircuitError::InvalidWitness(report) => {
+                write!(f, "invalid vamp-ir witness: {report}")
+            }
+            VampIRCircuitError::SourceParsingError(e) => write!(f, "invalid vamp-ir source: {e}"),
+            VampIRCircuitError::IoError(e) => write!(f, "cannot read vamp-ir file: {e}"),
+        }
+    }
 }
 
-impl VampIRCircuitError {
-    fn from_variable_assignment_error(error: VariableAssignmentError) -> Self {
-        match error {
-            VariableAssignmentError::MissingAssignment(s) => {
-                VampIRCircuitError::MissingAssignment(s)
+/// The witnesses of a resource logic circuit are inconsistent, found by
+/// ResourceLogicCircuit::precheck before the synthesis.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResourceLogicPrecheckError {
+    /// The input resource at the index has no nullifier key to derive its nullifier.
+    MissingNullifierKey(usize),
+    /// The owned_resource_id is neither an input resource nullifier nor an output resource
+    /// commitment.
+    UnknownOwnedResourceId,
+    /// A field of the owned resource doesn't match the logic's witnesses, with the field name.
+    InconsistentOwnedResource(&'static str),
+    /// The quantity of the owned resource is out of the range the logic supports.
+    QuantityOutOfRange(u128),
+}
+
+impl fmt::Display for ResourceLogicPrecheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResourceLogicPrecheckError::MissingNullifierKey(index) => {
+                write!(f, "input resource {index} has no nullifier key")
+            }
+            ResourceLogicPrecheckError::UnknownOwnedResourceId => write!(
+                f,
+                "owned_resource_id matches none of the input nullifiers and output commitments"
+            ),
+            ResourceLogicPrecheckError::InconsistentOwnedResource(field) => {
+                write!(
+                    f,
+                    "the {field} of the owned resource doesn't match the logic"
+                )
+            }
+            ResourceLogicPrecheckError::QuantityOutOfRange(quantity) => {
+                write!(f, "the owned resource quantity {quantity} is out of range")
             }
         }
     }
 }
 
+#[cfg(feature = "prover")]
 impl VampIRResourceLogicCircuit {
+    /// Compiles the circuit and checks the named assignments cover exactly the named wires of
+    /// the circuit before anything is proved.
     pub fn from_vamp_ir_source(
         vamp_ir_source: &str,
         named_field_assignments: HashMap<String, Fp>,
@@ -841,72 +1441,77 @@ impl VampIRResourceLogicCircuit {
             &PrimeFieldOps::<Fp>::default(),
             &config,
         );
-        let mut circuit = Halo2Module::<Fp>::new(Rc::new(vamp_ir_module));
-        let params = Params::new(circuit.k);
-        let field_assignments = get_circuit_assignments(&circuit.module, &named_field_assignments)
-            .map_err(VampIRCircuitError::from_variable_assignment_error)?;
-
-        // Populate variable definitions
-        circuit.populate_variables(field_assignments.clone());
+        let field_assignments = get_circuit_assignments(&vamp_ir_module, &named_field_assignments)
+            .map_err(VampIRCircuitError::InvalidWitness)?;
 
-        // Get public inputs Fp
-        let public_inputs = circuit
-            .module
-            .pubs
-            .iter()
-            .map(|inst| field_assignments[&inst.id])
-            .collect::<Vec<pallas::Base>>();
-
-        Ok(Self {
-            params,
-            circuit,
-            public_inputs,
-        })
+        Ok(Self::new(vamp_ir_module, field_assignments))
     }
 
-    pub fn from_vamp_ir_file(vamp_ir_file: &PathBuf, inputs_file: &PathBuf) -> Self {
-        let config = Config { quiet: true };
-        let vamp_ir_source = fs::read_to_string(vamp_ir_file).expect("cannot read vamp-ir file");
-        let parsed_vamp_ir_module = Module::parse(&vamp_ir_source).unwrap();
-        let vamp_ir_module = compile(
-            parsed_vamp_ir_module,
-            &PrimeFieldOps::<Fp>::default(),
-            &config,
-        );
-        let mut circuit = Halo2Module::<Fp>::new(Rc::new(vamp_ir_module));
-        let params: Params<EqAffine> = Params::new(circuit.k);
+    /// Reads the circuit and the vamp-ir inputs file (a JSON object of name to integer).
+    pub fn from_vamp_ir_file(
+        vamp_ir_file: &PathBuf,
+        inputs_file: &PathBuf,
+    ) -> Result<Self, VampIRCircuitError> {
+        let inputs = fs::read_to_string(inputs_file)
+            .map_err(|e| VampIRCircuitError::IoError(e.to_string()))?;
+        let named_field_assignments =
+            parse_named_inputs(&inputs).map_err(VampIRCircuitError::InvalidWitness)?;
+        Self::from_vamp_ir_file_with_assignments(vamp_ir_file, named_field_assignments)
+    }
 
-        let var_assignments_ints = read_inputs_from_file(&circuit.module, inputs_file);
-        let mut var_assignments = HashMap::new();
-        for (k, v) in var_assignments_ints {
-            var_assignments.insert(k, make_constant(v));
-        }
+    pub fn from_vamp_ir_file_with_assignments(
+        vamp_ir_file: &PathBuf,
+        named_field_assignments: HashMap<String, Fp>,
+    ) -> Result<Self, VampIRCircuitError> {
+        let vamp_ir_source = fs::read_to_string(vamp_ir_file)
+            .map_err(|e| VampIRCircuitError::IoError(e.to_string()))?;
+        Self::from_vamp_ir_source(&vamp_ir_source, named_field_assignments)
+    }
 
-        // Populate variable definitions
-        circuit.populate_variables(var_assignments.clone());
+    fn new(module: Module, assignments: HashMap<VariableId, pallas::Base>) -> Self {
+        let module = Arc::new(module);
+        let params = setup_params(Self::build_circuit(&module, &assignments).k);
 
         // Get public inputs Fp
-        let public_inputs = circuit
-            .module
+        let public_inputs = module
             .pubs
             .iter()
-            .map(|inst| var_assignments[&inst.id])
+            .map(|inst| assignments[&inst.id])
             .collect::<Vec<pallas::Base>>();
 
         Self {
             params,
-            circuit,
+            module,
+            assignments,
             public_inputs,
         }
     }
+
+    fn build_circuit(
+        module: &Module,
+        assignments: &HashMap<VariableId, pallas::Base>,
+    ) -> Halo2Module<pallas::Base> {
+        let mut circuit = Halo2Module::<Fp>::new(Rc::new(module.clone()));
+        // Populate variable definitions
+        circuit.populate_variables(assignments.clone());
+        circuit
+    }
+
+    pub fn circuit(&self) -> Halo2Module<pallas::Base> {
+        Self::build_circuit(&self.module, &self.assignments)
+    }
 }
 
-impl ResourceLogicVerifyingInfoTrait for VampIRResourceLogicCircuit {
-    fn get_verifying_info(&self) -> ResourceLogicVerifyingInfo {
-        let mut rng = OsRng;
-        let vk = keygen_vk(&self.params, &self.circuit).expect("keygen_vk should not fail");
-        let pk =
-            keygen_pk(&self.params, vk.clone(), &self.circuit).expect("keygen_pk should not fail");
+#[cfg(feature = "prover")]
+impl VampIRResourceLogicCircuit {
+    pub(crate) fn create_verifying_info(
+        &self,
+        params: &Params,
+        mut rng: &mut dyn RngCore,
+    ) -> Result<ResourceLogicVerifyingInfo, TransactionError> {
+        let circuit = self.circuit();
+        let vk = keygen_vk(params, &circuit)?;
+        let pk = keygen_pk(params, vk.clone(), &circuit)?;
 
         let mut public_inputs = self.public_inputs.clone();
         let rseed = RandomSeed::random(&mut rng);
@@ -915,49 +1520,66 @@ impl ResourceLogicVerifyingInfoTrait for VampIRResourceLogicCircuit {
             &rseed,
         ));
 
-        let proof = Proof::create(
-            &pk,
-            &self.params,
-            self.circuit.clone(),
-            &[&public_inputs.to_vec()],
-            &mut rng,
-        )
-        .unwrap();
-        ResourceLogicVerifyingInfo {
+        let proof = Proof::create(&pk, params, circuit, &[&public_inputs.to_vec()], &mut rng)?;
+        Ok(ResourceLogicVerifyingInfo::new(
             vk,
             proof,
-            public_inputs: public_inputs.into(),
-        }
+            public_inputs.into(),
+        ))
+    }
+}
+
+#[cfg(feature = "prover")]
+impl ResourceLogicVerifyingInfoTrait for VampIRResourceLogicCircuit {
+    fn get_verifying_info(
+        &self,
+        rng: &mut dyn RngCore,
+    ) -> Result<ResourceLogicVerifyingInfo, TransactionError> {
+        self.create_verifying_info(&self.params, rng)
+    }
+
+    fn get_verifying_info_with_params_store(
+        &self,
+        params_store: &ParamsStore,
+        rng: &mut dyn RngCore,
+    ) -> Result<ResourceLogicVerifyingInfo, TransactionError> {
+        let params = params_store.get(self.params.k())?;
+        self.create_verifying_info(&params, rng)
     }
 
     fn verify_transparently(&self) -> Result<ResourceLogicPublicInputs, TransactionError> {
         use halo2_proofs::dev::MockProver;
-        let mut rng = OsRng;
         let mut public_inputs = self.public_inputs.clone();
-        let rseed = RandomSeed::random(&mut rng);
+        let rseed = RandomSeed::random(transparent_verification_rng());
         public_inputs.extend(ResourceLogicPublicInputs::get_public_input_padding(
             self.public_inputs.len(),
             &rseed,
         ));
         let prover =
-            MockProver::<pallas::Base>::run(15, &self.circuit, vec![public_inputs.to_vec()])
+            MockProver::<pallas::Base>::run(15, &self.circuit(), vec![public_inputs.to_vec()])
                 .unwrap();
-        prover.verify().unwrap();
+        crate::circuit::verify_failure::verify_with_explanations(&prover)
+            .map_err(TransactionError::ResourceLogicConstraintsNotSatisfied)?;
         Ok(ResourceLogicPublicInputs::from(public_inputs))
     }
 
-    fn get_resource_logic_vk(&self) -> ResourceLogicVerifyingKey {
-        let vk = keygen_vk(&self.params, &self.circuit).expect("keygen_vk should not fail");
-        ResourceLogicVerifyingKey::from_vk(vk)
+    fn get_resource_logic_vk(&self) -> Result<ResourceLogicVerifyingKey, TransactionError> {
+        let vk = keygen_vk(&self.params, &self.circuit())?;
+        Ok(ResourceLogicVerifyingKey::from_vk(vk))
+    }
+
+    fn row_utilization(&self) -> Option<RowUtilization> {
+        crate::circuit::constraint_report::row_utilization(&self.circuit(), self.params.k()).ok()
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "prover"))]
 mod tests {
     use crate::circuit::resource_logic_circuit::{
         ResourceLogicVerifyingInfoTrait, VampIRResourceLogicCircuit,
     };
     use num_bigint::BigInt;
+    use rand::rngs::OsRng;
     use std::collections::HashMap;
     use std::path::PathBuf;
     use vamp_ir::halo2::synth::make_constant;
@@ -968,10 +1590,13 @@ mod tests {
         let vamp_ir_circuit_file = PathBuf::from("./src/circuit/vamp_ir_circuits/pyth.pir");
         let inputs_file = PathBuf::from("./src/circuit/vamp_ir_circuits/pyth.inputs");
         let resource_logic_circuit =
-            VampIRResourceLogicCircuit::from_vamp_ir_file(&vamp_ir_circuit_file, &inputs_file);
+            VampIRResourceLogicCircuit::from_vamp_ir_file(&vamp_ir_circuit_file, &inputs_file)
+                .unwrap();
 
         // generate proof and instance
-        let resource_logic_info = resource_logic_circuit.get_verifying_info();
+        let resource_logic_info = resource_logic_circuit
+            .get_verifying_info(&mut OsRng)
+            .unwrap();
 
         // verify the proof
         // TODO: use the resource_logic_info.verify() instead. resource_logic_info.verify() doesn't work now because it uses the fixed RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE params.
@@ -999,6 +1624,23 @@ mod tests {
         assert!(missing_x_assignment.is_err());
     }
 
+    #[test]
+    fn test_create_resource_logic_witness_report() {
+        use crate::circuit::resource_logic_circuit::VampIRCircuitError;
+
+        let result = VampIRResourceLogicCircuit::from_vamp_ir_source(
+            "x * y = 1;",
+            HashMap::from([(String::from("z"), make_constant(BigInt::from(1)))]),
+        );
+        match result {
+            Err(VampIRCircuitError::InvalidWitness(report)) => {
+                assert_eq!(report.missing, vec!["x", "y"]);
+                assert_eq!(report.unexpected, vec!["z"]);
+            }
+            _ => panic!("expected an invalid witness report"),
+        }
+    }
+
     #[test]
     fn test_create_resource_logic_with_no_assignment() {
         let zero_constraint = VampIRResourceLogicCircuit::from_vamp_ir_source("0;", HashMap::new());
@@ -1016,7 +1658,9 @@ mod tests {
         assert!(x_assignment_circuit.is_ok());
 
         let resource_logic_circuit = x_assignment_circuit.unwrap();
-        let resource_logic_info = resource_logic_circuit.get_verifying_info();
+        let resource_logic_info = resource_logic_circuit
+            .get_verifying_info(&mut OsRng)
+            .unwrap();
 
         assert!(resource_logic_info
             .proof
@@ -1039,7 +1683,9 @@ mod tests {
         assert!(x_assignment_circuit.is_ok());
 
         let resource_logic_circuit = x_assignment_circuit.unwrap();
-        let resource_logic_info = resource_logic_circuit.get_verifying_info();
+        let resource_logic_info = resource_logic_circuit
+            .get_verifying_info(&mut OsRng)
+            .unwrap();
 
         assert!(resource_logic_info
             .proof
@@ -1060,8 +1706,7 @@ mod tests {
             },
             resource_logic_examples::TrivialResourceLogicCircuit,
         };
-        use halo2_proofs::plonk::VerifyingKey;
-        use pasta_curves::vesta;
+        use crate::proof::VerifyingKey;
         use serde_json;
 
         #[derive(serde::Serialize, serde::Deserialize)]
@@ -1070,10 +1715,12 @@ mod tests {
                 serialize_with = "serde_serialize_verifying_key",
                 deserialize_with = "serde_deserialize_verifying_key"
             )]
-            vk: VerifyingKey<vesta::Affine>,
+            vk: VerifyingKey,
         }
 
-        let t = TrivialResourceLogicCircuit::default().get_resource_logic_vk();
+        let t = TrivialResourceLogicCircuit::default()
+            .get_resource_logic_vk()
+            .unwrap();
 
         let a = TestStruct {
             vk: t.get_vk().unwrap(),
@@ -1087,4 +1734,378 @@ mod tests {
 
         assert_eq!(a_bytes, deser_bytes);
     }
+
+    #[test]
+    fn test_mandatory_public_inputs() {
+        use crate::circuit::resource_logic_circuit::{
+            mandatory_public_inputs, ResourceLogicCircuit, ResourceLogicPrecheckError,
+        };
+        use crate::circuit::resource_logic_examples::tests::random_trivial_resource_logic_circuit;
+        use crate::constant::{
+            NUM_RESOURCE, RESOURCE_LOGIC_CIRCUIT_DYNAMIC_RESOURCE_LOGIC_CM_BEGIN_IDX,
+        };
+        use crate::nullifier::NullifierKeyContainer;
+        use rand::rngs::OsRng;
+
+        let mut rng = OsRng;
+        let circuit = random_trivial_resource_logic_circuit(&mut rng);
+        let public_inputs = mandatory_public_inputs(
+            &circuit.input_resources,
+            &circuit.output_resources,
+            circuit.owned_resource_id,
+        )
+        .unwrap();
+        // The nullifiers and commitments, then the owned resource id, up to the dynamic resource
+        // logic commitments
+        assert_eq!(public_inputs.len(), 2 * NUM_RESOURCE + 1);
+        assert_eq!(
+            public_inputs.len(),
+            RESOURCE_LOGIC_CIRCUIT_DYNAMIC_RESOURCE_LOGIC_CM_BEGIN_IDX
+        );
+        assert_eq!(public_inputs, circuit.get_mandatory_public_inputs());
+        assert_eq!(
+            public_inputs,
+            circuit.get_public_inputs(&mut rng).inner()
+                [..RESOURCE_LOGIC_CIRCUIT_DYNAMIC_RESOURCE_LOGIC_CM_BEGIN_IDX]
+                .to_vec()
+        );
+
+        // The nullifier of an input resource without the nullifier key can't be derived
+        let mut input_resources = circuit.input_resources;
+        input_resources[1].nk_container =
+            NullifierKeyContainer::from_npk(input_resources[1].get_npk());
+        assert_eq!(
+            mandatory_public_inputs(
+                &input_resources,
+                &circuit.output_resources,
+                circuit.owned_resource_id
+            ),
+            Err(ResourceLogicPrecheckError::MissingNullifierKey(1))
+        );
+    }
+
+    #[test]
+    fn test_lookup_table_budget() {
+        use crate::circuit::resource_logic_circuit::{LookupTableBudget, ResourceLogicCircuit};
+        use crate::circuit::resource_logic_examples::tests::random_trivial_resource_logic_circuit;
+        use crate::constant::{
+            MAX_RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE, RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+        };
+
+        // The default circuits and the small tables keep the default size
+        let circuit = random_trivial_resource_logic_circuit(&mut OsRng);
+        assert_eq!(circuit.params_size(), RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE);
+        let byte = LookupTableBudget::new().with_table("byte", 1 << 8);
+        assert_eq!(byte.params_size(), RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE);
+
+        // A full 16-bit table doesn't fit the usable rows of k = 16
+        let range16 = byte.clone().with_table("range16", 1 << 16);
+        assert_eq!(range16.rows(), 1 << 16);
+        assert_eq!(range16.params_size(), 17);
+        assert_eq!(
+            range16.clone().with_table("range16", 1 << 10),
+            range16,
+            "a table declared twice keeps its largest size"
+        );
+        assert_eq!(
+            LookupTableBudget::new()
+                .with_table("range16", (1 << 16) - 16)
+                .params_size(),
+            16
+        );
+        assert!(
+            LookupTableBudget::new()
+                .with_table("keccak", 1 << 17)
+                .params_size()
+                > MAX_RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE
+        );
+    }
+
+    #[cfg(feature = "borsh")]
+    #[test]
+    fn test_lookup_table_resource_logic_vk_round_trip() {
+        use crate::circuit::gadgets::{assign_free_advice, range_check::RangeCheckChip};
+        use crate::circuit::resource_logic_circuit::{
+            LookupTableBudget, ResourceLogicCircuit, ResourceLogicConfig,
+            ResourceLogicPublicInputs, ResourceLogicVerifyingInfo,
+        };
+        use crate::circuit::resource_logic_examples::tests::random_trivial_resource_logic_circuit;
+        use crate::constant::{DEFAULT_PARAMS_STORE, NUM_RESOURCE};
+        use crate::error::TransactionError;
+        use crate::resource::{OwnedResourceId, RandomSeed, Resource};
+        use crate::resource_logic_commitment::ResourceLogicCommitment;
+        use crate::resource_logic_vk::ResourceLogicVerifyingKey;
+        use crate::resource_logic_vk_registry::register_circuit_type;
+        use borsh::BorshDeserialize;
+        use halo2_gadgets::utilities::lookup_range_check::LookupRangeCheckConfig;
+        use halo2_proofs::{
+            circuit::{floor_planner, Layouter, Value},
+            plonk::{Circuit, ConstraintSystem, Error},
+        };
+        use pasta_curves::pallas;
+        use rand::RngCore;
+
+        // Range checks a witness with its own table, its constraint system isn't the one of
+        // ResourceLogicConfig
+        #[derive(Clone, Debug, Default)]
+        struct RangeTableResourceLogicCircuit {
+            owned_resource_id: OwnedResourceId,
+            input_resources: [Resource; NUM_RESOURCE],
+            output_resources: [Resource; NUM_RESOURCE],
+            value: u64,
+        }
+
+        impl Circuit<pallas::Base> for RangeTableResourceLogicCircuit {
+            type Config = (
+                ResourceLogicConfig,
+                LookupRangeCheckConfig<pallas::Base, 10>,
+            );
+            type FloorPlanner = floor_planner::V1;
+
+            fn without_witnesses(&self) -> Self {
+                Self::default()
+            }
+
+            fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+                let config = ResourceLogicConfig::configure(meta);
+                let table_idx = meta.lookup_table_column();
+                let range_check =
+                    LookupRangeCheckConfig::configure(meta, config.advices[0], table_idx);
+                (config, range_check)
+            }
+
+            fn synthesize(
+                &self,
+                (config, range_check): Self::Config,
+                mut layouter: impl Layouter<pallas::Base>,
+            ) -> Result<(), Error> {
+                let basic_variables = self.basic_constraints(
+                    config.clone(),
+                    layouter.namespace(|| "basic constraints"),
+                )?;
+                self.custom_constraints(
+                    config.clone(),
+                    layouter.namespace(|| "custom constraints"),
+                    basic_variables,
+                )?;
+                range_check.load(&mut layouter)?;
+                let value = assign_free_advice(
+                    layouter.namespace(|| "witness value"),
+                    config.advices[0],
+                    Value::known(pallas::Base::from(self.value)),
+                )?;
+                RangeCheckChip::construct(range_check)
+                    .range_check_u64(layouter.namespace(|| "range check"), &value)
+            }
+        }
+
+        impl ResourceLogicCircuit for RangeTableResourceLogicCircuit {
+            fn get_input_resources(&self) -> &[Resource; NUM_RESOURCE] {
+                &self.input_resources
+            }
+
+            fn get_output_resources(&self) -> &[Resource; NUM_RESOURCE] {
+                &self.output_resources
+            }
+
+            fn get_public_inputs(&self, mut rng: impl RngCore) -> ResourceLogicPublicInputs {
+                let mut public_inputs = self.get_mandatory_public_inputs();
+                let default_resource_logic_cm: [pallas::Base; 2] =
+                    ResourceLogicCommitment::default().to_public_inputs();
+                public_inputs.extend(default_resource_logic_cm);
+                public_inputs.extend(default_resource_logic_cm);
+                let padding = ResourceLogicPublicInputs::get_public_input_padding(
+                    public_inputs.len(),
+                    &RandomSeed::random(&mut rng),
+                );
+                public_inputs.extend(padding);
+                public_inputs.into()
+            }
+
+            fn get_owned_resource_id(&self) -> OwnedResourceId {
+                self.owned_resource_id
+            }
+
+            fn lookup_table_budget() -> LookupTableBudget {
+                LookupTableBudget::new().with_table("range10", 1 << 10)
+            }
+        }
+
+        resource_logic_verifying_info_impl!(RangeTableResourceLogicCircuit);
+
+        let trivial = random_trivial_resource_logic_circuit(&mut OsRng);
+        let circuit = RangeTableResourceLogicCircuit {
+            owned_resource_id: trivial.input_resources[0].get_nf().unwrap().into(),
+            input_resources: trivial.input_resources,
+            output_resources: trivial.output_resources,
+            value: 1 << 40,
+        };
+        let info = circuit.get_verifying_info(&mut OsRng).unwrap();
+        info.verify().unwrap();
+        let bytes = borsh::to_vec(&info).unwrap();
+
+        // Read with the constraint system of the trivial circuit, the vk doesn't match its
+        // compressed vk
+        assert!(ResourceLogicVerifyingInfo::try_from_slice(&bytes).is_err());
+
+        let compressed_vk = register_circuit_type::<RangeTableResourceLogicCircuit>().unwrap();
+        assert_eq!(
+            compressed_vk,
+            ResourceLogicVerifyingKey::from_vk((*info.vk).clone()).get_compressed()
+        );
+        let decoded = ResourceLogicVerifyingInfo::try_from_slice(&bytes).unwrap();
+        decoded.check_circuit_size().unwrap();
+        decoded.verify().unwrap();
+        assert_eq!(borsh::to_vec(&decoded).unwrap(), bytes);
+    }
+
+    // The params of the lookup-heavy logics above PARAMS_SIZE aren't embedded: the proving, the
+    // vk and the decoding fail with MissingParams until they are preloaded in the default store
+    #[cfg(feature = "borsh")]
+    #[test]
+    fn test_lookup_table_resource_logic_preloaded_params() {
+        use crate::circuit::resource_logic_circuit::{
+            LookupTableBudget, ResourceLogicCircuit, ResourceLogicConfig,
+            ResourceLogicPublicInputs, ResourceLogicVerifyingInfo,
+        };
+        use crate::circuit::resource_logic_examples::tests::random_trivial_resource_logic_circuit;
+        use crate::constant::{DEFAULT_PARAMS_STORE, NUM_RESOURCE};
+        use crate::error::TransactionError;
+        use crate::proof::setup_params;
+        use crate::resource::{OwnedResourceId, RandomSeed, Resource};
+        use crate::resource_logic_commitment::ResourceLogicCommitment;
+        use crate::resource_logic_vk::ResourceLogicVerifyingKey;
+        use crate::resource_logic_vk_registry::register_circuit_type;
+        use borsh::BorshDeserialize;
+        use halo2_gadgets::utilities::lookup_range_check::LookupRangeCheckConfig;
+        use halo2_proofs::{
+            circuit::{floor_planner, Layouter, Value},
+            plonk::{Circuit, ConstraintSystem, Error},
+        };
+        use pasta_curves::pallas;
+        use rand::RngCore;
+
+        // Range checks a witness with a 15-bit table, which doesn't fit the default params
+        #[derive(Clone, Debug, Default)]
+        struct Range15TableResourceLogicCircuit {
+            owned_resource_id: OwnedResourceId,
+            input_resources: [Resource; NUM_RESOURCE],
+            output_resources: [Resource; NUM_RESOURCE],
+            value: u64,
+        }
+
+        impl Circuit<pallas::Base> for Range15TableResourceLogicCircuit {
+            type Config = (
+                ResourceLogicConfig,
+                LookupRangeCheckConfig<pallas::Base, 15>,
+            );
+            type FloorPlanner = floor_planner::V1;
+
+            fn without_witnesses(&self) -> Self {
+                Self::default()
+            }
+
+            fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+                let config = ResourceLogicConfig::configure(meta);
+                let table_idx = meta.lookup_table_column();
+                let range_check =
+                    LookupRangeCheckConfig::configure(meta, config.advices[0], table_idx);
+                (config, range_check)
+            }
+
+            fn synthesize(
+                &self,
+                (config, range_check): Self::Config,
+                mut layouter: impl Layouter<pallas::Base>,
+            ) -> Result<(), Error> {
+                let basic_variables = self.basic_constraints(
+                    config.clone(),
+                    layouter.namespace(|| "basic constraints"),
+                )?;
+                self.custom_constraints(
+                    config,
+                    layouter.namespace(|| "custom constraints"),
+                    basic_variables,
+                )?;
+                range_check.load(&mut layouter)?;
+                range_check.witness_check(
+                    layouter.namespace(|| "15 bits range check"),
+                    Value::known(pallas::Base::from(self.value)),
+                    1,
+                    true,
+                )?;
+                Ok(())
+            }
+        }
+
+        impl ResourceLogicCircuit for Range15TableResourceLogicCircuit {
+            fn get_input_resources(&self) -> &[Resource; NUM_RESOURCE] {
+                &self.input_resources
+            }
+
+            fn get_output_resources(&self) -> &[Resource; NUM_RESOURCE] {
+                &self.output_resources
+            }
+
+            fn get_public_inputs(&self, mut rng: impl RngCore) -> ResourceLogicPublicInputs {
+                let mut public_inputs = self.get_mandatory_public_inputs();
+                let default_resource_logic_cm: [pallas::Base; 2] =
+                    ResourceLogicCommitment::default().to_public_inputs();
+                public_inputs.extend(default_resource_logic_cm);
+                public_inputs.extend(default_resource_logic_cm);
+                let padding = ResourceLogicPublicInputs::get_public_input_padding(
+                    public_inputs.len(),
+                    &RandomSeed::random(&mut rng),
+                );
+                public_inputs.extend(padding);
+                public_inputs.into()
+            }
+
+            fn get_owned_resource_id(&self) -> OwnedResourceId {
+                self.owned_resource_id
+            }
+
+            fn lookup_table_budget() -> LookupTableBudget {
+                LookupTableBudget::new().with_table("range15", 1 << 15)
+            }
+        }
+
+        resource_logic_verifying_info_impl!(Range15TableResourceLogicCircuit);
+
+        let trivial = random_trivial_resource_logic_circuit(&mut OsRng);
+        let circuit = Range15TableResourceLogicCircuit {
+            owned_resource_id: trivial.input_resources[0].get_nf().unwrap().into(),
+            input_resources: trivial.input_resources,
+            output_resources: trivial.output_resources,
+            value: (1 << 15) - 1,
+        };
+        assert_eq!(circuit.params_size(), 16);
+
+        if !DEFAULT_PARAMS_STORE.contains(16) {
+            assert!(matches!(
+                circuit.get_resource_logic_vk(),
+                Err(TransactionError::MissingParams(16))
+            ));
+            assert!(matches!(
+                circuit.get_verifying_info(&mut OsRng),
+                Err(TransactionError::MissingParams(16))
+            ));
+            DEFAULT_PARAMS_STORE.preload(setup_params(16));
+        }
+
+        let info = circuit.get_verifying_info(&mut OsRng).unwrap();
+        assert_eq!(info.circuit_size.k, 16);
+        info.verify().unwrap();
+        let bytes = borsh::to_vec(&info).unwrap();
+
+        let compressed_vk = register_circuit_type::<Range15TableResourceLogicCircuit>().unwrap();
+        assert_eq!(
+            compressed_vk,
+            ResourceLogicVerifyingKey::from_vk((*info.vk).clone()).get_compressed()
+        );
+        let decoded = ResourceLogicVerifyingInfo::try_from_slice(&bytes).unwrap();
+        decoded.check_circuit_size().unwrap();
+        decoded.verify().unwrap();
+        assert_eq!(borsh::to_vec(&decoded).unwrap(), bytes);
+    }
 }
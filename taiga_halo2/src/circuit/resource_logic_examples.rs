@@ -1,74 +1,97 @@
 #[cfg(feature = "borsh")]
 use crate::circuit::resource_logic_bytecode::{ResourceLogicByteCode, ResourceLogicRepresentation};
 use crate::{
+    circuit::constraint_report::{row_utilization, RowUtilization},
     circuit::resource_logic_circuit::{
-        ResourceLogicCircuit, ResourceLogicConfig, ResourceLogicPublicInputs,
-        ResourceLogicVerifyingInfo, ResourceLogicVerifyingInfoTrait,
+        transparent_verification_rng, ResourceLogicCircuit, ResourceLogicConfig,
+        ResourceLogicPublicInputs, ResourceLogicVerifyingInfoTrait,
     },
-    constant::{NUM_RESOURCE, RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE, SETUP_PARAMS_MAP},
+    constant::{DEFAULT_PARAMS_STORE, NUM_RESOURCE, RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE},
     error::TransactionError,
-    proof::Proof,
-    resource::{RandomSeed, Resource},
+    resource::{OwnedResourceId, RandomSeed, Resource},
     resource_logic_commitment::ResourceLogicCommitment,
     resource_logic_vk::ResourceLogicVerifyingKey,
 };
-#[cfg(feature = "borsh")]
-use borsh::{BorshDeserialize, BorshSerialize};
-use halo2_proofs::plonk::{keygen_pk, keygen_vk, ProvingKey};
+#[cfg(feature = "prover")]
+use crate::{
+    circuit::resource_logic_circuit::ResourceLogicVerifyingInfo,
+    keys::KEY_CACHE,
+    params_store::ParamsStore,
+    proof::{Proof, ProvingKey},
+};
+#[cfg(feature = "prover")]
+use halo2_proofs::plonk::keygen_pk;
+use halo2_proofs::plonk::keygen_vk;
 use halo2_proofs::{
     circuit::{floor_planner, Layouter},
     plonk::{Circuit, ConstraintSystem, Error},
 };
 use lazy_static::lazy_static;
-use pasta_curves::{pallas, vesta};
-use rand::{rngs::OsRng, RngCore};
+use pasta_curves::pallas;
+use rand::RngCore;
 #[cfg(feature = "nif")]
 use rustler::{Decoder, Encoder, Env, NifResult, NifStruct, Term};
 
-#[cfg(feature = "examples")]
+#[cfg(feature = "example-bridge")]
+pub mod bridge;
+#[cfg(feature = "example-intents")]
 pub mod cascade_intent;
-#[cfg(feature = "examples")]
+#[cfg(feature = "example-field-addition")]
 mod field_addition;
-#[cfg(feature = "examples")]
+#[cfg(feature = "example-migration")]
+pub mod migration;
+#[cfg(feature = "example-intents")]
 pub mod or_relation_intent;
-#[cfg(feature = "examples")]
+#[cfg(feature = "example-intents")]
 pub mod partial_fulfillment_intent;
-#[cfg(feature = "examples")]
+#[cfg(feature = "example-payment-channel")]
+pub mod payment_channel;
+#[cfg(feature = "example-randomness-beacon")]
+pub mod randomness_beacon;
+#[cfg(feature = "example-receiver")]
 pub mod receiver_resource_logic;
-#[cfg(feature = "examples")]
+#[cfg(feature = "example-signature-verification")]
 pub mod signature_verification;
-#[cfg(feature = "examples")]
+#[cfg(feature = "example-subscription")]
+pub mod subscription;
+#[cfg(feature = "example-token")]
 pub mod token;
+#[cfg(feature = "example-intents")]
+pub mod token_merge;
 
 lazy_static! {
     pub static ref TRIVIAL_RESOURCE_LOGIC_VK: ResourceLogicVerifyingKey = {
-        let params = SETUP_PARAMS_MAP
-            .get(&RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE)
+        let params = DEFAULT_PARAMS_STORE
+            .get(RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE)
             .unwrap();
         let empty_circuit = TrivialResourceLogicCircuit::default();
-        let vk = keygen_vk(params, &empty_circuit).expect("keygen_vk should not fail");
+        let vk = keygen_vk(&params, &empty_circuit).expect("keygen_vk should not fail");
         ResourceLogicVerifyingKey::from_vk(vk)
     };
-    pub static ref TRIVIAL_RESOURCE_LOGIC_PK: ProvingKey<vesta::Affine> = {
-        let params = SETUP_PARAMS_MAP
-            .get(&RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE)
+    pub static ref COMPRESSED_TRIVIAL_RESOURCE_LOGIC_VK: pallas::Base =
+        TRIVIAL_RESOURCE_LOGIC_VK.get_compressed();
+}
+
+#[cfg(feature = "prover")]
+lazy_static! {
+    pub static ref TRIVIAL_RESOURCE_LOGIC_PK: ProvingKey = {
+        let params = DEFAULT_PARAMS_STORE
+            .get(RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE)
             .unwrap();
         let empty_circuit = TrivialResourceLogicCircuit::default();
         keygen_pk(
-            params,
+            &params,
             TRIVIAL_RESOURCE_LOGIC_VK.get_vk().unwrap(),
             &empty_circuit,
         )
         .expect("keygen_pk should not fail")
     };
-    pub static ref COMPRESSED_TRIVIAL_RESOURCE_LOGIC_VK: pallas::Base =
-        TRIVIAL_RESOURCE_LOGIC_VK.get_compressed();
 }
 
 // TrivialResourceLogicCircuit with empty custom constraints.
 #[derive(Clone, Debug, Default)]
 pub struct TrivialResourceLogicCircuit {
-    pub owned_resource_id: pallas::Base,
+    pub owned_resource_id: OwnedResourceId,
     pub input_resources: [Resource; NUM_RESOURCE],
     pub output_resources: [Resource; NUM_RESOURCE],
 }
@@ -85,7 +108,7 @@ struct TrivialResourceLogicCircuitProxy {
 
 impl TrivialResourceLogicCircuit {
     pub fn new(
-        owned_resource_id: pallas::Base,
+        owned_resource_id: OwnedResourceId,
         input_resources: [Resource; NUM_RESOURCE],
         output_resources: [Resource; NUM_RESOURCE],
     ) -> Self {
@@ -96,27 +119,14 @@ impl TrivialResourceLogicCircuit {
         }
     }
 
-    // Only for test
     #[cfg(feature = "borsh")]
     pub fn to_bytecode(&self) -> ResourceLogicByteCode {
         ResourceLogicByteCode::new(ResourceLogicRepresentation::Trivial, self.to_bytes())
     }
 
-    // Only for test
-    #[cfg(feature = "borsh")]
-    pub fn to_bytes(&self) -> Vec<u8> {
-        borsh::to_vec(&self).unwrap()
-    }
-
-    // Only for test
-    #[cfg(feature = "borsh")]
-    pub fn from_bytes(bytes: &Vec<u8>) -> Self {
-        BorshDeserialize::deserialize(&mut bytes.as_ref()).unwrap()
-    }
-
     fn to_proxy(&self) -> TrivialResourceLogicCircuitProxy {
         TrivialResourceLogicCircuitProxy {
-            owned_resource_id: self.owned_resource_id,
+            owned_resource_id: self.owned_resource_id.inner(),
             input_resources: self.input_resources.to_vec(),
             output_resources: self.output_resources.to_vec(),
         }
@@ -124,44 +134,25 @@ impl TrivialResourceLogicCircuit {
 }
 
 #[cfg(feature = "borsh")]
-impl BorshSerialize for TrivialResourceLogicCircuit {
-    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
-        use ff::PrimeField;
-        writer.write_all(&self.owned_resource_id.to_repr())?;
-        for input in self.input_resources.iter() {
-            input.serialize(writer)?;
-        }
-
-        for output in self.output_resources.iter() {
-            output.serialize(writer)?;
-        }
-        Ok(())
-    }
-}
-
-#[cfg(feature = "borsh")]
-impl BorshDeserialize for TrivialResourceLogicCircuit {
-    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
-        let owned_resource_id = crate::utils::read_base_field(reader)?;
-        let input_resources: Vec<_> = (0..NUM_RESOURCE)
-            .map(|_| Resource::deserialize_reader(reader))
-            .collect::<Result<_, _>>()?;
-        let output_resources: Vec<_> = (0..NUM_RESOURCE)
-            .map(|_| Resource::deserialize_reader(reader))
-            .collect::<Result<_, _>>()?;
-        Ok(Self {
-            owned_resource_id,
-            input_resources: input_resources.try_into().unwrap(),
-            output_resources: output_resources.try_into().unwrap(),
-        })
-    }
-}
+resource_logic_circuit_encoding_impl!(TrivialResourceLogicCircuit {
+    1 => owned_resource_id,
+    2 => input_resources,
+    3 => output_resources,
+});
 
 impl TrivialResourceLogicCircuitProxy {
     fn to_concrete(&self) -> Option<TrivialResourceLogicCircuit> {
-        let input_resources = self.input_resources.clone().try_into().ok()?;
+        let input_resources: [Resource; NUM_RESOURCE] =
+            self.input_resources.clone().try_into().ok()?;
         let output_resources = self.output_resources.clone().try_into().ok()?;
-        let owned_resource_id = self.owned_resource_id;
+        // The proxy holds the field element, it's an input resource id iff it's an input
+        // nullifier
+        let input_nfs: Vec<_> = input_resources
+            .iter()
+            .filter_map(|resource| resource.get_nf())
+            .map(|nf| nf.inner())
+            .collect();
+        let owned_resource_id = OwnedResourceId::from_field(self.owned_resource_id, &input_nfs);
         Some(TrivialResourceLogicCircuit {
             owned_resource_id,
             input_resources,
@@ -207,7 +198,7 @@ impl ResourceLogicCircuit for TrivialResourceLogicCircuit {
         public_inputs.into()
     }
 
-    fn get_owned_resource_id(&self) -> pallas::Base {
+    fn get_owned_resource_id(&self) -> OwnedResourceId {
         self.owned_resource_id
     }
 }
@@ -215,51 +206,87 @@ impl ResourceLogicCircuit for TrivialResourceLogicCircuit {
 resource_logic_circuit_impl!(TrivialResourceLogicCircuit);
 
 impl ResourceLogicVerifyingInfoTrait for TrivialResourceLogicCircuit {
-    fn get_verifying_info(&self) -> ResourceLogicVerifyingInfo {
-        let mut rng = OsRng;
-        let params = SETUP_PARAMS_MAP.get(&15).unwrap();
+    #[cfg(feature = "prover")]
+    fn get_verifying_info(
+        &self,
+        mut rng: &mut dyn RngCore,
+    ) -> Result<ResourceLogicVerifyingInfo, TransactionError> {
+        let params = DEFAULT_PARAMS_STORE.get(RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE)?;
         let public_inputs = self.get_public_inputs(&mut rng);
         let proof = Proof::create(
             &TRIVIAL_RESOURCE_LOGIC_PK,
-            params,
+            &params,
             self.clone(),
             &[public_inputs.inner()],
             &mut rng,
-        )
-        .unwrap();
-        ResourceLogicVerifyingInfo {
-            vk: TRIVIAL_RESOURCE_LOGIC_PK.get_vk().clone(),
+        )?;
+        Ok(ResourceLogicVerifyingInfo::new(
+            TRIVIAL_RESOURCE_LOGIC_PK.get_vk().clone(),
             proof,
             public_inputs,
-        }
+        ))
+    }
+
+    // The precomputed TRIVIAL_RESOURCE_LOGIC_PK only matches the default params, so the keys
+    // of the given store come from the key cache.
+    #[cfg(feature = "prover")]
+    fn get_verifying_info_with_params_store(
+        &self,
+        params_store: &ParamsStore,
+        mut rng: &mut dyn RngCore,
+    ) -> Result<ResourceLogicVerifyingInfo, TransactionError> {
+        self.precheck()
+            .map_err(TransactionError::ResourceLogicPrecheck)?;
+        let params = params_store.get(RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE)?;
+        let pk = KEY_CACHE.proving_key(&params, self, self.cache_key())?;
+        let vk = pk.get_vk().clone();
+        let public_inputs = self.get_public_inputs(&mut rng);
+        let proof = Proof::create(
+            &pk,
+            &params,
+            self.clone(),
+            &[public_inputs.inner()],
+            &mut rng,
+        )?;
+        Ok(ResourceLogicVerifyingInfo::new(vk, proof, public_inputs))
     }
 
     fn verify_transparently(&self) -> Result<ResourceLogicPublicInputs, TransactionError> {
         use halo2_proofs::dev::MockProver;
-        let mut rng = OsRng;
-        let public_inputs = self.get_public_inputs(&mut rng);
+        self.precheck()
+            .map_err(TransactionError::ResourceLogicPrecheck)?;
+        let public_inputs = self.get_public_inputs(transparent_verification_rng());
         let prover =
             MockProver::<pallas::Base>::run(15, self, vec![public_inputs.to_vec()]).unwrap();
-        prover.verify().unwrap();
+        crate::circuit::verify_failure::verify_with_explanations(&prover)
+            .map_err(TransactionError::ResourceLogicConstraintsNotSatisfied)?;
         Ok(public_inputs)
     }
 
-    fn get_resource_logic_vk(&self) -> ResourceLogicVerifyingKey {
-        TRIVIAL_RESOURCE_LOGIC_VK.clone()
+    fn get_resource_logic_vk(&self) -> Result<ResourceLogicVerifyingKey, TransactionError> {
+        Ok(TRIVIAL_RESOURCE_LOGIC_VK.clone())
+    }
+
+    fn row_utilization(&self) -> Option<RowUtilization> {
+        row_utilization(self, RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE).ok()
     }
 }
 
 #[cfg(test)]
 pub mod tests {
     use super::TrivialResourceLogicCircuit;
-    use crate::{constant::NUM_RESOURCE, resource::tests::random_resource};
+    use crate::{
+        constant::NUM_RESOURCE,
+        resource::{tests::random_resource, OwnedResourceId, ResourceCommitment},
+    };
     use ff::Field;
     use pasta_curves::pallas;
     use rand::RngCore;
     pub fn random_trivial_resource_logic_circuit<R: RngCore>(
         mut rng: R,
     ) -> TrivialResourceLogicCircuit {
-        let owned_resource_id = pallas::Base::random(&mut rng);
+        let owned_resource_id =
+            OwnedResourceId::Cm(ResourceCommitment::from(pallas::Base::random(&mut rng)));
         let input_resources = [(); NUM_RESOURCE].map(|_| random_resource(&mut rng));
         let output_resources = [(); NUM_RESOURCE].map(|_| random_resource(&mut rng));
         TrivialResourceLogicCircuit::new(owned_resource_id, input_resources, output_resources)
@@ -268,6 +295,7 @@ pub mod tests {
     #[test]
     fn test_halo2_trivial_resource_logic_circuit() {
         use crate::circuit::resource_logic_circuit::ResourceLogicCircuit;
+        use crate::circuit::verify_failure::assert_verifies;
         use crate::constant::RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE;
         use halo2_proofs::dev::MockProver;
         use rand::rngs::OsRng;
@@ -282,6 +310,61 @@ pub mod tests {
             vec![public_inputs.to_vec()],
         )
         .unwrap();
-        assert_eq!(prover.verify(), Ok(()));
+        assert_verifies(&prover);
+    }
+
+    #[test]
+    fn test_trivial_resource_logic_circuit_size() {
+        use crate::circuit::resource_logic_circuit::{
+            ResourceLogicCircuitSize, ResourceLogicVerifyingInfoTrait,
+        };
+        use crate::constant::{
+            MAX_RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE, RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+        };
+        use crate::error::TransactionError;
+        use rand::rngs::OsRng;
+
+        let circuit = random_trivial_resource_logic_circuit(OsRng);
+        let verifying_info = circuit.get_verifying_info(&mut OsRng).unwrap();
+        assert_eq!(
+            verifying_info.circuit_size,
+            ResourceLogicCircuitSize {
+                k: RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+            }
+        );
+        assert_eq!(
+            verifying_info.weight(),
+            1 << RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE
+        );
+        assert!(verifying_info.check_circuit_size().is_ok());
+
+        // An understated size doesn't match the vk
+        let mut understated = verifying_info.clone();
+        understated.circuit_size.k -= 1;
+        assert!(matches!(
+            understated.check_circuit_size(),
+            Err(TransactionError::InconsistentResourceLogicCircuitSize(_))
+        ));
+
+        // A too large circuit is rejected
+        let mut too_large = verifying_info.clone();
+        too_large.circuit_size.k = MAX_RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE + 1;
+        assert!(matches!(
+            too_large.check_circuit_size(),
+            Err(TransactionError::ResourceLogicCircuitTooLarge(_))
+        ));
+
+        #[cfg(feature = "borsh")]
+        {
+            let bytes = borsh::to_vec(&verifying_info).unwrap();
+            let decoded: crate::circuit::resource_logic_circuit::ResourceLogicVerifyingInfo =
+                borsh::from_slice(&bytes).unwrap();
+            assert_eq!(decoded.circuit_size, verifying_info.circuit_size);
+            assert!(decoded.verify().is_ok());
+            assert!(borsh::from_slice::<
+                crate::circuit::resource_logic_circuit::ResourceLogicVerifyingInfo,
+            >(&borsh::to_vec(&too_large).unwrap())
+            .is_err());
+        }
     }
 }
@@ -0,0 +1,610 @@
+/// This example is to demonstrate taiga as a bridging endpoint. A bridge
+/// resource represents an asset locked on an external chain, its label is the
+/// external asset identifier.
+///
+/// Bridging in: a bridge resource is minted by consuming an ephemeral bridge
+/// resource, which is only valid upon an external event (the asset was locked
+/// on the external chain for the recipient) proven against an external root.
+/// The external root is supplied as a public input, the verifier checks it
+/// against the roots accepted by its light client of the external chain. The
+/// minted resource must be the bridge resource of the event asset and quantity.
+///
+/// The claim resource is fully determined by the event: its nullifier key is the
+/// public `BRIDGE_CLAIM_NK`, its nonce is the event nonce and its rseed is zero.
+/// Claiming the same event twice reveals the same nullifier, so the replay is
+/// rejected as a double spend.
+///
+/// Bridging out: the bridge resource is sent to the burn address and the
+/// destruction is proven to the external chain with `prove_burned`.
+use crate::{
+    burn::is_burned,
+    circuit::{
+        blake2s::publicize_default_dynamic_resource_logic_commitments,
+        gadgets::{
+            assign_free_advice, assign_free_constant,
+            boolean::AssignedBool,
+            merkle_path::{fixed_depth_path, merkle_poseidon_gadget, MerkleSelectChip},
+            mul::{MulChip, MulInstructions},
+            poseidon_hash::poseidon_hash_gadget,
+            target_resource_variable::{get_is_input_resource_flag, get_owned_resource_variable},
+        },
+        resource_logic_bytecode::{ResourceLogicByteCode, ResourceLogicRepresentation},
+        resource_logic_circuit::{
+            BasicResourceLogicVariables, ResourceLogicCircuit, ResourceLogicConfig,
+            ResourceLogicPublicInputs, ResourceLogicVerifyingInfoTrait,
+        },
+    },
+    constant::{
+        DEFAULT_PARAMS_STORE, NUM_RESOURCE, RESOURCE_LOGIC_CIRCUIT_CUSTOM_PUBLIC_INPUT_BEGIN_IDX,
+    },
+    error::TransactionError,
+    merkle_tree::{MerklePath, Node, LR},
+    nullifier::{Nullifier, NullifierKeyContainer},
+    resource::{OwnedResourceId, RandomSeed, Resource},
+    resource_logic_commitment::ResourceLogicCommitment,
+    resource_logic_vk::ResourceLogicVerifyingKey,
+    utils::poseidon_hash_n,
+};
+use halo2_proofs::arithmetic::Field;
+use halo2_proofs::{
+    circuit::{floor_planner, Layouter, Value},
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use lazy_static::lazy_static;
+use pasta_curves::{group::ff::PrimeField, pallas};
+use rand::RngCore;
+
+/// The depth of the external event tree.
+pub const EXTERNAL_EVENT_TREE_DEPTH: usize = 16;
+
+lazy_static! {
+    pub static ref BRIDGE_VK: ResourceLogicVerifyingKey =
+        BridgeResourceLogicCircuit::default().get_resource_logic_vk().unwrap();
+    pub static ref COMPRESSED_BRIDGE_VK: pallas::Base = BRIDGE_VK.get_compressed();
+    /// The public nullifier key of the claim resources, anyone can nullify a claimed event.
+    pub static ref BRIDGE_CLAIM_NK: pallas::Base = pallas::Base::zero();
+    pub static ref BRIDGE_CLAIM_NPK: pallas::Base =
+        NullifierKeyContainer::from_key(*BRIDGE_CLAIM_NK).get_npk();
+}
+
+/// The event of the external chain locking an asset for a taiga recipient.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BridgeEvent {
+    pub asset: pallas::Base,
+    pub recipient_npk: pallas::Base,
+    pub quantity: u128,
+    // distinguishes the events locking the same quantity for the same recipient
+    pub event_nonce: pallas::Base,
+}
+
+impl BridgeEvent {
+    /// event_commitment = poseidon_hash(asset || recipient_npk || quantity || event_nonce)
+    pub fn commitment(&self) -> pallas::Base {
+        poseidon_hash_n([
+            self.asset,
+            self.recipient_npk,
+            pallas::Base::from_u128(self.quantity),
+            self.event_nonce,
+        ])
+    }
+
+    /// Creates the ephemeral input resource claiming the event. The claim resource of an
+    /// event is unique.
+    pub fn create_claim_resource(&self) -> Resource {
+        Resource::new_input_resource(
+            *COMPRESSED_BRIDGE_VK,
+            self.asset,
+            self.recipient_npk,
+            self.quantity,
+            *BRIDGE_CLAIM_NK,
+            Nullifier::from(self.event_nonce),
+            true,
+            pallas::Base::zero(),
+        )
+    }
+
+    /// The nullifier revealed by claiming the event.
+    pub fn nullifier(&self) -> Nullifier {
+        self.create_claim_resource().get_nf().unwrap()
+    }
+
+    /// Creates the bridge resource minted for the recipient.
+    pub fn create_minted_resource<R: RngCore>(&self, mut rng: R) -> Resource {
+        let rseed = pallas::Base::random(&mut rng);
+        Resource::new_output_resource(
+            *COMPRESSED_BRIDGE_VK,
+            self.asset,
+            pallas::Base::zero(),
+            self.quantity,
+            self.recipient_npk,
+            false,
+            rseed,
+        )
+    }
+}
+
+// BridgeResourceLogicCircuit
+#[derive(Clone, Debug)]
+pub struct BridgeResourceLogicCircuit {
+    pub owned_resource_id: OwnedResourceId,
+    pub input_resources: [Resource; NUM_RESOURCE],
+    pub output_resources: [Resource; NUM_RESOURCE],
+    // The root of the external event tree, accepted by the light client of the external chain.
+    pub external_root: pallas::Base,
+    // The path of the event commitment in the external event tree.
+    pub event_path: MerklePath,
+}
+
+impl BridgeResourceLogicCircuit {
+    pub fn to_bytecode(&self) -> ResourceLogicByteCode {
+        ResourceLogicByteCode::new(ResourceLogicRepresentation::Bridge, self.to_bytes())
+    }
+
+    fn owned_resource(&self) -> Option<(&Resource, bool)> {
+        if let Some(resource) = self.input_resources.iter().find(|resource| {
+            resource.get_nf().map(OwnedResourceId::Nf) == Some(self.owned_resource_id)
+        }) {
+            return Some((resource, true));
+        }
+        self.output_resources
+            .iter()
+            .find(|resource| OwnedResourceId::Cm(resource.commitment()) == self.owned_resource_id)
+            .map(|resource| (resource, false))
+    }
+
+    // The claimed event commitment if the owned resource is an ephemeral input (a mint),
+    // otherwise zero.
+    fn claimed_event_commitment(&self) -> pallas::Base {
+        match self.owned_resource() {
+            Some((resource, true)) if resource.is_ephemeral => BridgeEvent {
+                asset: resource.get_label(),
+                recipient_npk: resource.value,
+                quantity: resource.quantity,
+                event_nonce: resource.nonce.inner(),
+            }
+            .commitment(),
+            _ => pallas::Base::zero(),
+        }
+    }
+}
+
+impl Default for BridgeResourceLogicCircuit {
+    fn default() -> Self {
+        Self {
+            owned_resource_id: OwnedResourceId::default(),
+            input_resources: Default::default(),
+            output_resources: Default::default(),
+            external_root: pallas::Base::zero(),
+            event_path: MerklePath::from_path(vec![
+                (Node::from(pallas::Base::one()), LR::L);
+                EXTERNAL_EVENT_TREE_DEPTH
+            ]),
+        }
+    }
+}
+
+impl ResourceLogicCircuit for BridgeResourceLogicCircuit {
+    // Add custom constraints
+    fn custom_constraints(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<pallas::Base>,
+        basic_variables: BasicResourceLogicVariables,
+    ) -> Result<(), Error> {
+        let mul_chip = MulChip::construct(config.mul_config.clone());
+        let owned_resource_id = basic_variables.get_owned_resource_id();
+        let is_input_resource = get_is_input_resource_flag(
+            config.get_is_input_resource_flag_config,
+            layouter.namespace(|| "get is_input_resource_flag"),
+            &owned_resource_id,
+            &basic_variables.get_input_resource_nfs(),
+            &basic_variables.get_output_resource_cms(),
+        )?;
+
+        // is_ephemeral is boolean-constrained in the resource commitment
+        let is_ephemeral = AssignedBool::from_bool_checked(get_owned_resource_variable(
+            config.get_owned_resource_variable_config,
+            layouter.namespace(|| "get owned resource is_ephemeral"),
+            &owned_resource_id,
+            &basic_variables.get_is_ephemeral_searchable_pairs(),
+        )?);
+
+        // Consuming an ephemeral bridge resource mints the bridge resources
+        let is_mint = is_input_resource.and(
+            &mul_chip,
+            layouter.namespace(|| "is_mint = is_input_resource * is_ephemeral"),
+            &is_ephemeral,
+        )?;
+
+        // Compute the event commitment from the owned resource
+        let asset = get_owned_resource_variable(
+            config.get_owned_resource_variable_config,
+            layouter.namespace(|| "get owned resource label"),
+            &owned_resource_id,
+            &basic_variables.get_label_searchable_pairs(),
+        )?;
+        let recipient_npk = get_owned_resource_variable(
+            config.get_owned_resource_variable_config,
+            layouter.namespace(|| "get owned resource value"),
+            &owned_resource_id,
+            &basic_variables.get_value_searchable_pairs(),
+        )?;
+        let quantity = get_owned_resource_variable(
+            config.get_owned_resource_variable_config,
+            layouter.namespace(|| "get owned resource quantity"),
+            &owned_resource_id,
+            &basic_variables.get_quantity_searchable_pairs(),
+        )?;
+        let event_nonce = get_owned_resource_variable(
+            config.get_owned_resource_variable_config,
+            layouter.namespace(|| "get owned resource nonce"),
+            &owned_resource_id,
+            &basic_variables.get_nonce_searchable_pairs(),
+        )?;
+        let event_commitment = poseidon_hash_gadget(
+            config.poseidon_config.clone(),
+            layouter.namespace(|| "event commitment"),
+            [
+                asset.clone(),
+                recipient_npk.clone(),
+                quantity.clone(),
+                event_nonce,
+            ],
+        )?;
+
+        // The claim resource is determined by the event, so that it has a unique nullifier
+        let npk = get_owned_resource_variable(
+            config.get_owned_resource_variable_config,
+            layouter.namespace(|| "get owned resource npk"),
+            &owned_resource_id,
+            &basic_variables.get_npk_searchable_pairs(),
+        )?;
+        let claim_npk = assign_free_constant(
+            layouter.namespace(|| "constant claim npk"),
+            config.advices[0],
+            *BRIDGE_CLAIM_NPK,
+        )?;
+        layouter.assign_region(
+            || "conditional equal: check the claim npk",
+            |mut region| {
+                config.conditional_equal_config.assign_region(
+                    &is_mint,
+                    &npk,
+                    &claim_npk,
+                    0,
+                    &mut region,
+                )
+            },
+        )?;
+        let rseed = get_owned_resource_variable(
+            config.get_owned_resource_variable_config,
+            layouter.namespace(|| "get owned resource rseed"),
+            &owned_resource_id,
+            &basic_variables.get_rseed_searchable_pairs(),
+        )?;
+        let zero = assign_free_constant(
+            layouter.namespace(|| "constant zero"),
+            config.advices[0],
+            pallas::Base::zero(),
+        )?;
+        layouter.assign_region(
+            || "conditional equal: check the claim rseed",
+            |mut region| {
+                config.conditional_equal_config.assign_region(
+                    &is_mint,
+                    &rseed,
+                    &zero,
+                    0,
+                    &mut region,
+                )
+            },
+        )?;
+
+        // Compute the external root from the event commitment
+        let merkle_chip = MerkleSelectChip::from_resource_logic_config(&config);
+        let event_path = fixed_depth_path::<EXTERNAL_EVENT_TREE_DEPTH>(&self.event_path)?;
+        let root = merkle_poseidon_gadget(
+            layouter.namespace(|| "external event merkle"),
+            &merkle_chip,
+            event_commitment.clone(),
+            &event_path,
+        )?;
+
+        // Check the external root when minting
+        let external_root = assign_free_advice(
+            layouter.namespace(|| "witness external_root"),
+            config.advices[0],
+            Value::known(self.external_root),
+        )?;
+        layouter.assign_region(
+            || "conditional equal: check the external root",
+            |mut region| {
+                config.conditional_equal_config.assign_region(
+                    &is_mint,
+                    &root,
+                    &external_root,
+                    0,
+                    &mut region,
+                )
+            },
+        )?;
+
+        // Mint the bridge resource of the event asset and quantity to the recipient
+        let logic = get_owned_resource_variable(
+            config.get_owned_resource_variable_config,
+            layouter.namespace(|| "get owned resource logic"),
+            &owned_resource_id,
+            &basic_variables.get_logic_searchable_pairs(),
+        )?;
+        let minted_resource = &basic_variables.output_resource_variables[0].resource_variables;
+        for (name, expected, minted) in [
+            ("logic", &logic, &minted_resource.logic),
+            ("label", &asset, &minted_resource.label),
+            ("quantity", &quantity, &minted_resource.quantity),
+            ("recipient", &recipient_npk, &minted_resource.npk),
+        ] {
+            layouter.assign_region(
+                || format!("conditional equal: check the minted {}", name),
+                |mut region| {
+                    config.conditional_equal_config.assign_region(
+                        &is_mint,
+                        expected,
+                        minted,
+                        0,
+                        &mut region,
+                    )
+                },
+            )?;
+        }
+
+        // Publicize the external root and the claimed event commitment
+        layouter.constrain_instance(
+            external_root.cell(),
+            config.instances,
+            RESOURCE_LOGIC_CIRCUIT_CUSTOM_PUBLIC_INPUT_BEGIN_IDX,
+        )?;
+        let claimed_event_commitment = MulInstructions::mul(
+            &mul_chip,
+            layouter.namespace(|| "claimed event commitment = is_mint * event_commitment"),
+            &is_mint,
+            &event_commitment,
+        )?;
+        layouter.constrain_instance(
+            claimed_event_commitment.cell(),
+            config.instances,
+            RESOURCE_LOGIC_CIRCUIT_CUSTOM_PUBLIC_INPUT_BEGIN_IDX + 1,
+        )?;
+
+        // Publicize the dynamic resource_logic commitments with default value
+        publicize_default_dynamic_resource_logic_commitments(
+            &mut layouter,
+            config.advices[0],
+            config.instances,
+        )?;
+
+        Ok(())
+    }
+
+    fn get_input_resources(&self) -> &[Resource; NUM_RESOURCE] {
+        &self.input_resources
+    }
+
+    fn get_output_resources(&self) -> &[Resource; NUM_RESOURCE] {
+        &self.output_resources
+    }
+
+    fn get_public_inputs(&self, mut rng: impl RngCore) -> ResourceLogicPublicInputs {
+        let mut public_inputs = self.get_mandatory_public_inputs();
+        let default_resource_logic_cm: [pallas::Base; 2] =
+            ResourceLogicCommitment::default().to_public_inputs();
+        public_inputs.extend(default_resource_logic_cm);
+        public_inputs.extend(default_resource_logic_cm);
+        public_inputs.push(self.external_root);
+        public_inputs.push(self.claimed_event_commitment());
+        let padding = ResourceLogicPublicInputs::get_public_input_padding(
+            public_inputs.len(),
+            &RandomSeed::random(&mut rng),
+        );
+        public_inputs.extend(padding);
+        public_inputs.into()
+    }
+
+    fn get_owned_resource_id(&self) -> OwnedResourceId {
+        self.owned_resource_id
+    }
+}
+
+resource_logic_circuit_impl!(BridgeResourceLogicCircuit);
+resource_logic_verifying_info_impl!(BridgeResourceLogicCircuit);
+resource_logic_circuit_encoding_impl!(BridgeResourceLogicCircuit {
+    1 => owned_resource_id,
+    2 => input_resources,
+    3 => output_resources,
+    4 => external_root,
+    // 5 was the event nonce, which is the nonce of the claim resource now
+    6 => event_path,
+}, |circuit| {
+    if circuit.event_path.get_path().len() != EXTERNAL_EVENT_TREE_DEPTH {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "invalid external event path depth",
+        ));
+    }
+    Ok(())
+});
+
+/// Creates the bridge resource sent to the burn address, bridging the asset out to the
+/// external recipient.
+pub fn create_burn_resource<R: RngCore>(
+    mut rng: R,
+    asset: pallas::Base,
+    external_recipient: pallas::Base,
+    quantity: u128,
+) -> Resource {
+    let rseed = pallas::Base::random(&mut rng);
+    let resource = Resource::new_burn_resource(
+        *COMPRESSED_BRIDGE_VK,
+        asset,
+        external_recipient,
+        quantity,
+        false,
+        rseed,
+    );
+    debug_assert!(is_burned(&resource));
+    resource
+}
+
+#[test]
+fn test_halo2_bridge_resource_logic_circuit() {
+    use crate::burn::prove_burned;
+    use crate::circuit::verify_failure::assert_verifies;
+    use crate::constant::{RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE, TAIGA_COMMITMENT_TREE_DEPTH};
+    use crate::resource::{tests::random_resource, ResourceKind};
+    use halo2_proofs::dev::MockProver;
+    use rand::rngs::OsRng;
+
+    let mut rng = OsRng;
+    let recipient = random_resource(&mut rng);
+    let event = BridgeEvent {
+        asset: pallas::Base::random(&mut rng),
+        recipient_npk: recipient.get_npk(),
+        quantity: 5u128,
+        event_nonce: pallas::Base::random(&mut rng),
+    };
+    let event_path = MerklePath::random(&mut rng, EXTERNAL_EVENT_TREE_DEPTH);
+    let external_root = event_path.root(Node::from(event.commitment())).inner();
+
+    // Bridging in: consume the ephemeral claim resource and mint to the recipient
+    let mint_circuit = {
+        let claim_resource = event.create_claim_resource();
+        let input_resources = [claim_resource, random_resource(&mut rng)];
+        let output_resources = [
+            event.create_minted_resource(&mut rng),
+            random_resource(&mut rng),
+        ];
+        BridgeResourceLogicCircuit {
+            owned_resource_id: input_resources[0].get_nf().unwrap().into(),
+            input_resources,
+            output_resources,
+            external_root,
+            event_path: event_path.clone(),
+        }
+    };
+
+    // Test serialization
+    let mint_circuit = {
+        let circuit_bytes = mint_circuit.to_bytes();
+        BridgeResourceLogicCircuit::from_bytes(&circuit_bytes)
+    };
+
+    let public_inputs = mint_circuit.get_public_inputs(&mut rng);
+    assert_eq!(
+        public_inputs.get_from_index(RESOURCE_LOGIC_CIRCUIT_CUSTOM_PUBLIC_INPUT_BEGIN_IDX + 1),
+        event.commitment()
+    );
+    let prover = MockProver::<pallas::Base>::run(
+        RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+        &mint_circuit,
+        vec![public_inputs.to_vec()],
+    )
+    .unwrap();
+    assert_verifies(&prover);
+
+    // The mint fails against another external root
+    {
+        let mut circuit = mint_circuit.clone();
+        circuit.external_root = pallas::Base::random(&mut rng);
+        let public_inputs = circuit.get_public_inputs(&mut rng);
+        let prover = MockProver::<pallas::Base>::run(
+            RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+            &circuit,
+            vec![public_inputs.to_vec()],
+        )
+        .unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    // The mint fails to another recipient, or of another kind or quantity
+    let minted_resource = mint_circuit.output_resources[0];
+    for forged_minted_resource in [
+        random_resource(&mut rng),
+        Resource {
+            quantity: event.quantity + 1,
+            ..minted_resource
+        },
+        Resource {
+            kind: ResourceKind::new(*COMPRESSED_BRIDGE_VK, pallas::Base::random(&mut rng)),
+            ..minted_resource
+        },
+        Resource {
+            kind: ResourceKind::new(pallas::Base::random(&mut rng), event.asset),
+            ..minted_resource
+        },
+    ] {
+        let mut circuit = mint_circuit.clone();
+        circuit.output_resources[0] = forged_minted_resource;
+        let public_inputs = circuit.get_public_inputs(&mut rng);
+        let prover = MockProver::<pallas::Base>::run(
+            RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+            &circuit,
+            vec![public_inputs.to_vec()],
+        )
+        .unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    // The event can only be claimed with its own claim resource, whose nullifier is unique
+    assert_eq!(
+        mint_circuit.input_resources[0].get_nf(),
+        Some(event.nullifier())
+    );
+    let mut forged_claim_resources = [event.create_claim_resource(); 2];
+    forged_claim_resources[0].nk_container = NullifierKeyContainer::random_key(&mut rng);
+    forged_claim_resources[1].rseed = pallas::Base::random(&mut rng);
+    for forged_claim_resource in forged_claim_resources {
+        assert_ne!(forged_claim_resource.get_nf(), Some(event.nullifier()));
+        let mut circuit = mint_circuit.clone();
+        circuit.input_resources[0] = forged_claim_resource;
+        circuit.owned_resource_id = forged_claim_resource.get_nf().unwrap().into();
+        let public_inputs = circuit.get_public_inputs(&mut rng);
+        let prover = MockProver::<pallas::Base>::run(
+            RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+            &circuit,
+            vec![public_inputs.to_vec()],
+        )
+        .unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    // Bridging out: send the bridge resource to the burn address
+    let burn_resource = create_burn_resource(
+        &mut rng,
+        event.asset,
+        pallas::Base::random(&mut rng),
+        event.quantity,
+    );
+    let burn_circuit = BridgeResourceLogicCircuit {
+        owned_resource_id: burn_resource.commitment().into(),
+        input_resources: [(); NUM_RESOURCE].map(|_| random_resource(&mut rng)),
+        output_resources: [burn_resource, random_resource(&mut rng)],
+        ..Default::default()
+    };
+    let public_inputs = burn_circuit.get_public_inputs(&mut rng);
+    assert_eq!(
+        public_inputs.get_from_index(RESOURCE_LOGIC_CIRCUIT_CUSTOM_PUBLIC_INPUT_BEGIN_IDX + 1),
+        pallas::Base::zero()
+    );
+    let prover = MockProver::<pallas::Base>::run(
+        RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+        &burn_circuit,
+        vec![public_inputs.to_vec()],
+    )
+    .unwrap();
+    assert_verifies(&prover);
+
+    // Prove the destruction to the external chain
+    let merkle_path = MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH);
+    let anchor = burn_resource.calculate_root(&merkle_path);
+    let burn_proof = prove_burned(&burn_resource, merkle_path).unwrap();
+    assert!(burn_proof.verify(&anchor).is_ok());
+}
@@ -16,39 +16,45 @@ use crate::{
         resource_logic_bytecode::{ResourceLogicByteCode, ResourceLogicRepresentation},
         resource_logic_circuit::{
             BasicResourceLogicVariables, ResourceLogicCircuit, ResourceLogicConfig,
-            ResourceLogicPublicInputs, ResourceLogicVerifyingInfo, ResourceLogicVerifyingInfoTrait,
+            ResourceLogicPublicInputs, ResourceLogicVerifyingInfoTrait,
         },
+        verify_failure::NamedCheck,
     },
-    constant::{NUM_RESOURCE, SETUP_PARAMS_MAP},
+    constant::{DEFAULT_PARAMS_STORE, NUM_RESOURCE},
     error::TransactionError,
     nullifier::Nullifier,
-    proof::Proof,
-    resource::{RandomSeed, Resource},
+    resource::{OwnedResourceId, RandomSeed, Resource},
     resource_logic_commitment::ResourceLogicCommitment,
     resource_logic_vk::ResourceLogicVerifyingKey,
-    utils::read_base_field,
 };
-use borsh::{BorshDeserialize, BorshSerialize};
 use halo2_proofs::arithmetic::Field;
 use halo2_proofs::{
     circuit::{floor_planner, Layouter, Value},
-    plonk::{keygen_pk, keygen_vk, Circuit, ConstraintSystem, Error},
+    plonk::{Circuit, ConstraintSystem, Error},
 };
 use lazy_static::lazy_static;
-use pasta_curves::{group::ff::PrimeField, pallas};
-use rand::rngs::OsRng;
+use pasta_curves::pallas;
 use rand::RngCore;
 
 lazy_static! {
     pub static ref CASCADE_INTENT_VK: ResourceLogicVerifyingKey =
-        CascadeIntentResourceLogicCircuit::default().get_resource_logic_vk();
+        CascadeIntentResourceLogicCircuit::default()
+            .get_resource_logic_vk()
+            .unwrap();
     pub static ref COMPRESSED_CASCADE_INTENT_VK: pallas::Base = CASCADE_INTENT_VK.get_compressed();
 }
 
+// The checks of the intent, the ids tag the explanations of their failures
+pub const CASCADE_INTENT_LABEL: NamedCheck = NamedCheck::new("check label", "CASCADE_INTENT_LABEL");
+pub const CASCADE_INTENT_CASCADE_RESOURCE: NamedCheck = NamedCheck::new(
+    "conditional equal: check the cascade resource",
+    "CASCADE_INTENT_CASCADE_RESOURCE",
+);
+
 // CascadeIntentResourceLogicCircuit
 #[derive(Clone, Debug, Default)]
 pub struct CascadeIntentResourceLogicCircuit {
-    pub owned_resource_id: pallas::Base,
+    pub owned_resource_id: OwnedResourceId,
     pub input_resources: [Resource; NUM_RESOURCE],
     pub output_resources: [Resource; NUM_RESOURCE],
     // use the resource commitment to identify the resource.
@@ -64,14 +70,6 @@ impl CascadeIntentResourceLogicCircuit {
     pub fn to_bytecode(&self) -> ResourceLogicByteCode {
         ResourceLogicByteCode::new(ResourceLogicRepresentation::CascadeIntent, self.to_bytes())
     }
-
-    pub fn to_bytes(&self) -> Vec<u8> {
-        borsh::to_vec(&self).unwrap()
-    }
-
-    pub fn from_bytes(bytes: &Vec<u8>) -> Self {
-        BorshDeserialize::deserialize(&mut bytes.as_ref()).unwrap()
-    }
 }
 
 impl ResourceLogicCircuit for CascadeIntentResourceLogicCircuit {
@@ -108,13 +106,13 @@ impl ResourceLogicCircuit for CascadeIntentResourceLogicCircuit {
 
         // check the label of intent resource
         layouter.assign_region(
-            || "check label",
+            || CASCADE_INTENT_LABEL.region_name(),
             |mut region| region.constrain_equal(cascade_resource_cm.cell(), label.cell()),
         )?;
 
         // check the cascade resource
         layouter.assign_region(
-            || "conditional equal: check the cascade resource",
+            || CASCADE_INTENT_CASCADE_RESOURCE.region_name(),
             |mut region| {
                 config.conditional_equal_config.assign_region(
                     &is_input_resource,
@@ -158,48 +156,19 @@ impl ResourceLogicCircuit for CascadeIntentResourceLogicCircuit {
         public_inputs.into()
     }
 
-    fn get_owned_resource_id(&self) -> pallas::Base {
+    fn get_owned_resource_id(&self) -> OwnedResourceId {
         self.owned_resource_id
     }
 }
 
 resource_logic_circuit_impl!(CascadeIntentResourceLogicCircuit);
 resource_logic_verifying_info_impl!(CascadeIntentResourceLogicCircuit);
-
-impl BorshSerialize for CascadeIntentResourceLogicCircuit {
-    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
-        writer.write_all(&self.owned_resource_id.to_repr())?;
-        for input in self.input_resources.iter() {
-            input.serialize(writer)?;
-        }
-
-        for output in self.output_resources.iter() {
-            output.serialize(writer)?;
-        }
-
-        writer.write_all(&self.cascade_resource_cm.to_repr())?;
-        Ok(())
-    }
-}
-
-impl BorshDeserialize for CascadeIntentResourceLogicCircuit {
-    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
-        let owned_resource_id = read_base_field(reader)?;
-        let input_resources: Vec<_> = (0..NUM_RESOURCE)
-            .map(|_| Resource::deserialize_reader(reader))
-            .collect::<Result<_, _>>()?;
-        let output_resources: Vec<_> = (0..NUM_RESOURCE)
-            .map(|_| Resource::deserialize_reader(reader))
-            .collect::<Result<_, _>>()?;
-        let cascade_resource_cm = read_base_field(reader)?;
-        Ok(Self {
-            owned_resource_id,
-            input_resources: input_resources.try_into().unwrap(),
-            output_resources: output_resources.try_into().unwrap(),
-            cascade_resource_cm,
-        })
-    }
-}
+resource_logic_circuit_encoding_impl!(CascadeIntentResourceLogicCircuit {
+    1 => owned_resource_id,
+    2 => input_resources,
+    3 => output_resources,
+    4 => cascade_resource_cm,
+});
 
 pub fn create_intent_resource<R: RngCore>(
     mut rng: R,
@@ -213,7 +182,7 @@ pub fn create_intent_resource<R: RngCore>(
         *COMPRESSED_CASCADE_INTENT_VK,
         label,
         pallas::Base::zero(),
-        1u64,
+        1u128,
         nk,
         nonce,
         true,
@@ -223,6 +192,7 @@ pub fn create_intent_resource<R: RngCore>(
 
 #[test]
 fn test_halo2_cascade_intent_resource_logic_circuit() {
+    use crate::circuit::verify_failure::assert_verifies;
     use crate::constant::RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE;
     use crate::resource::tests::random_resource;
     use halo2_proofs::arithmetic::Field;
@@ -239,7 +209,7 @@ fn test_halo2_cascade_intent_resource_logic_circuit() {
         let output_resources = [(); NUM_RESOURCE].map(|_| random_resource(&mut rng));
 
         CascadeIntentResourceLogicCircuit {
-            owned_resource_id: input_resources[0].get_nf().unwrap().inner(),
+            owned_resource_id: input_resources[0].get_nf().unwrap().into(),
             input_resources,
             output_resources,
             cascade_resource_cm,
@@ -260,5 +230,5 @@ fn test_halo2_cascade_intent_resource_logic_circuit() {
         vec![public_inputs.to_vec()],
     )
     .unwrap();
-    assert_eq!(prover.verify(), Ok(()));
+    assert_verifies(&prover);
 }
@@ -7,30 +7,28 @@ use crate::{
         },
         resource_logic_circuit::{
             BasicResourceLogicVariables, ResourceLogicCircuit, ResourceLogicConfig,
-            ResourceLogicPublicInputs, ResourceLogicVerifyingInfo, ResourceLogicVerifyingInfoTrait,
+            ResourceLogicPublicInputs, ResourceLogicVerifyingInfoTrait,
         },
     },
     constant::{
-        NUM_RESOURCE, RESOURCE_LOGIC_CIRCUIT_CUSTOM_PUBLIC_INPUT_BEGIN_IDX, SETUP_PARAMS_MAP,
+        DEFAULT_PARAMS_STORE, NUM_RESOURCE, RESOURCE_LOGIC_CIRCUIT_CUSTOM_PUBLIC_INPUT_BEGIN_IDX,
     },
     error::TransactionError,
-    proof::Proof,
-    resource::{RandomSeed, Resource},
+    resource::{OwnedResourceId, RandomSeed, Resource},
     resource_logic_commitment::ResourceLogicCommitment,
     resource_logic_vk::ResourceLogicVerifyingKey,
 };
 use halo2_proofs::{
     circuit::{floor_planner, Layouter, Value},
-    plonk::{keygen_pk, keygen_vk, Circuit, ConstraintSystem, Error},
+    plonk::{Circuit, ConstraintSystem, Error},
 };
 use pasta_curves::pallas;
-use rand::rngs::OsRng;
 use rand::RngCore;
 
 // FieldAdditionResourceLogicCircuit with a trivial constraint a + b = c.
 #[derive(Clone, Debug, Default)]
 struct FieldAdditionResourceLogicCircuit {
-    owned_resource_id: pallas::Base,
+    owned_resource_id: OwnedResourceId,
     input_resources: [Resource; NUM_RESOURCE],
     output_resources: [Resource; NUM_RESOURCE],
     a: pallas::Base,
@@ -102,7 +100,7 @@ impl ResourceLogicCircuit for FieldAdditionResourceLogicCircuit {
         public_inputs.into()
     }
 
-    fn get_owned_resource_id(&self) -> pallas::Base {
+    fn get_owned_resource_id(&self) -> OwnedResourceId {
         self.owned_resource_id
     }
 }
@@ -112,8 +110,9 @@ resource_logic_verifying_info_impl!(FieldAdditionResourceLogicCircuit);
 
 #[test]
 fn test_halo2_addition_resource_logic_circuit() {
+    use crate::circuit::verify_failure::assert_verifies;
     use crate::constant::RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE;
-    use crate::resource::tests::random_resource;
+    use crate::resource::{tests::random_resource, ResourceCommitment};
     use halo2_proofs::arithmetic::Field;
     use halo2_proofs::dev::MockProver;
     use rand::rngs::OsRng;
@@ -124,7 +123,7 @@ fn test_halo2_addition_resource_logic_circuit() {
         let output_resources = [(); NUM_RESOURCE].map(|_| random_resource(&mut rng));
         let a = pallas::Base::random(&mut rng);
         let b = pallas::Base::random(&mut rng);
-        let owned_resource_id = pallas::Base::random(&mut rng);
+        let owned_resource_id = ResourceCommitment::from(pallas::Base::random(&mut rng)).into();
         FieldAdditionResourceLogicCircuit {
             owned_resource_id,
             input_resources,
@@ -141,5 +140,5 @@ fn test_halo2_addition_resource_logic_circuit() {
         vec![public_inputs.to_vec()],
     )
     .unwrap();
-    assert_eq!(prover.verify(), Ok(()));
+    assert_verifies(&prover);
 }
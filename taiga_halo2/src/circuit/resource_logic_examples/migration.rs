@@ -0,0 +1,931 @@
+/// This example is to demonstrate the upgrade of a resource logic. A circuit fix changes the vk
+/// of the logic, and so the kind of its resources: the resources of the old kind would be
+/// stranded. The migration logic moves them to the new kind, one for one.
+///
+/// A kind upgrade (old kind -> new kind) is approved by the upgrade authority of the application,
+/// the label of the new kind commits to the approved old kinds. The migration goes through a
+/// migration ticket,
+/// an ephemeral resource with the migration logic labelled with the upgrade id:
+/// - ptx 1: inputs [old resource, _] -> outputs [old resource burned, ticket]. Creating the
+///   ticket checks the old resource is sent to the burn address with the quantity of the ticket.
+/// - ptx 2: inputs [ticket, new-kind mint] -> outputs [new resource, _]. Consuming the ticket
+///   checks the new-kind resource is minted from an ephemeral resource with the quantity of the
+///   ticket.
+///
+/// The tickets only balance if both ptxs are in the transaction. The logic of the new kind accepts
+/// the mint with `check_migration_mint`, which checks in the circuit that the ticket migrates an
+/// old kind approved by the label of the new kind: a ticket created from a kind the authority
+/// didn't approve can't mint it. The old logic has nothing to do. The upgrade id is also
+/// published by the migration logic, for the indexers.
+use crate::{
+    burn::is_burned,
+    circuit::{
+        blake2s::publicize_default_dynamic_resource_logic_commitments,
+        gadgets::{
+            assign_free_advice, assign_free_constant,
+            boolean::AssignedBool,
+            merkle_path::{merkle_poseidon_gadget, MerkleSelectChip},
+            mul::MulChip,
+            poseidon_hash::poseidon_hash_gadget,
+            target_resource_variable::{get_is_input_resource_flag, get_owned_resource_variable},
+        },
+        resource_logic_bytecode::{ResourceLogicByteCode, ResourceLogicRepresentation},
+        resource_logic_circuit::{
+            BasicResourceLogicVariables, CircuitFieldEncoding, ResourceLogicCircuit,
+            ResourceLogicConfig, ResourceLogicPublicInputs, ResourceLogicVerifyingInfoTrait,
+        },
+    },
+    constant::{
+        BURN_NPK, DEFAULT_PARAMS_STORE, NUM_RESOURCE,
+        RESOURCE_LOGIC_CIRCUIT_CUSTOM_PUBLIC_INPUT_BEGIN_IDX,
+    },
+    error::TransactionError,
+    merkle_tree::{Node, LR},
+    nullifier::Nullifier,
+    resource::{OwnedResourceId, RandomSeed, Resource, ResourceKind},
+    resource_logic_commitment::ResourceLogicCommitment,
+    resource_logic_vk::ResourceLogicVerifyingKey,
+    utils::{poseidon_hash_n, read_base_field},
+};
+use halo2_proofs::arithmetic::Field;
+use halo2_proofs::{
+    circuit::{floor_planner, AssignedCell, Layouter, Value},
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use lazy_static::lazy_static;
+use pasta_curves::{group::ff::PrimeField, pallas};
+use rand::RngCore;
+
+lazy_static! {
+    pub static ref MIGRATION_VK: ResourceLogicVerifyingKey =
+        MigrationResourceLogicCircuit::default()
+            .get_resource_logic_vk()
+            .unwrap();
+    pub static ref COMPRESSED_MIGRATION_VK: pallas::Base = MIGRATION_VK.get_compressed();
+}
+
+/// The upgrade of the resources of the old kind to the new kind.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct KindUpgrade {
+    pub old_kind: ResourceKind,
+    pub new_kind: ResourceKind,
+}
+
+impl KindUpgrade {
+    pub fn new(old_kind: ResourceKind, new_kind: ResourceKind) -> Self {
+        Self { old_kind, new_kind }
+    }
+
+    /// upgrade_id = poseidon_hash(old_logic || old_label || new_logic || new_label)
+    pub fn id(&self) -> pallas::Base {
+        poseidon_hash_n([
+            self.old_kind.logic,
+            self.old_kind.label,
+            self.new_kind.logic,
+            self.new_kind.label,
+        ])
+    }
+
+    /// Creates the migration ticket of the quantity, created with the old resource burned and
+    /// consumed with the new resource minted.
+    pub fn create_ticket_resource<R: RngCore>(
+        &self,
+        mut rng: R,
+        quantity: u128,
+        nk: pallas::Base,
+    ) -> Resource {
+        let rseed = pallas::Base::random(&mut rng);
+        let nonce = Nullifier::random(&mut rng);
+        Resource::new_input_resource(
+            *COMPRESSED_MIGRATION_VK,
+            self.id(),
+            pallas::Base::zero(),
+            quantity,
+            nk,
+            nonce,
+            true,
+            rseed,
+        )
+    }
+
+    /// Creates the old resource sent to the burn address, it keeps the value and the quantity
+    /// of the migrated resource.
+    pub fn create_burned_resource<R: RngCore>(&self, mut rng: R, old: &Resource) -> Resource {
+        debug_assert_eq!(old.kind, self.old_kind);
+        let rseed = pallas::Base::random(&mut rng);
+        let resource = Resource::new_burn_resource(
+            self.old_kind.logic,
+            self.old_kind.label,
+            old.value,
+            old.quantity,
+            false,
+            rseed,
+        );
+        debug_assert!(is_burned(&resource));
+        resource
+    }
+
+    /// Creates the ephemeral resource of the new kind consumed to mint the new resource.
+    pub fn create_mint_resource<R: RngCore>(
+        &self,
+        mut rng: R,
+        quantity: u128,
+        nk: pallas::Base,
+    ) -> Resource {
+        let rseed = pallas::Base::random(&mut rng);
+        let nonce = Nullifier::random(&mut rng);
+        Resource::new_input_resource(
+            self.new_kind.logic,
+            self.new_kind.label,
+            pallas::Base::zero(),
+            quantity,
+            nk,
+            nonce,
+            true,
+            rseed,
+        )
+    }
+
+    /// Creates the resource of the new kind replacing the old resource, with its value,
+    /// quantity and owner.
+    pub fn create_migrated_resource<R: RngCore>(&self, mut rng: R, old: &Resource) -> Resource {
+        debug_assert_eq!(old.kind, self.old_kind);
+        let rseed = pallas::Base::random(&mut rng);
+        Resource::new_output_resource(
+            self.new_kind.logic,
+            self.new_kind.label,
+            old.value,
+            old.quantity,
+            old.get_npk(),
+            false,
+            rseed,
+        )
+    }
+}
+
+impl CircuitFieldEncoding for KindUpgrade {
+    fn encode<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(&self.old_kind.logic.to_repr())?;
+        writer.write_all(&self.old_kind.label.to_repr())?;
+        writer.write_all(&self.new_kind.logic.to_repr())?;
+        writer.write_all(&self.new_kind.label.to_repr())?;
+        Ok(())
+    }
+
+    fn decode<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let old_logic = read_base_field(reader)?;
+        let old_label = read_base_field(reader)?;
+        let new_logic = read_base_field(reader)?;
+        let new_label = read_base_field(reader)?;
+        Ok(Self::new(
+            ResourceKind::new(old_logic, old_label),
+            ResourceKind::new(new_logic, new_label),
+        ))
+    }
+}
+
+/// The depth of the tree of the old kinds approved for a migration, it holds up to 16 kinds.
+pub const MIGRATION_APPROVAL_TREE_DEPTH: usize = 4;
+
+/// The upgrades to a new kind approved by the upgrade authority of an application. The label of
+/// the new kind commits to the approved old kinds:
+///
+/// label = poseidon_hash(app_label || approved_root)
+///
+/// where approved_root is the root of the poseidon merkle tree of the
+/// poseidon_hash(old_logic || old_label) of the approved old kinds, padded with zero leaves. The
+/// approved kinds are fixed with the new kind: approving another old kind later gives another
+/// kind.
+#[derive(Clone, Debug, Default)]
+pub struct ApprovedUpgrades {
+    new_logic: pallas::Base,
+    app_label: pallas::Base,
+    old_kinds: Vec<ResourceKind>,
+}
+
+impl ApprovedUpgrades {
+    pub fn new(new_logic: pallas::Base, app_label: pallas::Base) -> Self {
+        Self {
+            new_logic,
+            app_label,
+            old_kinds: vec![],
+        }
+    }
+
+    pub fn approve(&mut self, old_kind: ResourceKind) -> Result<(), TransactionError> {
+        if self.old_kinds.contains(&old_kind) {
+            return Ok(());
+        }
+        if self.old_kinds.len() == 1 << MIGRATION_APPROVAL_TREE_DEPTH {
+            return Err(TransactionError::TooManyApprovedUpgrades(
+                self.old_kinds.len() + 1,
+            ));
+        }
+        self.old_kinds.push(old_kind);
+        Ok(())
+    }
+
+    /// The new kind, its label commits to the approved old kinds.
+    pub fn new_kind(&self) -> ResourceKind {
+        ResourceKind::new(
+            self.new_logic,
+            poseidon_hash_n([self.app_label, self.approved_root()]),
+        )
+    }
+
+    /// The upgrade of the old kind to the new kind.
+    pub fn upgrade(&self, old_kind: ResourceKind) -> KindUpgrade {
+        KindUpgrade::new(old_kind, self.new_kind())
+    }
+
+    pub fn is_approved(&self, upgrade_id: &pallas::Base) -> bool {
+        self.old_kinds
+            .iter()
+            .any(|old_kind| self.upgrade(*old_kind).id() == *upgrade_id)
+    }
+
+    /// Checks the upgrade id published by a migration logic proof is approved.
+    pub fn check_public_inputs(
+        &self,
+        public_inputs: &ResourceLogicPublicInputs,
+    ) -> Result<(), TransactionError> {
+        let upgrade_id =
+            public_inputs.get_from_index(RESOURCE_LOGIC_CIRCUIT_CUSTOM_PUBLIC_INPUT_BEGIN_IDX);
+        if !self.is_approved(&upgrade_id) {
+            return Err(TransactionError::UnapprovedUpgrade);
+        }
+        Ok(())
+    }
+
+    /// The witness of `check_migration_mint` for the mints migrating the approved old kind.
+    pub fn approval(&self, old_kind: &ResourceKind) -> Option<MigrationApproval> {
+        let mut index = self.old_kinds.iter().position(|kind| kind == old_kind)?;
+        let mut level = self.leaves();
+        let mut path = [(pallas::Base::zero(), LR::L); MIGRATION_APPROVAL_TREE_DEPTH];
+        for sibling in path.iter_mut() {
+            // The sibling of an even node is on its right
+            *sibling = if index % 2 == 0 {
+                (level[index + 1], LR::R)
+            } else {
+                (level[index - 1], LR::L)
+            };
+            level = parent_level(&level);
+            index /= 2;
+        }
+        Some(MigrationApproval {
+            app_label: self.app_label,
+            approved_root: level[0],
+            old_kind: *old_kind,
+            path,
+        })
+    }
+
+    pub fn approved_root(&self) -> pallas::Base {
+        let mut level = self.leaves();
+        while level.len() > 1 {
+            level = parent_level(&level);
+        }
+        level[0]
+    }
+
+    fn leaves(&self) -> Vec<pallas::Base> {
+        let mut leaves: Vec<_> = self
+            .old_kinds
+            .iter()
+            .map(|kind| poseidon_hash_n([kind.logic, kind.label]))
+            .collect();
+        leaves.resize(1 << MIGRATION_APPROVAL_TREE_DEPTH, pallas::Base::zero());
+        leaves
+    }
+}
+
+fn parent_level(level: &[pallas::Base]) -> Vec<pallas::Base> {
+    level
+        .chunks(2)
+        .map(|pair| Node::combine(&pair[0].into(), &pair[1].into()).inner())
+        .collect()
+}
+
+/// The witness of `check_migration_mint`: the approved old kind of the migration and its path
+/// in the tree of the approved old kinds.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MigrationApproval {
+    pub app_label: pallas::Base,
+    pub approved_root: pallas::Base,
+    pub old_kind: ResourceKind,
+    pub path: [(pallas::Base, LR); MIGRATION_APPROVAL_TREE_DEPTH],
+}
+
+// MigrationResourceLogicCircuit
+#[derive(Clone, Debug, Default)]
+pub struct MigrationResourceLogicCircuit {
+    pub owned_resource_id: OwnedResourceId,
+    pub input_resources: [Resource; NUM_RESOURCE],
+    pub output_resources: [Resource; NUM_RESOURCE],
+    pub upgrade: KindUpgrade,
+}
+
+impl MigrationResourceLogicCircuit {
+    pub fn to_bytecode(&self) -> ResourceLogicByteCode {
+        ResourceLogicByteCode::new(ResourceLogicRepresentation::Migration, self.to_bytes())
+    }
+}
+
+// Selects the lhs if the owned ticket is consumed, the rhs if it's created.
+fn select(
+    config: &ResourceLogicConfig,
+    layouter: &mut impl Layouter<pallas::Base>,
+    is_input_resource: &AssignedBool,
+    lhs: &AssignedCell<pallas::Base, pallas::Base>,
+    rhs: &AssignedCell<pallas::Base, pallas::Base>,
+) -> Result<AssignedCell<pallas::Base, pallas::Base>, Error> {
+    layouter.assign_region(
+        || "conditional select",
+        |mut region| {
+            config.conditional_select_config.assign_region(
+                is_input_resource,
+                lhs,
+                rhs,
+                0,
+                &mut region,
+            )
+        },
+    )
+}
+
+fn constrain_equal(
+    layouter: &mut impl Layouter<pallas::Base>,
+    name: &str,
+    lhs: &AssignedCell<pallas::Base, pallas::Base>,
+    rhs: &AssignedCell<pallas::Base, pallas::Base>,
+) -> Result<(), Error> {
+    layouter.assign_region(
+        || name,
+        |mut region| region.constrain_equal(lhs.cell(), rhs.cell()),
+    )
+}
+
+impl ResourceLogicCircuit for MigrationResourceLogicCircuit {
+    // Add custom constraints
+    fn custom_constraints(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<pallas::Base>,
+        basic_variables: BasicResourceLogicVariables,
+    ) -> Result<(), Error> {
+        let owned_resource_id = basic_variables.get_owned_resource_id();
+        let is_input_resource = get_is_input_resource_flag(
+            config.get_is_input_resource_flag_config,
+            layouter.namespace(|| "get is_input_resource_flag"),
+            &owned_resource_id,
+            &basic_variables.get_input_resource_nfs(),
+            &basic_variables.get_output_resource_cms(),
+        )?;
+
+        // Check the label of the ticket is the upgrade id
+        let old_logic = assign_free_advice(
+            layouter.namespace(|| "witness old logic"),
+            config.advices[0],
+            Value::known(self.upgrade.old_kind.logic),
+        )?;
+        let old_label = assign_free_advice(
+            layouter.namespace(|| "witness old label"),
+            config.advices[0],
+            Value::known(self.upgrade.old_kind.label),
+        )?;
+        let new_logic = assign_free_advice(
+            layouter.namespace(|| "witness new logic"),
+            config.advices[0],
+            Value::known(self.upgrade.new_kind.logic),
+        )?;
+        let new_label = assign_free_advice(
+            layouter.namespace(|| "witness new label"),
+            config.advices[0],
+            Value::known(self.upgrade.new_kind.label),
+        )?;
+        let upgrade_id = poseidon_hash_gadget(
+            config.poseidon_config.clone(),
+            layouter.namespace(|| "upgrade id"),
+            [
+                old_logic.clone(),
+                old_label.clone(),
+                new_logic.clone(),
+                new_label.clone(),
+            ],
+        )?;
+        let label = get_owned_resource_variable(
+            config.get_owned_resource_variable_config,
+            layouter.namespace(|| "get owned resource label"),
+            &owned_resource_id,
+            &basic_variables.get_label_searchable_pairs(),
+        )?;
+        constrain_equal(&mut layouter, "check the upgrade id", &label, &upgrade_id)?;
+        let quantity = get_owned_resource_variable(
+            config.get_owned_resource_variable_config,
+            layouter.namespace(|| "get owned resource quantity"),
+            &owned_resource_id,
+            &basic_variables.get_quantity_searchable_pairs(),
+        )?;
+
+        // Consuming the ticket mints the new kind: the second input is the ephemeral mint and the
+        // first output the new resource. Creating the ticket burns the old kind: the first input
+        // is the old resource and the first output its burn.
+        let expected_logic = select(
+            &config,
+            &mut layouter,
+            &is_input_resource,
+            &new_logic,
+            &old_logic,
+        )?;
+        let expected_label = select(
+            &config,
+            &mut layouter,
+            &is_input_resource,
+            &new_label,
+            &old_label,
+        )?;
+        let mint = &basic_variables.input_resource_variables[1].resource_variables;
+        let old = &basic_variables.input_resource_variables[0].resource_variables;
+        let output = &basic_variables.output_resource_variables[0].resource_variables;
+        for (expected, mint_variable, old_variable, output_variable) in [
+            (&expected_logic, &mint.logic, &old.logic, &output.logic),
+            (&expected_label, &mint.label, &old.label, &output.label),
+            (&quantity, &mint.quantity, &old.quantity, &output.quantity),
+        ] {
+            let input_variable = select(
+                &config,
+                &mut layouter,
+                &is_input_resource,
+                mint_variable,
+                old_variable,
+            )?;
+            constrain_equal(&mut layouter, "check the input", &input_variable, expected)?;
+            constrain_equal(&mut layouter, "check the output", output_variable, expected)?;
+        }
+
+        // The mint is ephemeral
+        let one = assign_free_constant(
+            layouter.namespace(|| "one"),
+            config.advices[0],
+            pallas::Base::one(),
+        )?;
+        let is_ephemeral = select(
+            &config,
+            &mut layouter,
+            &is_input_resource,
+            &mint.is_ephemeral,
+            &one,
+        )?;
+        constrain_equal(
+            &mut layouter,
+            "check the mint is ephemeral",
+            &is_ephemeral,
+            &one,
+        )?;
+
+        // The burned resource is sent to the burn address
+        let burn_npk = assign_free_constant(
+            layouter.namespace(|| "burn npk"),
+            config.advices[0],
+            *BURN_NPK,
+        )?;
+        let expected_npk = select(
+            &config,
+            &mut layouter,
+            &is_input_resource,
+            &output.npk,
+            &burn_npk,
+        )?;
+        constrain_equal(&mut layouter, "check the burn", &output.npk, &expected_npk)?;
+
+        // Publicize the upgrade id
+        layouter.constrain_instance(
+            upgrade_id.cell(),
+            config.instances,
+            RESOURCE_LOGIC_CIRCUIT_CUSTOM_PUBLIC_INPUT_BEGIN_IDX,
+        )?;
+
+        // Publicize the dynamic resource_logic commitments with default value
+        publicize_default_dynamic_resource_logic_commitments(
+            &mut layouter,
+            config.advices[0],
+            config.instances,
+        )?;
+
+        Ok(())
+    }
+
+    fn get_input_resources(&self) -> &[Resource; NUM_RESOURCE] {
+        &self.input_resources
+    }
+
+    fn get_output_resources(&self) -> &[Resource; NUM_RESOURCE] {
+        &self.output_resources
+    }
+
+    fn get_public_inputs(&self, mut rng: impl RngCore) -> ResourceLogicPublicInputs {
+        let mut public_inputs = self.get_mandatory_public_inputs();
+        let default_resource_logic_cm: [pallas::Base; 2] =
+            ResourceLogicCommitment::default().to_public_inputs();
+        public_inputs.extend(default_resource_logic_cm);
+        public_inputs.extend(default_resource_logic_cm);
+        public_inputs.push(self.upgrade.id());
+        let padding = ResourceLogicPublicInputs::get_public_input_padding(
+            public_inputs.len(),
+            &RandomSeed::random(&mut rng),
+        );
+        public_inputs.extend(padding);
+        public_inputs.into()
+    }
+
+    fn get_owned_resource_id(&self) -> OwnedResourceId {
+        self.owned_resource_id
+    }
+}
+
+resource_logic_circuit_impl!(MigrationResourceLogicCircuit);
+resource_logic_verifying_info_impl!(MigrationResourceLogicCircuit);
+resource_logic_circuit_encoding_impl!(MigrationResourceLogicCircuit {
+    1 => owned_resource_id,
+    2 => input_resources,
+    3 => output_resources,
+    4 => upgrade,
+});
+
+/// The check for the logic of the new kind accepting the migration mints: consuming an ephemeral
+/// resource of the kind requires a migration ticket as the first input, and the ticket must
+/// migrate an old kind approved by the label of the kind, see `ApprovedUpgrades`. A logic minting
+/// the kind for other purposes would combine the check with its own mint conditions.
+pub fn check_migration_mint(
+    config: &ResourceLogicConfig,
+    mut layouter: impl Layouter<pallas::Base>,
+    basic_variables: &BasicResourceLogicVariables,
+    is_input_resource: &AssignedBool,
+    approval: &MigrationApproval,
+) -> Result<(), Error> {
+    let mul_chip = MulChip::construct(config.mul_config.clone());
+    let owned_resource_id = basic_variables.get_owned_resource_id();
+    // is_ephemeral is boolean-constrained in the resource commitment
+    let is_ephemeral = AssignedBool::from_bool_checked(get_owned_resource_variable(
+        config.get_owned_resource_variable_config,
+        layouter.namespace(|| "get owned resource is_ephemeral"),
+        &owned_resource_id,
+        &basic_variables.get_is_ephemeral_searchable_pairs(),
+    )?);
+    let is_mint = is_input_resource.and(
+        &mul_chip,
+        layouter.namespace(|| "is_mint = is_input_resource * is_ephemeral"),
+        &is_ephemeral,
+    )?;
+    let logic = get_owned_resource_variable(
+        config.get_owned_resource_variable_config,
+        layouter.namespace(|| "get owned resource logic"),
+        &owned_resource_id,
+        &basic_variables.get_logic_searchable_pairs(),
+    )?;
+    let label = get_owned_resource_variable(
+        config.get_owned_resource_variable_config,
+        layouter.namespace(|| "get owned resource label"),
+        &owned_resource_id,
+        &basic_variables.get_label_searchable_pairs(),
+    )?;
+
+    // The label of the kind commits to the approved old kinds
+    let app_label = assign_free_advice(
+        layouter.namespace(|| "witness app label"),
+        config.advices[0],
+        Value::known(approval.app_label),
+    )?;
+    let approved_root = assign_free_advice(
+        layouter.namespace(|| "witness approved root"),
+        config.advices[0],
+        Value::known(approval.approved_root),
+    )?;
+    let committed_label = poseidon_hash_gadget(
+        config.poseidon_config.clone(),
+        layouter.namespace(|| "label commitment"),
+        [app_label, approved_root.clone()],
+    )?;
+
+    // The old kind is in the approved kinds
+    let old_logic = assign_free_advice(
+        layouter.namespace(|| "witness old logic"),
+        config.advices[0],
+        Value::known(approval.old_kind.logic),
+    )?;
+    let old_label = assign_free_advice(
+        layouter.namespace(|| "witness old label"),
+        config.advices[0],
+        Value::known(approval.old_kind.label),
+    )?;
+    let old_kind_leaf = poseidon_hash_gadget(
+        config.poseidon_config.clone(),
+        layouter.namespace(|| "old kind leaf"),
+        [old_logic.clone(), old_label.clone()],
+    )?;
+    let root = merkle_poseidon_gadget(
+        layouter.namespace(|| "approved root"),
+        &MerkleSelectChip::from_resource_logic_config(config),
+        old_kind_leaf,
+        &approval.path,
+    )?;
+
+    // The ticket migrates the old kind to the kind of the mint
+    let upgrade_id = poseidon_hash_gadget(
+        config.poseidon_config.clone(),
+        layouter.namespace(|| "upgrade id"),
+        [old_logic, old_label, logic, label.clone()],
+    )?;
+    let migration_vk = assign_free_constant(
+        layouter.namespace(|| "migration vk"),
+        config.advices[0],
+        *COMPRESSED_MIGRATION_VK,
+    )?;
+    let ticket = &basic_variables.input_resource_variables[0].resource_variables;
+    for (name, lhs, rhs) in [
+        ("the label commitment", &label, &committed_label),
+        ("the approved kinds", &root, &approved_root),
+        ("the ticket logic", &ticket.logic, &migration_vk),
+        ("the ticket upgrade", &ticket.label, &upgrade_id),
+    ] {
+        layouter.assign_region(
+            || format!("conditional equal: check {name}"),
+            |mut region| {
+                config
+                    .conditional_equal_config
+                    .assign_region(&is_mint, lhs, rhs, 0, &mut region)
+            },
+        )?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_halo2_migration_resource_logic_circuit() {
+    use crate::circuit::verify_failure::assert_verifies;
+    use crate::constant::RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE;
+    use crate::resource::tests::random_resource;
+    use halo2_proofs::dev::MockProver;
+    use rand::rngs::OsRng;
+
+    let mut rng = OsRng;
+    let old_kind = ResourceKind::new(
+        pallas::Base::random(&mut rng),
+        pallas::Base::random(&mut rng),
+    );
+    let mut approved_upgrades = ApprovedUpgrades::new(
+        pallas::Base::random(&mut rng),
+        pallas::Base::random(&mut rng),
+    );
+    let upgrade = approved_upgrades.upgrade(old_kind);
+    let mut old_resource = random_resource(&mut rng);
+    old_resource.kind = upgrade.old_kind;
+    let nk = old_resource.get_nk().unwrap();
+    let ticket = upgrade.create_ticket_resource(&mut rng, old_resource.quantity, nk);
+
+    let run = |circuit: &MigrationResourceLogicCircuit| {
+        let public_inputs = circuit.get_public_inputs(OsRng);
+        MockProver::<pallas::Base>::run(
+            RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+            circuit,
+            vec![public_inputs.to_vec()],
+        )
+        .unwrap()
+    };
+
+    // Creating the ticket burns the old resource
+    let burn_circuit = MigrationResourceLogicCircuit {
+        owned_resource_id: ticket.commitment().into(),
+        input_resources: [old_resource, random_resource(&mut rng)],
+        output_resources: [
+            upgrade.create_burned_resource(&mut rng, &old_resource),
+            ticket,
+        ],
+        upgrade,
+    };
+    assert_verifies(&run(&burn_circuit));
+
+    // The old resource is not burned
+    {
+        let mut circuit = burn_circuit.clone();
+        circuit.output_resources[0].nk_container = old_resource.nk_container;
+        assert!(run(&circuit).verify().is_err());
+    }
+
+    // Consuming the ticket mints the new resource
+    let mint_circuit = MigrationResourceLogicCircuit {
+        owned_resource_id: ticket.get_nf().unwrap().into(),
+        input_resources: [
+            ticket,
+            upgrade.create_mint_resource(&mut rng, old_resource.quantity, nk),
+        ],
+        output_resources: [
+            upgrade.create_migrated_resource(&mut rng, &old_resource),
+            random_resource(&mut rng),
+        ],
+        upgrade,
+    };
+
+    // Test serialization
+    let mint_circuit = {
+        let circuit_bytes = mint_circuit.to_bytes();
+        MigrationResourceLogicCircuit::from_bytes(&circuit_bytes)
+    };
+    assert_verifies(&run(&mint_circuit));
+
+    // The published upgrade id is checked against the approved upgrades
+    let public_inputs = mint_circuit.get_public_inputs(&mut rng);
+    assert!(matches!(
+        approved_upgrades.check_public_inputs(&public_inputs),
+        Err(TransactionError::UnapprovedUpgrade)
+    ));
+    approved_upgrades.approve(old_kind).unwrap();
+    // Approving changes the label of the new kind
+    let upgrade = approved_upgrades.upgrade(old_kind);
+    let public_inputs = MigrationResourceLogicCircuit {
+        upgrade,
+        ..mint_circuit.clone()
+    }
+    .get_public_inputs(&mut rng);
+    assert!(approved_upgrades
+        .check_public_inputs(&public_inputs)
+        .is_ok());
+
+    // The new resource has another quantity
+    {
+        let mut circuit = mint_circuit.clone();
+        circuit.output_resources[0].quantity += 1;
+        assert!(run(&circuit).verify().is_err());
+    }
+
+    // The mint is not ephemeral
+    {
+        let mut circuit = mint_circuit.clone();
+        circuit.input_resources[1].is_ephemeral = false;
+        assert!(run(&circuit).verify().is_err());
+    }
+
+    // The ticket is labelled with another upgrade
+    {
+        let mut circuit = mint_circuit.clone();
+        circuit.upgrade.new_kind.label = pallas::Base::random(&mut rng);
+        assert!(run(&circuit).verify().is_err());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A logic of the new kind only accepting the migration mints
+    #[derive(Clone, Debug, Default)]
+    struct MigratedKindResourceLogicCircuit {
+        owned_resource_id: OwnedResourceId,
+        input_resources: [Resource; NUM_RESOURCE],
+        output_resources: [Resource; NUM_RESOURCE],
+        approval: MigrationApproval,
+    }
+
+    impl ResourceLogicCircuit for MigratedKindResourceLogicCircuit {
+        fn custom_constraints(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+            basic_variables: BasicResourceLogicVariables,
+        ) -> Result<(), Error> {
+            let is_input_resource = get_is_input_resource_flag(
+                config.get_is_input_resource_flag_config,
+                layouter.namespace(|| "get is_input_resource_flag"),
+                &basic_variables.get_owned_resource_id(),
+                &basic_variables.get_input_resource_nfs(),
+                &basic_variables.get_output_resource_cms(),
+            )?;
+            check_migration_mint(
+                &config,
+                layouter.namespace(|| "check migration mint"),
+                &basic_variables,
+                &is_input_resource,
+                &self.approval,
+            )?;
+            publicize_default_dynamic_resource_logic_commitments(
+                &mut layouter,
+                config.advices[0],
+                config.instances,
+            )
+        }
+
+        fn get_input_resources(&self) -> &[Resource; NUM_RESOURCE] {
+            &self.input_resources
+        }
+
+        fn get_output_resources(&self) -> &[Resource; NUM_RESOURCE] {
+            &self.output_resources
+        }
+
+        fn get_public_inputs(&self, mut rng: impl RngCore) -> ResourceLogicPublicInputs {
+            let mut public_inputs = self.get_mandatory_public_inputs();
+            let default_resource_logic_cm: [pallas::Base; 2] =
+                ResourceLogicCommitment::default().to_public_inputs();
+            public_inputs.extend(default_resource_logic_cm);
+            public_inputs.extend(default_resource_logic_cm);
+            let padding = ResourceLogicPublicInputs::get_public_input_padding(
+                public_inputs.len(),
+                &RandomSeed::random(&mut rng),
+            );
+            public_inputs.extend(padding);
+            public_inputs.into()
+        }
+
+        fn get_owned_resource_id(&self) -> OwnedResourceId {
+            self.owned_resource_id
+        }
+    }
+
+    resource_logic_circuit_impl!(MigratedKindResourceLogicCircuit);
+    resource_logic_verifying_info_impl!(MigratedKindResourceLogicCircuit);
+
+    #[test]
+    fn test_check_migration_mint() {
+        use crate::circuit::verify_failure::assert_verifies;
+        use crate::constant::RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE;
+        use crate::resource::tests::random_resource;
+        use halo2_proofs::dev::MockProver;
+        use rand::rngs::OsRng;
+
+        let mut rng = OsRng;
+        let random_kind =
+            || ResourceKind::new(pallas::Base::random(OsRng), pallas::Base::random(OsRng));
+        let (old_kind, other_old_kind, worthless_kind) =
+            (random_kind(), random_kind(), random_kind());
+        let mut approved_upgrades = ApprovedUpgrades::new(
+            pallas::Base::random(&mut rng),
+            pallas::Base::random(&mut rng),
+        );
+        approved_upgrades.approve(other_old_kind).unwrap();
+        approved_upgrades.approve(old_kind).unwrap();
+        assert!(approved_upgrades.approval(&worthless_kind).is_none());
+
+        // The mint of the new kind consumes a ticket of the upgrade
+        let mint_circuit = |upgrade: KindUpgrade, approval: MigrationApproval| {
+            let mut rng = OsRng;
+            let nk = pallas::Base::random(&mut rng);
+            let mint = upgrade.create_mint_resource(&mut rng, 5, nk);
+            MigratedKindResourceLogicCircuit {
+                owned_resource_id: mint.get_nf().unwrap().into(),
+                input_resources: [upgrade.create_ticket_resource(&mut rng, 5, nk), mint],
+                output_resources: [random_resource(&mut rng), random_resource(&mut rng)],
+                approval,
+            }
+        };
+        let run = |circuit: &MigratedKindResourceLogicCircuit| {
+            let public_inputs = circuit.get_public_inputs(OsRng);
+            MockProver::<pallas::Base>::run(
+                RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+                circuit,
+                vec![public_inputs.to_vec()],
+            )
+            .unwrap()
+        };
+
+        let approval = approved_upgrades.approval(&old_kind).unwrap();
+        assert_eq!(approval.approved_root, approved_upgrades.approved_root());
+        let circuit = mint_circuit(approved_upgrades.upgrade(old_kind), approval);
+        assert_verifies(&run(&circuit));
+
+        // A self-issued ticket of a kind the authority didn't approve
+        let forged_upgrade = approved_upgrades.upgrade(worthless_kind);
+        let forged_approval = MigrationApproval {
+            old_kind: worthless_kind,
+            ..approval
+        };
+        assert!(run(&mint_circuit(forged_upgrade, forged_approval))
+            .verify()
+            .is_err());
+        // The approval of another kind doesn't match the ticket
+        assert!(run(&mint_circuit(forged_upgrade, approval))
+            .verify()
+            .is_err());
+
+        // The label of the kind doesn't commit to the approved kinds
+        let other_label = MigrationApproval {
+            app_label: pallas::Base::random(&mut rng),
+            ..approval
+        };
+        assert!(run(&mint_circuit(
+            approved_upgrades.upgrade(old_kind),
+            other_label
+        ))
+        .verify()
+        .is_err());
+
+        // The approval tree is full
+        let mut full = ApprovedUpgrades::default();
+        for _ in 0..1 << MIGRATION_APPROVAL_TREE_DEPTH {
+            full.approve(random_kind()).unwrap();
+        }
+        assert!(matches!(
+            full.approve(random_kind()),
+            Err(TransactionError::TooManyApprovedUpgrades(17))
+        ));
+    }
+}
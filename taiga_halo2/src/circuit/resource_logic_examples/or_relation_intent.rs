@@ -13,42 +13,58 @@ use crate::{
         resource_logic_bytecode::{ResourceLogicByteCode, ResourceLogicRepresentation},
         resource_logic_circuit::{
             BasicResourceLogicVariables, ResourceLogicCircuit, ResourceLogicConfig,
-            ResourceLogicPublicInputs, ResourceLogicVerifyingInfo, ResourceLogicVerifyingInfoTrait,
+            ResourceLogicPublicInputs, ResourceLogicVerifyingInfoTrait,
         },
         resource_logic_examples::token::{Token, TOKEN_VK},
+        verify_failure::NamedCheck,
     },
-    constant::{NUM_RESOURCE, SETUP_PARAMS_MAP},
+    constant::{DEFAULT_PARAMS_STORE, NUM_RESOURCE},
     error::TransactionError,
     nullifier::Nullifier,
-    proof::Proof,
-    resource::{RandomSeed, Resource},
+    resource::{OwnedResourceId, RandomSeed, Resource},
     resource_logic_commitment::ResourceLogicCommitment,
     resource_logic_vk::ResourceLogicVerifyingKey,
     utils::poseidon_hash_n,
-    utils::read_base_field,
 };
-use borsh::{BorshDeserialize, BorshSerialize};
 use halo2_proofs::arithmetic::Field;
 use halo2_proofs::{
     circuit::{floor_planner, Layouter, Value},
-    plonk::{keygen_pk, keygen_vk, Circuit, ConstraintSystem, Error},
+    plonk::{Circuit, ConstraintSystem, Error},
 };
 use lazy_static::lazy_static;
-use pasta_curves::{group::ff::PrimeField, pallas};
-use rand::rngs::OsRng;
+use pasta_curves::pallas;
 use rand::RngCore;
 
 lazy_static! {
     pub static ref OR_RELATION_INTENT_VK: ResourceLogicVerifyingKey =
-        OrRelationIntentResourceLogicCircuit::default().get_resource_logic_vk();
+        OrRelationIntentResourceLogicCircuit::default()
+            .get_resource_logic_vk()
+            .unwrap();
     pub static ref COMPRESSED_OR_RELATION_INTENT_VK: pallas::Base =
         OR_RELATION_INTENT_VK.get_compressed();
 }
 
+// The checks of the intent, the ids tag the explanations of their failures
+pub const OR_INTENT_LABEL: NamedCheck = NamedCheck::new("check label", "OR_INTENT_LABEL");
+pub const OR_INTENT_RECEIVED_TOKEN_LOGIC: NamedCheck = NamedCheck::new(
+    "conditional equal: check received token logic",
+    "OR_INTENT_RECEIVED_TOKEN_LOGIC",
+);
+pub const OR_INTENT_RECEIVED_TOKEN_NPK: NamedCheck = NamedCheck::new(
+    "conditional equal: check received token npk",
+    "OR_INTENT_RECEIVED_TOKEN_NPK",
+);
+pub const OR_INTENT_RECEIVED_TOKEN_VALUE: NamedCheck = NamedCheck::new(
+    "conditional equal: check received token value",
+    "OR_INTENT_RECEIVED_TOKEN_VALUE",
+);
+pub const OR_INTENT_CONDITIONS: NamedCheck =
+    NamedCheck::new("extended or relation", "OR_INTENT_CONDITIONS");
+
 // OrRelationIntentResourceLogicCircuit
 #[derive(Clone, Debug, Default)]
 pub struct OrRelationIntentResourceLogicCircuit {
-    pub owned_resource_id: pallas::Base,
+    pub owned_resource_id: OwnedResourceId,
     pub input_resources: [Resource; NUM_RESOURCE],
     pub output_resources: [Resource; NUM_RESOURCE],
     pub token_1: Token,
@@ -64,9 +80,9 @@ impl OrRelationIntentResourceLogicCircuit {
         receiver_npk: pallas::Base,
         receiver_value: pallas::Base,
     ) -> pallas::Base {
-        let token_property_1 = token_1.encode_name();
+        let token_property_1 = token_1.encode_label();
         let token_quantity_1 = token_1.encode_quantity();
-        let token_property_2 = token_2.encode_name();
+        let token_property_2 = token_2.encode_label();
         let token_quantity_2 = token_2.encode_quantity();
         poseidon_hash_n([
             token_property_1,
@@ -85,14 +101,6 @@ impl OrRelationIntentResourceLogicCircuit {
             self.to_bytes(),
         )
     }
-
-    pub fn to_bytes(&self) -> Vec<u8> {
-        borsh::to_vec(&self).unwrap()
-    }
-
-    pub fn from_bytes(bytes: &Vec<u8>) -> Self {
-        BorshDeserialize::deserialize(&mut bytes.as_ref()).unwrap()
-    }
 }
 
 impl ResourceLogicCircuit for OrRelationIntentResourceLogicCircuit {
@@ -119,9 +127,9 @@ impl ResourceLogicCircuit for OrRelationIntentResourceLogicCircuit {
         )?;
 
         let token_property_1 = assign_free_advice(
-            layouter.namespace(|| "witness token name in token_1"),
+            layouter.namespace(|| "witness token label in token_1"),
             config.advices[0],
-            Value::known(self.token_1.encode_name()),
+            Value::known(self.token_1.encode_label()),
         )?;
 
         let token_quantity_1 = assign_free_advice(
@@ -131,9 +139,9 @@ impl ResourceLogicCircuit for OrRelationIntentResourceLogicCircuit {
         )?;
 
         let token_property_2 = assign_free_advice(
-            layouter.namespace(|| "witness token name in token_2"),
+            layouter.namespace(|| "witness token label in token_2"),
             config.advices[0],
-            Value::known(self.token_2.encode_name()),
+            Value::known(self.token_2.encode_label()),
         )?;
 
         let token_quantity_2 = assign_free_advice(
@@ -179,13 +187,13 @@ impl ResourceLogicCircuit for OrRelationIntentResourceLogicCircuit {
 
         // check the label of intent resource
         layouter.assign_region(
-            || "check label",
+            || OR_INTENT_LABEL.region_name(),
             |mut region| region.constrain_equal(encoded_label.cell(), label.cell()),
         )?;
 
         // check the resource_logic vk of output resource
         layouter.assign_region(
-            || "conditional equal: check resource_logic vk",
+            || OR_INTENT_RECEIVED_TOKEN_LOGIC.region_name(),
             |mut region| {
                 config.conditional_equal_config.assign_region(
                     &is_input_resource,
@@ -201,7 +209,7 @@ impl ResourceLogicCircuit for OrRelationIntentResourceLogicCircuit {
 
         // check npk
         layouter.assign_region(
-            || "conditional equal: check npk",
+            || OR_INTENT_RECEIVED_TOKEN_NPK.region_name(),
             |mut region| {
                 config.conditional_equal_config.assign_region(
                     &is_input_resource,
@@ -217,7 +225,7 @@ impl ResourceLogicCircuit for OrRelationIntentResourceLogicCircuit {
 
         // check value
         layouter.assign_region(
-            || "conditional equal: check value",
+            || OR_INTENT_RECEIVED_TOKEN_VALUE.region_name(),
             |mut region| {
                 config.conditional_equal_config.assign_region(
                     &is_input_resource,
@@ -239,7 +247,7 @@ impl ResourceLogicCircuit for OrRelationIntentResourceLogicCircuit {
             .resource_variables
             .quantity;
         layouter.assign_region(
-            || "extended or relatioin",
+            || OR_INTENT_CONDITIONS.region_name(),
             |mut region| {
                 config.extended_or_relation_config.assign_region(
                     &is_input_resource,
@@ -287,59 +295,22 @@ impl ResourceLogicCircuit for OrRelationIntentResourceLogicCircuit {
         public_inputs.into()
     }
 
-    fn get_owned_resource_id(&self) -> pallas::Base {
+    fn get_owned_resource_id(&self) -> OwnedResourceId {
         self.owned_resource_id
     }
 }
 
 resource_logic_circuit_impl!(OrRelationIntentResourceLogicCircuit);
 resource_logic_verifying_info_impl!(OrRelationIntentResourceLogicCircuit);
-
-impl BorshSerialize for OrRelationIntentResourceLogicCircuit {
-    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
-        writer.write_all(&self.owned_resource_id.to_repr())?;
-        for input in self.input_resources.iter() {
-            input.serialize(writer)?;
-        }
-
-        for output in self.output_resources.iter() {
-            output.serialize(writer)?;
-        }
-
-        self.token_1.serialize(writer)?;
-        self.token_2.serialize(writer)?;
-
-        writer.write_all(&self.receiver_npk.to_repr())?;
-        writer.write_all(&self.receiver_value.to_repr())?;
-
-        Ok(())
-    }
-}
-
-impl BorshDeserialize for OrRelationIntentResourceLogicCircuit {
-    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
-        let owned_resource_id = read_base_field(reader)?;
-        let input_resources: Vec<_> = (0..NUM_RESOURCE)
-            .map(|_| Resource::deserialize_reader(reader))
-            .collect::<Result<_, _>>()?;
-        let output_resources: Vec<_> = (0..NUM_RESOURCE)
-            .map(|_| Resource::deserialize_reader(reader))
-            .collect::<Result<_, _>>()?;
-        let token_1 = Token::deserialize_reader(reader)?;
-        let token_2 = Token::deserialize_reader(reader)?;
-        let receiver_npk = read_base_field(reader)?;
-        let receiver_value = read_base_field(reader)?;
-        Ok(Self {
-            owned_resource_id,
-            input_resources: input_resources.try_into().unwrap(),
-            output_resources: output_resources.try_into().unwrap(),
-            token_1,
-            token_2,
-            receiver_npk,
-            receiver_value,
-        })
-    }
-}
+resource_logic_circuit_encoding_impl!(OrRelationIntentResourceLogicCircuit {
+    1 => owned_resource_id,
+    2 => input_resources,
+    3 => output_resources,
+    4 => token_1,
+    5 => token_2,
+    6 => receiver_npk,
+    7 => receiver_value,
+});
 
 pub fn create_intent_resource<R: RngCore>(
     mut rng: R,
@@ -361,7 +332,7 @@ pub fn create_intent_resource<R: RngCore>(
         *COMPRESSED_OR_RELATION_INTENT_VK,
         label,
         pallas::Base::zero(),
-        1u64,
+        1u128,
         nk,
         nonce,
         true,
@@ -369,8 +340,37 @@ pub fn create_intent_resource<R: RngCore>(
     )
 }
 
+// The pure intent counterpart of create_intent_resource, it's published without consuming a
+// real resource.
+pub fn create_pure_intent_resource<R: RngCore>(
+    mut rng: R,
+    token_1: &Token,
+    token_2: &Token,
+    receiver_npk: pallas::Base,
+    receiver_value: pallas::Base,
+    nk: pallas::Base,
+) -> Resource {
+    let label = OrRelationIntentResourceLogicCircuit::encode_label(
+        token_1,
+        token_2,
+        receiver_npk,
+        receiver_value,
+    );
+    let rseed = pallas::Base::random(&mut rng);
+    let nonce = Nullifier::random(&mut rng);
+    Resource::new_pure_intent_resource(
+        *COMPRESSED_OR_RELATION_INTENT_VK,
+        label,
+        pallas::Base::zero(),
+        nk,
+        nonce,
+        rseed,
+    )
+}
+
 #[test]
 fn test_halo2_or_relation_intent_resource_logic_circuit() {
+    use crate::circuit::verify_failure::assert_verifies;
     use crate::constant::RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE;
     use crate::{
         circuit::resource_logic_examples::token::COMPRESSED_TOKEN_VK,
@@ -386,7 +386,7 @@ fn test_halo2_or_relation_intent_resource_logic_circuit() {
         let token_1 = Token::new("token1".to_string(), 1u64);
         let token_2 = Token::new("token2".to_string(), 2u64);
         output_resources[0].kind.logic = *COMPRESSED_TOKEN_VK;
-        output_resources[0].kind.label = token_1.encode_name();
+        output_resources[0].kind.label = token_1.encode_label();
         output_resources[0].quantity = token_1.quantity();
 
         let nk = pallas::Base::random(&mut rng);
@@ -402,7 +402,7 @@ fn test_halo2_or_relation_intent_resource_logic_circuit() {
         let padding_input_resource = Resource::random_padding_resource(&mut rng);
         let input_resources = [intent_resource, padding_input_resource];
         OrRelationIntentResourceLogicCircuit {
-            owned_resource_id: input_resources[0].get_nf().unwrap().inner(),
+            owned_resource_id: input_resources[0].get_nf().unwrap().into(),
             input_resources,
             output_resources,
             token_1,
@@ -426,5 +426,5 @@ fn test_halo2_or_relation_intent_resource_logic_circuit() {
         vec![public_inputs.to_vec()],
     )
     .unwrap();
-    assert_eq!(prover.verify(), Ok(()));
+    assert_verifies(&prover);
 }
@@ -5,44 +5,94 @@ use crate::{
     circuit::{
         blake2s::publicize_default_dynamic_resource_logic_commitments,
         gadgets::{
-            assign_free_constant,
             mul::MulChip,
-            sub::{SubChip, SubInstructions},
+            range_check::RangeCheckChip,
+            sub::SubChip,
             target_resource_variable::{get_is_input_resource_flag, get_owned_resource_variable},
         },
         resource_logic_bytecode::{ResourceLogicByteCode, ResourceLogicRepresentation},
         resource_logic_circuit::{
             BasicResourceLogicVariables, ResourceLogicCircuit, ResourceLogicConfig,
-            ResourceLogicPublicInputs, ResourceLogicVerifyingInfo, ResourceLogicVerifyingInfoTrait,
+            ResourceLogicPublicInputs, ResourceLogicVerifyingInfoTrait,
         },
+        verify_failure::NamedCheck,
     },
-    constant::{NUM_RESOURCE, SETUP_PARAMS_MAP},
+    constant::{DEFAULT_PARAMS_STORE, NUM_RESOURCE},
     error::TransactionError,
-    proof::Proof,
-    resource::{RandomSeed, Resource},
+    resource::{OwnedResourceId, RandomSeed, Resource},
     resource_logic_commitment::ResourceLogicCommitment,
     resource_logic_vk::ResourceLogicVerifyingKey,
-    utils::read_base_field,
 };
-use borsh::{BorshDeserialize, BorshSerialize};
 use halo2_proofs::{
     circuit::{floor_planner, Layouter},
-    plonk::{keygen_pk, keygen_vk, Circuit, ConstraintSystem, Error},
+    plonk::{Circuit, ConstraintSystem, Error},
 };
 use lazy_static::lazy_static;
-use pasta_curves::{group::ff::PrimeField, pallas};
-use rand::rngs::OsRng;
+use pasta_curves::pallas;
 use rand::RngCore;
 
 pub mod swap;
-pub use swap::Swap;
+pub use swap::{EncryptedSwapOpening, Swap, SwapOpening};
 
 mod label;
 use label::PartialFulfillmentIntentLabel;
 
+// The checks of the intent, the ids tag the explanations of their failures
+pub const PF_INTENT_LABEL: NamedCheck = NamedCheck::new("check label", "PF_INTENT_LABEL");
+pub const PF_INTENT_BOUGHT_TOKEN_LOGIC: NamedCheck = NamedCheck::new(
+    "conditional equal: check bought token logic",
+    "PF_INTENT_BOUGHT_TOKEN_LOGIC",
+);
+pub const PF_INTENT_BOUGHT_TOKEN_LABEL: NamedCheck = NamedCheck::new(
+    "conditional equal: check bought token label",
+    "PF_INTENT_BOUGHT_TOKEN_LABEL",
+);
+pub const PF_INTENT_BOUGHT_TOKEN_NPK: NamedCheck = NamedCheck::new(
+    "conditional equal: check bought token npk",
+    "PF_INTENT_BOUGHT_TOKEN_NPK",
+);
+pub const PF_INTENT_BOUGHT_TOKEN_VALUE: NamedCheck = NamedCheck::new(
+    "conditional equal: check bought token value",
+    "PF_INTENT_BOUGHT_TOKEN_VALUE",
+);
+pub const PF_INTENT_SOLD_TOKEN_LOGIC: NamedCheck = NamedCheck::new(
+    "conditional equal: check sold token logic",
+    "PF_INTENT_SOLD_TOKEN_LOGIC",
+);
+pub const PF_INTENT_SOLD_TOKEN_LABEL: NamedCheck = NamedCheck::new(
+    "conditional equal: check sold token label",
+    "PF_INTENT_SOLD_TOKEN_LABEL",
+);
+pub const PF_INTENT_SOLD_TOKEN_QUANTITY: NamedCheck = NamedCheck::new(
+    "conditional equal: check sold token quantity",
+    "PF_INTENT_SOLD_TOKEN_QUANTITY",
+);
+pub const PF_INTENT_RETURNED_TOKEN_LOGIC: NamedCheck = NamedCheck::new(
+    "conditional equal: check returned token logic",
+    "PF_INTENT_RETURNED_TOKEN_LOGIC",
+);
+pub const PF_INTENT_RETURNED_TOKEN_LABEL: NamedCheck = NamedCheck::new(
+    "conditional equal: check returned token label",
+    "PF_INTENT_RETURNED_TOKEN_LABEL",
+);
+pub const PF_INTENT_RETURNED_TOKEN_NPK: NamedCheck = NamedCheck::new(
+    "conditional equal: check returned token npk",
+    "PF_INTENT_RETURNED_TOKEN_NPK",
+);
+pub const PF_INTENT_RETURNED_TOKEN_VALUE: NamedCheck = NamedCheck::new(
+    "conditional equal: check returned token value",
+    "PF_INTENT_RETURNED_TOKEN_VALUE",
+);
+pub const PF_INTENT_PRICE_RATIO: NamedCheck = NamedCheck::new(
+    "conditional equal: check price ratio",
+    "PF_INTENT_PRICE_RATIO",
+);
+
 lazy_static! {
     pub static ref PARTIAL_FULFILLMENT_INTENT_VK: ResourceLogicVerifyingKey =
-        PartialFulfillmentIntentResourceLogicCircuit::default().get_resource_logic_vk();
+        PartialFulfillmentIntentResourceLogicCircuit::default()
+            .get_resource_logic_vk()
+            .unwrap();
     pub static ref COMPRESSED_PARTIAL_FULFILLMENT_INTENT_VK: pallas::Base =
         PARTIAL_FULFILLMENT_INTENT_VK.get_compressed();
 }
@@ -50,7 +100,7 @@ lazy_static! {
 // PartialFulfillmentIntentResourceLogicCircuit
 #[derive(Clone, Debug, Default)]
 pub struct PartialFulfillmentIntentResourceLogicCircuit {
-    pub owned_resource_id: pallas::Base,
+    pub owned_resource_id: OwnedResourceId,
     pub input_resources: [Resource; NUM_RESOURCE],
     pub output_resources: [Resource; NUM_RESOURCE],
     pub swap: Swap,
@@ -63,14 +113,6 @@ impl PartialFulfillmentIntentResourceLogicCircuit {
             self.to_bytes(),
         )
     }
-
-    pub fn to_bytes(&self) -> Vec<u8> {
-        borsh::to_vec(&self).unwrap()
-    }
-
-    pub fn from_bytes(bytes: &Vec<u8>) -> Self {
-        BorshDeserialize::deserialize(&mut bytes.as_ref()).unwrap()
-    }
 }
 
 impl ResourceLogicCircuit for PartialFulfillmentIntentResourceLogicCircuit {
@@ -106,7 +148,7 @@ impl ResourceLogicCircuit for PartialFulfillmentIntentResourceLogicCircuit {
         //  - as witnessed in the swap, and
         //  - as encoded in the intent resource
         layouter.assign_region(
-            || "check label",
+            || PF_INTENT_LABEL.region_name(),
             |mut region| region.constrain_equal(encoded_label.cell(), owned_resource_label.cell()),
         )?;
 
@@ -125,20 +167,11 @@ impl ResourceLogicCircuit for PartialFulfillmentIntentResourceLogicCircuit {
             layouter.namespace(|| "is_input_resource checks"),
         )?;
 
-        let is_output_resource = {
-            let constant_one = assign_free_constant(
-                layouter.namespace(|| "one"),
-                config.advices[0],
-                pallas::Base::one(),
-            )?;
-            // TODO: use a nor gate to replace the sub gate.
-            SubInstructions::sub(
-                &sub_chip,
-                layouter.namespace(|| "expected_sold_quantity - returned_quantity"),
-                &is_input_resource,
-                &constant_one,
-            )?
-        };
+        let is_output_resource = is_input_resource.not(
+            &sub_chip,
+            layouter.namespace(|| "is_output_resource = 1 - is_input_resource"),
+            config.advices[0],
+        )?;
         // Conditional checks if is_output_resource == 1
         label.is_output_resource_checks(
             &is_output_resource,
@@ -154,6 +187,8 @@ impl ResourceLogicCircuit for PartialFulfillmentIntentResourceLogicCircuit {
             &config.conditional_equal_config,
             &sub_chip,
             &mul_chip,
+            &RangeCheckChip::construct(config.range_check_config),
+            config.advices[0],
             layouter.namespace(|| "is_partial_fulfillment checks"),
         )?;
 
@@ -189,49 +224,20 @@ impl ResourceLogicCircuit for PartialFulfillmentIntentResourceLogicCircuit {
         public_inputs.into()
     }
 
-    fn get_owned_resource_id(&self) -> pallas::Base {
+    fn get_owned_resource_id(&self) -> OwnedResourceId {
         self.owned_resource_id
     }
 }
 
 resource_logic_circuit_impl!(PartialFulfillmentIntentResourceLogicCircuit);
 resource_logic_verifying_info_impl!(PartialFulfillmentIntentResourceLogicCircuit);
-
-impl BorshSerialize for PartialFulfillmentIntentResourceLogicCircuit {
-    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
-        writer.write_all(&self.owned_resource_id.to_repr())?;
-        for input in self.input_resources.iter() {
-            input.serialize(writer)?;
-        }
-
-        for output in self.output_resources.iter() {
-            output.serialize(writer)?;
-        }
-
-        self.swap.serialize(writer)?;
-
-        Ok(())
-    }
-}
-
-impl BorshDeserialize for PartialFulfillmentIntentResourceLogicCircuit {
-    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
-        let owned_resource_id = read_base_field(reader)?;
-        let input_resources: Vec<_> = (0..NUM_RESOURCE)
-            .map(|_| Resource::deserialize_reader(reader))
-            .collect::<Result<_, _>>()?;
-        let output_resources: Vec<_> = (0..NUM_RESOURCE)
-            .map(|_| Resource::deserialize_reader(reader))
-            .collect::<Result<_, _>>()?;
-        let swap = Swap::deserialize_reader(reader)?;
-        Ok(Self {
-            owned_resource_id,
-            input_resources: input_resources.try_into().unwrap(),
-            output_resources: output_resources.try_into().unwrap(),
-            swap,
-        })
-    }
-}
+circuit_field_encoding_from_borsh!(Swap);
+resource_logic_circuit_encoding_impl!(PartialFulfillmentIntentResourceLogicCircuit {
+    1 => owned_resource_id,
+    2 => input_resources,
+    3 => output_resources,
+    4 => swap,
+});
 
 #[cfg(test)]
 mod tests {
@@ -270,7 +276,7 @@ mod tests {
         let output_resources = [intent_resource, output_padding_resource];
 
         let circuit = PartialFulfillmentIntentResourceLogicCircuit {
-            owned_resource_id: intent_resource.commitment().inner(),
+            owned_resource_id: intent_resource.commitment().into(),
             input_resources,
             output_resources,
             swap,
@@ -299,7 +305,7 @@ mod tests {
         let (input_resources, output_resources) = swap.fill(&mut rng, intent_resource, bob_sell);
 
         let circuit = PartialFulfillmentIntentResourceLogicCircuit {
-            owned_resource_id: intent_resource.get_nf().unwrap().inner(),
+            owned_resource_id: intent_resource.get_nf().unwrap().into(),
             input_resources,
             output_resources,
             swap,
@@ -328,7 +334,7 @@ mod tests {
         let (input_resources, output_resources) = swap.fill(&mut rng, intent_resource, bob_sell);
 
         let circuit = PartialFulfillmentIntentResourceLogicCircuit {
-            owned_resource_id: intent_resource.get_nf().unwrap().inner(),
+            owned_resource_id: intent_resource.get_nf().unwrap().into(),
             input_resources,
             output_resources,
             swap,
@@ -350,4 +356,106 @@ mod tests {
         .unwrap();
         prover.assert_satisfied();
     }
+
+    #[test]
+    fn hidden_swap_fulfillment() {
+        use borsh::BorshDeserialize;
+        use group::Group;
+
+        let mut rng = OsRng;
+        let sell = Token::new("token1".to_string(), 2u64);
+        let buy = Token::new("token2".to_string(), 4u64);
+
+        let sk = pallas::Scalar::random(&mut rng);
+        let auth = TokenAuthorization::from_sk_vk(&sk, &COMPRESSED_TOKEN_AUTH_VK);
+        let swap = Swap::random_hidden(&mut rng, sell, buy, auth);
+        assert!(swap.is_hidden());
+        let intent_resource = swap.create_intent_resource(&mut rng);
+
+        // The label doesn't match the public intent with the same parameters
+        let public_swap = Swap {
+            blind: pallas::Base::zero(),
+            ..swap.clone()
+        };
+        assert_ne!(intent_resource.get_label(), public_swap.encode_label());
+
+        // Only the solver can open the label
+        let solver_sk = pallas::Scalar::random(&mut rng);
+        let solver_pk = pallas::Point::generator() * solver_sk;
+        let hint_bytes = borsh::to_vec(&swap.encrypt_opening(&solver_pk, &mut rng)).unwrap();
+        let hint = EncryptedSwapOpening::try_from_slice(&hint_bytes).unwrap();
+        // A hint with an identity ephemeral key doesn't decode
+        {
+            use group::GroupEncoding;
+            let mut identity_hint_bytes = hint_bytes.clone();
+            identity_hint_bytes[..32].copy_from_slice(&pallas::Point::identity().to_bytes());
+            assert!(EncryptedSwapOpening::try_from_slice(&identity_hint_bytes).is_err());
+        }
+        let opening = hint
+            .decrypt(&solver_sk, &intent_resource.get_label())
+            .unwrap();
+        assert_eq!(opening, swap.opening());
+        assert_eq!(opening.bought_token, swap.buy.encode_label());
+        let other_sk = pallas::Scalar::random(&mut rng);
+        assert!(hint
+            .decrypt(&other_sk, &intent_resource.get_label())
+            .is_none());
+        assert!(hint
+            .decrypt(&solver_sk, &public_swap.encode_label())
+            .is_none());
+
+        let bob_sell = Token::new(swap.buy.name().inner().to_string(), 2u64);
+        let (input_resources, output_resources) = swap.fill(&mut rng, intent_resource, bob_sell);
+
+        let circuit = PartialFulfillmentIntentResourceLogicCircuit {
+            owned_resource_id: intent_resource.get_nf().unwrap().into(),
+            input_resources,
+            output_resources,
+            swap,
+        };
+        let circuit = {
+            let circuit_bytes = circuit.to_bytes();
+            PartialFulfillmentIntentResourceLogicCircuit::from_bytes(&circuit_bytes)
+        };
+        let public_inputs = circuit.get_public_inputs(&mut rng);
+
+        let prover = MockProver::<pallas::Base>::run(
+            RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+            &circuit,
+            vec![public_inputs.to_vec()],
+        )
+        .unwrap();
+        prover.assert_satisfied();
+
+        // A wrong blind doesn't open the label
+        let mut circuit = circuit;
+        circuit.swap.blind = pallas::Base::random(&mut rng);
+        let err = circuit.verify_transparently().unwrap_err();
+        assert!(err.failed_check_ids().contains(&PF_INTENT_LABEL.id));
+    }
+
+    #[test]
+    fn unbalanced_partial_fulfillment_fails_the_price_ratio_check() {
+        let mut rng = OsRng;
+        let sell = Token::new("token1".to_string(), 2u64);
+        let buy = Token::new("token2".to_string(), 4u64);
+
+        let swap = swap(&mut rng, sell, buy);
+        let intent_resource = swap.create_intent_resource(&mut rng);
+
+        let bob_sell = Token::new(swap.buy.name().inner().to_string(), 2u64);
+        let (input_resources, mut output_resources) =
+            swap.fill(&mut rng, intent_resource, bob_sell);
+        // Return more of the sold token than the price allows
+        output_resources[1].quantity += 1;
+
+        let circuit = PartialFulfillmentIntentResourceLogicCircuit {
+            owned_resource_id: intent_resource.get_nf().unwrap().into(),
+            input_resources,
+            output_resources,
+            swap,
+        };
+        let err = circuit.verify_transparently().unwrap_err();
+        assert!(err.failed_check_ids().contains(&PF_INTENT_PRICE_RATIO.id));
+    }
 }
@@ -1,16 +1,25 @@
 use crate::circuit::{
     gadgets::{
+        boolean::AssignedBool,
         conditional_equal::ConditionalEqualConfig,
         mul::{MulChip, MulInstructions},
         poseidon_hash::poseidon_hash_gadget,
+        range_check::RangeCheckChip,
         sub::{SubChip, SubInstructions},
     },
     resource_logic_circuit::BasicResourceLogicVariables,
+    resource_logic_examples::partial_fulfillment_intent::{
+        PF_INTENT_BOUGHT_TOKEN_LABEL, PF_INTENT_BOUGHT_TOKEN_LOGIC, PF_INTENT_BOUGHT_TOKEN_NPK,
+        PF_INTENT_BOUGHT_TOKEN_VALUE, PF_INTENT_PRICE_RATIO, PF_INTENT_RETURNED_TOKEN_LABEL,
+        PF_INTENT_RETURNED_TOKEN_LOGIC, PF_INTENT_RETURNED_TOKEN_NPK,
+        PF_INTENT_RETURNED_TOKEN_VALUE, PF_INTENT_SOLD_TOKEN_LABEL, PF_INTENT_SOLD_TOKEN_LOGIC,
+        PF_INTENT_SOLD_TOKEN_QUANTITY,
+    },
 };
 use halo2_gadgets::poseidon::Pow5Config as PoseidonConfig;
 use halo2_proofs::{
     circuit::{AssignedCell, Layouter},
-    plonk::Error,
+    plonk::{Advice, Column, Error},
 };
 use pasta_curves::pallas;
 
@@ -23,6 +32,7 @@ pub struct PartialFulfillmentIntentLabel {
     pub bought_token_quantity: AssignedCell<pallas::Base, pallas::Base>,
     pub receiver_npk: AssignedCell<pallas::Base, pallas::Base>,
     pub receiver_value: AssignedCell<pallas::Base, pallas::Base>,
+    pub blind: AssignedCell<pallas::Base, pallas::Base>,
 }
 
 impl PartialFulfillmentIntentLabel {
@@ -43,6 +53,7 @@ impl PartialFulfillmentIntentLabel {
                 self.token_resource_logic_vk.clone(),
                 self.receiver_npk.clone(),
                 self.receiver_value.clone(),
+                self.blind.clone(),
             ],
         )
     }
@@ -50,13 +61,13 @@ impl PartialFulfillmentIntentLabel {
     /// Checks to be enforced if `is_input_resource == 1`
     pub fn is_input_resource_checks(
         &self,
-        is_input_resource: &AssignedCell<pallas::Base, pallas::Base>,
+        is_input_resource: &AssignedBool,
         basic_variables: &BasicResourceLogicVariables,
         config: &ConditionalEqualConfig,
         mut layouter: impl Layouter<pallas::Base>,
     ) -> Result<(), Error> {
         layouter.assign_region(
-            || "conditional equal: check bought token vk",
+            || PF_INTENT_BOUGHT_TOKEN_LOGIC.region_name(),
             |mut region| {
                 config.assign_region(
                     is_input_resource,
@@ -71,7 +82,7 @@ impl PartialFulfillmentIntentLabel {
         )?;
 
         layouter.assign_region(
-            || "conditional equal: check bought token vk",
+            || PF_INTENT_BOUGHT_TOKEN_LABEL.region_name(),
             |mut region| {
                 config.assign_region(
                     is_input_resource,
@@ -87,7 +98,7 @@ impl PartialFulfillmentIntentLabel {
 
         // check npk
         layouter.assign_region(
-            || "conditional equal: check bought token npk",
+            || PF_INTENT_BOUGHT_TOKEN_NPK.region_name(),
             |mut region| {
                 config.assign_region(
                     is_input_resource,
@@ -103,7 +114,7 @@ impl PartialFulfillmentIntentLabel {
 
         // check value
         layouter.assign_region(
-            || "conditional equal: check bought token value",
+            || PF_INTENT_BOUGHT_TOKEN_VALUE.region_name(),
             |mut region| {
                 config.assign_region(
                     is_input_resource,
@@ -123,13 +134,13 @@ impl PartialFulfillmentIntentLabel {
     /// Checks to be enforced if `is_output_resource == 1`
     pub fn is_output_resource_checks(
         &self,
-        is_output_resource: &AssignedCell<pallas::Base, pallas::Base>,
+        is_output_resource: &AssignedBool,
         basic_variables: &BasicResourceLogicVariables,
         config: &ConditionalEqualConfig,
         mut layouter: impl Layouter<pallas::Base>,
     ) -> Result<(), Error> {
         layouter.assign_region(
-            || "conditional equal: check sold token resource_logic_vk",
+            || PF_INTENT_SOLD_TOKEN_LOGIC.region_name(),
             |mut region| {
                 config.assign_region(
                     is_output_resource,
@@ -144,7 +155,7 @@ impl PartialFulfillmentIntentLabel {
         )?;
 
         layouter.assign_region(
-            || "conditional equal: check sold token label",
+            || PF_INTENT_SOLD_TOKEN_LABEL.region_name(),
             |mut region| {
                 config.assign_region(
                     is_output_resource,
@@ -159,7 +170,7 @@ impl PartialFulfillmentIntentLabel {
         )?;
 
         layouter.assign_region(
-            || "conditional equal: check sold token quantity",
+            || PF_INTENT_SOLD_TOKEN_QUANTITY.region_name(),
             |mut region| {
                 config.assign_region(
                     is_output_resource,
@@ -177,17 +188,21 @@ impl PartialFulfillmentIntentLabel {
     }
 
     /// Checks to be enforced if `is_partial_fulfillment == 1`
+    #[allow(clippy::too_many_arguments)]
     pub fn is_partial_fulfillment_checks(
         &self,
-        is_input_resource: &AssignedCell<pallas::Base, pallas::Base>,
+        is_input_resource: &AssignedBool,
         basic_variables: &BasicResourceLogicVariables,
         config: &ConditionalEqualConfig,
         sub_chip: &SubChip<pallas::Base>,
         mul_chip: &MulChip<pallas::Base>,
+        range_check_chip: &RangeCheckChip,
+        advice: Column<Advice>,
         mut layouter: impl Layouter<pallas::Base>,
     ) -> Result<(), Error> {
+        // is_partial_fulfillment = is_input_resource && expected_bought_quantity != actual_bought_quantity
         let is_partial_fulfillment = {
-            let is_partial_fulfillment = SubInstructions::sub(
+            let unfilled_quantity = SubInstructions::sub(
                 sub_chip,
                 layouter
                     .namespace(|| "expected_bought_token_quantity - actual_bought_token_quantity"),
@@ -196,17 +211,23 @@ impl PartialFulfillmentIntentLabel {
                     .resource_variables
                     .quantity,
             )?;
-            MulInstructions::mul(
+            let is_partially_filled = AssignedBool::is_nonzero(
                 mul_chip,
-                layouter.namespace(|| "is_input * is_partial_fulfillment"),
-                is_input_resource,
-                &is_partial_fulfillment,
+                sub_chip,
+                layouter.namespace(|| "unfilled_quantity != 0"),
+                advice,
+                &unfilled_quantity,
+            )?;
+            is_input_resource.and(
+                mul_chip,
+                layouter.namespace(|| "is_input && is_partially_filled"),
+                &is_partially_filled,
             )?
         };
 
         // check returned token vk if it's partially fulfilled
         layouter.assign_region(
-            || "conditional equal: check returned token vk",
+            || PF_INTENT_RETURNED_TOKEN_LOGIC.region_name(),
             |mut region| {
                 config.assign_region(
                     &is_partial_fulfillment,
@@ -222,7 +243,7 @@ impl PartialFulfillmentIntentLabel {
 
         // check return token label if it's partially fulfilled
         layouter.assign_region(
-            || "conditional equal: check returned token label",
+            || PF_INTENT_RETURNED_TOKEN_LABEL.region_name(),
             |mut region| {
                 config.assign_region(
                     &is_partial_fulfillment,
@@ -237,7 +258,7 @@ impl PartialFulfillmentIntentLabel {
         )?;
 
         layouter.assign_region(
-            || "conditional equal: check returned token npk",
+            || PF_INTENT_RETURNED_TOKEN_NPK.region_name(),
             |mut region| {
                 config.assign_region(
                     &is_partial_fulfillment,
@@ -252,7 +273,7 @@ impl PartialFulfillmentIntentLabel {
         )?;
 
         layouter.assign_region(
-            || "conditional equal: check returned token value",
+            || PF_INTENT_RETURNED_TOKEN_VALUE.region_name(),
             |mut region| {
                 config.assign_region(
                     &is_partial_fulfillment,
@@ -277,6 +298,29 @@ impl PartialFulfillmentIntentLabel {
                     .quantity,
             )?;
 
+            // The products below must not wrap in the field: the intent quantities and the
+            // actual sold quantity are u64, the actual bought quantity is u128 by the compliance
+            // circuit. The actual sold quantity is only range checked if it's partially
+            // fulfilled, the returned resource may be anything otherwise.
+            range_check_chip.range_check_u64(
+                layouter.namespace(|| "range check expected_sold_quantity"),
+                &self.sold_token_quantity,
+            )?;
+            range_check_chip.range_check_u64(
+                layouter.namespace(|| "range check expected_bought_quantity"),
+                &self.bought_token_quantity,
+            )?;
+            let checked_actual_sold_quantity = MulInstructions::mul(
+                mul_chip,
+                layouter.namespace(|| "is_partial_fulfillment * actual_sold_quantity"),
+                &is_partial_fulfillment,
+                &actual_sold_quantity,
+            )?;
+            range_check_chip.range_check_u64(
+                layouter.namespace(|| "range check actual_sold_quantity"),
+                &checked_actual_sold_quantity,
+            )?;
+
             // check (expected_bought_quantity * actual_sold_quantity) == (expected_sold_quantity * actual_bought_quantity)
             // if it's partially fulfilled
             let expected_bought_mul_actual_sold_quantity = MulInstructions::mul(
@@ -295,17 +339,17 @@ impl PartialFulfillmentIntentLabel {
             )?;
 
             layouter.assign_region(
-                    || "conditional equal: expected_bought_quantity * actual_sold_quantity == expected_sold_quantity * actual_bought_quantity",
-                    |mut region| {
-                        config.assign_region(
-                            &is_partial_fulfillment,
-                            &expected_bought_mul_actual_sold_quantity,
-                            &expected_sold_mul_actual_bought_quantity,
-                            0,
-                            &mut region,
-                        )
-                    },
-                )?;
+                || PF_INTENT_PRICE_RATIO.region_name(),
+                |mut region| {
+                    config.assign_region(
+                        &is_partial_fulfillment,
+                        &expected_bought_mul_actual_sold_quantity,
+                        &expected_sold_mul_actual_bought_quantity,
+                        0,
+                        &mut region,
+                    )
+                },
+            )?;
         }
 
         Ok(())
@@ -4,9 +4,11 @@ use crate::{
         gadgets::assign_free_advice,
         resource_logic_examples::token::{Token, TokenAuthorization, TokenResource, TOKEN_VK},
     },
-    constant::NUM_RESOURCE,
+    constant::{NUM_RESOURCE, RESOURCE_ENCRYPTION_CIPHERTEXT_NUM},
+    ka::{EphemeralPublicKey, EphemeralSecretKey},
     resource::Resource,
-    utils::poseidon_hash_n,
+    resource_encryption::{ResourceCiphertext, ResourcePlaintext},
+    utils::{poseidon_hash_n, read_base_field, read_point},
 };
 use borsh::{BorshDeserialize, BorshSerialize};
 use halo2_proofs::arithmetic::Field;
@@ -14,14 +16,44 @@ use halo2_proofs::{
     circuit::{Layouter, Value},
     plonk::{Advice, Column, Error},
 };
-use pasta_curves::pallas;
+use pasta_curves::{
+    group::{ff::PrimeField, Group, GroupEncoding},
+    pallas,
+};
 use rand::RngCore;
 
-#[derive(Clone, Debug, Default, BorshSerialize, BorshDeserialize)]
+/// The swap behind a partial fulfillment intent.
+///
+/// The label of the intent resource is a commitment to the swap parameters, blinded by
+/// `blind`. A zero blind makes a public intent: anyone who guesses the tokens and quantities
+/// can recompute the label. A random blind hides the parameters until the intent is settled,
+/// and the owner sends the opening to the solver off-chain in an `EncryptedSwapOpening`.
+#[derive(Clone, Debug, Default)]
 pub struct Swap {
     pub sell: TokenResource,
     pub buy: Token,
     pub auth: TokenAuthorization,
+    pub blind: pallas::Base,
+}
+
+/// The parameters committed to in the label of the intent resource.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SwapOpening {
+    pub sold_token: pallas::Base,
+    pub sold_token_quantity: pallas::Base,
+    pub bought_token: pallas::Base,
+    pub bought_token_quantity: pallas::Base,
+    pub token_resource_logic_vk: pallas::Base,
+    pub receiver_npk: pallas::Base,
+    pub receiver_value: pallas::Base,
+    pub blind: pallas::Base,
+}
+
+/// The opening of a hidden swap, encrypted to the solver under an ephemeral DH key.
+#[derive(Clone, Debug)]
+pub struct EncryptedSwapOpening {
+    epk: EphemeralPublicKey,
+    ciphertext: ResourceCiphertext,
 }
 
 impl Swap {
@@ -38,7 +70,53 @@ impl Swap {
             sell.create_random_input_token_resource(&mut rng, nk, &auth)
         };
 
-        Swap { sell, buy, auth }
+        Swap {
+            sell,
+            buy,
+            auth,
+            blind: pallas::Base::zero(),
+        }
+    }
+
+    /// Creates a swap whose parameters are hidden by a random blind in the intent label.
+    pub fn random_hidden(
+        mut rng: impl RngCore,
+        sell: Token,
+        buy: Token,
+        auth: TokenAuthorization,
+    ) -> Self {
+        let blind = pallas::Base::random(&mut rng);
+        Swap {
+            blind,
+            ..Self::random(&mut rng, sell, buy, auth)
+        }
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.blind != pallas::Base::zero()
+    }
+
+    pub fn opening(&self) -> SwapOpening {
+        SwapOpening {
+            sold_token: self.sell.encode_label(),
+            sold_token_quantity: self.sell.encode_quantity(),
+            bought_token: self.buy.encode_label(),
+            bought_token_quantity: self.buy.encode_quantity(),
+            // Assuming the sold_token and bought_token have the same TOKEN_VK
+            token_resource_logic_vk: TOKEN_VK.get_compressed(),
+            receiver_npk: self.sell.resource().get_npk(),
+            receiver_value: self.sell.resource().value,
+            blind: self.blind,
+        }
+    }
+
+    /// Encrypts the opening of the intent label to the solver.
+    pub fn encrypt_opening(
+        &self,
+        solver_pk: &pallas::Point,
+        rng: impl RngCore,
+    ) -> EncryptedSwapOpening {
+        EncryptedSwapOpening::encrypt(&self.opening(), solver_pk, rng)
     }
 
     /// Either:
@@ -67,10 +145,12 @@ impl Swap {
         let returned_resource = if offer.quantity() < self.buy.quantity() {
             let filled_quantity = offer.quantity() / ratio;
             let returned_quantity = self.sell.quantity - filled_quantity;
-            let returned_token = Token::new(
+            let returned_token = Token::new_with_quantity_mode(
                 self.sell.token_name().inner().to_string(),
                 returned_quantity,
-            );
+                self.sell.quantity_mode(),
+            )
+            .with_auth_policy(self.sell.auth_policy);
             *returned_token
                 .create_random_output_token_resource(
                     &mut rng,
@@ -89,16 +169,7 @@ impl Swap {
     }
 
     pub fn encode_label(&self) -> pallas::Base {
-        poseidon_hash_n([
-            self.sell.encode_name(),
-            self.sell.encode_quantity(),
-            self.buy.encode_name(),
-            self.buy.encode_quantity(),
-            // Assuming the sold_token and bought_token have the same TOKEN_VK
-            TOKEN_VK.get_compressed(),
-            self.sell.resource().get_npk(),
-            self.sell.resource().value,
-        ])
+        self.opening().encode_label()
     }
 
     pub fn create_intent_resource<R: RngCore>(&self, mut rng: R) -> Resource {
@@ -108,7 +179,7 @@ impl Swap {
             *COMPRESSED_PARTIAL_FULFILLMENT_INTENT_VK,
             self.encode_label(),
             pallas::Base::zero(),
-            1u64,
+            1u128,
             self.sell.resource().nk_container.get_nk().unwrap(),
             self.sell.resource().get_nf().unwrap(),
             true,
@@ -116,6 +187,79 @@ impl Swap {
         )
     }
 
+    /// Assign variables encoded in label
+    pub fn assign_label(
+        &self,
+        column: Column<Advice>,
+        layouter: impl Layouter<pallas::Base>,
+    ) -> Result<PartialFulfillmentIntentLabel, Error> {
+        self.opening().assign_label(column, layouter)
+    }
+}
+
+impl BorshSerialize for Swap {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.sell.serialize(writer)?;
+        self.buy.serialize(writer)?;
+        self.auth.serialize(writer)?;
+        writer.write_all(&self.blind.to_repr())?;
+        Ok(())
+    }
+}
+
+impl BorshDeserialize for Swap {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let sell = TokenResource::deserialize_reader(reader)?;
+        let buy = Token::deserialize_reader(reader)?;
+        let auth = TokenAuthorization::deserialize_reader(reader)?;
+        let blind = read_base_field(reader)?;
+        Ok(Self {
+            sell,
+            buy,
+            auth,
+            blind,
+        })
+    }
+}
+
+impl SwapOpening {
+    const LEN: usize = 8;
+
+    pub fn encode_label(&self) -> pallas::Base {
+        poseidon_hash_n(self.to_fields())
+    }
+
+    /// Checks the opening against the label of an intent resource.
+    pub fn opens(&self, label: &pallas::Base) -> bool {
+        self.encode_label() == *label
+    }
+
+    pub fn to_fields(&self) -> [pallas::Base; Self::LEN] {
+        [
+            self.sold_token,
+            self.sold_token_quantity,
+            self.bought_token,
+            self.bought_token_quantity,
+            self.token_resource_logic_vk,
+            self.receiver_npk,
+            self.receiver_value,
+            self.blind,
+        ]
+    }
+
+    pub fn from_fields(fields: &[pallas::Base; Self::LEN]) -> Self {
+        Self {
+            sold_token: fields[0],
+            sold_token_quantity: fields[1],
+            bought_token: fields[2],
+            bought_token_quantity: fields[3],
+            token_resource_logic_vk: fields[4],
+            receiver_npk: fields[5],
+            receiver_value: fields[6],
+            blind: fields[7],
+        }
+    }
+
     /// Assign variables encoded in label
     pub fn assign_label(
         &self,
@@ -125,43 +269,49 @@ impl Swap {
         let token_resource_logic_vk = assign_free_advice(
             layouter.namespace(|| "witness token resource_logic vk"),
             column,
-            Value::known(TOKEN_VK.get_compressed()),
+            Value::known(self.token_resource_logic_vk),
         )?;
 
         let sold_token = assign_free_advice(
             layouter.namespace(|| "witness sold_token"),
             column,
-            Value::known(self.sell.encode_name()),
+            Value::known(self.sold_token),
         )?;
 
         let sold_token_quantity = assign_free_advice(
             layouter.namespace(|| "witness sold_token_quantity"),
             column,
-            Value::known(self.sell.encode_quantity()),
+            Value::known(self.sold_token_quantity),
         )?;
 
         let bought_token = assign_free_advice(
             layouter.namespace(|| "witness bought_token"),
             column,
-            Value::known(self.buy.encode_name()),
+            Value::known(self.bought_token),
         )?;
 
         let bought_token_quantity = assign_free_advice(
             layouter.namespace(|| "witness bought_token_quantity"),
             column,
-            Value::known(self.buy.encode_quantity()),
+            Value::known(self.bought_token_quantity),
         )?;
 
         let receiver_npk = assign_free_advice(
             layouter.namespace(|| "witness receiver npk"),
             column,
-            Value::known(self.sell.resource().get_npk()),
+            Value::known(self.receiver_npk),
         )?;
 
         let receiver_value = assign_free_advice(
             layouter.namespace(|| "witness receiver value"),
             column,
-            Value::known(self.sell.resource().value),
+            Value::known(self.receiver_value),
+        )?;
+
+        let blind = assign_free_advice(
+            layouter.namespace(|| "witness label blind"),
+            column,
+            Value::known(self.blind),
         )?;
 
         Ok(PartialFulfillmentIntentLabel {
@@ -172,6 +322,66 @@ impl Swap {
             bought_token_quantity,
             receiver_npk,
             receiver_value,
+            blind,
+        })
+    }
+}
+
+impl EncryptedSwapOpening {
+    pub fn encrypt(
+        opening: &SwapOpening,
+        solver_pk: &pallas::Point,
+        mut rng: impl RngCore,
+    ) -> Self {
+        let esk = EphemeralSecretKey::random(&mut rng);
+        let secret_key = esk.agree(solver_pk);
+        let encrypt_nonce = pallas::Base::random(&mut rng);
+        let plaintext = ResourcePlaintext::padding(&opening.to_fields());
+        Self {
+            epk: esk.public_key(),
+            ciphertext: ResourceCiphertext::encrypt(&plaintext, &secret_key, &encrypt_nonce),
+        }
+    }
+
+    /// Decrypts the opening with the solver key. Returns None if the ciphertext doesn't
+    /// authenticate or the opening doesn't open the label of the intent resource.
+    pub fn decrypt(&self, solver_sk: &pallas::Scalar, label: &pallas::Base) -> Option<SwapOpening> {
+        let secret_key = self.epk.agree(solver_sk);
+        let plaintext = self.ciphertext.decrypt(&secret_key)?;
+        let fields: [pallas::Base; SwapOpening::LEN] =
+            plaintext[..SwapOpening::LEN].try_into().unwrap();
+        let opening = SwapOpening::from_fields(&fields);
+        opening.opens(label).then_some(opening)
+    }
+}
+
+impl BorshSerialize for EncryptedSwapOpening {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(&self.epk.inner().to_bytes())?;
+        for element in self.ciphertext.inner().iter() {
+            writer.write_all(&element.to_repr())?;
+        }
+        Ok(())
+    }
+}
+
+impl BorshDeserialize for EncryptedSwapOpening {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let epk = read_point(reader)?;
+        // The shared secret of an identity key has no coordinates to key the decryption with
+        if bool::from(epk.is_identity()) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "identity ephemeral public key",
+            ));
+        }
+        let epk = EphemeralPublicKey::from_point(epk);
+        let ciphertext: Vec<_> = (0..RESOURCE_ENCRYPTION_CIPHERTEXT_NUM)
+            .map(|_| read_base_field(reader))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            epk,
+            ciphertext: ciphertext.into(),
         })
     }
 }
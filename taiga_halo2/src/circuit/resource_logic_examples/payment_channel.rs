@@ -0,0 +1,1353 @@
+/// This example is to demonstrate how an off-chain payment channel between two
+/// parties maps onto resources. The channel resource locks the funds of the
+/// channel, its value commits to the parties, the current balance split and
+/// the pending close:
+///
+/// value = poseidon_hash(pk_a || pk_b || npk_a || npk_b || dispute_period || state_commitment || close_after)
+/// state_commitment = poseidon_hash(channel_id || balance_a || balance_b || sequence)
+///
+/// The channel id is the label of the channel resources, so a state signed for a channel can't
+/// be disputed on another channel of the same parties. close_after is zero while the channel is open. The parties exchange states
+/// signed by both of them off-chain. Consuming the channel resource takes one
+/// of four actions, the first three create the next channel resource (the
+/// first output) with the same kind and quantity:
+///
+/// Update: both parties sign the ptx, the next channel holds the next state,
+/// whose sequence is incremented. An update also cancels a pending close.
+///
+/// Close: either party alone starts closing an open channel at its current
+/// state, the close is pending until close_after = now + dispute_period.
+///
+/// Dispute: before close_after, either party overrides the pending close with
+/// a state of a higher sequence signed by both parties. The close stays
+/// pending until the same close_after.
+///
+/// Settle: from close_after on, either party releases the balances of the
+/// pending close to the payout resources of the parties (the first output to
+/// party a, the second to party b).
+///
+/// The times are checked in the circuit against `now`, which is published so
+/// the ledger checks it against its own clock with `check_channel_times`.
+///
+/// The kinds of the payout resources are not constrained here, balancing them
+/// against the channel resource is up to the application.
+use crate::{
+    circuit::{
+        blake2s::publicize_default_dynamic_resource_logic_commitments,
+        gadgets::{
+            add::{AddChip, AddInstructions},
+            assign_free_advice, assign_free_constant,
+            boolean::AssignedBool,
+            comparison::ComparisonChip,
+            mul::{MulChip, MulInstructions},
+            poseidon_hash::poseidon_hash_gadget,
+            schnorr::{SchnorrChip, Signature},
+            sub::SubChip,
+            target_resource_variable::{get_is_input_resource_flag, get_owned_resource_variable},
+        },
+        resource_logic_bytecode::{ResourceLogicByteCode, ResourceLogicRepresentation},
+        resource_logic_circuit::{
+            BasicResourceLogicVariables, ResourceLogicCircuit, ResourceLogicConfig,
+            ResourceLogicPublicInputs, ResourceLogicVerifyingInfoTrait,
+        },
+        resource_logic_examples::signature_verification::{
+            ptx_message, ptx_message_hash_gadget, SchnorrSignature,
+        },
+    },
+    constant::{
+        DEFAULT_PARAMS_STORE, NUM_RESOURCE, RESOURCE_LOGIC_CIRCUIT_CUSTOM_PUBLIC_INPUT_BEGIN_IDX,
+    },
+    error::TransactionError,
+    nullifier::{Nullifier, NullifierKeyContainer},
+    resource::{OwnedResourceId, RandomSeed, Resource},
+    resource_logic_commitment::ResourceLogicCommitment,
+    resource_logic_vk::ResourceLogicVerifyingKey,
+    transaction::Transaction,
+    utils::{poseidon_hash_n, read_base_field, read_point},
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+use halo2_gadgets::ecc::{chip::EccChip, NonIdentityPoint};
+use halo2_proofs::{
+    arithmetic::Field,
+    circuit::{floor_planner, Layouter, Value},
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use lazy_static::lazy_static;
+use pasta_curves::{
+    arithmetic::CurveAffine,
+    group::{ff::PrimeField, Curve, Group, GroupEncoding},
+    pallas,
+};
+use rand::RngCore;
+
+/// The index of the published time, zero if the ptx doesn't close, dispute or settle the channel.
+pub const PAYMENT_CHANNEL_TIME_PUBLIC_INPUT_IDX: usize =
+    RESOURCE_LOGIC_CIRCUIT_CUSTOM_PUBLIC_INPUT_BEGIN_IDX;
+
+/// How far the published time can lag behind the verifier clock, it bounds how much a party can
+/// shorten the dispute period of its close.
+pub const PAYMENT_CHANNEL_MAX_TIME_DRIFT: u64 = 10;
+
+lazy_static! {
+    pub static ref PAYMENT_CHANNEL_VK: ResourceLogicVerifyingKey =
+        PaymentChannelResourceLogicCircuit::default()
+            .get_resource_logic_vk()
+            .unwrap();
+    pub static ref COMPRESSED_PAYMENT_CHANNEL_VK: pallas::Base =
+        PAYMENT_CHANNEL_VK.get_compressed();
+}
+
+/// The parties and the dispute period of a channel, fixed when the channel is funded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PaymentChannel {
+    // The label of the channel resources, fresh for each channel
+    pub id: pallas::Base,
+    // The signing keys of the parties
+    pub pk_a: pallas::Point,
+    pub pk_b: pallas::Point,
+    // The payout addresses of the parties
+    pub npk_a: pallas::Base,
+    pub npk_b: pallas::Base,
+    // A unilateral close can be settled this long after it's started
+    pub dispute_period: u64,
+}
+
+/// The balance split of a channel. Each update increments the sequence.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct ChannelState {
+    pub balance_a: u128,
+    pub balance_b: u128,
+    pub sequence: u64,
+}
+
+/// The action taken by consuming the channel resource.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum ChannelAction {
+    #[default]
+    Update,
+    Close,
+    Dispute,
+    Settle,
+}
+
+impl ChannelState {
+    /// state_commitment = poseidon_hash(channel_id || balance_a || balance_b || sequence)
+    pub fn commitment(&self, channel_id: pallas::Base) -> pallas::Base {
+        poseidon_hash_n([
+            channel_id,
+            pallas::Base::from_u128(self.balance_a),
+            pallas::Base::from_u128(self.balance_b),
+            pallas::Base::from(self.sequence),
+        ])
+    }
+
+    /// The off-chain signature of the state of the channel `channel_id` by a party, a dispute
+    /// needs the signatures of both.
+    pub fn sign<R: RngCore>(
+        &self,
+        rng: R,
+        channel_id: pallas::Base,
+        sk: pallas::Scalar,
+    ) -> SchnorrSignature {
+        SchnorrSignature {
+            pk: pallas::Point::generator() * sk,
+            signature: Signature::sign(rng, sk, self.commitment(channel_id)),
+        }
+    }
+
+    /// The state after moving `quantity` from party a to party b, or back if `a_to_b` is false.
+    pub fn pay(&self, quantity: u128, a_to_b: bool) -> Option<Self> {
+        let (balance_a, balance_b) = if a_to_b {
+            (
+                self.balance_a.checked_sub(quantity)?,
+                self.balance_b.checked_add(quantity)?,
+            )
+        } else {
+            (
+                self.balance_a.checked_add(quantity)?,
+                self.balance_b.checked_sub(quantity)?,
+            )
+        };
+        Some(Self {
+            balance_a,
+            balance_b,
+            sequence: self.sequence.checked_add(1)?,
+        })
+    }
+
+    pub fn total(&self) -> u128 {
+        self.balance_a + self.balance_b
+    }
+}
+
+impl Default for PaymentChannel {
+    fn default() -> Self {
+        Self {
+            id: pallas::Base::zero(),
+            pk_a: pallas::Point::generator(),
+            pk_b: pallas::Point::generator(),
+            npk_a: pallas::Base::zero(),
+            npk_b: pallas::Base::zero(),
+            dispute_period: 0,
+        }
+    }
+}
+
+impl PaymentChannel {
+    /// Encodes the channel, its state and its pending close into the value of the channel
+    /// resource.
+    pub fn encode_value(&self, state: &ChannelState, close_after: u64) -> pallas::Base {
+        let pk_a = self.pk_a.to_affine().coordinates().unwrap();
+        let pk_b = self.pk_b.to_affine().coordinates().unwrap();
+        poseidon_hash_n([
+            *pk_a.x(),
+            *pk_a.y(),
+            *pk_b.x(),
+            *pk_b.y(),
+            self.npk_a,
+            self.npk_b,
+            pallas::Base::from(self.dispute_period),
+            state.commitment(self.id),
+            pallas::Base::from(close_after),
+        ])
+    }
+
+    /// Creates the channel resource to consume. `nk` is shared by the parties so that either
+    /// of them can close the channel.
+    pub fn create_input_resource<R: RngCore>(
+        &self,
+        mut rng: R,
+        nk: pallas::Base,
+        state: &ChannelState,
+        close_after: u64,
+    ) -> Resource {
+        let rseed = pallas::Base::random(&mut rng);
+        let nonce = Nullifier::random(&mut rng);
+        Resource::new_input_resource(
+            *COMPRESSED_PAYMENT_CHANNEL_VK,
+            self.id,
+            self.encode_value(state, close_after),
+            state.total(),
+            nk,
+            nonce,
+            false,
+            rseed,
+        )
+    }
+
+    /// Creates the channel resource holding `state`, funding the channel or continuing it.
+    pub fn create_output_resource<R: RngCore>(
+        &self,
+        mut rng: R,
+        nk: pallas::Base,
+        state: &ChannelState,
+        close_after: u64,
+    ) -> Resource {
+        let rseed = pallas::Base::random(&mut rng);
+        Resource::new_output_resource(
+            *COMPRESSED_PAYMENT_CHANNEL_VK,
+            self.id,
+            self.encode_value(state, close_after),
+            state.total(),
+            NullifierKeyContainer::from_key(nk).get_npk(),
+            false,
+            rseed,
+        )
+    }
+}
+
+/// Whether the time published in `public_inputs` is the verifier time `now`, up to
+/// `PAYMENT_CHANNEL_MAX_TIME_DRIFT`. The public inputs of a ptx that doesn't need the time
+/// publish zero and are always accepted.
+pub fn is_recent(public_inputs: &ResourceLogicPublicInputs, now: u64) -> bool {
+    let time = public_inputs.get_from_index(PAYMENT_CHANNEL_TIME_PUBLIC_INPUT_IDX);
+    if time == pallas::Base::zero() {
+        return true;
+    }
+    // The circuit range checks the time to u64
+    let time = u64::from_le_bytes(time.to_repr()[..8].try_into().unwrap());
+    time <= now && now - time <= PAYMENT_CHANNEL_MAX_TIME_DRIFT
+}
+
+/// Checks that the channel closes, disputes and settles of the shielded ptxs of the transaction
+/// publish a recent time, see `is_recent`. The stale time is reported with the index of its ptx.
+pub fn check_channel_times(tx: &Transaction, now: u64) -> Result<(), TransactionError> {
+    for (ptx_index, ptx) in tx
+        .get_shielded_ptx_bundle()
+        .partial_txs()
+        .iter()
+        .enumerate()
+    {
+        if ptx
+            .get_input_resource_logic_public_inputs(*COMPRESSED_PAYMENT_CHANNEL_VK)
+            .into_iter()
+            .any(|public_inputs| !is_recent(public_inputs, now))
+        {
+            return Err(TransactionError::StaleChannelTime(ptx_index));
+        }
+    }
+    Ok(())
+}
+
+// PaymentChannelResourceLogicCircuit
+#[derive(Clone, Debug, Default)]
+pub struct PaymentChannelResourceLogicCircuit {
+    pub owned_resource_id: OwnedResourceId,
+    pub input_resources: [Resource; NUM_RESOURCE],
+    pub output_resources: [Resource; NUM_RESOURCE],
+    pub channel: PaymentChannel,
+    // The state and the pending close of the owned channel resource
+    pub state: ChannelState,
+    pub close_after: u64,
+    pub action: ChannelAction,
+    // The state of the next channel resource when updating, the overriding state when disputing
+    pub next_state: ChannelState,
+    // The time of the close, dispute or settle
+    pub now: u64,
+    // The signatures of the parties, of the ptx when updating and of the next state when disputing
+    pub signature_a: SchnorrSignature,
+    pub signature_b: SchnorrSignature,
+}
+
+impl PaymentChannelResourceLogicCircuit {
+    /// The circuit of the owned channel resource holding `state`, with the pending close
+    /// `close_after`. It funds or continues the channel if the resource is an output, the
+    /// action of an input is set with `update`, `close`, `dispute` or `settle`.
+    pub fn new(
+        owned_resource_id: OwnedResourceId,
+        input_resources: [Resource; NUM_RESOURCE],
+        output_resources: [Resource; NUM_RESOURCE],
+        channel: PaymentChannel,
+        state: ChannelState,
+        close_after: u64,
+    ) -> Self {
+        Self {
+            owned_resource_id,
+            input_resources,
+            output_resources,
+            channel,
+            state,
+            close_after,
+            ..Default::default()
+        }
+    }
+
+    /// Updates the channel to `next_state`, signed by both parties.
+    pub fn update<R: RngCore>(
+        self,
+        mut rng: R,
+        next_state: ChannelState,
+        sk_a: pallas::Scalar,
+        sk_b: pallas::Scalar,
+    ) -> Self {
+        let message = ptx_message(&self.input_resources, &self.output_resources);
+        let signature_a = SchnorrSignature::sign(&mut rng, sk_a, message.clone());
+        let signature_b = SchnorrSignature::sign(&mut rng, sk_b, message);
+        Self {
+            action: ChannelAction::Update,
+            next_state,
+            signature_a,
+            signature_b,
+            ..self
+        }
+    }
+
+    /// Starts closing the channel at `now`, which needs no signature.
+    pub fn close(self, now: u64) -> Self {
+        Self {
+            action: ChannelAction::Close,
+            next_state: self.state,
+            now,
+            ..self
+        }
+    }
+
+    /// Overrides the pending close with `next_state` and its signatures by the parties.
+    pub fn dispute(
+        self,
+        next_state: ChannelState,
+        signature_a: SchnorrSignature,
+        signature_b: SchnorrSignature,
+        now: u64,
+    ) -> Self {
+        Self {
+            action: ChannelAction::Dispute,
+            next_state,
+            now,
+            signature_a,
+            signature_b,
+            ..self
+        }
+    }
+
+    /// Settles the pending close at `now`, which needs no signature.
+    pub fn settle(self, now: u64) -> Self {
+        Self {
+            action: ChannelAction::Settle,
+            now,
+            ..self
+        }
+    }
+
+    pub fn to_bytecode(&self) -> ResourceLogicByteCode {
+        ResourceLogicByteCode::new(ResourceLogicRepresentation::PaymentChannel, self.to_bytes())
+    }
+
+    // The time if the owned resource is an input closing, disputing or settling the channel,
+    // otherwise zero.
+    fn published_time(&self) -> pallas::Base {
+        if self.owned_resource_id.is_input_resource() && self.action != ChannelAction::Update {
+            pallas::Base::from(self.now)
+        } else {
+            pallas::Base::zero()
+        }
+    }
+}
+
+impl ResourceLogicCircuit for PaymentChannelResourceLogicCircuit {
+    // Add custom constraints
+    fn custom_constraints(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<pallas::Base>,
+        basic_variables: BasicResourceLogicVariables,
+    ) -> Result<(), Error> {
+        let ecc_chip = EccChip::construct(config.ecc_config.clone());
+        let add_chip = AddChip::construct(config.add_config.clone(), ());
+        let sub_chip = SubChip::construct(config.sub_config.clone(), ());
+        let mul_chip = MulChip::construct(config.mul_config.clone());
+        let comparison_chip = ComparisonChip::construct(
+            config.advices[0],
+            config.add_config.clone(),
+            config.sub_config.clone(),
+            config.mul_config.clone(),
+            config.range_check_config,
+        );
+
+        let owned_resource_id = basic_variables.get_owned_resource_id();
+        let is_input_resource = get_is_input_resource_flag(
+            config.get_is_input_resource_flag_config,
+            layouter.namespace(|| "get is_input_resource_flag"),
+            &owned_resource_id,
+            &basic_variables.get_input_resource_nfs(),
+            &basic_variables.get_output_resource_cms(),
+        )?;
+
+        // At most one of the close, dispute and settle flags is set, the update is the default
+        let [close_flag, dispute_flag, settle_flag] = [
+            ChannelAction::Close,
+            ChannelAction::Dispute,
+            ChannelAction::Settle,
+        ]
+        .map(|action| {
+            AssignedBool::witness(
+                &mul_chip,
+                layouter.namespace(|| format!("witness {:?} flag", action)),
+                config.advices[0],
+                Value::known(self.action == action),
+            )
+        });
+        let (close_flag, dispute_flag, settle_flag) = (close_flag?, dispute_flag?, settle_flag?);
+        let close_or_dispute = AddInstructions::add(
+            &add_chip,
+            layouter.namespace(|| "close + dispute"),
+            &close_flag,
+            &dispute_flag,
+        )?;
+        let timed_flag = AddInstructions::add(
+            &add_chip,
+            layouter.namespace(|| "close + dispute + settle"),
+            &close_or_dispute,
+            &settle_flag,
+        )?;
+        let timed_flag = AssignedBool::assert_bool(
+            &mul_chip,
+            layouter.namespace(|| "bool check the action flags"),
+            &timed_flag,
+        )?;
+        let update_flag = timed_flag.not(
+            &sub_chip,
+            layouter.namespace(|| "update = 1 - (close + dispute + settle)"),
+            config.advices[0],
+        )?;
+        let continue_flag = settle_flag.not(
+            &sub_chip,
+            layouter.namespace(|| "continue = 1 - settle"),
+            config.advices[0],
+        )?;
+        let [is_update, is_close, is_dispute, is_settle, is_timed, is_continue] = [
+            ("update", &update_flag),
+            ("close", &close_flag),
+            ("dispute", &dispute_flag),
+            ("settle", &settle_flag),
+            ("timed", &timed_flag),
+            ("continue", &continue_flag),
+        ]
+        .map(|(name, flag)| {
+            is_input_resource.and(
+                &mul_chip,
+                layouter.namespace(|| format!("is_{name} = is_input_resource * {name}")),
+                flag,
+            )
+        });
+        let (is_update, is_close, is_dispute, is_settle, is_timed, is_continue) = (
+            is_update?,
+            is_close?,
+            is_dispute?,
+            is_settle?,
+            is_timed?,
+            is_continue?,
+        );
+
+        // Witness the channel
+        let pk_a = NonIdentityPoint::new(
+            ecc_chip.clone(),
+            layouter.namespace(|| "witness pk_a"),
+            Value::known(self.channel.pk_a.to_affine()),
+        )?;
+        let pk_b = NonIdentityPoint::new(
+            ecc_chip,
+            layouter.namespace(|| "witness pk_b"),
+            Value::known(self.channel.pk_b.to_affine()),
+        )?;
+        let npk_a = assign_free_advice(
+            layouter.namespace(|| "witness npk_a"),
+            config.advices[0],
+            Value::known(self.channel.npk_a),
+        )?;
+        let npk_b = assign_free_advice(
+            layouter.namespace(|| "witness npk_b"),
+            config.advices[0],
+            Value::known(self.channel.npk_b),
+        )?;
+        let dispute_period = assign_free_advice(
+            layouter.namespace(|| "witness dispute_period"),
+            config.advices[0],
+            Value::known(pallas::Base::from(self.channel.dispute_period)),
+        )?;
+
+        // Witness the state and check the value encoding of the owned resource
+        let balance_a = assign_free_advice(
+            layouter.namespace(|| "witness balance_a"),
+            config.advices[0],
+            Value::known(pallas::Base::from_u128(self.state.balance_a)),
+        )?;
+        let balance_b = assign_free_advice(
+            layouter.namespace(|| "witness balance_b"),
+            config.advices[0],
+            Value::known(pallas::Base::from_u128(self.state.balance_b)),
+        )?;
+        let sequence = assign_free_advice(
+            layouter.namespace(|| "witness sequence"),
+            config.advices[0],
+            Value::known(pallas::Base::from(self.state.sequence)),
+        )?;
+        let close_after = assign_free_advice(
+            layouter.namespace(|| "witness close_after"),
+            config.advices[0],
+            Value::known(pallas::Base::from(self.close_after)),
+        )?;
+        // The channel id is the label of the owned resource
+        let label = get_owned_resource_variable(
+            config.get_owned_resource_variable_config,
+            layouter.namespace(|| "get owned resource label"),
+            &owned_resource_id,
+            &basic_variables.get_label_searchable_pairs(),
+        )?;
+        let state_commitment = poseidon_hash_gadget(
+            config.poseidon_config.clone(),
+            layouter.namespace(|| "state commitment"),
+            [
+                label.clone(),
+                balance_a.clone(),
+                balance_b.clone(),
+                sequence.clone(),
+            ],
+        )?;
+        let encoded_value = poseidon_hash_gadget(
+            config.poseidon_config.clone(),
+            layouter.namespace(|| "value encoding"),
+            [
+                pk_a.inner().x(),
+                pk_a.inner().y(),
+                pk_b.inner().x(),
+                pk_b.inner().y(),
+                npk_a.clone(),
+                npk_b.clone(),
+                dispute_period.clone(),
+                state_commitment,
+                close_after.clone(),
+            ],
+        )?;
+        let value = get_owned_resource_variable(
+            config.get_owned_resource_variable_config,
+            layouter.namespace(|| "get owned resource value"),
+            &owned_resource_id,
+            &basic_variables.get_value_searchable_pairs(),
+        )?;
+        layouter.assign_region(
+            || "check value encoding",
+            |mut region| region.constrain_equal(encoded_value.cell(), value.cell()),
+        )?;
+
+        // The balances of a consumed channel add up to its quantity
+        let quantity = get_owned_resource_variable(
+            config.get_owned_resource_variable_config,
+            layouter.namespace(|| "get owned resource quantity"),
+            &owned_resource_id,
+            &basic_variables.get_quantity_searchable_pairs(),
+        )?;
+        let total = AddInstructions::add(
+            &add_chip,
+            layouter.namespace(|| "balance_a + balance_b"),
+            &balance_a,
+            &balance_b,
+        )?;
+        layouter.assign_region(
+            || "conditional equal: check the balances",
+            |mut region| {
+                config.conditional_equal_config.assign_region(
+                    &is_input_resource,
+                    &total,
+                    &quantity,
+                    0,
+                    &mut region,
+                )
+            },
+        )?;
+
+        // Check the times: the close is pending if close_after > 0, it can be disputed before
+        // close_after and settled from close_after on
+        let zero = assign_free_constant(
+            layouter.namespace(|| "zero"),
+            config.advices[0],
+            pallas::Base::zero(),
+        )?;
+        let one = assign_free_constant(
+            layouter.namespace(|| "one"),
+            config.advices[0],
+            pallas::Base::one(),
+        )?;
+        let now = assign_free_advice(
+            layouter.namespace(|| "witness now"),
+            config.advices[0],
+            Value::known(pallas::Base::from(self.now)),
+        )?;
+        let is_now_set =
+            comparison_chip.is_less_than(layouter.namespace(|| "0 < now"), &zero, &now)?;
+        let is_pending = comparison_chip.is_less_than(
+            layouter.namespace(|| "0 < close_after"),
+            &zero,
+            &close_after,
+        )?;
+        let is_expired = comparison_chip.is_leq(
+            layouter.namespace(|| "close_after <= now"),
+            &close_after,
+            &now,
+        )?;
+        let next_sequence = assign_free_advice(
+            layouter.namespace(|| "witness next sequence"),
+            config.advices[0],
+            Value::known(pallas::Base::from(self.next_state.sequence)),
+        )?;
+        let is_later_sequence = comparison_chip.is_less_than(
+            layouter.namespace(|| "sequence < next sequence"),
+            &sequence,
+            &next_sequence,
+        )?;
+        for (name, flag, actual, expected) in [
+            ("the time is published", &is_timed, &is_now_set, &one),
+            ("close an open channel", &is_close, &is_pending, &zero),
+            ("dispute a pending close", &is_dispute, &is_pending, &one),
+            (
+                "dispute before close_after",
+                &is_dispute,
+                &is_expired,
+                &zero,
+            ),
+            (
+                "dispute with a later state",
+                &is_dispute,
+                &is_later_sequence,
+                &one,
+            ),
+            ("settle a pending close", &is_settle, &is_pending, &one),
+            ("settle from close_after on", &is_settle, &is_expired, &one),
+        ] {
+            layouter.assign_region(
+                || format!("conditional equal: {name}"),
+                |mut region| {
+                    config.conditional_equal_config.assign_region(
+                        flag,
+                        actual,
+                        expected,
+                        0,
+                        &mut region,
+                    )
+                },
+            )?;
+        }
+
+        // Publicize the time
+        let published_time = MulInstructions::mul(
+            &mul_chip,
+            layouter.namespace(|| "published time = is_timed * now"),
+            &is_timed,
+            &now,
+        )?;
+        layouter.constrain_instance(
+            published_time.cell(),
+            config.instances,
+            PAYMENT_CHANNEL_TIME_PUBLIC_INPUT_IDX,
+        )?;
+
+        // Witness the next state
+        let next_balance_a = assign_free_advice(
+            layouter.namespace(|| "witness next balance_a"),
+            config.advices[0],
+            Value::known(pallas::Base::from_u128(self.next_state.balance_a)),
+        )?;
+        let next_balance_b = assign_free_advice(
+            layouter.namespace(|| "witness next balance_b"),
+            config.advices[0],
+            Value::known(pallas::Base::from_u128(self.next_state.balance_b)),
+        )?;
+        // The next balances can't wrap around the field
+        config.u128_config.decompose(
+            layouter.namespace(|| "decompose next balance_a"),
+            &next_balance_a,
+        )?;
+        config.u128_config.decompose(
+            layouter.namespace(|| "decompose next balance_b"),
+            &next_balance_b,
+        )?;
+        let next_total = AddInstructions::add(
+            &add_chip,
+            layouter.namespace(|| "next balance_a + next balance_b"),
+            &next_balance_a,
+            &next_balance_b,
+        )?;
+        let next_state_commitment = poseidon_hash_gadget(
+            config.poseidon_config.clone(),
+            layouter.namespace(|| "next state commitment"),
+            [
+                label.clone(),
+                next_balance_a.clone(),
+                next_balance_b.clone(),
+                next_sequence.clone(),
+            ],
+        )?;
+
+        // Update: the sequence is incremented. Close: the state is unchanged.
+        let incremented_sequence = AddInstructions::add(
+            &add_chip,
+            layouter.namespace(|| "sequence + 1"),
+            &sequence,
+            &one,
+        )?;
+        for (name, flag, actual, expected) in [
+            (
+                "update the sequence",
+                &is_update,
+                &next_sequence,
+                &incremented_sequence,
+            ),
+            ("close balance_a", &is_close, &next_balance_a, &balance_a),
+            ("close balance_b", &is_close, &next_balance_b, &balance_b),
+            ("close sequence", &is_close, &next_sequence, &sequence),
+        ] {
+            layouter.assign_region(
+                || format!("conditional equal: {name}"),
+                |mut region| {
+                    config.conditional_equal_config.assign_region(
+                        flag,
+                        actual,
+                        expected,
+                        0,
+                        &mut region,
+                    )
+                },
+            )?;
+        }
+
+        // Update: both parties sign the ptx. Dispute: both parties signed the next state.
+        let ptx_message_hash = ptx_message_hash_gadget(
+            &config,
+            layouter.namespace(|| "ptx message hash"),
+            &basic_variables,
+        )?;
+        let message_hash = layouter.assign_region(
+            || "conditional select: message hash",
+            |mut region| {
+                config.conditional_select_config.assign_region(
+                    &is_dispute,
+                    &next_state_commitment,
+                    &ptx_message_hash,
+                    0,
+                    &mut region,
+                )
+            },
+        )?;
+        let is_signed = is_update.or(
+            &add_chip,
+            &sub_chip,
+            &mul_chip,
+            layouter.namespace(|| "is_signed = is_update or is_dispute"),
+            &is_dispute,
+        )?;
+        let schnorr_chip = SchnorrChip::from_resource_logic_config(&config);
+        schnorr_chip.conditional_verify(
+            layouter.namespace(|| "verify the signature of party a"),
+            &config.conditional_equal_config,
+            &is_signed,
+            &pk_a,
+            &message_hash,
+            &self.signature_a.signature,
+        )?;
+        schnorr_chip.conditional_verify(
+            layouter.namespace(|| "verify the signature of party b"),
+            &config.conditional_equal_config,
+            &is_signed,
+            &pk_b,
+            &message_hash,
+            &self.signature_b.signature,
+        )?;
+
+        // The pending close of the next channel: none after an update, now + dispute_period
+        // after a close and unchanged after a dispute
+        let close_deadline = AddInstructions::add(
+            &add_chip,
+            layouter.namespace(|| "now + dispute_period"),
+            &now,
+            &dispute_period,
+        )?;
+        let disputed_close_after = layouter.assign_region(
+            || "conditional select: disputed close_after",
+            |mut region| {
+                config.conditional_select_config.assign_region(
+                    &is_dispute,
+                    &close_after,
+                    &zero,
+                    0,
+                    &mut region,
+                )
+            },
+        )?;
+        let next_close_after = layouter.assign_region(
+            || "conditional select: next close_after",
+            |mut region| {
+                config.conditional_select_config.assign_region(
+                    &is_close,
+                    &close_deadline,
+                    &disputed_close_after,
+                    0,
+                    &mut region,
+                )
+            },
+        )?;
+        let next_encoded_value = poseidon_hash_gadget(
+            config.poseidon_config.clone(),
+            layouter.namespace(|| "next value encoding"),
+            [
+                pk_a.inner().x(),
+                pk_a.inner().y(),
+                pk_b.inner().x(),
+                pk_b.inner().y(),
+                npk_a.clone(),
+                npk_b.clone(),
+                dispute_period,
+                next_state_commitment,
+                next_close_after,
+            ],
+        )?;
+
+        // Update, close and dispute: the first output is the next channel resource
+        let logic = get_owned_resource_variable(
+            config.get_owned_resource_variable_config,
+            layouter.namespace(|| "get owned resource logic"),
+            &owned_resource_id,
+            &basic_variables.get_logic_searchable_pairs(),
+        )?;
+        let next_channel = &basic_variables.output_resource_variables[0].resource_variables;
+        for (name, expected, actual) in [
+            ("value", &next_encoded_value, &next_channel.value),
+            ("label", &label, &next_channel.label),
+            ("logic", &logic, &next_channel.logic),
+            ("quantity", &quantity, &next_channel.quantity),
+            ("balances", &quantity, &next_total),
+        ] {
+            layouter.assign_region(
+                || format!("conditional equal: check the next channel {name}"),
+                |mut region| {
+                    config.conditional_equal_config.assign_region(
+                        &is_continue,
+                        expected,
+                        actual,
+                        0,
+                        &mut region,
+                    )
+                },
+            )?;
+        }
+
+        // Settle: release the balances to the parties
+        let payout_a = &basic_variables.output_resource_variables[0].resource_variables;
+        let payout_b = &basic_variables.output_resource_variables[1].resource_variables;
+        for (name, expected, actual) in [
+            ("payout a npk", &npk_a, &payout_a.npk),
+            ("payout a quantity", &balance_a, &payout_a.quantity),
+            ("payout b npk", &npk_b, &payout_b.npk),
+            ("payout b quantity", &balance_b, &payout_b.quantity),
+        ] {
+            layouter.assign_region(
+                || format!("conditional equal: check the {name}"),
+                |mut region| {
+                    config.conditional_equal_config.assign_region(
+                        &is_settle,
+                        expected,
+                        actual,
+                        0,
+                        &mut region,
+                    )
+                },
+            )?;
+        }
+
+        // Publicize the dynamic resource_logic commitments with default value
+        publicize_default_dynamic_resource_logic_commitments(
+            &mut layouter,
+            config.advices[0],
+            config.instances,
+        )?;
+
+        Ok(())
+    }
+
+    fn get_input_resources(&self) -> &[Resource; NUM_RESOURCE] {
+        &self.input_resources
+    }
+
+    fn get_output_resources(&self) -> &[Resource; NUM_RESOURCE] {
+        &self.output_resources
+    }
+
+    fn get_public_inputs(&self, mut rng: impl RngCore) -> ResourceLogicPublicInputs {
+        let mut public_inputs = self.get_mandatory_public_inputs();
+        let default_resource_logic_cm: [pallas::Base; 2] =
+            ResourceLogicCommitment::default().to_public_inputs();
+        public_inputs.extend(default_resource_logic_cm);
+        public_inputs.extend(default_resource_logic_cm);
+        public_inputs.push(self.published_time());
+        let padding = ResourceLogicPublicInputs::get_public_input_padding(
+            public_inputs.len(),
+            &RandomSeed::random(&mut rng),
+        );
+        public_inputs.extend(padding);
+        public_inputs.into()
+    }
+
+    fn get_owned_resource_id(&self) -> OwnedResourceId {
+        self.owned_resource_id
+    }
+}
+
+resource_logic_circuit_impl!(PaymentChannelResourceLogicCircuit);
+resource_logic_verifying_info_impl!(PaymentChannelResourceLogicCircuit);
+circuit_field_encoding_from_borsh!(PaymentChannel, ChannelState, ChannelAction);
+resource_logic_circuit_encoding_impl!(PaymentChannelResourceLogicCircuit {
+    1 => owned_resource_id,
+    2 => input_resources,
+    3 => output_resources,
+    4 => channel,
+    5 => state,
+    6 => close_after,
+    7 => action,
+    8 => next_state,
+    9 => now,
+    10 => signature_a,
+    11 => signature_b,
+});
+
+impl BorshSerialize for PaymentChannel {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(&self.id.to_repr())?;
+        writer.write_all(&self.pk_a.to_bytes())?;
+        writer.write_all(&self.pk_b.to_bytes())?;
+        writer.write_all(&self.npk_a.to_repr())?;
+        writer.write_all(&self.npk_b.to_repr())?;
+        self.dispute_period.serialize(writer)?;
+        Ok(())
+    }
+}
+
+impl BorshDeserialize for PaymentChannel {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let id = read_base_field(reader)?;
+        let pk_a = read_point(reader)?;
+        let pk_b = read_point(reader)?;
+        let npk_a = read_base_field(reader)?;
+        let npk_b = read_base_field(reader)?;
+        let dispute_period = u64::deserialize_reader(reader)?;
+        Ok(Self {
+            id,
+            pk_a,
+            pk_b,
+            npk_a,
+            npk_b,
+            dispute_period,
+        })
+    }
+}
+
+#[test]
+fn test_halo2_payment_channel_resource_logic_circuit() {
+    use crate::circuit::verify_failure::assert_verifies;
+    use crate::constant::RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE;
+    use crate::resource::tests::random_resource;
+    use halo2_proofs::dev::MockProver;
+    use rand::rngs::OsRng;
+
+    let mut rng = OsRng;
+    let sk_a = pallas::Scalar::random(&mut rng);
+    let sk_b = pallas::Scalar::random(&mut rng);
+    let channel = PaymentChannel {
+        id: pallas::Base::random(&mut rng),
+        pk_a: pallas::Point::generator() * sk_a,
+        pk_b: pallas::Point::generator() * sk_b,
+        npk_a: pallas::Base::random(&mut rng),
+        npk_b: pallas::Base::random(&mut rng),
+        dispute_period: 100,
+    };
+    let nk = pallas::Base::random(&mut rng);
+    let state = ChannelState {
+        balance_a: 10,
+        balance_b: 5,
+        sequence: 3,
+    };
+
+    let run = |circuit: &PaymentChannelResourceLogicCircuit| {
+        let public_inputs = circuit.get_public_inputs(OsRng);
+        MockProver::<pallas::Base>::run(
+            RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+            circuit,
+            vec![public_inputs.to_vec()],
+        )
+        .unwrap()
+    };
+    // The circuit consuming the channel resource at `state` with the pending close `close_after`
+    let consume = |state: &ChannelState,
+                   close_after: u64,
+                   output_resources: [Resource; NUM_RESOURCE],
+                   rng: &mut OsRng| {
+        let channel_resource = channel.create_input_resource(&mut *rng, nk, state, close_after);
+        PaymentChannelResourceLogicCircuit::new(
+            channel_resource.get_nf().unwrap().into(),
+            [channel_resource, random_resource(&mut *rng)],
+            output_resources,
+            channel,
+            *state,
+            close_after,
+        )
+    };
+    let next_channel = |state: &ChannelState, close_after: u64, rng: &mut OsRng| {
+        [
+            channel.create_output_resource(&mut *rng, nk, state, close_after),
+            random_resource(&mut *rng),
+        ]
+    };
+    let payout = |npk: pallas::Base, quantity: u128, rng: &mut OsRng| {
+        Resource::new_output_resource(
+            pallas::Base::random(&mut *rng),
+            channel.id,
+            pallas::Base::zero(),
+            quantity,
+            npk,
+            false,
+            pallas::Base::random(&mut *rng),
+        )
+    };
+
+    // Update: party a pays 4 to party b
+    let next_state = state.pay(4, true).unwrap();
+    let update_circuit = consume(&state, 0, next_channel(&next_state, 0, &mut rng), &mut rng)
+        .update(&mut rng, next_state, sk_a, sk_b);
+
+    // Test serialization
+    let update_circuit = {
+        let circuit_bytes = update_circuit.to_bytes();
+        PaymentChannelResourceLogicCircuit::from_bytes(&circuit_bytes)
+    };
+    assert_verifies(&run(&update_circuit));
+
+    // The update fails without the signature of party b
+    {
+        let mut circuit = update_circuit.clone();
+        circuit.signature_b = circuit.signature_a.clone();
+        assert!(run(&circuit).verify().is_err());
+    }
+
+    // The update fails if the next channel takes a share of the funds
+    {
+        let stolen_state = ChannelState {
+            balance_a: next_state.balance_a - 1,
+            ..next_state
+        };
+        let circuit = consume(
+            &state,
+            0,
+            next_channel(&stolen_state, 0, &mut rng),
+            &mut rng,
+        )
+        .update(&mut rng, stolen_state, sk_a, sk_b);
+        assert!(run(&circuit).verify().is_err());
+    }
+
+    // Close: either party starts closing at the current state without signatures
+    let now = 1000;
+    let close_after = now + channel.dispute_period;
+    let close_circuit = consume(
+        &next_state,
+        0,
+        next_channel(&next_state, close_after, &mut rng),
+        &mut rng,
+    )
+    .close(now);
+    let public_inputs = close_circuit.get_public_inputs(&mut rng);
+    assert!(is_recent(&public_inputs, now));
+    assert!(is_recent(
+        &public_inputs,
+        now + PAYMENT_CHANNEL_MAX_TIME_DRIFT
+    ));
+    assert!(!is_recent(&public_inputs, now - 1));
+    assert!(!is_recent(
+        &public_inputs,
+        now + PAYMENT_CHANNEL_MAX_TIME_DRIFT + 1
+    ));
+    assert_verifies(&run(&close_circuit));
+
+    // The close fails with a shortened dispute period, without a time or on a pending close
+    for circuit in [
+        consume(
+            &next_state,
+            0,
+            next_channel(&next_state, now, &mut rng),
+            &mut rng,
+        )
+        .close(now),
+        consume(
+            &next_state,
+            0,
+            next_channel(&next_state, channel.dispute_period, &mut rng),
+            &mut rng,
+        )
+        .close(0),
+        consume(
+            &next_state,
+            close_after,
+            next_channel(&next_state, close_after, &mut rng),
+            &mut rng,
+        )
+        .close(now),
+    ] {
+        assert!(run(&circuit).verify().is_err());
+    }
+
+    // The close fails at a stale state
+    {
+        let mut circuit = close_circuit.clone();
+        circuit.next_state = state;
+        assert!(run(&circuit).verify().is_err());
+    }
+
+    // Dispute: party b overrides the pending close with a later state signed by both parties
+    let later_state = next_state.pay(3, true).unwrap();
+    let dispute = |disputed_state: &ChannelState, now: u64, rng: &mut OsRng| {
+        consume(
+            &next_state,
+            close_after,
+            next_channel(disputed_state, close_after, &mut *rng),
+            &mut *rng,
+        )
+        .dispute(
+            *disputed_state,
+            disputed_state.sign(&mut *rng, channel.id, sk_a),
+            disputed_state.sign(&mut *rng, channel.id, sk_b),
+            now,
+        )
+    };
+    let dispute_circuit = dispute(&later_state, close_after - 1, &mut rng);
+    assert_verifies(&run(&dispute_circuit));
+
+    // The dispute fails with an earlier state, after close_after or without both signatures
+    for circuit in [
+        dispute(&state, now, &mut rng),
+        dispute(&next_state, now, &mut rng),
+        dispute(&later_state, close_after, &mut rng),
+        {
+            let mut circuit = dispute_circuit.clone();
+            let sk = pallas::Scalar::random(&mut rng);
+            circuit.signature_b = later_state.sign(&mut rng, channel.id, sk);
+            circuit
+        },
+    ] {
+        assert!(run(&circuit).verify().is_err());
+    }
+
+    // The dispute fails with a state signed by both parties for another of their channels
+    {
+        let other_channel_id = pallas::Base::random(&mut rng);
+        let mut circuit = dispute_circuit.clone();
+        circuit.signature_a = later_state.sign(&mut rng, other_channel_id, sk_a);
+        circuit.signature_b = later_state.sign(&mut rng, other_channel_id, sk_b);
+        assert!(run(&circuit).verify().is_err());
+    }
+
+    // The dispute fails on an open channel
+    {
+        let circuit = consume(
+            &next_state,
+            0,
+            next_channel(&later_state, 0, &mut rng),
+            &mut rng,
+        )
+        .dispute(
+            later_state,
+            later_state.sign(&mut rng, channel.id, sk_a),
+            later_state.sign(&mut rng, channel.id, sk_b),
+            now,
+        );
+        assert!(run(&circuit).verify().is_err());
+    }
+
+    // Settle: either party releases the balances of the disputed close from close_after on
+    let settle = |balance_a: u128, close_after: u64, now: u64, rng: &mut OsRng| {
+        consume(
+            &later_state,
+            close_after,
+            [
+                payout(channel.npk_a, balance_a, &mut *rng),
+                payout(channel.npk_b, later_state.total() - balance_a, &mut *rng),
+            ],
+            &mut *rng,
+        )
+        .settle(now)
+    };
+    let settle_circuit = settle(later_state.balance_a, close_after, close_after, &mut rng);
+    assert!(is_recent(
+        &settle_circuit.get_public_inputs(&mut rng),
+        close_after
+    ));
+    assert_verifies(&run(&settle_circuit));
+
+    // The settle fails before close_after, on an open channel or if a party takes the balance
+    // of the other
+    for circuit in [
+        settle(
+            later_state.balance_a,
+            close_after,
+            close_after - 1,
+            &mut rng,
+        ),
+        settle(later_state.balance_a, 0, close_after, &mut rng),
+        settle(later_state.total(), close_after, close_after, &mut rng),
+    ] {
+        assert!(run(&circuit).verify().is_err());
+    }
+
+    // Funding: creating the channel resource needs no signature
+    let funding_circuit = PaymentChannelResourceLogicCircuit::new(
+        update_circuit.output_resources[0].commitment().into(),
+        [(); NUM_RESOURCE].map(|_| random_resource(&mut rng)),
+        update_circuit.output_resources,
+        channel,
+        next_state,
+        0,
+    );
+    assert_eq!(
+        funding_circuit
+            .get_public_inputs(&mut rng)
+            .get_from_index(PAYMENT_CHANNEL_TIME_PUBLIC_INPUT_IDX),
+        pallas::Base::zero()
+    );
+    assert_verifies(&run(&funding_circuit));
+}
+
+#[test]
+fn test_check_channel_times() {
+    use crate::merkle_tree::MerklePath;
+    use crate::resource::ResourceLogics;
+    use crate::shielded_ptx::ShieldedPartialTransactionBuilder;
+    use crate::transaction::{ShieldedPartialTxBundle, TransparentPartialTxBundle};
+    use rand::rngs::OsRng;
+
+    let mut rng = OsRng;
+    let channel = PaymentChannel {
+        id: pallas::Base::random(&mut rng),
+        pk_a: pallas::Point::random(&mut rng),
+        pk_b: pallas::Point::random(&mut rng),
+        npk_a: pallas::Base::random(&mut rng),
+        npk_b: pallas::Base::random(&mut rng),
+        dispute_period: 100,
+    };
+    let nk = pallas::Base::random(&mut rng);
+    let state = ChannelState {
+        balance_a: 10,
+        balance_b: 5,
+        sequence: 3,
+    };
+    let now = 1000;
+    let close_after = now + channel.dispute_period;
+
+    // Party a starts closing the channel at `now`
+    let ptx = ShieldedPartialTransactionBuilder::new()
+        .add_input(
+            channel.create_input_resource(&mut rng, nk, &state, 0),
+            MerklePath::random(&mut rng, crate::constant::TAIGA_COMMITMENT_TREE_DEPTH),
+            move |_, resource, input_resources, output_resources| {
+                let resource_logic = PaymentChannelResourceLogicCircuit::new(
+                    resource.get_nf().unwrap().into(),
+                    input_resources,
+                    output_resources,
+                    channel,
+                    state,
+                    0,
+                )
+                .close(now);
+                ResourceLogics::from_application(Box::new(resource_logic))
+            },
+        )
+        .add_output(
+            channel.create_output_resource(&mut rng, nk, &state, close_after),
+            move |_, resource, input_resources, output_resources| {
+                let resource_logic = PaymentChannelResourceLogicCircuit::new(
+                    resource.commitment().into(),
+                    input_resources,
+                    output_resources,
+                    channel,
+                    state,
+                    close_after,
+                );
+                ResourceLogics::from_application(Box::new(resource_logic))
+            },
+        )
+        .build(&mut rng)
+        .unwrap();
+    ptx.verify_proof().unwrap();
+    let tx = Transaction::build(
+        &mut rng,
+        ShieldedPartialTxBundle::new(vec![ptx]),
+        TransparentPartialTxBundle::default(),
+    )
+    .unwrap();
+
+    // The ledger accepts the close up to the drift, and rejects a backdated or future time,
+    // which would shorten or lengthen the dispute period
+    check_channel_times(&tx, now).unwrap();
+    check_channel_times(&tx, now + PAYMENT_CHANNEL_MAX_TIME_DRIFT).unwrap();
+    for ledger_now in [now - 1, now + PAYMENT_CHANNEL_MAX_TIME_DRIFT + 1] {
+        assert!(matches!(
+            check_channel_times(&tx, ledger_now),
+            Err(TransactionError::StaleChannelTime(0))
+        ));
+    }
+}
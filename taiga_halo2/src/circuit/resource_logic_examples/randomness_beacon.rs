@@ -0,0 +1,564 @@
+/// This example is to demonstrate how an application ingests the value of a
+/// randomness beacon to settle a probabilistic outcome, e.g. a lottery draw or
+/// a random NFT trait. A ticket resource commits to the beacon and the round
+/// it is settled with, and to the kind of the reward:
+///
+/// value = poseidon_hash(beacon_pk || round || reward_logic || reward_label)
+///
+/// The beacon signs poseidon_hash(round || randomness) of each round with the
+/// Schnorr signature of the header chain gadget. Consuming the ticket verifies
+/// the signature and mints the reward (the first output) whose value is the
+/// draw of the ticket:
+///
+/// draw = poseidon_hash(randomness || ticket_nf)
+///
+/// The beacon id and the round are published, both when the ticket is created
+/// and when it's consumed. The verifier must check the beacon is trusted, and
+/// must only accept the creation of a ticket for a round the beacon hasn't
+/// revealed yet, otherwise the owner picks a round known to be favorable.
+/// Binding the draw to the nullifier makes the draws of the tickets of a
+/// round independent, and the owner can't grind it since the nullifier is
+/// fixed at the creation. The owner can still withhold an unfavorable draw by
+/// not consuming the ticket, the application must not reward the absence of a
+/// draw.
+use crate::{
+    circuit::{
+        blake2s::publicize_default_dynamic_resource_logic_commitments,
+        gadgets::{
+            assign_free_advice,
+            header_chain::HeaderSignature,
+            poseidon_hash::poseidon_hash_gadget,
+            schnorr::SchnorrChip,
+            target_resource_variable::{get_is_input_resource_flag, get_owned_resource_variable},
+        },
+        resource_logic_bytecode::{ResourceLogicByteCode, ResourceLogicRepresentation},
+        resource_logic_circuit::{
+            BasicResourceLogicVariables, ResourceLogicCircuit, ResourceLogicConfig,
+            ResourceLogicPublicInputs, ResourceLogicVerifyingInfoTrait,
+        },
+    },
+    constant::{
+        DEFAULT_PARAMS_STORE, NUM_RESOURCE, RESOURCE_LOGIC_CIRCUIT_CUSTOM_PUBLIC_INPUT_BEGIN_IDX,
+    },
+    error::TransactionError,
+    nullifier::Nullifier,
+    resource::{OwnedResourceId, RandomSeed, Resource},
+    resource_logic_commitment::ResourceLogicCommitment,
+    resource_logic_vk::ResourceLogicVerifyingKey,
+    utils::{poseidon_hash_n, read_base_field, read_point, read_scalar_field},
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+use halo2_gadgets::ecc::{chip::EccChip, NonIdentityPoint};
+use halo2_proofs::{
+    arithmetic::Field,
+    circuit::{floor_planner, Layouter, Value},
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use lazy_static::lazy_static;
+use pasta_curves::{
+    arithmetic::CurveAffine,
+    group::{ff::PrimeField, Curve, Group, GroupEncoding},
+    pallas,
+};
+use rand::RngCore;
+
+/// The index of the published beacon id.
+pub const BEACON_ID_PUBLIC_INPUT_IDX: usize = RESOURCE_LOGIC_CIRCUIT_CUSTOM_PUBLIC_INPUT_BEGIN_IDX;
+/// The index of the published round.
+pub const BEACON_ROUND_PUBLIC_INPUT_IDX: usize =
+    RESOURCE_LOGIC_CIRCUIT_CUSTOM_PUBLIC_INPUT_BEGIN_IDX + 1;
+
+lazy_static! {
+    pub static ref RANDOMNESS_BEACON_VK: ResourceLogicVerifyingKey =
+        RandomnessBeaconResourceLogicCircuit::default()
+            .get_resource_logic_vk()
+            .unwrap();
+    pub static ref COMPRESSED_RANDOMNESS_BEACON_VK: pallas::Base =
+        RANDOMNESS_BEACON_VK.get_compressed();
+}
+
+/// The id of the beacon with the public key `pk`: poseidon_hash(pk)
+pub fn beacon_id(pk: &pallas::Point) -> pallas::Base {
+    let pk = pk.to_affine().coordinates().unwrap();
+    poseidon_hash_n([*pk.x(), *pk.y()])
+}
+
+/// The value of a beacon round, signed by the beacon.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BeaconValue {
+    pub randomness: pallas::Base,
+    pub signature: HeaderSignature,
+}
+
+impl BeaconValue {
+    pub fn sign<R: RngCore>(
+        rng: R,
+        sk: pallas::Scalar,
+        round: u64,
+        randomness: pallas::Base,
+    ) -> Self {
+        let signature = HeaderSignature::sign(rng, sk, Self::message(round, randomness));
+        Self {
+            randomness,
+            signature,
+        }
+    }
+
+    pub fn verify(&self, pk: &pallas::Point, round: u64) -> bool {
+        self.signature
+            .verify(pk, Self::message(round, self.randomness))
+    }
+
+    // The signed message: poseidon_hash(round || randomness)
+    fn message(round: u64, randomness: pallas::Base) -> pallas::Base {
+        poseidon_hash_n([pallas::Base::from(round), randomness])
+    }
+}
+
+/// The commitments of a ticket, fixed when the ticket is created.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BeaconTicket {
+    pub beacon_pk: pallas::Point,
+    pub round: u64,
+    pub reward_logic: pallas::Base,
+    pub reward_label: pallas::Base,
+}
+
+impl Default for BeaconTicket {
+    fn default() -> Self {
+        Self {
+            beacon_pk: pallas::Point::generator(),
+            round: 0,
+            reward_logic: pallas::Base::zero(),
+            reward_label: pallas::Base::zero(),
+        }
+    }
+}
+
+impl BeaconTicket {
+    pub fn encode_value(&self) -> pallas::Base {
+        let beacon_pk = self.beacon_pk.to_affine().coordinates().unwrap();
+        poseidon_hash_n([
+            *beacon_pk.x(),
+            *beacon_pk.y(),
+            pallas::Base::from(self.round),
+            self.reward_logic,
+            self.reward_label,
+        ])
+    }
+
+    /// The draw of the ticket with the nullifier `ticket_nf`.
+    pub fn draw(randomness: pallas::Base, ticket_nf: pallas::Base) -> pallas::Base {
+        poseidon_hash_n([randomness, ticket_nf])
+    }
+
+    /// Creates the ticket to consume.
+    pub fn create_input_resource<R: RngCore>(
+        &self,
+        mut rng: R,
+        label: pallas::Base,
+        nk: pallas::Base,
+    ) -> Resource {
+        let rseed = pallas::Base::random(&mut rng);
+        let nonce = Nullifier::random(&mut rng);
+        Resource::new_input_resource(
+            *COMPRESSED_RANDOMNESS_BEACON_VK,
+            label,
+            self.encode_value(),
+            1u128,
+            nk,
+            nonce,
+            false,
+            rseed,
+        )
+    }
+
+    /// Creates the reward of the ticket with the nullifier `ticket_nf`.
+    pub fn create_reward_resource<R: RngCore>(
+        &self,
+        mut rng: R,
+        randomness: pallas::Base,
+        ticket_nf: pallas::Base,
+        npk: pallas::Base,
+    ) -> Resource {
+        let rseed = pallas::Base::random(&mut rng);
+        Resource::new_output_resource(
+            self.reward_logic,
+            self.reward_label,
+            Self::draw(randomness, ticket_nf),
+            1u128,
+            npk,
+            false,
+            rseed,
+        )
+    }
+}
+
+/// Maps a draw to one of `outcomes` outcomes, e.g. the trait of an NFT or the winning tier of a
+/// lottery. The bias of the reduction is negligible for small numbers of outcomes.
+pub fn draw_outcome(draw: pallas::Base, outcomes: u64) -> u64 {
+    assert!(outcomes > 0);
+    let draw = draw.to_repr();
+    u64::from_le_bytes(draw[..8].try_into().unwrap()) % outcomes
+}
+
+// RandomnessBeaconResourceLogicCircuit
+#[derive(Clone, Debug, Default)]
+pub struct RandomnessBeaconResourceLogicCircuit {
+    pub owned_resource_id: OwnedResourceId,
+    pub input_resources: [Resource; NUM_RESOURCE],
+    pub output_resources: [Resource; NUM_RESOURCE],
+    pub ticket: BeaconTicket,
+    // The value of the round of the ticket, ignored when the ticket is created
+    pub beacon_value: BeaconValue,
+}
+
+impl RandomnessBeaconResourceLogicCircuit {
+    pub fn to_bytecode(&self) -> ResourceLogicByteCode {
+        ResourceLogicByteCode::new(
+            ResourceLogicRepresentation::RandomnessBeacon,
+            self.to_bytes(),
+        )
+    }
+}
+
+impl ResourceLogicCircuit for RandomnessBeaconResourceLogicCircuit {
+    // Add custom constraints
+    fn custom_constraints(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<pallas::Base>,
+        basic_variables: BasicResourceLogicVariables,
+    ) -> Result<(), Error> {
+        let owned_resource_id = basic_variables.get_owned_resource_id();
+        let is_input_resource = get_is_input_resource_flag(
+            config.get_is_input_resource_flag_config,
+            layouter.namespace(|| "get is_input_resource_flag"),
+            &owned_resource_id,
+            &basic_variables.get_input_resource_nfs(),
+            &basic_variables.get_output_resource_cms(),
+        )?;
+
+        // Witness the ticket and check the value encoding
+        let beacon_pk = NonIdentityPoint::new(
+            EccChip::construct(config.ecc_config.clone()),
+            layouter.namespace(|| "witness beacon pk"),
+            Value::known(self.ticket.beacon_pk.to_affine()),
+        )?;
+        let round = assign_free_advice(
+            layouter.namespace(|| "witness round"),
+            config.advices[0],
+            Value::known(pallas::Base::from(self.ticket.round)),
+        )?;
+        let reward_logic = assign_free_advice(
+            layouter.namespace(|| "witness reward logic"),
+            config.advices[0],
+            Value::known(self.ticket.reward_logic),
+        )?;
+        let reward_label = assign_free_advice(
+            layouter.namespace(|| "witness reward label"),
+            config.advices[0],
+            Value::known(self.ticket.reward_label),
+        )?;
+        let encoded_value = poseidon_hash_gadget(
+            config.poseidon_config.clone(),
+            layouter.namespace(|| "value encoding"),
+            [
+                beacon_pk.inner().x(),
+                beacon_pk.inner().y(),
+                round.clone(),
+                reward_logic.clone(),
+                reward_label.clone(),
+            ],
+        )?;
+        let value = get_owned_resource_variable(
+            config.get_owned_resource_variable_config,
+            layouter.namespace(|| "get owned resource value"),
+            &owned_resource_id,
+            &basic_variables.get_value_searchable_pairs(),
+        )?;
+        layouter.assign_region(
+            || "check value encoding",
+            |mut region| region.constrain_equal(encoded_value.cell(), value.cell()),
+        )?;
+
+        // Verify the signature of the beacon value when consuming the ticket
+        let randomness = assign_free_advice(
+            layouter.namespace(|| "witness randomness"),
+            config.advices[0],
+            Value::known(self.beacon_value.randomness),
+        )?;
+        let message = poseidon_hash_gadget(
+            config.poseidon_config.clone(),
+            layouter.namespace(|| "beacon message"),
+            [round.clone(), randomness.clone()],
+        )?;
+        SchnorrChip::from_resource_logic_config(&config).conditional_verify(
+            layouter.namespace(|| "verify the beacon signature"),
+            &config.conditional_equal_config,
+            &is_input_resource,
+            &beacon_pk,
+            &message,
+            &self.beacon_value.signature,
+        )?;
+
+        // Mint the reward with the draw of the ticket, the owned resource id is the nullifier
+        let draw = poseidon_hash_gadget(
+            config.poseidon_config.clone(),
+            layouter.namespace(|| "draw"),
+            [randomness, owned_resource_id],
+        )?;
+        let reward = &basic_variables.output_resource_variables[0].resource_variables;
+        for (name, expected, actual) in [
+            ("value", &draw, &reward.value),
+            ("logic", &reward_logic, &reward.logic),
+            ("label", &reward_label, &reward.label),
+        ] {
+            layouter.assign_region(
+                || format!("conditional equal: check the reward {name}"),
+                |mut region| {
+                    config.conditional_equal_config.assign_region(
+                        &is_input_resource,
+                        expected,
+                        actual,
+                        0,
+                        &mut region,
+                    )
+                },
+            )?;
+        }
+
+        // Publicize the beacon id and the round
+        let beacon_id = poseidon_hash_gadget(
+            config.poseidon_config.clone(),
+            layouter.namespace(|| "beacon id"),
+            [beacon_pk.inner().x(), beacon_pk.inner().y()],
+        )?;
+        layouter.constrain_instance(
+            beacon_id.cell(),
+            config.instances,
+            BEACON_ID_PUBLIC_INPUT_IDX,
+        )?;
+        layouter.constrain_instance(
+            round.cell(),
+            config.instances,
+            BEACON_ROUND_PUBLIC_INPUT_IDX,
+        )?;
+
+        // Publicize the dynamic resource_logic commitments with default value
+        publicize_default_dynamic_resource_logic_commitments(
+            &mut layouter,
+            config.advices[0],
+            config.instances,
+        )?;
+
+        Ok(())
+    }
+
+    fn get_input_resources(&self) -> &[Resource; NUM_RESOURCE] {
+        &self.input_resources
+    }
+
+    fn get_output_resources(&self) -> &[Resource; NUM_RESOURCE] {
+        &self.output_resources
+    }
+
+    fn get_public_inputs(&self, mut rng: impl RngCore) -> ResourceLogicPublicInputs {
+        let mut public_inputs = self.get_mandatory_public_inputs();
+        let default_resource_logic_cm: [pallas::Base; 2] =
+            ResourceLogicCommitment::default().to_public_inputs();
+        public_inputs.extend(default_resource_logic_cm);
+        public_inputs.extend(default_resource_logic_cm);
+        public_inputs.push(beacon_id(&self.ticket.beacon_pk));
+        public_inputs.push(pallas::Base::from(self.ticket.round));
+        let padding = ResourceLogicPublicInputs::get_public_input_padding(
+            public_inputs.len(),
+            &RandomSeed::random(&mut rng),
+        );
+        public_inputs.extend(padding);
+        public_inputs.into()
+    }
+
+    fn get_owned_resource_id(&self) -> OwnedResourceId {
+        self.owned_resource_id
+    }
+}
+
+resource_logic_circuit_impl!(RandomnessBeaconResourceLogicCircuit);
+resource_logic_verifying_info_impl!(RandomnessBeaconResourceLogicCircuit);
+circuit_field_encoding_from_borsh!(BeaconTicket, BeaconValue);
+resource_logic_circuit_encoding_impl!(RandomnessBeaconResourceLogicCircuit {
+    1 => owned_resource_id,
+    2 => input_resources,
+    3 => output_resources,
+    4 => ticket,
+    5 => beacon_value,
+});
+
+impl BorshSerialize for BeaconValue {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(&self.randomness.to_repr())?;
+        writer.write_all(&self.signature.r.to_bytes())?;
+        writer.write_all(&self.signature.s.to_repr())?;
+        Ok(())
+    }
+}
+
+impl BorshDeserialize for BeaconValue {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let randomness = read_base_field(reader)?;
+        let r = read_point(reader)?;
+        let s = read_scalar_field(reader)?;
+        Ok(Self {
+            randomness,
+            signature: HeaderSignature { r, s },
+        })
+    }
+}
+
+impl BorshSerialize for BeaconTicket {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(&self.beacon_pk.to_bytes())?;
+        self.round.serialize(writer)?;
+        writer.write_all(&self.reward_logic.to_repr())?;
+        writer.write_all(&self.reward_label.to_repr())?;
+        Ok(())
+    }
+}
+
+impl BorshDeserialize for BeaconTicket {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let beacon_pk = read_point(reader)?;
+        let round = u64::deserialize_reader(reader)?;
+        let reward_logic = read_base_field(reader)?;
+        let reward_label = read_base_field(reader)?;
+        Ok(Self {
+            beacon_pk,
+            round,
+            reward_logic,
+            reward_label,
+        })
+    }
+}
+
+#[test]
+fn test_halo2_randomness_beacon_resource_logic_circuit() {
+    use crate::circuit::verify_failure::assert_verifies;
+    use crate::constant::RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE;
+    use crate::resource::tests::random_resource;
+    use halo2_proofs::dev::MockProver;
+    use rand::rngs::OsRng;
+
+    let mut rng = OsRng;
+    let beacon_sk = pallas::Scalar::random(&mut rng);
+    let ticket = BeaconTicket {
+        beacon_pk: pallas::Point::generator() * beacon_sk,
+        round: 42,
+        reward_logic: pallas::Base::random(&mut rng),
+        reward_label: pallas::Base::random(&mut rng),
+    };
+    let ticket_resource = ticket.create_input_resource(
+        &mut rng,
+        pallas::Base::random(&mut rng),
+        pallas::Base::random(&mut rng),
+    );
+    let ticket_nf = ticket_resource.get_nf().unwrap().inner();
+    let randomness = pallas::Base::random(&mut rng);
+    let beacon_value = BeaconValue::sign(&mut rng, beacon_sk, ticket.round, randomness);
+    assert!(beacon_value.verify(&ticket.beacon_pk, ticket.round));
+
+    let run = |circuit: &RandomnessBeaconResourceLogicCircuit| {
+        let public_inputs = circuit.get_public_inputs(OsRng);
+        MockProver::<pallas::Base>::run(
+            RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+            circuit,
+            vec![public_inputs.to_vec()],
+        )
+        .unwrap()
+    };
+
+    // Consume the ticket and mint the reward with the draw
+    let reward = ticket.create_reward_resource(
+        &mut rng,
+        randomness,
+        ticket_nf,
+        pallas::Base::random(&mut rng),
+    );
+    let circuit = RandomnessBeaconResourceLogicCircuit {
+        owned_resource_id: ticket_resource.get_nf().unwrap().into(),
+        input_resources: [ticket_resource, random_resource(&mut rng)],
+        output_resources: [reward, random_resource(&mut rng)],
+        ticket,
+        beacon_value,
+    };
+
+    // Test serialization
+    let circuit = {
+        let circuit_bytes = circuit.to_bytes();
+        RandomnessBeaconResourceLogicCircuit::from_bytes(&circuit_bytes)
+    };
+
+    let public_inputs = circuit.get_public_inputs(&mut rng);
+    assert_eq!(
+        public_inputs.get_from_index(BEACON_ID_PUBLIC_INPUT_IDX),
+        beacon_id(&ticket.beacon_pk)
+    );
+    assert_eq!(
+        public_inputs.get_from_index(BEACON_ROUND_PUBLIC_INPUT_IDX),
+        pallas::Base::from(ticket.round)
+    );
+    assert!(draw_outcome(reward.value, 6) < 6);
+    assert_verifies(&run(&circuit));
+
+    // The value of another round is rejected
+    {
+        let mut circuit = circuit.clone();
+        circuit.beacon_value = BeaconValue::sign(&mut rng, beacon_sk, ticket.round + 1, randomness);
+        assert!(run(&circuit).verify().is_err());
+    }
+
+    // A randomness not signed by the beacon is rejected
+    {
+        let mut circuit = circuit.clone();
+        let randomness = pallas::Base::random(&mut rng);
+        circuit.beacon_value = BeaconValue::sign(
+            &mut rng,
+            pallas::Scalar::random(&mut rng),
+            ticket.round,
+            randomness,
+        );
+        circuit.output_resources[0] = ticket.create_reward_resource(
+            &mut rng,
+            randomness,
+            ticket_nf,
+            pallas::Base::random(&mut rng),
+        );
+        assert!(run(&circuit).verify().is_err());
+    }
+
+    // A reward with another draw is rejected
+    {
+        let mut circuit = circuit.clone();
+        circuit.output_resources[0].value = pallas::Base::random(&mut rng);
+        assert!(run(&circuit).verify().is_err());
+    }
+
+    // Creating the ticket needs no beacon value
+    let ticket_output = Resource::new_output_resource(
+        *COMPRESSED_RANDOMNESS_BEACON_VK,
+        pallas::Base::random(&mut rng),
+        ticket.encode_value(),
+        1u128,
+        pallas::Base::random(&mut rng),
+        false,
+        pallas::Base::random(&mut rng),
+    );
+    let creation_circuit = RandomnessBeaconResourceLogicCircuit {
+        owned_resource_id: ticket_output.commitment().into(),
+        input_resources: [(); NUM_RESOURCE].map(|_| random_resource(&mut rng)),
+        output_resources: [ticket_output, random_resource(&mut rng)],
+        ticket,
+        ..Default::default()
+    };
+    assert_verifies(&run(&creation_circuit));
+}
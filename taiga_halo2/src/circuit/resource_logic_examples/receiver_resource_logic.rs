@@ -2,51 +2,189 @@ use crate::{
     circuit::{
         blake2s::publicize_default_dynamic_resource_logic_commitments,
         gadgets::{
-            add::AddChip, assign_free_advice, poseidon_hash::poseidon_hash_gadget,
+            add::AddChip,
+            assign_free_advice,
+            hash_accumulator::HashAccumulatorChip,
+            mul::MulChip,
+            poseidon_hash::poseidon_hash_gadget,
+            sub::SubChip,
             target_resource_variable::get_owned_resource_variable,
+            vk_policy::{vk_policy_commitment, vk_policy_gadget},
         },
         resource_encryption_circuit::resource_encryption_gadget,
         resource_logic_bytecode::{ResourceLogicByteCode, ResourceLogicRepresentation},
         resource_logic_circuit::{
             BasicResourceLogicVariables, ResourceLogicCircuit, ResourceLogicConfig,
-            ResourceLogicPublicInputs, ResourceLogicVerifyingInfo, ResourceLogicVerifyingInfoTrait,
+            ResourceLogicPublicInputs, ResourceLogicVerifyingInfoTrait,
         },
-        resource_logic_examples::signature_verification::COMPRESSED_TOKEN_AUTH_VK,
     },
-    constant::{GENERATOR, NUM_RESOURCE, SETUP_PARAMS_MAP},
+    constant::{
+        TaigaFixedBases, DEFAULT_PARAMS_STORE, NUM_RESOURCE,
+        RECEIVER_POLICY_PERSONALIZATION_TO_FIELD,
+    },
     error::TransactionError,
-    proof::Proof,
-    resource::{RandomSeed, Resource},
-    resource_encryption::{ResourceCiphertext, ResourcePlaintext, SecretKey},
+    ka::EphemeralSecretKey,
+    resource::{OwnedResourceId, RandomSeed, Resource},
+    resource_encryption::{ResourceCiphertext, ResourcePlaintext},
     resource_logic_commitment::ResourceLogicCommitment,
     resource_logic_vk::ResourceLogicVerifyingKey,
-    utils::{mod_r_p, read_base_field, read_point},
+    utils::{poseidon_hash_n, read_base_field},
 };
 use borsh::{BorshDeserialize, BorshSerialize};
-use group::{cofactor::CofactorCurveAffine, ff::PrimeField, Curve, Group, GroupEncoding};
-use halo2_gadgets::ecc::{chip::EccChip, NonIdentityPoint};
+use group::{ff::PrimeField, Curve, Group};
+use halo2_gadgets::{
+    ecc::{chip::EccChip, NonIdentityPoint},
+    poseidon::Pow5Config as PoseidonConfig,
+};
 use halo2_proofs::{
-    arithmetic::CurveAffine,
-    circuit::{floor_planner, Layouter, Value},
-    plonk::{keygen_pk, keygen_vk, Circuit, ConstraintSystem, Error},
+    circuit::{floor_planner, AssignedCell, Layouter, Value},
+    plonk::{Advice, Circuit, Column, ConstraintSystem, Error},
 };
 use lazy_static::lazy_static;
-use pasta_curves::pallas;
-use rand::rngs::OsRng;
+use pasta_curves::{arithmetic::CurveAffine, pallas};
 use rand::RngCore;
 
 const CIPHER_LEN: usize = 9;
 
+/// The number of receiver resource logics in a receiver policy.
+pub const RECEIVER_POLICY_SIZE: usize = 2;
+
 lazy_static! {
-    pub static ref RECEIVER_VK: ResourceLogicVerifyingKey =
-        ReceiverResourceLogicCircuit::default().get_resource_logic_vk();
+    pub static ref RECEIVER_VK: ResourceLogicVerifyingKey = ReceiverResourceLogicCircuit::default()
+        .get_resource_logic_vk()
+        .unwrap();
     pub static ref COMPRESSED_RECEIVER_VK: pallas::Base = RECEIVER_VK.get_compressed();
 }
 
+/// A receiver policy is the set of receiver resource logics a token resource accepts, e.g. either
+/// the standard receiver or a custodial receiver. The value of the token resource commits to the
+/// policy instead of a single receiver resource logic, and the resource logics check the receiver
+/// resource logic is a member of it. The policy is fixed-size, a policy with fewer receivers
+/// repeats them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReceiverPolicy {
+    receiver_resource_logic_vks: [pallas::Base; RECEIVER_POLICY_SIZE],
+}
+
+impl ReceiverPolicy {
+    pub fn new(receiver_resource_logic_vks: [pallas::Base; RECEIVER_POLICY_SIZE]) -> Self {
+        Self {
+            receiver_resource_logic_vks,
+        }
+    }
+
+    // The policy only accepts the receiver_resource_logic_vk
+    pub fn single(receiver_resource_logic_vk: pallas::Base) -> Self {
+        Self::new([receiver_resource_logic_vk; RECEIVER_POLICY_SIZE])
+    }
+
+    // The policy only accepts the standard receiver resource logic
+    pub fn standard() -> Self {
+        Self::single(*COMPRESSED_RECEIVER_VK)
+    }
+
+    pub fn receiver_resource_logic_vks(&self) -> &[pallas::Base; RECEIVER_POLICY_SIZE] {
+        &self.receiver_resource_logic_vks
+    }
+
+    pub fn contains(&self, receiver_resource_logic_vk: &pallas::Base) -> bool {
+        self.receiver_resource_logic_vks
+            .contains(receiver_resource_logic_vk)
+    }
+
+    // The commitment to the policy encoded in the value of the token resource
+    pub fn commitment(&self) -> pallas::Base {
+        vk_policy_commitment(
+            *RECEIVER_POLICY_PERSONALIZATION_TO_FIELD,
+            &self.receiver_resource_logic_vks,
+        )
+    }
+}
+
+impl BorshSerialize for ReceiverPolicy {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        for vk in self.receiver_resource_logic_vks.iter() {
+            writer.write_all(&vk.to_repr())?;
+        }
+        Ok(())
+    }
+}
+
+impl BorshDeserialize for ReceiverPolicy {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let receiver_resource_logic_vks: Vec<_> = (0..RECEIVER_POLICY_SIZE)
+            .map(|_| read_base_field(reader))
+            .collect::<Result<_, _>>()?;
+        Ok(Self::new(receiver_resource_logic_vks.try_into().unwrap()))
+    }
+}
+
+/// Derives the value of a resource owned by the receiver, as the receiver resource logic decodes
+/// it: the hash of the receiver public key, the authorization resource logic vk and the
+/// commitment to the receiver policy. Senders set it as the value of the output resource, a
+/// mismatch only surfaces as a failing receiver proof. `receiver_value_gadget` is the in-circuit
+/// counterpart.
+pub fn derive_receiver_value(
+    receiver_pk: &pallas::Point,
+    auth_resource_logic_vk: pallas::Base,
+    receiver_policy: &ReceiverPolicy,
+) -> pallas::Base {
+    let pk_coord = receiver_pk.to_affine().coordinates().unwrap();
+    poseidon_hash_n([
+        *pk_coord.x(),
+        *pk_coord.y(),
+        auth_resource_logic_vk,
+        receiver_policy.commitment(),
+    ])
+}
+
+/// Computes the receiver value in the circuit, see `derive_receiver_value`.
+pub fn receiver_value_gadget(
+    layouter: impl Layouter<pallas::Base>,
+    poseidon_config: PoseidonConfig<pallas::Base, 3, 2>,
+    receiver_pk: &NonIdentityPoint<pallas::Affine, EccChip<TaigaFixedBases>>,
+    auth_resource_logic_vk: AssignedCell<pallas::Base, pallas::Base>,
+    receiver_policy_commitment: AssignedCell<pallas::Base, pallas::Base>,
+) -> Result<AssignedCell<pallas::Base, pallas::Base>, Error> {
+    poseidon_hash_gadget(
+        poseidon_config,
+        layouter,
+        [
+            receiver_pk.inner().x(),
+            receiver_pk.inner().y(),
+            auth_resource_logic_vk,
+            receiver_policy_commitment,
+        ],
+    )
+}
+
+/// Checks the receiver resource logic is a member of the receiver policy, and returns the
+/// commitment to the policy.
+pub fn receiver_policy_gadget(
+    layouter: impl Layouter<pallas::Base>,
+    advice: Column<Advice>,
+    accumulator_chip: &HashAccumulatorChip,
+    sub_chip: &SubChip<pallas::Base>,
+    mul_chip: &MulChip<pallas::Base>,
+    receiver_policy: &ReceiverPolicy,
+    receiver_resource_logic_vk: &AssignedCell<pallas::Base, pallas::Base>,
+) -> Result<AssignedCell<pallas::Base, pallas::Base>, Error> {
+    vk_policy_gadget(
+        layouter,
+        advice,
+        accumulator_chip,
+        *RECEIVER_POLICY_PERSONALIZATION_TO_FIELD,
+        sub_chip,
+        mul_chip,
+        &receiver_policy.receiver_resource_logic_vks,
+        receiver_resource_logic_vk,
+    )
+}
+
 // ReceiverResourceLogicCircuit is used in the token resource_logic as dynamic resource_logic and contains the resource encryption constraints.
 #[derive(Clone, Debug)]
 pub struct ReceiverResourceLogicCircuit {
-    pub owned_resource_id: pallas::Base,
+    pub owned_resource_id: OwnedResourceId,
     pub input_resources: [Resource; NUM_RESOURCE],
     pub output_resources: [Resource; NUM_RESOURCE],
     pub resource_logic_vk: pallas::Base,
@@ -54,26 +192,20 @@ pub struct ReceiverResourceLogicCircuit {
     pub sk: pallas::Base,
     pub rcv_pk: pallas::Point,
     pub auth_resource_logic_vk: pallas::Base,
+    // The receiver policy of the owned resource, it must include resource_logic_vk
+    pub receiver_policy: ReceiverPolicy,
 }
 
 impl ReceiverResourceLogicCircuit {
     pub fn to_bytecode(&self) -> ResourceLogicByteCode {
         ResourceLogicByteCode::new(ResourceLogicRepresentation::Receiver, self.to_bytes())
     }
-
-    pub fn to_bytes(&self) -> Vec<u8> {
-        borsh::to_vec(&self).unwrap()
-    }
-
-    pub fn from_bytes(bytes: &Vec<u8>) -> Self {
-        BorshDeserialize::deserialize(&mut bytes.as_ref()).unwrap()
-    }
 }
 
 impl Default for ReceiverResourceLogicCircuit {
     fn default() -> Self {
         Self {
-            owned_resource_id: pallas::Base::zero(),
+            owned_resource_id: OwnedResourceId::default(),
             input_resources: [(); NUM_RESOURCE].map(|_| Resource::default()),
             output_resources: [(); NUM_RESOURCE].map(|_| Resource::default()),
             resource_logic_vk: pallas::Base::zero(),
@@ -81,6 +213,7 @@ impl Default for ReceiverResourceLogicCircuit {
             sk: pallas::Base::zero(),
             rcv_pk: pallas::Point::generator(),
             auth_resource_logic_vk: pallas::Base::zero(),
+            receiver_policy: ReceiverPolicy::default(),
         }
     }
 }
@@ -125,7 +258,7 @@ impl ResourceLogicCircuit for ReceiverResourceLogicCircuit {
         let auth_resource_logic_vk = assign_free_advice(
             layouter.namespace(|| "witness auth resource_logic vk"),
             config.advices[0],
-            Value::known(*COMPRESSED_TOKEN_AUTH_VK),
+            Value::known(self.auth_resource_logic_vk),
         )?;
         let receiver_resource_logic_vk = assign_free_advice(
             layouter.namespace(|| "witness receiver resource_logic vk"),
@@ -133,16 +266,32 @@ impl ResourceLogicCircuit for ReceiverResourceLogicCircuit {
             Value::known(self.resource_logic_vk),
         )?;
 
-        // Decode the value, and check the value encoding
-        let encoded_value = poseidon_hash_gadget(
+        let sub_chip = SubChip::construct(config.sub_config.clone(), ());
+        let mul_chip = MulChip::construct(config.mul_config.clone());
+        let accumulator_chip = HashAccumulatorChip::construct(
             config.poseidon_config.clone(),
+            config.conditional_select_config,
+            AddChip::construct(config.add_config.clone(), ()),
+            mul_chip.clone(),
+            config.advices[0],
+        );
+        let receiver_policy = receiver_policy_gadget(
+            layouter.namespace(|| "receiver policy"),
+            config.advices[0],
+            &accumulator_chip,
+            &sub_chip,
+            &mul_chip,
+            &self.receiver_policy,
+            &receiver_resource_logic_vk,
+        )?;
+
+        // Decode the value, and check the value encoding
+        let encoded_value = receiver_value_gadget(
             layouter.namespace(|| "value encoding"),
-            [
-                rcv_pk.inner().x(),
-                rcv_pk.inner().y(),
-                auth_resource_logic_vk,
-                receiver_resource_logic_vk,
-            ],
+            config.poseidon_config.clone(),
+            &rcv_pk,
+            auth_resource_logic_vk,
+            receiver_policy,
         )?;
 
         layouter.assign_region(
@@ -261,7 +410,7 @@ impl ResourceLogicCircuit for ReceiverResourceLogicCircuit {
         public_inputs.extend(custom_public_input_padding.iter());
         assert_eq!(NUM_RESOURCE, 2);
         let target_resource = if self.get_owned_resource_id()
-            == self.get_output_resources()[0].commitment().inner()
+            == OwnedResourceId::Cm(self.get_output_resources()[0].commitment())
         {
             self.get_output_resources()[0]
         } else {
@@ -271,86 +420,53 @@ impl ResourceLogicCircuit for ReceiverResourceLogicCircuit {
             target_resource.kind.logic,
             target_resource.kind.label,
             target_resource.value,
-            pallas::Base::from(target_resource.quantity),
+            pallas::Base::from_u128(target_resource.quantity),
             target_resource.nonce.inner(),
             target_resource.get_npk(),
             pallas::Base::from(target_resource.is_ephemeral as u64),
             target_resource.rseed,
         ];
         let plaintext = ResourcePlaintext::padding(&message);
-        let key = SecretKey::from_dh_exchange(&self.rcv_pk, &mod_r_p(self.sk));
+        let esk = EphemeralSecretKey::from_base(self.sk);
+        let key = esk.agree(&self.rcv_pk);
         let cipher = ResourceCiphertext::encrypt(&plaintext, &key, &self.encrypt_nonce);
         cipher.inner().iter().for_each(|&c| public_inputs.push(c));
 
-        let generator = GENERATOR.to_curve();
-        let pk = generator * mod_r_p(self.sk);
-        let pk_coord = pk.to_affine().coordinates().unwrap();
-        public_inputs.push(*pk_coord.x());
-        public_inputs.push(*pk_coord.y());
+        let (pk_x, pk_y) = esk.public_key().to_coordinates();
+        public_inputs.push(pk_x);
+        public_inputs.push(pk_y);
         public_inputs.into()
     }
 
-    fn get_owned_resource_id(&self) -> pallas::Base {
+    fn get_owned_resource_id(&self) -> OwnedResourceId {
         self.owned_resource_id
     }
 }
 
 resource_logic_circuit_impl!(ReceiverResourceLogicCircuit);
 resource_logic_verifying_info_impl!(ReceiverResourceLogicCircuit);
-
-impl BorshSerialize for ReceiverResourceLogicCircuit {
-    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
-        writer.write_all(&self.owned_resource_id.to_repr())?;
-        for input in self.input_resources.iter() {
-            input.serialize(writer)?;
-        }
-
-        for output in self.output_resources.iter() {
-            output.serialize(writer)?;
-        }
-
-        writer.write_all(&self.resource_logic_vk.to_repr())?;
-        writer.write_all(&self.encrypt_nonce.to_repr())?;
-        writer.write_all(&self.sk.to_repr())?;
-        writer.write_all(&self.rcv_pk.to_bytes())?;
-        writer.write_all(&self.auth_resource_logic_vk.to_repr())?;
-
-        Ok(())
-    }
-}
-
-impl BorshDeserialize for ReceiverResourceLogicCircuit {
-    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
-        let owned_resource_id = read_base_field(reader)?;
-        let input_resources: Vec<_> = (0..NUM_RESOURCE)
-            .map(|_| Resource::deserialize_reader(reader))
-            .collect::<Result<_, _>>()?;
-        let output_resources: Vec<_> = (0..NUM_RESOURCE)
-            .map(|_| Resource::deserialize_reader(reader))
-            .collect::<Result<_, _>>()?;
-        let resource_logic_vk = read_base_field(reader)?;
-        let encrypt_nonce = read_base_field(reader)?;
-        let sk = read_base_field(reader)?;
-        let rcv_pk = read_point(reader)?;
-        let auth_resource_logic_vk = read_base_field(reader)?;
-        Ok(Self {
-            owned_resource_id,
-            input_resources: input_resources.try_into().unwrap(),
-            output_resources: output_resources.try_into().unwrap(),
-            resource_logic_vk,
-            encrypt_nonce,
-            sk,
-            rcv_pk,
-            auth_resource_logic_vk,
-        })
-    }
-}
+circuit_field_encoding_from_borsh!(ReceiverPolicy);
+resource_logic_circuit_encoding_impl!(ReceiverResourceLogicCircuit {
+    1 => owned_resource_id,
+    2 => input_resources,
+    3 => output_resources,
+    4 => resource_logic_vk,
+    5 => encrypt_nonce,
+    6 => sk,
+    7 => rcv_pk,
+    8 => auth_resource_logic_vk,
+    9 => receiver_policy,
+});
 
 #[test]
 fn test_halo2_receiver_resource_logic_circuit() {
-    use crate::constant::RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE;
-    use crate::{resource::tests::random_resource, utils::poseidon_hash_n};
+    use crate::circuit::resource_logic_examples::signature_verification::COMPRESSED_TOKEN_AUTH_VK;
+    use crate::circuit::verify_failure::assert_verifies;
+    use crate::constant::{GENERATOR, RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE};
+    use crate::resource::tests::random_resource;
+    use crate::utils::mod_r_p;
     use ff::{Field, PrimeField};
+    use group::cofactor::CofactorCurveAffine;
     use halo2_proofs::dev::MockProver;
     use rand::rngs::OsRng;
 
@@ -363,14 +479,12 @@ fn test_halo2_receiver_resource_logic_circuit() {
         let rcv_sk = pallas::Base::random(&mut rng);
         let generator = GENERATOR.to_curve();
         let rcv_pk = generator * mod_r_p(rcv_sk);
-        let rcv_pk_coord = rcv_pk.to_affine().coordinates().unwrap();
-        output_resources[0].value = poseidon_hash_n([
-            *rcv_pk_coord.x(),
-            *rcv_pk_coord.y(),
-            *COMPRESSED_TOKEN_AUTH_VK,
-            *COMPRESSED_RECEIVER_VK,
-        ]);
-        let owned_resource_id = output_resources[0].commitment().inner();
+        // The policy accepts either a custodial receiver or the standard receiver
+        let receiver_policy =
+            ReceiverPolicy::new([pallas::Base::random(&mut rng), *COMPRESSED_RECEIVER_VK]);
+        output_resources[0].value =
+            derive_receiver_value(&rcv_pk, *COMPRESSED_TOKEN_AUTH_VK, &receiver_policy);
+        let owned_resource_id = output_resources[0].commitment().into();
         (
             ReceiverResourceLogicCircuit {
                 owned_resource_id,
@@ -381,13 +495,14 @@ fn test_halo2_receiver_resource_logic_circuit() {
                 sk,
                 rcv_pk,
                 auth_resource_logic_vk: *COMPRESSED_TOKEN_AUTH_VK,
+                receiver_policy,
             },
             rcv_sk,
         )
     };
 
     // Test serialization
-    let circuit = {
+    let mut circuit = {
         let circuit_bytes = circuit.to_bytes();
         ReceiverResourceLogicCircuit::from_bytes(&circuit_bytes)
     };
@@ -400,7 +515,7 @@ fn test_halo2_receiver_resource_logic_circuit() {
         vec![public_inputs.to_vec()],
     )
     .unwrap();
-    assert_eq!(prover.verify(), Ok(()));
+    assert_verifies(&prover);
 
     let de_cipher = public_inputs.decrypt(rcv_sk).unwrap();
     assert_eq!(de_cipher[0], circuit.output_resources[0].get_logic());
@@ -408,7 +523,7 @@ fn test_halo2_receiver_resource_logic_circuit() {
     assert_eq!(de_cipher[2], circuit.output_resources[0].value);
     assert_eq!(
         de_cipher[3],
-        pallas::Base::from(circuit.output_resources[0].quantity)
+        pallas::Base::from_u128(circuit.output_resources[0].quantity)
     );
     assert_eq!(de_cipher[4], circuit.output_resources[0].nonce.inner());
     assert_eq!(de_cipher[5], circuit.output_resources[0].get_npk());
@@ -417,4 +532,22 @@ fn test_halo2_receiver_resource_logic_circuit() {
         pallas::Base::from(circuit.output_resources[0].is_ephemeral)
     );
     assert_eq!(de_cipher[7], circuit.output_resources[0].rseed);
+
+    // The receiver resource logic is not in the policy
+    let custodial_vk = circuit.receiver_policy.receiver_resource_logic_vks()[0];
+    circuit.receiver_policy = ReceiverPolicy::single(custodial_vk);
+    circuit.output_resources[0].value = derive_receiver_value(
+        &circuit.rcv_pk,
+        *COMPRESSED_TOKEN_AUTH_VK,
+        &circuit.receiver_policy,
+    );
+    circuit.owned_resource_id = circuit.output_resources[0].commitment().into();
+    let public_inputs = circuit.get_public_inputs(&mut rng);
+    let prover = MockProver::<pallas::Base>::run(
+        RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+        &circuit,
+        vec![public_inputs.to_vec()],
+    )
+    .unwrap();
+    assert!(prover.verify().is_err());
 }
@@ -2,114 +2,131 @@ use crate::{
     circuit::{
         blake2s::publicize_default_dynamic_resource_logic_commitments,
         gadgets::{
-            assign_free_advice, poseidon_hash::poseidon_hash_gadget,
+            assign_free_advice,
+            poseidon_hash::poseidon_hash_gadget,
+            schnorr::{SchnorrChip, Signature},
             target_resource_variable::get_owned_resource_variable,
         },
         resource_logic_bytecode::{ResourceLogicByteCode, ResourceLogicRepresentation},
         resource_logic_circuit::{
             BasicResourceLogicVariables, ResourceLogicCircuit, ResourceLogicConfig,
-            ResourceLogicPublicInputs, ResourceLogicVerifyingInfo, ResourceLogicVerifyingInfoTrait,
+            ResourceLogicPublicInputs, ResourceLogicVerifyingInfoTrait,
         },
     },
-    constant::{TaigaFixedBasesFull, NUM_RESOURCE, SETUP_PARAMS_MAP},
+    constant::{DEFAULT_PARAMS_STORE, NUM_RESOURCE},
     error::TransactionError,
-    proof::Proof,
-    resource::{RandomSeed, Resource},
+    resource::{OwnedResourceId, RandomSeed, Resource},
     resource_logic_commitment::ResourceLogicCommitment,
     resource_logic_vk::ResourceLogicVerifyingKey,
-    utils::{mod_r_p, poseidon_hash_n, read_base_field, read_point, read_scalar_field},
+    utils::{poseidon_hash_n, read_point, read_scalar_field},
 };
 use borsh::{BorshDeserialize, BorshSerialize};
-use halo2_gadgets::ecc::{chip::EccChip, FixedPoint, NonIdentityPoint, ScalarFixed, ScalarVar};
+use halo2_gadgets::ecc::{chip::EccChip, NonIdentityPoint};
 use halo2_proofs::{
-    arithmetic::Field,
-    circuit::{floor_planner, Layouter, Value},
-    plonk::{keygen_pk, keygen_vk, Circuit, ConstraintSystem, Error},
+    circuit::{floor_planner, AssignedCell, Layouter, Value},
+    plonk::{Circuit, ConstraintSystem, Error},
 };
 use lazy_static::lazy_static;
 use pasta_curves::{
-    arithmetic::CurveAffine,
     group::{ff::PrimeField, Curve, Group, GroupEncoding},
     pallas,
 };
-use rand::rngs::OsRng;
 use rand::RngCore;
 
 // The message contains the input resource nullifiers and output resource commitments
 const MESSAGE_LEN: usize = NUM_RESOURCE * 2;
-const POSEIDON_HASH_LEN: usize = MESSAGE_LEN + 4;
 lazy_static! {
     pub static ref TOKEN_AUTH_VK: ResourceLogicVerifyingKey =
-        SignatureVerificationResourceLogicCircuit::default().get_resource_logic_vk();
+        SignatureVerificationResourceLogicCircuit::default()
+            .get_resource_logic_vk()
+            .unwrap();
     pub static ref COMPRESSED_TOKEN_AUTH_VK: pallas::Base = TOKEN_AUTH_VK.get_compressed();
 }
 
+/// A signature of the ptx message with its public key, the message is signed by its hash.
 #[derive(Clone, Debug)]
 pub struct SchnorrSignature {
-    // public key
-    pk: pallas::Point,
-    // signature (r,s)
-    r: pallas::Point,
-    s: pallas::Scalar,
+    pub(crate) pk: pallas::Point,
+    pub(crate) signature: Signature,
 }
 
 impl Default for SchnorrSignature {
     fn default() -> Self {
         Self {
             pk: pallas::Point::generator(),
-            r: pallas::Point::generator(),
-            s: pallas::Scalar::one(),
+            signature: Signature::default(),
         }
     }
 }
 
 impl SchnorrSignature {
-    pub fn sign<R: RngCore>(mut rng: R, sk: pallas::Scalar, message: Vec<pallas::Base>) -> Self {
+    pub fn sign<R: RngCore>(rng: R, sk: pallas::Scalar, message: Vec<pallas::Base>) -> Self {
         // TDOD: figure out whether the generator is applicable.
-        let generator = pallas::Point::generator();
-        let pk = generator * sk;
-        let pk_coord = pk.to_affine().coordinates().unwrap();
-        // Generate a random number: z
-        let z = pallas::Scalar::random(&mut rng);
-        // Compute: R = z*G
-        let r = generator * z;
-        let r_coord = r.to_affine().coordinates().unwrap();
-        // Compute: s = z + Hash(r||P||m)*sk
-        assert_eq!(message.len(), MESSAGE_LEN);
-        let h = mod_r_p(poseidon_hash_n::<POSEIDON_HASH_LEN>([
-            *r_coord.x(),
-            *r_coord.y(),
-            *pk_coord.x(),
-            *pk_coord.y(),
-            message[0],
-            message[1],
-            message[2],
-            message[3],
-        ]));
-        let s = z + h * sk;
-        Self { pk, r, s }
+        let pk = pallas::Point::generator() * sk;
+        let message: [pallas::Base; MESSAGE_LEN] =
+            message.try_into().expect("the message is the ptx message");
+        let signature = Signature::sign(rng, sk, poseidon_hash_n(message));
+        Self { pk, signature }
     }
 }
 
+/// The message of a ptx signature: the input resource nullifiers and output resource commitments.
+pub(crate) fn ptx_message(
+    input_resources: &[Resource; NUM_RESOURCE],
+    output_resources: &[Resource; NUM_RESOURCE],
+) -> Vec<pallas::Base> {
+    input_resources
+        .iter()
+        .zip(output_resources.iter())
+        .flat_map(|(input_resource, output_resource)| {
+            [
+                input_resource.get_nf().unwrap().inner(),
+                output_resource.commitment().inner(),
+            ]
+        })
+        .collect()
+}
+
+/// The hash of the ptx message in the circuit, the message hash signed by `SchnorrSignature`.
+pub(crate) fn ptx_message_hash_gadget(
+    config: &ResourceLogicConfig,
+    layouter: impl Layouter<pallas::Base>,
+    basic_variables: &BasicResourceLogicVariables,
+) -> Result<AssignedCell<pallas::Base, pallas::Base>, Error> {
+    let nfs = basic_variables.get_input_resource_nfs();
+    let cms = basic_variables.get_output_resource_cms();
+    assert_eq!(NUM_RESOURCE, 2);
+    poseidon_hash_gadget(
+        config.poseidon_config.clone(),
+        layouter,
+        [
+            nfs[0].clone(),
+            cms[0].clone(),
+            nfs[1].clone(),
+            cms[1].clone(),
+        ],
+    )
+}
+
 // SignatureVerificationResourceLogicCircuit uses the schnorr signature.
 #[derive(Clone, Debug, Default)]
 pub struct SignatureVerificationResourceLogicCircuit {
-    pub owned_resource_id: pallas::Base,
+    pub owned_resource_id: OwnedResourceId,
     pub input_resources: [Resource; NUM_RESOURCE],
     pub output_resources: [Resource; NUM_RESOURCE],
     pub resource_logic_vk: pallas::Base,
     pub signature: SchnorrSignature,
-    pub receiver_resource_logic_vk: pallas::Base,
+    pub receiver_policy_commitment: pallas::Base,
 }
 
 impl SignatureVerificationResourceLogicCircuit {
     pub fn new(
-        owned_resource_id: pallas::Base,
+        owned_resource_id: OwnedResourceId,
         input_resources: [Resource; NUM_RESOURCE],
         output_resources: [Resource; NUM_RESOURCE],
         resource_logic_vk: pallas::Base,
         signature: SchnorrSignature,
-        receiver_resource_logic_vk: pallas::Base,
+        receiver_policy_commitment: pallas::Base,
     ) -> Self {
         Self {
             owned_resource_id,
@@ -117,18 +134,18 @@ impl SignatureVerificationResourceLogicCircuit {
             output_resources,
             resource_logic_vk,
             signature,
-            receiver_resource_logic_vk,
+            receiver_policy_commitment,
         }
     }
 
     pub fn from_sk_and_sign<R: RngCore>(
         mut rng: R,
-        owned_resource_id: pallas::Base,
+        owned_resource_id: OwnedResourceId,
         input_resources: [Resource; NUM_RESOURCE],
         output_resources: [Resource; NUM_RESOURCE],
         resource_logic_vk: pallas::Base,
         sk: pallas::Scalar,
-        receiver_resource_logic_vk: pallas::Base,
+        receiver_policy_commitment: pallas::Base,
     ) -> Self {
         assert_eq!(NUM_RESOURCE, 2);
         let mut message = vec![];
@@ -148,7 +165,7 @@ impl SignatureVerificationResourceLogicCircuit {
             output_resources,
             resource_logic_vk,
             signature,
-            receiver_resource_logic_vk,
+            receiver_policy_commitment,
         }
     }
 
@@ -158,14 +175,6 @@ impl SignatureVerificationResourceLogicCircuit {
             self.to_bytes(),
         )
     }
-
-    pub fn to_bytes(&self) -> Vec<u8> {
-        borsh::to_vec(&self).unwrap()
-    }
-
-    pub fn from_bytes(bytes: &Vec<u8>) -> Self {
-        BorshDeserialize::deserialize(&mut bytes.as_ref()).unwrap()
-    }
 }
 
 impl ResourceLogicCircuit for SignatureVerificationResourceLogicCircuit {
@@ -176,11 +185,8 @@ impl ResourceLogicCircuit for SignatureVerificationResourceLogicCircuit {
         mut layouter: impl Layouter<pallas::Base>,
         basic_variables: BasicResourceLogicVariables,
     ) -> Result<(), Error> {
-        // Construct an ECC chip
-        let ecc_chip = EccChip::construct(config.ecc_config);
-
         let pk = NonIdentityPoint::new(
-            ecc_chip.clone(),
+            EccChip::construct(config.ecc_config.clone()),
             layouter.namespace(|| "witness pk"),
             Value::known(self.signature.pk.to_affine()),
         )?;
@@ -199,10 +205,10 @@ impl ResourceLogicCircuit for SignatureVerificationResourceLogicCircuit {
             config.advices[0],
             Value::known(self.resource_logic_vk),
         )?;
-        let receiver_resource_logic_vk = assign_free_advice(
-            layouter.namespace(|| "witness receiver resource_logic vk"),
+        let receiver_policy_commitment = assign_free_advice(
+            layouter.namespace(|| "witness receiver policy commitment"),
             config.advices[0],
-            Value::known(self.receiver_resource_logic_vk),
+            Value::known(self.receiver_policy_commitment),
         )?;
 
         // Decode the value, and check the value encoding
@@ -213,7 +219,7 @@ impl ResourceLogicCircuit for SignatureVerificationResourceLogicCircuit {
                 pk.inner().x(),
                 pk.inner().y(),
                 auth_resource_logic_vk,
-                receiver_resource_logic_vk,
+                receiver_policy_commitment,
             ],
         )?;
 
@@ -222,54 +228,19 @@ impl ResourceLogicCircuit for SignatureVerificationResourceLogicCircuit {
             |mut region| region.constrain_equal(encoded_value.cell(), value.cell()),
         )?;
 
-        let r = NonIdentityPoint::new(
-            ecc_chip.clone(),
-            layouter.namespace(|| "witness r"),
-            Value::known(self.signature.r.to_affine()),
+        // Verify the signature of the ptx message
+        let message_hash = ptx_message_hash_gadget(
+            &config,
+            layouter.namespace(|| "ptx message hash"),
+            &basic_variables,
         )?;
-        let s_scalar = ScalarFixed::new(
-            ecc_chip.clone(),
-            layouter.namespace(|| "witness s"),
-            Value::known(self.signature.s),
+        SchnorrChip::from_resource_logic_config(&config).verify(
+            layouter.namespace(|| "verify signature"),
+            &pk,
+            &message_hash,
+            &self.signature.signature,
         )?;
 
-        // Verify: s*G = R + Hash(r||P||m)*P
-        // s*G
-        let generator =
-            FixedPoint::from_inner(ecc_chip.clone(), TaigaFixedBasesFull::BaseGenerator);
-        let (s_g, _) = generator.mul(layouter.namespace(|| "s_scalar * generator"), &s_scalar)?;
-
-        // Hash(r||P||m)
-        let h_scalar = {
-            let nfs = basic_variables.get_input_resource_nfs();
-            let cms = basic_variables.get_output_resource_cms();
-            assert_eq!(NUM_RESOURCE, 2);
-            let h = poseidon_hash_gadget(
-                config.poseidon_config,
-                layouter.namespace(|| "Poseidon_hash(r, P, m)"),
-                [
-                    r.inner().x(),
-                    r.inner().y(),
-                    pk.inner().x(),
-                    pk.inner().y(),
-                    nfs[0].clone(),
-                    cms[0].clone(),
-                    nfs[1].clone(),
-                    cms[1].clone(),
-                ],
-            )?;
-
-            ScalarVar::from_base(ecc_chip, layouter.namespace(|| "ScalarVar from_base"), &h)?
-        };
-
-        // Hash(r||P||m)*P
-        let (h_p, _) = pk.mul(layouter.namespace(|| "hP"), h_scalar)?;
-
-        // R + Hash(r||P||m)*P
-        let rhs = r.add(layouter.namespace(|| "R + Hash(r||P||m)*P"), &h_p)?;
-
-        s_g.constrain_equal(layouter.namespace(|| "s*G = R + Hash(r||P||m)*P"), &rhs)?;
-
         // Publicize the dynamic resource_logic commitments with default value
         publicize_default_dynamic_resource_logic_commitments(
             &mut layouter,
@@ -302,61 +273,28 @@ impl ResourceLogicCircuit for SignatureVerificationResourceLogicCircuit {
         public_inputs.into()
     }
 
-    fn get_owned_resource_id(&self) -> pallas::Base {
+    fn get_owned_resource_id(&self) -> OwnedResourceId {
         self.owned_resource_id
     }
 }
 
 resource_logic_circuit_impl!(SignatureVerificationResourceLogicCircuit);
 resource_logic_verifying_info_impl!(SignatureVerificationResourceLogicCircuit);
-
-impl BorshSerialize for SignatureVerificationResourceLogicCircuit {
-    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
-        writer.write_all(&self.owned_resource_id.to_repr())?;
-        for input in self.input_resources.iter() {
-            input.serialize(writer)?;
-        }
-
-        for output in self.output_resources.iter() {
-            output.serialize(writer)?;
-        }
-
-        writer.write_all(&self.resource_logic_vk.to_repr())?;
-        self.signature.serialize(writer)?;
-        writer.write_all(&self.receiver_resource_logic_vk.to_repr())?;
-
-        Ok(())
-    }
-}
-
-impl BorshDeserialize for SignatureVerificationResourceLogicCircuit {
-    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
-        let owned_resource_id = read_base_field(reader)?;
-        let input_resources: Vec<_> = (0..NUM_RESOURCE)
-            .map(|_| Resource::deserialize_reader(reader))
-            .collect::<Result<_, _>>()?;
-        let output_resources: Vec<_> = (0..NUM_RESOURCE)
-            .map(|_| Resource::deserialize_reader(reader))
-            .collect::<Result<_, _>>()?;
-        let resource_logic_vk = read_base_field(reader)?;
-        let signature = SchnorrSignature::deserialize_reader(reader)?;
-        let receiver_resource_logic_vk = read_base_field(reader)?;
-        Ok(Self {
-            owned_resource_id,
-            input_resources: input_resources.try_into().unwrap(),
-            output_resources: output_resources.try_into().unwrap(),
-            resource_logic_vk,
-            signature,
-            receiver_resource_logic_vk,
-        })
-    }
-}
+circuit_field_encoding_from_borsh!(SchnorrSignature);
+resource_logic_circuit_encoding_impl!(SignatureVerificationResourceLogicCircuit {
+    1 => owned_resource_id,
+    2 => input_resources,
+    3 => output_resources,
+    4 => resource_logic_vk,
+    5 => signature,
+    6 => receiver_policy_commitment,
+});
 
 impl BorshSerialize for SchnorrSignature {
     fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
         writer.write_all(&self.pk.to_bytes())?;
-        writer.write_all(&self.r.to_bytes())?;
-        writer.write_all(&self.s.to_repr())?;
+        writer.write_all(&self.signature.r.to_bytes())?;
+        writer.write_all(&self.signature.s.to_repr())?;
 
         Ok(())
     }
@@ -366,19 +304,27 @@ impl BorshDeserialize for SchnorrSignature {
     fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
         let pk = read_point(reader)?;
         let r = read_point(reader)?;
+        if bool::from(pk.is_identity() | r.is_identity()) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "identity point in the signature",
+            ));
+        }
         let s = read_scalar_field(reader)?;
-        Ok(Self { pk, r, s })
+        Ok(Self {
+            pk,
+            signature: Signature { r, s },
+        })
     }
 }
 
 #[test]
 fn test_halo2_sig_verification_resource_logic_circuit() {
-    use crate::circuit::resource_logic_examples::{
-        receiver_resource_logic::COMPRESSED_RECEIVER_VK, token::TokenAuthorization,
-    };
+    use crate::circuit::resource_logic_examples::token::TokenAuthorization;
+    use crate::circuit::verify_failure::assert_verifies;
     use crate::constant::RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE;
     use crate::resource::tests::random_resource;
-    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::{arithmetic::Field, dev::MockProver};
     use rand::rngs::OsRng;
 
     let mut rng = OsRng;
@@ -389,7 +335,7 @@ fn test_halo2_sig_verification_resource_logic_circuit() {
         let auth_vk = pallas::Base::random(&mut rng);
         let auth = TokenAuthorization::from_sk_vk(&sk, &auth_vk);
         input_resources[0].value = auth.to_value();
-        let owned_resource_id = input_resources[0].get_nf().unwrap().inner();
+        let owned_resource_id = input_resources[0].get_nf().unwrap().into();
         SignatureVerificationResourceLogicCircuit::from_sk_and_sign(
             &mut rng,
             owned_resource_id,
@@ -397,7 +343,7 @@ fn test_halo2_sig_verification_resource_logic_circuit() {
             output_resources,
             auth_vk,
             sk,
-            *COMPRESSED_RECEIVER_VK,
+            auth.receiver_policy.commitment(),
         )
     };
 
@@ -407,6 +353,14 @@ fn test_halo2_sig_verification_resource_logic_circuit() {
         SignatureVerificationResourceLogicCircuit::from_bytes(&circuit_bytes)
     };
 
+    // A signature with an identity key or R doesn't decode
+    let signature_bytes = borsh::to_vec(&circuit.signature).unwrap();
+    for offset in [0, 32] {
+        let mut bytes = signature_bytes.clone();
+        bytes[offset..offset + 32].copy_from_slice(&pallas::Point::identity().to_bytes());
+        assert!(SchnorrSignature::try_from_slice(&bytes).is_err());
+    }
+
     let public_inputs = circuit.get_public_inputs(&mut rng);
 
     let prover = MockProver::<pallas::Base>::run(
@@ -415,5 +369,5 @@ fn test_halo2_sig_verification_resource_logic_circuit() {
         vec![public_inputs.to_vec()],
     )
     .unwrap();
-    assert_eq!(prover.verify(), Ok(()));
+    assert_verifies(&prover);
 }
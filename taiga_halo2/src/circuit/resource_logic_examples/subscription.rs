@@ -0,0 +1,959 @@
+/// This example is to demonstrate a long-lived authorization: a subscription where the payer
+/// allows a merchant to pull a bounded amount per epoch. The authorization resource holds the
+/// prepaid funds of the subscription, its value commits to the terms and the next due epoch:
+///
+/// value = poseidon_hash(payer_pk || merchant_pk || payer_npk || merchant_npk || cap || due_epoch)
+///
+/// Consuming the authorization resource takes one of two paths:
+///
+/// Pull: the merchant signs the ptx, which pays `amount <= cap` to the merchant (the second
+/// output) and creates the next authorization resource (the first output) with the remaining
+/// funds and the due epoch `epoch + 1`, where `epoch >= due_epoch` is the epoch of the pull. So
+/// the merchant pulls at most once per epoch, the missed epochs can be pulled later. The epoch
+/// of the pull is published, the ledger rejects the transactions pulling ahead of its current
+/// epoch with `check_pulls_due`.
+///
+/// Cancel: the payer signs the ptx, which releases the remaining funds to the payer (the first
+/// output).
+///
+/// The kinds of the payout resources are not constrained here, balancing them against the
+/// authorization resource is up to the application.
+use crate::{
+    circuit::{
+        blake2s::publicize_default_dynamic_resource_logic_commitments,
+        gadgets::{
+            add::{AddChip, AddInstructions},
+            assign_free_advice, assign_free_constant,
+            boolean::AssignedBool,
+            mul::{MulChip, MulInstructions},
+            poseidon_hash::poseidon_hash_gadget,
+            schnorr::SchnorrChip,
+            sub::SubChip,
+            target_resource_variable::{get_is_input_resource_flag, get_owned_resource_variable},
+        },
+        resource_logic_bytecode::{ResourceLogicByteCode, ResourceLogicRepresentation},
+        resource_logic_circuit::{
+            BasicResourceLogicVariables, ResourceLogicCircuit, ResourceLogicConfig,
+            ResourceLogicPublicInputs, ResourceLogicVerifyingInfoTrait,
+        },
+        resource_logic_examples::signature_verification::{
+            ptx_message, ptx_message_hash_gadget, SchnorrSignature,
+        },
+    },
+    constant::{
+        DEFAULT_PARAMS_STORE, NUM_RESOURCE, RESOURCE_LOGIC_CIRCUIT_CUSTOM_PUBLIC_INPUT_BEGIN_IDX,
+    },
+    error::TransactionError,
+    nullifier::{Nullifier, NullifierKeyContainer},
+    resource::{OwnedResourceId, RandomSeed, Resource},
+    resource_logic_commitment::ResourceLogicCommitment,
+    resource_logic_vk::ResourceLogicVerifyingKey,
+    transaction::Transaction,
+    utils::{poseidon_hash_n, read_base_field, read_point},
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+use halo2_gadgets::ecc::{chip::EccChip, NonIdentityPoint};
+use halo2_proofs::{
+    arithmetic::Field,
+    circuit::{floor_planner, Layouter, Value},
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use lazy_static::lazy_static;
+use pasta_curves::{
+    arithmetic::CurveAffine,
+    group::{ff::PrimeField, Curve, Group, GroupEncoding},
+    pallas,
+};
+use rand::RngCore;
+
+/// The index of the published epoch of the pull, zero if the ptx doesn't pull.
+pub const SUBSCRIPTION_PULL_EPOCH_PUBLIC_INPUT_IDX: usize =
+    RESOURCE_LOGIC_CIRCUIT_CUSTOM_PUBLIC_INPUT_BEGIN_IDX;
+
+lazy_static! {
+    pub static ref SUBSCRIPTION_VK: ResourceLogicVerifyingKey =
+        SubscriptionResourceLogicCircuit::default()
+            .get_resource_logic_vk()
+            .unwrap();
+    pub static ref COMPRESSED_SUBSCRIPTION_VK: pallas::Base = SUBSCRIPTION_VK.get_compressed();
+}
+
+/// The terms of a subscription, fixed when the payer funds it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Subscription {
+    // The signing keys of the parties
+    pub payer_pk: pallas::Point,
+    pub merchant_pk: pallas::Point,
+    // The payout addresses of the parties
+    pub payer_npk: pallas::Base,
+    pub merchant_npk: pallas::Base,
+    // The maximum amount of a pull
+    pub cap: u128,
+}
+
+/// A pull of `amount` by the merchant in `epoch`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct Pull {
+    pub epoch: u64,
+    pub amount: u128,
+}
+
+impl Default for Subscription {
+    fn default() -> Self {
+        Self {
+            payer_pk: pallas::Point::generator(),
+            merchant_pk: pallas::Point::generator(),
+            payer_npk: pallas::Base::zero(),
+            merchant_npk: pallas::Base::zero(),
+            cap: 0,
+        }
+    }
+}
+
+impl Subscription {
+    /// Encodes the terms and the due epoch into the value of the authorization resource.
+    pub fn encode_value(&self, due_epoch: u64) -> pallas::Base {
+        let payer_pk = self.payer_pk.to_affine().coordinates().unwrap();
+        let merchant_pk = self.merchant_pk.to_affine().coordinates().unwrap();
+        poseidon_hash_n([
+            *payer_pk.x(),
+            *payer_pk.y(),
+            *merchant_pk.x(),
+            *merchant_pk.y(),
+            self.payer_npk,
+            self.merchant_npk,
+            pallas::Base::from_u128(self.cap),
+            pallas::Base::from(due_epoch),
+        ])
+    }
+
+    /// Creates the authorization resource to consume. `nk` is shared by the parties so that the
+    /// merchant can pull and the payer can cancel.
+    pub fn create_input_resource<R: RngCore>(
+        &self,
+        mut rng: R,
+        label: pallas::Base,
+        nk: pallas::Base,
+        due_epoch: u64,
+        balance: u128,
+    ) -> Resource {
+        let rseed = pallas::Base::random(&mut rng);
+        let nonce = Nullifier::random(&mut rng);
+        Resource::new_input_resource(
+            *COMPRESSED_SUBSCRIPTION_VK,
+            label,
+            self.encode_value(due_epoch),
+            balance,
+            nk,
+            nonce,
+            false,
+            rseed,
+        )
+    }
+
+    /// Creates the authorization resource holding `balance`, funding the subscription or after a
+    /// pull.
+    pub fn create_output_resource<R: RngCore>(
+        &self,
+        mut rng: R,
+        label: pallas::Base,
+        nk: pallas::Base,
+        due_epoch: u64,
+        balance: u128,
+    ) -> Resource {
+        let rseed = pallas::Base::random(&mut rng);
+        Resource::new_output_resource(
+            *COMPRESSED_SUBSCRIPTION_VK,
+            label,
+            self.encode_value(due_epoch),
+            balance,
+            NullifierKeyContainer::from_key(nk).get_npk(),
+            false,
+            rseed,
+        )
+    }
+}
+
+/// Whether the pull published in `public_inputs` is due at the verifier epoch `current_epoch`,
+/// i.e. the merchant doesn't pull ahead of time. The public inputs of a ptx that doesn't pull
+/// are always accepted.
+pub fn is_pull_due(public_inputs: &ResourceLogicPublicInputs, current_epoch: u64) -> bool {
+    let pull_epoch = public_inputs
+        .get_from_index(SUBSCRIPTION_PULL_EPOCH_PUBLIC_INPUT_IDX)
+        .to_repr();
+    // The epoch is range checked to u128 only, an epoch beyond u64 is never due
+    pull_epoch[8..].iter().all(|b| *b == 0)
+        && u64::from_le_bytes(pull_epoch[..8].try_into().unwrap()) <= current_epoch
+}
+
+/// Checks that no shielded ptx of the transaction pulls from a subscription ahead of the ledger
+/// epoch `current_epoch`. The early pull is reported with the index of its ptx.
+pub fn check_pulls_due(tx: &Transaction, current_epoch: u64) -> Result<(), TransactionError> {
+    for (ptx_index, ptx) in tx
+        .get_shielded_ptx_bundle()
+        .partial_txs()
+        .iter()
+        .enumerate()
+    {
+        if ptx
+            .get_input_resource_logic_public_inputs(*COMPRESSED_SUBSCRIPTION_VK)
+            .into_iter()
+            .any(|public_inputs| !is_pull_due(public_inputs, current_epoch))
+        {
+            return Err(TransactionError::PullNotDue(ptx_index));
+        }
+    }
+    Ok(())
+}
+
+// SubscriptionResourceLogicCircuit
+#[derive(Clone, Debug, Default)]
+pub struct SubscriptionResourceLogicCircuit {
+    pub owned_resource_id: OwnedResourceId,
+    pub input_resources: [Resource; NUM_RESOURCE],
+    pub output_resources: [Resource; NUM_RESOURCE],
+    pub subscription: Subscription,
+    // The due epoch of the owned authorization resource
+    pub due_epoch: u64,
+    // The pull of the merchant, ignored when cancelling
+    pub pull: Pull,
+    pub is_cancel: bool,
+    // The signature of the ptx by the merchant when pulling, by the payer when cancelling
+    pub signature: SchnorrSignature,
+}
+
+impl SubscriptionResourceLogicCircuit {
+    /// The circuit pulling `pull.amount` in `pull.epoch`, signed by the merchant.
+    #[allow(clippy::too_many_arguments)]
+    pub fn pull<R: RngCore>(
+        mut rng: R,
+        owned_resource_id: OwnedResourceId,
+        input_resources: [Resource; NUM_RESOURCE],
+        output_resources: [Resource; NUM_RESOURCE],
+        subscription: Subscription,
+        due_epoch: u64,
+        pull: Pull,
+        merchant_sk: pallas::Scalar,
+    ) -> Self {
+        let message = ptx_message(&input_resources, &output_resources);
+        let signature = SchnorrSignature::sign(&mut rng, merchant_sk, message);
+        Self {
+            owned_resource_id,
+            input_resources,
+            output_resources,
+            subscription,
+            due_epoch,
+            pull,
+            is_cancel: false,
+            signature,
+        }
+    }
+
+    /// The circuit cancelling the subscription, signed by the payer.
+    pub fn cancel<R: RngCore>(
+        mut rng: R,
+        owned_resource_id: OwnedResourceId,
+        input_resources: [Resource; NUM_RESOURCE],
+        output_resources: [Resource; NUM_RESOURCE],
+        subscription: Subscription,
+        due_epoch: u64,
+        payer_sk: pallas::Scalar,
+    ) -> Self {
+        let message = ptx_message(&input_resources, &output_resources);
+        let signature = SchnorrSignature::sign(&mut rng, payer_sk, message);
+        Self {
+            owned_resource_id,
+            input_resources,
+            output_resources,
+            subscription,
+            due_epoch,
+            pull: Pull::default(),
+            is_cancel: true,
+            signature,
+        }
+    }
+
+    pub fn to_bytecode(&self) -> ResourceLogicByteCode {
+        ResourceLogicByteCode::new(ResourceLogicRepresentation::Subscription, self.to_bytes())
+    }
+
+    // The owned resource if it's an input
+    fn owned_input_resource(&self) -> Option<&Resource> {
+        self.input_resources.iter().find(|resource| {
+            resource.get_nf().map(OwnedResourceId::Nf) == Some(self.owned_resource_id)
+        })
+    }
+
+    // The epoch of the pull if the owned resource is an input pulled from, otherwise zero.
+    fn pull_epoch(&self) -> pallas::Base {
+        if self.owned_input_resource().is_some() && !self.is_cancel {
+            pallas::Base::from(self.pull.epoch)
+        } else {
+            pallas::Base::zero()
+        }
+    }
+}
+
+impl ResourceLogicCircuit for SubscriptionResourceLogicCircuit {
+    // Add custom constraints
+    fn custom_constraints(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<pallas::Base>,
+        basic_variables: BasicResourceLogicVariables,
+    ) -> Result<(), Error> {
+        let ecc_chip = EccChip::construct(config.ecc_config.clone());
+        let add_chip = AddChip::construct(config.add_config.clone(), ());
+        let sub_chip = SubChip::construct(config.sub_config.clone(), ());
+        let mul_chip = MulChip::construct(config.mul_config.clone());
+
+        let owned_resource_id = basic_variables.get_owned_resource_id();
+        let is_input_resource = get_is_input_resource_flag(
+            config.get_is_input_resource_flag_config,
+            layouter.namespace(|| "get is_input_resource_flag"),
+            &owned_resource_id,
+            &basic_variables.get_input_resource_nfs(),
+            &basic_variables.get_output_resource_cms(),
+        )?;
+        let is_cancel = AssignedBool::witness(
+            &mul_chip,
+            layouter.namespace(|| "witness is_cancel"),
+            config.advices[0],
+            Value::known(self.is_cancel),
+        )?;
+        let is_active = is_cancel.not(
+            &sub_chip,
+            layouter.namespace(|| "is_active = 1 - is_cancel"),
+            config.advices[0],
+        )?;
+        let is_pull = is_input_resource.and(
+            &mul_chip,
+            layouter.namespace(|| "is_pull = is_input_resource * is_active"),
+            &is_active,
+        )?;
+        let is_cancelling = is_input_resource.and(
+            &mul_chip,
+            layouter.namespace(|| "is_cancelling = is_input_resource * is_cancel"),
+            &is_cancel,
+        )?;
+
+        // Witness the terms
+        let payer_pk = NonIdentityPoint::new(
+            ecc_chip.clone(),
+            layouter.namespace(|| "witness payer_pk"),
+            Value::known(self.subscription.payer_pk.to_affine()),
+        )?;
+        let merchant_pk = NonIdentityPoint::new(
+            ecc_chip,
+            layouter.namespace(|| "witness merchant_pk"),
+            Value::known(self.subscription.merchant_pk.to_affine()),
+        )?;
+        let payer_npk = assign_free_advice(
+            layouter.namespace(|| "witness payer_npk"),
+            config.advices[0],
+            Value::known(self.subscription.payer_npk),
+        )?;
+        let merchant_npk = assign_free_advice(
+            layouter.namespace(|| "witness merchant_npk"),
+            config.advices[0],
+            Value::known(self.subscription.merchant_npk),
+        )?;
+        let cap = assign_free_advice(
+            layouter.namespace(|| "witness cap"),
+            config.advices[0],
+            Value::known(pallas::Base::from_u128(self.subscription.cap)),
+        )?;
+        let due_epoch = assign_free_advice(
+            layouter.namespace(|| "witness due_epoch"),
+            config.advices[0],
+            Value::known(pallas::Base::from(self.due_epoch)),
+        )?;
+
+        // Check the value encoding of the owned resource
+        let encoded_value = poseidon_hash_gadget(
+            config.poseidon_config.clone(),
+            layouter.namespace(|| "value encoding"),
+            [
+                payer_pk.inner().x(),
+                payer_pk.inner().y(),
+                merchant_pk.inner().x(),
+                merchant_pk.inner().y(),
+                payer_npk.clone(),
+                merchant_npk.clone(),
+                cap.clone(),
+                due_epoch.clone(),
+            ],
+        )?;
+        let value = get_owned_resource_variable(
+            config.get_owned_resource_variable_config,
+            layouter.namespace(|| "get owned resource value"),
+            &owned_resource_id,
+            &basic_variables.get_value_searchable_pairs(),
+        )?;
+        layouter.assign_region(
+            || "check value encoding",
+            |mut region| region.constrain_equal(encoded_value.cell(), value.cell()),
+        )?;
+
+        // The amount is bounded by the cap: amount + slack = cap, both range checked
+        let amount = assign_free_advice(
+            layouter.namespace(|| "witness amount"),
+            config.advices[0],
+            Value::known(pallas::Base::from_u128(self.pull.amount)),
+        )?;
+        let cap_slack = assign_free_advice(
+            layouter.namespace(|| "witness cap slack"),
+            config.advices[0],
+            Value::known(pallas::Base::from_u128(
+                self.subscription.cap.wrapping_sub(self.pull.amount),
+            )),
+        )?;
+        for (name, cell) in [("amount", &amount), ("cap slack", &cap_slack)] {
+            config
+                .u128_config
+                .decompose(layouter.namespace(|| format!("decompose {name}")), cell)?;
+        }
+        let bounded_cap = AddInstructions::add(
+            &add_chip,
+            layouter.namespace(|| "amount + cap slack"),
+            &amount,
+            &cap_slack,
+        )?;
+        layouter.assign_region(
+            || "check amount <= cap",
+            |mut region| region.constrain_equal(bounded_cap.cell(), cap.cell()),
+        )?;
+
+        // The pulled funds come out of the authorization resource
+        let quantity = get_owned_resource_variable(
+            config.get_owned_resource_variable_config,
+            layouter.namespace(|| "get owned resource quantity"),
+            &owned_resource_id,
+            &basic_variables.get_quantity_searchable_pairs(),
+        )?;
+        let balance = self
+            .owned_input_resource()
+            .map_or(0, |resource| resource.quantity);
+        let remaining = assign_free_advice(
+            layouter.namespace(|| "witness remaining"),
+            config.advices[0],
+            Value::known(pallas::Base::from_u128(
+                balance.wrapping_sub(self.pull.amount),
+            )),
+        )?;
+        config
+            .u128_config
+            .decompose(layouter.namespace(|| "decompose remaining"), &remaining)?;
+        let total = AddInstructions::add(
+            &add_chip,
+            layouter.namespace(|| "remaining + amount"),
+            &remaining,
+            &amount,
+        )?;
+        layouter.assign_region(
+            || "conditional equal: check the remaining funds",
+            |mut region| {
+                config.conditional_equal_config.assign_region(
+                    &is_pull,
+                    &total,
+                    &quantity,
+                    0,
+                    &mut region,
+                )
+            },
+        )?;
+
+        // The epoch of the pull is at least the due epoch: epoch = due_epoch + gap
+        let epoch_gap = assign_free_advice(
+            layouter.namespace(|| "witness epoch gap"),
+            config.advices[0],
+            Value::known(pallas::Base::from(
+                self.pull.epoch.saturating_sub(self.due_epoch),
+            )),
+        )?;
+        config
+            .u128_config
+            .decompose(layouter.namespace(|| "decompose epoch gap"), &epoch_gap)?;
+        let pull_epoch = AddInstructions::add(
+            &add_chip,
+            layouter.namespace(|| "pull epoch = due_epoch + gap"),
+            &due_epoch,
+            &epoch_gap,
+        )?;
+        let one = assign_free_constant(
+            layouter.namespace(|| "one"),
+            config.advices[0],
+            pallas::Base::one(),
+        )?;
+        let next_due_epoch = AddInstructions::add(
+            &add_chip,
+            layouter.namespace(|| "next due epoch = pull epoch + 1"),
+            &pull_epoch,
+            &one,
+        )?;
+
+        // Pull: the merchant signs the ptx paying the merchant and creating the next
+        // authorization resource
+        let message_hash = ptx_message_hash_gadget(
+            &config,
+            layouter.namespace(|| "ptx message hash"),
+            &basic_variables,
+        )?;
+        let schnorr_chip = SchnorrChip::from_resource_logic_config(&config);
+        schnorr_chip.conditional_verify(
+            layouter.namespace(|| "verify the signature of the merchant"),
+            &config.conditional_equal_config,
+            &is_pull,
+            &merchant_pk,
+            &message_hash,
+            &self.signature.signature,
+        )?;
+
+        let next_encoded_value = poseidon_hash_gadget(
+            config.poseidon_config.clone(),
+            layouter.namespace(|| "next value encoding"),
+            [
+                payer_pk.inner().x(),
+                payer_pk.inner().y(),
+                merchant_pk.inner().x(),
+                merchant_pk.inner().y(),
+                payer_npk.clone(),
+                merchant_npk.clone(),
+                cap,
+                next_due_epoch,
+            ],
+        )?;
+        let label = get_owned_resource_variable(
+            config.get_owned_resource_variable_config,
+            layouter.namespace(|| "get owned resource label"),
+            &owned_resource_id,
+            &basic_variables.get_label_searchable_pairs(),
+        )?;
+        let logic = get_owned_resource_variable(
+            config.get_owned_resource_variable_config,
+            layouter.namespace(|| "get owned resource logic"),
+            &owned_resource_id,
+            &basic_variables.get_logic_searchable_pairs(),
+        )?;
+        let next_authorization = &basic_variables.output_resource_variables[0].resource_variables;
+        let payment = &basic_variables.output_resource_variables[1].resource_variables;
+        for (name, expected, actual) in [
+            (
+                "next authorization value",
+                &next_encoded_value,
+                &next_authorization.value,
+            ),
+            (
+                "next authorization label",
+                &label,
+                &next_authorization.label,
+            ),
+            (
+                "next authorization logic",
+                &logic,
+                &next_authorization.logic,
+            ),
+            (
+                "next authorization quantity",
+                &remaining,
+                &next_authorization.quantity,
+            ),
+            ("payment npk", &merchant_npk, &payment.npk),
+            ("payment quantity", &amount, &payment.quantity),
+        ] {
+            layouter.assign_region(
+                || format!("conditional equal: check the {name}"),
+                |mut region| {
+                    config.conditional_equal_config.assign_region(
+                        &is_pull,
+                        expected,
+                        actual,
+                        0,
+                        &mut region,
+                    )
+                },
+            )?;
+        }
+
+        // Cancel: the payer signs the ptx releasing the funds to the payer
+        schnorr_chip.conditional_verify(
+            layouter.namespace(|| "verify the signature of the payer"),
+            &config.conditional_equal_config,
+            &is_cancelling,
+            &payer_pk,
+            &message_hash,
+            &self.signature.signature,
+        )?;
+        let refund = &basic_variables.output_resource_variables[0].resource_variables;
+        for (name, expected, actual) in [
+            ("refund npk", &payer_npk, &refund.npk),
+            ("refund quantity", &quantity, &refund.quantity),
+        ] {
+            layouter.assign_region(
+                || format!("conditional equal: check the {name}"),
+                |mut region| {
+                    config.conditional_equal_config.assign_region(
+                        &is_cancelling,
+                        expected,
+                        actual,
+                        0,
+                        &mut region,
+                    )
+                },
+            )?;
+        }
+
+        // Publicize the epoch of the pull
+        let published_epoch = MulInstructions::mul(
+            &mul_chip,
+            layouter.namespace(|| "published epoch = is_pull * pull epoch"),
+            &is_pull,
+            &pull_epoch,
+        )?;
+        layouter.constrain_instance(
+            published_epoch.cell(),
+            config.instances,
+            SUBSCRIPTION_PULL_EPOCH_PUBLIC_INPUT_IDX,
+        )?;
+
+        // Publicize the dynamic resource_logic commitments with default value
+        publicize_default_dynamic_resource_logic_commitments(
+            &mut layouter,
+            config.advices[0],
+            config.instances,
+        )?;
+
+        Ok(())
+    }
+
+    fn get_input_resources(&self) -> &[Resource; NUM_RESOURCE] {
+        &self.input_resources
+    }
+
+    fn get_output_resources(&self) -> &[Resource; NUM_RESOURCE] {
+        &self.output_resources
+    }
+
+    fn get_public_inputs(&self, mut rng: impl RngCore) -> ResourceLogicPublicInputs {
+        let mut public_inputs = self.get_mandatory_public_inputs();
+        let default_resource_logic_cm: [pallas::Base; 2] =
+            ResourceLogicCommitment::default().to_public_inputs();
+        public_inputs.extend(default_resource_logic_cm);
+        public_inputs.extend(default_resource_logic_cm);
+        public_inputs.push(self.pull_epoch());
+        let padding = ResourceLogicPublicInputs::get_public_input_padding(
+            public_inputs.len(),
+            &RandomSeed::random(&mut rng),
+        );
+        public_inputs.extend(padding);
+        public_inputs.into()
+    }
+
+    fn get_owned_resource_id(&self) -> OwnedResourceId {
+        self.owned_resource_id
+    }
+}
+
+resource_logic_circuit_impl!(SubscriptionResourceLogicCircuit);
+resource_logic_verifying_info_impl!(SubscriptionResourceLogicCircuit);
+circuit_field_encoding_from_borsh!(Subscription, Pull);
+resource_logic_circuit_encoding_impl!(SubscriptionResourceLogicCircuit {
+    1 => owned_resource_id,
+    2 => input_resources,
+    3 => output_resources,
+    4 => subscription,
+    5 => due_epoch,
+    6 => pull,
+    7 => is_cancel,
+    8 => signature,
+});
+
+impl BorshSerialize for Subscription {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(&self.payer_pk.to_bytes())?;
+        writer.write_all(&self.merchant_pk.to_bytes())?;
+        writer.write_all(&self.payer_npk.to_repr())?;
+        writer.write_all(&self.merchant_npk.to_repr())?;
+        self.cap.serialize(writer)?;
+        Ok(())
+    }
+}
+
+impl BorshDeserialize for Subscription {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let payer_pk = read_point(reader)?;
+        let merchant_pk = read_point(reader)?;
+        let payer_npk = read_base_field(reader)?;
+        let merchant_npk = read_base_field(reader)?;
+        let cap = u128::deserialize_reader(reader)?;
+        Ok(Self {
+            payer_pk,
+            merchant_pk,
+            payer_npk,
+            merchant_npk,
+            cap,
+        })
+    }
+}
+
+#[test]
+fn test_halo2_subscription_resource_logic_circuit() {
+    use crate::circuit::verify_failure::assert_verifies;
+    use crate::constant::RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE;
+    use crate::resource::tests::random_resource;
+    use halo2_proofs::dev::MockProver;
+    use rand::rngs::OsRng;
+
+    let mut rng = OsRng;
+    let payer_sk = pallas::Scalar::random(&mut rng);
+    let merchant_sk = pallas::Scalar::random(&mut rng);
+    let subscription = Subscription {
+        payer_pk: pallas::Point::generator() * payer_sk,
+        merchant_pk: pallas::Point::generator() * merchant_sk,
+        payer_npk: pallas::Base::random(&mut rng),
+        merchant_npk: pallas::Base::random(&mut rng),
+        cap: 10,
+    };
+    let label = pallas::Base::random(&mut rng);
+    let nk = pallas::Base::random(&mut rng);
+    let due_epoch = 5;
+    let balance = 25;
+    let authorization = subscription.create_input_resource(&mut rng, label, nk, due_epoch, balance);
+    let owned_resource_id = OwnedResourceId::Nf(authorization.get_nf().unwrap());
+    let input_resources = [authorization, random_resource(&mut rng)];
+
+    let run = |circuit: &SubscriptionResourceLogicCircuit| {
+        let public_inputs = circuit.get_public_inputs(OsRng);
+        MockProver::<pallas::Base>::run(
+            RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+            circuit,
+            vec![public_inputs.to_vec()],
+        )
+        .unwrap()
+    };
+    let payout = |npk: pallas::Base, quantity: u128, rng: &mut OsRng| {
+        Resource::new_output_resource(
+            pallas::Base::random(&mut *rng),
+            label,
+            pallas::Base::zero(),
+            quantity,
+            npk,
+            false,
+            pallas::Base::random(&mut *rng),
+        )
+    };
+    let pull_circuit = |pull: Pull, next_due_epoch: u64, remaining: u128, rng: &mut OsRng| {
+        let output_resources = [
+            subscription.create_output_resource(&mut *rng, label, nk, next_due_epoch, remaining),
+            payout(subscription.merchant_npk, pull.amount, &mut *rng),
+        ];
+        SubscriptionResourceLogicCircuit::pull(
+            rng,
+            owned_resource_id,
+            input_resources,
+            output_resources,
+            subscription,
+            due_epoch,
+            pull,
+            merchant_sk,
+        )
+    };
+
+    // Pull: the merchant pulls the cap two epochs after the due epoch
+    let pull = Pull {
+        epoch: 7,
+        amount: 10,
+    };
+    let circuit = pull_circuit(pull, 8, 15, &mut rng);
+
+    // Test serialization
+    let circuit = {
+        let circuit_bytes = circuit.to_bytes();
+        SubscriptionResourceLogicCircuit::from_bytes(&circuit_bytes)
+    };
+    assert_verifies(&run(&circuit));
+    let public_inputs = circuit.get_public_inputs(&mut rng);
+    assert!(!is_pull_due(&public_inputs, 6));
+    assert!(is_pull_due(&public_inputs, 7));
+
+    // The pull fails without the signature of the merchant
+    {
+        let mut circuit = circuit.clone();
+        circuit.signature = SchnorrSignature::sign(
+            &mut rng,
+            payer_sk,
+            ptx_message(&circuit.input_resources, &circuit.output_resources),
+        );
+        assert!(run(&circuit).verify().is_err());
+    }
+
+    // The pull fails over the cap
+    {
+        let pull = Pull {
+            epoch: 7,
+            amount: 11,
+        };
+        assert!(run(&pull_circuit(pull, 8, 14, &mut rng)).verify().is_err());
+    }
+
+    // The pull fails before the due epoch, the merchant pulls at most once per epoch
+    {
+        let pull = Pull {
+            epoch: 4,
+            amount: 10,
+        };
+        assert!(run(&pull_circuit(pull, 5, 15, &mut rng)).verify().is_err());
+    }
+
+    // The pull fails if the next authorization doesn't advance the due epoch
+    {
+        assert!(run(&pull_circuit(pull, 7, 15, &mut rng)).verify().is_err());
+    }
+
+    // The pull fails if the next authorization keeps the pulled funds
+    {
+        assert!(run(&pull_circuit(pull, 8, 25, &mut rng)).verify().is_err());
+    }
+
+    // Cancel: the payer releases the funds
+    let cancel_circuit = SubscriptionResourceLogicCircuit::cancel(
+        &mut rng,
+        owned_resource_id,
+        input_resources,
+        [
+            payout(subscription.payer_npk, balance, &mut rng),
+            random_resource(&mut rng),
+        ],
+        subscription,
+        due_epoch,
+        payer_sk,
+    );
+    assert!(is_pull_due(&cancel_circuit.get_public_inputs(&mut rng), 0));
+    assert_verifies(&run(&cancel_circuit));
+
+    // The merchant can't cancel
+    {
+        let circuit = SubscriptionResourceLogicCircuit::cancel(
+            &mut rng,
+            owned_resource_id,
+            input_resources,
+            cancel_circuit.output_resources,
+            subscription,
+            due_epoch,
+            merchant_sk,
+        );
+        assert!(run(&circuit).verify().is_err());
+    }
+
+    // Funding: creating the authorization resource needs no signature
+    let funding_circuit = SubscriptionResourceLogicCircuit {
+        owned_resource_id: circuit.output_resources[0].commitment().into(),
+        input_resources: [(); NUM_RESOURCE].map(|_| random_resource(&mut rng)),
+        output_resources: circuit.output_resources,
+        subscription,
+        due_epoch: 8,
+        ..Default::default()
+    };
+    assert_verifies(&run(&funding_circuit));
+}
+
+#[test]
+fn test_check_pulls_due() {
+    use crate::circuit::resource_logic_examples::{
+        TrivialResourceLogicCircuit, COMPRESSED_TRIVIAL_RESOURCE_LOGIC_VK,
+    };
+    use crate::merkle_tree::MerklePath;
+    use crate::resource::ResourceLogics;
+    use crate::shielded_ptx::ShieldedPartialTransactionBuilder;
+    use crate::transaction::{ShieldedPartialTxBundle, TransparentPartialTxBundle};
+    use rand::rngs::OsRng;
+
+    let mut rng = OsRng;
+    let merchant_sk = pallas::Scalar::random(&mut rng);
+    let subscription = Subscription {
+        payer_pk: pallas::Point::random(&mut rng),
+        merchant_pk: pallas::Point::generator() * merchant_sk,
+        payer_npk: pallas::Base::random(&mut rng),
+        merchant_npk: pallas::Base::random(&mut rng),
+        cap: 10,
+    };
+    let label = pallas::Base::random(&mut rng);
+    let nk = pallas::Base::random(&mut rng);
+    let (due_epoch, balance) = (5, 25);
+    let pull = Pull {
+        epoch: 7,
+        amount: 10,
+    };
+    let payment = Resource::new_output_resource(
+        *COMPRESSED_TRIVIAL_RESOURCE_LOGIC_VK,
+        label,
+        pallas::Base::zero(),
+        pull.amount,
+        subscription.merchant_npk,
+        false,
+        pallas::Base::random(&mut rng),
+    );
+    let ptx = ShieldedPartialTransactionBuilder::new()
+        .add_input(
+            subscription.create_input_resource(&mut rng, label, nk, due_epoch, balance),
+            MerklePath::random(&mut rng, crate::constant::TAIGA_COMMITMENT_TREE_DEPTH),
+            move |rng, resource, input_resources, output_resources| {
+                let resource_logic = SubscriptionResourceLogicCircuit::pull(
+                    rng,
+                    resource.get_nf().unwrap().into(),
+                    input_resources,
+                    output_resources,
+                    subscription,
+                    due_epoch,
+                    pull,
+                    merchant_sk,
+                );
+                ResourceLogics::from_application(Box::new(resource_logic))
+            },
+        )
+        .add_output(
+            subscription.create_output_resource(
+                &mut rng,
+                label,
+                nk,
+                pull.epoch + 1,
+                balance - pull.amount,
+            ),
+            move |_, resource, input_resources, output_resources| {
+                let resource_logic = SubscriptionResourceLogicCircuit {
+                    owned_resource_id: resource.commitment().into(),
+                    input_resources,
+                    output_resources,
+                    subscription,
+                    due_epoch: pull.epoch + 1,
+                    ..Default::default()
+                };
+                ResourceLogics::from_application(Box::new(resource_logic))
+            },
+        )
+        .add_output(payment, |_, resource, input_resources, output_resources| {
+            let resource_logic = TrivialResourceLogicCircuit::new(
+                resource.commitment().into(),
+                input_resources,
+                output_resources,
+            );
+            ResourceLogics::from_application(Box::new(resource_logic))
+        })
+        .build(&mut rng)
+        .unwrap();
+    ptx.verify_proof().unwrap();
+    let tx = Transaction::build(
+        &mut rng,
+        ShieldedPartialTxBundle::new(vec![ptx]),
+        TransparentPartialTxBundle::default(),
+    )
+    .unwrap();
+
+    // The ledger accepts the pull from its epoch on, and rejects it before
+    check_pulls_due(&tx, pull.epoch).unwrap();
+    check_pulls_due(&tx, pull.epoch + 1).unwrap();
+    assert!(matches!(
+        check_pulls_due(&tx, pull.epoch - 1),
+        Err(TransactionError::PullNotDue(0))
+    ));
+}
@@ -2,16 +2,26 @@ use crate::{
     circuit::{
         blake2s::{resource_logic_commitment_gadget, Blake2sChip},
         gadgets::{
+            add::AddChip,
             assign_free_advice, assign_free_constant,
+            hash_accumulator::HashAccumulatorChip,
+            kind_quantity::output_kind_quantity_gadget,
+            mul::MulChip,
             poseidon_hash::poseidon_hash_gadget,
+            range_check::RangeCheckChip,
+            sub::SubChip,
             target_resource_variable::{get_is_input_resource_flag, get_owned_resource_variable},
+            vk_policy::{vk_policy_commitment, vk_policy_gadget},
+        },
+        resource_logic_bytecode::{
+            ApplicationByteCode, ResourceLogicByteCode, ResourceLogicRepresentation,
         },
-        resource_logic_bytecode::{ResourceLogicByteCode, ResourceLogicRepresentation},
         resource_logic_circuit::{
             BasicResourceLogicVariables, ResourceLogicCircuit, ResourceLogicConfig,
-            ResourceLogicPublicInputs, ResourceLogicVerifyingInfo, ResourceLogicVerifyingInfoTrait,
+            ResourceLogicPrecheckError, ResourceLogicPublicInputs, ResourceLogicVerifyingInfoTrait,
         },
         resource_logic_examples::receiver_resource_logic::{
+            derive_receiver_value, receiver_policy_gadget, ReceiverPolicy,
             ReceiverResourceLogicCircuit, COMPRESSED_RECEIVER_VK,
         },
         resource_logic_examples::signature_verification::{
@@ -19,19 +29,19 @@ use crate::{
         },
     },
     constant::{
-        NUM_RESOURCE, PRF_EXPAND_DYNAMIC_RESOURCE_LOGIC_1_CM_R,
+        DEFAULT_PARAMS_STORE, NUM_RESOURCE, PRF_EXPAND_DYNAMIC_RESOURCE_LOGIC_1_CM_R,
         RESOURCE_LOGIC_CIRCUIT_FIRST_DYNAMIC_RESOURCE_LOGIC_CM_1,
         RESOURCE_LOGIC_CIRCUIT_FIRST_DYNAMIC_RESOURCE_LOGIC_CM_2,
         RESOURCE_LOGIC_CIRCUIT_SECOND_DYNAMIC_RESOURCE_LOGIC_CM_1,
-        RESOURCE_LOGIC_CIRCUIT_SECOND_DYNAMIC_RESOURCE_LOGIC_CM_2, SETUP_PARAMS_MAP,
+        RESOURCE_LOGIC_CIRCUIT_SECOND_DYNAMIC_RESOURCE_LOGIC_CM_2,
+        TOKEN_AUTH_POLICY_PERSONALIZATION_TO_FIELD,
     },
     error::TransactionError,
     nullifier::Nullifier,
-    proof::Proof,
-    resource::{RandomSeed, Resource, ResourceLogics},
+    resource::{OwnedResourceId, RandomSeed, Resource, ResourceLogics},
     resource_logic_commitment::ResourceLogicCommitment,
     resource_logic_vk::ResourceLogicVerifyingKey,
-    utils::{poseidon_hash_n, read_base_field, read_point},
+    utils::{poseidon_hash, poseidon_hash_bytes, read_base_field, read_point},
 };
 use borsh::{BorshDeserialize, BorshSerialize};
 use ff::Field;
@@ -39,28 +49,39 @@ use group::{Curve, Group, GroupEncoding};
 use halo2_gadgets::ecc::{chip::EccChip, NonIdentityPoint};
 use halo2_proofs::{
     circuit::{floor_planner, Layouter, Value},
-    plonk::{keygen_pk, keygen_vk, Circuit, ConstraintSystem, Error},
+    plonk::{Circuit, ConstraintSystem, Error},
 };
 use lazy_static::lazy_static;
-use pasta_curves::arithmetic::CurveAffine;
 use pasta_curves::{group::ff::PrimeField, pallas};
-use rand::{rngs::OsRng, Rng, RngCore};
+use rand::{Rng, RngCore};
 
 lazy_static! {
-    pub static ref TOKEN_VK: ResourceLogicVerifyingKey =
-        TokenResourceLogicCircuit::default().get_resource_logic_vk();
+    pub static ref TOKEN_VK: ResourceLogicVerifyingKey = TokenResourceLogicCircuit::default()
+        .get_resource_logic_vk()
+        .unwrap();
     pub static ref COMPRESSED_TOKEN_VK: pallas::Base = TOKEN_VK.get_compressed();
+    pub static ref TOKEN_U128_VK: ResourceLogicVerifyingKey = TokenResourceLogicCircuit {
+        quantity_mode: TokenQuantityMode::U128,
+        ..Default::default()
+    }
+    .get_resource_logic_vk()
+    .unwrap();
+    pub static ref COMPRESSED_TOKEN_U128_VK: pallas::Base = TOKEN_U128_VK.get_compressed();
 }
 
 #[derive(Clone, Debug, Default, Eq, PartialEq, BorshSerialize, BorshDeserialize)]
 pub struct TokenName(String);
 
 impl TokenName {
+    /// The Poseidon hash of the length-bound encoding of the name.
     pub fn encode(&self) -> pallas::Base {
-        assert!(self.0.len() < 32);
-        let mut bytes: [u8; 32] = [0; 32];
-        bytes[..self.0.len()].copy_from_slice(self.0.as_bytes());
-        pallas::Base::from_repr(bytes).unwrap()
+        poseidon_hash_bytes(self.0.as_bytes())
+    }
+
+    /// The token label: the name bound to the auth policy, so tokens of the same name with
+    /// different auth policies are different resource kinds.
+    pub fn encode_label(&self, auth_policy: &TokenAuthPolicy) -> pallas::Base {
+        poseidon_hash(self.encode(), auth_policy.commitment())
     }
 
     pub fn inner(&self) -> String {
@@ -68,34 +89,157 @@ impl TokenName {
     }
 }
 
+/// The range of the token quantity enforced by the token resource logic.
+/// The two modes have different resource logic vks, so they are different resource kinds.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, BorshSerialize, BorshDeserialize)]
+pub enum TokenQuantityMode {
+    /// The quantity is constrained to u64, as the intent examples assume.
+    #[default]
+    U64,
+    /// The quantity can use the full u128 range checked in the compliance circuit,
+    /// e.g. for 18-decimal tokens.
+    U128,
+}
+
+impl TokenQuantityMode {
+    pub fn resource_logic_vk(&self) -> pallas::Base {
+        match self {
+            TokenQuantityMode::U64 => *COMPRESSED_TOKEN_VK,
+            TokenQuantityMode::U128 => *COMPRESSED_TOKEN_U128_VK,
+        }
+    }
+}
+
+/// The number of auth resource logics in a token auth policy.
+pub const TOKEN_AUTH_POLICY_SIZE: usize = 2;
+
+/// A token auth policy is the set of auth resource logics that can consume the resources of a
+/// token, e.g. the signature verification or a custom authorization scheme. The label of the
+/// token commits to the policy, and the token resource logic checks the auth resource logic of
+/// the owned resource is a member of it. The policy is fixed-size, a policy with fewer auth
+/// resource logics repeats them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TokenAuthPolicy {
+    auth_resource_logic_vks: [pallas::Base; TOKEN_AUTH_POLICY_SIZE],
+}
+
+impl TokenAuthPolicy {
+    pub fn new(auth_resource_logic_vks: [pallas::Base; TOKEN_AUTH_POLICY_SIZE]) -> Self {
+        Self {
+            auth_resource_logic_vks,
+        }
+    }
+
+    // The policy only accepts the auth_resource_logic_vk
+    pub fn single(auth_resource_logic_vk: pallas::Base) -> Self {
+        Self::new([auth_resource_logic_vk; TOKEN_AUTH_POLICY_SIZE])
+    }
+
+    // The policy only accepts the signature verification resource logic
+    pub fn standard() -> Self {
+        Self::single(*COMPRESSED_TOKEN_AUTH_VK)
+    }
+
+    pub fn auth_resource_logic_vks(&self) -> &[pallas::Base; TOKEN_AUTH_POLICY_SIZE] {
+        &self.auth_resource_logic_vks
+    }
+
+    pub fn contains(&self, auth_resource_logic_vk: &pallas::Base) -> bool {
+        self.auth_resource_logic_vks
+            .contains(auth_resource_logic_vk)
+    }
+
+    // The commitment to the policy encoded in the label of the token
+    pub fn commitment(&self) -> pallas::Base {
+        vk_policy_commitment(
+            *TOKEN_AUTH_POLICY_PERSONALIZATION_TO_FIELD,
+            &self.auth_resource_logic_vks,
+        )
+    }
+}
+
+impl BorshSerialize for TokenAuthPolicy {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        for vk in self.auth_resource_logic_vks.iter() {
+            writer.write_all(&vk.to_repr())?;
+        }
+        Ok(())
+    }
+}
+
+impl BorshDeserialize for TokenAuthPolicy {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let auth_resource_logic_vks: Vec<_> = (0..TOKEN_AUTH_POLICY_SIZE)
+            .map(|_| read_base_field(reader))
+            .collect::<Result<_, _>>()?;
+        Ok(Self::new(auth_resource_logic_vks.try_into().unwrap()))
+    }
+}
+
 #[derive(Clone, Debug, Default, BorshSerialize, BorshDeserialize)]
 pub struct Token {
     name: TokenName,
-    quantity: u64,
+    quantity: u128,
+    quantity_mode: TokenQuantityMode,
+    auth_policy: TokenAuthPolicy,
 }
 
 impl Token {
     pub fn new(name: String, quantity: u64) -> Self {
+        Self::new_with_quantity_mode(name, quantity.into(), TokenQuantityMode::U64)
+    }
+
+    pub fn new_u128(name: String, quantity: u128) -> Self {
+        Self::new_with_quantity_mode(name, quantity, TokenQuantityMode::U128)
+    }
+
+    pub fn new_with_quantity_mode(
+        name: String,
+        quantity: u128,
+        quantity_mode: TokenQuantityMode,
+    ) -> Self {
+        assert!(quantity_mode == TokenQuantityMode::U128 || quantity <= u64::MAX as u128);
         Self {
             name: TokenName(name),
             quantity,
+            quantity_mode,
+            auth_policy: TokenAuthPolicy::standard(),
         }
     }
 
+    // Accept the auth resource logics of the policy instead of the signature verification only,
+    // e.g. for tokens with a custom authorization scheme.
+    pub fn with_auth_policy(mut self, auth_policy: TokenAuthPolicy) -> Self {
+        self.auth_policy = auth_policy;
+        self
+    }
+
     pub fn name(&self) -> &TokenName {
         &self.name
     }
 
-    pub fn quantity(&self) -> u64 {
+    pub fn quantity(&self) -> u128 {
         self.quantity
     }
 
+    pub fn quantity_mode(&self) -> TokenQuantityMode {
+        self.quantity_mode
+    }
+
+    pub fn auth_policy(&self) -> &TokenAuthPolicy {
+        &self.auth_policy
+    }
+
     pub fn encode_name(&self) -> pallas::Base {
         self.name.encode()
     }
 
+    pub fn encode_label(&self) -> pallas::Base {
+        self.name.encode_label(&self.auth_policy)
+    }
+
     pub fn encode_quantity(&self) -> pallas::Base {
-        pallas::Base::from(self.quantity)
+        pallas::Base::from_u128(self.quantity)
     }
 
     pub fn create_random_input_token_resource<R: RngCore>(
@@ -104,12 +248,12 @@ impl Token {
         nk: pallas::Base,
         auth: &TokenAuthorization,
     ) -> TokenResource {
-        let label = self.encode_name();
+        let label = self.encode_label();
         let value = auth.to_value();
         let rseed = pallas::Base::random(&mut rng);
         let nonce = Nullifier::random(&mut rng);
         let resource = Resource::new_input_resource(
-            *COMPRESSED_TOKEN_VK,
+            self.quantity_mode.resource_logic_vk(),
             label,
             value,
             self.quantity(),
@@ -121,6 +265,7 @@ impl Token {
 
         TokenResource {
             token_name: self.name().clone(),
+            auth_policy: self.auth_policy,
             resource,
         }
     }
@@ -131,11 +276,11 @@ impl Token {
         npk: pallas::Base,
         auth: &TokenAuthorization,
     ) -> TokenResource {
-        let label = self.encode_name();
+        let label = self.encode_label();
         let value = auth.to_value();
         let rseed = pallas::Base::random(&mut rng);
         let resource = Resource::new_output_resource(
-            *COMPRESSED_TOKEN_VK,
+            self.quantity_mode.resource_logic_vk(),
             label,
             value,
             self.quantity(),
@@ -146,6 +291,7 @@ impl Token {
 
         TokenResource {
             token_name: self.name().clone(),
+            auth_policy: self.auth_policy,
             resource,
         }
     }
@@ -154,6 +300,7 @@ impl Token {
 #[derive(Clone, Debug, Default, BorshDeserialize, BorshSerialize)]
 pub struct TokenResource {
     pub token_name: TokenName,
+    pub auth_policy: TokenAuthPolicy,
     pub resource: Resource,
 }
 
@@ -174,8 +321,20 @@ impl TokenResource {
         self.token_name.encode()
     }
 
+    pub fn encode_label(&self) -> pallas::Base {
+        self.token_name.encode_label(&self.auth_policy)
+    }
+
     pub fn encode_quantity(&self) -> pallas::Base {
-        pallas::Base::from(self.resource().quantity)
+        pallas::Base::from_u128(self.resource().quantity)
+    }
+
+    pub fn quantity_mode(&self) -> TokenQuantityMode {
+        if self.resource.get_logic() == *COMPRESSED_TOKEN_U128_VK {
+            TokenQuantityMode::U128
+        } else {
+            TokenQuantityMode::U64
+        }
     }
 
     pub fn resource(&self) -> &Resource {
@@ -184,23 +343,102 @@ impl TokenResource {
 
     pub fn generate_input_token_resource_logics<R: RngCore>(
         &self,
-        mut rng: R,
+        rng: R,
         auth: TokenAuthorization,
         auth_sk: pallas::Scalar,
         input_resources: [Resource; NUM_RESOURCE],
         output_resources: [Resource; NUM_RESOURCE],
     ) -> ResourceLogics {
+        let (token_resource_logic, token_auth_resource_logic) =
+            self.input_token_circuits(rng, auth, auth_sk, input_resources, output_resources);
+
+        ResourceLogics::new(
+            Box::new(token_resource_logic),
+            vec![Box::new(token_auth_resource_logic)],
+        )
+        .expect("a token has one dynamic resource logic")
+    }
+
+    pub fn generate_output_token_resource_logics<R: RngCore>(
+        &self,
+        rng: R,
+        auth: TokenAuthorization,
+        input_resources: [Resource; NUM_RESOURCE],
+        output_resources: [Resource; NUM_RESOURCE],
+    ) -> ResourceLogics {
+        let (token_resource_logic, receiver_resource_logic) =
+            self.output_token_circuits(rng, auth, input_resources, output_resources);
+
+        ResourceLogics::new(
+            Box::new(token_resource_logic),
+            vec![Box::new(receiver_resource_logic)],
+        )
+        .expect("a token has one dynamic resource logic")
+    }
+
+    // The transparent counterpart of generate_input_token_resource_logics, the resource logics
+    // are checked by executing them instead of proofs.
+    pub fn generate_input_token_application<R: RngCore>(
+        &self,
+        rng: R,
+        auth: TokenAuthorization,
+        auth_sk: pallas::Scalar,
+        input_resources: [Resource; NUM_RESOURCE],
+        output_resources: [Resource; NUM_RESOURCE],
+    ) -> ApplicationByteCode {
+        let (token_resource_logic, token_auth_resource_logic) =
+            self.input_token_circuits(rng, auth, auth_sk, input_resources, output_resources);
+
+        ApplicationByteCode::new(
+            token_resource_logic.to_bytecode(),
+            vec![token_auth_resource_logic.to_bytecode()],
+        )
+        .expect("a token has one dynamic resource logic")
+    }
+
+    // The transparent counterpart of generate_output_token_resource_logics
+    pub fn generate_output_token_application<R: RngCore>(
+        &self,
+        rng: R,
+        auth: TokenAuthorization,
+        input_resources: [Resource; NUM_RESOURCE],
+        output_resources: [Resource; NUM_RESOURCE],
+    ) -> ApplicationByteCode {
+        let (token_resource_logic, receiver_resource_logic) =
+            self.output_token_circuits(rng, auth, input_resources, output_resources);
+
+        ApplicationByteCode::new(
+            token_resource_logic.to_bytecode(),
+            vec![receiver_resource_logic.to_bytecode()],
+        )
+        .expect("a token has one dynamic resource logic")
+    }
+
+    pub(crate) fn input_token_circuits<R: RngCore>(
+        &self,
+        mut rng: R,
+        auth: TokenAuthorization,
+        auth_sk: pallas::Scalar,
+        input_resources: [Resource; NUM_RESOURCE],
+        output_resources: [Resource; NUM_RESOURCE],
+    ) -> (
+        TokenResourceLogicCircuit,
+        SignatureVerificationResourceLogicCircuit,
+    ) {
         let TokenResource {
             token_name,
+            auth_policy,
             resource,
         } = self;
         // token resource logic
-        let nf = resource.get_nf().unwrap().inner();
+        let nf = resource.get_nf().unwrap();
         let token_resource_logic = TokenResourceLogicCircuit {
-            owned_resource_id: nf,
+            owned_resource_id: nf.into(),
             input_resources,
             output_resources,
             token_name: token_name.clone(),
+            auth_policy: *auth_policy,
+            quantity_mode: self.quantity_mode(),
             auth,
             receiver_resource_logic_vk: *COMPRESSED_RECEIVER_VK,
             rseed: RandomSeed::random(&mut rng),
@@ -209,39 +447,39 @@ impl TokenResource {
         // token auth resource logic
         let token_auth_resource_logic = SignatureVerificationResourceLogicCircuit::from_sk_and_sign(
             &mut rng,
-            nf,
+            nf.into(),
             input_resources,
             output_resources,
             auth.vk,
             auth_sk,
-            *COMPRESSED_RECEIVER_VK,
+            auth.receiver_policy.commitment(),
         );
 
-        ResourceLogics::new(
-            Box::new(token_resource_logic),
-            vec![Box::new(token_auth_resource_logic)],
-        )
+        (token_resource_logic, token_auth_resource_logic)
     }
 
-    pub fn generate_output_token_resource_logics<R: RngCore>(
+    pub(crate) fn output_token_circuits<R: RngCore>(
         &self,
         mut rng: R,
         auth: TokenAuthorization,
         input_resources: [Resource; NUM_RESOURCE],
         output_resources: [Resource; NUM_RESOURCE],
-    ) -> ResourceLogics {
+    ) -> (TokenResourceLogicCircuit, ReceiverResourceLogicCircuit) {
         let TokenResource {
             token_name,
+            auth_policy,
             resource,
         } = self;
 
-        let owned_resource_id = resource.commitment().inner();
+        let owned_resource_id = OwnedResourceId::Cm(resource.commitment());
         // token resource logic
         let token_resource_logic = TokenResourceLogicCircuit {
             owned_resource_id,
             input_resources,
             output_resources,
             token_name: token_name.clone(),
+            auth_policy: *auth_policy,
+            quantity_mode: self.quantity_mode(),
             auth,
             receiver_resource_logic_vk: *COMPRESSED_RECEIVER_VK,
             rseed: RandomSeed::random(&mut rng),
@@ -256,26 +494,30 @@ impl TokenResource {
             encrypt_nonce: pallas::Base::from_u128(rng.gen()),
             sk: pallas::Base::random(&mut rng),
             rcv_pk: auth.pk,
-            auth_resource_logic_vk: *COMPRESSED_TOKEN_AUTH_VK,
+            auth_resource_logic_vk: auth.vk,
+            receiver_policy: auth.receiver_policy,
         };
 
-        ResourceLogics::new(
-            Box::new(token_resource_logic),
-            vec![Box::new(receiver_resource_logic)],
-        )
+        (token_resource_logic, receiver_resource_logic)
     }
 }
 
 // TokenResourceLogicCircuit
 #[derive(Clone, Debug)]
 pub struct TokenResourceLogicCircuit {
-    pub owned_resource_id: pallas::Base,
+    pub owned_resource_id: OwnedResourceId,
     pub input_resources: [Resource; NUM_RESOURCE],
     pub output_resources: [Resource; NUM_RESOURCE],
     // The token_name goes to label. It can be extended to a list and embedded to label.
     pub token_name: TokenName,
+    // The auth resource logics accepted by the token, the label commits to them.
+    pub auth_policy: TokenAuthPolicy,
+    // The quantity_mode is fixed in the circuit, each mode has its own resource logic vk.
+    pub quantity_mode: TokenQuantityMode,
     // The auth goes to value and defines how to consume and create the resource.
     pub auth: TokenAuthorization,
+    // The receiver resource logic of the output resource, it must be in the receiver policy of
+    // the auth.
     pub receiver_resource_logic_vk: pallas::Base,
     // rseed is to generate the randomness for resource_logic commitment
     pub rseed: RandomSeed,
@@ -285,6 +527,8 @@ pub struct TokenResourceLogicCircuit {
 pub struct TokenAuthorization {
     pub pk: pallas::Point,
     pub vk: pallas::Base,
+    // The receiver resource logics the resource accepts
+    pub receiver_policy: ReceiverPolicy,
 }
 
 impl Default for TokenAuthorization {
@@ -292,6 +536,7 @@ impl Default for TokenAuthorization {
         Self {
             pk: pallas::Point::generator(),
             vk: pallas::Base::one(),
+            receiver_policy: ReceiverPolicy::default(),
         }
     }
 }
@@ -300,23 +545,17 @@ impl TokenResourceLogicCircuit {
     pub fn to_bytecode(&self) -> ResourceLogicByteCode {
         ResourceLogicByteCode::new(ResourceLogicRepresentation::Token, self.to_bytes())
     }
-
-    pub fn to_bytes(&self) -> Vec<u8> {
-        borsh::to_vec(&self).unwrap()
-    }
-
-    pub fn from_bytes(bytes: &Vec<u8>) -> Self {
-        BorshDeserialize::deserialize(&mut bytes.as_ref()).unwrap()
-    }
 }
 
 impl Default for TokenResourceLogicCircuit {
     fn default() -> Self {
         Self {
-            owned_resource_id: pallas::Base::zero(),
+            owned_resource_id: OwnedResourceId::default(),
             input_resources: [(); NUM_RESOURCE].map(|_| Resource::default()),
             output_resources: [(); NUM_RESOURCE].map(|_| Resource::default()),
             token_name: TokenName("Token_name".to_string()),
+            auth_policy: TokenAuthPolicy::default(),
+            quantity_mode: TokenQuantityMode::U64,
             auth: TokenAuthorization::default(),
             receiver_resource_logic_vk: pallas::Base::zero(),
             rseed: RandomSeed::default(),
@@ -342,6 +581,34 @@ impl ResourceLogicCircuit for TokenResourceLogicCircuit {
 
         // We can add more constraints on token_property or extend the token_properties.
 
+        let sub_chip = SubChip::construct(config.sub_config.clone(), ());
+        let mul_chip = MulChip::construct(config.mul_config.clone());
+        let accumulator_chip = HashAccumulatorChip::construct(
+            config.poseidon_config.clone(),
+            config.conditional_select_config,
+            AddChip::construct(config.add_config.clone(), ()),
+            mul_chip.clone(),
+            config.advices[0],
+        );
+
+        let auth_resource_logic_vk = assign_free_advice(
+            layouter.namespace(|| "witness auth resource_logic vk"),
+            config.advices[0],
+            Value::known(self.auth.vk),
+        )?;
+
+        // The auth resource logic must be in the auth policy of the token
+        let auth_policy = vk_policy_gadget(
+            layouter.namespace(|| "auth policy"),
+            config.advices[0],
+            &accumulator_chip,
+            *TOKEN_AUTH_POLICY_PERSONALIZATION_TO_FIELD,
+            &sub_chip,
+            &mul_chip,
+            self.auth_policy.auth_resource_logic_vks(),
+            &auth_resource_logic_vk,
+        )?;
+
         // search target resource and get the label
         let label = get_owned_resource_variable(
             config.get_owned_resource_variable_config,
@@ -351,11 +618,52 @@ impl ResourceLogicCircuit for TokenResourceLogicCircuit {
         )?;
 
         // check label
+        let encoded_label = poseidon_hash_gadget(
+            config.poseidon_config.clone(),
+            layouter.namespace(|| "label encoding"),
+            [token_property, auth_policy],
+        )?;
         layouter.assign_region(
             || "check label",
-            |mut region| region.constrain_equal(token_property.cell(), label.cell()),
+            |mut region| region.constrain_equal(encoded_label.cell(), label.cell()),
         )?;
 
+        let constant_zero = assign_free_constant(
+            layouter.namespace(|| "zero"),
+            config.advices[0],
+            pallas::Base::zero(),
+        )?;
+
+        // The compliance circuit checks the quantity is u128, constrain it to u64 in U64 mode.
+        if self.quantity_mode == TokenQuantityMode::U64 {
+            let quantity = get_owned_resource_variable(
+                config.get_owned_resource_variable_config,
+                layouter.namespace(|| "get owned resource quantity"),
+                &owned_resource_id,
+                &basic_variables.get_quantity_searchable_pairs(),
+            )?;
+            RangeCheckChip::construct(config.range_check_config)
+                .range_check_u64(layouter.namespace(|| "check quantity is u64"), &quantity)?;
+
+            // The total quantity of the token created in the ptx must be u64 too
+            let logic = get_owned_resource_variable(
+                config.get_owned_resource_variable_config,
+                layouter.namespace(|| "get owned resource logic"),
+                &owned_resource_id,
+                &basic_variables.get_logic_searchable_pairs(),
+            )?;
+            output_kind_quantity_gadget(
+                layouter.namespace(|| "total output quantity"),
+                config.advices[0],
+                &mul_chip,
+                &sub_chip,
+                &config.u128_config,
+                &logic,
+                &label,
+                &basic_variables,
+            )?;
+        }
+
         // Construct an ECC chip
         let ecc_chip = EccChip::construct(config.ecc_config);
 
@@ -365,12 +673,6 @@ impl ResourceLogicCircuit for TokenResourceLogicCircuit {
             Value::known(self.auth.pk.to_affine()),
         )?;
 
-        let auth_resource_logic_vk = assign_free_advice(
-            layouter.namespace(|| "witness auth resource_logic vk"),
-            config.advices[0],
-            Value::known(self.auth.vk),
-        )?;
-
         // search target resource and get the value
         let value = get_owned_resource_variable(
             config.get_owned_resource_variable_config,
@@ -385,6 +687,17 @@ impl ResourceLogicCircuit for TokenResourceLogicCircuit {
             Value::known(self.receiver_resource_logic_vk),
         )?;
 
+        // The receiver resource logic must be in the receiver policy
+        let receiver_policy = receiver_policy_gadget(
+            layouter.namespace(|| "receiver policy"),
+            config.advices[0],
+            &accumulator_chip,
+            &sub_chip,
+            &mul_chip,
+            &self.auth.receiver_policy,
+            &receiver_resource_logic_vk,
+        )?;
+
         // Decode the value, and check the value encoding
         let encoded_value = poseidon_hash_gadget(
             config.poseidon_config,
@@ -393,7 +706,7 @@ impl ResourceLogicCircuit for TokenResourceLogicCircuit {
                 pk.inner().x(),
                 pk.inner().y(),
                 auth_resource_logic_vk.clone(),
-                receiver_resource_logic_vk.clone(),
+                receiver_policy,
             ],
         )?;
 
@@ -409,11 +722,6 @@ impl ResourceLogicCircuit for TokenResourceLogicCircuit {
             &owned_resource_id,
             &basic_variables.get_is_ephemeral_searchable_pairs(),
         )?;
-        let constant_zero = assign_free_constant(
-            layouter.namespace(|| "zero"),
-            config.advices[0],
-            pallas::Base::zero(),
-        )?;
         layouter.assign_region(
             || "check is_ephemeral",
             |mut region| region.constrain_equal(is_ephemeral.cell(), constant_zero.cell()),
@@ -510,13 +818,10 @@ impl ResourceLogicCircuit for TokenResourceLogicCircuit {
 
     fn get_public_inputs(&self, mut rng: impl RngCore) -> ResourceLogicPublicInputs {
         let mut public_inputs = self.get_mandatory_public_inputs();
-        let dynamic_resource_logic = if self.owned_resource_id
-            == self.output_resources[0].commitment().inner()
-            || self.owned_resource_id == self.output_resources[1].commitment().inner()
-        {
-            self.receiver_resource_logic_vk
-        } else {
+        let dynamic_resource_logic = if self.get_owned_resource_id().is_input_resource() {
             self.auth.vk
+        } else {
+            self.receiver_resource_logic_vk
         };
 
         let resource_logic_com_r = self
@@ -538,63 +843,100 @@ impl ResourceLogicCircuit for TokenResourceLogicCircuit {
         public_inputs.into()
     }
 
-    fn get_owned_resource_id(&self) -> pallas::Base {
+    fn get_owned_resource_id(&self) -> OwnedResourceId {
         self.owned_resource_id
     }
-}
 
-resource_logic_circuit_impl!(TokenResourceLogicCircuit);
-resource_logic_verifying_info_impl!(TokenResourceLogicCircuit);
-
-impl BorshSerialize for TokenResourceLogicCircuit {
-    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
-        writer.write_all(&self.owned_resource_id.to_repr())?;
-        for input in self.input_resources.iter() {
-            input.serialize(writer)?;
+    fn custom_precheck(&self) -> Result<(), ResourceLogicPrecheckError> {
+        let owned_resource = self
+            .get_owned_resource()
+            .ok_or(ResourceLogicPrecheckError::UnknownOwnedResourceId)?;
+        if owned_resource.get_label() != self.token_name.encode_label(&self.auth_policy) {
+            return Err(ResourceLogicPrecheckError::InconsistentOwnedResource(
+                "label",
+            ));
         }
-
-        for output in self.output_resources.iter() {
-            output.serialize(writer)?;
+        if !self.auth_policy.contains(&self.auth.vk) {
+            return Err(ResourceLogicPrecheckError::InconsistentOwnedResource(
+                "auth policy",
+            ));
+        }
+        // The value encodes the auth and the receiver policy
+        if owned_resource.value != self.auth.to_value() {
+            return Err(ResourceLogicPrecheckError::InconsistentOwnedResource(
+                "value",
+            ));
+        }
+        if !self
+            .auth
+            .receiver_policy
+            .contains(&self.receiver_resource_logic_vk)
+        {
+            return Err(ResourceLogicPrecheckError::InconsistentOwnedResource(
+                "receiver policy",
+            ));
+        }
+        if owned_resource.is_ephemeral {
+            return Err(ResourceLogicPrecheckError::InconsistentOwnedResource(
+                "is_ephemeral",
+            ));
+        }
+        if self.quantity_mode == TokenQuantityMode::U64 {
+            if owned_resource.quantity > u64::MAX as u128 {
+                return Err(ResourceLogicPrecheckError::QuantityOutOfRange(
+                    owned_resource.quantity,
+                ));
+            }
+            // The total quantity of the token created in the ptx
+            let total_quantity = self
+                .output_resources
+                .iter()
+                .filter(|resource| resource.kind == owned_resource.kind)
+                .try_fold(0u128, |total, resource| {
+                    total.checked_add(resource.quantity)
+                })
+                .unwrap_or(u128::MAX);
+            if total_quantity > u64::MAX as u128 {
+                return Err(ResourceLogicPrecheckError::QuantityOutOfRange(
+                    total_quantity,
+                ));
+            }
         }
-
-        self.token_name.serialize(writer)?;
-        self.auth.serialize(writer)?;
-        writer.write_all(&self.receiver_resource_logic_vk.to_repr())?;
-        self.rseed.serialize(writer)?;
-
         Ok(())
     }
-}
 
-impl BorshDeserialize for TokenResourceLogicCircuit {
-    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
-        let owned_resource_id = read_base_field(reader)?;
-        let input_resources: Vec<_> = (0..NUM_RESOURCE)
-            .map(|_| Resource::deserialize_reader(reader))
-            .collect::<Result<_, _>>()?;
-        let output_resources: Vec<_> = (0..NUM_RESOURCE)
-            .map(|_| Resource::deserialize_reader(reader))
-            .collect::<Result<_, _>>()?;
-        let token_name = TokenName::deserialize_reader(reader)?;
-        let auth = TokenAuthorization::deserialize_reader(reader)?;
-        let receiver_resource_logic_vk = read_base_field(reader)?;
-        let rseed = RandomSeed::deserialize_reader(reader)?;
-        Ok(Self {
-            owned_resource_id,
-            input_resources: input_resources.try_into().unwrap(),
-            output_resources: output_resources.try_into().unwrap(),
-            token_name,
-            auth,
-            receiver_resource_logic_vk,
-            rseed,
-        })
+    // The quantity range check is only in the U64 mode circuit, the modes have different keys
+    fn cache_key(&self) -> u64 {
+        self.quantity_mode as u64
     }
 }
 
+resource_logic_circuit_impl!(TokenResourceLogicCircuit);
+resource_logic_verifying_info_impl!(TokenResourceLogicCircuit);
+circuit_field_encoding_from_borsh!(
+    Token,
+    TokenName,
+    TokenQuantityMode,
+    TokenAuthorization,
+    TokenAuthPolicy
+);
+resource_logic_circuit_encoding_impl!(TokenResourceLogicCircuit {
+    1 => owned_resource_id,
+    2 => input_resources,
+    3 => output_resources,
+    4 => token_name,
+    5 => quantity_mode,
+    6 => auth,
+    7 => receiver_resource_logic_vk,
+    8 => rseed,
+    9 => auth_policy,
+});
+
 impl BorshSerialize for TokenAuthorization {
     fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
         writer.write_all(&self.pk.to_bytes())?;
         writer.write_all(&self.vk.to_repr())?;
+        self.receiver_policy.serialize(writer)?;
         Ok(())
     }
 }
@@ -603,42 +945,51 @@ impl BorshDeserialize for TokenAuthorization {
     fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
         let pk = read_point(reader)?;
         let vk = read_base_field(reader)?;
+        let receiver_policy = ReceiverPolicy::deserialize_reader(reader)?;
 
-        Ok(Self { pk, vk })
+        Ok(Self {
+            pk,
+            vk,
+            receiver_policy,
+        })
     }
 }
 
 impl TokenAuthorization {
     pub fn new(pk: pallas::Point, vk: pallas::Base) -> Self {
-        Self { pk, vk }
+        Self {
+            pk,
+            vk,
+            receiver_policy: ReceiverPolicy::standard(),
+        }
     }
 
     pub fn random<R: RngCore>(mut rng: R) -> Self {
-        Self {
-            pk: pallas::Point::random(&mut rng),
-            vk: *COMPRESSED_TOKEN_AUTH_VK,
-        }
+        Self::new(pallas::Point::random(&mut rng), *COMPRESSED_TOKEN_AUTH_VK)
+    }
+
+    // Accept the receiver resource logics of the policy instead of the standard receiver only,
+    // e.g. for custodial receive flows.
+    pub fn with_receiver_policy(mut self, receiver_policy: ReceiverPolicy) -> Self {
+        self.receiver_policy = receiver_policy;
+        self
     }
 
+    // The value of the token resources owned by the authorization, see `derive_receiver_value`
     pub fn to_value(&self) -> pallas::Base {
-        let pk_coord = self.pk.to_affine().coordinates().unwrap();
-        poseidon_hash_n::<4>([
-            *pk_coord.x(),
-            *pk_coord.y(),
-            self.vk,
-            *COMPRESSED_RECEIVER_VK,
-        ])
+        derive_receiver_value(&self.pk, self.vk, &self.receiver_policy)
     }
 
     pub fn from_sk_vk(sk: &pallas::Scalar, vk: &pallas::Base) -> Self {
         let generator = pallas::Point::generator().to_affine();
         let pk = generator * sk;
-        Self { pk, vk: *vk }
+        Self::new(pk, *vk)
     }
 }
 
 #[test]
 fn test_halo2_token_resource_logic_circuit() {
+    use crate::circuit::verify_failure::assert_verifies;
     use crate::constant::RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE;
     use crate::resource::tests::random_resource;
     use halo2_proofs::dev::MockProver;
@@ -650,13 +1001,15 @@ fn test_halo2_token_resource_logic_circuit() {
         let output_resources = [(); NUM_RESOURCE].map(|_| random_resource(&mut rng));
         let token_name = TokenName("Token_name".to_string());
         let auth = TokenAuthorization::random(&mut rng);
-        input_resources[0].kind.label = token_name.encode();
+        input_resources[0].kind.label = token_name.encode_label(&TokenAuthPolicy::standard());
         input_resources[0].value = auth.to_value();
         TokenResourceLogicCircuit {
-            owned_resource_id: input_resources[0].get_nf().unwrap().inner(),
+            owned_resource_id: input_resources[0].get_nf().unwrap().into(),
             input_resources,
             output_resources,
             token_name,
+            auth_policy: TokenAuthPolicy::standard(),
+            quantity_mode: TokenQuantityMode::U64,
             auth,
             receiver_resource_logic_vk: *COMPRESSED_RECEIVER_VK,
             rseed: RandomSeed::random(&mut rng),
@@ -677,5 +1030,280 @@ fn test_halo2_token_resource_logic_circuit() {
         vec![public_inputs.to_vec()],
     )
     .unwrap();
-    assert_eq!(prover.verify(), Ok(()));
+    assert_verifies(&prover);
+}
+
+#[test]
+fn test_halo2_token_resource_logic_circuit_u128_quantity() {
+    use crate::circuit::verify_failure::assert_verifies;
+    use crate::constant::RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE;
+    use crate::resource::tests::random_resource;
+    use halo2_proofs::dev::MockProver;
+    use rand::rngs::OsRng;
+
+    let mut rng = OsRng;
+    let mut input_resources = [(); NUM_RESOURCE].map(|_| random_resource(&mut rng));
+    let output_resources = [(); NUM_RESOURCE].map(|_| random_resource(&mut rng));
+    let token_name = TokenName("Token_name".to_string());
+    let auth = TokenAuthorization::random(&mut rng);
+    input_resources[0].kind.label = token_name.encode_label(&TokenAuthPolicy::standard());
+    input_resources[0].value = auth.to_value();
+    input_resources[0].quantity = u128::MAX;
+    let mut circuit = TokenResourceLogicCircuit {
+        owned_resource_id: input_resources[0].get_nf().unwrap().into(),
+        input_resources,
+        output_resources,
+        token_name,
+        auth_policy: TokenAuthPolicy::standard(),
+        quantity_mode: TokenQuantityMode::U128,
+        auth,
+        receiver_resource_logic_vk: *COMPRESSED_RECEIVER_VK,
+        rseed: RandomSeed::random(&mut rng),
+    };
+    let public_inputs = circuit.get_public_inputs(&mut rng);
+
+    let prover = MockProver::<pallas::Base>::run(
+        RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+        &circuit,
+        vec![public_inputs.to_vec()],
+    )
+    .unwrap();
+    assert_verifies(&prover);
+
+    // The quantity doesn't fit in the U64 mode
+    circuit.quantity_mode = TokenQuantityMode::U64;
+    let prover = MockProver::<pallas::Base>::run(
+        RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+        &circuit,
+        vec![public_inputs.to_vec()],
+    )
+    .unwrap();
+    assert!(prover.verify().is_err());
+    assert_eq!(
+        circuit.precheck(),
+        Err(ResourceLogicPrecheckError::QuantityOutOfRange(u128::MAX))
+    );
+}
+
+#[test]
+fn test_token_quantity_mode_vks() {
+    // Both modes are keyed apart in the key cache, whichever is generated first
+    assert_ne!(*COMPRESSED_TOKEN_U128_VK, *COMPRESSED_TOKEN_VK);
+    assert_ne!(
+        TokenQuantityMode::U64.resource_logic_vk(),
+        TokenQuantityMode::U128.resource_logic_vk()
+    );
+}
+
+#[test]
+fn test_halo2_token_resource_logic_circuit_receiver_policy() {
+    use crate::circuit::verify_failure::assert_verifies;
+    use crate::constant::RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE;
+    use crate::resource::tests::random_resource;
+    use halo2_proofs::dev::MockProver;
+    use rand::rngs::OsRng;
+
+    let mut rng = OsRng;
+    // The resource can be received by either the standard receiver or a custodial receiver
+    let custodial_receiver_vk = pallas::Base::random(&mut rng);
+    let receiver_policy = ReceiverPolicy::new([*COMPRESSED_RECEIVER_VK, custodial_receiver_vk]);
+    let input_resources = [(); NUM_RESOURCE].map(|_| random_resource(&mut rng));
+    let mut output_resources = [(); NUM_RESOURCE].map(|_| random_resource(&mut rng));
+    let token_name = TokenName("Token_name".to_string());
+    let auth = TokenAuthorization::random(&mut rng).with_receiver_policy(receiver_policy);
+    output_resources[0].kind.label = token_name.encode_label(&TokenAuthPolicy::standard());
+    output_resources[0].value = auth.to_value();
+    let mut circuit = TokenResourceLogicCircuit {
+        owned_resource_id: output_resources[0].commitment().into(),
+        input_resources,
+        output_resources,
+        token_name,
+        auth_policy: TokenAuthPolicy::standard(),
+        quantity_mode: TokenQuantityMode::U64,
+        auth,
+        receiver_resource_logic_vk: custodial_receiver_vk,
+        rseed: RandomSeed::random(&mut rng),
+    };
+    assert_eq!(circuit.precheck(), Ok(()));
+    let public_inputs = circuit.get_public_inputs(&mut rng);
+    let prover = MockProver::<pallas::Base>::run(
+        RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+        &circuit,
+        vec![public_inputs.to_vec()],
+    )
+    .unwrap();
+    assert_verifies(&prover);
+
+    // A receiver outside of the policy is rejected
+    circuit.receiver_resource_logic_vk = pallas::Base::random(&mut rng);
+    let public_inputs = circuit.get_public_inputs(&mut rng);
+    let prover = MockProver::<pallas::Base>::run(
+        RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+        &circuit,
+        vec![public_inputs.to_vec()],
+    )
+    .unwrap();
+    assert!(prover.verify().is_err());
+}
+
+#[test]
+fn test_halo2_token_resource_logic_circuit_auth_policy() {
+    use crate::circuit::verify_failure::assert_verifies;
+    use crate::constant::RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE;
+    use crate::resource::tests::random_resource;
+    use halo2_proofs::dev::MockProver;
+    use rand::rngs::OsRng;
+
+    let mut rng = OsRng;
+    // The token can be consumed by either the signature verification or a custom auth scheme
+    let custom_auth_vk = pallas::Base::random(&mut rng);
+    let auth_policy = TokenAuthPolicy::new([*COMPRESSED_TOKEN_AUTH_VK, custom_auth_vk]);
+    let token_name = TokenName("Token_name".to_string());
+    // The auth policy is part of the resource kind
+    assert_ne!(
+        token_name.encode_label(&auth_policy),
+        token_name.encode_label(&TokenAuthPolicy::standard())
+    );
+
+    let mut input_resources = [(); NUM_RESOURCE].map(|_| random_resource(&mut rng));
+    let output_resources = [(); NUM_RESOURCE].map(|_| random_resource(&mut rng));
+    let auth = TokenAuthorization::new(pallas::Point::random(&mut rng), custom_auth_vk);
+    input_resources[0].kind.label = token_name.encode_label(&auth_policy);
+    input_resources[0].value = auth.to_value();
+    let mut circuit = TokenResourceLogicCircuit {
+        owned_resource_id: input_resources[0].get_nf().unwrap().into(),
+        input_resources,
+        output_resources,
+        token_name,
+        auth_policy,
+        quantity_mode: TokenQuantityMode::U64,
+        auth,
+        receiver_resource_logic_vk: *COMPRESSED_RECEIVER_VK,
+        rseed: RandomSeed::random(&mut rng),
+    };
+    assert_eq!(circuit.precheck(), Ok(()));
+    let public_inputs = circuit.get_public_inputs(&mut rng);
+    let prover = MockProver::<pallas::Base>::run(
+        RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+        &circuit,
+        vec![public_inputs.to_vec()],
+    )
+    .unwrap();
+    assert_verifies(&prover);
+
+    // An auth resource logic outside of the policy is rejected
+    circuit.auth_policy = TokenAuthPolicy::standard();
+    circuit.input_resources[0].kind.label = circuit.token_name.encode_label(&circuit.auth_policy);
+    circuit.owned_resource_id = circuit.input_resources[0].get_nf().unwrap().into();
+    assert_eq!(
+        circuit.precheck(),
+        Err(ResourceLogicPrecheckError::InconsistentOwnedResource(
+            "auth policy"
+        ))
+    );
+    let public_inputs = circuit.get_public_inputs(&mut rng);
+    let prover = MockProver::<pallas::Base>::run(
+        RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+        &circuit,
+        vec![public_inputs.to_vec()],
+    )
+    .unwrap();
+    assert!(prover.verify().is_err());
+}
+
+#[test]
+fn test_halo2_token_resource_logic_circuit_total_quantity() {
+    use crate::constant::RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE;
+    use crate::resource::tests::random_resource;
+    use halo2_proofs::dev::MockProver;
+    use rand::rngs::OsRng;
+
+    let mut rng = OsRng;
+    let input_resources = [(); NUM_RESOURCE].map(|_| random_resource(&mut rng));
+    let mut output_resources = [(); NUM_RESOURCE].map(|_| random_resource(&mut rng));
+    let token_name = TokenName("Token_name".to_string());
+    let auth = TokenAuthorization::random(&mut rng);
+    // Two outputs of the token, both u64 but the total is not
+    for output_resource in output_resources.iter_mut() {
+        output_resource.kind.logic = *COMPRESSED_TOKEN_VK;
+        output_resource.kind.label = token_name.encode_label(&TokenAuthPolicy::standard());
+        output_resource.value = auth.to_value();
+        output_resource.quantity = u64::MAX as u128;
+    }
+    let circuit = TokenResourceLogicCircuit {
+        owned_resource_id: output_resources[0].commitment().into(),
+        input_resources,
+        output_resources,
+        token_name,
+        auth_policy: TokenAuthPolicy::standard(),
+        quantity_mode: TokenQuantityMode::U64,
+        auth,
+        receiver_resource_logic_vk: *COMPRESSED_RECEIVER_VK,
+        rseed: RandomSeed::random(&mut rng),
+    };
+    assert_eq!(
+        circuit.precheck(),
+        Err(ResourceLogicPrecheckError::QuantityOutOfRange(
+            2 * u64::MAX as u128
+        ))
+    );
+    let public_inputs = circuit.get_public_inputs(&mut rng);
+    let prover = MockProver::<pallas::Base>::run(
+        RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+        &circuit,
+        vec![public_inputs.to_vec()],
+    )
+    .unwrap();
+    assert!(prover.verify().is_err());
+}
+
+#[test]
+fn test_token_resource_logic_precheck() {
+    use crate::resource::tests::random_resource;
+    use rand::rngs::OsRng;
+
+    let mut rng = OsRng;
+    let mut input_resources = [(); NUM_RESOURCE].map(|_| random_resource(&mut rng));
+    let output_resources = [(); NUM_RESOURCE].map(|_| random_resource(&mut rng));
+    let token_name = TokenName("Token_name".to_string());
+    let auth = TokenAuthorization::random(&mut rng);
+    input_resources[0].kind.label = token_name.encode_label(&TokenAuthPolicy::standard());
+    input_resources[0].value = auth.to_value();
+    input_resources[0].quantity = 5000u128;
+    let mut circuit = TokenResourceLogicCircuit {
+        owned_resource_id: input_resources[0].get_nf().unwrap().into(),
+        input_resources,
+        output_resources,
+        token_name,
+        auth_policy: TokenAuthPolicy::standard(),
+        quantity_mode: TokenQuantityMode::U64,
+        auth,
+        receiver_resource_logic_vk: *COMPRESSED_RECEIVER_VK,
+        rseed: RandomSeed::random(&mut rng),
+    };
+    assert_eq!(circuit.precheck(), Ok(()));
+
+    // The owned resource id doesn't match any resource
+    let owned_resource_id = circuit.owned_resource_id;
+    circuit.owned_resource_id = Nullifier::from(pallas::Base::random(&mut rng)).into();
+    assert_eq!(
+        circuit.precheck(),
+        Err(ResourceLogicPrecheckError::UnknownOwnedResourceId)
+    );
+    assert!(matches!(
+        circuit.verify_transparently(),
+        Err(TransactionError::ResourceLogicPrecheck(
+            ResourceLogicPrecheckError::UnknownOwnedResourceId
+        ))
+    ));
+    circuit.owned_resource_id = owned_resource_id;
+
+    // The receiver resource logic vk is not in the receiver policy
+    circuit.receiver_resource_logic_vk = pallas::Base::random(&mut rng);
+    assert_eq!(
+        circuit.precheck(),
+        Err(ResourceLogicPrecheckError::InconsistentOwnedResource(
+            "receiver policy"
+        ))
+    );
 }
@@ -0,0 +1,270 @@
+/// Spending many token resources of one kind to fund a single output, e.g. from a wallet with a
+/// fragmented balance.
+///
+/// The inputs are selected largest first until they cover the quantity. A ptx spends at most
+/// NUM_RESOURCE inputs, so more inputs are spread over a chain of ptxs cascaded by cascade
+/// intents: every ptx but the last creates a cascade intent naming the next input, and the next
+/// ptx consumes the intent along with that input. The last ptx creates the output and the
+/// change. The intents only balance if the whole chain is in the transaction.
+use crate::{
+    circuit::resource_logic_examples::{
+        cascade_intent::{create_intent_resource, CascadeIntentResourceLogicCircuit},
+        token::{Token, TokenAuthorization, TokenResource},
+    },
+    constant::NUM_RESOURCE,
+    error::TransactionError,
+    merkle_tree::MerklePath,
+    resource::ResourceLogics,
+    shielded_ptx::{ShieldedPartialTransaction, ShieldedPartialTransactionBuilder},
+};
+use pasta_curves::pallas;
+use rand::RngCore;
+
+/// Selects the inputs covering `quantity`, largest first. Returns the selected inputs and the
+/// change.
+pub fn select_token_inputs(
+    mut inputs: Vec<(TokenResource, MerklePath)>,
+    quantity: u128,
+) -> Result<(Vec<(TokenResource, MerklePath)>, u128), TransactionError> {
+    if let Some((first, _)) = inputs.first() {
+        let kind = first.kind;
+        if inputs.iter().any(|(input, _)| input.kind != kind) {
+            return Err(TransactionError::InconsistentResourceKind);
+        }
+    }
+
+    inputs.sort_by(|(lhs, _), (rhs, _)| rhs.quantity.cmp(&lhs.quantity));
+    let mut selected = vec![];
+    let mut total = 0u128;
+    for input in inputs {
+        if total >= quantity && !selected.is_empty() {
+            break;
+        }
+        total += input.0.quantity;
+        selected.push(input);
+    }
+    if selected.is_empty() || total < quantity {
+        return Err(TransactionError::InsufficientQuantity(total));
+    }
+    Ok((selected, total - quantity))
+}
+
+/// Creates the ptxs paying `quantity` of the token to the receiver from the inputs, the change
+/// goes back to the owner of the inputs. The inputs are of one kind and owned by `auth`.
+#[allow(clippy::too_many_arguments)]
+pub fn create_token_merge_ptxs<R: RngCore>(
+    mut rng: R,
+    inputs: Vec<(TokenResource, MerklePath)>,
+    auth: TokenAuthorization,
+    auth_sk: pallas::Scalar,
+    quantity: u128,
+    receiver_npk: pallas::Base,
+    receiver_auth: TokenAuthorization,
+) -> Result<Vec<ShieldedPartialTransaction>, TransactionError> {
+    let (inputs, change) = select_token_inputs(inputs, quantity)?;
+    let template = inputs[0].0.clone();
+    let owner_nk = template
+        .get_nk()
+        .ok_or(TransactionError::MissingTransparentResourceNullifierKey)?;
+    let token = |quantity| {
+        Token::new_with_quantity_mode(
+            template.token_name().inner(),
+            quantity,
+            template.quantity_mode(),
+        )
+        .with_auth_policy(template.auth_policy)
+    };
+
+    // The first ptx spends NUM_RESOURCE inputs, the next ones an intent and one input each
+    let mut inputs = inputs.into_iter();
+    let mut groups = vec![inputs.by_ref().take(NUM_RESOURCE).collect::<Vec<_>>()];
+    groups.extend(inputs.map(|input| vec![input]));
+    let cascade_resource_cms = groups
+        .iter()
+        .skip(1)
+        .map(|group| group[0].0.commitment().inner())
+        .collect::<Vec<_>>();
+
+    let num_ptxs = groups.len();
+    let mut ptxs = Vec::with_capacity(num_ptxs);
+    for (index, group) in groups.into_iter().enumerate() {
+        let mut builder = ShieldedPartialTransactionBuilder::new();
+
+        // The intent is the first input, the cascade resource is checked to be the second one
+        if index > 0 {
+            let cascade_resource_cm = cascade_resource_cms[index - 1];
+            let intent_resource = create_intent_resource(&mut rng, cascade_resource_cm, owner_nk);
+            builder = builder.add_input(
+                intent_resource,
+                MerklePath::default(),
+                move |_, resource, input_resources, output_resources| {
+                    let intent_resource_logic = CascadeIntentResourceLogicCircuit {
+                        owned_resource_id: resource.get_nf().unwrap().into(),
+                        input_resources,
+                        output_resources,
+                        cascade_resource_cm,
+                    };
+                    ResourceLogics::from_application(Box::new(intent_resource_logic))
+                },
+            );
+        }
+        for (input, merkle_path) in group {
+            builder = builder.add_input(
+                *input.resource(),
+                merkle_path,
+                move |rng, _, input_resources, output_resources| {
+                    input.generate_input_token_resource_logics(
+                        rng,
+                        auth,
+                        auth_sk,
+                        input_resources,
+                        output_resources,
+                    )
+                },
+            );
+        }
+
+        if let Some(&cascade_resource_cm) = cascade_resource_cms.get(index) {
+            let intent_resource = create_intent_resource(&mut rng, cascade_resource_cm, owner_nk);
+            builder = builder.add_output(
+                intent_resource,
+                move |_, resource, input_resources, output_resources| {
+                    let intent_resource_logic = CascadeIntentResourceLogicCircuit {
+                        owned_resource_id: resource.commitment().into(),
+                        input_resources,
+                        output_resources,
+                        cascade_resource_cm,
+                    };
+                    ResourceLogics::from_application(Box::new(intent_resource_logic))
+                },
+            );
+        } else {
+            let output = token(quantity).create_random_output_token_resource(
+                &mut rng,
+                receiver_npk,
+                &receiver_auth,
+            );
+            builder = add_token_output(builder, output, receiver_auth);
+            if change > 0 {
+                let change = token(change).create_random_output_token_resource(
+                    &mut rng,
+                    template.get_npk(),
+                    &auth,
+                );
+                builder = add_token_output(builder, change, auth);
+            }
+        }
+
+        ptxs.push(builder.build(&mut rng)?);
+    }
+    Ok(ptxs)
+}
+
+/// Adds the token output to the builder, with the token resource logics of the output.
+pub(crate) fn add_token_output(
+    builder: ShieldedPartialTransactionBuilder<'static>,
+    output: TokenResource,
+    auth: TokenAuthorization,
+) -> ShieldedPartialTransactionBuilder<'static> {
+    builder.add_output(
+        *output.resource(),
+        move |rng, resource, input_resources, output_resources| {
+            // The nonce of the output resource is set in the compliance pair
+            TokenResource {
+                resource: *resource,
+                ..output
+            }
+            .generate_output_token_resource_logics(
+                rng,
+                auth,
+                input_resources,
+                output_resources,
+            )
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        circuit::resource_logic_examples::signature_verification::COMPRESSED_TOKEN_AUTH_VK,
+        constant::TAIGA_COMMITMENT_TREE_DEPTH,
+        transaction::{ShieldedPartialTxBundle, Transaction, TransparentPartialTxBundle},
+    };
+    use ff::Field;
+    use rand::rngs::OsRng;
+
+    fn token_inputs(
+        mut rng: impl RngCore,
+        name: &str,
+        quantities: &[u64],
+        nk: pallas::Base,
+        auth: &TokenAuthorization,
+    ) -> Vec<(TokenResource, MerklePath)> {
+        quantities
+            .iter()
+            .map(|quantity| {
+                let input = Token::new(name.to_string(), *quantity)
+                    .create_random_input_token_resource(&mut rng, nk, auth);
+                let merkle_path = MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH);
+                (input, merkle_path)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_select_token_inputs() {
+        let mut rng = OsRng;
+        let nk = pallas::Base::random(&mut rng);
+        let auth = TokenAuthorization::random(&mut rng);
+        let inputs = token_inputs(&mut rng, "BTC", &[1, 5, 2, 3], nk, &auth);
+
+        let (selected, change) = select_token_inputs(inputs.clone(), 7).unwrap();
+        let quantities = selected
+            .iter()
+            .map(|(input, _)| input.quantity)
+            .collect::<Vec<_>>();
+        assert_eq!(quantities, vec![5, 3]);
+        assert_eq!(change, 1);
+
+        assert!(matches!(
+            select_token_inputs(inputs.clone(), 12),
+            Err(TransactionError::InsufficientQuantity(11))
+        ));
+
+        let mut mixed = inputs;
+        mixed.extend(token_inputs(&mut rng, "ETH", &[1], nk, &auth));
+        assert!(matches!(
+            select_token_inputs(mixed, 1),
+            Err(TransactionError::InconsistentResourceKind)
+        ));
+    }
+
+    #[test]
+    fn test_token_merge_ptxs() {
+        let mut rng = OsRng;
+        let nk = pallas::Base::random(&mut rng);
+        let sk = pallas::Scalar::random(&mut rng);
+        let auth = TokenAuthorization::from_sk_vk(&sk, &COMPRESSED_TOKEN_AUTH_VK);
+        let inputs = token_inputs(&mut rng, "BTC", &[3, 2, 2], nk, &auth);
+
+        let receiver_sk = pallas::Scalar::random(&mut rng);
+        let receiver_auth = TokenAuthorization::from_sk_vk(&receiver_sk, &COMPRESSED_TOKEN_AUTH_VK);
+        let receiver_npk = pallas::Base::random(&mut rng);
+
+        // The three inputs don't fit in one ptx
+        let ptxs =
+            create_token_merge_ptxs(&mut rng, inputs, auth, sk, 6, receiver_npk, receiver_auth)
+                .unwrap();
+        assert_eq!(ptxs.len(), 2);
+
+        let tx = Transaction::build(
+            &mut rng,
+            ShieldedPartialTxBundle::new(ptxs),
+            TransparentPartialTxBundle::default(),
+        )
+        .unwrap();
+        tx.execute().unwrap();
+    }
+}
@@ -1,21 +1,72 @@
 /// This module consists of definitions that will eventually be incorporated into the vamp-ir library
-use pasta_curves::Fp;
-use std::collections::HashMap;
-use vamp_ir::ast::{Module, Pat, VariableId};
+use pasta_curves::{group::ff::PrimeField, Fp};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 
+#[cfg(feature = "prover")]
+use num_bigint::BigInt;
+#[cfg(feature = "prover")]
+use std::collections::HashSet;
+#[cfg(feature = "prover")]
+use vamp_ir::ast::{Module, Pat, VariableId};
+#[cfg(feature = "prover")]
 use vamp_ir::transform::collect_module_variables;
 
-#[derive(Debug)]
-pub(crate) enum VariableAssignmentError {
-    MissingAssignment(String),
+/// The problems found with the named inputs of a vamp-ir circuit, reported before proving.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct WitnessReport {
+    /// The inputs could not be read at all.
+    pub malformed: Option<String>,
+    /// Named wires required by the circuit without an assignment.
+    pub missing: Vec<String>,
+    /// Assignments that are not field elements, with the reason.
+    pub mistyped: Vec<(String, String)>,
+    /// Assignments to names the circuit doesn't have.
+    pub unexpected: Vec<String>,
+}
+
+impl WitnessReport {
+    pub fn is_empty(&self) -> bool {
+        self.malformed.is_none()
+            && self.missing.is_empty()
+            && self.mistyped.is_empty()
+            && self.unexpected.is_empty()
+    }
+
+    fn malformed(reason: impl Into<String>) -> Self {
+        Self {
+            malformed: Some(reason.into()),
+            ..Default::default()
+        }
+    }
+}
+
+impl fmt::Display for WitnessReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut problems = vec![];
+        if let Some(reason) = &self.malformed {
+            problems.push(format!("malformed inputs: {reason}"));
+        }
+        if !self.missing.is_empty() {
+            problems.push(format!("missing: {}", self.missing.join(", ")));
+        }
+        for (name, reason) in self.mistyped.iter() {
+            problems.push(format!("mistyped {name}: {reason}"));
+        }
+        if !self.unexpected.is_empty() {
+            problems.push(format!("unexpected: {}", self.unexpected.join(", ")));
+        }
+        f.write_str(&problems.join("; "))
+    }
 }
 
 /// Convert named circuit assignments to assignments of vamp-ir variableIds.
 /// Useful for calling vamp-ir Halo2Module::populate_variable_assignments
+#[cfg(feature = "prover")]
 pub(crate) fn get_circuit_assignments(
     module: &Module,
     named_assignments: &HashMap<String, Fp>,
-) -> Result<HashMap<VariableId, Fp>, VariableAssignmentError> {
+) -> Result<HashMap<VariableId, Fp>, WitnessReport> {
     let mut input_variables = HashMap::new();
     collect_module_variables(module, &mut input_variables);
     // Defined variables should not be requested from user
@@ -25,20 +76,164 @@ pub(crate) fn get_circuit_assignments(
         }
     }
 
-    input_variables
-        .iter()
-        .filter_map(|(id, expected_var)| {
-            expected_var.name.as_deref().map(|var_name| {
-                named_assignments
-                    .get(var_name)
-                    .cloned()
-                    .ok_or_else(|| VariableAssignmentError::MissingAssignment(var_name.to_string()))
-                    .map(|assignment| (*id, assignment))
-            })
-        })
-        .collect()
+    let mut report = WitnessReport::default();
+    let mut assignments = HashMap::new();
+    let mut expected_names = HashSet::new();
+    for (id, expected_var) in input_variables.iter() {
+        if let Some(var_name) = expected_var.name.as_deref() {
+            expected_names.insert(var_name);
+            match named_assignments.get(var_name) {
+                Some(assignment) => {
+                    assignments.insert(*id, *assignment);
+                }
+                None => report.missing.push(var_name.to_string()),
+            }
+        }
+    }
+    report.unexpected = named_assignments
+        .keys()
+        .filter(|name| !expected_names.contains(name.as_str()))
+        .cloned()
+        .collect();
+    report.missing.sort();
+    report.missing.dedup();
+    report.unexpected.sort();
+
+    if report.is_empty() {
+        Ok(assignments)
+    } else {
+        Err(report)
+    }
+}
+
+/// Parse the vamp-ir inputs format, a JSON object of name to integer, e.g.
+/// `{ "x": "15", "y": -3 }`. Integers can be decimal or 0x prefixed hex strings, or JSON numbers
+/// within 64 bits. A name assigned more than once keeps its last value, as in vamp-ir.
+#[cfg(feature = "prover")]
+pub(crate) fn parse_named_inputs(source: &str) -> Result<HashMap<String, Fp>, WitnessReport> {
+    let entries: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(source).map_err(|err| WitnessReport::malformed(err.to_string()))?;
+
+    let mut report = WitnessReport::default();
+    let mut assignments = HashMap::new();
+    for (name, value) in entries {
+        let assignment = match &value {
+            serde_json::Value::String(value) => parse_field_element(value),
+            serde_json::Value::Number(number) if number.is_i64() || number.is_u64() => {
+                parse_field_element(&number.to_string())
+            }
+            _ => Err(format!("expected an integer, found {value}")),
+        };
+        match assignment {
+            Ok(assignment) => {
+                assignments.insert(name, assignment);
+            }
+            Err(reason) => report.mistyped.push((name, reason)),
+        }
+    }
+
+    if report.is_empty() {
+        Ok(assignments)
+    } else {
+        Err(report)
+    }
+}
+
+/// Encode the named assignments as bytes: the number of entries, then for each entry the name
+/// length, the name and the field element repr. Entries are sorted by name.
+pub(crate) fn encode_named_assignments(assignments: &HashMap<String, Fp>) -> Vec<u8> {
+    let sorted: BTreeMap<_, _> = assignments.iter().collect();
+    let mut bytes = vec![];
+    bytes.extend((sorted.len() as u32).to_le_bytes());
+    for (name, value) in sorted {
+        bytes.extend((name.len() as u32).to_le_bytes());
+        bytes.extend(name.as_bytes());
+        bytes.extend(value.to_repr());
+    }
+    bytes
 }
 
+pub(crate) fn decode_named_assignments(
+    mut bytes: &[u8],
+) -> Result<HashMap<String, Fp>, WitnessReport> {
+    fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8], WitnessReport> {
+        if bytes.len() < len {
+            return Err(WitnessReport::malformed("unexpected end of inputs"));
+        }
+        let (head, tail) = bytes.split_at(len);
+        *bytes = tail;
+        Ok(head)
+    }
+    fn take_u32(bytes: &mut &[u8]) -> Result<usize, WitnessReport> {
+        Ok(u32::from_le_bytes(take(bytes, 4)?.try_into().unwrap()) as usize)
+    }
+
+    let mut report = WitnessReport::default();
+    let mut assignments = HashMap::new();
+    let num = take_u32(&mut bytes)?;
+    for _ in 0..num {
+        let name_len = take_u32(&mut bytes)?;
+        let name = String::from_utf8(take(&mut bytes, name_len)?.to_vec())
+            .map_err(|_| WitnessReport::malformed("input name is not utf8"))?;
+        let repr: [u8; 32] = take(&mut bytes, 32)?.try_into().unwrap();
+        match Option::<Fp>::from(Fp::from_repr(repr)) {
+            Some(value) => {
+                assignments.insert(name, value);
+            }
+            None => report
+                .mistyped
+                .push((name, "not a canonical field element".to_string())),
+        }
+    }
+    if !bytes.is_empty() {
+        report.malformed = Some("trailing bytes after the inputs".to_string());
+    }
+
+    if report.is_empty() {
+        Ok(assignments)
+    } else {
+        Err(report)
+    }
+}
+
+#[cfg(feature = "prover")]
+fn parse_field_element(value: &str) -> Result<Fp, String> {
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, value),
+    };
+    let integer = match digits.strip_prefix("0x") {
+        Some(hex) => BigInt::parse_bytes(hex.as_bytes(), 16),
+        None => BigInt::parse_bytes(digits.as_bytes(), 10),
+    }
+    .ok_or_else(|| format!("expected an integer, found {value:?}"))?;
+    // Reduced modulo p as vamp-ir's make_constant, without depending on vamp-ir
+    let magnitude = Fp::from_str_vartime(&integer.to_str_radix(10))
+        .ok_or_else(|| format!("expected an integer, found {value:?}"))?;
+    Ok(if negative { -magnitude } else { magnitude })
+}
+
+#[cfg(feature = "prover")]
 pub(crate) fn parse(unparsed_file: &str) -> Result<Module, String> {
     Module::parse(unparsed_file).map_err(|err| err.to_string())
 }
+
+#[cfg(feature = "prover")]
+#[test]
+fn test_named_inputs() {
+    let inputs = parse_named_inputs(r#"{ "x": "15", "y": -3, "z": "0x10" }"#).unwrap();
+    assert_eq!(inputs["x"], Fp::from(15));
+    assert_eq!(inputs["y"], -Fp::from(3));
+    assert_eq!(inputs["z"], Fp::from(16));
+    assert_eq!(
+        decode_named_assignments(&encode_named_assignments(&inputs)).unwrap(),
+        inputs
+    );
+
+    let report = parse_named_inputs(r#"{ "x": "1.5", "y": true, "z": 2.5 }"#).unwrap_err();
+    assert_eq!(report.mistyped.len(), 3);
+    assert!(parse_named_inputs("[1, 2]")
+        .unwrap_err()
+        .malformed
+        .is_some());
+}
@@ -0,0 +1,298 @@
+/// Explanations of MockProver failures in terms of the resource logic checks.
+///
+/// A failure only names the region or the gate it occurred in ("Constraint 0 in gate 3
+/// ('conditional equal') in Region 120 ('conditional equal: check sold token quantity')"). The
+/// regions of the resource logics are named after the checks they enforce, so the failures are
+/// translated back to the checks ("sold token quantity equality, enforced when the condition
+/// holds") to help app developers find the check that failed. The regions of `NamedCheck`s also
+/// carry a stable check id ("PF_INTENT_PRICE_RATIO"), the explanations of their failures are
+/// tagged with it so tests and users can assert on the check instead of the layout.
+use halo2_proofs::dev::{MockProver, VerifyFailure};
+use pasta_curves::pallas;
+
+// The semantic descriptions of the gates, for failures outside of a named region
+const GATE_CHECKS: &[(&str, &str)] = &[
+    (
+        "conditional equal",
+        "conditional equality: the values must be equal when the flag is set",
+    ),
+    (
+        "conditional select",
+        "conditional selection between two values",
+    ),
+    (
+        "get is_input_resource_flag",
+        "the owned resource must be one of the input or output resources",
+    ),
+    (
+        "get owned resource variable",
+        "the variable must belong to the owned resource",
+    ),
+    (
+        "Compose is_ephemeral and quantity",
+        "resource commitment: is_ephemeral, is_transparent_kind and quantity encoding",
+    ),
+    (
+        "transparent kind",
+        "the kind of a transparent-kind resource must be published, zeros otherwise",
+    ),
+    ("merkle path check", "merkle path of the input resource"),
+    ("extended or relation", "or relation between two tokens"),
+];
+
+/// A check of a resource logic: the namespace of the region enforcing it and a stable id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NamedCheck {
+    pub namespace: &'static str,
+    pub id: &'static str,
+}
+
+impl NamedCheck {
+    pub const fn new(namespace: &'static str, id: &'static str) -> Self {
+        Self { namespace, id }
+    }
+
+    /// The name of the region enforcing the check: the namespace tagged with the id.
+    pub fn region_name(&self) -> String {
+        format!("{} [{}]", self.namespace, self.id)
+    }
+}
+
+/// Splits a region name into the namespace and the check id, see `NamedCheck::region_name`.
+fn split_check_id(region: &str) -> (&str, Option<&str>) {
+    match region
+        .strip_suffix(']')
+        .and_then(|region| region.rsplit_once(" ["))
+    {
+        Some((namespace, id)) => (namespace, Some(id)),
+        None => (region, None),
+    }
+}
+
+/// Returns the check id the explanation is tagged with, if the failed check is a `NamedCheck`.
+pub fn check_id(explanation: &str) -> Option<&str> {
+    explanation
+        .strip_prefix('[')?
+        .split_once(']')
+        .map(|(id, _)| id)
+}
+
+/// Returns the region name of the failure, if the failure is located in a region.
+fn region_name(failure: &VerifyFailure) -> Option<String> {
+    let description = failure.to_string();
+    let start = description.find("Region ")?;
+    let region = &description[start..];
+    let name_start = region.find("('")? + 2;
+    let name_end = region[name_start..].find("')")? + name_start;
+    Some(region[name_start..name_end].to_string())
+}
+
+/// Returns the gate name of the failure, if the failure is a gate constraint.
+fn gate_name(failure: &VerifyFailure) -> Option<String> {
+    let description = failure.to_string();
+    let start = description.find("in gate ")?;
+    let gate = &description[start..];
+    let name_start = gate.find("('")? + 2;
+    let name_end = gate[name_start..].find("')")? + name_start;
+    Some(gate[name_start..name_end].to_string())
+}
+
+/// Translates a region name to the check it enforces.
+fn region_check(region: &str) -> String {
+    if let Some(check) = region.strip_prefix("conditional equal: ") {
+        let check = check.strip_prefix("check ").unwrap_or(check);
+        format!("{check} equality, enforced when the condition holds")
+    } else if let Some(check) = region.strip_prefix("conditional select: ") {
+        format!("selection of {check}")
+    } else if region.contains("bool check") {
+        format!("boolean flag check ({region})")
+    } else if region.contains("range check") {
+        format!("range check ({region})")
+    } else if let Some(check) = region.strip_prefix("check ") {
+        format!("{check} check")
+    } else {
+        region.to_string()
+    }
+}
+
+/// Explains the failure with the check behind its region or gate, tagged with the check id of a
+/// `NamedCheck` ("[PF_INTENT_PRICE_RATIO] ...").
+pub fn explain_failure(failure: &VerifyFailure) -> String {
+    let region = region_name(failure);
+    let (namespace, id) = match region.as_deref() {
+        Some(region) => {
+            let (namespace, id) = split_check_id(region);
+            (Some(namespace), id)
+        }
+        None => (None, None),
+    };
+    let check = match (namespace, gate_name(failure)) {
+        (Some(namespace), _) => region_check(namespace),
+        (None, Some(gate)) => GATE_CHECKS
+            .iter()
+            .find(|(name, _)| *name == gate)
+            .map(|(_, check)| check.to_string())
+            .unwrap_or(gate),
+        (None, None) => "unnamed check".to_string(),
+    };
+    let check = match id {
+        Some(id) => format!("[{id}] {check}"),
+        None => check,
+    };
+    match failure {
+        VerifyFailure::Permutation { .. } => {
+            format!("{check}: a copied cell differs from its copy [{failure}]")
+        }
+        VerifyFailure::Lookup { .. } => format!("{check}: lookup failed [{failure}]"),
+        VerifyFailure::CellNotAssigned { .. } => {
+            format!("{check}: a queried cell is not assigned [{failure}]")
+        }
+        _ => format!("{check} failed [{failure}]"),
+    }
+}
+
+/// Explains each failure of a MockProver verification.
+pub fn explain_failures(failures: &[VerifyFailure]) -> Vec<String> {
+    failures.iter().map(explain_failure).collect()
+}
+
+/// Verifies the MockProver, with the explained failures as the error.
+pub fn verify_with_explanations(prover: &MockProver<pallas::Base>) -> Result<(), Vec<String>> {
+    prover
+        .verify()
+        .map_err(|failures| explain_failures(&failures))
+}
+
+/// Asserts the MockProver verifies, panics with the explained failures otherwise.
+pub fn assert_verifies(prover: &MockProver<pallas::Base>) {
+    if let Err(explanations) = verify_with_explanations(prover) {
+        panic!(
+            "the circuit is not satisfied:\n  {}",
+            explanations.join("\n  ")
+        );
+    }
+}
+
+#[test]
+fn test_region_check_explanations() {
+    assert_eq!(
+        region_check("conditional equal: check sold token quantity"),
+        "sold token quantity equality, enforced when the condition holds"
+    );
+    assert_eq!(
+        region_check("conditional select: left"),
+        "selection of left"
+    );
+    assert_eq!(
+        region_check("is_left bool check"),
+        "boolean flag check (is_left bool check)"
+    );
+    assert_eq!(region_check("check label"), "label check");
+    assert_eq!(region_check("load private"), "load private");
+
+    let check = NamedCheck::new(
+        "conditional equal: check price ratio",
+        "PF_INTENT_PRICE_RATIO",
+    );
+    assert_eq!(
+        split_check_id(&check.region_name()),
+        (check.namespace, Some(check.id))
+    );
+    assert_eq!(split_check_id("check label"), ("check label", None));
+    assert_eq!(
+        check_id("[PF_INTENT_PRICE_RATIO] price ratio equality failed [..]"),
+        Some("PF_INTENT_PRICE_RATIO")
+    );
+    assert_eq!(check_id("label check failed [..]"), None);
+}
+
+#[test]
+fn test_explain_mock_prover_failures() {
+    use crate::circuit::gadgets::boolean::AssignedBool;
+    use crate::circuit::gadgets::{
+        assign_free_advice,
+        conditional_equal::ConditionalEqualConfig,
+        mul::{MulChip, MulConfig},
+    };
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{Advice, Circuit, Column, ConstraintSystem, Error},
+    };
+
+    #[derive(Default)]
+    struct MyCircuit {
+        lhs: pallas::Base,
+        rhs: pallas::Base,
+    }
+
+    impl Circuit<pallas::Base> for MyCircuit {
+        type Config = ([Column<Advice>; 3], ConditionalEqualConfig, MulConfig);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            let advices = [
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+            ];
+            for advice in advices.iter() {
+                meta.enable_equality(*advice);
+            }
+            let conditional_equal_config = ConditionalEqualConfig::configure(meta, advices);
+            let mul_config = MulChip::configure(meta, [advices[0], advices[1]]);
+            (advices, conditional_equal_config, mul_config)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), Error> {
+            let (advices, conditional_equal_config, mul_config) = config;
+            let mul_chip = MulChip::construct(mul_config);
+            let flag = assign_free_advice(
+                layouter.namespace(|| "witness flag"),
+                advices[0],
+                Value::known(pallas::Base::one()),
+            )?;
+            let flag = AssignedBool::assert_bool(&mul_chip, layouter.namespace(|| "flag"), &flag)?;
+            let lhs = assign_free_advice(
+                layouter.namespace(|| "witness lhs"),
+                advices[1],
+                Value::known(self.lhs),
+            )?;
+            let rhs = assign_free_advice(
+                layouter.namespace(|| "witness rhs"),
+                advices[2],
+                Value::known(self.rhs),
+            )?;
+            layouter.assign_region(
+                || "conditional equal: check sold token quantity",
+                |mut region| {
+                    conditional_equal_config.assign_region(&flag, &lhs, &rhs, 0, &mut region)
+                },
+            )?;
+            Ok(())
+        }
+    }
+
+    let circuit = MyCircuit {
+        lhs: pallas::Base::one(),
+        rhs: pallas::Base::one(),
+    };
+    let prover = MockProver::<pallas::Base>::run(4, &circuit, vec![]).unwrap();
+    assert_verifies(&prover);
+
+    let circuit = MyCircuit {
+        lhs: pallas::Base::one(),
+        rhs: pallas::Base::zero(),
+    };
+    let prover = MockProver::<pallas::Base>::run(4, &circuit, vec![]).unwrap();
+    let explanations = verify_with_explanations(&prover).unwrap_err();
+    assert!(explanations.iter().any(|explanation| explanation
+        .starts_with("sold token quantity equality, enforced when the condition holds")));
+}
@@ -0,0 +1,240 @@
+#[cfg(feature = "circuit-report")]
+use crate::{
+    circuit::constraint_report::{row_utilization, RowUtilization},
+    constant::{COMPLIANCE_CIRCUIT_PARAMS_SIZE, COMPLIANCE_VERIFYING_KEY, DEFAULT_PARAMS_STORE},
+};
+/// Per-circuit efficiency reports: the rows used against the rows available at the chosen `k`,
+/// the proving and verification times and the serialized sizes of the proof and the vk.
+///
+/// With the `circuit-report` feature, the proofs created between `start_recording` and
+/// `finish_recording` are recorded: the compliance proofs and the resource logic proofs of the
+/// partial transaction builders. Each recorded proof is verified once more to time the
+/// verification, so the recording slows the proving down. The report is exported as JSON to
+/// track the efficiency of the circuits over releases, the tx examples write it to the path in
+/// `TAIGA_CIRCUIT_REPORT`. Without the feature the proofs are created as is.
+use crate::{
+    circuit::resource_logic_circuit::{ResourceLogic, ResourceLogicVerifyingInfo},
+    compliance::ComplianceInfo,
+    shielded_ptx::ComplianceVerifyingInfo,
+};
+#[cfg(feature = "circuit-report")]
+use lazy_static::lazy_static;
+#[cfg(feature = "circuit-report")]
+use serde::Serialize;
+#[cfg(feature = "circuit-report")]
+use std::{path::Path, sync::Mutex, time::Instant};
+
+/// The report of a proof.
+#[cfg(feature = "circuit-report")]
+#[derive(Clone, Debug, Serialize)]
+pub struct CircuitReport {
+    /// "Compliance", or the registered name of the logic, e.g. "TokenV2"
+    pub name: String,
+    pub k: u32,
+    /// None for the resource logics that don't measure their rows
+    pub rows_used: Option<usize>,
+    /// The rows of `2^k` above the blinding rows
+    pub rows_available: Option<usize>,
+    /// The proving time, the first proof of a circuit type also generates the proving key
+    pub proving_time_us: u64,
+    pub verifying_time_us: u64,
+    pub verified: bool,
+    pub proof_size: usize,
+    pub vk_size: usize,
+}
+
+#[cfg(feature = "circuit-report")]
+impl CircuitReport {
+    /// The share of the available rows the circuit uses, if its rows were measured.
+    pub fn utilization(&self) -> Option<f64> {
+        Some(self.rows_used? as f64 / self.rows_available? as f64)
+    }
+}
+
+#[cfg(feature = "circuit-report")]
+#[derive(Clone, Debug, Serialize)]
+pub struct CircuitEfficiencyReport {
+    /// The version of the crate the circuits were proved with
+    pub version: &'static str,
+    pub circuits: Vec<CircuitReport>,
+}
+
+#[cfg(feature = "circuit-report")]
+impl Default for CircuitEfficiencyReport {
+    fn default() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            circuits: vec![],
+        }
+    }
+}
+
+#[cfg(feature = "circuit-report")]
+impl CircuitEfficiencyReport {
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn write_json<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let json = self.to_json().map_err(std::io::Error::from)?;
+        std::fs::write(path, json)
+    }
+}
+
+// The report being recorded, the proofs of all the threads are recorded
+#[cfg(feature = "circuit-report")]
+lazy_static! {
+    static ref RECORDING: Mutex<Option<CircuitEfficiencyReport>> = Mutex::new(None);
+}
+
+/// Starts recording the proofs, a report being recorded is dropped.
+#[cfg(feature = "circuit-report")]
+pub fn start_recording() {
+    *RECORDING.lock().unwrap() = Some(CircuitEfficiencyReport::default());
+}
+
+/// Stops recording and returns the report, if the recording was started.
+#[cfg(feature = "circuit-report")]
+pub fn finish_recording() -> Option<CircuitEfficiencyReport> {
+    RECORDING.lock().unwrap().take()
+}
+
+#[cfg(feature = "circuit-report")]
+fn is_recording() -> bool {
+    RECORDING.lock().unwrap().is_some()
+}
+
+#[cfg(feature = "circuit-report")]
+fn record(report: CircuitReport) {
+    if let Some(recording) = RECORDING.lock().unwrap().as_mut() {
+        recording.circuits.push(report);
+    }
+}
+
+/// Runs the proving of a compliance unit, the proof is recorded when recording.
+pub(crate) fn measure_compliance<E>(
+    compliance_info: &ComplianceInfo,
+    prove: impl FnOnce() -> Result<ComplianceVerifyingInfo, E>,
+) -> Result<ComplianceVerifyingInfo, E> {
+    #[cfg(feature = "circuit-report")]
+    {
+        if !is_recording() {
+            return prove();
+        }
+        let start = Instant::now();
+        let verifying_info = prove()?;
+        let proving_time = start.elapsed();
+        let start = Instant::now();
+        let verified = verifying_info.verify().is_ok();
+        let verifying_time = start.elapsed();
+
+        let (_, circuit) = compliance_info.build();
+        let RowUtilization {
+            k,
+            rows_used,
+            rows_available,
+        } = row_utilization(&circuit, COMPLIANCE_CIRCUIT_PARAMS_SIZE)
+            .expect("the compliance circuit was just proved");
+        record(CircuitReport {
+            name: "Compliance".to_string(),
+            k,
+            rows_used: Some(rows_used),
+            rows_available: Some(rows_available),
+            proving_time_us: proving_time.as_micros() as u64,
+            verifying_time_us: verifying_time.as_micros() as u64,
+            verified,
+            proof_size: verifying_info.get_compliance_proof().inner().len(),
+            vk_size: COMPLIANCE_VERIFYING_KEY.to_bytes().len(),
+        });
+        Ok(verifying_info)
+    }
+    #[cfg(not(feature = "circuit-report"))]
+    {
+        let _ = compliance_info;
+        prove()
+    }
+}
+
+/// Runs the proving of a resource logic, the proof is recorded when recording.
+pub(crate) fn measure_resource_logic<E>(
+    resource_logic: &ResourceLogic,
+    prove: impl FnOnce() -> Result<ResourceLogicVerifyingInfo, E>,
+) -> Result<ResourceLogicVerifyingInfo, E> {
+    #[cfg(feature = "circuit-report")]
+    {
+        if !is_recording() {
+            return prove();
+        }
+        let start = Instant::now();
+        let verifying_info = prove()?;
+        let proving_time = start.elapsed();
+        let start = Instant::now();
+        let verified = DEFAULT_PARAMS_STORE
+            .get(verifying_info.circuit_size.k)
+            .map_or(false, |params| {
+                verifying_info.verify_with_params(&params).is_ok()
+            });
+        let verifying_time = start.elapsed();
+
+        let row_utilization = resource_logic.row_utilization();
+        record(CircuitReport {
+            name: verifying_info.get_logic_name(),
+            k: verifying_info.circuit_size.k,
+            rows_used: row_utilization.map(|rows| rows.rows_used),
+            rows_available: row_utilization.map(|rows| rows.rows_available),
+            proving_time_us: proving_time.as_micros() as u64,
+            verifying_time_us: verifying_time.as_micros() as u64,
+            verified,
+            proof_size: verifying_info.proof.inner().len(),
+            vk_size: verifying_info.vk.to_bytes().len(),
+        });
+        Ok(verifying_info)
+    }
+    #[cfg(not(feature = "circuit-report"))]
+    {
+        let _ = resource_logic;
+        prove()
+    }
+}
+
+#[cfg(all(test, feature = "circuit-report"))]
+#[test]
+fn test_circuit_report() {
+    use crate::{constant::NUM_RESOURCE, shielded_ptx::testing::create_shielded_ptx};
+
+    start_recording();
+    create_shielded_ptx();
+    let report = finish_recording().unwrap();
+    assert!(finish_recording().is_none());
+
+    // The compliance units and the application resource logics of the ptx at least, the tests
+    // proving in parallel are recorded too
+    let compliances = report
+        .circuits
+        .iter()
+        .filter(|circuit| circuit.name == "Compliance")
+        .count();
+    assert!(compliances >= NUM_RESOURCE);
+    let verified = report
+        .circuits
+        .iter()
+        .filter(|circuit| circuit.verified)
+        .count();
+    assert!(verified >= 3 * NUM_RESOURCE);
+    for circuit in report.circuits.iter() {
+        // The logics of the crate measure their rows
+        let (rows_used, rows_available) =
+            (circuit.rows_used.unwrap(), circuit.rows_available.unwrap());
+        assert!(rows_used > 0);
+        assert!(circuit.utilization().unwrap() <= 1.0);
+        assert!(rows_available < 1 << circuit.k);
+        assert!(circuit.proof_size > 0 && circuit.vk_size > 0);
+    }
+
+    let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+    assert_eq!(
+        json["circuits"].as_array().unwrap().len(),
+        report.circuits.len()
+    );
+    assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+}
@@ -7,11 +7,13 @@ use crate::{
     circuit::compliance_circuit::ComplianceCircuit,
     constant::{PRF_EXPAND_INPUT_RESOURCE_LOGIC_CM_R, PRF_EXPAND_OUTPUT_RESOURCE_LOGIC_CM_R},
     delta_commitment::DeltaCommitment,
-    merkle_tree::{Anchor, MerklePath},
+    error::TransactionError,
+    merkle_tree::{Anchor, MerklePath, WitnessTracker},
     nullifier::Nullifier,
-    resource::{RandomSeed, Resource, ResourceCommitment},
+    resource::{RandomSeed, Resource, ResourceCommitment, ResourceKind},
     resource_logic_commitment::ResourceLogicCommitment,
 };
+use ff::Field;
 use pasta_curves::pallas;
 use rand::RngCore;
 
@@ -23,6 +25,8 @@ use serde;
 
 #[cfg(feature = "borsh")]
 use borsh::{BorshDeserialize, BorshSerialize};
+#[cfg(feature = "borsh")]
+use ff::PrimeField;
 
 /// The public inputs of compliance proof.
 #[derive(Debug, Clone)]
@@ -42,6 +46,12 @@ pub struct CompliancePublicInputs {
     pub input_resource_logic_commitment: ResourceLogicCommitment,
     /// The commitment to output resource logic
     pub output_resource_logic_commitment: ResourceLogicCommitment,
+    /// The kind of the input resource, disclosed when it's a transparent-kind resource. The
+    /// is_transparent_kind flag of the resource is public too, Some iff the flag is set.
+    pub input_transparent_kind: Option<ResourceKind>,
+    /// The kind of the output resource, disclosed when it's a transparent-kind resource. The
+    /// is_transparent_kind flag of the resource is public too, Some iff the flag is set.
+    pub output_transparent_kind: Option<ResourceKind>,
 }
 
 /// The information to build CompliancePublicInputs and ComplianceCircuit.
@@ -50,12 +60,31 @@ pub struct CompliancePublicInputs {
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 pub struct ComplianceInfo {
     input_resource: Resource,
-    input_merkle_path: MerklePath,
+    // None until the merkle path is bound, see `new_pending`. The borsh layout is the one of a
+    // bound path, a pending path is written as the empty path.
+    #[cfg_attr(
+        feature = "borsh",
+        borsh(
+            serialize_with = "borsh_serialize_merkle_path",
+            deserialize_with = "borsh_deserialize_merkle_path"
+        )
+    )]
+    input_merkle_path: Option<MerklePath>,
     input_anchor: Anchor,
     output_resource: Resource,
     // rseed is to generate the randomness of the delta commitment and resource
     // logic commitments
     rseed: RandomSeed,
+    // The randomness of the delta commitment when it's set explicitly instead of derived from
+    // rseed, see `disclose_delta_blinding`
+    #[cfg_attr(
+        feature = "borsh",
+        borsh(
+            serialize_with = "borsh_serialize_rcv",
+            deserialize_with = "borsh_deserialize_rcv"
+        )
+    )]
+    rcv: Option<pallas::Scalar>,
 }
 
 impl CompliancePublicInputs {
@@ -64,6 +93,9 @@ impl CompliancePublicInputs {
             self.input_resource_logic_commitment.to_public_inputs();
         let output_resource_logic_commitment =
             self.output_resource_logic_commitment.to_public_inputs();
+        // The circuit publishes zeros for the kinds of the other resources
+        let input_transparent_kind = self.input_transparent_kind.unwrap_or_default();
+        let output_transparent_kind = self.output_transparent_kind.unwrap_or_default();
         vec![
             self.nf.inner(),
             self.anchor.inner(),
@@ -74,6 +106,12 @@ impl CompliancePublicInputs {
             input_resource_logic_commitment[1],
             output_resource_logic_commitment[0],
             output_resource_logic_commitment[1],
+            input_transparent_kind.logic,
+            input_transparent_kind.label,
+            output_transparent_kind.logic,
+            output_transparent_kind.label,
+            pallas::Base::from(self.input_transparent_kind.is_some()),
+            pallas::Base::from(self.output_transparent_kind.is_some()),
         ]
     }
 }
@@ -87,6 +125,8 @@ impl BorshSerialize for CompliancePublicInputs {
         writer.write_all(&self.delta.to_bytes())?;
         writer.write_all(&self.input_resource_logic_commitment.to_bytes())?;
         writer.write_all(&self.output_resource_logic_commitment.to_bytes())?;
+        borsh_serialize_transparent_kind(&self.input_transparent_kind, writer)?;
+        borsh_serialize_transparent_kind(&self.output_transparent_kind, writer)?;
         Ok(())
     }
 }
@@ -113,6 +153,8 @@ impl BorshDeserialize for CompliancePublicInputs {
         let output_resource_logic_commitment_bytes = <[u8; 32]>::deserialize_reader(reader)?;
         let output_resource_logic_commitment =
             ResourceLogicCommitment::from_bytes(output_resource_logic_commitment_bytes);
+        let input_transparent_kind = borsh_deserialize_transparent_kind(reader)?;
+        let output_transparent_kind = borsh_deserialize_transparent_kind(reader)?;
 
         Ok(CompliancePublicInputs {
             anchor,
@@ -121,6 +163,8 @@ impl BorshDeserialize for CompliancePublicInputs {
             delta,
             input_resource_logic_commitment,
             output_resource_logic_commitment,
+            input_transparent_kind,
+            output_transparent_kind,
         })
     }
 }
@@ -145,16 +189,91 @@ impl ComplianceInfo {
 
         Self {
             input_resource,
-            input_merkle_path,
+            input_merkle_path: Some(input_merkle_path),
             input_anchor,
             output_resource: *output_resource,
             rseed: RandomSeed::random(&mut rng),
+            rcv: None,
+        }
+    }
+
+    // Defer the binding of the merkle path and the anchor, e.g. the input resource is not yet
+    // confirmed in the commitment tree. Everything else is set: the nullifier, the output
+    // resource nonce and the randomness, so the resource logics and the intents built on the
+    // resources don't change when the path is bound with `finalize_with_path` before proving.
+    pub fn new_pending<R: RngCore>(
+        input_resource: Resource,
+        output_resource: &mut Resource,
+        mut rng: R,
+    ) -> Self {
+        output_resource.set_nonce(&input_resource);
+
+        Self {
+            input_resource,
+            input_merkle_path: None,
+            input_anchor: Anchor::from(pallas::Base::zero()),
+            output_resource: *output_resource,
+            rseed: RandomSeed::random(&mut rng),
+            rcv: None,
+        }
+    }
+
+    // Bind the merkle path and the anchor like `new`, once the input resource is confirmed.
+    pub fn finalize_with_path(
+        &mut self,
+        input_merkle_path: MerklePath,
+        custom_anchor: Option<Anchor>,
+    ) {
+        self.input_anchor = match custom_anchor {
+            Some(anchor) => anchor,
+            None => self.input_resource.calculate_root(&input_merkle_path),
+        };
+        self.input_merkle_path = Some(input_merkle_path);
+    }
+
+    // The merkle path of the input resource is not bound yet, the compliance can't be proven.
+    pub fn is_pending(&self) -> bool {
+        self.input_merkle_path.is_none()
+    }
+
+    // Replace the merkle path of the input resource, e.g. after the commitment tree grew. The
+    // anchor is recalculated, the randomness is kept so the delta commitment doesn't change.
+    pub fn set_input_merkle_path(&mut self, input_merkle_path: MerklePath) {
+        self.finalize_with_path(input_merkle_path, None);
+    }
+
+    // Refresh the anchor with the merkle path of the input resource from the tracker. The anchor
+    // of an ephemeral input resource is not checked against a path, it's set to `latest_anchor`.
+    pub fn refresh_input_anchor(
+        &mut self,
+        tracker: &impl WitnessTracker,
+        latest_anchor: Anchor,
+    ) -> Result<(), TransactionError> {
+        if self.input_resource.is_ephemeral {
+            self.input_anchor = latest_anchor;
+            self.input_merkle_path
+                .get_or_insert_with(MerklePath::default);
+            return Ok(());
         }
+        let input_merkle_path = tracker
+            .merkle_path(&self.input_resource.commitment())
+            .ok_or(TransactionError::UntrackedResource)?;
+        self.set_input_merkle_path(input_merkle_path);
+        Ok(())
+    }
+
+    pub fn get_input_anchor(&self) -> Anchor {
+        self.input_anchor
     }
 
     // Get the randomness of delta commitment
     pub fn get_rcv(&self) -> pallas::Scalar {
-        self.rseed.get_rcv()
+        self.rcv.unwrap_or_else(|| self.rseed.get_rcv())
+    }
+
+    // Set the randomness of delta commitment explicitly
+    pub fn set_rcv(&mut self, rcv: pallas::Scalar) {
+        self.rcv = Some(rcv);
     }
 
     // Get the randomness of input resource application resource logic commitment
@@ -170,8 +289,12 @@ impl ComplianceInfo {
     }
 
     // Only used in transparent scenario: the anchor is untrusted, recalculate root when executing it transparently.
+    // The placeholder anchor is returned while the merkle path is pending.
     pub fn calculate_root(&self) -> Anchor {
-        self.input_resource.calculate_root(&self.input_merkle_path)
+        match &self.input_merkle_path {
+            Some(input_merkle_path) => self.input_resource.calculate_root(input_merkle_path),
+            None => self.input_anchor,
+        }
     }
 
     // Get delta commitment
@@ -179,6 +302,19 @@ impl ComplianceInfo {
         DeltaCommitment::commit(&self.input_resource, &self.output_resource, blind_r)
     }
 
+    // Get the merkle path of the input resource, None while it's pending
+    pub fn get_input_merkle_path(&self) -> Option<&MerklePath> {
+        self.input_merkle_path.as_ref()
+    }
+
+    pub fn get_input_resource(&self) -> &Resource {
+        &self.input_resource
+    }
+
+    pub fn get_output_resource(&self) -> &Resource {
+        &self.output_resource
+    }
+
     pub fn get_input_resource_nullifier(&self) -> Nullifier {
         self.input_resource.get_nf().unwrap()
     }
@@ -187,6 +323,23 @@ impl ComplianceInfo {
         self.output_resource.commitment()
     }
 
+    // The checks of the compliance circuit that apply to transparent resources. The nullifier,
+    // commitment and delta are computed from the plaintext resources and the anchor is
+    // recalculated, what's left is the link between the input and the output resource.
+    pub fn verify_transparently(&self) -> Result<(), TransactionError> {
+        if self.is_pending() {
+            return Err(TransactionError::PendingMerklePath);
+        }
+        let nf = self
+            .input_resource
+            .get_nf()
+            .ok_or(TransactionError::MissingTransparentResourceNullifierKey)?;
+        if nf != self.output_resource.nonce {
+            return Err(TransactionError::InconsistentOutputResourceNonce);
+        }
+        Ok(())
+    }
+
     pub fn build(&self) -> (CompliancePublicInputs, ComplianceCircuit) {
         let nf = self.get_input_resource_nullifier();
         assert_eq!(
@@ -218,11 +371,19 @@ impl ComplianceInfo {
             delta,
             input_resource_logic_commitment,
             output_resource_logic_commitment,
+            input_transparent_kind: self.input_resource.get_transparent_kind(),
+            output_transparent_kind: self.output_resource.get_transparent_kind(),
         };
 
         let compliance_circuit = ComplianceCircuit {
             input_resource: self.input_resource,
-            merkle_path: self.input_merkle_path.get_path().try_into().unwrap(),
+            merkle_path: self
+                .input_merkle_path
+                .as_ref()
+                .expect("The merkle path of the input resource must be bound before proving")
+                .get_path()
+                .try_into()
+                .unwrap(),
             output_resource: self.output_resource,
             rcv,
             input_resource_logic_cm_r,
@@ -233,12 +394,87 @@ impl ComplianceInfo {
     }
 }
 
+/// Sets the randomness of the delta commitments of a ptx to sum to the disclosed value (e.g.
+/// zero), by adjusting the randomness of the last compliance. Knowing the disclosed value,
+/// regulators or counterparties can check the net per-kind delta of the ptx with
+/// `verify_disclosed_delta`, without a binding signature. The delta commitments of the ptx are no
+/// longer hiding to them.
+pub fn disclose_delta_blinding(compliances: &mut [ComplianceInfo], disclosed_r: pallas::Scalar) {
+    if let Some((last, others)) = compliances.split_last_mut() {
+        let others_sum: pallas::Scalar = others.iter().map(|info| info.get_rcv()).sum();
+        last.set_rcv(disclosed_r - others_sum);
+    }
+}
+
+// No compliance is proven with the empty path, it marks the pending paths without changing the
+// layout of the bound ones
+#[cfg(feature = "borsh")]
+fn borsh_serialize_merkle_path<W: std::io::Write>(
+    merkle_path: &Option<MerklePath>,
+    writer: &mut W,
+) -> std::io::Result<()> {
+    match merkle_path {
+        Some(merkle_path) => merkle_path.serialize(writer),
+        None => MerklePath::from_path(vec![]).serialize(writer),
+    }
+}
+
+#[cfg(feature = "borsh")]
+fn borsh_deserialize_merkle_path<R: std::io::Read>(
+    reader: &mut R,
+) -> std::io::Result<Option<MerklePath>> {
+    let merkle_path = MerklePath::deserialize_reader(reader)?;
+    Ok((merkle_path != MerklePath::from_path(vec![])).then_some(merkle_path))
+}
+
+#[cfg(feature = "borsh")]
+fn borsh_serialize_rcv<W: std::io::Write>(
+    rcv: &Option<pallas::Scalar>,
+    writer: &mut W,
+) -> std::io::Result<()> {
+    rcv.map(|rcv| rcv.to_repr()).serialize(writer)
+}
+
+#[cfg(feature = "borsh")]
+fn borsh_deserialize_rcv<R: std::io::Read>(
+    reader: &mut R,
+) -> std::io::Result<Option<pallas::Scalar>> {
+    Option::<[u8; 32]>::deserialize_reader(reader)?
+        .map(|bytes| {
+            Option::from(pallas::Scalar::from_repr(bytes)).ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "rcv not in field")
+            })
+        })
+        .transpose()
+}
+
+#[cfg(feature = "borsh")]
+fn borsh_serialize_transparent_kind<W: std::io::Write>(
+    kind: &Option<ResourceKind>,
+    writer: &mut W,
+) -> std::io::Result<()> {
+    kind.map(|kind| kind.to_bytes()).serialize(writer)
+}
+
+#[cfg(feature = "borsh")]
+fn borsh_deserialize_transparent_kind<R: std::io::Read>(
+    reader: &mut R,
+) -> std::io::Result<Option<ResourceKind>> {
+    Option::<[u8; 64]>::deserialize_reader(reader)?
+        .map(|bytes| {
+            Option::from(ResourceKind::from_bytes(bytes)).ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "kind not in field")
+            })
+        })
+        .transpose()
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::ComplianceInfo;
     use crate::constant::TAIGA_COMMITMENT_TREE_DEPTH;
     use crate::merkle_tree::MerklePath;
-    use crate::resource::tests::random_resource;
+    use crate::resource::{tests::random_resource, RandomSeed, Resource};
     use rand::RngCore;
 
     pub fn random_compliance_info<R: RngCore>(mut rng: R) -> ComplianceInfo {
@@ -253,4 +489,121 @@ pub mod tests {
             &mut rng,
         )
     }
+
+    // Keep the nonce of the output resource as it is, to simulate a malicious prover
+    pub fn create_compliance_info_unchecked<R: RngCore>(
+        mut rng: R,
+        input_resource: Resource,
+        output_resource: Resource,
+    ) -> ComplianceInfo {
+        let input_merkle_path = MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH);
+        ComplianceInfo {
+            input_resource,
+            input_anchor: input_resource.calculate_root(&input_merkle_path),
+            input_merkle_path: Some(input_merkle_path),
+            output_resource,
+            rseed: RandomSeed::random(&mut rng),
+            rcv: None,
+        }
+    }
+
+    #[test]
+    fn pending_merkle_path_test() {
+        use crate::error::TransactionError;
+        use rand::rngs::OsRng;
+
+        let mut rng = OsRng;
+        let input_resource = random_resource(&mut rng);
+        let mut output_resource = random_resource(&mut rng);
+        let mut info = ComplianceInfo::new_pending(input_resource, &mut output_resource, &mut rng);
+        assert!(info.is_pending());
+        // The output nonce is bound before the path
+        assert_eq!(output_resource.nonce, input_resource.get_nf().unwrap());
+        assert!(matches!(
+            info.verify_transparently(),
+            Err(TransactionError::PendingMerklePath)
+        ));
+
+        let rcv = info.get_rcv();
+        let merkle_path = MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH);
+        info.finalize_with_path(merkle_path.clone(), None);
+        assert!(!info.is_pending());
+        assert_eq!(
+            info.get_input_anchor(),
+            input_resource.calculate_root(&merkle_path)
+        );
+        assert_eq!(info.calculate_root(), info.get_input_anchor());
+        // The randomness doesn't change, the delta commitment stays the same
+        assert_eq!(info.get_rcv(), rcv);
+    }
+
+    #[cfg(feature = "borsh")]
+    #[test]
+    fn pending_merkle_path_borsh_test() {
+        use borsh::BorshDeserialize;
+        use rand::rngs::OsRng;
+
+        let mut rng = OsRng;
+        let input_resource = random_resource(&mut rng);
+        let mut output_resource = random_resource(&mut rng);
+        let mut info = ComplianceInfo::new_pending(input_resource, &mut output_resource, &mut rng);
+        let bytes = borsh::to_vec(&info).unwrap();
+        assert!(ComplianceInfo::try_from_slice(&bytes).unwrap().is_pending());
+
+        // A bound path keeps the layout of the compliances without pending paths
+        let merkle_path = MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH);
+        info.finalize_with_path(merkle_path.clone(), None);
+        let bytes = borsh::to_vec(&info).unwrap();
+        let mut prefix = borsh::to_vec(&input_resource).unwrap();
+        prefix.extend(borsh::to_vec(&merkle_path).unwrap());
+        assert!(bytes.starts_with(&prefix));
+        let decoded = ComplianceInfo::try_from_slice(&bytes).unwrap();
+        assert_eq!(decoded.get_input_merkle_path(), Some(&merkle_path));
+    }
+
+    #[test]
+    fn disclosed_delta_blinding_test() {
+        use super::disclose_delta_blinding;
+        use crate::delta_commitment::{net_delta, verify_disclosed_delta};
+        use halo2_proofs::arithmetic::Field;
+        use pasta_curves::pallas;
+        use rand::rngs::OsRng;
+
+        let mut rng = OsRng;
+        let mut compliances: Vec<_> = (0..2).map(|_| random_compliance_info(&mut rng)).collect();
+        let kind_deltas: Vec<_> = compliances
+            .iter()
+            .flat_map(|info| {
+                [
+                    (
+                        info.input_resource.get_kind(),
+                        info.input_resource.quantity as i128,
+                    ),
+                    (
+                        info.output_resource.get_kind(),
+                        -(info.output_resource.quantity as i128),
+                    ),
+                ]
+            })
+            .collect();
+        let expected_net_delta = net_delta(kind_deltas.clone());
+
+        for disclosed_r in [pallas::Scalar::zero(), pallas::Scalar::random(&mut rng)] {
+            disclose_delta_blinding(&mut compliances, disclosed_r);
+            let rcv_sum: pallas::Scalar = compliances.iter().map(|info| info.get_rcv()).sum();
+            assert_eq!(rcv_sum, disclosed_r);
+
+            let deltas: Vec<_> = compliances
+                .iter()
+                .map(|info| info.get_delta_commitment(&info.get_rcv()))
+                .collect();
+            assert!(verify_disclosed_delta(&deltas, &disclosed_r, &expected_net_delta).is_ok());
+
+            // Another net delta or disclosed value doesn't open
+            let other_net_delta = net_delta(kind_deltas[1..].to_vec());
+            assert!(verify_disclosed_delta(&deltas, &disclosed_r, &other_net_delta).is_err());
+            let other_r = disclosed_r + pallas::Scalar::one();
+            assert!(verify_disclosed_delta(&deltas, &other_r, &expected_net_delta).is_err());
+        }
+    }
 }
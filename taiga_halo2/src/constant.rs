@@ -1,5 +1,9 @@
 use crate::circuit::compliance_circuit::ComplianceCircuit;
-use crate::utils::to_field_elements;
+use crate::params_store::{ParamsGenerationPolicy, ParamsStore};
+#[cfg(feature = "prover")]
+use crate::proof::ProvingKey;
+use crate::proof::VerifyingKey;
+use crate::utils::{hash_to_field, to_field_elements, HashToCurveDomain, HashToFieldMethod};
 use group::Group;
 use halo2_gadgets::{
     ecc::{
@@ -11,19 +15,19 @@ use halo2_gadgets::{
     },
     sinsemilla::{primitives::CommitDomain, CommitDomains, HashDomains},
 };
-use halo2_proofs::{
-    plonk::{keygen_pk, keygen_vk, ProvingKey, VerifyingKey},
-    poly::commitment::Params,
-};
+#[cfg(feature = "prover")]
+use halo2_proofs::plonk::keygen_pk;
+use halo2_proofs::plonk::keygen_vk;
 use lazy_static::lazy_static;
-use pasta_curves::{group::Curve, pallas, vesta};
-use std::collections::HashMap;
+use pasta_curves::{group::Curve, pallas};
 
 /// SWU hash-to-curve personalization for the resource commitment generator
 pub const RESOURCE_COMMITMENT_PERSONALIZATION: &str = "Taiga-NoteCommit";
 
 pub const TRANSACTION_BINDING_HASH_PERSONALIZATION: &[u8; 16] = b"TxBindingSigHash";
 
+pub const TRANSACTION_ID_PERSONALIZATION: &[u8; 16] = b"Taiga_TxId______";
+
 pub const RESOURCE_LOGIC_COMMITMENT_PERSONALIZATION: &[u8; 8] = b"VPCommit";
 
 pub const PRF_EXPAND_PERSONALIZATION: &[u8; 16] = b"Taiga_ExpandSeed";
@@ -32,6 +36,43 @@ lazy_static! {
         to_field_elements(PRF_EXPAND_PERSONALIZATION)[0];
 }
 
+pub const BURN_NPK_PERSONALIZATION: &[u8; 16] = b"Taiga_BurnNpk___";
+lazy_static! {
+    /// The npk of the burn address. It is a nothing-up-my-sleeve hash, so nobody knows an nk
+    /// with prf_nf(nk, 0) = BURN_NPK and resources sent to it can never be spent.
+    pub static ref BURN_NPK: pallas::Base =
+        hash_to_field(HashToFieldMethod::Blake2b, BURN_NPK_PERSONALIZATION, b"");
+}
+
+pub const DETECTION_TAG_PERSONALIZATION: &[u8; 16] = b"Taiga_DetectTag_";
+lazy_static! {
+    pub static ref DETECTION_TAG_PERSONALIZATION_TO_FIELD: pallas::Base =
+        to_field_elements(DETECTION_TAG_PERSONALIZATION)[0];
+}
+
+pub const DETECTION_FILTER_PERSONALIZATION: &[u8; 16] = b"Taiga_DetectFltr";
+
+/// The hash accumulator domains of the vk policies, see `vk_policy`.
+pub const TOKEN_AUTH_POLICY_PERSONALIZATION: &[u8; 16] = b"Taiga_AuthPolicy";
+pub const RECEIVER_POLICY_PERSONALIZATION: &[u8; 16] = b"Taiga_RcvPolicy_";
+lazy_static! {
+    pub static ref TOKEN_AUTH_POLICY_PERSONALIZATION_TO_FIELD: pallas::Base =
+        to_field_elements(TOKEN_AUTH_POLICY_PERSONALIZATION)[0];
+    pub static ref RECEIVER_POLICY_PERSONALIZATION_TO_FIELD: pallas::Base =
+        to_field_elements(RECEIVER_POLICY_PERSONALIZATION)[0];
+}
+
+/// Bits per detection tag and number of hash functions of the block detection filter, for a
+/// false positive rate of about 1%.
+pub const DETECTION_FILTER_BITS_PER_TAG: usize = 10;
+pub const DETECTION_FILTER_HASH_NUM: u32 = 7;
+
+/// The maximum precision of fuzzy message detection keys: the false positive rate of a
+/// detection key ranges from 1 to 2^-FMD_GAMMA.
+pub const FMD_GAMMA: usize = 24;
+pub const FMD_BIT_HASH_PERSONALIZATION: &[u8; 16] = b"Taiga_FMD_BitHsh";
+pub const FMD_SCALAR_HASH_PERSONALIZATION: &[u8; 16] = b"Taiga_FMD_ScaHsh";
+
 pub const PRF_EXPAND_PSI: u8 = 0;
 pub const PRF_EXPAND_RCM: u8 = 1;
 pub const PRF_EXPAND_PUBLIC_INPUT_PADDING: u8 = 2;
@@ -58,16 +99,28 @@ pub const COMPLIANCE_INPUT_RESOURCE_LOGIC_CM_1_ROW_IDX: usize = 5;
 pub const COMPLIANCE_INPUT_RESOURCE_LOGIC_CM_2_ROW_IDX: usize = 6;
 pub const COMPLIANCE_OUTPUT_RESOURCE_LOGIC_CM_1_ROW_IDX: usize = 7;
 pub const COMPLIANCE_OUTPUT_RESOURCE_LOGIC_CM_2_ROW_IDX: usize = 8;
+// The kinds of the transparent-kind resources, zero for the other resources
+pub const COMPLIANCE_INPUT_TRANSPARENT_LOGIC_ROW_IDX: usize = 9;
+pub const COMPLIANCE_INPUT_TRANSPARENT_LABEL_ROW_IDX: usize = 10;
+pub const COMPLIANCE_OUTPUT_TRANSPARENT_LOGIC_ROW_IDX: usize = 11;
+pub const COMPLIANCE_OUTPUT_TRANSPARENT_LABEL_ROW_IDX: usize = 12;
+// The is_transparent_kind flags, binding a disclosed kind to the flag of the resource
+pub const COMPLIANCE_INPUT_TRANSPARENT_FLAG_ROW_IDX: usize = 13;
+pub const COMPLIANCE_OUTPUT_TRANSPARENT_FLAG_ROW_IDX: usize = 14;
 
 pub const POSEIDON_TO_CURVE_INPUT_LEN: usize = 3;
 pub const CURVE_ID: &str = "pallas";
 pub const VALUE_BASE_DOMAIN_POSTFIX: &str = "Taiga-NoteType";
+// The kind points of the transparent-kind resources are derived in their own domain, so that their
+// quantities only balance against resources of the kind with the flag set
+pub const TRANSPARENT_VALUE_BASE_DOMAIN_POSTFIX: &str = "Taiga-TransparentKind";
 
 pub const RESOURCE_LOGIC_CIRCUIT_PUBLIC_INPUT_NUM: usize =
     RESOURCE_LOGIC_CIRCUIT_MANDATORY_PUBLIC_INPUT_NUM
         + RESOURCE_LOGIC_CIRCUIT_CUSTOM_PUBLIC_INPUT_NUM
         + RESOURCE_LOGIC_CIRCUIT_RESOURCE_ENCRYPTION_PUBLIC_INPUT_NUM;
-pub const RESOURCE_LOGIC_CIRCUIT_MANDATORY_PUBLIC_INPUT_NUM: usize = 9;
+pub const RESOURCE_LOGIC_CIRCUIT_MANDATORY_PUBLIC_INPUT_NUM: usize =
+    RESOURCE_LOGIC_CIRCUIT_DYNAMIC_RESOURCE_LOGIC_CM_BEGIN_IDX + 2 * MAX_DYNAMIC_RESOURCE_LOGIC_NUM;
 pub const RESOURCE_LOGIC_CIRCUIT_CUSTOM_PUBLIC_INPUT_NUM: usize = 2;
 pub const RESOURCE_LOGIC_CIRCUIT_RESOURCE_ENCRYPTION_PUBLIC_INPUT_NUM: usize =
     RESOURCE_ENCRYPTION_CIPHERTEXT_NUM + 2; // ciphertext(12) + public_key(2)
@@ -77,10 +130,28 @@ pub const RESOURCE_LOGIC_CIRCUIT_OUTPUT_CM_ONE_PUBLIC_INPUT_IDX: usize = 1;
 pub const RESOURCE_LOGIC_CIRCUIT_NULLIFIER_TWO_PUBLIC_INPUT_IDX: usize = 2;
 pub const RESOURCE_LOGIC_CIRCUIT_OUTPUT_CM_TWO_PUBLIC_INPUT_IDX: usize = 3;
 pub const RESOURCE_LOGIC_CIRCUIT_OWNED_RESOURCE_ID_PUBLIC_INPUT_IDX: usize = 4;
+
+// The protocol maximum of dynamic resource logics per resource. Every resource logic circuit
+// publishes one commitment (two fields) per dynamic resource logic slot, so the cap bounds both the
+// public inputs and the proofs to verify per resource.
+pub const MAX_DYNAMIC_RESOURCE_LOGIC_NUM: usize = 2;
+pub const RESOURCE_LOGIC_CIRCUIT_DYNAMIC_RESOURCE_LOGIC_CM_BEGIN_IDX: usize = 5;
 pub const RESOURCE_LOGIC_CIRCUIT_FIRST_DYNAMIC_RESOURCE_LOGIC_CM_1: usize = 5;
 pub const RESOURCE_LOGIC_CIRCUIT_FIRST_DYNAMIC_RESOURCE_LOGIC_CM_2: usize = 6;
 pub const RESOURCE_LOGIC_CIRCUIT_SECOND_DYNAMIC_RESOURCE_LOGIC_CM_1: usize = 7;
 pub const RESOURCE_LOGIC_CIRCUIT_SECOND_DYNAMIC_RESOURCE_LOGIC_CM_2: usize = 8;
+// The public input indexes of the dynamic resource logic commitment slots
+pub const RESOURCE_LOGIC_CIRCUIT_DYNAMIC_RESOURCE_LOGIC_CMS: [[usize; 2];
+    MAX_DYNAMIC_RESOURCE_LOGIC_NUM] = [
+    [
+        RESOURCE_LOGIC_CIRCUIT_FIRST_DYNAMIC_RESOURCE_LOGIC_CM_1,
+        RESOURCE_LOGIC_CIRCUIT_FIRST_DYNAMIC_RESOURCE_LOGIC_CM_2,
+    ],
+    [
+        RESOURCE_LOGIC_CIRCUIT_SECOND_DYNAMIC_RESOURCE_LOGIC_CM_1,
+        RESOURCE_LOGIC_CIRCUIT_SECOND_DYNAMIC_RESOURCE_LOGIC_CM_2,
+    ],
+];
 pub const RESOURCE_LOGIC_CIRCUIT_CUSTOM_PUBLIC_INPUT_BEGIN_IDX: usize =
     RESOURCE_LOGIC_CIRCUIT_MANDATORY_PUBLIC_INPUT_NUM;
 pub const RESOURCE_LOGIC_CIRCUIT_RESOURCE_ENCRYPTION_PUBLIC_INPUT_BEGIN_IDX: usize =
@@ -94,61 +165,71 @@ pub const RESOURCE_LOGIC_CIRCUIT_RESOURCE_ENCRYPTION_PK_Y_IDX: usize = 24;
 // Resource encryption
 pub const RESOURCE_ENCRYPTION_PLAINTEXT_NUM: usize = 10;
 pub const RESOURCE_ENCRYPTION_CIPHERTEXT_NUM: usize = RESOURCE_ENCRYPTION_PLAINTEXT_NUM + 2; // msg(10) + MAC(1) + NOUNCE(1)
+pub const BATCH_RESOURCE_ENCRYPTION_CIPHERTEXT_NUM: usize = RESOURCE_ENCRYPTION_PLAINTEXT_NUM + 1; // msg(10) + MAC(1)
+
+pub const AEAD_KEY_PERSONALIZATION: &[u8; 16] = b"Taiga_AeadKeyDrv";
+pub const AEAD_NONCE_LEN: usize = 12;
 
 // Poseidon parameters
 pub const POSEIDON_RATE: usize = 2;
 pub const POSEIDON_WIDTH: usize = 3;
 
 lazy_static! {
-    pub static ref POSEIDON_TO_FIELD_U_0_POSTFIX: Vec<pallas::Base> = {
-        let mut postfix = format!(
-            "{}{}{}{}{}",
-            VALUE_BASE_DOMAIN_POSTFIX, "-", CURVE_ID, "-", 0,
-        )
-        .into_bytes();
-        postfix.push((4 + CURVE_ID.len() + VALUE_BASE_DOMAIN_POSTFIX.len()) as u8);
-        to_field_elements(&postfix)
-    };
-    pub static ref POSEIDON_TO_FIELD_U_1_POSTFIX: Vec<pallas::Base> = {
-        let mut postfix = format!(
-            "{}{}{}{}{}",
-            VALUE_BASE_DOMAIN_POSTFIX, "-", CURVE_ID, "-", 1,
-        )
-        .into_bytes();
-        postfix.push((4 + CURVE_ID.len() + VALUE_BASE_DOMAIN_POSTFIX.len()) as u8);
-        to_field_elements(&postfix)
-    };
+    // The domain of the resource kinds, see `ResourceKind::derive_kind`
+    pub static ref RESOURCE_KIND_DOMAIN: HashToCurveDomain =
+        HashToCurveDomain::new(VALUE_BASE_DOMAIN_POSTFIX);
+    // The domain of the kinds of the transparent-kind resources, see
+    // `ResourceKind::derive_transparent_kind`
+    pub static ref TRANSPARENT_RESOURCE_KIND_DOMAIN: HashToCurveDomain =
+        HashToCurveDomain::new(TRANSPARENT_VALUE_BASE_DOMAIN_POSTFIX);
+    pub static ref POSEIDON_TO_FIELD_U_0_POSTFIX: Vec<pallas::Base> =
+        vec![RESOURCE_KIND_DOMAIN.u_0_postfix()];
+    pub static ref POSEIDON_TO_FIELD_U_1_POSTFIX: Vec<pallas::Base> =
+        vec![RESOURCE_KIND_DOMAIN.u_1_postfix()];
 }
 
 pub const PARAMS_SIZE: u32 = 15;
 pub const COMPLIANCE_CIRCUIT_PARAMS_SIZE: u32 = PARAMS_SIZE;
 pub const RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE: u32 = PARAMS_SIZE;
+// The largest resource logic circuit the verifiers accept, larger circuits are rejected before
+// their proofs are verified.
+pub const MAX_RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE: u32 = 17;
 
-// Setup params map
+// The default params store, preloaded with the embedded params. The precomputed proving and
+// verifying keys are generated from it. It never generates params: the verifiers would otherwise
+// run the setup of any size an untrusted encoding declares. The resource logics with lookup
+// tables above PARAMS_SIZE, up to MAX_RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE, need the params of their
+// size: preload them with `DEFAULT_PARAMS_STORE.load_from_bytes(k, bytes)` (or
+// `preload(setup_params(k))` in tests) before proving, computing the vks or decoding the
+// verifying infos of these logics, which otherwise fail with `TransactionError::MissingParams`.
 lazy_static! {
-    pub static ref SETUP_PARAMS_MAP: HashMap<u32, Params<vesta::Affine>> = {
-        let mut m = HashMap::new();
-        let params_15 = {
-            let bytes = include_bytes!("../params/params_15");
-            Params::<vesta::Affine>::read(&mut &bytes[..]).unwrap()
-        };
+    pub static ref DEFAULT_PARAMS_STORE: ParamsStore =
+        ParamsStore::with_embedded_params(ParamsGenerationPolicy::PreloadedOnly);
+}
 
-        m.insert(PARAMS_SIZE, params_15);
-        m
+// Compliance proving key and verifying key
+#[cfg(feature = "prover")]
+lazy_static! {
+    pub static ref COMPLIANCE_VERIFYING_KEY: VerifyingKey = COMPLIANCE_PROVING_KEY.get_vk().clone();
+    pub static ref COMPLIANCE_PROVING_KEY: ProvingKey = {
+        let params = DEFAULT_PARAMS_STORE
+            .get(COMPLIANCE_CIRCUIT_PARAMS_SIZE)
+            .unwrap();
+        let empty_circuit: ComplianceCircuit = Default::default();
+        let vk = keygen_vk(&params, &empty_circuit).expect("keygen_vk should not fail");
+        keygen_pk(&params, vk, &empty_circuit).expect("keygen_pk should not fail")
     };
 }
 
-// Compliance proving key and verifying key
+// A verifier only needs the verifying key, the proving key is not generated
+#[cfg(not(feature = "prover"))]
 lazy_static! {
-    pub static ref COMPLIANCE_VERIFYING_KEY: VerifyingKey<vesta::Affine> =
-        COMPLIANCE_PROVING_KEY.get_vk().clone();
-    pub static ref COMPLIANCE_PROVING_KEY: ProvingKey<vesta::Affine> = {
-        let params = SETUP_PARAMS_MAP
-            .get(&COMPLIANCE_CIRCUIT_PARAMS_SIZE)
+    pub static ref COMPLIANCE_VERIFYING_KEY: VerifyingKey = {
+        let params = DEFAULT_PARAMS_STORE
+            .get(COMPLIANCE_CIRCUIT_PARAMS_SIZE)
             .unwrap();
         let empty_circuit: ComplianceCircuit = Default::default();
-        let vk = keygen_vk(params, &empty_circuit).expect("keygen_vk should not fail");
-        keygen_pk(params, vk, &empty_circuit).expect("keygen_pk should not fail")
+        keygen_vk(&params, &empty_circuit).expect("keygen_vk should not fail")
     };
 }
 
@@ -6100,8 +6181,6 @@ impl FixedPoint<pallas::Affine> for Short {
     }
 }
 
-pub const MAX_DYNAMIC_RESOURCE_LOGIC_NUM: usize = 2;
-
 #[ignore]
 #[test]
 fn r_u_z_generate() {
@@ -6130,9 +6209,10 @@ fn r_u_z_generate() {
 #[ignore]
 #[test]
 fn export_params() {
+    use crate::proof::setup_params;
     use std::io::Write;
 
-    let params: Params<vesta::Affine> = Params::new(PARAMS_SIZE);
+    let params = setup_params(PARAMS_SIZE);
     let mut bytes = vec![];
     params.write(&mut bytes).unwrap();
     let mut file = std::fs::File::create("./params/params_15")
@@ -6148,7 +6228,7 @@ fn export_params() {
 // fn export_compliance_proving_key() {
 //     use std::io::Write;
 
-//     let params = SETUP_PARAMS_MAP.get(&COMPLIANCE_CIRCUIT_PARAMS_SIZE).unwrap();
+//     let params = DEFAULT_PARAMS_STORE.get(COMPLIANCE_CIRCUIT_PARAMS_SIZE).unwrap();
 //     let empty_circuit: ComplianceCircuit = Default::default();
 //     let vk = keygen_vk(params, &empty_circuit).expect("keygen_vk should not fail");
 //     let pk = keygen_pk(params, vk, &empty_circuit).expect("keygen_pk should not fail");
@@ -6165,7 +6245,7 @@ fn export_params() {
 //     use crate::circuit::resource_logic_examples::TrivialResourceLogicCircuit;
 //     use std::io::Write;
 
-//     let params = SETUP_PARAMS_MAP.get(&RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE).unwrap();
+//     let params = DEFAULT_PARAMS_STORE.get(RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE).unwrap();
 //     let empty_circuit = TrivialResourceLogicCircuit::default();
 //     let vk = keygen_vk(params, &empty_circuit).expect("keygen_vk should not fail");
 //     let pk = keygen_pk(params, vk, &empty_circuit).expect("keygen_pk should not fail");
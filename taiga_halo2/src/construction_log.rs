@@ -0,0 +1,465 @@
+/// Construction logs of transactions and partial transactions, for bug reports.
+///
+/// A `ConstructionLog` records everything `Transaction::from_spec` reads: the spec with the seed
+/// of its rng, the circuit parameter sizes and the resource logic circuits of the crate, as the
+/// registry digests. A proof failure reported by a user is reproduced by replaying the log, the
+/// seed draws the same keys and witnesses again. The replay refuses the logs recorded with other
+/// circuits, their witnesses wouldn't reproduce the failure.
+///
+/// A `PartialTransactionLog` records the build of a partial transaction by
+/// `ShieldedPartialTransactionBuilder` or `ShieldedPartialTransaction::from_bytecode`: the
+/// resources, the anchors of the inputs and the circuits of their resource logics. The resource
+/// logics of the builder are closures, so these logs describe the failure but can't be replayed.
+///
+/// The partial transaction logs are redacted: the resources are recorded without their nullifier
+/// keys and their rseeds, the merkle paths by their anchors, and neither the rng seed nor the
+/// witnesses of the circuits are recorded. The seed of a construction log derives the keys and
+/// the witnesses of its spec again, `ConstructionLog::redacted` drops it before the log is shared
+/// with whoever may not spend the resources, the redacted log can't be replayed.
+use crate::{
+    circuit::resource_logic_bytecode::ApplicationByteCode,
+    compliance::ComplianceInfo,
+    constant::{COMPLIANCE_CIRCUIT_PARAMS_SIZE, RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE},
+    error::TransactionError,
+    merkle_tree::Anchor,
+    resource::{Resource, ResourceLogics},
+    resource_logic_vk_registry::RESOURCE_LOGIC_VK_REGISTRY,
+    shielded_ptx::{ShieldedPartialTransaction, ShieldedPartialTransactionBuilder},
+    transaction::Transaction,
+    transaction_spec::TransactionSpec,
+};
+use pasta_curves::pallas;
+use rand::{rngs::OsRng, RngCore};
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConstructionLog {
+    /// The version of the crate the transaction was built with
+    pub crate_version: String,
+    pub compliance_params_size: u32,
+    pub resource_logic_params_size: u32,
+    /// The known resource logics, as ("<Name>V<version>", hex encoded compressed vk)
+    pub circuits: Vec<(String, String)>,
+    /// The spec, its seed is always set
+    pub spec: TransactionSpec,
+    /// The error of the construction, if it failed
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl ConstructionLog {
+    fn new(spec: TransactionSpec, error: Option<String>) -> Self {
+        Self {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            compliance_params_size: COMPLIANCE_CIRCUIT_PARAMS_SIZE,
+            resource_logic_params_size: RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+            circuits: RESOURCE_LOGIC_VK_REGISTRY.digests(),
+            spec,
+            error,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("the log serializes to JSON")
+    }
+
+    pub fn from_json(log: &str) -> Result<Self, TransactionError> {
+        serde_json::from_str(log)
+            .map_err(|e| TransactionError::InvalidConstructionLog(e.to_string()))
+    }
+
+    /// The log without the seed of the spec, which derives the keys and the witnesses. It
+    /// describes the failure but can't be replayed.
+    pub fn redacted(&self) -> Self {
+        Self {
+            spec: TransactionSpec {
+                seed: None,
+                ..self.spec.clone()
+            },
+            ..self.clone()
+        }
+    }
+
+    /// Builds the transaction of the log again. The circuits and the parameter sizes of the crate
+    /// must be the ones the log was recorded with.
+    pub fn replay(&self) -> Result<Transaction, TransactionError> {
+        if self.spec.seed.is_none() {
+            return Err(TransactionError::InvalidConstructionLog(
+                "the seed is missing".to_string(),
+            ));
+        }
+        if self.compliance_params_size != COMPLIANCE_CIRCUIT_PARAMS_SIZE
+            || self.resource_logic_params_size != RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE
+        {
+            return Err(TransactionError::InvalidConstructionLog(
+                "the circuit parameter sizes differ".to_string(),
+            ));
+        }
+        let circuits = RESOURCE_LOGIC_VK_REGISTRY.digests();
+        if let Some((logic, _)) = self
+            .circuits
+            .iter()
+            .find(|circuit| !circuits.contains(circuit))
+        {
+            return Err(TransactionError::InvalidConstructionLog(format!(
+                "the {logic} circuit of the log is not a circuit of this build, version {}",
+                env!("CARGO_PKG_VERSION")
+            )));
+        }
+        Transaction::from_spec(&self.spec)
+    }
+}
+
+impl Transaction {
+    /// Builds the transaction of the spec like `from_spec` and records the construction log. A
+    /// spec without a seed is built with a random seed, which is recorded.
+    pub fn from_spec_recorded(
+        spec: &TransactionSpec,
+    ) -> (Result<Self, TransactionError>, ConstructionLog) {
+        let spec = TransactionSpec {
+            seed: Some(spec.seed.unwrap_or_else(|| OsRng.next_u64())),
+            ..spec.clone()
+        };
+        let result = Transaction::from_spec(&spec);
+        let error = result.as_ref().err().map(|e| e.to_string());
+        (result, ConstructionLog::new(spec, error))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PartialTransactionLog {
+    /// The version of the crate the partial transaction was built with
+    pub crate_version: String,
+    /// The resources of the compliances, the padding resources included
+    pub input_resources: Vec<RedactedResource>,
+    /// The anchors of the input resources, the ones of their merkle paths or of the pending
+    /// inputs
+    pub input_anchors: Vec<Anchor>,
+    pub output_resources: Vec<RedactedResource>,
+    /// The resource logic circuits of every resource, the application logic first. The builder
+    /// records them as "<Name>V<version>", the bytecode by the name of its representation.
+    pub input_circuits: Vec<Vec<String>>,
+    pub output_circuits: Vec<Vec<String>>,
+    /// The error of the construction, if it failed
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// A resource of a partial transaction log, without its nullifier key and its rseed. The
+/// commitment identifies the resource.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RedactedResource {
+    pub logic: pallas::Base,
+    pub label: pallas::Base,
+    pub value: pallas::Base,
+    pub quantity: u128,
+    pub npk: pallas::Base,
+    pub nonce: pallas::Base,
+    pub is_ephemeral: bool,
+    pub is_transparent_kind: bool,
+    pub commitment: pallas::Base,
+}
+
+impl From<&Resource> for RedactedResource {
+    fn from(resource: &Resource) -> Self {
+        Self {
+            logic: resource.get_logic(),
+            label: resource.get_label(),
+            value: resource.value,
+            quantity: resource.quantity,
+            npk: resource.get_npk(),
+            nonce: resource.nonce.inner(),
+            is_ephemeral: resource.is_ephemeral,
+            is_transparent_kind: resource.is_transparent_kind,
+            commitment: resource.commitment().inner(),
+        }
+    }
+}
+
+impl PartialTransactionLog {
+    fn new(compliances: &[ComplianceInfo]) -> Self {
+        Self {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            input_resources: compliances
+                .iter()
+                .map(|compliance| compliance.get_input_resource().into())
+                .collect(),
+            input_anchors: compliances
+                .iter()
+                .map(|compliance| compliance.calculate_root())
+                .collect(),
+            output_resources: compliances
+                .iter()
+                .map(|compliance| compliance.get_output_resource().into())
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("the log serializes to JSON")
+    }
+
+    pub fn from_json(log: &str) -> Result<Self, TransactionError> {
+        serde_json::from_str(log)
+            .map_err(|e| TransactionError::InvalidConstructionLog(e.to_string()))
+    }
+}
+
+fn resource_logics_circuits(resource_logics: &ResourceLogics) -> Vec<String> {
+    std::iter::once(resource_logics.get_application_resource_logic())
+        .chain(
+            resource_logics
+                .get_dynamic_resource_logics()
+                .iter()
+                .map(|resource_logic| resource_logic.as_ref()),
+        )
+        .map(|resource_logic| RESOURCE_LOGIC_VK_REGISTRY.describe_resource_logic(resource_logic))
+        .collect()
+}
+
+fn application_circuits(application: &ApplicationByteCode) -> Vec<String> {
+    std::iter::once(application.app_resource_logic_bytecode())
+        .chain(application.dynamic_resource_logic_bytecode())
+        .map(|bytecode| bytecode.representation().name().to_string())
+        .collect()
+}
+
+impl<'a> ShieldedPartialTransactionBuilder<'a> {
+    /// Builds the partial transaction like `build`, and records the log of the construction.
+    pub fn build_recorded(
+        self,
+    ) -> (
+        Result<ShieldedPartialTransaction, TransactionError>,
+        PartialTransactionLog,
+    ) {
+        let mut rng = OsRng;
+        let witnesses = match self.witnesses(&mut rng) {
+            Ok(witnesses) => witnesses,
+            Err(e) => {
+                let log = PartialTransactionLog {
+                    error: Some(e.to_string()),
+                    ..PartialTransactionLog::new(&[])
+                };
+                return (Err(e), log);
+            }
+        };
+        let mut log = PartialTransactionLog::new(&witnesses.compliances);
+        log.input_circuits = witnesses
+            .input_resource_logics
+            .iter()
+            .map(resource_logics_circuits)
+            .collect();
+        log.output_circuits = witnesses
+            .output_resource_logics
+            .iter()
+            .map(resource_logics_circuits)
+            .collect();
+        let result = witnesses.prove(rng);
+        log.error = result.as_ref().err().map(|e| e.to_string());
+        (result, log)
+    }
+}
+
+impl ShieldedPartialTransaction {
+    /// Builds the partial transaction like `from_bytecode`, and records the log of the
+    /// construction.
+    pub fn from_bytecode_recorded(
+        compliances: Vec<ComplianceInfo>,
+        input_resource_app: Vec<ApplicationByteCode>,
+        output_resource_app: Vec<ApplicationByteCode>,
+        hints: Vec<u8>,
+    ) -> (Result<Self, TransactionError>, PartialTransactionLog) {
+        let mut log = PartialTransactionLog::new(&compliances);
+        log.input_circuits = input_resource_app
+            .iter()
+            .map(application_circuits)
+            .collect();
+        log.output_circuits = output_resource_app
+            .iter()
+            .map(application_circuits)
+            .collect();
+        let result = Self::from_bytecode(
+            compliances,
+            input_resource_app,
+            output_resource_app,
+            hints,
+            OsRng,
+        );
+        log.error = result.as_ref().err().map(|e| e.to_string());
+        (result, log)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConstructionLog, PartialTransactionLog};
+    use crate::{
+        circuit::resource_logic_examples::{
+            signature_verification::COMPRESSED_TOKEN_AUTH_VK,
+            token::{Token, TokenAuthorization},
+        },
+        constant::{NUM_RESOURCE, TAIGA_COMMITMENT_TREE_DEPTH},
+        error::TransactionError,
+        executable::Executable,
+        merkle_tree::MerklePath,
+        nullifier::NullifierKeyContainer,
+        shielded_ptx::ShieldedPartialTransactionBuilder,
+        transaction::Transaction,
+        transaction_spec::{PartialTransactionSpec, ResourceSpec, TransactionSpec},
+    };
+    use ff::Field;
+    use pasta_curves::pallas;
+    use rand::rngs::OsRng;
+
+    fn resource(owner: &str, quantity: u128) -> ResourceSpec {
+        ResourceSpec {
+            logic: "Token".to_string(),
+            owner: owner.to_string(),
+            token: "btc".to_string(),
+            quantity,
+            intent: None,
+        }
+    }
+
+    #[test]
+    fn test_construction_log_replay() {
+        // Alice pays Bob, the spec has no seed
+        let spec = TransactionSpec {
+            seed: None,
+            partial_transactions: vec![PartialTransactionSpec {
+                spends: vec![resource("alice", 5)],
+                creates: vec![resource("bob", 5)],
+            }],
+        };
+        let (tx, log) = Transaction::from_spec_recorded(&spec);
+        let tx = tx.unwrap();
+        assert!(log.spec.seed.is_some() && log.error.is_none());
+
+        let log = ConstructionLog::from_json(&log.to_json()).unwrap();
+        let replayed = log.replay().unwrap();
+        assert_eq!(tx.execute().unwrap(), replayed.execute().unwrap());
+
+        // The logs of other circuits are refused
+        let mut other_circuits = log.clone();
+        other_circuits.circuits[0].1 = "00".to_string();
+        assert!(matches!(
+            other_circuits.replay(),
+            Err(TransactionError::InvalidConstructionLog(_))
+        ));
+    }
+
+    #[test]
+    fn test_construction_log_records_the_error() {
+        let spec = TransactionSpec {
+            seed: Some(3),
+            partial_transactions: vec![PartialTransactionSpec {
+                spends: vec![resource("alice", 5); 3],
+                creates: vec![],
+            }],
+        };
+        let (result, log) = Transaction::from_spec_recorded(&spec);
+        let error = result.unwrap_err().to_string();
+        assert_eq!(log.error, Some(error.clone()));
+        assert_eq!(log.replay().unwrap_err().to_string(), error);
+
+        assert!(matches!(
+            ConstructionLog::from_json("{}"),
+            Err(TransactionError::InvalidConstructionLog(_))
+        ));
+    }
+
+    #[test]
+    fn test_partial_transaction_log() {
+        let mut rng = OsRng;
+        let auth_sk = pallas::Scalar::random(&mut rng);
+        let auth = TokenAuthorization::from_sk_vk(&auth_sk, &COMPRESSED_TOKEN_AUTH_VK);
+        let nk = NullifierKeyContainer::random_key(&mut rng);
+        let token = Token::new("btc".to_string(), 5u64);
+        let input = token.create_random_input_token_resource(&mut rng, nk.get_nk().unwrap(), &auth);
+        let output = token.create_random_output_token_resource(&mut rng, nk.get_npk(), &auth);
+        let (input_resource, output_resource) = (*input.resource(), *output.resource());
+        let merkle_path = MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH);
+
+        let (ptx, log) = ShieldedPartialTransactionBuilder::new()
+            .add_input(
+                *input.resource(),
+                merkle_path.clone(),
+                move |rng, _, input_resources, output_resources| {
+                    input.generate_input_token_resource_logics(
+                        rng,
+                        auth,
+                        auth_sk,
+                        input_resources,
+                        output_resources,
+                    )
+                },
+            )
+            .add_output(
+                *output.resource(),
+                move |rng, resource, input_resources, output_resources| {
+                    let mut output = output;
+                    output.resource = *resource;
+                    output.generate_output_token_resource_logics(
+                        rng,
+                        auth,
+                        input_resources,
+                        output_resources,
+                    )
+                },
+            )
+            .build_recorded();
+        ptx.unwrap().execute().unwrap();
+
+        let json = log.to_json();
+        let log = PartialTransactionLog::from_json(&json).unwrap();
+        assert!(log.error.is_none());
+        assert_eq!(log.input_resources.len(), NUM_RESOURCE);
+        assert_eq!(
+            log.input_resources[0].commitment,
+            input_resource.commitment().inner()
+        );
+        assert_eq!(
+            log.input_anchors[0],
+            input_resource.calculate_root(&merkle_path)
+        );
+        assert_eq!(log.input_circuits[0].len(), 2);
+        assert!(log.input_circuits[0][0].starts_with("TokenV"));
+        assert!(log.output_circuits[1][0].starts_with("TrivialV"));
+
+        // The log has none of the keys or the witnesses
+        let mut secrets = vec![
+            nk.get_nk().unwrap(),
+            input_resource.rseed,
+            output_resource.rseed,
+        ];
+        secrets.extend(merkle_path.get_path().into_iter().map(|(node, _)| node));
+        for secret in secrets {
+            let secret = serde_json::to_string(&secret).unwrap();
+            assert!(!json.contains(secret.trim_matches('"')));
+        }
+        let auth_sk = serde_json::to_string(&auth_sk).unwrap();
+        assert!(!json.contains(auth_sk.trim_matches('"')));
+    }
+
+    #[test]
+    fn test_redacted_construction_log() {
+        let spec = TransactionSpec {
+            seed: Some(5),
+            partial_transactions: vec![PartialTransactionSpec {
+                spends: vec![resource("alice", 5)],
+                creates: vec![resource("bob", 5)],
+            }],
+        };
+        let (_, log) = Transaction::from_spec_recorded(&spec);
+        let redacted = ConstructionLog::from_json(&log.redacted().to_json()).unwrap();
+        assert_eq!(redacted.spec.seed, None);
+        assert_eq!(redacted.circuits, log.circuits);
+        assert!(matches!(
+            redacted.replay(),
+            Err(TransactionError::InvalidConstructionLog(_))
+        ));
+    }
+}
@@ -1,8 +1,9 @@
 use crate::constant::RESOURCE_COMMITMENT_R_GENERATOR;
+use crate::error::TransactionError;
 use crate::resource::Resource;
 use halo2_proofs::arithmetic::CurveAffine;
 use pasta_curves::group::cofactor::CofactorCurveAffine;
-use pasta_curves::group::{Curve, Group, GroupEncoding};
+use pasta_curves::group::{ff::PrimeField, Curve, Group, GroupEncoding};
 use pasta_curves::pallas;
 #[cfg(feature = "nif")]
 use rustler::NifTuple;
@@ -25,8 +26,8 @@ impl DeltaCommitment {
         let base_input = input_resource.get_kind();
         let base_output = output_resource.get_kind();
         DeltaCommitment(
-            base_input * pallas::Scalar::from(input_resource.quantity)
-                - base_output * pallas::Scalar::from(output_resource.quantity)
+            base_input * pallas::Scalar::from_u128(input_resource.quantity)
+                - base_output * pallas::Scalar::from_u128(output_resource.quantity)
                 + RESOURCE_COMMITMENT_R_GENERATOR.to_curve() * blind_r,
         )
     }
@@ -59,3 +60,33 @@ impl DeltaCommitment {
         pallas::Point::from_bytes(&bytes).map(DeltaCommitment)
     }
 }
+
+/// The unblinded net delta of the kinds: sum(kind * (input quantity - output quantity)).
+pub fn net_delta(kind_deltas: impl IntoIterator<Item = (pallas::Point, i128)>) -> pallas::Point {
+    kind_deltas
+        .into_iter()
+        .fold(pallas::Point::identity(), |sum, (kind, quantity)| {
+            let quantity_scalar = pallas::Scalar::from_u128(quantity.unsigned_abs());
+            if quantity < 0 {
+                sum - kind * quantity_scalar
+            } else {
+                sum + kind * quantity_scalar
+            }
+        })
+}
+
+/// Checks the delta commitments of a ptx whose blinding randomness sums to the disclosed value
+/// open to the net delta, without the binding signature.
+pub fn verify_disclosed_delta(
+    deltas: &[DeltaCommitment],
+    disclosed_r: &pallas::Scalar,
+    net_delta: &pallas::Point,
+) -> Result<(), TransactionError> {
+    let sum = deltas
+        .iter()
+        .fold(pallas::Point::identity(), |sum, delta| sum + delta.inner());
+    if sum - RESOURCE_COMMITMENT_R_GENERATOR.to_curve() * disclosed_r != *net_delta {
+        return Err(TransactionError::InvalidDisclosedDelta);
+    }
+    Ok(())
+}
@@ -1,3 +1,10 @@
+use crate::circuit::resource_logic_bytecode::MalformedLogicInputsError;
+use crate::circuit::resource_logic_circuit::{ResourceLogicPrecheckError, VampIRCircuitError};
+use crate::circuit::verify_failure::check_id;
+use crate::constant::{
+    MAX_DYNAMIC_RESOURCE_LOGIC_NUM, MAX_RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE, NUM_RESOURCE,
+};
+use crate::nullifier::Nullifier;
 use core::fmt;
 use halo2_proofs::plonk::Error as PlonkError;
 use std::fmt::Display;
@@ -16,6 +23,18 @@ pub enum TransactionError {
     InconsistentOutputResourceCommitment,
     /// Owned resource id is inconsistent between the compliance and the resource logic.
     InconsistentOwnedResourceID,
+    /// The nonce of the output resource is not the nullifier of the input resource.
+    InconsistentOutputResourceNonce,
+    /// The same nullifier is revealed more than once in the transaction.
+    DuplicateNullifier,
+    /// The same output resource commitment is created more than once in the transaction.
+    DuplicateOutputResourceCommitment,
+    /// The resource is not sent to the burn address.
+    NotBurnResource,
+    /// The burned resource is not in the commitment tree of the anchor.
+    InvalidBurnProof,
+    /// The delta commitments don't open to the disclosed net delta.
+    InvalidDisclosedDelta,
     /// IO error
     IoError(std::io::Error),
     /// Transparent resource nullifier key is missing
@@ -26,6 +45,148 @@ pub enum TransactionError {
     MissingPartialTxBindingSignatureR,
     /// ResourceLogicRepresentation is not valid
     InvalidResourceLogicRepresentation,
+    /// Params of the requested size are not loaded in the ParamsStore
+    MissingParams(u32),
+    /// Loaded params have an unexpected size
+    InvalidParamsSize(u32),
+    /// The proving task was cancelled
+    ProvingCancelled,
+    /// The resource logic proof is not valid, with the logic the proof claims
+    InvalidResourceLogicProof(String, PlonkError),
+    /// The VampIR circuit or its inputs are not valid
+    VampIR(VampIRCircuitError),
+    /// The resource logic witnesses are inconsistent, found before the proving
+    ResourceLogicPrecheck(ResourceLogicPrecheckError),
+    /// The resource logic constraints are not satisfied, with the explained failures
+    ResourceLogicConstraintsNotSatisfied(Vec<String>),
+    /// The resource has more dynamic resource logics than MAX_DYNAMIC_RESOURCE_LOGIC_NUM
+    TooManyDynamicResourceLogics(usize),
+    /// The inputs of a resource logic bytecode don't match the schema of its representation
+    MalformedLogicInputs(MalformedLogicInputsError),
+    /// The transaction spec can't be parsed or describes an unsupported transaction
+    InvalidTransactionSpec(String),
+    /// The declared circuit size of the resource logic doesn't match its vk, with the logic name
+    InconsistentResourceLogicCircuitSize(String),
+    /// The resource logic circuit is larger than MAX_RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE, with its k
+    ResourceLogicCircuitTooLarge(u32),
+    /// The proof to re-prove doesn't exist or the new witness changes what the other proofs
+    /// depend on
+    InvalidReproving(String),
+    /// The witness tracker has no merkle path of the input resource
+    UntrackedResource,
+    /// The partial transaction doesn't have NUM_RESOURCE input or output resources, with their
+    /// number
+    TooManyResources(usize),
+    /// The commitment tree has no free leaf left
+    CommitmentTreeFull,
+    /// The anchor is not a recorded root of the commitment tree
+    UnknownAnchor,
+    /// The resources don't cover the requested quantity, with the available quantity
+    InsufficientQuantity(u128),
+    /// The resources are expected to be of the same kind
+    InconsistentResourceKind,
+    /// The nullifier is already spent, with the index of the partial transaction revealing it
+    DoubleSpend(usize, Nullifier),
+    /// The batch of proofs is not valid, though every proof is valid on its own
+    InvalidBatchProof,
+    /// The kind upgrade is not approved by the upgrade authority
+    UnapprovedUpgrade,
+    /// The merkle path of a compliance input is not bound yet, see `ComplianceInfo::new_pending`
+    PendingMerklePath,
+    /// The intent resource is not a pure intent, it must be ephemeral and of quantity zero
+    NonPureIntentResource,
+    /// The construction log can't be parsed or replayed by this build of the crate
+    InvalidConstructionLog(String),
+    /// The upgrade authority approved more old kinds than the approval tree holds
+    TooManyApprovedUpgrades(usize),
+    /// A subscription pull is ahead of the current epoch, with the index of the partial
+    /// transaction pulling
+    PullNotDue(usize),
+    /// The disclosed delta of the shielded partial transaction is already recorded, with its index
+    DuplicateDisclosure(usize),
+    /// The disclosed delta lists the same resource kind more than once
+    DuplicateDisclosedKind,
+    /// The transaction has no shielded partial transaction at the index
+    UnknownPartialTransaction(usize),
+    /// A payment channel close, dispute or settle publishes a time away from the current time,
+    /// with the index of the partial transaction
+    StaleChannelTime(usize),
+    /// The batched verification of the transaction returns another result than the
+    /// verification of one proof at a time
+    InconsistentBatchedResult,
+}
+
+impl TransactionError {
+    /// The ids of the named checks that failed, when the resource logic constraints are not
+    /// satisfied.
+    pub fn failed_check_ids(&self) -> Vec<&str> {
+        match self {
+            TransactionError::ResourceLogicConstraintsNotSatisfied(explanations) => explanations
+                .iter()
+                .filter_map(|explanation| check_id(explanation))
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// The stable numeric code of the error, for the FFI and RPC consumers that can't match on
+    /// the enum or on the Display strings. A code is never reused or reassigned: new variants
+    /// take the next free code.
+    pub fn code(&self) -> u32 {
+        use TransactionError::*;
+        match self {
+            Proof(..) => 1,
+            InvalidBindingSignature => 2,
+            MissingBindingSignatures => 3,
+            InconsistentNullifier => 4,
+            InconsistentOutputResourceCommitment => 5,
+            InconsistentOwnedResourceID => 6,
+            InconsistentOutputResourceNonce => 7,
+            DuplicateNullifier => 8,
+            DuplicateOutputResourceCommitment => 9,
+            NotBurnResource => 10,
+            InvalidBurnProof => 11,
+            InvalidDisclosedDelta => 12,
+            IoError(..) => 13,
+            MissingTransparentResourceNullifierKey => 14,
+            MissingTransparentResourceMerklePath => 15,
+            MissingPartialTxBindingSignatureR => 16,
+            InvalidResourceLogicRepresentation => 17,
+            MissingParams(..) => 18,
+            InvalidParamsSize(..) => 19,
+            ProvingCancelled => 20,
+            InvalidResourceLogicProof(..) => 21,
+            VampIR(..) => 22,
+            ResourceLogicPrecheck(..) => 23,
+            ResourceLogicConstraintsNotSatisfied(..) => 24,
+            TooManyDynamicResourceLogics(..) => 25,
+            MalformedLogicInputs(..) => 26,
+            InvalidTransactionSpec(..) => 27,
+            InconsistentResourceLogicCircuitSize(..) => 28,
+            ResourceLogicCircuitTooLarge(..) => 29,
+            InvalidReproving(..) => 30,
+            UntrackedResource => 31,
+            TooManyResources(..) => 32,
+            CommitmentTreeFull => 33,
+            UnknownAnchor => 34,
+            // 35 was the spent nullifier of the indexer, reported as DoubleSpend now
+            InsufficientQuantity(..) => 36,
+            InconsistentResourceKind => 37,
+            DoubleSpend(..) => 38,
+            InvalidBatchProof => 39,
+            UnapprovedUpgrade => 40,
+            PendingMerklePath => 41,
+            NonPureIntentResource => 42,
+            InvalidConstructionLog(..) => 43,
+            TooManyApprovedUpgrades(..) => 44,
+            PullNotDue(..) => 45,
+            DuplicateDisclosure(..) => 46,
+            DuplicateDisclosedKind => 47,
+            UnknownPartialTransaction(..) => 48,
+            StaleChannelTime(..) => 49,
+            InconsistentBatchedResult => 50,
+        }
+    }
 }
 
 impl Display for TransactionError {
@@ -44,6 +205,22 @@ impl Display for TransactionError {
             InconsistentOwnedResourceID => {
                 f.write_str("Owned resource id is not consistent between the compliance and the resource logic")
             }
+            InconsistentOutputResourceNonce => f.write_str(
+                "The nonce of the output resource is not the nullifier of the input resource",
+            ),
+            DuplicateNullifier => {
+                f.write_str("The same nullifier is revealed more than once in the transaction")
+            }
+            DuplicateOutputResourceCommitment => f.write_str(
+                "The same output resource commitment is created more than once in the transaction",
+            ),
+            NotBurnResource => f.write_str("The resource is not sent to the burn address"),
+            InvalidBurnProof => {
+                f.write_str("The burned resource is not in the commitment tree of the anchor")
+            }
+            InvalidDisclosedDelta => {
+                f.write_str("The delta commitments don't open to the disclosed net delta")
+            }
             IoError(e) => f.write_str(&format!("IoError error: {e}")),
             MissingTransparentResourceNullifierKey => {
                 f.write_str("Transparent resource nullifier key is missing")
@@ -57,6 +234,82 @@ impl Display for TransactionError {
             InvalidResourceLogicRepresentation => {
                 f.write_str("ResourceLogicRepresentation is not valid, add borsh feature if using native resource logic examples ")
             }
+            MissingParams(k) => f.write_str(&format!("Params of size {k} are not loaded")),
+            InvalidParamsSize(k) => f.write_str(&format!("Params size {k} is not expected")),
+            ProvingCancelled => f.write_str("The proving task was cancelled"),
+            InvalidResourceLogicProof(logic, e) => f.write_str(&format!(
+                "Resource logic proof is not valid, proof claims {logic} logic: {e}"
+            )),
+            VampIR(e) => f.write_str(&format!("VampIR error: {e}")),
+            ResourceLogicPrecheck(e) => {
+                f.write_str(&format!("Resource logic witnesses are inconsistent: {e}"))
+            }
+            ResourceLogicConstraintsNotSatisfied(failures) => f.write_str(&format!(
+                "Resource logic constraints are not satisfied:\n  {}",
+                failures.join("\n  ")
+            )),
+            TooManyDynamicResourceLogics(num) => f.write_str(&format!(
+                "The resource has {num} dynamic resource logics, the maximum is {MAX_DYNAMIC_RESOURCE_LOGIC_NUM}"
+            )),
+            MalformedLogicInputs(e) => {
+                f.write_str(&format!("Resource logic inputs are malformed: {e}"))
+            }
+            InvalidTransactionSpec(e) => f.write_str(&format!("Transaction spec is not valid: {e}")),
+            InconsistentResourceLogicCircuitSize(logic) => f.write_str(&format!(
+                "The declared circuit size of the {logic} logic doesn't match its vk"
+            )),
+            ResourceLogicCircuitTooLarge(k) => f.write_str(&format!(
+                "The resource logic circuit of size {k} is larger than the maximum {MAX_RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE}"
+            )),
+            InvalidReproving(e) => f.write_str(&format!("Re-proving is not valid: {e}")),
+            UntrackedResource => {
+                f.write_str("The witness tracker has no merkle path of the input resource")
+            }
+            TooManyResources(num) => f.write_str(&format!(
+                "The partial transaction has {num} input or output resources instead of {NUM_RESOURCE}"
+            )),
+            CommitmentTreeFull => f.write_str("The commitment tree is full"),
+            UnknownAnchor => f.write_str("The anchor is not a root of the commitment tree"),
+            InsufficientQuantity(quantity) => f.write_str(&format!(
+                "The resources don't cover the requested quantity, {quantity} available"
+            )),
+            InconsistentResourceKind => f.write_str("The resources are of different kinds"),
+            DoubleSpend(ptx_index, nf) => f.write_str(&format!(
+                "Double spend of nullifier {:?} in partial transaction {ptx_index}",
+                nf.inner()
+            )),
+            InvalidBatchProof => f.write_str("The batch of proofs is not valid"),
+            UnapprovedUpgrade => f.write_str("The kind upgrade is not approved"),
+            PendingMerklePath => {
+                f.write_str("The merkle path of the compliance input is not bound yet")
+            }
+            NonPureIntentResource => {
+                f.write_str("The intent resource is not ephemeral or its quantity is not zero")
+            }
+            InvalidConstructionLog(e) => {
+                f.write_str(&format!("Construction log is not valid: {e}"))
+            }
+            TooManyApprovedUpgrades(num) => f.write_str(&format!(
+                "{num} approved upgrades exceed the approval tree capacity"
+            )),
+            PullNotDue(ptx_index) => f.write_str(&format!(
+                "The subscription pull of the partial transaction {ptx_index} is not due yet"
+            )),
+            DuplicateDisclosure(ptx_index) => f.write_str(&format!(
+                "The disclosed delta of the partial transaction {ptx_index} is already recorded"
+            )),
+            DuplicateDisclosedKind => {
+                f.write_str("The disclosed delta lists the same resource kind more than once")
+            }
+            UnknownPartialTransaction(ptx_index) => f.write_str(&format!(
+                "The transaction has no shielded partial transaction {ptx_index}"
+            )),
+            StaleChannelTime(ptx_index) => f.write_str(&format!(
+                "The payment channel time of the partial transaction {ptx_index} is not recent"
+            )),
+            InconsistentBatchedResult => f.write_str(
+                "The batched verification returns another result than the one proof at a time",
+            ),
         }
     }
 }
@@ -72,3 +325,127 @@ impl From<std::io::Error> for TransactionError {
         TransactionError::IoError(e)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The codes are pinned: a released code must keep its variant, new variants are appended.
+    const PINNED_CODES: [(u32, &str); 49] = [
+        (1, "Proof"),
+        (2, "InvalidBindingSignature"),
+        (3, "MissingBindingSignatures"),
+        (4, "InconsistentNullifier"),
+        (5, "InconsistentOutputResourceCommitment"),
+        (6, "InconsistentOwnedResourceID"),
+        (7, "InconsistentOutputResourceNonce"),
+        (8, "DuplicateNullifier"),
+        (9, "DuplicateOutputResourceCommitment"),
+        (10, "NotBurnResource"),
+        (11, "InvalidBurnProof"),
+        (12, "InvalidDisclosedDelta"),
+        (13, "IoError"),
+        (14, "MissingTransparentResourceNullifierKey"),
+        (15, "MissingTransparentResourceMerklePath"),
+        (16, "MissingPartialTxBindingSignatureR"),
+        (17, "InvalidResourceLogicRepresentation"),
+        (18, "MissingParams"),
+        (19, "InvalidParamsSize"),
+        (20, "ProvingCancelled"),
+        (21, "InvalidResourceLogicProof"),
+        (22, "VampIR"),
+        (23, "ResourceLogicPrecheck"),
+        (24, "ResourceLogicConstraintsNotSatisfied"),
+        (25, "TooManyDynamicResourceLogics"),
+        (26, "MalformedLogicInputs"),
+        (27, "InvalidTransactionSpec"),
+        (28, "InconsistentResourceLogicCircuitSize"),
+        (29, "ResourceLogicCircuitTooLarge"),
+        (30, "InvalidReproving"),
+        (31, "UntrackedResource"),
+        (32, "TooManyResources"),
+        (33, "CommitmentTreeFull"),
+        (34, "UnknownAnchor"),
+        (36, "InsufficientQuantity"),
+        (37, "InconsistentResourceKind"),
+        (38, "DoubleSpend"),
+        (39, "InvalidBatchProof"),
+        (40, "UnapprovedUpgrade"),
+        (41, "PendingMerklePath"),
+        (42, "NonPureIntentResource"),
+        (43, "InvalidConstructionLog"),
+        (44, "TooManyApprovedUpgrades"),
+        (45, "PullNotDue"),
+        (46, "DuplicateDisclosure"),
+        (47, "DuplicateDisclosedKind"),
+        (48, "UnknownPartialTransaction"),
+        (49, "StaleChannelTime"),
+        (50, "InconsistentBatchedResult"),
+    ];
+
+    fn all_errors() -> Vec<TransactionError> {
+        use TransactionError::*;
+        vec![
+            Proof(PlonkError::Synthesis),
+            InvalidBindingSignature,
+            MissingBindingSignatures,
+            InconsistentNullifier,
+            InconsistentOutputResourceCommitment,
+            InconsistentOwnedResourceID,
+            InconsistentOutputResourceNonce,
+            DuplicateNullifier,
+            DuplicateOutputResourceCommitment,
+            NotBurnResource,
+            InvalidBurnProof,
+            InvalidDisclosedDelta,
+            IoError(std::io::Error::other("io")),
+            MissingTransparentResourceNullifierKey,
+            MissingTransparentResourceMerklePath,
+            MissingPartialTxBindingSignatureR,
+            InvalidResourceLogicRepresentation,
+            MissingParams(13),
+            InvalidParamsSize(13),
+            ProvingCancelled,
+            InvalidResourceLogicProof("Token".to_string(), PlonkError::ConstraintSystemFailure),
+            VampIR(VampIRCircuitError::IoError("io".to_string())),
+            ResourceLogicPrecheck(ResourceLogicPrecheckError::UnknownOwnedResourceId),
+            ResourceLogicConstraintsNotSatisfied(vec![]),
+            TooManyDynamicResourceLogics(3),
+            MalformedLogicInputs(MalformedLogicInputsError::MissingVersion),
+            InvalidTransactionSpec("spec".to_string()),
+            InconsistentResourceLogicCircuitSize("Token".to_string()),
+            ResourceLogicCircuitTooLarge(20),
+            InvalidReproving("reproving".to_string()),
+            UntrackedResource,
+            TooManyResources(3),
+            CommitmentTreeFull,
+            UnknownAnchor,
+            InsufficientQuantity(1),
+            InconsistentResourceKind,
+            DoubleSpend(0, Nullifier::default()),
+            InvalidBatchProof,
+            UnapprovedUpgrade,
+            PendingMerklePath,
+            NonPureIntentResource,
+            InvalidConstructionLog("log".to_string()),
+            TooManyApprovedUpgrades(17),
+            PullNotDue(0),
+            DuplicateDisclosure(0),
+            DuplicateDisclosedKind,
+            UnknownPartialTransaction(0),
+            StaleChannelTime(0),
+            InconsistentBatchedResult,
+        ]
+    }
+
+    #[test]
+    fn test_error_codes_are_stable() {
+        let errors = all_errors();
+        assert_eq!(errors.len(), PINNED_CODES.len());
+        for (error, (code, name)) in errors.iter().zip(PINNED_CODES) {
+            let debug = format!("{error:?}");
+            assert_eq!(debug.split('(').next().unwrap(), name);
+            assert_eq!(error.code(), code, "the code of {name} changed");
+        }
+    }
+}
@@ -0,0 +1,449 @@
+/// Execution traces of partial transactions, exported as JSON for audits.
+///
+/// A trace lists the resources of every ptx and every check run on them, with the values each
+/// check reads and binds: the compliance checks with the nullifiers, the output commitments and
+/// the anchors, the resource logic checks with their public inputs. Auditors review the behavior
+/// of an application from it without reading the circuits.
+///
+/// The transparent ptxs are traced from their bytecode. The shielded ptxs only carry proofs, so
+/// they are traced from the witnesses the prover holds before proving: the compliance infos and
+/// the resource logics. The checks run transparently, a failed check is recorded with its error
+/// and the trace goes on.
+use crate::{
+    circuit::{
+        resource_logic_bytecode::{ApplicationByteCode, ResourceLogicByteCode},
+        resource_logic_circuit::ResourceLogicPublicInputs,
+    },
+    compliance::ComplianceInfo,
+    error::TransactionError,
+    nullifier::Nullifier,
+    resource::{OwnedResourceId, Resource, ResourceCommitment, ResourceLogics},
+    resource_logic_vk_registry::RESOURCE_LOGIC_VK_REGISTRY,
+    transaction::TransparentPartialTxBundle,
+    transparent_ptx::TransparentPartialTransaction,
+    utils::hex_encode,
+};
+use ff::PrimeField;
+use pasta_curves::pallas;
+use serde::Serialize;
+
+fn hex(value: pallas::Base) -> String {
+    hex_encode(&value.to_repr())
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PartialTransactionKind {
+    Transparent,
+    Shielded,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceRole {
+    Input,
+    Output,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ResourceTrace {
+    pub role: ResourceRole,
+    pub index: usize,
+    /// The nullifier of an input resource or the commitment of an output resource
+    pub id: String,
+    pub logic: String,
+    pub label: String,
+    pub value: String,
+    pub quantity: u128,
+    pub nonce: String,
+    pub npk: String,
+    pub is_ephemeral: bool,
+    pub is_transparent_kind: bool,
+}
+
+impl ResourceTrace {
+    fn new(role: ResourceRole, index: usize, id: pallas::Base, resource: &Resource) -> Self {
+        Self {
+            role,
+            index,
+            id: hex(id),
+            logic: hex(resource.get_logic()),
+            label: hex(resource.get_label()),
+            value: hex(resource.value),
+            quantity: resource.quantity,
+            nonce: hex(resource.nonce.inner()),
+            npk: hex(resource.get_npk()),
+            is_ephemeral: resource.is_ephemeral,
+            is_transparent_kind: resource.is_transparent_kind,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CheckTrace {
+    /// The check, e.g. "compliance" or "application resource logic"
+    pub check: &'static str,
+    /// The resource the check is run for
+    pub role: ResourceRole,
+    pub index: usize,
+    /// The name of the resource logic, as in the vk registry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logic: Option<String>,
+    /// The ids of the resources the check reads
+    pub inputs: Vec<String>,
+    /// The values the check binds, e.g. the public inputs of a resource logic
+    pub outputs: Vec<String>,
+    /// The error of a failed check
+    pub error: Option<String>,
+}
+
+impl CheckTrace {
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PartialTransactionTrace {
+    pub kind: PartialTransactionKind,
+    pub resources: Vec<ResourceTrace>,
+    pub checks: Vec<CheckTrace>,
+}
+
+impl PartialTransactionTrace {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(CheckTrace::passed)
+    }
+
+    // Traces the compliances and returns the nullifiers and output commitments the resource
+    // logics are checked against
+    fn new(
+        kind: PartialTransactionKind,
+        compliances: &[ComplianceInfo],
+    ) -> (Self, Vec<Nullifier>, Vec<ResourceCommitment>) {
+        let mut trace = Self {
+            kind,
+            resources: vec![],
+            checks: vec![],
+        };
+        let nfs: Vec<_> = compliances
+            .iter()
+            .map(|compliance| compliance.get_input_resource_nullifier())
+            .collect();
+        let cms: Vec<_> = compliances
+            .iter()
+            .map(|compliance| compliance.get_output_resource_cm())
+            .collect();
+        for (index, compliance) in compliances.iter().enumerate() {
+            trace.resources.push(ResourceTrace::new(
+                ResourceRole::Input,
+                index,
+                nfs[index].inner(),
+                compliance.get_input_resource(),
+            ));
+            trace.resources.push(ResourceTrace::new(
+                ResourceRole::Output,
+                index,
+                cms[index].inner(),
+                compliance.get_output_resource(),
+            ));
+            trace.checks.push(CheckTrace {
+                check: "compliance",
+                role: ResourceRole::Input,
+                index,
+                logic: None,
+                inputs: vec![hex(nfs[index].inner()), hex(cms[index].inner())],
+                outputs: vec![hex(compliance.calculate_root().inner())],
+                error: compliance
+                    .verify_transparently()
+                    .err()
+                    .map(|e| e.to_string()),
+            });
+        }
+        (trace, nfs, cms)
+    }
+
+    // Records the check of a resource logic from its transparently computed public inputs
+    #[allow(clippy::too_many_arguments)]
+    fn add_resource_logic_check(
+        &mut self,
+        check: &'static str,
+        role: ResourceRole,
+        index: usize,
+        logic: String,
+        owned_resource_id: OwnedResourceId,
+        public_inputs: Result<ResourceLogicPublicInputs, TransactionError>,
+        nfs: &[Nullifier],
+        cms: &[ResourceCommitment],
+    ) {
+        let (outputs, result) = match public_inputs {
+            Ok(public_inputs) => {
+                let result = ResourceLogicByteCode::check_transparent_public_inputs(
+                    &public_inputs,
+                    nfs,
+                    cms,
+                )
+                .and_then(|id| {
+                    // Make sure the resource logic is checked for the resource
+                    if id == owned_resource_id {
+                        Ok(())
+                    } else {
+                        Err(TransactionError::InconsistentOwnedResourceID)
+                    }
+                });
+                (
+                    public_inputs
+                        .inner()
+                        .iter()
+                        .map(|input| hex(*input))
+                        .collect(),
+                    result,
+                )
+            }
+            Err(e) => (vec![], Err(e)),
+        };
+        self.checks.push(CheckTrace {
+            check,
+            role,
+            index,
+            logic: Some(logic),
+            inputs: vec![hex(owned_resource_id.inner())],
+            outputs,
+            error: result.err().map(|e| e.to_string()),
+        });
+    }
+
+    fn add_application(
+        &mut self,
+        role: ResourceRole,
+        index: usize,
+        app: &ApplicationByteCode,
+        owned_resource_id: OwnedResourceId,
+        nfs: &[Nullifier],
+        cms: &[ResourceCommitment],
+    ) {
+        if let Err(e) = app.check_dynamic_resource_logic_num() {
+            self.checks.push(CheckTrace {
+                check: "application",
+                role,
+                index,
+                logic: None,
+                inputs: vec![hex(owned_resource_id.inner())],
+                outputs: vec![],
+                error: Some(e.to_string()),
+            });
+            return;
+        }
+        let bytecodes = std::iter::once((
+            "application resource logic",
+            app.app_resource_logic_bytecode(),
+        ))
+        .chain(
+            app.dynamic_resource_logic_bytecode()
+                .iter()
+                .map(|bytecode| ("dynamic resource logic", bytecode)),
+        );
+        for (check, bytecode) in bytecodes {
+            self.add_resource_logic_check(
+                check,
+                role,
+                index,
+                bytecode.representation().name().to_string(),
+                owned_resource_id,
+                bytecode.transparent_public_inputs(),
+                nfs,
+                cms,
+            );
+        }
+    }
+
+    fn add_resource_logics(
+        &mut self,
+        role: ResourceRole,
+        index: usize,
+        resource_logics: &ResourceLogics,
+        owned_resource_id: OwnedResourceId,
+        nfs: &[Nullifier],
+        cms: &[ResourceCommitment],
+    ) {
+        let logics = std::iter::once((
+            "application resource logic",
+            resource_logics.get_application_resource_logic(),
+        ))
+        .chain(
+            resource_logics
+                .get_dynamic_resource_logics()
+                .iter()
+                .map(|logic| ("dynamic resource logic", logic.as_ref())),
+        );
+        for (check, logic) in logics {
+            let name = RESOURCE_LOGIC_VK_REGISTRY.describe_resource_logic(logic);
+            self.add_resource_logic_check(
+                check,
+                role,
+                index,
+                name,
+                owned_resource_id,
+                logic.verify_transparently(),
+                nfs,
+                cms,
+            );
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ExecutionTrace {
+    pub partial_transactions: Vec<PartialTransactionTrace>,
+}
+
+impl ExecutionTrace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_transparent_bundle(bundle: &TransparentPartialTxBundle) -> Self {
+        let mut trace = Self::new();
+        for ptx in bundle.partial_txs() {
+            trace.add_transparent_ptx(ptx);
+        }
+        trace
+    }
+
+    pub fn add_transparent_ptx(&mut self, ptx: &TransparentPartialTransaction) {
+        let (mut trace, nfs, cms) = PartialTransactionTrace::new(
+            PartialTransactionKind::Transparent,
+            ptx.get_compliances(),
+        );
+        for (index, (app, nf)) in ptx
+            .get_input_resource_apps()
+            .iter()
+            .zip(nfs.iter())
+            .enumerate()
+        {
+            trace.add_application(
+                ResourceRole::Input,
+                index,
+                app,
+                OwnedResourceId::Nf(*nf),
+                &nfs,
+                &cms,
+            );
+        }
+        for (index, (app, cm)) in ptx
+            .get_output_resource_apps()
+            .iter()
+            .zip(cms.iter())
+            .enumerate()
+        {
+            trace.add_application(
+                ResourceRole::Output,
+                index,
+                app,
+                OwnedResourceId::Cm(*cm),
+                &nfs,
+                &cms,
+            );
+        }
+        self.partial_transactions.push(trace);
+    }
+
+    /// Traces a shielded ptx from the witnesses passed to `ShieldedPartialTransaction::build`.
+    pub fn add_shielded_witnesses(
+        &mut self,
+        compliances: &[ComplianceInfo],
+        input_resource_logics: &[ResourceLogics],
+        output_resource_logics: &[ResourceLogics],
+    ) {
+        let (mut trace, nfs, cms) =
+            PartialTransactionTrace::new(PartialTransactionKind::Shielded, compliances);
+        for (index, (resource_logics, nf)) in
+            input_resource_logics.iter().zip(nfs.iter()).enumerate()
+        {
+            trace.add_resource_logics(
+                ResourceRole::Input,
+                index,
+                resource_logics,
+                OwnedResourceId::Nf(*nf),
+                &nfs,
+                &cms,
+            );
+        }
+        for (index, (resource_logics, cm)) in
+            output_resource_logics.iter().zip(cms.iter()).enumerate()
+        {
+            trace.add_resource_logics(
+                ResourceRole::Output,
+                index,
+                resource_logics,
+                OwnedResourceId::Cm(*cm),
+                &nfs,
+                &cms,
+            );
+        }
+        self.partial_transactions.push(trace);
+    }
+
+    /// Whether every traced check passed.
+    pub fn passed(&self) -> bool {
+        self.partial_transactions
+            .iter()
+            .all(PartialTransactionTrace::passed)
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "borsh")]
+mod tests {
+    use super::*;
+    use crate::transparent_ptx::testing::create_transparent_ptx;
+
+    #[test]
+    fn test_transparent_execution_trace() {
+        let ptx = create_transparent_ptx();
+        let mut trace = ExecutionTrace::new();
+        trace.add_transparent_ptx(&ptx);
+        assert!(trace.passed());
+
+        let ptx_trace = &trace.partial_transactions[0];
+        assert_eq!(ptx_trace.resources.len(), 4);
+        // Two compliances and four application resource logics
+        assert_eq!(ptx_trace.checks.len(), 6);
+        assert!(ptx_trace
+            .checks
+            .iter()
+            .filter(|check| check.logic.is_some())
+            .all(|check| check.logic.as_deref() == Some("Trivial")));
+
+        let json: serde_json::Value = serde_json::from_str(&trace.to_json().unwrap()).unwrap();
+        assert_eq!(
+            json["partial_transactions"][0]["kind"],
+            serde_json::json!("transparent")
+        );
+
+        // The resource logics of the inputs are swapped, their owned resource ids don't match
+        let compliances = ptx.get_compliances().to_vec();
+        let mut input_apps = ptx.get_input_resource_apps().to_vec();
+        input_apps.swap(0, 1);
+        let swapped = TransparentPartialTransaction::new(
+            compliances,
+            input_apps,
+            ptx.get_output_resource_apps().to_vec(),
+            vec![],
+        );
+        let mut trace = ExecutionTrace::new();
+        trace.add_transparent_ptx(&swapped);
+        assert!(!trace.passed());
+        let failed: Vec<_> = trace.partial_transactions[0]
+            .checks
+            .iter()
+            .filter(|check| !check.passed())
+            .collect();
+        assert_eq!(failed.len(), 2);
+        assert!(failed.iter().all(|check| check.role == ResourceRole::Input));
+    }
+}
@@ -0,0 +1,538 @@
+/// The C interface for embedding Taiga in nodes written in other languages, e.g. Go or C++.
+///
+/// Resources, shielded partial transactions and transactions are opaque handles, created by the
+/// `taiga_*` functions and released with their `*_free` function. The other values cross the
+/// boundary as byte buffers: the field elements as their 32-byte little-endian repr, the
+/// quantities as 16 little-endian bytes and the compound values as their borsh encodings, see
+/// `taiga_api` for the layouts. The partial transactions and transactions are prefixed with
+/// the format version, see `serialization`. The buffers returned by the library are released with
+/// `taiga_buffer_free`.
+///
+/// Every function returns a `TaigaStatus` and writes its result through the out pointer on
+/// success. On failure `taiga_last_error_message` describes the error, panics are caught and
+/// reported as `TaigaStatus::Panic` instead of unwinding into the caller.
+///
+/// The C declarations are generated with cbindgen into `include/taiga.h`, and
+/// `tests/ffi_smoke.c` links a C program against the library.
+use crate::{
+    circuit::resource_logic_bytecode::ApplicationByteCode,
+    compliance::ComplianceInfo,
+    resource::Resource,
+    shielded_ptx::ShieldedPartialTransaction,
+    taiga_api,
+    transaction::{ShieldedPartialTxBundle, Transaction, TransparentPartialTxBundle},
+};
+use borsh::BorshDeserialize;
+use ff::PrimeField;
+use pasta_curves::pallas;
+use rand::rngs::OsRng;
+use std::cell::RefCell;
+use std::ffi::{c_char, CString};
+use std::fmt::Display;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TaigaStatus {
+    Ok = 0,
+    NullPointer = 1,
+    /// A 32-byte input is not a canonical field element
+    InvalidField = 2,
+    /// A byte buffer is not a valid borsh encoding
+    DecodingFailed = 3,
+    /// Building or verifying the partial transaction or transaction failed
+    TransactionFailed = 4,
+    Panic = 5,
+}
+
+/// A byte buffer allocated by the library, released with `taiga_buffer_free`.
+#[repr(C)]
+pub struct TaigaBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl TaigaBuffer {
+    fn new(bytes: Vec<u8>) -> Self {
+        let len = bytes.len();
+        let data = Box::into_raw(bytes.into_boxed_slice()) as *mut u8;
+        Self { data, len }
+    }
+}
+
+pub struct TaigaResource(Resource);
+
+pub struct TaigaPartialTransaction(ShieldedPartialTransaction);
+
+pub struct TaigaTransaction(Transaction);
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+struct FfiError {
+    status: TaigaStatus,
+    message: String,
+}
+
+impl FfiError {
+    fn new(status: TaigaStatus, message: impl Display) -> Self {
+        Self {
+            status,
+            message: message.to_string(),
+        }
+    }
+}
+
+// Runs the body, records the error message of a failure and catches the panics
+fn run(body: impl FnOnce() -> Result<(), FfiError>) -> TaigaStatus {
+    let error = match catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(())) => return TaigaStatus::Ok,
+        Ok(Err(e)) => e,
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            FfiError::new(TaigaStatus::Panic, message)
+        }
+    };
+    LAST_ERROR.with(|last_error| {
+        *last_error.borrow_mut() = CString::new(error.message.replace('\0', " ")).ok();
+    });
+    error.status
+}
+
+fn transaction_error(e: impl Display) -> FfiError {
+    FfiError::new(TaigaStatus::TransactionFailed, e)
+}
+
+unsafe fn reference<'a, T>(handle: *const T) -> Result<&'a T, FfiError> {
+    handle
+        .as_ref()
+        .ok_or_else(|| FfiError::new(TaigaStatus::NullPointer, "null handle"))
+}
+
+unsafe fn write<T>(out: *mut T, value: T) -> Result<(), FfiError> {
+    if out.is_null() {
+        return Err(FfiError::new(TaigaStatus::NullPointer, "null out pointer"));
+    }
+    out.write(value);
+    Ok(())
+}
+
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> Result<&'a [u8], FfiError> {
+    if len == 0 {
+        return Ok(&[]);
+    }
+    if data.is_null() {
+        return Err(FfiError::new(TaigaStatus::NullPointer, "null buffer"));
+    }
+    Ok(std::slice::from_raw_parts(data, len))
+}
+
+unsafe fn decode<T: BorshDeserialize>(data: *const u8, len: usize) -> Result<T, FfiError> {
+    T::try_from_slice(bytes(data, len)?).map_err(|e| FfiError::new(TaigaStatus::DecodingFailed, e))
+}
+
+unsafe fn field(repr: *const [u8; 32]) -> Result<pallas::Base, FfiError> {
+    let repr = reference(repr)?;
+    Option::from(pallas::Base::from_repr(*repr)).ok_or_else(|| {
+        FfiError::new(
+            TaigaStatus::InvalidField,
+            "not a canonical field element encoding",
+        )
+    })
+}
+
+unsafe fn quantity(bytes: *const [u8; 16]) -> Result<u128, FfiError> {
+    Ok(u128::from_le_bytes(*reference(bytes)?))
+}
+
+/// The message of the last failure on the calling thread, or null. The string is owned by the
+/// library and valid until the next failing call on the thread.
+#[no_mangle]
+pub extern "C" fn taiga_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// # Safety
+/// The buffer must have been returned by the library and not released yet.
+#[no_mangle]
+pub unsafe extern "C" fn taiga_buffer_free(buffer: TaigaBuffer) {
+    if !buffer.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            buffer.data,
+            buffer.len,
+        )));
+    }
+}
+
+/// Creates an input resource with a random nonce and rseed, see
+/// `taiga_api::create_input_resource`.
+///
+/// # Safety
+/// The field pointers must point to 32 readable bytes, `quantity_le` to 16 and `out` must be
+/// writable.
+#[no_mangle]
+pub unsafe extern "C" fn taiga_resource_create_input(
+    logic: *const [u8; 32],
+    label: *const [u8; 32],
+    value: *const [u8; 32],
+    quantity_le: *const [u8; 16],
+    nk: *const [u8; 32],
+    is_ephemeral: bool,
+    out: *mut *mut TaigaResource,
+) -> TaigaStatus {
+    run(|| {
+        let resource = taiga_api::create_input_resource(
+            field(logic)?,
+            field(label)?,
+            field(value)?,
+            quantity(quantity_le)?,
+            field(nk)?,
+            is_ephemeral,
+            OsRng,
+        );
+        write(out, Box::into_raw(Box::new(TaigaResource(resource))))
+    })
+}
+
+/// Creates an output resource owned by the npk with a random rseed, see
+/// `taiga_api::create_output_resource`.
+///
+/// # Safety
+/// The field pointers must point to 32 readable bytes, `quantity_le` to 16 and `out` must be
+/// writable.
+#[no_mangle]
+pub unsafe extern "C" fn taiga_resource_create_output(
+    logic: *const [u8; 32],
+    label: *const [u8; 32],
+    value: *const [u8; 32],
+    quantity_le: *const [u8; 16],
+    npk: *const [u8; 32],
+    is_ephemeral: bool,
+    out: *mut *mut TaigaResource,
+) -> TaigaStatus {
+    run(|| {
+        let resource = taiga_api::create_output_resource(
+            field(logic)?,
+            field(label)?,
+            field(value)?,
+            quantity(quantity_le)?,
+            field(npk)?,
+            is_ephemeral,
+            OsRng,
+        );
+        write(out, Box::into_raw(Box::new(TaigaResource(resource))))
+    })
+}
+
+/// # Safety
+/// `data` must point to `len` readable bytes and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn taiga_resource_from_bytes(
+    data: *const u8,
+    len: usize,
+    out: *mut *mut TaigaResource,
+) -> TaigaStatus {
+    run(|| {
+        let resource = taiga_api::resource_deserialize(bytes(data, len)?.to_vec())
+            .map_err(|e| FfiError::new(TaigaStatus::DecodingFailed, e))?;
+        write(out, Box::into_raw(Box::new(TaigaResource(resource))))
+    })
+}
+
+/// # Safety
+/// `resource` must be a live handle and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn taiga_resource_to_bytes(
+    resource: *const TaigaResource,
+    out: *mut TaigaBuffer,
+) -> TaigaStatus {
+    run(|| {
+        let bytes = taiga_api::resource_serialize(&reference(resource)?.0)
+            .map_err(|e| FfiError::new(TaigaStatus::DecodingFailed, e))?;
+        write(out, TaigaBuffer::new(bytes))
+    })
+}
+
+/// Writes the commitment of the resource.
+///
+/// # Safety
+/// `resource` must be a live handle and `out` must point to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn taiga_resource_commitment(
+    resource: *const TaigaResource,
+    out: *mut [u8; 32],
+) -> TaigaStatus {
+    run(|| {
+        let cm = reference(resource)?.0.commitment();
+        write(out, cm.inner().to_repr())
+    })
+}
+
+/// # Safety
+/// `resource` must be null or a live handle, it is invalid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn taiga_resource_free(resource: *mut TaigaResource) {
+    if !resource.is_null() {
+        drop(Box::from_raw(resource));
+    }
+}
+
+/// Builds a shielded partial transaction from the borsh encoded compliance infos
+/// (`Vec<ComplianceInfo>`) and applications of the input and output resources
+/// (`Vec<ApplicationByteCode>`), generating all the proofs.
+///
+/// # Safety
+/// Every buffer must point to its length of readable bytes and `out` must be writable.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn taiga_ptx_from_bytecode(
+    compliances: *const u8,
+    compliances_len: usize,
+    input_resource_apps: *const u8,
+    input_resource_apps_len: usize,
+    output_resource_apps: *const u8,
+    output_resource_apps_len: usize,
+    hints: *const u8,
+    hints_len: usize,
+    out: *mut *mut TaigaPartialTransaction,
+) -> TaigaStatus {
+    run(|| {
+        let compliances: Vec<ComplianceInfo> = decode(compliances, compliances_len)?;
+        let input_resource_apps: Vec<ApplicationByteCode> =
+            decode(input_resource_apps, input_resource_apps_len)?;
+        let output_resource_apps: Vec<ApplicationByteCode> =
+            decode(output_resource_apps, output_resource_apps_len)?;
+        let ptx = taiga_api::create_shielded_partial_transaction(
+            compliances,
+            input_resource_apps,
+            output_resource_apps,
+            bytes(hints, hints_len)?.to_vec(),
+            OsRng,
+        )
+        .map_err(transaction_error)?;
+        write(out, Box::into_raw(Box::new(TaigaPartialTransaction(ptx))))
+    })
+}
+
+/// # Safety
+/// `data` must point to `len` readable bytes and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn taiga_ptx_from_bytes(
+    data: *const u8,
+    len: usize,
+    out: *mut *mut TaigaPartialTransaction,
+) -> TaigaStatus {
+    run(|| {
+        let ptx = taiga_api::partial_transaction_deserialize(bytes(data, len)?.to_vec())
+            .map_err(|e| FfiError::new(TaigaStatus::DecodingFailed, e))?;
+        write(out, Box::into_raw(Box::new(TaigaPartialTransaction(ptx))))
+    })
+}
+
+/// # Safety
+/// `ptx` must be a live handle and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn taiga_ptx_to_bytes(
+    ptx: *const TaigaPartialTransaction,
+    out: *mut TaigaBuffer,
+) -> TaigaStatus {
+    run(|| {
+        let bytes = taiga_api::partial_transaction_serialize(&reference(ptx)?.0)
+            .map_err(|e| FfiError::new(TaigaStatus::DecodingFailed, e))?;
+        write(out, TaigaBuffer::new(bytes))
+    })
+}
+
+/// Verifies the proofs of the partial transaction.
+///
+/// # Safety
+/// `ptx` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn taiga_ptx_verify(ptx: *const TaigaPartialTransaction) -> TaigaStatus {
+    run(|| reference(ptx)?.0.verify_proof().map_err(transaction_error))
+}
+
+/// # Safety
+/// `ptx` must be null or a live handle, it is invalid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn taiga_ptx_free(ptx: *mut TaigaPartialTransaction) {
+    if !ptx.is_null() {
+        drop(Box::from_raw(ptx));
+    }
+}
+
+/// Builds a transaction from the shielded partial transactions, the handles stay valid.
+///
+/// # Safety
+/// `ptxs` must point to `len` live handles and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn taiga_transaction_build(
+    ptxs: *const *const TaigaPartialTransaction,
+    len: usize,
+    out: *mut *mut TaigaTransaction,
+) -> TaigaStatus {
+    run(|| {
+        let handles = if len == 0 {
+            &[][..]
+        } else {
+            reference(ptxs)?;
+            std::slice::from_raw_parts(ptxs, len)
+        };
+        let ptxs = handles
+            .iter()
+            .map(|ptx| reference(*ptx).map(|ptx| ptx.0.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let tx = Transaction::build(
+            OsRng,
+            ShieldedPartialTxBundle::new(ptxs),
+            TransparentPartialTxBundle::default(),
+        )
+        .map_err(transaction_error)?;
+        write(out, Box::into_raw(Box::new(TaigaTransaction(tx))))
+    })
+}
+
+/// # Safety
+/// `data` must point to `len` readable bytes and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn taiga_transaction_from_bytes(
+    data: *const u8,
+    len: usize,
+    out: *mut *mut TaigaTransaction,
+) -> TaigaStatus {
+    run(|| {
+        let tx = taiga_api::transaction_deserialize(bytes(data, len)?.to_vec())
+            .map_err(|e| FfiError::new(TaigaStatus::DecodingFailed, e))?;
+        write(out, Box::into_raw(Box::new(TaigaTransaction(tx))))
+    })
+}
+
+/// # Safety
+/// `tx` must be a live handle and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn taiga_transaction_to_bytes(
+    tx: *const TaigaTransaction,
+    out: *mut TaigaBuffer,
+) -> TaigaStatus {
+    run(|| {
+        let bytes = taiga_api::transaction_serialize(&reference(tx)?.0)
+            .map_err(|e| FfiError::new(TaigaStatus::DecodingFailed, e))?;
+        write(out, TaigaBuffer::new(bytes))
+    })
+}
+
+/// Verifies the transaction and writes the borsh encoded `TransactionResult`.
+///
+/// # Safety
+/// `tx` must be a live handle and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn taiga_transaction_execute(
+    tx: *const TaigaTransaction,
+    out: *mut TaigaBuffer,
+) -> TaigaStatus {
+    run(|| {
+        let result = reference(tx)?.0.execute().map_err(transaction_error)?;
+        let bytes =
+            borsh::to_vec(&result).map_err(|e| FfiError::new(TaigaStatus::DecodingFailed, e))?;
+        write(out, TaigaBuffer::new(bytes))
+    })
+}
+
+/// # Safety
+/// `tx` must be null or a live handle, it is invalid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn taiga_transaction_free(tx: *mut TaigaTransaction) {
+    if !tx.is_null() {
+        drop(Box::from_raw(tx));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    #[test]
+    fn test_ffi_resource() {
+        let logic = pallas::Base::from(1u64).to_repr();
+        let label = pallas::Base::from(2u64).to_repr();
+        let value = pallas::Base::from(3u64).to_repr();
+        let nk = pallas::Base::from(4u64).to_repr();
+        let quantity = 5u128.to_le_bytes();
+
+        unsafe {
+            let mut resource = ptr::null_mut();
+            let status = taiga_resource_create_input(
+                &logic,
+                &label,
+                &value,
+                &quantity,
+                &nk,
+                false,
+                &mut resource,
+            );
+            assert_eq!(status, TaigaStatus::Ok);
+            assert_eq!((*resource).0.quantity, 5);
+
+            let mut buffer = TaigaBuffer {
+                data: ptr::null_mut(),
+                len: 0,
+            };
+            assert_eq!(
+                taiga_resource_to_bytes(resource, &mut buffer),
+                TaigaStatus::Ok
+            );
+            assert_eq!(buffer.len, taiga_api::RESOURCE_SIZE);
+            let mut decoded = ptr::null_mut();
+            assert_eq!(
+                taiga_resource_from_bytes(buffer.data, buffer.len, &mut decoded),
+                TaigaStatus::Ok
+            );
+            assert_eq!((*decoded).0, (*resource).0);
+
+            let mut cm = [0u8; 32];
+            assert_eq!(taiga_resource_commitment(decoded, &mut cm), TaigaStatus::Ok);
+            assert_eq!(cm, (*resource).0.commitment().inner().to_repr());
+
+            taiga_buffer_free(buffer);
+            taiga_resource_free(decoded);
+            taiga_resource_free(resource);
+
+            // The failures are reported with a status and a message
+            let invalid_field = [0xffu8; 32];
+            let mut resource = ptr::null_mut();
+            let status = taiga_resource_create_input(
+                &invalid_field,
+                &label,
+                &value,
+                &quantity,
+                &nk,
+                false,
+                &mut resource,
+            );
+            assert_eq!(status, TaigaStatus::InvalidField);
+            assert!(resource.is_null());
+            assert!(!taiga_last_error_message().is_null());
+
+            let status = taiga_resource_from_bytes([1u8, 2, 3].as_ptr(), 3, &mut resource);
+            assert_eq!(status, TaigaStatus::DecodingFailed);
+            assert_eq!(
+                taiga_resource_commitment(ptr::null(), &mut cm),
+                TaigaStatus::NullPointer
+            );
+            let message = CStr::from_ptr(taiga_last_error_message());
+            assert_eq!(message.to_str().unwrap(), "null handle");
+        }
+    }
+}
@@ -0,0 +1,286 @@
+/// Fuzzy message detection (the FMD2 scheme of Beck, Len, Miers and Green).
+///
+/// A receiver holds an `FmdSecretKey` and publishes the `FmdPublicKey`. When creating an output
+/// resource for the receiver, the sender generates a `FuzzyDetectionTag` from the public key and
+/// publishes it along the ciphertext. The receiver hands an `FmdDetectionKey` of a chosen
+/// precision n to a detection server. The server matches every tag of the receiver, and other
+/// tags with probability 2^-n, so it learns which outputs may belong to the receiver only up to
+/// the false positive rate the receiver chose.
+///
+/// The ptx builder tags the outputs added with `add_detectable_output`, and the server finds
+/// them in the partial transactions with `FmdDetectionKey::detect_outputs`.
+use crate::{
+    constant::{FMD_BIT_HASH_PERSONALIZATION, FMD_GAMMA, FMD_SCALAR_HASH_PERSONALIZATION},
+    shielded_ptx::ShieldedPartialTransaction,
+};
+use blake2b_simd::Params as Blake2bParams;
+use ff::{Field, FromUniformBytes};
+use group::{Group, GroupEncoding};
+use pasta_curves::pallas;
+use rand::RngCore;
+
+#[cfg(feature = "borsh")]
+use crate::utils::{read_point, read_scalar_field};
+#[cfg(feature = "borsh")]
+use borsh::{BorshDeserialize, BorshSerialize};
+#[cfg(feature = "borsh")]
+use ff::PrimeField;
+
+#[cfg(feature = "nif")]
+use rustler::{Decoder, Encoder, Env, NifResult, Term};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FmdSecretKey([pallas::Scalar; FMD_GAMMA]);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FmdPublicKey([pallas::Point; FMD_GAMMA]);
+
+/// The first n secret keys, given to a detection server.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FmdDetectionKey(Vec<pallas::Scalar>);
+
+/// tag = (P = r * G, y = (z - m) / r, c) where m = H(P || c) and, for each i,
+/// c_i = H(P || r * X_i || z * G) xor 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FuzzyDetectionTag {
+    p: pallas::Point,
+    y: pallas::Scalar,
+    // the bit c_i at position i
+    ciphertext_bits: u32,
+}
+
+impl FmdSecretKey {
+    pub fn random<R: RngCore>(mut rng: R) -> Self {
+        Self([(); FMD_GAMMA].map(|_| pallas::Scalar::random(&mut rng)))
+    }
+
+    pub fn public_key(&self) -> FmdPublicKey {
+        FmdPublicKey(self.0.map(|x| pallas::Point::generator() * x))
+    }
+
+    /// Extracts the detection key with a false positive rate of 2^-precision.
+    pub fn detection_key(&self, precision: usize) -> FmdDetectionKey {
+        assert!(
+            precision <= FMD_GAMMA,
+            "the precision of a detection key is at most {FMD_GAMMA}"
+        );
+        FmdDetectionKey(self.0[..precision].to_vec())
+    }
+}
+
+impl FmdPublicKey {
+    /// Generates the tag of an output resource for the owner of the key.
+    pub fn generate_tag<R: RngCore>(&self, mut rng: R) -> FuzzyDetectionTag {
+        let r = pallas::Scalar::random(&mut rng);
+        let z = pallas::Scalar::random(&mut rng);
+        let p = pallas::Point::generator() * r;
+        let q = pallas::Point::generator() * z;
+        let ciphertext_bits = self.0.iter().enumerate().fold(0u32, |bits, (i, x)| {
+            let k = hash_to_bit(&p, &(x * r), &q);
+            bits | (u32::from(k ^ 1) << i)
+        });
+        let m = hash_to_scalar(&p, ciphertext_bits);
+        let y = (z - m) * r.invert().unwrap();
+        FuzzyDetectionTag {
+            p,
+            y,
+            ciphertext_bits,
+        }
+    }
+}
+
+impl FmdDetectionKey {
+    pub fn precision(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn false_positive_rate(&self) -> f64 {
+        0.5f64.powi(self.precision() as i32)
+    }
+
+    /// Returns true if the tag was generated for the owner of the key, and for other tags with
+    /// probability of the false positive rate.
+    pub fn test(&self, tag: &FuzzyDetectionTag) -> bool {
+        let m = hash_to_scalar(&tag.p, tag.ciphertext_bits);
+        let q = tag.p * tag.y + pallas::Point::generator() * m;
+        self.0.iter().enumerate().all(|(i, x)| {
+            let k = hash_to_bit(&tag.p, &(tag.p * x), &q);
+            k ^ ((tag.ciphertext_bits >> i) as u8 & 1) == 1
+        })
+    }
+
+    /// Returns the indexes of the matching tags.
+    pub fn detect<'a>(&self, tags: impl IntoIterator<Item = &'a FuzzyDetectionTag>) -> Vec<usize> {
+        tags.into_iter()
+            .enumerate()
+            .filter(|(_, tag)| self.test(tag))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Returns the (partial transaction, output) indexes of the matching outputs of the partial
+    /// transactions.
+    pub fn detect_outputs<'a>(
+        &self,
+        ptxs: impl IntoIterator<Item = &'a ShieldedPartialTransaction>,
+    ) -> Vec<(usize, usize)> {
+        ptxs.into_iter()
+            .enumerate()
+            .flat_map(|(ptx_index, ptx)| {
+                self.detect(ptx.get_output_detection_tags())
+                    .into_iter()
+                    .map(move |output_index| (ptx_index, output_index))
+            })
+            .collect()
+    }
+}
+
+// H(P || r * X_i || Q), a single bit
+fn hash_to_bit(p: &pallas::Point, shared: &pallas::Point, q: &pallas::Point) -> u8 {
+    let mut h = Blake2bParams::new()
+        .hash_length(32)
+        .personal(FMD_BIT_HASH_PERSONALIZATION)
+        .to_state();
+    h.update(&p.to_bytes());
+    h.update(&shared.to_bytes());
+    h.update(&q.to_bytes());
+    h.finalize().as_bytes()[0] & 1
+}
+
+// H(P || c), a scalar
+fn hash_to_scalar(p: &pallas::Point, ciphertext_bits: u32) -> pallas::Scalar {
+    let mut h = Blake2bParams::new()
+        .hash_length(64)
+        .personal(FMD_SCALAR_HASH_PERSONALIZATION)
+        .to_state();
+    h.update(&p.to_bytes());
+    h.update(&ciphertext_bits.to_le_bytes());
+    let bytes = *h.finalize().as_array();
+    pallas::Scalar::from_uniform_bytes(&bytes)
+}
+
+#[cfg(feature = "borsh")]
+impl BorshSerialize for FuzzyDetectionTag {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(&self.p.to_bytes())?;
+        writer.write_all(&self.y.to_repr())?;
+        writer.write_all(&self.ciphertext_bits.to_le_bytes())?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl BorshDeserialize for FuzzyDetectionTag {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let p = read_point(reader)?;
+        let y = read_scalar_field(reader)?;
+        let mut ciphertext_bits = [0u8; 4];
+        reader.read_exact(&mut ciphertext_bits)?;
+        Ok(Self {
+            p,
+            y,
+            ciphertext_bits: u32::from_le_bytes(ciphertext_bits),
+        })
+    }
+}
+
+// The NIF term of a tag is its borsh encoding
+#[cfg(feature = "nif")]
+impl Encoder for FuzzyDetectionTag {
+    fn encode<'a>(&self, env: Env<'a>) -> Term<'a> {
+        borsh::to_vec(self).unwrap_or_default().encode(env)
+    }
+}
+
+#[cfg(feature = "nif")]
+impl<'a> Decoder<'a> for FuzzyDetectionTag {
+    fn decode(term: Term<'a>) -> NifResult<Self> {
+        let bytes: Vec<u8> = term.decode()?;
+        BorshDeserialize::deserialize(&mut bytes.as_slice())
+            .map_err(|_e| rustler::Error::Atom("Failure to decode"))
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl BorshSerialize for FmdDetectionKey {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(&[self.0.len() as u8])?;
+        for x in self.0.iter() {
+            writer.write_all(&x.to_repr())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl BorshDeserialize for FmdDetectionKey {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let mut precision = [0u8; 1];
+        reader.read_exact(&mut precision)?;
+        let precision = precision[0] as usize;
+        if precision > FMD_GAMMA {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "invalid detection key precision",
+            ));
+        }
+        let xs = (0..precision)
+            .map(|_| read_scalar_field(reader))
+            .collect::<Result<_, _>>()?;
+        Ok(Self(xs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FmdSecretKey;
+    use crate::constant::FMD_GAMMA;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn fmd_detection_test() {
+        let mut rng = OsRng;
+        let sk = FmdSecretKey::random(&mut rng);
+        let pk = sk.public_key();
+        let other_pk = FmdSecretKey::random(&mut rng).public_key();
+
+        let own_tags: Vec<_> = (0..10).map(|_| pk.generate_tag(&mut rng)).collect();
+        let other_tags: Vec<_> = (0..200).map(|_| other_pk.generate_tag(&mut rng)).collect();
+
+        // The full precision key matches exactly the own tags
+        let dk = sk.detection_key(FMD_GAMMA);
+        assert_eq!(dk.detect(&own_tags), (0..10).collect::<Vec<_>>());
+        assert!(dk.detect(&other_tags).is_empty());
+
+        // The precision 0 key matches everything
+        let dk = sk.detection_key(0);
+        assert_eq!(dk.false_positive_rate(), 1.0);
+        assert_eq!(dk.detect(&other_tags).len(), other_tags.len());
+
+        // A precision 2 key matches about a quarter of the other tags
+        let dk = sk.detection_key(2);
+        assert!(own_tags.iter().all(|tag| dk.test(tag)));
+        let false_positives = dk.detect(&other_tags).len();
+        assert!((20..=80).contains(&false_positives));
+    }
+
+    #[cfg(feature = "borsh")]
+    #[test]
+    fn fmd_borsh_serialization_test() {
+        use super::{FmdDetectionKey, FuzzyDetectionTag};
+        use borsh::BorshDeserialize;
+
+        let mut rng = OsRng;
+        let sk = FmdSecretKey::random(&mut rng);
+        let tag = sk.public_key().generate_tag(&mut rng);
+        let borsh = borsh::to_vec(&tag).unwrap();
+        let de_tag = FuzzyDetectionTag::deserialize(&mut borsh.as_ref()).unwrap();
+        assert_eq!(tag, de_tag);
+
+        let dk = sk.detection_key(10);
+        let borsh = borsh::to_vec(&dk).unwrap();
+        let de_dk = FmdDetectionKey::deserialize(&mut borsh.as_ref()).unwrap();
+        assert_eq!(dk, de_dk);
+    }
+}
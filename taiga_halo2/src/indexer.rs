@@ -0,0 +1,276 @@
+/// Ingests the executed transactions into the ledger state kept in a `Storage`.
+///
+/// A transaction is ingested if its anchors are known roots and its nullifiers are not spent:
+/// its nullifiers are recorded, its output commitments are appended to the commitment tree and
+/// the new root is recorded as an anchor, in a single storage batch. The frontier of the
+/// commitment tree and the nullifier set are rebuilt from the storage when the indexer is
+/// opened.
+///
+/// A block is ingested with the detection tags published along its transactions, and the
+/// detection filter over the tags of the ingested transactions is returned for publication with
+/// the block.
+use crate::{
+    error::TransactionError,
+    merkle_tree::{Anchor, CommitmentTree},
+    nullifier::NullifierSet,
+    resource_detection::{BlockDetectionFilter, DetectionTag},
+    storage::{Storage, StorageBatch},
+    transaction::{Transaction, TransactionResult},
+};
+use std::io;
+
+pub struct Indexer<S: Storage> {
+    storage: S,
+    tree: CommitmentTree,
+    nullifiers: NullifierSet,
+}
+
+/// An ingested block.
+#[derive(Debug, Clone)]
+pub struct IngestedBlock {
+    /// The detection filter over the tags of the ingested transactions.
+    pub detection_filter: BlockDetectionFilter,
+    /// The indexes of the rejected transactions, left out of the block.
+    pub rejected: Vec<usize>,
+}
+
+impl<S: Storage> Indexer<S> {
+    /// Opens the indexer on the storage. The root of an empty storage is recorded as the first
+    /// anchor.
+    pub fn new(mut storage: S) -> Result<Self, TransactionError> {
+        let mut tree = CommitmentTree::new();
+        for position in 0..storage.num_commitments()? {
+            let cm = storage.get_commitment(position)?.ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "missing stored commitment")
+            })?;
+            tree.append(cm)?;
+        }
+        if storage.latest_anchor()?.is_none() {
+            storage.write_batch(&StorageBatch {
+                anchor: Some(tree.root()),
+                ..Default::default()
+            })?;
+        }
+        let nullifiers = storage.nullifiers()?;
+        Ok(Self {
+            storage,
+            tree,
+            nullifiers,
+        })
+    }
+
+    /// Executes the transaction and ingests it. The double spends are rejected before the
+    /// proofs are checked.
+    pub fn ingest_transaction(
+        &mut self,
+        tx: &Transaction,
+    ) -> Result<TransactionResult, TransactionError> {
+        self.nullifiers.check_transaction(tx)?;
+        let result = tx.execute()?;
+        self.write(&result)?;
+        Ok(result)
+    }
+
+    /// Ingests a transaction executed by the caller, `result` is the result of `tx.execute()`.
+    /// Nothing is written if the transaction is rejected.
+    pub fn ingest(
+        &mut self,
+        tx: &Transaction,
+        result: &TransactionResult,
+    ) -> Result<(), TransactionError> {
+        self.nullifiers.check_transaction(tx)?;
+        self.write(result)
+    }
+
+    fn write(&mut self, result: &TransactionResult) -> Result<(), TransactionError> {
+        for anchor in result.anchors.iter() {
+            if !self.storage.contains_anchor(anchor)? {
+                return Err(TransactionError::UnknownAnchor);
+            }
+        }
+        let mut tree = self.tree.clone();
+        for cm in result.output_cms.iter() {
+            tree.append(*cm)?;
+        }
+        self.storage.write_batch(&StorageBatch {
+            nullifiers: result.nullifiers.clone(),
+            commitments: result.output_cms.clone(),
+            anchor: Some(tree.root()),
+        })?;
+        self.nullifiers
+            .insert_batch(result.nullifiers.iter().copied());
+        self.tree = tree;
+        Ok(())
+    }
+
+    /// Executes and ingests the transactions of a block in order, with the detection tags of
+    /// their outputs. The rejected transactions and their tags are left out of the block, a
+    /// storage error aborts it.
+    pub fn ingest_block(
+        &mut self,
+        block: &[(Transaction, Vec<DetectionTag>)],
+    ) -> Result<IngestedBlock, TransactionError> {
+        let results: Vec<_> = block.iter().map(|(tx, _)| tx.execute()).collect();
+        self.ingest_block_results(
+            block
+                .iter()
+                .zip(results)
+                .map(|((tx, tags), result)| (tx, result, tags.as_slice())),
+        )
+    }
+
+    /// Ingests the transactions of a block with the results of their execution, see
+    /// `ingest_block`.
+    pub fn ingest_block_results<'a>(
+        &mut self,
+        block: impl IntoIterator<
+            Item = (
+                &'a Transaction,
+                Result<TransactionResult, TransactionError>,
+                &'a [DetectionTag],
+            ),
+        >,
+    ) -> Result<IngestedBlock, TransactionError> {
+        let mut tags = vec![];
+        let mut rejected = vec![];
+        for (index, (tx, result, tx_tags)) in block.into_iter().enumerate() {
+            match result.and_then(|result| self.ingest(tx, &result)) {
+                Ok(()) => tags.extend_from_slice(tx_tags),
+                Err(TransactionError::IoError(e)) => return Err(TransactionError::IoError(e)),
+                Err(_) => rejected.push(index),
+            }
+        }
+        Ok(IngestedBlock {
+            detection_filter: BlockDetectionFilter::new(&tags),
+            rejected,
+        })
+    }
+
+    pub fn root(&self) -> Anchor {
+        self.tree.root()
+    }
+
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    pub fn into_storage(self) -> S {
+        self.storage
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "borsh")]
+mod tests {
+    use super::*;
+    use crate::{
+        storage::MemoryStorage,
+        transaction::{testing::create_transparent_ptx_bundle, ShieldedPartialTxBundle},
+    };
+    use rand::rngs::OsRng;
+
+    // A transaction of a transparent ptx, with its result. The storage knows its anchors.
+    fn create_transaction(storage: &mut MemoryStorage) -> (Transaction, TransactionResult) {
+        let tx = Transaction::build(
+            OsRng,
+            ShieldedPartialTxBundle::default(),
+            create_transparent_ptx_bundle(1),
+        )
+        .unwrap();
+        let result = tx.execute().unwrap();
+        for anchor in result.anchors.iter() {
+            storage
+                .write_batch(&StorageBatch {
+                    anchor: Some(*anchor),
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+        (tx, result)
+    }
+
+    #[test]
+    fn test_indexer() {
+        let mut storage = MemoryStorage::new();
+        let (tx, result) = create_transaction(&mut storage);
+        let (unknown_anchor_tx, _) = create_transaction(&mut MemoryStorage::new());
+
+        let mut indexer = Indexer::new(storage).unwrap();
+        let root = indexer.root();
+        assert_eq!(indexer.ingest_transaction(&tx).unwrap(), result);
+        assert_ne!(indexer.root(), root);
+        assert_eq!(
+            indexer.storage().num_commitments().unwrap(),
+            result.output_cms.len() as u64
+        );
+        assert_eq!(
+            indexer.storage().latest_anchor().unwrap(),
+            Some(indexer.root())
+        );
+        assert!(result
+            .nullifiers
+            .iter()
+            .all(|nf| indexer.storage().contains_nullifier(nf).unwrap()));
+
+        // The nullifiers are spent
+        assert!(matches!(
+            indexer.ingest(&tx, &result),
+            Err(TransactionError::DoubleSpend(0, _))
+        ));
+        // The anchors are not roots of the tree
+        assert!(matches!(
+            indexer.ingest_transaction(&unknown_anchor_tx),
+            Err(TransactionError::UnknownAnchor)
+        ));
+        // The rejected transactions aren't written
+        assert_eq!(
+            indexer.storage().num_commitments().unwrap(),
+            result.output_cms.len() as u64
+        );
+
+        // Reopening the storage rebuilds the commitment tree and the nullifier set
+        let root = indexer.root();
+        let mut indexer = Indexer::new(indexer.into_storage()).unwrap();
+        assert_eq!(indexer.root(), root);
+        assert!(matches!(
+            indexer.ingest(&tx, &result),
+            Err(TransactionError::DoubleSpend(0, _))
+        ));
+    }
+
+    #[test]
+    fn test_indexer_block() {
+        use ff::{Field, PrimeField};
+        use pasta_curves::pallas;
+
+        let mut rng = OsRng;
+        let mut storage = MemoryStorage::new();
+        let (first, first_result) = create_transaction(&mut storage);
+        let (second, second_result) = create_transaction(&mut storage);
+        let mut indexer = Indexer::new(storage).unwrap();
+        let tags: Vec<_> = (0..3)
+            .map(|_| DetectionTag::from_bytes(pallas::Base::random(&mut rng).to_repr()).unwrap())
+            .collect();
+
+        // The second spend of the nullifiers and the failed execution are rejected with their tags
+        let block = indexer
+            .ingest_block_results([
+                (&first, Ok(first_result.clone()), &tags[0..1]),
+                (&first, Ok(first_result.clone()), &tags[1..2]),
+                (
+                    &second,
+                    Err(TransactionError::InconsistentNullifier),
+                    &tags[1..2],
+                ),
+                (&second, Ok(second_result.clone()), &tags[2..3]),
+            ])
+            .unwrap();
+        assert_eq!(block.rejected, vec![1, 2]);
+        assert_eq!(
+            indexer.storage().num_commitments().unwrap(),
+            (first_result.output_cms.len() + second_result.output_cms.len()) as u64
+        );
+        assert!(block.detection_filter.may_contain(&tags[0]));
+        assert!(block.detection_filter.may_contain(&tags[2]));
+    }
+}
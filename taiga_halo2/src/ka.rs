@@ -0,0 +1,168 @@
+/// Key agreement of the resource encryption.
+///
+/// The sender generates an ephemeral key and agrees on a shared secret with the receiver's
+/// public key by a DH exchange on Pallas. The conventions are the ones of the receiver resource
+/// logic circuit:
+///   - the ephemeral secret key is a base field element, used as a scalar by `mod_r_p`,
+///   - the ephemeral public key is `esk * GENERATOR`, published as its (x, y) coordinates,
+///   - the shared secret is the DH point, the Poseidon sponge of `ResourceCiphertext` is keyed with
+///     its coordinates.
+/// Off-circuit encryptions (e.g. the AEAD ciphers) derive their symmetric keys from the shared
+/// secret with `SharedSecret::kdf`, under their own personalization.
+use crate::{constant::GENERATOR, utils::mod_r_p};
+use blake2b_simd::Params as Blake2bParams;
+use ff::{Field, PrimeField};
+use group::{prime::PrimeCurveAffine, Curve, GroupEncoding};
+use halo2_proofs::arithmetic::CurveAffine;
+use pasta_curves::pallas;
+use rand::RngCore;
+
+/// The ephemeral secret key of the sender.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EphemeralSecretKey(pallas::Base);
+
+/// The ephemeral public key, published along the ciphertext.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EphemeralPublicKey(pallas::Point);
+
+/// The DH shared secret of the sender and the receiver.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SharedSecret(pallas::Point);
+
+impl EphemeralSecretKey {
+    pub fn random<R: RngCore>(rng: R) -> Self {
+        Self(pallas::Base::random(rng))
+    }
+
+    pub fn from_base(esk: pallas::Base) -> Self {
+        Self(esk)
+    }
+
+    pub fn inner(&self) -> pallas::Base {
+        self.0
+    }
+
+    pub fn public_key(&self) -> EphemeralPublicKey {
+        EphemeralPublicKey(GENERATOR.to_curve() * mod_r_p(self.0))
+    }
+
+    /// The sender side of the key agreement.
+    pub fn agree(&self, rcv_pk: &pallas::Point) -> SharedSecret {
+        SharedSecret::from_dh_exchange(rcv_pk, &mod_r_p(self.0))
+    }
+}
+
+impl EphemeralPublicKey {
+    pub fn from_point(epk: pallas::Point) -> Self {
+        Self(epk)
+    }
+
+    pub fn inner(&self) -> pallas::Point {
+        self.0
+    }
+
+    /// The encoding published by the receiver resource logic.
+    pub fn to_coordinates(&self) -> (pallas::Base, pallas::Base) {
+        let coordinates = self.0.to_affine().coordinates().unwrap();
+        (*coordinates.x(), *coordinates.y())
+    }
+
+    /// Returns None if (x, y) is not on the curve.
+    pub fn from_coordinates(x: pallas::Base, y: pallas::Base) -> Option<Self> {
+        Option::<pallas::Affine>::from(pallas::Affine::from_xy(x, y)).map(|epk| Self(epk.into()))
+    }
+
+    /// The compressed encoding, for the off-circuit ciphertexts.
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_bytes()
+    }
+
+    pub fn from_bytes(bytes: &[u8; 32]) -> Option<Self> {
+        Option::from(pallas::Point::from_bytes(bytes)).map(Self)
+    }
+
+    /// The receiver side of the key agreement.
+    pub fn agree(&self, rcv_sk: &pallas::Scalar) -> SharedSecret {
+        SharedSecret::from_dh_exchange(&self.0, rcv_sk)
+    }
+}
+
+impl SharedSecret {
+    pub fn from_dh_exchange(pk: &pallas::Point, sk: &pallas::Scalar) -> Self {
+        Self(pk * sk)
+    }
+
+    pub fn inner(&self) -> pallas::Point {
+        self.0
+    }
+
+    pub fn get_coordinates(&self) -> (pallas::Base, pallas::Base) {
+        let coordinates = self.0.to_affine().coordinates().unwrap();
+        (*coordinates.x(), *coordinates.y())
+    }
+
+    /// Derives a 32-byte symmetric key, domain separated by the personalization.
+    /// key = blake2b(personalization, x || y)
+    pub fn kdf(&self, personalization: &[u8; 16]) -> [u8; 32] {
+        let (x, y) = self.get_coordinates();
+        let mut h = Blake2bParams::new()
+            .hash_length(32)
+            .personal(personalization)
+            .to_state();
+        h.update(&x.to_repr());
+        h.update(&y.to_repr());
+        h.finalize().as_bytes().try_into().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constant::AEAD_KEY_PERSONALIZATION;
+
+    // esk = 0x0123456789abcdef, rcv_sk = 0xfedcba9876543210
+    const EPK_BYTES: [u8; 32] = [
+        195, 175, 86, 208, 210, 95, 144, 199, 219, 243, 29, 101, 23, 203, 46, 134, 8, 212, 134,
+        139, 27, 63, 20, 70, 138, 160, 50, 52, 122, 122, 100, 59,
+    ];
+    const RCV_PK_BYTES: [u8; 32] = [
+        102, 39, 92, 209, 190, 203, 255, 78, 157, 129, 185, 50, 1, 200, 37, 166, 168, 48, 170, 78,
+        85, 179, 2, 89, 87, 19, 181, 159, 83, 96, 187, 20,
+    ];
+    const SHARED_SECRET_BYTES: [u8; 32] = [
+        145, 248, 224, 26, 158, 193, 210, 111, 109, 71, 62, 14, 133, 49, 94, 46, 76, 158, 132, 65,
+        122, 154, 61, 182, 74, 231, 229, 115, 160, 15, 153, 191,
+    ];
+    const AEAD_KEY: [u8; 32] = [
+        144, 221, 84, 43, 68, 181, 61, 42, 26, 30, 185, 164, 186, 19, 227, 216, 57, 81, 244, 19,
+        115, 140, 40, 23, 115, 191, 185, 29, 1, 47, 175, 217,
+    ];
+
+    #[test]
+    fn key_agreement_test_vectors() {
+        let esk = EphemeralSecretKey::from_base(pallas::Base::from(0x0123456789abcdefu64));
+        let rcv_sk = pallas::Scalar::from(0xfedcba9876543210u64);
+        let rcv_pk = GENERATOR.to_curve() * rcv_sk;
+        assert_eq!(rcv_pk.to_bytes(), RCV_PK_BYTES);
+
+        let epk = esk.public_key();
+        assert_eq!(epk.to_bytes(), EPK_BYTES);
+        assert_eq!(EphemeralPublicKey::from_bytes(&EPK_BYTES), Some(epk));
+        let (x, y) = epk.to_coordinates();
+        assert_eq!(EphemeralPublicKey::from_coordinates(x, y), Some(epk));
+        assert_eq!(
+            EphemeralPublicKey::from_coordinates(x, y + pallas::Base::one()),
+            None
+        );
+
+        // Both sides agree on the shared secret
+        let sender_secret = esk.agree(&rcv_pk);
+        let receiver_secret = epk.agree(&rcv_sk);
+        assert_eq!(sender_secret, receiver_secret);
+        assert_eq!(sender_secret.inner().to_bytes(), SHARED_SECRET_BYTES);
+
+        // The derived keys are domain separated
+        assert_eq!(sender_secret.kdf(AEAD_KEY_PERSONALIZATION), AEAD_KEY);
+        assert_ne!(sender_secret.kdf(b"Taiga_OtherDomai"), AEAD_KEY);
+    }
+}
@@ -0,0 +1,220 @@
+/// KeyCache memoizes the verifying and proving keys of the circuits, keyed by the circuit type,
+/// its configuration and the size k of the params.
+///
+/// The keys of a circuit don't depend on its witness, only on the type and the configuration
+/// fixed in the circuit, e.g. the quantity mode of the token resource logic (see
+/// `ResourceLogicCircuit::cache_key`). One key generation serves every proof of the type and
+/// configuration. An entry keeps the params it was generated from and is only served for
+/// those params: a key requested with other params of the same size, e.g. from another
+/// `ParamsStore`, is generated again and replaces the entry. The cache lives in memory, the
+/// proving keys can't be serialized with this version of halo2.
+use crate::error::TransactionError;
+#[cfg(feature = "prover")]
+use crate::proof::ProvingKey;
+use crate::proof::{Params, VerifyingKey};
+#[cfg(feature = "prover")]
+use halo2_proofs::plonk::keygen_pk;
+use halo2_proofs::plonk::{keygen_vk, Circuit};
+use lazy_static::lazy_static;
+use pasta_curves::pallas;
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+// The global cache used by the proving paths of the native resource logics and the compliance
+// circuit.
+lazy_static! {
+    pub static ref KEY_CACHE: KeyCache = KeyCache::new();
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct KeyCacheMetrics {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+// The circuit type, the configuration of the circuit and the size of the params
+type CacheId = (TypeId, u64, u32);
+
+#[derive(Debug)]
+struct CachedKeys {
+    params: Arc<Params>,
+    vk: VerifyingKey,
+    #[cfg(feature = "prover")]
+    pk: Option<Arc<ProvingKey>>,
+}
+
+#[derive(Debug, Default)]
+pub struct KeyCache {
+    entries: RwLock<HashMap<CacheId, CachedKeys>>,
+    counters: Counters,
+}
+
+impl KeyCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the verifying key of the circuit type and configuration, generating it on the
+    /// first request. The circuits of a type whose keys differ must pass different
+    /// configurations, the circuits of a type with a single configuration pass 0.
+    pub fn verifying_key<C: Circuit<pallas::Base> + 'static>(
+        &self,
+        params: &Arc<Params>,
+        circuit: &C,
+        configuration: u64,
+    ) -> Result<VerifyingKey, TransactionError> {
+        let id = (TypeId::of::<C>(), configuration, params.k());
+        if let Some(keys) = self.get(&id, params) {
+            self.counters.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(keys.vk.clone());
+        }
+        self.counters.misses.fetch_add(1, Ordering::Relaxed);
+
+        let vk = keygen_vk(params, circuit)?;
+        let mut entries = self.entries.write().unwrap();
+        entries.insert(
+            id,
+            CachedKeys {
+                params: params.clone(),
+                vk: vk.clone(),
+                #[cfg(feature = "prover")]
+                pk: None,
+            },
+        );
+        Ok(vk)
+    }
+
+    /// Returns the proving key of the circuit type and configuration, generating it on the first
+    /// request. A cached verifying key of the type and configuration is reused.
+    #[cfg(feature = "prover")]
+    pub fn proving_key<C: Circuit<pallas::Base> + 'static>(
+        &self,
+        params: &Arc<Params>,
+        circuit: &C,
+        configuration: u64,
+    ) -> Result<Arc<ProvingKey>, TransactionError> {
+        let id = (TypeId::of::<C>(), configuration, params.k());
+        let vk = match self.get(&id, params) {
+            Some(CachedKeys { pk: Some(pk), .. }) => {
+                self.counters.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(pk);
+            }
+            Some(keys) => keys.vk,
+            None => keygen_vk(params, circuit)?,
+        };
+        self.counters.misses.fetch_add(1, Ordering::Relaxed);
+
+        let pk = Arc::new(keygen_pk(params, vk.clone(), circuit)?);
+        let mut entries = self.entries.write().unwrap();
+        entries.insert(
+            id,
+            CachedKeys {
+                params: params.clone(),
+                vk,
+                pk: Some(pk.clone()),
+            },
+        );
+        Ok(pk)
+    }
+
+    /// The number of cached circuit types, configurations and sizes.
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops every cached key, e.g. to release the memory of the proving keys.
+    pub fn clear(&self) {
+        self.entries.write().unwrap().clear();
+    }
+
+    pub fn metrics(&self) -> KeyCacheMetrics {
+        KeyCacheMetrics {
+            hits: self.counters.hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    // The cached keys of the circuit type and configuration, if they were generated from the
+    // same params.
+    fn get(&self, id: &CacheId, params: &Arc<Params>) -> Option<CachedKeys> {
+        let entries = self.entries.read().unwrap();
+        entries
+            .get(id)
+            .filter(|keys| Arc::ptr_eq(&keys.params, params))
+            .map(|keys| CachedKeys {
+                params: keys.params.clone(),
+                vk: keys.vk.clone(),
+                #[cfg(feature = "prover")]
+                pk: keys.pk.clone(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KeyCache;
+    use crate::circuit::resource_logic_circuit::ResourceLogicVerifyingKey;
+    use crate::circuit::resource_logic_examples::{
+        TrivialResourceLogicCircuit, COMPRESSED_TRIVIAL_RESOURCE_LOGIC_VK,
+    };
+    use crate::constant::{DEFAULT_PARAMS_STORE, RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_key_cache() {
+        let cache = KeyCache::new();
+        let params = DEFAULT_PARAMS_STORE
+            .get(RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE)
+            .unwrap();
+        let circuit = TrivialResourceLogicCircuit::default();
+
+        let vk = cache.verifying_key(&params, &circuit, 0).unwrap();
+        assert_eq!(
+            ResourceLogicVerifyingKey::from_vk(vk).get_compressed(),
+            *COMPRESSED_TRIVIAL_RESOURCE_LOGIC_VK
+        );
+        cache.verifying_key(&params, &circuit, 0).unwrap();
+        assert_eq!(cache.len(), 1);
+        let metrics = cache.metrics();
+        assert_eq!((metrics.hits, metrics.misses), (1, 1));
+
+        #[cfg(feature = "prover")]
+        {
+            let pk = cache.proving_key(&params, &circuit, 0).unwrap();
+            assert!(Arc::ptr_eq(
+                &pk,
+                &cache.proving_key(&params, &circuit, 0).unwrap()
+            ));
+            let metrics = cache.metrics();
+            assert_eq!((metrics.hits, metrics.misses), (2, 2));
+        }
+
+        // Nor does another configuration of the type
+        let misses = cache.metrics().misses;
+        cache.verifying_key(&params, &circuit, 1).unwrap();
+        assert_eq!(cache.metrics().misses, misses + 1);
+        assert_eq!(cache.len(), 2);
+
+        // Other params of the same size don't hit the entry
+        let misses = cache.metrics().misses;
+        let other_params = Arc::new((*params).clone());
+        cache.verifying_key(&other_params, &circuit, 0).unwrap();
+        assert_eq!(cache.metrics().misses, misses + 1);
+        assert_eq!(cache.len(), 2);
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+}
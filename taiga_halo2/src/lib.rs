@@ -1,22 +1,79 @@
 #![allow(dead_code)]
 #![allow(clippy::large_enum_variant)]
 
+#[cfg(feature = "aead")]
+pub mod aead_encryption;
+#[cfg(feature = "async")]
+pub mod async_prover;
 pub mod binding_signature;
+pub mod burn;
 pub mod circuit;
+pub mod circuit_report;
 pub mod compliance;
 pub mod constant;
+#[cfg(feature = "construction-log")]
+pub mod construction_log;
 pub mod delta_commitment;
 pub mod error;
 mod executable;
+#[cfg(feature = "execution-trace")]
+pub mod execution_trace;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod fuzzy_detection;
+pub mod indexer;
+pub mod ka;
+pub mod keys;
 pub mod merkle_tree;
 pub mod nullifier;
+pub mod params_store;
+pub mod prelude;
 pub mod proof;
+pub mod prover_options;
 pub mod resource;
+pub mod resource_detection;
 pub mod resource_encryption;
 pub mod resource_logic_commitment;
 pub mod resource_logic_vk;
+pub mod resource_logic_vk_registry;
+#[cfg(feature = "borsh")]
+pub mod serialization;
 pub mod shielded_ptx;
+#[cfg(test)]
+#[cfg(feature = "example-token")]
+mod snapshots;
+#[cfg(feature = "example-intents")]
+pub mod solver;
+pub mod storage;
+#[cfg(all(feature = "example-bridge", feature = "example-intents"))]
+pub mod stress;
+pub mod supply;
 pub mod taiga_api;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
 pub mod transaction;
+#[cfg(feature = "spec")]
+pub mod transaction_spec;
 pub mod transparent_ptx;
 pub mod utils;
+pub mod verification_trace;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+// Compile-time assertions that the core types can be shared across threads.
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync + ?Sized>() {}
+    assert_send_sync::<circuit::resource_logic_circuit::ResourceLogic>();
+    assert_send_sync::<circuit::resource_logic_circuit::ResourceLogicVerifyingInfo>();
+    #[cfg(feature = "prover")]
+    assert_send_sync::<circuit::resource_logic_circuit::VampIRResourceLogicCircuit>();
+    assert_send_sync::<circuit::resource_logic_bytecode::ApplicationByteCode>();
+    assert_send_sync::<params_store::ParamsStore>();
+    assert_send_sync::<resource::ResourceLogics>();
+    assert_send_sync::<resource_logic_vk::ResourceLogicVerifyingKey>();
+    assert_send_sync::<shielded_ptx::ComplianceVerifyingInfo>();
+    assert_send_sync::<shielded_ptx::ResourceLogicVerifyingInfoSet>();
+    assert_send_sync::<shielded_ptx::ShieldedPartialTransaction>();
+    assert_send_sync::<transaction::Transaction>();
+    assert_send_sync::<transparent_ptx::TransparentPartialTransaction>();
+};
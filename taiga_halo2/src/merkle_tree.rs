@@ -1,11 +1,14 @@
+use std::collections::VecDeque;
 use std::hash::{Hash, Hasher};
 
+use crate::error::TransactionError;
 use crate::merkle_tree::LR::{L, R};
 use crate::resource::ResourceCommitment;
 use crate::utils::poseidon_hash;
 use crate::{constant::TAIGA_COMMITMENT_TREE_DEPTH, resource::Resource};
 use ff::PrimeField;
 use halo2_proofs::arithmetic::Field;
+use lazy_static::lazy_static;
 use pasta_curves::pallas;
 use rand::distributions::{Distribution, Standard};
 use rand::{Rng, RngCore};
@@ -30,6 +33,7 @@ impl Anchor {
         self.0
     }
 
+    /// The canonical encoding: the 32-byte little-endian repr of the anchor.
     pub fn to_bytes(&self) -> [u8; 32] {
         self.0.to_repr()
     }
@@ -73,6 +77,46 @@ impl BorshDeserialize for Anchor {
     }
 }
 
+/// The recent roots of the commitment tree the ledger accepts as anchors, the oldest first.
+#[derive(Clone, Debug)]
+pub struct AnchorHistory {
+    anchors: VecDeque<Anchor>,
+    capacity: usize,
+}
+
+impl AnchorHistory {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "the anchor history can't be empty");
+        Self {
+            anchors: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Records a new root, the oldest root falls out of the window when it's full.
+    pub fn push(&mut self, anchor: Anchor) {
+        if self.anchors.len() == self.capacity {
+            self.anchors.pop_front();
+        }
+        self.anchors.push_back(anchor);
+    }
+
+    pub fn contains(&self, anchor: &Anchor) -> bool {
+        self.anchors.contains(anchor)
+    }
+
+    pub fn latest(&self) -> Option<Anchor> {
+        self.anchors.back().copied()
+    }
+}
+
+/// Tracks the merkle paths of resource commitments as the commitment tree grows, e.g. the
+/// resources of a wallet.
+pub trait WitnessTracker {
+    /// The merkle path of the resource commitment in the current tree, None if it's not tracked.
+    fn merkle_path(&self, cm: &ResourceCommitment) -> Option<MerklePath>;
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Copy, Hash, Default)]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -214,3 +258,216 @@ impl Hash for Node {
         self.0.to_repr().hash(state);
     }
 }
+
+lazy_static! {
+    // The roots of the empty subtrees by level, the empty leaves are zero
+    static ref EMPTY_ROOTS: Vec<Node> = {
+        let mut roots = vec![Node::from(pallas::Base::zero())];
+        for level in 0..TAIGA_COMMITMENT_TREE_DEPTH {
+            roots.push(Node::combine(&roots[level], &roots[level]));
+        }
+        roots
+    };
+}
+
+/// The append-only commitment tree. Only the frontier is kept: the last leaf and its left
+/// siblings, enough to append leaves, compute the root and witness the last leaf.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CommitmentTree {
+    size: u64,
+    // The last leaf, None if the tree is empty
+    leaf: Option<Node>,
+    // The left siblings on the path of the last leaf, from the lowest level
+    ommers: Vec<Node>,
+}
+
+impl CommitmentTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of leaves appended.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn append(&mut self, cm: ResourceCommitment) -> Result<(), TransactionError> {
+        if self.size == 1 << TAIGA_COMMITMENT_TREE_DEPTH {
+            return Err(TransactionError::CommitmentTreeFull);
+        }
+        if let Some(leaf) = self.leaf {
+            // Merge the subtrees the last leaf completes, the first incomplete one is the left
+            // sibling of the new leaf
+            let position = self.size - 1;
+            let mut node = leaf;
+            let mut level = 0;
+            while (position >> level) & 1 == 1 {
+                node = Node::combine(&self.ommers[level], &node);
+                level += 1;
+            }
+            self.ommers.drain(..level);
+            self.ommers.insert(0, node);
+        }
+        self.leaf = Some(cm.into());
+        self.size += 1;
+        Ok(())
+    }
+
+    pub fn root(&self) -> Anchor {
+        self.subtree_root(TAIGA_COMMITMENT_TREE_DEPTH).into()
+    }
+
+    /// The witness of the last leaf, None if the tree is empty.
+    pub fn witness(&self) -> Option<IncrementalWitness> {
+        IncrementalWitness::from_tree(self)
+    }
+
+    // The root of the subtree at `level` containing the last leaf, the leaves after the last one
+    // are empty
+    fn subtree_root(&self, level: usize) -> Node {
+        let Some(leaf) = self.leaf else {
+            return EMPTY_ROOTS[level];
+        };
+        let position = self.size - 1;
+        let mut ommers = self.ommers.iter();
+        let mut node = leaf;
+        for (l, empty_root) in EMPTY_ROOTS.iter().enumerate().take(level) {
+            node = if (position >> l) & 1 == 1 {
+                Node::combine(ommers.next().unwrap(), &node)
+            } else {
+                Node::combine(&node, empty_root)
+            };
+        }
+        node
+    }
+}
+
+/// The merkle path of a leaf, kept up to date by appending the leaves appended to the tree after
+/// it. A wallet keeps a witness of each of its resources to spend them against a recent root.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IncrementalWitness {
+    tree: CommitmentTree,
+    position: u64,
+    // The siblings on the path by level: the left ones are known when the witness is created,
+    // the right ones once their subtree is complete
+    siblings: Vec<Option<Node>>,
+}
+
+impl IncrementalWitness {
+    /// The witness of the last leaf of the tree, None if the tree is empty.
+    pub fn from_tree(tree: &CommitmentTree) -> Option<Self> {
+        tree.leaf?;
+        let position = tree.size - 1;
+        let mut ommers = tree.ommers.iter();
+        let siblings = (0..TAIGA_COMMITMENT_TREE_DEPTH)
+            .map(|level| ((position >> level) & 1 == 1).then(|| *ommers.next().unwrap()))
+            .collect();
+        Some(Self {
+            tree: tree.clone(),
+            position,
+            siblings,
+        })
+    }
+
+    /// The position of the witnessed leaf.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Appends the next leaf of the tree.
+    pub fn append(&mut self, cm: ResourceCommitment) -> Result<(), TransactionError> {
+        self.tree.append(cm)?;
+        // Record the right siblings the new leaf completes
+        let last = self.tree.size - 1;
+        for level in 0..TAIGA_COMMITMENT_TREE_DEPTH {
+            if self.siblings[level].is_none()
+                && last == self.right_sibling_start(level) + (1 << level) - 1
+            {
+                self.siblings[level] = Some(self.tree.subtree_root(level));
+            }
+        }
+        Ok(())
+    }
+
+    /// The root of the tree the witness is up to date with.
+    pub fn root(&self) -> Anchor {
+        self.tree.root()
+    }
+
+    /// The merkle path of the witnessed leaf to the root.
+    pub fn path(&self) -> MerklePath {
+        let last = self.tree.size - 1;
+        let path = self
+            .siblings
+            .iter()
+            .enumerate()
+            .map(|(level, sibling)| match sibling {
+                Some(sibling) if (self.position >> level) & 1 == 1 => (*sibling, L),
+                Some(sibling) => (*sibling, R),
+                // The right sibling is empty or incomplete
+                None if last < self.right_sibling_start(level) => (EMPTY_ROOTS[level], R),
+                None => (self.tree.subtree_root(level), R),
+            })
+            .collect();
+        MerklePath::from_path(path)
+    }
+
+    // The position of the first leaf of the right sibling at `level`
+    fn right_sibling_start(&self, level: usize) -> u64 {
+        ((self.position >> level) + 1) << level
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    // The root of the tree recomputed from all the leaves
+    fn naive_root(leaves: &[ResourceCommitment]) -> Anchor {
+        let mut level: Vec<Node> = leaves.iter().map(|cm| Node::from(*cm)).collect();
+        for empty_root in EMPTY_ROOTS.iter().take(TAIGA_COMMITMENT_TREE_DEPTH) {
+            level = level
+                .chunks(2)
+                .map(|pair| Node::combine(&pair[0], pair.get(1).unwrap_or(empty_root)))
+                .collect();
+        }
+        level
+            .first()
+            .copied()
+            .unwrap_or(EMPTY_ROOTS[TAIGA_COMMITMENT_TREE_DEPTH])
+            .into()
+    }
+
+    #[test]
+    fn test_commitment_tree() {
+        let mut rng = OsRng;
+        let mut tree = CommitmentTree::new();
+        assert_eq!(tree.root(), naive_root(&[]));
+        assert!(tree.witness().is_none());
+
+        let mut leaves = vec![];
+        let mut witnesses: Vec<(ResourceCommitment, IncrementalWitness)> = vec![];
+        for i in 0..19 {
+            let cm = ResourceCommitment::from(pallas::Base::random(&mut rng));
+            tree.append(cm).unwrap();
+            witnesses
+                .iter_mut()
+                .for_each(|(_, witness)| witness.append(cm).unwrap());
+            leaves.push(cm);
+            assert_eq!(tree.size(), leaves.len() as u64);
+            assert_eq!(tree.root(), naive_root(&leaves));
+
+            // Witness some of the leaves, at odd and even positions
+            if i % 3 != 1 {
+                let witness = tree.witness().unwrap();
+                assert_eq!(witness.position(), i);
+                witnesses.push((cm, witness));
+            }
+            for (cm, witness) in witnesses.iter() {
+                assert_eq!(witness.root(), tree.root());
+                assert_eq!(witness.path().root(Node::from(*cm)), tree.root());
+            }
+        }
+    }
+}
@@ -1,7 +1,11 @@
+use std::collections::HashSet;
 use std::hash::Hash;
 
 use crate::{
+    error::TransactionError,
+    executable::Executable,
     resource::ResourceCommitment,
+    transaction::Transaction,
     utils::{poseidon_hash_n, prf_nf},
 };
 use halo2_proofs::arithmetic::Field;
@@ -55,6 +59,7 @@ impl Nullifier {
         self.0
     }
 
+    /// The canonical encoding: the 32-byte little-endian repr of the nullifier.
     pub fn to_bytes(&self) -> [u8; 32] {
         self.0.to_repr()
     }
@@ -102,6 +107,61 @@ impl Hash for Nullifier {
     }
 }
 
+/// The nullifiers revealed by the executed transactions, to detect the double spends of new
+/// transactions.
+#[derive(Debug, Clone, Default)]
+pub struct NullifierSet(HashSet<Nullifier>);
+
+impl NullifierSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains(&self, nf: &Nullifier) -> bool {
+        self.0.contains(nf)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Nullifier> {
+        self.0.iter()
+    }
+
+    /// Inserts the nullifiers revealed by an executed transaction, e.g. the nullifiers of its
+    /// `TransactionResult`.
+    pub fn insert_batch(&mut self, nfs: impl IntoIterator<Item = Nullifier>) {
+        self.0.extend(nfs);
+    }
+
+    /// Checks that no nullifier of the transaction is spent. The double spend is reported with
+    /// the index of its ptx, the shielded ptxs are indexed before the transparent ones as in the
+    /// execution.
+    pub fn check_transaction(&self, tx: &Transaction) -> Result<(), TransactionError> {
+        let shielded_nfs = tx
+            .get_shielded_ptx_bundle()
+            .partial_txs()
+            .iter()
+            .map(|ptx| ptx.get_nullifiers());
+        let transparent_nfs = tx
+            .get_transparent_ptx_bundle()
+            .partial_txs()
+            .iter()
+            .map(|ptx| ptx.get_nullifiers());
+        for (ptx_index, nfs) in shielded_nfs.chain(transparent_nfs).enumerate() {
+            if let Some(nf) = nfs.into_iter().find(|nf| self.contains(nf)) {
+                return Err(TransactionError::DoubleSpend(ptx_index, nf));
+            }
+        }
+        Ok(())
+    }
+}
+
 impl NullifierKeyContainer {
     pub fn random_key<R: RngCore>(mut rng: R) -> Self {
         NullifierKeyContainer::Key(pallas::Base::random(&mut rng))
@@ -153,7 +213,7 @@ impl Default for NullifierKeyContainer {
     }
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-utils"))]
 pub mod tests {
     use halo2_proofs::arithmetic::Field;
     use pasta_curves::pallas;
@@ -172,4 +232,42 @@ pub mod tests {
     pub fn random_nullifier_key_commitment<R: RngCore>(mut rng: R) -> NullifierKeyContainer {
         NullifierKeyContainer::from_npk(pallas::Base::random(&mut rng))
     }
+
+    #[test]
+    fn test_nullifier_set() {
+        use super::NullifierSet;
+        use crate::error::TransactionError;
+        use crate::transaction::{
+            testing::create_shielded_ptx_bundle, Transaction, TransparentPartialTxBundle,
+        };
+        use rand::rngs::OsRng;
+
+        let mut rng = OsRng;
+        let tx = Transaction::build(
+            &mut rng,
+            create_shielded_ptx_bundle(2),
+            TransparentPartialTxBundle::default(),
+        )
+        .unwrap();
+        let result = tx.execute().unwrap();
+
+        let mut nf_set = NullifierSet::new();
+        nf_set.insert_batch([random_nullifier(&mut rng)]);
+        nf_set.check_transaction(&tx).unwrap();
+
+        // Spend a nullifier of the second ptx
+        let spent = *result.nullifiers.last().unwrap();
+        nf_set.insert_batch([spent]);
+        assert!(nf_set.contains(&spent));
+        assert!(matches!(
+            nf_set.check_transaction(&tx),
+            Err(TransactionError::DoubleSpend(1, nf)) if nf == spent
+        ));
+
+        nf_set.insert_batch(result.nullifiers);
+        assert!(matches!(
+            nf_set.check_transaction(&tx),
+            Err(TransactionError::DoubleSpend(0, _))
+        ));
+    }
 }
@@ -0,0 +1,199 @@
+/// ParamsStore holds the IPA setup parameters keyed by the circuit size k.
+///
+/// A store is created explicitly and passed to the proving and verifying functions, so provers
+/// with different parameter sets can run in one process. `DEFAULT_PARAMS_STORE` in `constant`
+/// preloads the embedded params and backs the precomputed proving and verifying keys.
+use crate::constant::PARAMS_SIZE;
+use crate::error::TransactionError;
+use crate::proof::{read_params, setup_params, Params};
+use std::collections::HashMap;
+#[cfg(feature = "prover")]
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// What the store does when the params of the requested size are not loaded.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ParamsGenerationPolicy {
+    /// Generate the params on the first request and keep them.
+    #[default]
+    Lazy,
+    /// Only serve preloaded params, a missing size is an error.
+    PreloadedOnly,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ParamsStoreMetrics {
+    pub hits: u64,
+    pub misses: u64,
+    pub generated: u64,
+    pub preloaded: u64,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    generated: AtomicU64,
+    preloaded: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+pub struct ParamsStore {
+    params: RwLock<HashMap<u32, Arc<Params>>>,
+    policy: ParamsGenerationPolicy,
+    counters: Counters,
+}
+
+impl ParamsStore {
+    pub fn new(policy: ParamsGenerationPolicy) -> Self {
+        Self {
+            params: RwLock::new(HashMap::new()),
+            policy,
+            counters: Counters::default(),
+        }
+    }
+
+    /// Creates a store with the params shipped in the crate (k = PARAMS_SIZE) preloaded.
+    pub fn with_embedded_params(policy: ParamsGenerationPolicy) -> Self {
+        let store = Self::new(policy);
+        store
+            .load_from_bytes(PARAMS_SIZE, include_bytes!("../params/params_15"))
+            .expect("embedded params should be valid");
+        store
+    }
+
+    pub fn policy(&self) -> ParamsGenerationPolicy {
+        self.policy
+    }
+
+    /// Adds the params of size `params.k()`, replacing any existing ones of the same size.
+    pub fn preload(&self, params: Params) {
+        self.counters.preloaded.fetch_add(1, Ordering::Relaxed);
+        self.params
+            .write()
+            .unwrap()
+            .insert(params.k(), Arc::new(params));
+    }
+
+    /// Reads serialized params and checks they have the expected size.
+    pub fn load_from_bytes(&self, k: u32, bytes: &[u8]) -> Result<(), TransactionError> {
+        let params = read_params(&mut &bytes[..])?;
+        if params.k() != k {
+            return Err(TransactionError::InvalidParamsSize(params.k()));
+        }
+        self.preload(params);
+        Ok(())
+    }
+
+    /// Loads serialized params from a memory-mapped file. The file is paged in while the params
+    /// are read instead of being copied to the heap first, and unmapped right after.
+    #[cfg(feature = "prover")]
+    pub fn load_from_file(&self, k: u32, path: impl AsRef<Path>) -> Result<(), TransactionError> {
+        let file = std::fs::File::open(path)?;
+        // SAFETY: the map is read-only and dropped before returning, the params file must not be
+        // truncated while it's being read.
+        let bytes = unsafe { memmap2::Mmap::map(&file)? };
+        self.load_from_bytes(k, &bytes)
+    }
+
+    pub fn contains(&self, k: u32) -> bool {
+        self.params.read().unwrap().contains_key(&k)
+    }
+
+    /// Returns the params of size k, generating them if the policy allows.
+    pub fn get(&self, k: u32) -> Result<Arc<Params>, TransactionError> {
+        if let Some(params) = self.params.read().unwrap().get(&k) {
+            self.counters.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(params.clone());
+        }
+        self.counters.misses.fetch_add(1, Ordering::Relaxed);
+
+        match self.policy {
+            ParamsGenerationPolicy::PreloadedOnly => Err(TransactionError::MissingParams(k)),
+            ParamsGenerationPolicy::Lazy => {
+                let mut params = self.params.write().unwrap();
+                // Another thread may have generated them while we were waiting for the lock.
+                let params = params.entry(k).or_insert_with(|| {
+                    self.counters.generated.fetch_add(1, Ordering::Relaxed);
+                    Arc::new(setup_params(k))
+                });
+                Ok(params.clone())
+            }
+        }
+    }
+
+    pub fn metrics(&self) -> ParamsStoreMetrics {
+        ParamsStoreMetrics {
+            hits: self.counters.hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+            generated: self.counters.generated.load(Ordering::Relaxed),
+            preloaded: self.counters.preloaded.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ParamsGenerationPolicy, ParamsStore};
+    use crate::constant::PARAMS_SIZE;
+    use crate::error::TransactionError;
+    use crate::proof::setup_params;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_params_store_policy_and_metrics() {
+        let store = ParamsStore::new(ParamsGenerationPolicy::PreloadedOnly);
+        assert!(matches!(
+            store.get(4),
+            Err(TransactionError::MissingParams(4))
+        ));
+
+        store.preload(setup_params(4));
+        let params = store.get(4).unwrap();
+        assert_eq!(params.k(), 4);
+
+        let lazy_store = ParamsStore::new(ParamsGenerationPolicy::Lazy);
+        let generated = lazy_store.get(5).unwrap();
+        assert!(Arc::ptr_eq(&generated, &lazy_store.get(5).unwrap()));
+
+        let metrics = store.metrics();
+        assert_eq!((metrics.hits, metrics.misses, metrics.preloaded), (1, 1, 1));
+        let metrics = lazy_store.metrics();
+        assert_eq!((metrics.hits, metrics.misses, metrics.generated), (1, 1, 1));
+    }
+
+    #[test]
+    fn test_params_store_independent_sets() {
+        let embedded = ParamsStore::with_embedded_params(ParamsGenerationPolicy::PreloadedOnly);
+        let custom = ParamsStore::new(ParamsGenerationPolicy::PreloadedOnly);
+        custom.preload(setup_params(PARAMS_SIZE));
+        assert!(embedded.contains(PARAMS_SIZE) && custom.contains(PARAMS_SIZE));
+        assert!(!Arc::ptr_eq(
+            &embedded.get(PARAMS_SIZE).unwrap(),
+            &custom.get(PARAMS_SIZE).unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_params_store_load_from_file() {
+        let params = setup_params(4);
+        let mut bytes = vec![];
+        params.write(&mut bytes).unwrap();
+        let path = std::env::temp_dir().join(format!("taiga_params_4_{}", std::process::id()));
+        std::fs::write(&path, &bytes).unwrap();
+
+        let store = ParamsStore::new(ParamsGenerationPolicy::PreloadedOnly);
+        assert!(matches!(
+            store.load_from_file(5, &path),
+            Err(TransactionError::InvalidParamsSize(4))
+        ));
+        store.load_from_file(4, &path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut loaded = vec![];
+        store.get(4).unwrap().write(&mut loaded).unwrap();
+        assert_eq!(loaded, bytes);
+        assert!(store.load_from_file(4, &path).is_err());
+    }
+}
@@ -0,0 +1,31 @@
+/// The user-facing API: the partial transaction builders, the transaction types, the keys, the
+/// ledger state and the wallet scanning. `use taiga_halo2::prelude::*` covers building, verifying
+/// and indexing transactions; the circuit plumbing is documented with the `circuit-internals`
+/// feature only.
+pub use crate::{
+    error::TransactionError,
+    fuzzy_detection::{FmdDetectionKey, FmdPublicKey, FmdSecretKey, FuzzyDetectionTag},
+    indexer::Indexer,
+    merkle_tree::{
+        Anchor, AnchorHistory, CommitmentTree, IncrementalWitness, MerklePath, WitnessTracker,
+    },
+    nullifier::{Nullifier, NullifierKeyContainer, NullifierSet},
+    params_store::ParamsStore,
+    prover_options::ProverOptions,
+    resource::{RandomSeed, Resource, ResourceCommitment, ResourceKind, ResourceLogics},
+    resource_detection::{BlockDetectionFilter, DetectionKey, DetectionTag},
+    resource_encryption::{ResourceCiphertext, ResourcePlaintext, SecretKey},
+    resource_logic_vk_registry::{ResourceLogicVkRegistry, RESOURCE_LOGIC_VK_REGISTRY},
+    shielded_ptx::ShieldedPartialTransaction,
+    storage::{MemoryStorage, Storage, StorageBatch},
+    transaction::{
+        ShieldedPartialTxBundle, Transaction, TransactionResult, TransparentPartialTxBundle,
+    },
+    transparent_ptx::TransparentPartialTransaction,
+};
+
+#[cfg(feature = "prover")]
+pub use crate::shielded_ptx::ShieldedPartialTransactionBuilder;
+
+#[cfg(feature = "sled")]
+pub use crate::storage::SledStorage;
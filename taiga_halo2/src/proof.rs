@@ -1,12 +1,32 @@
+use crate::{
+    constant::{POSEIDON_RATE, POSEIDON_WIDTH},
+    error::TransactionError,
+    params_store::ParamsStore,
+};
+use ff::{Field, PrimeField};
+use group::GroupEncoding;
+use halo2_gadgets::poseidon::primitives as poseidon;
+#[cfg(feature = "prover")]
+use halo2_proofs::plonk::Circuit;
 use halo2_proofs::{
-    plonk::{self, Circuit, ProvingKey, SingleVerifier, VerifyingKey},
-    poly::commitment::Params,
-    transcript::{Blake2bRead, Blake2bWrite},
+    plonk::{self, SingleVerifier, VerificationStrategy},
+    poly::commitment::{self, Guard, MSM},
+    transcript::{
+        Blake2bRead, Blake2bWrite, Challenge255, EncodedChallenge, Transcript, TranscriptRead,
+        TranscriptWrite,
+    },
+};
+use pasta_curves::{
+    arithmetic::{Coordinates, CurveAffine},
+    pallas, vesta,
 };
-use pasta_curves::{pallas, vesta};
+use rand::rngs::OsRng;
+#[cfg(feature = "prover")]
 use rand::RngCore;
 #[cfg(feature = "nif")]
 use rustler::NifTuple;
+use std::collections::BTreeMap;
+use std::io;
 
 #[cfg(feature = "serde")]
 use serde;
@@ -14,22 +34,297 @@ use serde;
 #[cfg(feature = "borsh")]
 use borsh::{BorshDeserialize, BorshSerialize};
 
+/// The commitment scheme of the proofs: IPA over the vesta curve of the Pasta cycle.
+///
+/// The IPA-specific types (the params, the transcripts and the verification strategy) are
+/// confined to this module. The rest of the crate names the params and the keys through the
+/// aliases below and proves through `Proof`, so moving to another commitment scheme, e.g. KZG
+/// with a structured reference string, is contained in this module.
+pub type CommitmentScheme = vesta::Affine;
+pub type Params = commitment::Params<CommitmentScheme>;
+pub type ProvingKey = plonk::ProvingKey<CommitmentScheme>;
+pub type VerifyingKey = plonk::VerifyingKey<CommitmentScheme>;
+
+/// Generates the params for circuits of 2^k rows.
+pub fn setup_params(k: u32) -> Params {
+    Params::new(k)
+}
+
+/// Reads the params written by `Params::write`.
+pub fn read_params<R: io::Read>(reader: &mut R) -> io::Result<Params> {
+    Params::read(reader)
+}
+
+/// The k of the domain of the verifying key, the circuit has 2^k rows.
+pub fn verifying_key_k(vk: &VerifyingKey) -> u32 {
+    vk.get_domain().k()
+}
+
+/// The hash of the Fiat-Shamir transcript of the proofs.
+///
+/// `Blake2bTranscript` is the default and the cheapest natively. `PoseidonTranscript` is
+/// algebraic over the base field of the commitment scheme, so a verifier circuit on the other
+/// curve of the cycle can recompute the challenges, as the recursive verification of the proofs
+/// needs. The `poseidon-transcript` feature makes it the transcript of `Proof::create` and
+/// `Proof::verify`. A proof only verifies with the transcript it was created with, the keys and
+/// the params don't depend on the choice.
+pub trait TranscriptHash {
+    type Read<'a>: TranscriptRead<CommitmentScheme, Challenge255<CommitmentScheme>>;
+    type Write: TranscriptWrite<CommitmentScheme, Challenge255<CommitmentScheme>>;
+
+    fn init_read(proof: &[u8]) -> Self::Read<'_>;
+
+    fn init_write() -> Self::Write;
+
+    fn finalize(transcript: Self::Write) -> Vec<u8>;
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Blake2bTranscript;
+
+#[derive(Clone, Copy, Debug)]
+pub struct PoseidonTranscript;
+
+#[cfg(not(feature = "poseidon-transcript"))]
+pub type DefaultTranscript = Blake2bTranscript;
+#[cfg(feature = "poseidon-transcript")]
+pub type DefaultTranscript = PoseidonTranscript;
+
+impl TranscriptHash for Blake2bTranscript {
+    type Read<'a> = Blake2bRead<&'a [u8], CommitmentScheme, Challenge255<CommitmentScheme>>;
+    type Write = Blake2bWrite<Vec<u8>, CommitmentScheme, Challenge255<CommitmentScheme>>;
+
+    fn init_read(proof: &[u8]) -> Self::Read<'_> {
+        Blake2bRead::init(proof)
+    }
+
+    fn init_write() -> Self::Write {
+        Blake2bWrite::init(vec![])
+    }
+
+    fn finalize(transcript: Self::Write) -> Vec<u8> {
+        transcript.finalize()
+    }
+}
+
+impl TranscriptHash for PoseidonTranscript {
+    type Read<'a> = PoseidonRead<&'a [u8]>;
+    type Write = PoseidonWrite<Vec<u8>>;
+
+    fn init_read(proof: &[u8]) -> Self::Read<'_> {
+        PoseidonRead {
+            state: PoseidonState::new(),
+            reader: proof,
+        }
+    }
+
+    fn init_write() -> Self::Write {
+        PoseidonWrite {
+            state: PoseidonState::new(),
+            writer: vec![],
+        }
+    }
+
+    fn finalize(transcript: Self::Write) -> Vec<u8> {
+        transcript.writer
+    }
+}
+
+type TranscriptBase = <CommitmentScheme as CurveAffine>::Base;
+
+// A duplex Poseidon sponge over the base field of the commitment scheme. Every absorbed element
+// is added to the first rate element and permuted, a squeeze marks the second rate element
+// before permuting, so absorbing a zero and squeezing are distinct.
+#[derive(Clone, Debug)]
+struct PoseidonState(
+    poseidon::Sponge<
+        TranscriptBase,
+        poseidon::P128Pow5T3,
+        poseidon::Absorbing<TranscriptBase, POSEIDON_RATE>,
+        POSEIDON_WIDTH,
+        POSEIDON_RATE,
+    >,
+);
+
+impl PoseidonState {
+    fn new() -> Self {
+        // The same personalization as the Blake2b transcript of halo2
+        let domain = TranscriptBase::from_u128(u128::from_le_bytes(*b"Halo2-Transcript"));
+        let state = [TranscriptBase::zero(), TranscriptBase::zero(), domain];
+        Self(poseidon::Sponge::<
+            _,
+            poseidon::P128Pow5T3,
+            _,
+            POSEIDON_WIDTH,
+            POSEIDON_RATE,
+        >::init(state))
+    }
+
+    fn permute(&mut self) {
+        poseidon::permute::<_, poseidon::P128Pow5T3, POSEIDON_WIDTH, POSEIDON_RATE>(
+            &mut self.0.state,
+            &self.0.mds_matrix,
+            &self.0.round_constants,
+        );
+    }
+
+    fn absorb(&mut self, element: TranscriptBase) {
+        self.0.state[0] += element;
+        self.permute();
+    }
+
+    fn absorb_point(&mut self, point: CommitmentScheme) -> io::Result<()> {
+        // The identity is absorbed as (0, 0), which is not on the curve
+        let coordinates: Option<Coordinates<CommitmentScheme>> = point.coordinates().into();
+        let (x, y) = coordinates
+            .map(|coordinates| (*coordinates.x(), *coordinates.y()))
+            .unwrap_or((TranscriptBase::zero(), TranscriptBase::zero()));
+        self.absorb(x);
+        self.absorb(y);
+        Ok(())
+    }
+
+    fn absorb_scalar(&mut self, scalar: pallas::Base) -> io::Result<()> {
+        // The scalar field of vesta is smaller than its base field, the repr embeds canonically
+        let element =
+            Option::from(TranscriptBase::from_repr(scalar.to_repr())).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "scalar out of the transcript field",
+                )
+            })?;
+        self.absorb(element);
+        Ok(())
+    }
+
+    fn squeeze(&mut self) -> Challenge255<CommitmentScheme> {
+        self.0.state[1] += TranscriptBase::one();
+        self.permute();
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(&self.0.state[0].to_repr());
+        Challenge255::new(&bytes)
+    }
+}
+
+/// Reads a proof created with `PoseidonWrite`.
+#[derive(Debug)]
+pub struct PoseidonRead<R: io::Read> {
+    state: PoseidonState,
+    reader: R,
+}
+
+impl<R: io::Read> Transcript<CommitmentScheme, Challenge255<CommitmentScheme>> for PoseidonRead<R> {
+    fn squeeze_challenge(&mut self) -> Challenge255<CommitmentScheme> {
+        self.state.squeeze()
+    }
+
+    fn common_point(&mut self, point: CommitmentScheme) -> io::Result<()> {
+        self.state.absorb_point(point)
+    }
+
+    fn common_scalar(&mut self, scalar: pallas::Base) -> io::Result<()> {
+        self.state.absorb_scalar(scalar)
+    }
+}
+
+impl<R: io::Read> TranscriptRead<CommitmentScheme, Challenge255<CommitmentScheme>>
+    for PoseidonRead<R>
+{
+    fn read_point(&mut self) -> io::Result<CommitmentScheme> {
+        let mut compressed = <CommitmentScheme as GroupEncoding>::Repr::default();
+        self.reader.read_exact(compressed.as_mut())?;
+        let point: CommitmentScheme = Option::from(CommitmentScheme::from_bytes(&compressed))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "invalid point encoding in proof",
+                )
+            })?;
+        self.common_point(point)?;
+        Ok(point)
+    }
+
+    fn read_scalar(&mut self) -> io::Result<pallas::Base> {
+        let mut repr = [0u8; 32];
+        self.reader.read_exact(&mut repr)?;
+        let scalar = Option::from(pallas::Base::from_repr(repr)).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid field element encoding in proof",
+            )
+        })?;
+        self.common_scalar(scalar)?;
+        Ok(scalar)
+    }
+}
+
+/// Writes a proof with a Poseidon transcript, the points and the scalars are encoded as with
+/// the Blake2b transcript.
+#[derive(Debug)]
+pub struct PoseidonWrite<W: io::Write> {
+    state: PoseidonState,
+    writer: W,
+}
+
+impl<W: io::Write> Transcript<CommitmentScheme, Challenge255<CommitmentScheme>>
+    for PoseidonWrite<W>
+{
+    fn squeeze_challenge(&mut self) -> Challenge255<CommitmentScheme> {
+        self.state.squeeze()
+    }
+
+    fn common_point(&mut self, point: CommitmentScheme) -> io::Result<()> {
+        self.state.absorb_point(point)
+    }
+
+    fn common_scalar(&mut self, scalar: pallas::Base) -> io::Result<()> {
+        self.state.absorb_scalar(scalar)
+    }
+}
+
+impl<W: io::Write> TranscriptWrite<CommitmentScheme, Challenge255<CommitmentScheme>>
+    for PoseidonWrite<W>
+{
+    fn write_point(&mut self, point: CommitmentScheme) -> io::Result<()> {
+        self.common_point(point)?;
+        self.writer.write_all(point.to_bytes().as_ref())
+    }
+
+    fn write_scalar(&mut self, scalar: pallas::Base) -> io::Result<()> {
+        self.common_scalar(scalar)?;
+        self.writer.write_all(scalar.to_repr().as_ref())
+    }
+}
+
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "nif", derive(NifTuple))]
 #[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-pub struct Proof(Vec<u8>);
+pub struct Proof(#[cfg_attr(feature = "serde", serde(with = "crate::utils::serde_hex"))] Vec<u8>);
 
 impl Proof {
     /// Creates a proof for the given circuits and instances.
+    #[cfg(feature = "prover")]
     pub fn create<C: Circuit<pallas::Base>>(
-        pk: &ProvingKey<vesta::Affine>,
-        params: &Params<vesta::Affine>,
+        pk: &ProvingKey,
+        params: &Params,
+        circuit: C,
+        instance: &[&[pallas::Base]],
+        rng: impl RngCore,
+    ) -> Result<Self, plonk::Error> {
+        Self::create_with_transcript::<DefaultTranscript, C>(pk, params, circuit, instance, rng)
+    }
+
+    /// Creates a proof with the given transcript hash.
+    #[cfg(feature = "prover")]
+    pub fn create_with_transcript<T: TranscriptHash, C: Circuit<pallas::Base>>(
+        pk: &ProvingKey,
+        params: &Params,
         circuit: C,
         instance: &[&[pallas::Base]],
         mut rng: impl RngCore,
     ) -> Result<Self, plonk::Error> {
-        let mut transcript = Blake2bWrite::<_, vesta::Affine, _>::init(vec![]);
+        let mut transcript = T::init_write();
         plonk::create_proof(
             params,
             pk,
@@ -38,18 +333,28 @@ impl Proof {
             &mut rng,
             &mut transcript,
         )?;
-        Ok(Proof(transcript.finalize()))
+        Ok(Proof(T::finalize(transcript)))
     }
 
     /// Verifies this proof with the given instances.
     pub fn verify(
         &self,
-        vk: &VerifyingKey<vesta::Affine>,
-        params: &Params<vesta::Affine>,
+        vk: &VerifyingKey,
+        params: &Params,
+        instance: &[&[pallas::Base]],
+    ) -> Result<(), plonk::Error> {
+        self.verify_with_transcript::<DefaultTranscript>(vk, params, instance)
+    }
+
+    /// Verifies this proof with the transcript hash it was created with.
+    pub fn verify_with_transcript<T: TranscriptHash>(
+        &self,
+        vk: &VerifyingKey,
+        params: &Params,
         instance: &[&[pallas::Base]],
     ) -> Result<(), plonk::Error> {
         let strategy = SingleVerifier::new(params);
-        let mut transcript = Blake2bRead::init(&self.0[..]);
+        let mut transcript = T::init_read(&self.0[..]);
         plonk::verify_proof(params, vk, strategy, &[instance], &mut transcript)
     }
 
@@ -62,3 +367,196 @@ impl Proof {
         self.0.clone()
     }
 }
+
+/// Accumulates proofs and checks them together. The proofs verified against the same params are
+/// reduced to a single multi-scalar multiplication, the proofs are combined with random factors
+/// so that an invalid proof can't be offset by another one.
+#[derive(Default)]
+pub struct BatchVerifier<'a> {
+    items: Vec<BatchItem<'a>>,
+}
+
+struct BatchItem<'a> {
+    k: u32,
+    vk: &'a VerifyingKey,
+    proof: &'a Proof,
+    instance: Vec<Vec<pallas::Base>>,
+}
+
+impl<'a> BatchVerifier<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the proof to be verified against the params of size k.
+    pub fn add_proof(
+        &mut self,
+        k: u32,
+        vk: &'a VerifyingKey,
+        proof: &'a Proof,
+        instance: &[&[pallas::Base]],
+    ) {
+        self.items.push(BatchItem {
+            k,
+            vk,
+            proof,
+            instance: instance.iter().map(|column| column.to_vec()).collect(),
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Checks all the proofs, with one multi-scalar multiplication per params size. Returns
+    /// false if any proof is invalid, without identifying it.
+    pub fn finalize(self, params_store: &ParamsStore) -> Result<bool, TransactionError> {
+        let mut batches: BTreeMap<u32, Vec<BatchItem>> = BTreeMap::new();
+        for item in self.items {
+            batches.entry(item.k).or_default().push(item);
+        }
+
+        for (k, items) in batches {
+            let params = params_store.get(k)?;
+            let params: &Params = &params;
+            let mut acc = params.empty_msm();
+            for item in items {
+                let instance: Vec<&[pallas::Base]> =
+                    item.instance.iter().map(|column| &column[..]).collect();
+                let strategy = AccumulatorStrategy {
+                    msm: params.empty_msm(),
+                };
+                let mut transcript = DefaultTranscript::init_read(&item.proof.0[..]);
+                let Ok(mut msm) =
+                    plonk::verify_proof(params, item.vk, strategy, &[&instance], &mut transcript)
+                else {
+                    return Ok(false);
+                };
+                msm.scale(pallas::Base::random(OsRng));
+                acc.add_msm(&msm);
+            }
+            if !acc.eval() {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+// Defers the final multi-scalar multiplication of a proof to the batch
+struct AccumulatorStrategy<'params> {
+    msm: MSM<'params, CommitmentScheme>,
+}
+
+impl<'params> VerificationStrategy<'params, CommitmentScheme> for AccumulatorStrategy<'params> {
+    type Output = MSM<'params, CommitmentScheme>;
+
+    fn process<E: EncodedChallenge<CommitmentScheme>>(
+        self,
+        f: impl FnOnce(
+            MSM<'params, CommitmentScheme>,
+        ) -> Result<Guard<'params, CommitmentScheme, E>, plonk::Error>,
+    ) -> Result<Self::Output, plonk::Error> {
+        let guard = f(self.msm)?;
+        Ok(guard.use_challenges())
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "prover")]
+mod tests {
+    use super::{BatchVerifier, Blake2bTranscript, PoseidonTranscript, Proof, TranscriptHash};
+    use crate::circuit::resource_logic_circuit::ResourceLogicCircuit;
+    use crate::circuit::resource_logic_examples::{
+        TrivialResourceLogicCircuit, TRIVIAL_RESOURCE_LOGIC_PK,
+    };
+    use crate::constant::{DEFAULT_PARAMS_STORE, RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE};
+    use pasta_curves::pallas;
+    use rand::rngs::OsRng;
+
+    fn prove_and_verify<T: TranscriptHash, U: TranscriptHash>() -> bool {
+        let params = DEFAULT_PARAMS_STORE
+            .get(RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE)
+            .unwrap();
+        let circuit = TrivialResourceLogicCircuit::default();
+        let public_inputs = circuit.get_public_inputs(OsRng);
+        let proof = Proof::create_with_transcript::<T, _>(
+            &TRIVIAL_RESOURCE_LOGIC_PK,
+            &params,
+            circuit,
+            &[public_inputs.inner()],
+            OsRng,
+        )
+        .unwrap();
+        proof
+            .verify_with_transcript::<U>(
+                TRIVIAL_RESOURCE_LOGIC_PK.get_vk(),
+                &params,
+                &[public_inputs.inner()],
+            )
+            .is_ok()
+    }
+
+    #[test]
+    fn test_transcript_hashes() {
+        assert!(prove_and_verify::<Blake2bTranscript, Blake2bTranscript>());
+        assert!(prove_and_verify::<PoseidonTranscript, PoseidonTranscript>());
+
+        // A proof only verifies with the transcript it was created with
+        assert!(!prove_and_verify::<Blake2bTranscript, PoseidonTranscript>());
+        assert!(!prove_and_verify::<PoseidonTranscript, Blake2bTranscript>());
+    }
+
+    #[test]
+    fn test_batch_verifier_rejects_invalid_item() {
+        let params = DEFAULT_PARAMS_STORE
+            .get(RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE)
+            .unwrap();
+        let items: Vec<(Proof, Vec<pallas::Base>)> = (0..3)
+            .map(|_| {
+                let circuit = TrivialResourceLogicCircuit::default();
+                let public_inputs = circuit.get_public_inputs(OsRng).inner().to_vec();
+                let proof = Proof::create(
+                    &TRIVIAL_RESOURCE_LOGIC_PK,
+                    &params,
+                    circuit,
+                    &[&public_inputs[..]],
+                    OsRng,
+                )
+                .unwrap();
+                (proof, public_inputs)
+            })
+            .collect();
+        let vk = TRIVIAL_RESOURCE_LOGIC_PK.get_vk();
+        let verify_batch = |items: &[(Proof, Vec<pallas::Base>)]| {
+            let mut batch = BatchVerifier::new();
+            for (proof, public_inputs) in items {
+                batch.add_proof(
+                    RESOURCE_LOGIC_CIRCUIT_PARAMS_SIZE,
+                    vk,
+                    proof,
+                    &[&public_inputs[..]],
+                );
+            }
+            batch.finalize(&DEFAULT_PARAMS_STORE).unwrap()
+        };
+        assert!(verify_batch(&items));
+
+        // One tampered proof fails the whole batch
+        let mut tampered_proof = items.clone();
+        let mut bytes = tampered_proof[1].0.inner();
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 1;
+        tampered_proof[1].0 = Proof::new(bytes);
+        assert!(!verify_batch(&tampered_proof));
+
+        // So does a valid proof checked against an altered instance
+        let mut altered_instance = items;
+        altered_instance[2].1[0] += pallas::Base::one();
+        assert!(!verify_batch(&altered_instance));
+    }
+}
@@ -0,0 +1,58 @@
+/// Options controlling the memory use of the proof generation.
+///
+/// The peak memory of a partial transaction is dominated by the proving keys and the number of
+/// proofs in flight. The default options keep the current behaviour: proofs are generated one by
+/// one with the precomputed proving keys, which stay in memory once created.
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ProverOptions {
+    /// The maximum number of resource logic proofs generated at the same time.
+    pub max_parallel_proofs: usize,
+    /// Use the precomputed proving keys (COMPLIANCE_PROVING_KEY, TRIVIAL_RESOURCE_LOGIC_PK).
+    /// When disabled, the keys are generated for each proof and dropped right after it, trading
+    /// proving time for memory.
+    pub use_cached_proving_keys: bool,
+    /// A params file (k = PARAMS_SIZE, as written by `Params::write`) memory-mapped when the keys
+    /// are generated for each proof. The params are dropped with the partial transaction instead
+    /// of staying in `DEFAULT_PARAMS_STORE`. Ignored with the cached proving keys.
+    pub params_file: Option<PathBuf>,
+}
+
+impl Default for ProverOptions {
+    fn default() -> Self {
+        Self {
+            max_parallel_proofs: 1,
+            use_cached_proving_keys: true,
+            params_file: None,
+        }
+    }
+}
+
+impl ProverOptions {
+    /// Sequential proving without the resident proving keys, for machines with less than 8GB RAM.
+    /// Add `with_mapped_params` to also keep the params out of the resident memory.
+    pub fn low_memory() -> Self {
+        Self {
+            max_parallel_proofs: 1,
+            use_cached_proving_keys: false,
+            params_file: None,
+        }
+    }
+
+    /// Generates up to `max_parallel_proofs` resource logic proofs at the same time.
+    pub fn parallel(max_parallel_proofs: usize) -> Self {
+        assert!(max_parallel_proofs > 0);
+        Self {
+            max_parallel_proofs,
+            use_cached_proving_keys: true,
+            params_file: None,
+        }
+    }
+
+    /// Memory-maps the params from the given file instead of using the embedded ones.
+    pub fn with_mapped_params(mut self, params_file: impl Into<PathBuf>) -> Self {
+        self.params_file = Some(params_file.into());
+        self
+    }
+}
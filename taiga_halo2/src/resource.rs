@@ -6,14 +6,18 @@ use crate::{
         },
     },
     constant::{
-        NUM_RESOURCE, POSEIDON_TO_CURVE_INPUT_LEN, PRF_EXPAND_PERSONALIZATION,
+        MAX_DYNAMIC_RESOURCE_LOGIC_NUM, NUM_RESOURCE, PRF_EXPAND_PERSONALIZATION,
         PRF_EXPAND_PERSONALIZATION_TO_FIELD, PRF_EXPAND_PSI, PRF_EXPAND_PUBLIC_INPUT_PADDING,
-        PRF_EXPAND_RCM, PRF_EXPAND_VCM_R,
+        PRF_EXPAND_RCM, PRF_EXPAND_VCM_R, RESOURCE_KIND_DOMAIN, TRANSPARENT_RESOURCE_KIND_DOMAIN,
     },
     merkle_tree::{Anchor, MerklePath, Node},
     nullifier::{Nullifier, NullifierKeyContainer},
+    utils::{hash_to_curve, poseidon_hash_n},
+};
+#[cfg(feature = "prover")]
+use crate::{
+    circuit_report::measure_resource_logic, error::TransactionError, params_store::ParamsStore,
     shielded_ptx::ResourceLogicVerifyingInfoSet,
-    utils::{poseidon_hash_n, poseidon_to_curve},
 };
 use blake2b_simd::Params as Blake2bParams;
 use ff::{FromUniformBytes, PrimeField};
@@ -32,6 +36,9 @@ use serde;
 #[cfg(feature = "borsh")]
 use borsh::{BorshDeserialize, BorshSerialize};
 
+/// The length of the canonical encoding of a resource, see `Resource::to_bytes`.
+pub const RESOURCE_BYTES_LEN: usize = 210;
+
 /// A commitment to a resource.
 #[derive(Copy, Debug, Clone, PartialEq, Eq, Default)]
 #[cfg_attr(feature = "nif", derive(NifTuple))]
@@ -43,6 +50,7 @@ impl ResourceCommitment {
         self.0
     }
 
+    /// The canonical encoding: the 32-byte little-endian repr of the commitment.
     pub fn to_bytes(&self) -> [u8; 32] {
         self.0.to_repr()
     }
@@ -80,6 +88,90 @@ impl Hash for ResourceCommitment {
     }
 }
 
+/// The id of the resource a resource logic is checked for: the nullifier of an input resource
+/// or the commitment of an output resource.
+#[derive(Copy, Debug, Clone, PartialEq, Eq)]
+pub enum OwnedResourceId {
+    Nf(Nullifier),
+    Cm(ResourceCommitment),
+}
+
+impl OwnedResourceId {
+    pub fn inner(&self) -> pallas::Base {
+        match self {
+            OwnedResourceId::Nf(nf) => nf.inner(),
+            OwnedResourceId::Cm(cm) => cm.inner(),
+        }
+    }
+
+    pub fn is_input_resource(&self) -> bool {
+        matches!(self, OwnedResourceId::Nf(_))
+    }
+
+    // The owned resource id is a field element in the circuits and public inputs, it's an input
+    // resource id iff it's one of the input nullifiers. It's the same rule as the
+    // is_input_resource_flag derivation in the resource logic circuit.
+    pub fn from_field(owned_resource_id: pallas::Base, input_nfs: &[pallas::Base]) -> Self {
+        if input_nfs.contains(&owned_resource_id) {
+            OwnedResourceId::Nf(Nullifier::from(owned_resource_id))
+        } else {
+            OwnedResourceId::Cm(ResourceCommitment::from(owned_resource_id))
+        }
+    }
+}
+
+// The default circuits own the default output resource commitment
+impl Default for OwnedResourceId {
+    fn default() -> Self {
+        OwnedResourceId::Cm(ResourceCommitment::default())
+    }
+}
+
+impl From<Nullifier> for OwnedResourceId {
+    fn from(nf: Nullifier) -> Self {
+        OwnedResourceId::Nf(nf)
+    }
+}
+
+impl From<ResourceCommitment> for OwnedResourceId {
+    fn from(cm: ResourceCommitment) -> Self {
+        OwnedResourceId::Cm(cm)
+    }
+}
+
+// A kind byte, 0 for a nullifier and 1 for a commitment, followed by the field element
+#[cfg(feature = "borsh")]
+impl BorshSerialize for OwnedResourceId {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        match self {
+            OwnedResourceId::Nf(nf) => {
+                writer.write_all(&[0])?;
+                nf.serialize(writer)
+            }
+            OwnedResourceId::Cm(cm) => {
+                writer.write_all(&[1])?;
+                cm.serialize(writer)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl BorshDeserialize for OwnedResourceId {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        match u8::deserialize_reader(reader)? {
+            0 => Ok(OwnedResourceId::Nf(Nullifier::deserialize_reader(reader)?)),
+            1 => Ok(OwnedResourceId::Cm(ResourceCommitment::deserialize_reader(
+                reader,
+            )?)),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "invalid owned resource id kind",
+            )),
+        }
+    }
+}
+
 /// A resource
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 #[cfg_attr(feature = "nif", derive(NifStruct))]
@@ -91,13 +183,19 @@ pub struct Resource {
     /// sub-resource_logics and any other data can be encoded to the value
     pub value: pallas::Base,
     /// the quantity of the resource.
-    pub quantity: u64,
+    pub quantity: u128,
     /// NullifierKeyContainer contains the nullifier_key or the nullifier_key commitment.
     pub nk_container: NullifierKeyContainer,
     /// nonce guarantees the uniqueness of the resource computable fields
     pub nonce: Nullifier,
     /// If the is_ephemeral flag is false, the merkle path authorization(membership) of input resource will be checked in ComplianceProof.
     pub is_ephemeral: bool,
+    /// If the is_transparent_kind flag is true, the compliance proof publishes the kind of the
+    /// resource (not the quantity or the owner). It's set for the designated transparent-kind
+    /// assets so their type stays traceable. The kind point of the resource is derived with the
+    /// flag (see `get_kind`), so a transparent-kind resource only balances against resources of
+    /// the kind with the flag set.
+    pub is_transparent_kind: bool,
     /// randomness seed used to derive whatever randomness needed (e.g., the resource commitment randomness and nullifier derivation randomness)
     pub rseed: pallas::Base,
 }
@@ -132,7 +230,7 @@ impl Resource {
         logic: pallas::Base,
         label: pallas::Base,
         value: pallas::Base,
-        quantity: u64,
+        quantity: u128,
         nk: pallas::Base,
         nonce: Nullifier,
         is_ephemeral: bool,
@@ -145,6 +243,7 @@ impl Resource {
             quantity,
             nk_container: NullifierKeyContainer::Key(nk),
             is_ephemeral,
+            is_transparent_kind: false,
             nonce,
             rseed,
         }
@@ -156,7 +255,7 @@ impl Resource {
         logic: pallas::Base,
         label: pallas::Base,
         value: pallas::Base,
-        quantity: u64,
+        quantity: u128,
         npk: pallas::Base,
         is_ephemeral: bool,
         rseed: pallas::Base,
@@ -168,6 +267,7 @@ impl Resource {
             quantity,
             nk_container: NullifierKeyContainer::PublicKey(npk),
             is_ephemeral,
+            is_transparent_kind: false,
             rseed,
             nonce: Nullifier::default(),
         }
@@ -178,7 +278,7 @@ impl Resource {
         logic: pallas::Base,
         label: pallas::Base,
         value: pallas::Base,
-        quantity: u64,
+        quantity: u128,
         nk_container: NullifierKeyContainer,
         nonce: Nullifier,
         is_ephemeral: bool,
@@ -191,11 +291,31 @@ impl Resource {
             quantity,
             nk_container,
             is_ephemeral,
+            is_transparent_kind: false,
             nonce,
             rseed,
         }
     }
 
+    /// Create a pure intent resource: an ephemeral resource of quantity zero that is not backed
+    /// by consuming a real resource, so an intent can be published before committing funds.
+    /// The nonce is replaced by the compliance when the intent is created as an output resource.
+    pub fn new_pure_intent_resource(
+        logic: pallas::Base,
+        label: pallas::Base,
+        value: pallas::Base,
+        nk: pallas::Base,
+        nonce: Nullifier,
+        rseed: pallas::Base,
+    ) -> Self {
+        Self::new_input_resource(logic, label, value, 0, nk, nonce, true, rseed)
+    }
+
+    /// A pure intent resource is ephemeral and of quantity zero, it doesn't change the balance.
+    pub fn is_pure_intent(&self) -> bool {
+        self.is_ephemeral && self.quantity == 0
+    }
+
     pub fn random_padding_resource<R: RngCore>(mut rng: R) -> Self {
         let logic = *COMPRESSED_TRIVIAL_RESOURCE_LOGIC_VK;
         let label = pallas::Base::random(&mut rng);
@@ -212,16 +332,18 @@ impl Resource {
             nonce,
             rseed,
             is_ephemeral: true,
+            is_transparent_kind: false,
         }
     }
 
-    // resource_commitment = poseidon_hash(logic || label || value || npk || nonce || psi || is_ephemeral || quantity || rcm)
+    // resource_commitment = poseidon_hash(logic || label || value || npk || nonce || psi || is_transparent_kind || is_ephemeral || quantity || rcm)
     pub fn commitment(&self) -> ResourceCommitment {
-        let compose_is_ephemeral_quantity = if self.is_ephemeral {
-            pallas::Base::from_u128(1 << 64).square() + pallas::Base::from(self.quantity)
-        } else {
-            pallas::Base::from(self.quantity)
-        };
+        // compose = is_transparent_kind * 2^129 + is_ephemeral * 2^128 + quantity
+        let two_pow_128 = pallas::Base::from_u128(1 << 64).square();
+        let compose_is_ephemeral_quantity = two_pow_128
+            * (pallas::Base::from(self.is_ephemeral)
+                + pallas::Base::from(self.is_transparent_kind).double())
+            + pallas::Base::from_u128(self.quantity);
         let ret = poseidon_hash_n([
             self.get_logic(),
             self.get_label(),
@@ -252,8 +374,20 @@ impl Resource {
         self.nk_container.get_npk()
     }
 
+    /// The kind point the quantity of the resource is committed to: the transparent kind point
+    /// for a transparent-kind resource, so it doesn't balance against a resource of the kind
+    /// without the flag.
     pub fn get_kind(&self) -> pallas::Point {
-        self.kind.derive_kind()
+        if self.is_transparent_kind {
+            self.kind.derive_transparent_kind()
+        } else {
+            self.kind.derive_kind()
+        }
+    }
+
+    // The kind is disclosed in the compliance proof only for transparent-kind resources
+    pub fn get_transparent_kind(&self) -> Option<ResourceKind> {
+        self.is_transparent_kind.then_some(self.kind)
     }
 
     pub fn get_logic(&self) -> pallas::Base {
@@ -292,6 +426,65 @@ impl Resource {
     pub fn set_nonce(&mut self, input_resource: &Resource) {
         self.nonce = input_resource.get_nf().unwrap();
     }
+
+    /// The canonical encoding of a resource, in the order:
+    ///   logic (32) || label (32) || value (32) || quantity (16, little-endian)
+    ///   || nk_container tag (1, 0x01 = npk, 0x02 = nk) || nk or npk (32)
+    ///   || nonce (32) || flags (1, 0x01 = is_ephemeral, 0x02 = is_transparent_kind) || rseed (32)
+    /// Field elements are encoded as their 32-byte little-endian repr.
+    pub fn to_bytes(&self) -> [u8; RESOURCE_BYTES_LEN] {
+        let mut bytes = [0u8; RESOURCE_BYTES_LEN];
+        bytes[0..64].copy_from_slice(&self.kind.to_bytes());
+        bytes[64..96].copy_from_slice(&self.value.to_repr());
+        bytes[96..112].copy_from_slice(&self.quantity.to_le_bytes());
+        let (tag, nk) = match self.nk_container {
+            NullifierKeyContainer::PublicKey(npk) => (0x01, npk),
+            NullifierKeyContainer::Key(nk) => (0x02, nk),
+        };
+        bytes[112] = tag;
+        bytes[113..145].copy_from_slice(&nk.to_repr());
+        bytes[145..177].copy_from_slice(&self.nonce.to_bytes());
+        bytes[177] = u8::from(self.is_ephemeral) | (u8::from(self.is_transparent_kind) << 1);
+        bytes[178..210].copy_from_slice(&self.rseed.to_repr());
+        bytes
+    }
+
+    /// Decodes the canonical encoding. Returns None if a field element is not canonical, the tag
+    /// byte is not one of the documented values or a flag bit is unknown.
+    pub fn from_bytes(bytes: &[u8; RESOURCE_BYTES_LEN]) -> Option<Self> {
+        let read_field = |range: std::ops::Range<usize>| {
+            Option::<pallas::Base>::from(pallas::Base::from_repr(bytes[range].try_into().unwrap()))
+        };
+        let kind = Option::<ResourceKind>::from(ResourceKind::from_bytes(
+            bytes[0..64].try_into().unwrap(),
+        ))?;
+        let value = read_field(64..96)?;
+        let quantity = u128::from_le_bytes(bytes[96..112].try_into().unwrap());
+        let nk = read_field(113..145)?;
+        let nk_container = match bytes[112] {
+            0x01 => NullifierKeyContainer::from_npk(nk),
+            0x02 => NullifierKeyContainer::from_key(nk),
+            _ => return None,
+        };
+        let nonce = Nullifier::from(read_field(145..177)?);
+        let flags = bytes[177];
+        if flags & !0x03 != 0 {
+            return None;
+        }
+        let is_ephemeral = flags & 0x01 != 0;
+        let is_transparent_kind = flags & 0x02 != 0;
+        let rseed = read_field(178..210)?;
+        Some(Self {
+            kind,
+            value,
+            quantity,
+            nk_container,
+            nonce,
+            is_ephemeral,
+            is_transparent_kind,
+            rseed,
+        })
+    }
 }
 
 #[cfg(feature = "borsh")]
@@ -305,7 +498,7 @@ impl BorshSerialize for Resource {
         // Write value
         writer.write_all(&self.value.to_repr())?;
         // Write resource quantity
-        writer.write_u64::<LittleEndian>(self.quantity)?;
+        writer.write_u128::<LittleEndian>(self.quantity)?;
         // Write nk_container
         match self.nk_container {
             NullifierKeyContainer::PublicKey(nk) => {
@@ -319,8 +512,8 @@ impl BorshSerialize for Resource {
         }?;
         // Write nonce
         writer.write_all(&self.nonce.to_bytes())?;
-        // Write is_ephemeral
-        writer.write_u8(if self.is_ephemeral { 1 } else { 0 })?;
+        // Write the is_ephemeral and is_transparent_kind flags
+        writer.write_u8(u8::from(self.is_ephemeral) | (u8::from(self.is_transparent_kind) << 1))?;
         // Write rseed
         writer.write_all(&self.rseed.to_repr())?;
 
@@ -341,7 +534,7 @@ impl BorshDeserialize for Resource {
         // Read value
         let value = read_base_field(reader)?;
         // Read resource quantity
-        let quantity = reader.read_u64::<LittleEndian>()?;
+        let quantity = reader.read_u128::<LittleEndian>()?;
         // Read nk_container
         let nk_container_type = reader.read_u8()?;
         let nk = read_base_field(reader)?;
@@ -356,17 +549,23 @@ impl BorshDeserialize for Resource {
         let nonce = Option::from(Nullifier::from_bytes(nonce_bytes))
             .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "nonce not in field"))?;
 
-        // Read is_ephemeral
-        let mut is_ephemeral_byte = [0u8; 1];
-        reader.read_exact(&mut is_ephemeral_byte)?;
-        let is_ephemeral_byte = is_ephemeral_byte[0];
-        let is_ephemeral = is_ephemeral_byte == 0x01;
+        // Read the is_ephemeral and is_transparent_kind flags
+        let mut flags = [0u8; 1];
+        reader.read_exact(&mut flags)?;
+        if flags[0] & !0x03 != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unknown flag bits",
+            ));
+        }
+        let is_ephemeral = flags[0] & 0x01 != 0;
+        let is_transparent_kind = flags[0] & 0x02 != 0;
 
         // Read rseed
         let rseed = read_base_field(reader)?;
 
         // Construct resource
-        Ok(Resource::from_full(
+        let mut resource = Resource::from_full(
             logic,
             label,
             value,
@@ -375,7 +574,9 @@ impl BorshDeserialize for Resource {
             nonce,
             is_ephemeral,
             rseed,
-        ))
+        );
+        resource.is_transparent_kind = is_transparent_kind;
+        Ok(resource)
     }
 }
 
@@ -387,9 +588,30 @@ impl ResourceKind {
         }
     }
 
+    /// The kind point, the quantities of the kind are committed to its multiples in the delta
+    /// commitments. See `utils::hash_to_curve` to derive it in other implementations.
     pub fn derive_kind(&self) -> pallas::Point {
-        let inputs = [self.logic, self.label];
-        poseidon_to_curve::<POSEIDON_TO_CURVE_INPUT_LEN>(&inputs)
+        hash_to_curve(&RESOURCE_KIND_DOMAIN, [self.logic, self.label])
+    }
+
+    /// The kind point of the transparent-kind resources of the kind, derived in the
+    /// `TRANSPARENT_RESOURCE_KIND_DOMAIN`.
+    pub fn derive_transparent_kind(&self) -> pallas::Point {
+        hash_to_curve(&TRANSPARENT_RESOURCE_KIND_DOMAIN, [self.logic, self.label])
+    }
+
+    /// The canonical encoding of a resource kind: logic (32) || label (32).
+    pub fn to_bytes(&self) -> [u8; 64] {
+        let mut bytes = [0u8; 64];
+        bytes[0..32].copy_from_slice(&self.logic.to_repr());
+        bytes[32..64].copy_from_slice(&self.label.to_repr());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: [u8; 64]) -> CtOption<Self> {
+        let logic = pallas::Base::from_repr(bytes[0..32].try_into().unwrap());
+        let label = pallas::Base::from_repr(bytes[32..64].try_into().unwrap());
+        logic.and_then(|logic| label.map(|label| Self::new(logic, label)))
     }
 }
 
@@ -453,23 +675,80 @@ impl ResourceLogics {
     pub fn new(
         application_resource_logic: Box<ResourceLogic>,
         dynamic_resource_logics: Vec<Box<ResourceLogic>>,
-    ) -> Self {
-        Self {
+    ) -> Result<Self, TransactionError> {
+        if dynamic_resource_logics.len() > MAX_DYNAMIC_RESOURCE_LOGIC_NUM {
+            return Err(TransactionError::TooManyDynamicResourceLogics(
+                dynamic_resource_logics.len(),
+            ));
+        }
+        Ok(Self {
             application_resource_logic,
             dynamic_resource_logics,
+        })
+    }
+
+    /// The resource logics of a resource without dynamic resource logics.
+    pub fn from_application(application_resource_logic: Box<ResourceLogic>) -> Self {
+        Self {
+            application_resource_logic,
+            dynamic_resource_logics: vec![],
         }
     }
 
+    pub fn get_application_resource_logic(&self) -> &ResourceLogic {
+        self.application_resource_logic.as_ref()
+    }
+
+    pub fn get_dynamic_resource_logics(&self) -> &[Box<ResourceLogic>] {
+        &self.dynamic_resource_logics
+    }
+
     // Generate resource logic proofs
-    pub fn build(&self) -> ResourceLogicVerifyingInfoSet {
-        let app_resource_logic_verifying_info =
-            self.application_resource_logic.get_verifying_info();
+    #[cfg(feature = "prover")]
+    pub fn build<R: RngCore>(
+        &self,
+        mut rng: R,
+    ) -> Result<ResourceLogicVerifyingInfoSet, TransactionError> {
+        let mut prove = |resource_logic: &ResourceLogic| {
+            measure_resource_logic(resource_logic, || {
+                resource_logic.get_verifying_info(&mut rng)
+            })
+        };
+
+        let app_resource_logic_verifying_info = prove(self.application_resource_logic.as_ref())?;
+
+        let app_dynamic_resource_logic_verifying_info = self
+            .dynamic_resource_logics
+            .iter()
+            .map(|resource_logic| prove(resource_logic.as_ref()))
+            .collect::<Result<_, _>>()?;
+
+        ResourceLogicVerifyingInfoSet::new(
+            app_resource_logic_verifying_info,
+            app_dynamic_resource_logic_verifying_info,
+        )
+    }
+
+    // Generate resource logic proofs with the params from the given store
+    #[cfg(feature = "prover")]
+    pub fn build_with_params_store<R: RngCore>(
+        &self,
+        params_store: &ParamsStore,
+        mut rng: R,
+    ) -> Result<ResourceLogicVerifyingInfoSet, TransactionError> {
+        let mut prove = |resource_logic: &ResourceLogic| {
+            measure_resource_logic(resource_logic, || {
+                resource_logic.get_verifying_info_with_params_store(params_store, &mut rng)
+            })
+        };
+
+        let app_resource_logic_verifying_info = prove(self.application_resource_logic.as_ref())?;
 
         let app_dynamic_resource_logic_verifying_info = self
             .dynamic_resource_logics
             .iter()
-            .map(|verifying_info| verifying_info.get_verifying_info())
-            .collect();
+            .map(|resource_logic| prove(resource_logic.as_ref()))
+            .collect::<Result<_, _>>()?;
 
         ResourceLogicVerifyingInfoSet::new(
             app_resource_logic_verifying_info,
@@ -477,13 +756,33 @@ impl ResourceLogics {
         )
     }
 
+    /// Fills the free dynamic resource logic slots with trivial resource logics of the owned
+    /// resource, so the number of proofs doesn't reveal how many dynamic resource logics the
+    /// resource has.
+    pub fn pad_dynamic_resource_logics(
+        mut self,
+        owned_resource_id: OwnedResourceId,
+        input_resources: [Resource; NUM_RESOURCE],
+        output_resources: [Resource; NUM_RESOURCE],
+    ) -> Self {
+        while self.dynamic_resource_logics.len() < MAX_DYNAMIC_RESOURCE_LOGIC_NUM {
+            self.dynamic_resource_logics
+                .push(Box::new(TrivialResourceLogicCircuit::new(
+                    owned_resource_id,
+                    input_resources,
+                    output_resources,
+                )));
+        }
+        self
+    }
+
     // Create resource logics for an input padding resource
     pub fn create_input_padding_resource_resource_logics(
         resource: &Resource,
         input_resources: [Resource; NUM_RESOURCE],
         output_resources: [Resource; NUM_RESOURCE],
     ) -> Self {
-        let owned_resource_id = resource.get_nf().unwrap().inner();
+        let owned_resource_id = resource.get_nf().unwrap().into();
         let application_resource_logic = Box::new(TrivialResourceLogicCircuit::new(
             owned_resource_id,
             input_resources,
@@ -501,7 +800,7 @@ impl ResourceLogics {
         input_resources: [Resource; NUM_RESOURCE],
         output_resources: [Resource; NUM_RESOURCE],
     ) -> Self {
-        let owned_resource_id = resource.commitment().inner();
+        let owned_resource_id = resource.commitment().into();
         let application_resource_logic = Box::new(TrivialResourceLogicCircuit::new(
             owned_resource_id,
             input_resources,
@@ -514,7 +813,8 @@ impl ResourceLogics {
     }
 }
 
-#[cfg(test)]
+// The random resources are shared with the fixtures of `test_utils`
+#[cfg(any(test, feature = "test-utils"))]
 pub mod tests {
     use super::{Resource, ResourceKind};
     use crate::nullifier::tests::*;
@@ -534,14 +834,124 @@ pub mod tests {
         Resource {
             kind: random_kind(&mut rng),
             value: pallas::Base::random(&mut rng),
-            quantity: rng.gen(),
+            quantity: rng.gen::<u64>().into(),
             nk_container: random_nullifier_key(&mut rng),
             is_ephemeral: false,
+            is_transparent_kind: false,
             nonce,
             rseed,
         }
     }
 
+    #[test]
+    fn owned_resource_id_from_field_test() {
+        use super::OwnedResourceId;
+        use rand::rngs::OsRng;
+
+        let mut rng = OsRng;
+        let input_resources = [random_resource(&mut rng), random_resource(&mut rng)];
+        let input_nfs = input_resources.map(|resource| resource.get_nf().unwrap().inner());
+        let nf = input_resources[1].get_nf().unwrap();
+        let cm = random_resource(&mut rng).commitment();
+
+        let owned_resource_id = OwnedResourceId::from_field(nf.inner(), &input_nfs);
+        assert_eq!(owned_resource_id, OwnedResourceId::Nf(nf));
+        assert!(owned_resource_id.is_input_resource());
+
+        let owned_resource_id = OwnedResourceId::from_field(cm.inner(), &input_nfs);
+        assert_eq!(owned_resource_id, OwnedResourceId::Cm(cm));
+        assert!(!owned_resource_id.is_input_resource());
+    }
+
+    #[cfg(feature = "borsh")]
+    #[test]
+    fn owned_resource_id_borsh_test() {
+        use super::OwnedResourceId;
+        use borsh::BorshDeserialize;
+        use rand::rngs::OsRng;
+
+        let mut rng = OsRng;
+        let resource = random_resource(&mut rng);
+        for owned_resource_id in [
+            OwnedResourceId::Nf(resource.get_nf().unwrap()),
+            OwnedResourceId::Cm(resource.commitment()),
+        ] {
+            let bytes = borsh::to_vec(&owned_resource_id).unwrap();
+            assert_eq!(bytes.len(), 33);
+            assert_eq!(
+                OwnedResourceId::try_from_slice(&bytes).unwrap(),
+                owned_resource_id
+            );
+        }
+
+        // The same field is a nullifier or a commitment by its kind byte
+        let mut bytes = borsh::to_vec(&OwnedResourceId::Cm(resource.commitment())).unwrap();
+        bytes[0] = 0;
+        assert!(OwnedResourceId::try_from_slice(&bytes)
+            .unwrap()
+            .is_input_resource());
+        bytes[0] = 2;
+        assert!(OwnedResourceId::try_from_slice(&bytes).is_err());
+    }
+
+    #[test]
+    fn resource_canonical_encoding_test() {
+        use super::{ResourceCommitment, RESOURCE_BYTES_LEN};
+        use crate::{merkle_tree::Anchor, nullifier::Nullifier};
+        use ff::PrimeField;
+        use rand::rngs::OsRng;
+
+        let mut rng = OsRng;
+        let mut resource = random_resource(&mut rng);
+        let bytes = resource.to_bytes();
+        assert_eq!(Resource::from_bytes(&bytes), Some(resource));
+
+        resource.nk_container = random_nullifier_key_commitment(&mut rng);
+        resource.is_ephemeral = true;
+        resource.quantity = u128::MAX;
+        let bytes = resource.to_bytes();
+        assert_eq!(Resource::from_bytes(&bytes), Some(resource));
+
+        // The documented field order
+        assert_eq!(bytes[0..32], resource.get_logic().to_repr());
+        assert_eq!(bytes[32..64], resource.get_label().to_repr());
+        assert_eq!(bytes[96..112], u128::MAX.to_le_bytes());
+        assert_eq!(bytes[112], 0x01);
+        assert_eq!(bytes[113..145], resource.get_npk().to_repr());
+        assert_eq!(bytes[145..177], resource.nonce.to_bytes());
+        assert_eq!(bytes[177], 0x01);
+        assert_eq!(bytes[178..RESOURCE_BYTES_LEN], resource.rseed.to_repr());
+
+        // Unknown tags and non-canonical field elements are rejected
+        let mut invalid = bytes;
+        invalid[112] = 0x03;
+        assert_eq!(Resource::from_bytes(&invalid), None);
+        let mut invalid = bytes;
+        invalid[177] = 0x04;
+        assert_eq!(Resource::from_bytes(&invalid), None);
+
+        resource.is_transparent_kind = true;
+        let bytes = resource.to_bytes();
+        assert_eq!(bytes[177], 0x03);
+        assert_eq!(Resource::from_bytes(&bytes), Some(resource));
+        let mut invalid = bytes;
+        invalid[64..96].copy_from_slice(&[0xff; 32]);
+        assert_eq!(Resource::from_bytes(&invalid), None);
+
+        let kind = random_kind(&mut rng);
+        assert_eq!(ResourceKind::from_bytes(kind.to_bytes()).unwrap(), kind);
+        assert!(bool::from(ResourceKind::from_bytes([0xff; 64]).is_none()));
+
+        // The resource above only has the npk, the nullifier needs a resource with the nk
+        let nf = random_resource(&mut rng).get_nf().unwrap();
+        assert_eq!(Nullifier::from_bytes(nf.to_bytes()).unwrap(), nf);
+        let cm = resource.commitment();
+        assert_eq!(ResourceCommitment::from_bytes(cm.to_bytes()).unwrap(), cm);
+        let anchor = Anchor::from(pallas::Base::random(&mut rng));
+        assert_eq!(Anchor::from_bytes(anchor.to_bytes()).unwrap(), anchor);
+        assert!(bool::from(Nullifier::from_bytes([0xff; 32]).is_none()));
+    }
+
     #[cfg(feature = "borsh")]
     #[test]
     fn resource_borsh_serialization_test() {
@@ -568,6 +978,11 @@ pub mod tests {
             // BorshDeserialize
             let de_resource: Resource = BorshDeserialize::deserialize(&mut borsh.as_ref()).unwrap();
             assert_eq!(output_resource, de_resource);
+
+            // Unknown flag bits are rejected, as in the canonical encoding
+            let mut invalid = borsh.clone();
+            invalid[177] = 0x04;
+            assert!(Resource::deserialize(&mut invalid.as_ref()).is_err());
         }
 
         let icm = input_resource.commitment();
@@ -0,0 +1,407 @@
+/// Detection tags and per-block detection filters, so wallets can skip the trial decryption of
+/// blocks containing nothing of theirs.
+///
+/// The receiver derives a detection key from its secret key and hands it to the senders along
+/// its encryption public key. The detection key lets its holder detect the outputs of the
+/// receiver, but neither decrypt nor spend them. The sender derives the detection tag of an
+/// output from the detection key and the digest of the whole resource ciphertext, and publishes
+/// it along the transaction. The block builder ingests the transactions with
+/// `Indexer::ingest_block` and publishes the compact bloom filter over the tags of the block. The
+/// receiver derives the candidate tag of each output with hashes only, checks it against the
+/// filter, and only does the DH of the outputs matching the filter when it decrypts them, see
+/// `scan_block`. The outputs a builder encrypted in a batch ciphertext are found with a single
+/// DH per partial transaction, see `scan_batch_outputs`.
+use crate::{
+    circuit::resource_logic_circuit::ResourceLogicPublicInputs,
+    constant::{
+        DETECTION_FILTER_BITS_PER_TAG, DETECTION_FILTER_HASH_NUM, DETECTION_FILTER_PERSONALIZATION,
+        DETECTION_TAG_PERSONALIZATION_TO_FIELD,
+    },
+    resource::Resource,
+    resource_encryption::ResourceCiphertext,
+    shielded_ptx::ShieldedPartialTransaction,
+    utils::{poseidon_hash, poseidon_hash_n},
+};
+use blake2b_simd::Params as Blake2bParams;
+use ff::PrimeField;
+use pasta_curves::pallas;
+use subtle::CtOption;
+
+#[cfg(feature = "borsh")]
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// The detection key of a receiver.
+#[derive(Copy, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DetectionKey(pallas::Base);
+
+impl DetectionKey {
+    /// dk = poseidon_hash(personalization, receiver_sk)
+    pub fn from_sk(receiver_sk: &pallas::Base) -> Self {
+        Self(poseidon_hash(
+            *DETECTION_TAG_PERSONALIZATION_TO_FIELD,
+            *receiver_sk,
+        ))
+    }
+
+    pub fn inner(&self) -> pallas::Base {
+        self.0
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_repr()
+    }
+
+    pub fn from_bytes(bytes: [u8; 32]) -> CtOption<Self> {
+        pallas::Base::from_repr(bytes).map(DetectionKey)
+    }
+}
+
+/// The detection tag of an output resource ciphertext.
+#[derive(Copy, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DetectionTag(pallas::Base);
+
+impl DetectionTag {
+    /// tag = poseidon_hash(personalization || dk || poseidon_hash(ciphertext))
+    pub fn derive(key: &DetectionKey, ciphertext: &ResourceCiphertext) -> Self {
+        Self(poseidon_hash_n([
+            *DETECTION_TAG_PERSONALIZATION_TO_FIELD,
+            key.inner(),
+            poseidon_hash_n(*ciphertext.inner()),
+        ]))
+    }
+
+    /// The candidate tag of an output for the receiver, from the ciphertext in the public inputs
+    /// of its resource logic.
+    pub fn for_receiver(public_inputs: &ResourceLogicPublicInputs, key: &DetectionKey) -> Self {
+        Self::derive(key, &public_inputs.get_resource_ciphertext())
+    }
+
+    pub fn inner(&self) -> pallas::Base {
+        self.0
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_repr()
+    }
+
+    pub fn from_bytes(bytes: [u8; 32]) -> CtOption<Self> {
+        pallas::Base::from_repr(bytes).map(DetectionTag)
+    }
+
+    // The filter bit indexes of the tag, using double hashing: h1 + i * h2
+    fn filter_indexes(&self, num_bits: usize) -> impl Iterator<Item = usize> {
+        let hash = Blake2bParams::new()
+            .hash_length(16)
+            .personal(DETECTION_FILTER_PERSONALIZATION)
+            .hash(&self.to_bytes());
+        let h1 = u64::from_le_bytes(hash.as_bytes()[0..8].try_into().unwrap());
+        let h2 = u64::from_le_bytes(hash.as_bytes()[8..16].try_into().unwrap());
+        (0..DETECTION_FILTER_HASH_NUM as u64)
+            .map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % num_bits as u64) as usize)
+    }
+}
+
+/// A bloom filter over the detection tags of the outputs of a block. It never misses a tag it
+/// was built from, and matches other tags with a probability of about 1%. A filter is never
+/// empty: the decoders reject the filters without bits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize))]
+pub struct BlockDetectionFilter {
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_filter_bits"))]
+    bits: Vec<u8>,
+}
+
+impl BlockDetectionFilter {
+    pub fn new<'a>(tags: impl IntoIterator<Item = &'a DetectionTag>) -> Self {
+        let tags: Vec<_> = tags.into_iter().collect();
+        let num_bytes = (tags.len() * DETECTION_FILTER_BITS_PER_TAG)
+            .div_ceil(8)
+            .max(1);
+        let mut filter = Self {
+            bits: vec![0; num_bytes],
+        };
+        for tag in tags {
+            filter.insert(tag);
+        }
+        filter
+    }
+
+    fn num_bits(&self) -> usize {
+        self.bits.len() * 8
+    }
+
+    fn insert(&mut self, tag: &DetectionTag) {
+        for idx in tag.filter_indexes(self.num_bits()) {
+            self.bits[idx / 8] |= 1 << (idx % 8);
+        }
+    }
+
+    /// Returns false if the block has no output with the tag, true if it may have one.
+    pub fn may_contain(&self, tag: &DetectionTag) -> bool {
+        tag.filter_indexes(self.num_bits())
+            .all(|idx| self.bits[idx / 8] & (1 << (idx % 8)) != 0)
+    }
+
+    /// Returns false if the block has no output with any of the tags.
+    pub fn may_contain_any<'a>(&self, tags: impl IntoIterator<Item = &'a DetectionTag>) -> bool {
+        tags.into_iter().any(|tag| self.may_contain(tag))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bits
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl BorshDeserialize for BlockDetectionFilter {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let bits = Vec::<u8>::deserialize_reader(reader)?;
+        if bits.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "empty block detection filter",
+            ));
+        }
+        Ok(Self { bits })
+    }
+}
+
+#[cfg(feature = "serde")]
+fn deserialize_filter_bits<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<u8>, D::Error> {
+    let bits = <Vec<u8> as serde::Deserialize>::deserialize(deserializer)?;
+    if bits.is_empty() {
+        return Err(serde::de::Error::custom("empty block detection filter"));
+    }
+    Ok(bits)
+}
+
+/// Returns the indexes of the outputs of a block that may be the receiver's, given the public
+/// inputs of their resource logics. The candidate tags are checked against the filter before any
+/// DH: the receiver only does the key agreement of the returned outputs, when it decrypts them.
+/// Nothing is derived if the filter is empty.
+pub fn scan_block(
+    filter: &BlockDetectionFilter,
+    outputs: &[ResourceLogicPublicInputs],
+    key: &DetectionKey,
+) -> Vec<usize> {
+    if filter.bits.iter().all(|byte| *byte == 0) {
+        return vec![];
+    }
+    outputs
+        .iter()
+        .enumerate()
+        .filter(|(_, public_inputs)| {
+            filter.may_contain(&DetectionTag::for_receiver(public_inputs, key))
+        })
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Returns the output resources the partial transactions encrypted to the receiver in their
+/// batch output ciphertexts, with the index of their partial transaction. Each partial
+/// transaction costs a single DH, however many outputs it has.
+pub fn scan_batch_outputs<'a>(
+    ptxs: impl IntoIterator<Item = &'a ShieldedPartialTransaction>,
+    rcv_sk: &pallas::Scalar,
+) -> Vec<(usize, Resource)> {
+    ptxs.into_iter()
+        .enumerate()
+        .flat_map(|(index, ptx)| {
+            ptx.decrypt_outputs(rcv_sk)
+                .into_iter()
+                .map(move |resource| (index, resource))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{scan_block, BlockDetectionFilter, DetectionKey, DetectionTag};
+    use crate::{
+        circuit::resource_logic_circuit::ResourceLogicPublicInputs,
+        constant::{
+            GENERATOR, RESOURCE_ENCRYPTION_CIPHERTEXT_NUM, RESOURCE_LOGIC_CIRCUIT_PUBLIC_INPUT_NUM,
+            RESOURCE_LOGIC_CIRCUIT_RESOURCE_ENCRYPTION_PK_X_IDX,
+            RESOURCE_LOGIC_CIRCUIT_RESOURCE_ENCRYPTION_PK_Y_IDX,
+            RESOURCE_LOGIC_CIRCUIT_RESOURCE_ENCRYPTION_PUBLIC_INPUT_BEGIN_IDX,
+        },
+        ka::EphemeralSecretKey,
+        resource_encryption::{ResourceCiphertext, ResourcePlaintext, SecretKey},
+        utils::mod_r_p,
+    };
+    use group::{prime::PrimeCurveAffine, Group};
+    use halo2_proofs::arithmetic::Field;
+    use pasta_curves::pallas;
+    use rand::rngs::OsRng;
+    use rand::RngCore;
+
+    fn random_ciphertext<R: RngCore>(mut rng: R, key: &SecretKey) -> ResourceCiphertext {
+        let message: Vec<_> = (0..3).map(|_| pallas::Base::random(&mut rng)).collect();
+        let plaintext = ResourcePlaintext::padding(&message);
+        ResourceCiphertext::encrypt(&plaintext, key, &pallas::Base::random(&mut rng))
+    }
+
+    fn random_tag<R: RngCore>(mut rng: R) -> DetectionTag {
+        let key = SecretKey::from_dh_exchange(
+            &pallas::Point::random(&mut rng),
+            &pallas::Scalar::random(&mut rng),
+        );
+        let detection_key = DetectionKey::from_sk(&pallas::Base::random(&mut rng));
+        DetectionTag::derive(&detection_key, &random_ciphertext(&mut rng, &key))
+    }
+
+    fn random_output_public_inputs<R: RngCore>(
+        mut rng: R,
+    ) -> (ResourceLogicPublicInputs, DetectionTag) {
+        let receiver_sk = pallas::Base::random(&mut rng);
+        output_public_inputs(&mut rng, &receiver_sk)
+    }
+
+    // The public inputs of an output resource logic encrypting to the receiver, and the tag the
+    // sender publishes along them
+    fn output_public_inputs<R: RngCore>(
+        mut rng: R,
+        receiver_sk: &pallas::Base,
+    ) -> (ResourceLogicPublicInputs, DetectionTag) {
+        let receiver_pk = GENERATOR.to_curve() * mod_r_p(*receiver_sk);
+        let esk = EphemeralSecretKey::random(&mut rng);
+        let ciphertext = random_ciphertext(&mut rng, &esk.agree(&receiver_pk));
+        let tag = DetectionTag::derive(&DetectionKey::from_sk(receiver_sk), &ciphertext);
+        let mut public_inputs: Vec<_> = (0..RESOURCE_LOGIC_CIRCUIT_PUBLIC_INPUT_NUM)
+            .map(|_| pallas::Base::random(&mut rng))
+            .collect();
+        public_inputs[RESOURCE_LOGIC_CIRCUIT_RESOURCE_ENCRYPTION_PUBLIC_INPUT_BEGIN_IDX
+            ..RESOURCE_LOGIC_CIRCUIT_RESOURCE_ENCRYPTION_PUBLIC_INPUT_BEGIN_IDX
+                + RESOURCE_ENCRYPTION_CIPHERTEXT_NUM]
+            .copy_from_slice(ciphertext.inner());
+        let (epk_x, epk_y) = esk.public_key().to_coordinates();
+        public_inputs[RESOURCE_LOGIC_CIRCUIT_RESOURCE_ENCRYPTION_PK_X_IDX] = epk_x;
+        public_inputs[RESOURCE_LOGIC_CIRCUIT_RESOURCE_ENCRYPTION_PK_Y_IDX] = epk_y;
+        (public_inputs.into(), tag)
+    }
+
+    #[test]
+    fn detection_tag_test() {
+        let mut rng = OsRng;
+        let receiver_sk = pallas::Base::random(&mut rng);
+        let detection_key = DetectionKey::from_sk(&receiver_sk);
+        assert_eq!(
+            DetectionKey::from_bytes(detection_key.to_bytes()).unwrap(),
+            detection_key
+        );
+
+        // The sender and the receiver derive the same tag
+        let (output, tag) = output_public_inputs(&mut rng, &receiver_sk);
+        assert_eq!(DetectionTag::for_receiver(&output, &detection_key), tag);
+        assert_eq!(DetectionTag::from_bytes(tag.to_bytes()).unwrap(), tag);
+
+        // The tag is bound to the whole ciphertext, not only to its nonce and MAC
+        let ciphertext = output.get_resource_ciphertext();
+        let mut tampered = ciphertext.inner().to_vec();
+        tampered[0] += pallas::Base::one();
+        assert_ne!(
+            DetectionTag::derive(&detection_key, &ResourceCiphertext::from(tampered)),
+            tag
+        );
+
+        // And to the detection key
+        let other_key = DetectionKey::from_sk(&pallas::Base::random(&mut rng));
+        assert_ne!(DetectionTag::derive(&other_key, &ciphertext), tag);
+    }
+
+    #[test]
+    fn block_detection_filter_test() {
+        let mut rng = OsRng;
+        let block_tags: Vec<_> = (0..100).map(|_| random_tag(&mut rng)).collect();
+        let filter = BlockDetectionFilter::new(&block_tags);
+        assert_eq!(filter.as_bytes().len(), 125);
+
+        // No false negatives
+        assert!(block_tags.iter().all(|tag| filter.may_contain(tag)));
+
+        // Few false positives
+        let other_tags: Vec<_> = (0..1000).map(|_| random_tag(&mut rng)).collect();
+        let false_positives = other_tags
+            .iter()
+            .filter(|tag| filter.may_contain(tag))
+            .count();
+        assert!(false_positives < 50);
+
+        // An empty block matches nothing
+        let empty_filter = BlockDetectionFilter::new(std::iter::empty());
+        assert!(!empty_filter.may_contain_any(&block_tags));
+    }
+
+    #[cfg(feature = "borsh")]
+    #[test]
+    fn block_detection_filter_borsh_test() {
+        use borsh::BorshDeserialize;
+
+        let block_tags: Vec<_> = (0..10).map(|_| random_tag(OsRng)).collect();
+        let filter = BlockDetectionFilter::new(&block_tags);
+        let bytes = borsh::to_vec(&filter).unwrap();
+        assert_eq!(
+            BlockDetectionFilter::try_from_slice(&bytes).unwrap(),
+            filter
+        );
+
+        // A filter without bits would divide by zero when queried
+        let empty = borsh::to_vec(&Vec::<u8>::new()).unwrap();
+        assert!(BlockDetectionFilter::try_from_slice(&empty).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn block_detection_filter_serde_test() {
+        let block_tags: Vec<_> = (0..10).map(|_| random_tag(OsRng)).collect();
+        let filter = BlockDetectionFilter::new(&block_tags);
+        let json = serde_json::to_string(&filter).unwrap();
+        assert_eq!(
+            serde_json::from_str::<BlockDetectionFilter>(&json).unwrap(),
+            filter
+        );
+        assert!(serde_json::from_str::<BlockDetectionFilter>(r#"{"bits":[]}"#).is_err());
+    }
+
+    #[test]
+    fn scan_block_test() {
+        let mut rng = OsRng;
+        let receiver_sk = pallas::Base::random(&mut rng);
+        let detection_key = DetectionKey::from_sk(&receiver_sk);
+        let (output, tag) = output_public_inputs(&mut rng, &receiver_sk);
+
+        // A hit: the output of the receiver is found in the block and decrypts
+        let others: Vec<_> = (0..10)
+            .map(|_| random_output_public_inputs(&mut rng))
+            .collect();
+        let mut block: Vec<_> = others.iter().map(|(output, _)| output.clone()).collect();
+        block.insert(4, output);
+        let mut block_tags: Vec<_> = others.iter().map(|(_, tag)| *tag).collect();
+        block_tags.insert(4, tag);
+        let filter = BlockDetectionFilter::new(&block_tags);
+        let candidates = scan_block(&filter, &block, &detection_key);
+        assert!(candidates.contains(&4));
+        assert!(block[4].decrypt(receiver_sk).is_some());
+
+        // A miss: the blocks without an output of the receiver are skipped, up to the false
+        // positives of the filters
+        let matched_blocks = (0..20)
+            .filter(|_| {
+                let others: Vec<_> = (0..10)
+                    .map(|_| random_output_public_inputs(&mut rng))
+                    .collect();
+                let block: Vec<_> = others.iter().map(|(output, _)| output.clone()).collect();
+                let filter = BlockDetectionFilter::new(others.iter().map(|(_, tag)| tag));
+                !scan_block(&filter, &block, &detection_key).is_empty()
+            })
+            .count();
+        assert!(matched_blocks < 5);
+        let empty_filter = BlockDetectionFilter::new(std::iter::empty());
+        assert!(scan_block(&empty_filter, &block, &detection_key).is_empty());
+    }
+}
@@ -1,21 +1,55 @@
-use crate::constant::{
-    POSEIDON_RATE, POSEIDON_WIDTH, RESOURCE_ENCRYPTION_CIPHERTEXT_NUM,
-    RESOURCE_ENCRYPTION_PLAINTEXT_NUM,
+use crate::{
+    constant::{
+        BATCH_RESOURCE_ENCRYPTION_CIPHERTEXT_NUM, GENERATOR, POSEIDON_RATE, POSEIDON_WIDTH,
+        RESOURCE_ENCRYPTION_CIPHERTEXT_NUM, RESOURCE_ENCRYPTION_PLAINTEXT_NUM,
+    },
+    nullifier::{Nullifier, NullifierKeyContainer},
+    resource::Resource,
+    utils::poseidon_hash,
 };
 use ff::PrimeField;
-use group::Curve;
+use group::prime::PrimeCurveAffine;
 use halo2_gadgets::poseidon::primitives as poseidon;
-use halo2_proofs::arithmetic::CurveAffine;
 use pasta_curves::pallas;
 
+#[cfg(feature = "borsh")]
+use crate::utils::{read_base_field, read_point};
+#[cfg(feature = "borsh")]
+use borsh::{BorshDeserialize, BorshSerialize};
+#[cfg(feature = "borsh")]
+use group::GroupEncoding;
+
+#[cfg(feature = "nif")]
+use rustler::{Decoder, Encoder, Env, NifResult, Term};
+
 #[derive(Debug, Clone)]
 pub struct ResourceCiphertext([pallas::Base; RESOURCE_ENCRYPTION_CIPHERTEXT_NUM]);
 
 #[derive(Debug, Clone)]
 pub struct ResourcePlaintext([pallas::Base; RESOURCE_ENCRYPTION_PLAINTEXT_NUM]);
 
-#[derive(Debug, Clone)]
-pub struct SecretKey(pallas::Point);
+/// The symmetric key of the resource encryption is the shared secret of the key agreement.
+pub use crate::ka::SharedSecret as SecretKey;
+
+/// Several output resources for the same receiver, encrypted under one ephemeral DH key.
+/// Output i is encrypted with the shared key and the derived nonce poseidon(encrypt_nonce, i),
+/// so every output has its own key stream. The sender pk and the nonce are stored once, and
+/// only the encrypted message and the MAC are stored per output.
+///
+/// Encoding layout (borsh):
+/// | Parameters        | type                                  | size(bytes)   |
+/// |   -               |   -                                   |   -           |
+/// | sender_pk         | pallas::Point                         | 32            |
+/// | encrypt_nonce     | pallas::Base                          | 32            |
+/// | ciphertext num    | u32                                   | 4             |
+/// | ciphertexts       | [pallas::Base; 11]                    | 352 * num     |
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BatchResourceCiphertext {
+    sender_pk: pallas::Point,
+    encrypt_nonce: pallas::Base,
+    ciphertexts: Vec<[pallas::Base; BATCH_RESOURCE_ENCRYPTION_CIPHERTEXT_NUM]>,
+}
 
 impl ResourceCiphertext {
     pub fn inner(&self) -> &[pallas::Base; RESOURCE_ENCRYPTION_CIPHERTEXT_NUM] {
@@ -123,6 +157,153 @@ impl From<Vec<pallas::Base>> for ResourceCiphertext {
     }
 }
 
+impl BatchResourceCiphertext {
+    pub fn encrypt(
+        messages: &[ResourcePlaintext],
+        rcv_pk: &pallas::Point,
+        sender_sk: &pallas::Scalar,
+        encrypt_nonce: &pallas::Base,
+    ) -> Self {
+        let key = SecretKey::from_dh_exchange(rcv_pk, sender_sk);
+        let ciphertexts = messages
+            .iter()
+            .enumerate()
+            .map(|(i, message)| {
+                let nonce = Self::derive_nonce(encrypt_nonce, i);
+                let cipher = ResourceCiphertext::encrypt(message, &key, &nonce);
+                // Drop the nonce, it is derived again when decrypting
+                let mut ret = [pallas::Base::zero(); BATCH_RESOURCE_ENCRYPTION_CIPHERTEXT_NUM];
+                ret[..RESOURCE_ENCRYPTION_PLAINTEXT_NUM]
+                    .copy_from_slice(&cipher.inner()[..RESOURCE_ENCRYPTION_PLAINTEXT_NUM]);
+                ret[RESOURCE_ENCRYPTION_PLAINTEXT_NUM] =
+                    cipher.inner()[RESOURCE_ENCRYPTION_CIPHERTEXT_NUM - 1];
+                ret
+            })
+            .collect();
+        Self {
+            sender_pk: GENERATOR.to_curve() * sender_sk,
+            encrypt_nonce: *encrypt_nonce,
+            ciphertexts,
+        }
+    }
+
+    /// Encrypts the messages padded to the next power of two with zero messages, so the number
+    /// of ciphertexts doesn't reveal the number of outputs. The padding decrypts to zeros.
+    pub fn encrypt_padded(
+        messages: &[ResourcePlaintext],
+        rcv_pk: &pallas::Point,
+        sender_sk: &pallas::Scalar,
+        encrypt_nonce: &pallas::Base,
+    ) -> Self {
+        let mut messages = messages.to_vec();
+        messages.resize(
+            messages.len().next_power_of_two(),
+            ResourcePlaintext::padding(&[]),
+        );
+        Self::encrypt(&messages, rcv_pk, sender_sk, encrypt_nonce)
+    }
+
+    pub fn sender_pk(&self) -> pallas::Point {
+        self.sender_pk
+    }
+
+    pub fn len(&self) -> usize {
+        self.ciphertexts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ciphertexts.is_empty()
+    }
+
+    /// Recovers the single-output ciphertext of output i.
+    pub fn get_ciphertext(&self, i: usize) -> Option<ResourceCiphertext> {
+        self.ciphertexts.get(i).map(|cipher| {
+            let mut ret = cipher[..RESOURCE_ENCRYPTION_PLAINTEXT_NUM].to_vec();
+            ret.push(Self::derive_nonce(&self.encrypt_nonce, i));
+            ret.push(cipher[RESOURCE_ENCRYPTION_PLAINTEXT_NUM]);
+            ret.into()
+        })
+    }
+
+    /// Decrypts all the outputs with a single DH exchange. Returns None if any MAC check fails.
+    pub fn decrypt(&self, rcv_sk: &pallas::Scalar) -> Option<Vec<Vec<pallas::Base>>> {
+        let key = SecretKey::from_dh_exchange(&self.sender_pk, rcv_sk);
+        (0..self.len())
+            .map(|i| self.get_ciphertext(i).unwrap().decrypt(&key))
+            .collect()
+    }
+
+    /// Decrypts the resources of the batch, the padding is dropped. Returns None if any MAC
+    /// check fails or an output doesn't decrypt to a resource.
+    pub fn decrypt_resources(&self, rcv_sk: &pallas::Scalar) -> Option<Vec<Resource>> {
+        let padding = ResourcePlaintext::padding(&[]).to_vec();
+        self.decrypt(rcv_sk)?
+            .into_iter()
+            .filter(|message| *message != padding)
+            .map(|message| ResourcePlaintext::from(message).to_resource())
+            .collect()
+    }
+
+    fn derive_nonce(encrypt_nonce: &pallas::Base, index: usize) -> pallas::Base {
+        poseidon_hash(*encrypt_nonce, pallas::Base::from(index as u64))
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl BorshSerialize for BatchResourceCiphertext {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(&self.sender_pk.to_bytes())?;
+        writer.write_all(&self.encrypt_nonce.to_repr())?;
+        (self.ciphertexts.len() as u32).serialize(writer)?;
+        for cipher in self.ciphertexts.iter() {
+            for element in cipher.iter() {
+                writer.write_all(&element.to_repr())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl BorshDeserialize for BatchResourceCiphertext {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let sender_pk = read_point(reader)?;
+        let encrypt_nonce = read_base_field(reader)?;
+        let num = u32::deserialize_reader(reader)?;
+        let ciphertexts = (0..num)
+            .map(|_| {
+                let mut cipher = [pallas::Base::zero(); BATCH_RESOURCE_ENCRYPTION_CIPHERTEXT_NUM];
+                for element in cipher.iter_mut() {
+                    *element = read_base_field(reader)?;
+                }
+                Ok(cipher)
+            })
+            .collect::<std::io::Result<_>>()?;
+        Ok(Self {
+            sender_pk,
+            encrypt_nonce,
+            ciphertexts,
+        })
+    }
+}
+
+// The NIF term of a batch ciphertext is its borsh encoding
+#[cfg(feature = "nif")]
+impl Encoder for BatchResourceCiphertext {
+    fn encode<'a>(&self, env: Env<'a>) -> Term<'a> {
+        borsh::to_vec(self).unwrap_or_default().encode(env)
+    }
+}
+
+#[cfg(feature = "nif")]
+impl<'a> Decoder<'a> for BatchResourceCiphertext {
+    fn decode(term: Term<'a>) -> NifResult<Self> {
+        let bytes: Vec<u8> = term.decode()?;
+        BorshDeserialize::deserialize(&mut bytes.as_slice())
+            .map_err(|_e| rustler::Error::Atom("Failure to decode"))
+    }
+}
+
 impl ResourcePlaintext {
     pub fn inner(&self) -> &[pallas::Base; RESOURCE_ENCRYPTION_PLAINTEXT_NUM] {
         &self.0
@@ -132,6 +313,53 @@ impl ResourcePlaintext {
         self.0.to_vec()
     }
 
+    /// The plaintext of a resource for its receiver: the fields encrypted by the receiver
+    /// resource logic, followed by the is_transparent_kind flag.
+    pub fn from_resource(resource: &Resource) -> Self {
+        Self::padding(&[
+            resource.kind.logic,
+            resource.kind.label,
+            resource.value,
+            pallas::Base::from_u128(resource.quantity),
+            resource.nonce.inner(),
+            resource.get_npk(),
+            pallas::Base::from(resource.is_ephemeral as u64),
+            resource.rseed,
+            pallas::Base::from(resource.is_transparent_kind as u64),
+        ])
+    }
+
+    /// Recovers the output resource of `from_resource`. Returns None if the quantity or a flag
+    /// is out of range.
+    pub fn to_resource(&self) -> Option<Resource> {
+        let quantity_bytes = self.0[3].to_repr();
+        if quantity_bytes[16..].iter().any(|byte| *byte != 0) {
+            return None;
+        }
+        let quantity = u128::from_le_bytes(quantity_bytes[..16].try_into().unwrap());
+        let to_bool = |field: pallas::Base| {
+            if field == pallas::Base::zero() {
+                Some(false)
+            } else if field == pallas::Base::one() {
+                Some(true)
+            } else {
+                None
+            }
+        };
+        let mut resource = Resource::from_full(
+            self.0[0],
+            self.0[1],
+            self.0[2],
+            quantity,
+            NullifierKeyContainer::PublicKey(self.0[5]),
+            Nullifier::from(self.0[4]),
+            to_bool(self.0[6])?,
+            self.0[7],
+        );
+        resource.is_transparent_kind = to_bool(self.0[8])?;
+        Some(resource)
+    }
+
     pub fn padding(msg: &[pallas::Base]) -> Self {
         let mut plaintext = msg.to_owned();
         let padding = std::iter::repeat(pallas::Base::zero())
@@ -151,21 +379,6 @@ impl From<Vec<pallas::Base>> for ResourcePlaintext {
     }
 }
 
-impl SecretKey {
-    pub fn from_dh_exchange(pk: &pallas::Point, sk: &pallas::Scalar) -> Self {
-        Self(pk * sk)
-    }
-
-    pub fn inner(&self) -> pallas::Point {
-        self.0
-    }
-
-    pub fn get_coordinates(&self) -> (pallas::Base, pallas::Base) {
-        let coordinates = self.0.to_affine().coordinates().unwrap();
-        (*coordinates.x(), *coordinates.y())
-    }
-}
-
 #[test]
 fn test_halo2_resource_encryption() {
     use ff::Field;
@@ -193,3 +406,141 @@ fn test_halo2_resource_encryption() {
     let decryption = cipher.decrypt(&key).unwrap();
     assert_eq!(plaintext.to_vec(), decryption);
 }
+
+#[test]
+fn test_halo2_batch_resource_encryption() {
+    use ff::Field;
+    use rand::rngs::OsRng;
+
+    let mut rng = OsRng;
+    let rcv_sk = pallas::Scalar::random(&mut rng);
+    let rcv_pk = GENERATOR.to_curve() * rcv_sk;
+    let sender_sk = pallas::Scalar::random(&mut rng);
+    let encrypt_nonce = pallas::Base::random(&mut rng);
+
+    let mut plaintexts: Vec<_> = (0..3)
+        .map(|_| {
+            let message: Vec<_> = (0..8).map(|_| pallas::Base::random(&mut rng)).collect();
+            ResourcePlaintext::padding(&message)
+        })
+        .collect();
+    // The same message twice
+    plaintexts[2] = plaintexts[0].clone();
+    let cipher = BatchResourceCiphertext::encrypt(&plaintexts, &rcv_pk, &sender_sk, &encrypt_nonce);
+    assert_eq!(cipher.len(), 3);
+
+    // Decryption
+    let decryption = cipher.decrypt(&rcv_sk).unwrap();
+    let expected: Vec<_> = plaintexts.iter().map(|p| p.to_vec()).collect();
+    assert_eq!(decryption, expected);
+
+    // Every output has its own key stream
+    assert_ne!(
+        cipher.get_ciphertext(0).unwrap().inner(),
+        cipher.get_ciphertext(2).unwrap().inner()
+    );
+
+    // Other keys can't decrypt
+    assert!(cipher.decrypt(&pallas::Scalar::random(&mut rng)).is_none());
+
+    // A tampered output fails the MAC check
+    let mut tampered = cipher.clone();
+    tampered.ciphertexts[1][0] += pallas::Base::one();
+    assert!(tampered.decrypt(&rcv_sk).is_none());
+
+    // The padded batch has a power of two outputs, the padding decrypts to zeros
+    let padded =
+        BatchResourceCiphertext::encrypt_padded(&plaintexts, &rcv_pk, &sender_sk, &encrypt_nonce);
+    assert_eq!(padded.len(), 4);
+    let decryption = padded.decrypt(&rcv_sk).unwrap();
+    assert_eq!(decryption[..3], expected);
+    assert_eq!(
+        decryption[3],
+        vec![pallas::Base::zero(); RESOURCE_ENCRYPTION_PLAINTEXT_NUM]
+    );
+}
+
+#[test]
+fn test_resource_plaintext() {
+    use crate::resource::tests::random_resource;
+    use rand::rngs::OsRng;
+
+    let mut resource = random_resource(OsRng);
+    resource.is_transparent_kind = true;
+    let decrypted = ResourcePlaintext::from_resource(&resource)
+        .to_resource()
+        .unwrap();
+    assert_eq!(decrypted.commitment(), resource.commitment());
+    assert!(decrypted.is_transparent_kind);
+
+    // A quantity or a flag out of range isn't a resource
+    let mut message = ResourcePlaintext::from_resource(&resource).to_vec();
+    message[3] = pallas::Base::from_u128(u128::MAX) + pallas::Base::one();
+    assert!(ResourcePlaintext::from(message).to_resource().is_none());
+    let mut message = ResourcePlaintext::from_resource(&resource).to_vec();
+    message[6] = pallas::Base::from(2u64);
+    assert!(ResourcePlaintext::from(message).to_resource().is_none());
+}
+
+#[cfg(feature = "borsh")]
+#[test]
+fn test_batch_resource_ciphertext_borsh() {
+    use crate::resource::tests::random_resource;
+    use ff::Field;
+    use rand::rngs::OsRng;
+
+    let mut rng = OsRng;
+    let rcv_sk = pallas::Scalar::random(&mut rng);
+    let rcv_pk = GENERATOR.to_curve() * rcv_sk;
+    let resources = [random_resource(&mut rng), random_resource(&mut rng)];
+    let plaintexts: Vec<_> = resources
+        .iter()
+        .map(ResourcePlaintext::from_resource)
+        .collect();
+    let cipher = BatchResourceCiphertext::encrypt_padded(
+        &plaintexts,
+        &rcv_pk,
+        &pallas::Scalar::random(&mut rng),
+        &pallas::Base::random(&mut rng),
+    );
+
+    let bytes = borsh::to_vec(&cipher).unwrap();
+    assert_eq!(
+        bytes.len(),
+        32 + 32 + 4 + 2 * 32 * BATCH_RESOURCE_ENCRYPTION_CIPHERTEXT_NUM
+    );
+    let de_cipher = BatchResourceCiphertext::try_from_slice(&bytes).unwrap();
+    assert_eq!(de_cipher, cipher);
+    let decrypted: Vec<_> = de_cipher
+        .decrypt_resources(&rcv_sk)
+        .unwrap()
+        .iter()
+        .map(Resource::commitment)
+        .collect();
+    assert_eq!(decrypted, resources.map(|resource| resource.commitment()));
+
+    // A truncated encoding is rejected
+    assert!(BatchResourceCiphertext::try_from_slice(&bytes[..bytes.len() - 1]).is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_batch_resource_ciphertext_serde() {
+    use ff::Field;
+    use rand::rngs::OsRng;
+
+    let mut rng = OsRng;
+    let rcv_pk = GENERATOR.to_curve() * pallas::Scalar::random(&mut rng);
+    let plaintexts = vec![ResourcePlaintext::padding(&[pallas::Base::one()])];
+    let cipher = BatchResourceCiphertext::encrypt(
+        &plaintexts,
+        &rcv_pk,
+        &pallas::Scalar::random(&mut rng),
+        &pallas::Base::random(&mut rng),
+    );
+    let json = serde_json::to_string(&cipher).unwrap();
+    assert_eq!(
+        serde_json::from_str::<BatchResourceCiphertext>(&json).unwrap(),
+        cipher
+    );
+}
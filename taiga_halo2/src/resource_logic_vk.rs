@@ -1,21 +1,21 @@
+use crate::proof::VerifyingKey;
 use blake2b_simd::Params as Blake2bParams;
-use halo2_proofs::plonk::VerifyingKey;
 use pasta_curves::{
     group::ff::{FromUniformBytes, PrimeField},
-    pallas, vesta,
+    pallas,
 };
 use std::hash::Hash;
 
 #[derive(Debug, Clone)]
 pub enum ResourceLogicVerifyingKey {
     // VK.
-    Uncompressed(VerifyingKey<vesta::Affine>),
+    Uncompressed(VerifyingKey),
     // Compress vk into one element.
     Compressed(pallas::Base),
 }
 
 impl ResourceLogicVerifyingKey {
-    pub fn from_vk(vk: VerifyingKey<vesta::Affine>) -> Self {
+    pub fn from_vk(vk: VerifyingKey) -> Self {
         Self::Uncompressed(vk)
     }
 
@@ -23,7 +23,7 @@ impl ResourceLogicVerifyingKey {
         Self::Compressed(vk)
     }
 
-    pub fn get_vk(&self) -> Option<VerifyingKey<vesta::Affine>> {
+    pub fn get_vk(&self) -> Option<VerifyingKey> {
         match self {
             ResourceLogicVerifyingKey::Uncompressed(vk) => Some(vk.clone()),
             ResourceLogicVerifyingKey::Compressed(_) => None,
@@ -75,6 +75,7 @@ impl Eq for ResourceLogicVerifyingKey {}
 #[test]
 fn test_resource_logicd_hashing() {
     use crate::circuit::resource_logic_examples::tests::random_trivial_resource_logic_circuit;
+    use crate::proof::setup_params;
     use halo2_proofs::plonk;
     use rand::rngs::OsRng;
     use std::{collections::hash_map::DefaultHasher, hash::Hasher};
@@ -89,12 +90,12 @@ fn test_resource_logicd_hashing() {
     let circuit2 = random_trivial_resource_logic_circuit(&mut OsRng);
     let circuit3 = random_trivial_resource_logic_circuit(&mut OsRng);
 
-    let params1 = halo2_proofs::poly::commitment::Params::new(12);
+    let params1 = setup_params(12);
     let vk1 = plonk::keygen_vk(&params1, &circuit1).unwrap();
     let resource_logicd1 = ResourceLogicVerifyingKey::from_vk(vk1.clone());
     let vk1s = format!("{:?}", vk1.pinned());
 
-    let params2 = halo2_proofs::poly::commitment::Params::new(12);
+    let params2 = setup_params(12);
     let vk2 = plonk::keygen_vk(&params2, &circuit2).unwrap();
     let resource_logicd2 = ResourceLogicVerifyingKey::from_vk(vk2.clone());
     let vk2s = format!("{:?}", vk2.pinned());
@@ -107,7 +108,7 @@ fn test_resource_logicd_hashing() {
     ); // check that the hashes are the same
     assert_eq!(resource_logicd1, resource_logicd2); // check that the resource_logicd's are equal
 
-    let params3 = halo2_proofs::poly::commitment::Params::new(13); // different param => different key
+    let params3 = setup_params(13); // different param => different key
     let vk3 = plonk::keygen_vk(&params3, &circuit3).unwrap();
     let resource_logicd3 = ResourceLogicVerifyingKey::from_vk(vk3.clone());
     let vk3s = format!("{:?}", vk3.pinned());
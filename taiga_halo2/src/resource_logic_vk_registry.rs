@@ -0,0 +1,259 @@
+/// A registry of the known resource logic verifying keys.
+///
+/// Maps the compressed vks of the built-in circuits to a name and a version, so proofs and
+/// verification errors can name the logic they claim ("proof claims TokenV1 logic") instead of
+/// an opaque field element. The version is bumped whenever a circuit change alters its vk.
+///
+/// The circuit types with their own `configure`, e.g. the ones loading lookup tables, are
+/// registered apart with `register_circuit_type`, so their vks can be deserialized.
+use crate::{
+    circuit::resource_logic_circuit::{ResourceLogic, ResourceLogicCircuit},
+    circuit::resource_logic_examples::{
+        TrivialResourceLogicCircuit, COMPRESSED_TRIVIAL_RESOURCE_LOGIC_VK,
+    },
+    constant::COMPLIANCE_VERIFYING_KEY,
+    error::TransactionError,
+    proof::{Params, VerifyingKey},
+    resource_logic_vk::ResourceLogicVerifyingKey,
+    utils::hex_encode,
+};
+use lazy_static::lazy_static;
+use pasta_curves::{group::ff::PrimeField, pallas};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::RwLock;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct KnownResourceLogic {
+    pub name: &'static str,
+    pub version: u32,
+}
+
+impl fmt::Display for KnownResourceLogic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}V{}", self.name, self.version)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ResourceLogicVkRegistry {
+    entries: HashMap<[u8; 32], (KnownResourceLogic, pallas::Base)>,
+}
+
+impl ResourceLogicVkRegistry {
+    pub fn register(&mut self, name: &'static str, version: u32, compressed_vk: pallas::Base) {
+        self.entries.insert(
+            compressed_vk.to_repr(),
+            (KnownResourceLogic { name, version }, compressed_vk),
+        );
+    }
+
+    pub fn lookup(&self, compressed_vk: &pallas::Base) -> Option<KnownResourceLogic> {
+        self.entries
+            .get(&compressed_vk.to_repr())
+            .map(|(logic, _)| *logic)
+    }
+
+    /// Returns "<Name>V<version>" for known vks and the hex encoded vk otherwise.
+    pub fn describe(&self, compressed_vk: &pallas::Base) -> String {
+        match self.lookup(compressed_vk) {
+            Some(logic) => logic.to_string(),
+            None => format!("unknown logic 0x{}", hex_encode(&compressed_vk.to_repr())),
+        }
+    }
+
+    /// Describes the resource logic by its vk like `describe`, or says why its vk can't be
+    /// generated, e.g. the params of its size aren't loaded.
+    pub fn describe_resource_logic(&self, resource_logic: &ResourceLogic) -> String {
+        match resource_logic.get_resource_logic_vk() {
+            Ok(vk) => self.describe(&vk.get_compressed()),
+            Err(e) => format!("unknown logic ({e})"),
+        }
+    }
+
+    /// All the entries as ("<Name>V<version>", hex encoded compressed vk), sorted by name.
+    pub fn digests(&self) -> Vec<(String, String)> {
+        let mut digests: Vec<_> = self
+            .entries
+            .values()
+            .map(|(logic, vk)| (logic.to_string(), hex_encode(&vk.to_repr())))
+            .collect();
+        digests.sort();
+        digests
+    }
+}
+
+lazy_static! {
+    // Computing the vks runs the keygen of every registered circuit, the registry is only built
+    // on first use.
+    pub static ref RESOURCE_LOGIC_VK_REGISTRY: ResourceLogicVkRegistry = {
+        let mut registry = ResourceLogicVkRegistry::default();
+        registry.register("Trivial", 1, *COMPRESSED_TRIVIAL_RESOURCE_LOGIC_VK);
+        registry.register(
+            "Compliance",
+            3,
+            ResourceLogicVerifyingKey::from_vk(COMPLIANCE_VERIFYING_KEY.clone()).get_compressed(),
+        );
+        #[cfg(feature = "example-signature-verification")]
+        {
+            use crate::circuit::resource_logic_examples::signature_verification::COMPRESSED_TOKEN_AUTH_VK;
+            registry.register("SignatureVerification", 2, *COMPRESSED_TOKEN_AUTH_VK);
+        }
+        #[cfg(feature = "example-receiver")]
+        {
+            use crate::circuit::resource_logic_examples::receiver_resource_logic::COMPRESSED_RECEIVER_VK;
+            registry.register("Receiver", 1, *COMPRESSED_RECEIVER_VK);
+        }
+        #[cfg(feature = "example-token")]
+        {
+            use crate::circuit::resource_logic_examples::token::{
+                COMPRESSED_TOKEN_U128_VK, COMPRESSED_TOKEN_VK,
+            };
+            registry.register("Token", 2, *COMPRESSED_TOKEN_VK);
+            registry.register("TokenU128", 1, *COMPRESSED_TOKEN_U128_VK);
+        }
+        #[cfg(feature = "example-intents")]
+        {
+            use crate::circuit::resource_logic_examples::{
+                cascade_intent::COMPRESSED_CASCADE_INTENT_VK,
+                or_relation_intent::COMPRESSED_OR_RELATION_INTENT_VK,
+                partial_fulfillment_intent::COMPRESSED_PARTIAL_FULFILLMENT_INTENT_VK,
+            };
+            registry.register(
+                "PartialFulfillmentIntent",
+                3,
+                *COMPRESSED_PARTIAL_FULFILLMENT_INTENT_VK,
+            );
+            registry.register("OrRelationIntent", 1, *COMPRESSED_OR_RELATION_INTENT_VK);
+            registry.register("CascadeIntent", 1, *COMPRESSED_CASCADE_INTENT_VK);
+        }
+        #[cfg(feature = "example-bridge")]
+        {
+            use crate::circuit::resource_logic_examples::bridge::COMPRESSED_BRIDGE_VK;
+            registry.register("Bridge", 2, *COMPRESSED_BRIDGE_VK);
+        }
+        #[cfg(feature = "example-payment-channel")]
+        {
+            use crate::circuit::resource_logic_examples::payment_channel::COMPRESSED_PAYMENT_CHANNEL_VK;
+            registry.register("PaymentChannel", 4, *COMPRESSED_PAYMENT_CHANNEL_VK);
+        }
+        #[cfg(feature = "example-randomness-beacon")]
+        {
+            use crate::circuit::resource_logic_examples::randomness_beacon::COMPRESSED_RANDOMNESS_BEACON_VK;
+            registry.register("RandomnessBeacon", 1, *COMPRESSED_RANDOMNESS_BEACON_VK);
+        }
+        #[cfg(feature = "example-migration")]
+        {
+            use crate::circuit::resource_logic_examples::migration::COMPRESSED_MIGRATION_VK;
+            registry.register("Migration", 1, *COMPRESSED_MIGRATION_VK);
+        }
+        #[cfg(feature = "example-subscription")]
+        {
+            use crate::circuit::resource_logic_examples::subscription::COMPRESSED_SUBSCRIPTION_VK;
+            registry.register("Subscription", 2, *COMPRESSED_SUBSCRIPTION_VK);
+        }
+        registry
+    };
+}
+
+/// Reads a vk with the constraint system of a circuit type.
+pub type VkReader = fn(&mut dyn std::io::Read, &Params) -> std::io::Result<VerifyingKey>;
+
+lazy_static! {
+    // The vk readers of the registered circuit types, by compressed vk
+    static ref CIRCUIT_TYPE_VK_READERS: RwLock<HashMap<[u8; 32], VkReader>> =
+        RwLock::new(HashMap::new());
+}
+
+/// Registers a resource logic circuit type whose `configure` isn't `ResourceLogicConfig::configure`,
+/// e.g. one loading its own lookup tables, so the vks of the type can be deserialized. The
+/// circuits built with `resource_logic_circuit_impl` share the constraint system of the trivial
+/// circuit and don't need to be registered. Returns the compressed vk of the type, the params
+/// of its size have to be loaded in `DEFAULT_PARAMS_STORE`.
+pub fn register_circuit_type<C: ResourceLogicCircuit + Default + 'static>(
+) -> Result<pallas::Base, TransactionError> {
+    let compressed_vk = C::default().get_resource_logic_vk()?.get_compressed();
+    CIRCUIT_TYPE_VK_READERS
+        .write()
+        .unwrap()
+        .insert(compressed_vk.to_repr(), |mut reader, params| {
+            VerifyingKey::read::<_, C>(&mut reader, params)
+        });
+    Ok(compressed_vk)
+}
+
+/// The vk reader of the registered circuit type of the compressed vk, or the one of the circuits
+/// sharing the constraint system of the trivial circuit.
+pub fn circuit_type_vk_reader(compressed_vk: &pallas::Base) -> VkReader {
+    CIRCUIT_TYPE_VK_READERS
+        .read()
+        .unwrap()
+        .get(&compressed_vk.to_repr())
+        .copied()
+        .unwrap_or(|mut reader, params| {
+            VerifyingKey::read::<_, TrivialResourceLogicCircuit>(&mut reader, params)
+        })
+}
+
+// The pinned digests, one "<Name>V<version> <hex vk>" per line. A circuit change that alters a
+// vk must bump the version of the logic and pin the new version with `export_vk_digests`, which
+// keeps the entries of the logics outside of the enabled features.
+#[cfg(test)]
+const VK_DIGESTS_PATH: &str = "./params/vk_digests";
+
+#[cfg(test)]
+fn read_vk_digests() -> std::collections::BTreeMap<String, String> {
+    std::fs::read_to_string(VK_DIGESTS_PATH)
+        .unwrap_or_else(|err| {
+            panic!("cannot read {VK_DIGESTS_PATH} with {err}, run export_vk_digests to create it")
+        })
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(logic, digest)| (logic.to_string(), digest.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_vk_registry_digests_are_pinned() {
+    let pinned = read_vk_digests();
+    for (logic, digest) in RESOURCE_LOGIC_VK_REGISTRY.digests() {
+        let pinned_digest = pinned.get(&logic).unwrap_or_else(|| {
+            panic!("{logic} isn't pinned, run export_vk_digests if it's a new version")
+        });
+        assert_eq!(
+            *pinned_digest, digest,
+            "the vk of {logic} changed, bump its version in the registry"
+        );
+    }
+}
+
+#[test]
+fn test_vk_registry_lookup() {
+    use crate::circuit::resource_logic_examples::TRIVIAL_RESOURCE_LOGIC_VK;
+
+    let trivial = TRIVIAL_RESOURCE_LOGIC_VK.get_compressed();
+    assert_eq!(RESOURCE_LOGIC_VK_REGISTRY.describe(&trivial), "TrivialV1");
+    assert!(RESOURCE_LOGIC_VK_REGISTRY
+        .lookup(&pallas::Base::zero())
+        .is_none());
+    assert!(RESOURCE_LOGIC_VK_REGISTRY
+        .describe(&pallas::Base::zero())
+        .starts_with("unknown logic 0x"));
+}
+
+#[ignore]
+#[test]
+fn export_vk_digests() {
+    let mut digests = if std::path::Path::new(VK_DIGESTS_PATH).exists() {
+        read_vk_digests()
+    } else {
+        std::collections::BTreeMap::new()
+    };
+    digests.extend(RESOURCE_LOGIC_VK_REGISTRY.digests());
+    let digests: String = digests
+        .into_iter()
+        .map(|(logic, digest)| format!("{logic} {digest}\n"))
+        .collect();
+    std::fs::write(VK_DIGESTS_PATH, digests)
+        .unwrap_or_else(|err| panic!("cannot create vk_digests with {}", err));
+}
@@ -0,0 +1,261 @@
+/// Canonical versioned binary encoding of transactions and shielded partial transactions.
+///
+/// Encoding layout:
+/// | Parameters    | type  | size(bytes)   |
+/// |   -           |   -   |   -           |
+/// | version       | u8    | 1             |
+/// | body          | borsh | -             |
+///
+/// The body is the borsh encoding of the value, its layout is described in `taiga_api`.
+/// Any change to the borsh layout of a transaction must bump `TRANSACTION_FORMAT_VERSION`,
+/// so that nodes running different crate versions reject each other's encodings instead
+/// of silently decoding them differently.
+///
+/// An encoding is canonical if it is exactly the bytes the encoder produces for the decoded
+/// value: the decoder rejects trailing bytes and any body that doesn't re-encode to itself.
+///
+/// The padded encodings hide the size of the value in a size bucket:
+/// | Parameters    | type  | size(bytes)   |
+/// |   -           |   -   |   -           |
+/// | length        | u32   | 4             |
+/// | encoding      | bytes | length        |
+/// | zeros         | bytes | -             |
+///
+/// The bucket is the next power of two, at least `MIN_PADDED_ENCODING_SIZE` bytes.
+use crate::{shielded_ptx::ShieldedPartialTransaction, transaction::Transaction};
+use borsh::{BorshDeserialize, BorshSerialize};
+use std::fmt;
+
+/// The version of the current transaction encoding.
+/// Version 2 writes the resource logic vks of a shielded partial transaction once in a vk table.
+/// Version 3 writes the compressed vk before each resource logic vk.
+/// Version 4 writes only the k of the resource logic circuit sizes, the rows are 2^k.
+/// Version 5 writes the batch ciphertext of the outputs after the hints.
+/// Version 6 writes the fuzzy detection tags of the outputs after the batch ciphertext.
+pub const TRANSACTION_FORMAT_VERSION: u8 = 6;
+
+/// The smallest size bucket of the padded encodings.
+pub const MIN_PADDED_ENCODING_SIZE: usize = 1 << 12;
+
+/// The bytes are not a canonical encoding of the current version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SerializationError {
+    /// The bytes are empty, the version byte is missing.
+    MissingVersion,
+    /// The bytes are encoded with an unsupported version.
+    UnsupportedVersion(u8),
+    /// The body doesn't decode to the expected value.
+    InvalidEncoding(String),
+    /// The body decodes, but it isn't the canonical encoding of the value.
+    NonCanonical,
+    /// The padded bytes are not of the size bucket of the encoding, or the padding isn't zeros.
+    InvalidPadding,
+}
+
+impl fmt::Display for SerializationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerializationError::MissingVersion => write!(f, "the version is missing"),
+            SerializationError::UnsupportedVersion(version) => {
+                write!(f, "version {version} is not supported")
+            }
+            SerializationError::InvalidEncoding(e) => write!(f, "invalid encoding: {e}"),
+            SerializationError::NonCanonical => write!(f, "the encoding is not canonical"),
+            SerializationError::InvalidPadding => write!(f, "the padding is invalid"),
+        }
+    }
+}
+
+impl std::error::Error for SerializationError {}
+
+fn encode<T: BorshSerialize>(value: &T) -> Vec<u8> {
+    let mut bytes = vec![TRANSACTION_FORMAT_VERSION];
+    value
+        .serialize(&mut bytes)
+        .expect("borsh serialization into a Vec never fails");
+    bytes
+}
+
+fn decode<T: BorshSerialize + BorshDeserialize>(bytes: &[u8]) -> Result<T, SerializationError> {
+    let (version, body) = bytes
+        .split_first()
+        .ok_or(SerializationError::MissingVersion)?;
+    if *version != TRANSACTION_FORMAT_VERSION {
+        return Err(SerializationError::UnsupportedVersion(*version));
+    }
+    // try_from_slice rejects trailing bytes
+    let value =
+        T::try_from_slice(body).map_err(|e| SerializationError::InvalidEncoding(e.to_string()))?;
+    if borsh::to_vec(&value).map_err(|e| SerializationError::InvalidEncoding(e.to_string()))?
+        != body
+    {
+        return Err(SerializationError::NonCanonical);
+    }
+    Ok(value)
+}
+
+/// Encode a transaction canonically with the current format version.
+pub fn encode_transaction(tx: &Transaction) -> Vec<u8> {
+    encode(tx)
+}
+
+/// Decode a canonical transaction encoding of the current format version.
+pub fn decode_transaction(bytes: &[u8]) -> Result<Transaction, SerializationError> {
+    decode(bytes)
+}
+
+/// Encode a shielded partial transaction canonically with the current format version.
+pub fn encode_partial_transaction(ptx: &ShieldedPartialTransaction) -> Vec<u8> {
+    encode(ptx)
+}
+
+/// Decode a canonical shielded partial transaction encoding of the current format version.
+pub fn decode_partial_transaction(
+    bytes: &[u8],
+) -> Result<ShieldedPartialTransaction, SerializationError> {
+    decode(bytes)
+}
+
+/// The size bucket of a padded encoding of `len` bytes.
+pub fn padded_size(len: usize) -> usize {
+    (len + 4).next_power_of_two().max(MIN_PADDED_ENCODING_SIZE)
+}
+
+/// Pads the bytes to their size bucket.
+pub fn pad_to_bucket(bytes: &[u8]) -> Vec<u8> {
+    let mut padded = Vec::with_capacity(padded_size(bytes.len()));
+    padded.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    padded.extend_from_slice(bytes);
+    padded.resize(padded_size(bytes.len()), 0);
+    padded
+}
+
+/// Strips the padding of `pad_to_bucket`, the padded bytes must be exactly its output.
+pub fn unpad_from_bucket(padded: &[u8]) -> Result<&[u8], SerializationError> {
+    if padded.len() < 4 {
+        return Err(SerializationError::InvalidPadding);
+    }
+    let (len, rest) = padded.split_at(4);
+    let len = u32::from_le_bytes(len.try_into().unwrap()) as usize;
+    if len > rest.len() || padded.len() != padded_size(len) {
+        return Err(SerializationError::InvalidPadding);
+    }
+    let (bytes, padding) = rest.split_at(len);
+    if padding.iter().any(|byte| *byte != 0) {
+        return Err(SerializationError::InvalidPadding);
+    }
+    Ok(bytes)
+}
+
+/// Encode a shielded partial transaction canonically and pad it to its size bucket.
+pub fn encode_padded_partial_transaction(ptx: &ShieldedPartialTransaction) -> Vec<u8> {
+    pad_to_bucket(&encode(ptx))
+}
+
+/// Decode a padded shielded partial transaction encoding of the current format version.
+pub fn decode_padded_partial_transaction(
+    bytes: &[u8],
+) -> Result<ShieldedPartialTransaction, SerializationError> {
+    decode(unpad_from_bucket(bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        executable::Executable,
+        transaction::{testing::create_transparent_ptx_bundle, ShieldedPartialTxBundle},
+    };
+    use rand::rngs::OsRng;
+
+    fn transparent_tx() -> Transaction {
+        Transaction::build(
+            OsRng,
+            ShieldedPartialTxBundle::default(),
+            create_transparent_ptx_bundle(1),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_transaction_encoding_round_trip() {
+        let tx = transparent_tx();
+        let bytes = encode_transaction(&tx);
+        assert_eq!(bytes[0], TRANSACTION_FORMAT_VERSION);
+        assert_eq!(&bytes[1..], borsh::to_vec(&tx).unwrap());
+
+        let de_tx = decode_transaction(&bytes).unwrap();
+        assert_eq!(encode_transaction(&de_tx), bytes);
+        assert_eq!(tx.execute().unwrap(), de_tx.execute().unwrap());
+    }
+
+    #[test]
+    fn test_transaction_encoding_rejects_malformed_bytes() {
+        let bytes = encode_transaction(&transparent_tx());
+
+        assert_eq!(
+            decode_transaction(&[]).unwrap_err(),
+            SerializationError::MissingVersion
+        );
+
+        let mut unknown_version = bytes.clone();
+        unknown_version[0] = TRANSACTION_FORMAT_VERSION + 1;
+        assert_eq!(
+            decode_transaction(&unknown_version).unwrap_err(),
+            SerializationError::UnsupportedVersion(TRANSACTION_FORMAT_VERSION + 1)
+        );
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(matches!(
+            decode_transaction(&trailing),
+            Err(SerializationError::InvalidEncoding(_))
+        ));
+
+        assert!(matches!(
+            decode_transaction(&bytes[..bytes.len() - 1]),
+            Err(SerializationError::InvalidEncoding(_))
+        ));
+    }
+
+    #[test]
+    fn test_padding_to_size_buckets() {
+        for len in [
+            0,
+            1,
+            MIN_PADDED_ENCODING_SIZE - 4,
+            MIN_PADDED_ENCODING_SIZE,
+            10_000,
+        ] {
+            let bytes = vec![7u8; len];
+            let padded = pad_to_bucket(&bytes);
+            assert_eq!(padded.len(), padded_size(len));
+            assert!(padded.len().is_power_of_two() && padded.len() >= MIN_PADDED_ENCODING_SIZE);
+            assert_eq!(unpad_from_bucket(&padded).unwrap(), bytes);
+        }
+        // The encodings of close sizes share a bucket
+        assert_eq!(padded_size(100), padded_size(3000));
+
+        let padded = pad_to_bucket(&[1, 2, 3]);
+        let mut dirty = padded.clone();
+        *dirty.last_mut().unwrap() = 1;
+        assert_eq!(
+            unpad_from_bucket(&dirty).unwrap_err(),
+            SerializationError::InvalidPadding
+        );
+        let mut too_long = padded.clone();
+        too_long[..4].copy_from_slice(&(padded.len() as u32).to_le_bytes());
+        assert_eq!(
+            unpad_from_bucket(&too_long).unwrap_err(),
+            SerializationError::InvalidPadding
+        );
+        assert_eq!(
+            unpad_from_bucket(&padded[..padded.len() - 1]).unwrap_err(),
+            SerializationError::InvalidPadding
+        );
+        assert_eq!(
+            unpad_from_bucket(&[]).unwrap_err(),
+            SerializationError::InvalidPadding
+        );
+    }
+}
@@ -1,19 +1,49 @@
-use crate::circuit::resource_logic_circuit::{ResourceLogic, ResourceLogicVerifyingInfo};
-use crate::compliance::{ComplianceInfo, CompliancePublicInputs};
+use crate::circuit::compliance_circuit::ComplianceCircuit;
+use crate::circuit::resource_logic_circuit::{
+    ResourceLogicPublicInputs, ResourceLogicVerifyingInfo,
+};
+use crate::compliance::CompliancePublicInputs;
 use crate::constant::{
-    COMPLIANCE_CIRCUIT_PARAMS_SIZE, COMPLIANCE_PROVING_KEY, COMPLIANCE_VERIFYING_KEY,
-    MAX_DYNAMIC_RESOURCE_LOGIC_NUM, NUM_RESOURCE, SETUP_PARAMS_MAP,
+    COMPLIANCE_CIRCUIT_PARAMS_SIZE, COMPLIANCE_VERIFYING_KEY, DEFAULT_PARAMS_STORE,
+    MAX_DYNAMIC_RESOURCE_LOGIC_NUM, NUM_RESOURCE,
 };
 use crate::delta_commitment::DeltaCommitment;
 use crate::error::TransactionError;
 use crate::executable::Executable;
-use crate::merkle_tree::Anchor;
+use crate::fuzzy_detection::FuzzyDetectionTag;
+use crate::merkle_tree::{Anchor, AnchorHistory};
 use crate::nullifier::Nullifier;
-use crate::proof::Proof;
-use crate::resource::{ResourceCommitment, ResourceLogics};
-use halo2_proofs::plonk::Error;
+use crate::params_store::ParamsStore;
+use crate::proof::{BatchVerifier, Params, Proof};
+use crate::resource::{OwnedResourceId, Resource, ResourceCommitment, ResourceKind};
+use crate::resource_encryption::BatchResourceCiphertext;
+use crate::resource_logic_vk::ResourceLogicVerifyingKey;
+use crate::verification_trace::{
+    compliance_vk_digest, enter_component, trace_check, trace_proof, vk_digest,
+};
+use halo2_proofs::plonk::{keygen_vk, Error};
 use pasta_curves::pallas;
-use rand::RngCore;
+
+#[cfg(feature = "prover")]
+use crate::{
+    circuit::{
+        resource_logic_bytecode::ApplicationByteCode, resource_logic_circuit::ResourceLogic,
+    },
+    circuit_report::measure_compliance,
+    compliance::ComplianceInfo,
+    constant::{COMPLIANCE_PROVING_KEY, PARAMS_SIZE, TAIGA_COMMITMENT_TREE_DEPTH},
+    fuzzy_detection::{FmdPublicKey, FmdSecretKey},
+    keys::KEY_CACHE,
+    merkle_tree::{MerklePath, WitnessTracker},
+    params_store::ParamsGenerationPolicy,
+    prover_options::ProverOptions,
+    resource::ResourceLogics,
+    resource_encryption::ResourcePlaintext,
+};
+#[cfg(feature = "prover")]
+use halo2_proofs::arithmetic::Field;
+#[cfg(feature = "prover")]
+use rand::{rngs::StdRng, RngCore, SeedableRng};
 
 #[cfg(feature = "nif")]
 use rustler::{Decoder, Encoder, Env, NifResult, NifStruct, Term};
@@ -21,11 +51,12 @@ use rustler::{Decoder, Encoder, Env, NifResult, NifStruct, Term};
 #[cfg(feature = "serde")]
 use serde;
 
-use crate::circuit::resource_logic_bytecode::ApplicationByteCode;
 #[cfg(feature = "borsh")]
 use borsh::{BorshDeserialize, BorshSerialize};
 #[cfg(feature = "borsh")]
 use ff::PrimeField;
+#[cfg(feature = "borsh")]
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -35,6 +66,19 @@ pub struct ShieldedPartialTransaction {
     outputs: [ResourceLogicVerifyingInfoSet; NUM_RESOURCE],
     binding_sig_r: Option<pallas::Scalar>,
     hints: Vec<u8>,
+    // The outputs encrypted to their receiver in one batch. Like the hints, it isn't checked by
+    // the proofs.
+    output_ciphertext: Option<BatchResourceCiphertext>,
+    // The fuzzy detection tags of the outputs, none or one per output. Not checked by the
+    // proofs either.
+    output_detection_tags: Vec<FuzzyDetectionTag>,
+}
+
+/// The position of a resource in a partial transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourcePosition {
+    Input(usize),
+    Output(usize),
 }
 
 #[derive(Debug, Clone)]
@@ -69,9 +113,25 @@ struct ShieldedPartialTransactionProxy {
     outputs: Vec<ResourceLogicVerifyingInfoSet>,
     binding_sig_r: Option<pallas::Scalar>,
     hints: Vec<u8>,
+    output_ciphertext: Option<BatchResourceCiphertext>,
+    output_detection_tags: Vec<FuzzyDetectionTag>,
+}
+
+// The compliances and the resource logic sets of a partial transaction, one per resource
+#[cfg(feature = "prover")]
+fn resource_array<T>(items: Vec<T>) -> Result<[T; NUM_RESOURCE], TransactionError> {
+    let num = items.len();
+    items
+        .try_into()
+        .map_err(|_| TransactionError::TooManyResources(num))
 }
 
 impl ShieldedPartialTransaction {
+    /// Builds the partial transaction from the compliances and the bytecode of the resource
+    /// logics, e.g. received from a client. Malformed inputs are rejected with an error: a
+    /// number of compliances or resource logics other than NUM_RESOURCE, a pending merkle path
+    /// or a failed proof.
+    #[cfg(feature = "prover")]
     pub fn from_bytecode<R: RngCore>(
         compliances: Vec<ComplianceInfo>,
         input_resource_app: Vec<ApplicationByteCode>,
@@ -79,60 +139,228 @@ impl ShieldedPartialTransaction {
         hints: Vec<u8>,
         mut rng: R,
     ) -> Result<Self, TransactionError> {
-        let inputs: Result<Vec<_>, _> = input_resource_app
+        // The cheap checks come before the proving
+        for num in [
+            compliances.len(),
+            input_resource_app.len(),
+            output_resource_app.len(),
+        ] {
+            if num != NUM_RESOURCE {
+                return Err(TransactionError::TooManyResources(num));
+            }
+        }
+        if compliances.iter().any(ComplianceInfo::is_pending) {
+            return Err(TransactionError::PendingMerklePath);
+        }
+
+        let inputs = input_resource_app
             .into_iter()
-            .map(|bytecode| bytecode.generate_proofs())
-            .collect();
-        let outputs: Result<Vec<_>, _> = output_resource_app
+            .map(|bytecode| bytecode.generate_proofs(&mut rng))
+            .collect::<Result<Vec<_>, _>>()?;
+        let outputs = output_resource_app
             .into_iter()
-            .map(|bytecode| bytecode.generate_proofs())
-            .collect();
+            .map(|bytecode| bytecode.generate_proofs(&mut rng))
+            .collect::<Result<Vec<_>, _>>()?;
         let mut rcv_sum = pallas::Scalar::zero();
-        let compliances: Vec<ComplianceVerifyingInfo> = compliances
+        let compliances = compliances
             .iter()
             .map(|compliance_info| {
                 rcv_sum += compliance_info.get_rcv();
-                ComplianceVerifyingInfo::create(compliance_info, &mut rng).unwrap()
+                ComplianceVerifyingInfo::create(compliance_info, &mut rng)
             })
-            .collect();
+            .collect::<Result<Vec<_>, _>>()?;
 
         Ok(Self {
-            compliances: compliances.try_into().unwrap(),
-            inputs: inputs?.try_into().unwrap(),
-            outputs: outputs?.try_into().unwrap(),
+            compliances: resource_array(compliances)?,
+            inputs: resource_array(inputs)?,
+            outputs: resource_array(outputs)?,
             binding_sig_r: Some(rcv_sum),
             hints,
+            output_ciphertext: None,
+            output_detection_tags: vec![],
         })
     }
 
+    #[cfg(feature = "prover")]
     pub fn build<R: RngCore>(
         compliance_pairs: Vec<ComplianceInfo>,
         input_resource_resource_logics: Vec<ResourceLogics>,
         output_resource_resource_logics: Vec<ResourceLogics>,
         hints: Vec<u8>,
         mut rng: R,
-    ) -> Result<Self, Error> {
+    ) -> Result<Self, TransactionError> {
         // Generate compliance proofs
         let mut rcv_sum = pallas::Scalar::zero();
-        let compliances: Vec<ComplianceVerifyingInfo> = compliance_pairs
+        let compliances = compliance_pairs
             .iter()
             .map(|compliance_info| {
                 rcv_sum += compliance_info.get_rcv();
-                ComplianceVerifyingInfo::create(compliance_info, &mut rng).unwrap()
+                ComplianceVerifyingInfo::create(compliance_info, &mut rng)
             })
-            .collect();
+            .collect::<Result<Vec<_>, _>>()?;
 
         // Generate input resource logic proofs
-        let inputs: Vec<ResourceLogicVerifyingInfoSet> = input_resource_resource_logics
+        let inputs = input_resource_resource_logics
             .iter()
-            .map(|input_resource_resource_logic| input_resource_resource_logic.build())
-            .collect();
+            .map(|input_resource_resource_logic| input_resource_resource_logic.build(&mut rng))
+            .collect::<Result<Vec<_>, _>>()?;
 
         // Generate output resource logic proofs
-        let outputs: Vec<ResourceLogicVerifyingInfoSet> = output_resource_resource_logics
+        let outputs = output_resource_resource_logics
             .iter()
-            .map(|output_resource_resource_logic| output_resource_resource_logic.build())
+            .map(|output_resource_resource_logic| output_resource_resource_logic.build(&mut rng))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            compliances: compliances.try_into().unwrap(),
+            inputs: inputs.try_into().unwrap(),
+            outputs: outputs.try_into().unwrap(),
+            binding_sig_r: Some(rcv_sum),
+            hints,
+            output_ciphertext: None,
+            output_detection_tags: vec![],
+        })
+    }
+
+    /// Builds the partial transaction with the params from the given store instead of the
+    /// default params and the precomputed keys.
+    #[cfg(feature = "prover")]
+    pub fn build_with_params_store<R: RngCore>(
+        compliance_pairs: Vec<ComplianceInfo>,
+        input_resource_resource_logics: Vec<ResourceLogics>,
+        output_resource_resource_logics: Vec<ResourceLogics>,
+        hints: Vec<u8>,
+        params_store: &ParamsStore,
+        mut rng: R,
+    ) -> Result<Self, TransactionError> {
+        if compliance_pairs.iter().any(ComplianceInfo::is_pending) {
+            return Err(TransactionError::PendingMerklePath);
+        }
+        // Generate compliance proofs
+        let mut rcv_sum = pallas::Scalar::zero();
+        let compliances = compliance_pairs
+            .iter()
+            .map(|compliance_info| {
+                rcv_sum += compliance_info.get_rcv();
+                ComplianceVerifyingInfo::create_with_params_store(
+                    compliance_info,
+                    params_store,
+                    &mut rng,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Generate input resource logic proofs
+        let inputs = input_resource_resource_logics
+            .iter()
+            .map(|resource_logics| resource_logics.build_with_params_store(params_store, &mut rng))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Generate output resource logic proofs
+        let outputs = output_resource_resource_logics
+            .iter()
+            .map(|resource_logics| resource_logics.build_with_params_store(params_store, &mut rng))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            compliances: compliances.try_into().unwrap(),
+            inputs: inputs.try_into().unwrap(),
+            outputs: outputs.try_into().unwrap(),
+            binding_sig_r: Some(rcv_sum),
+            hints,
+            output_ciphertext: None,
+            output_detection_tags: vec![],
+        })
+    }
+
+    /// Builds the partial transaction with the memory/parallelism trade-offs from the options.
+    /// The compliance proofs are generated one by one as they share the rng. Every resource logic
+    /// proving thread gets its own rng, seeded from the given one.
+    #[cfg(feature = "prover")]
+    pub fn build_with_options<R: RngCore>(
+        compliance_pairs: Vec<ComplianceInfo>,
+        input_resource_resource_logics: Vec<ResourceLogics>,
+        output_resource_resource_logics: Vec<ResourceLogics>,
+        hints: Vec<u8>,
+        options: &ProverOptions,
+        mut rng: R,
+    ) -> Result<Self, TransactionError> {
+        if compliance_pairs.iter().any(ComplianceInfo::is_pending) {
+            return Err(TransactionError::PendingMerklePath);
+        }
+        // The mapped params only live as long as the proving, the other sizes are generated
+        let mapped_params_store = match &options.params_file {
+            Some(params_file) if !options.use_cached_proving_keys => {
+                let params_store = ParamsStore::new(ParamsGenerationPolicy::Lazy);
+                params_store.load_from_file(PARAMS_SIZE, params_file)?;
+                Some(params_store)
+            }
+            _ => None,
+        };
+        let params_store = mapped_params_store
+            .as_ref()
+            .unwrap_or(&DEFAULT_PARAMS_STORE);
+
+        // Generate compliance proofs
+        let mut rcv_sum = pallas::Scalar::zero();
+        let compliances = compliance_pairs
+            .iter()
+            .map(|compliance_info| {
+                rcv_sum += compliance_info.get_rcv();
+                if options.use_cached_proving_keys {
+                    Ok(ComplianceVerifyingInfo::create(compliance_info, &mut rng)?)
+                } else {
+                    ComplianceVerifyingInfo::create_with_params_store(
+                        compliance_info,
+                        params_store,
+                        &mut rng,
+                    )
+                }
+            })
+            .collect::<Result<Vec<_>, TransactionError>>()?;
+
+        // Generate input and output resource logic proofs
+        let build = |resource_logics: &ResourceLogics, mut rng: StdRng| {
+            if options.use_cached_proving_keys {
+                resource_logics.build(&mut rng)
+            } else {
+                resource_logics.build_with_params_store(params_store, &mut rng)
+            }
+        };
+        let all_resource_logics: Vec<&ResourceLogics> = input_resource_resource_logics
+            .iter()
+            .chain(output_resource_resource_logics.iter())
             .collect();
+        let mut verifying_info_sets = Vec::with_capacity(all_resource_logics.len());
+        for chunk in all_resource_logics.chunks(options.max_parallel_proofs.max(1)) {
+            let mut rngs = chunk
+                .iter()
+                .map(|_| StdRng::from_rng(&mut rng).expect("seeding the proving rng"))
+                .collect::<Vec<_>>();
+            if chunk.len() == 1 {
+                verifying_info_sets.push(build(chunk[0], rngs.pop().unwrap())?);
+                continue;
+            }
+            let results: Vec<Result<ResourceLogicVerifyingInfoSet, TransactionError>> =
+                std::thread::scope(|scope| {
+                    let handles: Vec<_> = chunk
+                        .iter()
+                        .zip(rngs)
+                        .map(|(resource_logics, rng)| {
+                            scope.spawn(move || build(*resource_logics, rng))
+                        })
+                        .collect();
+                    handles
+                        .into_iter()
+                        .map(|handle| handle.join().expect("proving thread panicked"))
+                        .collect()
+                });
+            for result in results {
+                verifying_info_sets.push(result?);
+            }
+        }
+        let outputs = verifying_info_sets.split_off(input_resource_resource_logics.len());
+        let inputs = verifying_info_sets;
 
         Ok(Self {
             compliances: compliances.try_into().unwrap(),
@@ -140,28 +368,200 @@ impl ShieldedPartialTransaction {
             outputs: outputs.try_into().unwrap(),
             binding_sig_r: Some(rcv_sum),
             hints,
+            output_ciphertext: None,
+            output_detection_tags: vec![],
         })
     }
 
+    /// Re-proves the compliance unit at `index` after its witness changed, e.g. a new merkle path
+    /// of the input resource after the commitment tree grew, keeping the other proofs. The new
+    /// witness may only change the anchor, the nullifier, the output commitment, the delta and
+    /// the resource logic commitments the other proofs depend on must stay the same.
+    #[cfg(feature = "prover")]
+    pub fn reprove_compliance<R: RngCore>(
+        &mut self,
+        index: usize,
+        compliance_info: &ComplianceInfo,
+        rng: R,
+    ) -> Result<(), TransactionError> {
+        let verifying_info = self.compliances.get(index).ok_or_else(|| {
+            TransactionError::InvalidReproving(format!("no compliance unit at {index}"))
+        })?;
+        if compliance_info.is_pending() {
+            return Err(TransactionError::PendingMerklePath);
+        }
+        let (compliance_instance, _) = compliance_info.build();
+        let mut expected_instance = verifying_info.compliance_instance.clone();
+        expected_instance.anchor = compliance_instance.anchor;
+        if expected_instance.to_instance() != compliance_instance.to_instance() {
+            return Err(TransactionError::InvalidReproving(format!(
+                "the compliance unit at {index} changes more than the anchor"
+            )));
+        }
+
+        self.compliances[index] = ComplianceVerifyingInfo::create(compliance_info, rng)?;
+        Ok(())
+    }
+
+    /// Re-proves a resource logic of the resource at `position` after its witness changed,
+    /// keeping the other proofs. `dynamic_index` selects a dynamic resource logic, the
+    /// application resource logic is re-proved if it's None. The new proof must own the same
+    /// resource.
+    #[cfg(feature = "prover")]
+    pub fn reprove_resource_logic<R: RngCore>(
+        &mut self,
+        position: ResourcePosition,
+        dynamic_index: Option<usize>,
+        resource_logic: &ResourceLogic,
+        mut rng: R,
+    ) -> Result<(), TransactionError> {
+        let verifying_info_set = match position {
+            ResourcePosition::Input(index) => self.inputs.get_mut(index),
+            ResourcePosition::Output(index) => self.outputs.get_mut(index),
+        }
+        .ok_or_else(|| {
+            TransactionError::InvalidReproving(format!("no resource at {position:?}"))
+        })?;
+        let verifying_info = match dynamic_index {
+            None => &mut verifying_info_set.app_resource_logic_verifying_info,
+            Some(index) => verifying_info_set
+                .app_dynamic_resource_logic_verifying_info
+                .get_mut(index)
+                .ok_or_else(|| {
+                    TransactionError::InvalidReproving(format!(
+                        "no dynamic resource logic {index} at {position:?}"
+                    ))
+                })?,
+        };
+
+        let new_verifying_info = resource_logic.get_verifying_info(&mut rng)?;
+        if new_verifying_info.get_owned_resource_id() != verifying_info.get_owned_resource_id() {
+            return Err(TransactionError::InconsistentOwnedResourceID);
+        }
+        *verifying_info = new_verifying_info;
+        Ok(())
+    }
+
+    /// The indexes of the compliance units whose anchor is not in the history anymore.
+    pub fn check_anchors(&self, anchor_history: &AnchorHistory) -> Vec<usize> {
+        self.compliances
+            .iter()
+            .enumerate()
+            .filter(|(_, verifying_info)| {
+                !anchor_history.contains(&verifying_info.compliance_instance.anchor)
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Refreshes the merkle paths of the compliance units with a stale anchor from the tracker
+    /// and re-proves them, e.g. for a long-lived signed intent whose tree moved on before the
+    /// settlement. `compliances` are the compliance infos the partial transaction was built
+    /// with, they are updated in place. Returns the indexes of the re-proved compliance units.
+    #[cfg(feature = "prover")]
+    pub fn refresh_stale_anchors<R: RngCore>(
+        &mut self,
+        anchor_history: &AnchorHistory,
+        tracker: &impl WitnessTracker,
+        compliances: &mut [ComplianceInfo],
+        mut rng: R,
+    ) -> Result<Vec<usize>, TransactionError> {
+        if compliances.len() != self.compliances.len() {
+            return Err(TransactionError::InvalidReproving(format!(
+                "expected {} compliance infos",
+                self.compliances.len()
+            )));
+        }
+        let latest_anchor = anchor_history.latest().ok_or_else(|| {
+            TransactionError::InvalidReproving("the anchor history is empty".to_string())
+        })?;
+
+        let stale = self.check_anchors(anchor_history);
+        for &index in stale.iter() {
+            compliances[index].refresh_input_anchor(tracker, latest_anchor)?;
+            // Don't prove for a root the ledger doesn't accept either
+            if !anchor_history.contains(&compliances[index].get_input_anchor()) {
+                return Err(TransactionError::InvalidReproving(format!(
+                    "the tracked merkle path of the compliance unit at {index} is stale"
+                )));
+            }
+            self.reprove_compliance(index, &compliances[index], &mut rng)?;
+        }
+        Ok(stale)
+    }
+
     // verify zk proof
     pub fn verify_proof(&self) -> Result<(), TransactionError> {
         // Verify compliance proofs
-        for verifying_info in self.compliances.iter() {
-            verifying_info.verify()?;
+        for (index, verifying_info) in self.compliances.iter().enumerate() {
+            trace_proof("compliance proof", index, compliance_vk_digest, || {
+                verifying_info.verify()
+            })?;
         }
 
         // Verify resource logic proofs of input resources
-        for verifying_info in self.inputs.iter() {
+        for (index, verifying_info) in self.inputs.iter().enumerate() {
+            let _span = enter_component("input resource", index);
             verifying_info.verify()?;
         }
         // Verify resource logic proofs of output resources
-        for verifying_info in self.outputs.iter() {
+        for (index, verifying_info) in self.outputs.iter().enumerate() {
+            let _span = enter_component("output resource", index);
             verifying_info.verify()?;
         }
 
         Ok(())
     }
 
+    /// Checks the ptx like `execute`, except that the proofs are added to the batch instead of
+    /// being verified.
+    pub fn execute_batched<'a>(
+        &'a self,
+        batch: &mut BatchVerifier<'a>,
+    ) -> Result<(), TransactionError> {
+        for verifying_info in self.compliances.iter() {
+            verifying_info.add_to_batch(batch);
+        }
+        for (index, verifying_info) in self.inputs.iter().enumerate() {
+            let _span = enter_component("input resource", index);
+            verifying_info.add_to_batch(batch)?;
+        }
+        for (index, verifying_info) in self.outputs.iter().enumerate() {
+            let _span = enter_component("output resource", index);
+            verifying_info.add_to_batch(batch)?;
+        }
+
+        trace_check("nullifier consistency", 0, || self.check_nullifiers())?;
+        trace_check("output commitment consistency", 0, || {
+            self.check_resource_commitments()
+        })?;
+        Ok(())
+    }
+
+    // verify zk proof with the params from the given store
+    pub fn verify_proof_with_params_store(
+        &self,
+        params_store: &ParamsStore,
+    ) -> Result<(), TransactionError> {
+        let compliance_params = params_store.get(COMPLIANCE_CIRCUIT_PARAMS_SIZE)?;
+        for (index, verifying_info) in self.compliances.iter().enumerate() {
+            trace_proof("compliance proof", index, compliance_vk_digest, || {
+                verifying_info.verify_with_params(&compliance_params)
+            })?;
+        }
+
+        for (index, verifying_info) in self.inputs.iter().enumerate() {
+            let _span = enter_component("input resource", index);
+            verifying_info.verify_with_params_store(params_store)?;
+        }
+        for (index, verifying_info) in self.outputs.iter().enumerate() {
+            let _span = enter_component("output resource", index);
+            verifying_info.verify_with_params_store(params_store)?;
+        }
+
+        Ok(())
+    }
+
     // check the nullifiers are from compliance proofs
     fn check_nullifiers(&self) -> Result<(), TransactionError> {
         assert_eq!(NUM_RESOURCE, 2);
@@ -193,7 +593,7 @@ impl ShieldedPartialTransaction {
             }
 
             // Check the owned_resource_id that resource logic uses is consistent with the nf from the compliance circuit
-            if owned_resource_id != compliance_nf.inner() {
+            if owned_resource_id != OwnedResourceId::Nf(*compliance_nf) {
                 return Err(TransactionError::InconsistentOwnedResourceID);
             }
         }
@@ -231,7 +631,7 @@ impl ShieldedPartialTransaction {
             }
 
             // Check the owned_resource_id that resource logic uses is consistent with the cm from the compliance circuit
-            if owned_resource_id != compliance_cm.inner() {
+            if owned_resource_id != OwnedResourceId::Cm(*compliance_cm) {
                 return Err(TransactionError::InconsistentOwnedResourceID);
             }
         }
@@ -246,9 +646,24 @@ impl ShieldedPartialTransaction {
             outputs: self.outputs.to_vec(),
             binding_sig_r: self.binding_sig_r,
             hints: self.hints.clone(),
+            output_ciphertext: self.output_ciphertext.clone(),
+            output_detection_tags: self.output_detection_tags.clone(),
         }
     }
 
+    /// The verification weight of the proofs: the rows of the compliance circuits and the
+    /// declared rows of the resource logic circuits.
+    pub fn weight(&self) -> u64 {
+        let compliance_weight = (self.compliances.len() as u64) << COMPLIANCE_CIRCUIT_PARAMS_SIZE;
+        compliance_weight
+            + self
+                .inputs
+                .iter()
+                .chain(self.outputs.iter())
+                .map(|verifying_info| verifying_info.weight())
+                .sum::<u64>()
+    }
+
     pub fn get_binding_sig_r(&self) -> Option<pallas::Scalar> {
         self.binding_sig_r
     }
@@ -257,12 +672,516 @@ impl ShieldedPartialTransaction {
         self.hints.clone()
     }
 
+    /// The batch ciphertext of the outputs, if the builder encrypted them to their receiver.
+    pub fn get_output_ciphertext(&self) -> Option<&BatchResourceCiphertext> {
+        self.output_ciphertext.as_ref()
+    }
+
+    /// The fuzzy detection tags of the outputs, in the order of the outputs. Empty if the
+    /// builder tagged no output.
+    pub fn get_output_detection_tags(&self) -> &[FuzzyDetectionTag] {
+        &self.output_detection_tags
+    }
+
+    /// Decrypts the output resources of the batch ciphertext with the receiver key, with a
+    /// single key agreement for all the outputs. Only the resources committed by the compliance
+    /// proofs are returned, an empty list if the outputs aren't the receiver's.
+    pub fn decrypt_outputs(&self, rcv_sk: &pallas::Scalar) -> Vec<Resource> {
+        let output_cms = self.get_output_cms();
+        self.output_ciphertext
+            .as_ref()
+            .and_then(|cipher| cipher.decrypt_resources(rcv_sk))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|resource| output_cms.contains(&resource.commitment()))
+            .collect()
+    }
+
+    /// The kinds the compliance proofs disclose, of the transparent-kind input and output
+    /// resources.
+    pub fn get_transparent_kinds(&self) -> Vec<ResourceKind> {
+        self.compliances
+            .iter()
+            .flat_map(|compliance| {
+                [
+                    compliance.compliance_instance.input_transparent_kind,
+                    compliance.compliance_instance.output_transparent_kind,
+                ]
+            })
+            .flatten()
+            .collect()
+    }
+
+    /// The public inputs of the resource logic proofs of the output resources. The receivers
+    /// trial-decrypt the resource ciphertexts in them to find their resources.
+    pub fn get_output_resource_logic_public_inputs(&self) -> Vec<&ResourceLogicPublicInputs> {
+        self.outputs
+            .iter()
+            .flat_map(|verifying_info_set| verifying_info_set.get_public_inputs())
+            .collect()
+    }
+
+    /// The public inputs of the application resource logics of the input resources whose logic
+    /// is `compressed_vk`, e.g. to check what the logics of a kind publish for the ledger.
+    pub fn get_input_resource_logic_public_inputs(
+        &self,
+        compressed_vk: pallas::Base,
+    ) -> Vec<&ResourceLogicPublicInputs> {
+        self.inputs
+            .iter()
+            .map(|verifying_info_set| &verifying_info_set.app_resource_logic_verifying_info)
+            .filter(|verifying_info| {
+                ResourceLogicVerifyingKey::from_vk((*verifying_info.vk).clone()).get_compressed()
+                    == compressed_vk
+            })
+            .map(|verifying_info| &verifying_info.public_inputs)
+            .collect()
+    }
+
+    /// The distinct resource logic vks in the order of first use, and the index of the vk of
+    /// every resource logic proof in the serialization order.
+    #[cfg(feature = "borsh")]
+    fn resource_logic_vk_table(&self) -> (Vec<&ResourceLogicVerifyingInfo>, Vec<u8>) {
+        let mut vk_table: Vec<&ResourceLogicVerifyingInfo> = vec![];
+        let mut vk_bytes_table: Vec<Vec<u8>> = vec![];
+        let mut vk_indexes = vec![];
+        for verifying_info_set in self.inputs.iter().chain(self.outputs.iter()) {
+            for verifying_info in
+                std::iter::once(&verifying_info_set.app_resource_logic_verifying_info).chain(
+                    verifying_info_set
+                        .app_dynamic_resource_logic_verifying_info
+                        .iter(),
+                )
+            {
+                // The proofs of a decoded ptx share their vk, the bytes compare the others
+                if let Some(index) = vk_table
+                    .iter()
+                    .position(|info| Arc::ptr_eq(&info.vk, &verifying_info.vk))
+                {
+                    vk_indexes.push(index as u8);
+                    continue;
+                }
+                let vk_bytes = verifying_info.vk.to_bytes();
+                let index = match vk_bytes_table.iter().position(|bytes| *bytes == vk_bytes) {
+                    Some(index) => index,
+                    None => {
+                        vk_table.push(verifying_info);
+                        vk_bytes_table.push(vk_bytes);
+                        vk_table.len() - 1
+                    }
+                };
+                vk_indexes.push(index as u8);
+            }
+        }
+        (vk_table, vk_indexes)
+    }
+
+    /// Feeds the statement of the ptx to the transaction id hash: the compliance instances, then
+    /// the public inputs of the resource logics of each resource. The proofs are randomized by
+    /// the prover, and the binding signature randomness and the hints are private, so they are
+    /// not hashed.
+    #[cfg(feature = "borsh")]
+    pub(crate) fn update_txid_hash(&self, h: &mut blake2b_simd::State) {
+        for compliance in self.compliances.iter() {
+            for field in compliance.compliance_instance.to_instance() {
+                h.update(&field.to_repr());
+            }
+        }
+        for verifying_info_set in self.inputs.iter().chain(self.outputs.iter()) {
+            let public_inputs = verifying_info_set.get_public_inputs();
+            h.update(&(public_inputs.len() as u32).to_le_bytes());
+            for field in public_inputs
+                .iter()
+                .flat_map(|public_inputs| public_inputs.inner())
+            {
+                h.update(&field.to_repr());
+            }
+        }
+    }
+
     pub fn clean_private_info(&mut self) {
         self.binding_sig_r = None;
         self.hints = vec![];
     }
 }
 
+/// Creates the resource logics of a resource from the resources of the partial transaction, as
+/// the resource logics witness all of them. The owned resource is passed with the nonce set by
+/// its compliance pair.
+#[cfg(feature = "prover")]
+pub type ResourceLogicsConstructor<'a> = Box<
+    dyn FnOnce(
+            &mut dyn RngCore,
+            &Resource,
+            [Resource; NUM_RESOURCE],
+            [Resource; NUM_RESOURCE],
+        ) -> ResourceLogics
+        + 'a,
+>;
+
+/// Builds a shielded partial transaction from the real resources only. The free slots are
+/// filled with padding resources and their padding resource logics, and the i-th input resource
+/// is paired with the i-th output resource in the compliance units.
+///
+/// The anchor of the padding and ephemeral input resources is not checked against a merkle
+/// path, it's the padding anchor if set, otherwise the anchor of the first non-ephemeral input
+/// resource, otherwise a random anchor. A ptx consuming only ephemeral resources should set the
+/// padding anchor to a recent root, as the ledger checks the anchors.
+///
+/// Pure intent resources, see `Resource::new_pure_intent_resource`, are added with `add_intent`
+/// and take the output slots after the outputs. A ptx publishing only pure intents consumes
+/// padding input resources only, it is balanced on its own and commits no funds.
+///
+/// With `with_uniform_size`, every resource gets `MAX_DYNAMIC_RESOURCE_LOGIC_NUM` dynamic
+/// resource logics, the free slots are proved with the trivial resource logic. Together with
+/// `serialization::encode_padded_partial_transaction`, the size of the ptx doesn't reveal how
+/// many real resources and dynamic resource logics it has.
+#[cfg(feature = "prover")]
+#[derive(Default)]
+pub struct ShieldedPartialTransactionBuilder<'a> {
+    inputs: Vec<(Resource, MerklePath, ResourceLogicsConstructor<'a>)>,
+    outputs: Vec<(Resource, ResourceLogicsConstructor<'a>)>,
+    intents: Vec<(Resource, ResourceLogicsConstructor<'a>)>,
+    padding_anchor: Option<Anchor>,
+    uniform_size: bool,
+    hints: Vec<u8>,
+    output_encryption_pk: Option<pallas::Point>,
+    // The fuzzy message detection key of the receiver of each output, if any
+    output_detection_keys: Vec<Option<FmdPublicKey>>,
+}
+
+#[cfg(feature = "prover")]
+impl<'a> ShieldedPartialTransactionBuilder<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_input(
+        mut self,
+        resource: Resource,
+        merkle_path: MerklePath,
+        resource_logics: impl FnOnce(
+                &mut dyn RngCore,
+                &Resource,
+                [Resource; NUM_RESOURCE],
+                [Resource; NUM_RESOURCE],
+            ) -> ResourceLogics
+            + 'a,
+    ) -> Self {
+        self.inputs
+            .push((resource, merkle_path, Box::new(resource_logics)));
+        self
+    }
+
+    pub fn add_output(
+        mut self,
+        resource: Resource,
+        resource_logics: impl FnOnce(
+                &mut dyn RngCore,
+                &Resource,
+                [Resource; NUM_RESOURCE],
+                [Resource; NUM_RESOURCE],
+            ) -> ResourceLogics
+            + 'a,
+    ) -> Self {
+        self.outputs.push((resource, Box::new(resource_logics)));
+        self.output_detection_keys.push(None);
+        self
+    }
+
+    /// Adds an output resource tagged for the receiver with the fuzzy message detection key, so
+    /// the detection server of the receiver finds it, see `FmdDetectionKey::detect_outputs`.
+    pub fn add_detectable_output(
+        mut self,
+        resource: Resource,
+        detection_key: FmdPublicKey,
+        resource_logics: impl FnOnce(
+                &mut dyn RngCore,
+                &Resource,
+                [Resource; NUM_RESOURCE],
+                [Resource; NUM_RESOURCE],
+            ) -> ResourceLogics
+            + 'a,
+    ) -> Self {
+        self.outputs.push((resource, Box::new(resource_logics)));
+        self.output_detection_keys.push(Some(detection_key));
+        self
+    }
+
+    pub fn add_intent(
+        mut self,
+        resource: Resource,
+        resource_logics: impl FnOnce(
+                &mut dyn RngCore,
+                &Resource,
+                [Resource; NUM_RESOURCE],
+                [Resource; NUM_RESOURCE],
+            ) -> ResourceLogics
+            + 'a,
+    ) -> Self {
+        self.intents.push((resource, Box::new(resource_logics)));
+        self
+    }
+
+    pub fn with_padding_anchor(mut self, anchor: Anchor) -> Self {
+        self.padding_anchor = Some(anchor);
+        self
+    }
+
+    /// Fills the free dynamic resource logic slots of every resource with trivial proofs.
+    pub fn with_uniform_size(mut self) -> Self {
+        self.uniform_size = true;
+        self
+    }
+
+    pub fn with_hints(mut self, hints: Vec<u8>) -> Self {
+        self.hints = hints;
+        self
+    }
+
+    /// Encrypts the outputs added with `add_output` to the receiver in one
+    /// `BatchResourceCiphertext`, under a single ephemeral key. The receiver recovers them with
+    /// `ShieldedPartialTransaction::decrypt_outputs`.
+    pub fn with_output_encryption(mut self, rcv_pk: pallas::Point) -> Self {
+        self.output_encryption_pk = Some(rcv_pk);
+        self
+    }
+
+    pub fn build<R: RngCore>(
+        self,
+        mut rng: R,
+    ) -> Result<ShieldedPartialTransaction, TransactionError> {
+        let witnesses = self.witnesses(&mut rng)?;
+        witnesses.prove(rng)
+    }
+
+    /// Builds the partial transaction with the memory/parallelism trade-offs from the options,
+    /// see `ShieldedPartialTransaction::build_with_options`.
+    pub fn build_with_options<R: RngCore>(
+        self,
+        options: &ProverOptions,
+        mut rng: R,
+    ) -> Result<ShieldedPartialTransaction, TransactionError> {
+        let witnesses = self.witnesses(&mut rng)?;
+        witnesses.prove_with_options(options, rng)
+    }
+
+    // Pads the resources and creates the compliances and the resource logics, the proving is
+    // left to the caller
+    pub(crate) fn witnesses<R: RngCore>(
+        self,
+        mut rng: R,
+    ) -> Result<PartialTransactionWitnesses, TransactionError> {
+        if self
+            .intents
+            .iter()
+            .any(|(resource, _)| !resource.is_pure_intent())
+        {
+            return Err(TransactionError::NonPureIntentResource);
+        }
+        let num_outputs = self.outputs.len();
+        let mut outputs = self.outputs;
+        outputs.extend(self.intents);
+
+        let num_resources = self.inputs.len().max(outputs.len());
+        if num_resources > NUM_RESOURCE {
+            return Err(TransactionError::TooManyResources(num_resources));
+        }
+
+        let padding_anchor = self.padding_anchor.unwrap_or_else(|| {
+            match self
+                .inputs
+                .iter()
+                .find(|(resource, _, _)| !resource.is_ephemeral)
+            {
+                Some((resource, merkle_path, _)) => resource.calculate_root(merkle_path),
+                None => Anchor::from(pallas::Base::random(&mut rng)),
+            }
+        });
+
+        // Fill the free slots with padding resources, the padding resource logics are created
+        // once all the resources are known
+        let mut input_slots = self.inputs.into_iter().map(Some).collect::<Vec<_>>();
+        input_slots.resize_with(NUM_RESOURCE, || None);
+        let mut output_slots = outputs.into_iter().map(Some).collect::<Vec<_>>();
+        output_slots.resize_with(NUM_RESOURCE, || None);
+
+        // Create compliance pairs
+        let mut compliances = Vec::with_capacity(NUM_RESOURCE);
+        let mut input_resources = Vec::with_capacity(NUM_RESOURCE);
+        let mut output_resources = Vec::with_capacity(NUM_RESOURCE);
+        let mut input_constructors = Vec::with_capacity(NUM_RESOURCE);
+        let mut output_constructors = Vec::with_capacity(NUM_RESOURCE);
+        for (input_slot, output_slot) in input_slots.into_iter().zip(output_slots) {
+            let (input_resource, merkle_path, custom_anchor, input_constructor) = match input_slot {
+                Some((resource, merkle_path, constructor)) => {
+                    let custom_anchor = resource.is_ephemeral.then_some(padding_anchor);
+                    (resource, merkle_path, custom_anchor, Some(constructor))
+                }
+                None => (
+                    Resource::random_padding_resource(&mut rng),
+                    MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH),
+                    Some(padding_anchor),
+                    None,
+                ),
+            };
+            let (mut output_resource, output_constructor) = match output_slot {
+                Some((resource, constructor)) => (resource, Some(constructor)),
+                None => (Resource::random_padding_resource(&mut rng), None),
+            };
+            compliances.push(ComplianceInfo::new(
+                input_resource,
+                merkle_path,
+                custom_anchor,
+                &mut output_resource,
+                &mut rng,
+            ));
+            input_resources.push(input_resource);
+            output_resources.push(output_resource);
+            input_constructors.push(input_constructor);
+            output_constructors.push(output_constructor);
+        }
+        let input_resources: [Resource; NUM_RESOURCE] = input_resources.try_into().unwrap();
+        let output_resources: [Resource; NUM_RESOURCE] = output_resources.try_into().unwrap();
+
+        // Encrypt the outputs once their nonces are set by the compliances
+        let output_ciphertext = self.output_encryption_pk.map(|rcv_pk| {
+            let plaintexts: Vec<_> = output_resources[..num_outputs]
+                .iter()
+                .map(ResourcePlaintext::from_resource)
+                .collect();
+            BatchResourceCiphertext::encrypt_padded(
+                &plaintexts,
+                &rcv_pk,
+                &pallas::Scalar::random(&mut rng),
+                &pallas::Base::random(&mut rng),
+            )
+        });
+
+        // Once an output is tagged, every output is: the others get the tags of random keys, so
+        // the tags don't tell the tagged outputs apart
+        let output_detection_tags = if self.output_detection_keys.iter().any(Option::is_some) {
+            let mut detection_keys = self.output_detection_keys;
+            detection_keys.resize(NUM_RESOURCE, None);
+            detection_keys
+                .into_iter()
+                .map(|detection_key| {
+                    detection_key
+                        .unwrap_or_else(|| FmdSecretKey::random(&mut rng).public_key())
+                        .generate_tag(&mut rng)
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+
+        // Create resource logics
+        let uniform_size = self.uniform_size;
+        let pad = |resource_logics: ResourceLogics, owned_resource_id: OwnedResourceId| {
+            if uniform_size {
+                resource_logics.pad_dynamic_resource_logics(
+                    owned_resource_id,
+                    input_resources,
+                    output_resources,
+                )
+            } else {
+                resource_logics
+            }
+        };
+        let input_resource_logics = input_constructors
+            .into_iter()
+            .zip(input_resources.iter())
+            .map(|(constructor, resource)| {
+                let resource_logics = match constructor {
+                    Some(constructor) => {
+                        constructor(&mut rng, resource, input_resources, output_resources)
+                    }
+                    None => ResourceLogics::create_input_padding_resource_resource_logics(
+                        resource,
+                        input_resources,
+                        output_resources,
+                    ),
+                };
+                pad(resource_logics, resource.get_nf().unwrap().into())
+            })
+            .collect();
+        let output_resource_logics = output_constructors
+            .into_iter()
+            .zip(output_resources.iter())
+            .map(|(constructor, resource)| {
+                let resource_logics = match constructor {
+                    Some(constructor) => {
+                        constructor(&mut rng, resource, input_resources, output_resources)
+                    }
+                    None => ResourceLogics::create_output_padding_resource_resource_logics(
+                        resource,
+                        input_resources,
+                        output_resources,
+                    ),
+                };
+                pad(resource_logics, resource.commitment().into())
+            })
+            .collect();
+
+        Ok(PartialTransactionWitnesses {
+            compliances,
+            input_resource_logics,
+            output_resource_logics,
+            hints: self.hints,
+            output_ciphertext,
+            output_detection_tags,
+        })
+    }
+}
+
+// The witnesses of a partial transaction built by `ShieldedPartialTransactionBuilder`
+#[cfg(feature = "prover")]
+pub(crate) struct PartialTransactionWitnesses {
+    pub(crate) compliances: Vec<ComplianceInfo>,
+    pub(crate) input_resource_logics: Vec<ResourceLogics>,
+    pub(crate) output_resource_logics: Vec<ResourceLogics>,
+    pub(crate) hints: Vec<u8>,
+    pub(crate) output_ciphertext: Option<BatchResourceCiphertext>,
+    pub(crate) output_detection_tags: Vec<FuzzyDetectionTag>,
+}
+
+#[cfg(feature = "prover")]
+impl PartialTransactionWitnesses {
+    pub(crate) fn prove<R: RngCore>(
+        self,
+        rng: R,
+    ) -> Result<ShieldedPartialTransaction, TransactionError> {
+        let mut ptx = ShieldedPartialTransaction::build(
+            self.compliances,
+            self.input_resource_logics,
+            self.output_resource_logics,
+            self.hints,
+            rng,
+        )?;
+        ptx.output_ciphertext = self.output_ciphertext;
+        ptx.output_detection_tags = self.output_detection_tags;
+        Ok(ptx)
+    }
+
+    pub(crate) fn prove_with_options<R: RngCore>(
+        self,
+        options: &ProverOptions,
+        rng: R,
+    ) -> Result<ShieldedPartialTransaction, TransactionError> {
+        let mut ptx = ShieldedPartialTransaction::build_with_options(
+            self.compliances,
+            self.input_resource_logics,
+            self.output_resource_logics,
+            self.hints,
+            options,
+            rng,
+        )?;
+        ptx.output_ciphertext = self.output_ciphertext;
+        ptx.output_detection_tags = self.output_detection_tags;
+        Ok(ptx)
+    }
+}
+
 impl ShieldedPartialTransactionProxy {
     fn to_concrete(&self) -> Option<ShieldedPartialTransaction> {
         let compliances = self.compliances.clone().try_into().ok()?;
@@ -274,6 +1193,8 @@ impl ShieldedPartialTransactionProxy {
             outputs,
             binding_sig_r: self.binding_sig_r,
             hints: self.hints.clone(),
+            output_ciphertext: self.output_ciphertext.clone(),
+            output_detection_tags: self.output_detection_tags.clone(),
         })
     }
 }
@@ -281,8 +1202,10 @@ impl ShieldedPartialTransactionProxy {
 impl Executable for ShieldedPartialTransaction {
     fn execute(&self) -> Result<(), TransactionError> {
         self.verify_proof()?;
-        self.check_nullifiers()?;
-        self.check_resource_commitments()?;
+        trace_check("nullifier consistency", 0, || self.check_nullifiers())?;
+        trace_check("output commitment consistency", 0, || {
+            self.check_resource_commitments()
+        })?;
         Ok(())
     }
 
@@ -323,12 +1246,25 @@ impl BorshSerialize for ShieldedPartialTransaction {
             compliance.serialize(writer)?;
         }
 
-        for input in self.inputs.iter() {
-            input.serialize(writer)?;
+        // Write every distinct vk once, the proofs refer to them by index
+        let (vk_table, vk_indexes) = self.resource_logic_vk_table();
+        writer.write_u8(vk_table.len() as u8)?;
+        for verifying_info in vk_table {
+            verifying_info.serialize_vk(writer)?;
         }
-
-        for output in self.outputs.iter() {
-            output.serialize(writer)?;
+        let mut vk_indexes = vk_indexes.into_iter();
+        for verifying_info_set in self.inputs.iter().chain(self.outputs.iter()) {
+            writer.write_u8(vk_indexes.next().unwrap())?;
+            verifying_info_set
+                .app_resource_logic_verifying_info
+                .serialize_without_vk(writer)?;
+            let dynamic_verifying_infos =
+                &verifying_info_set.app_dynamic_resource_logic_verifying_info;
+            (dynamic_verifying_infos.len() as u32).serialize(writer)?;
+            for verifying_info in dynamic_verifying_infos.iter() {
+                writer.write_u8(vk_indexes.next().unwrap())?;
+                verifying_info.serialize_without_vk(writer)?;
+            }
         }
 
         // Write binding_sig_r
@@ -343,6 +1279,8 @@ impl BorshSerialize for ShieldedPartialTransaction {
         };
 
         self.hints.serialize(writer)?;
+        self.output_ciphertext.serialize(writer)?;
+        self.output_detection_tags.serialize(writer)?;
 
         Ok(())
     }
@@ -355,12 +1293,54 @@ impl BorshDeserialize for ShieldedPartialTransaction {
         let compliances: Vec<_> = (0..NUM_RESOURCE)
             .map(|_| ComplianceVerifyingInfo::deserialize_reader(reader))
             .collect::<Result<_, _>>()?;
+
+        // Read every distinct vk once, the proofs of the same logic share it
+        let vk_num = reader.read_u8()?;
+        let vk_table: Vec<_> = (0..vk_num)
+            .map(|_| ResourceLogicVerifyingInfo::deserialize_vk(reader))
+            .collect::<Result<_, _>>()?;
+        let mut used_vks = vec![false; vk_table.len()];
+        let mut read_verifying_info = |reader: &mut R| {
+            let index = reader.read_u8()? as usize;
+            let (circuit_size, vk) = vk_table.get(index).ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("resource logic vk index {index} is out of the vk table"),
+                )
+            })?;
+            used_vks[index] = true;
+            ResourceLogicVerifyingInfo::deserialize_with_vk(reader, *circuit_size, Arc::clone(vk))
+        };
+        let mut read_verifying_info_set = |reader: &mut R| {
+            let app_resource_logic_verifying_info = read_verifying_info(reader)?;
+            let dynamic_resource_logic_num = u32::deserialize_reader(reader)? as usize;
+            if dynamic_resource_logic_num > MAX_DYNAMIC_RESOURCE_LOGIC_NUM {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    TransactionError::TooManyDynamicResourceLogics(dynamic_resource_logic_num)
+                        .to_string(),
+                ));
+            }
+            let app_dynamic_resource_logic_verifying_info = (0..dynamic_resource_logic_num)
+                .map(|_| read_verifying_info(reader))
+                .collect::<Result<_, _>>()?;
+            Ok(ResourceLogicVerifyingInfoSet {
+                app_resource_logic_verifying_info,
+                app_dynamic_resource_logic_verifying_info,
+            })
+        };
         let inputs: Vec<_> = (0..NUM_RESOURCE)
-            .map(|_| ResourceLogicVerifyingInfoSet::deserialize_reader(reader))
+            .map(|_| read_verifying_info_set(reader))
             .collect::<Result<_, _>>()?;
         let outputs: Vec<_> = (0..NUM_RESOURCE)
-            .map(|_| ResourceLogicVerifyingInfoSet::deserialize_reader(reader))
+            .map(|_| read_verifying_info_set(reader))
             .collect::<Result<_, _>>()?;
+        if used_vks.contains(&false) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "a resource logic vk of the vk table is not used",
+            ));
+        }
         let binding_sig_r_type = reader.read_u8()?;
         let binding_sig_r = if binding_sig_r_type == 0 {
             None
@@ -370,12 +1350,31 @@ impl BorshDeserialize for ShieldedPartialTransaction {
         };
 
         let hints = Vec::<u8>::deserialize_reader(reader)?;
+        let output_ciphertext = Option::<BatchResourceCiphertext>::deserialize_reader(reader)?;
+        if output_ciphertext
+            .as_ref()
+            .is_some_and(|cipher| cipher.len() > NUM_RESOURCE)
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "the output ciphertext has more outputs than the partial transaction",
+            ));
+        }
+        let output_detection_tags = Vec::<FuzzyDetectionTag>::deserialize_reader(reader)?;
+        if !output_detection_tags.is_empty() && output_detection_tags.len() != NUM_RESOURCE {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "the outputs are not tagged one by one",
+            ));
+        }
         Ok(ShieldedPartialTransaction {
             compliances: compliances.try_into().unwrap(),
             inputs: inputs.try_into().unwrap(),
             outputs: outputs.try_into().unwrap(),
             binding_sig_r,
             hints,
+            output_ciphertext,
+            output_detection_tags,
         })
     }
 }
@@ -397,76 +1396,173 @@ impl<'a> Decoder<'a> for ShieldedPartialTransaction {
 }
 
 impl ComplianceVerifyingInfo {
+    #[cfg(feature = "prover")]
     pub fn create<R: RngCore>(compliance_info: &ComplianceInfo, mut rng: R) -> Result<Self, Error> {
-        let (compliance_instance, circuit) = compliance_info.build();
-        let params = SETUP_PARAMS_MAP
-            .get(&COMPLIANCE_CIRCUIT_PARAMS_SIZE)
-            .unwrap();
-        let compliance_proof = Proof::create(
-            &COMPLIANCE_PROVING_KEY,
-            params,
-            circuit,
-            &[&compliance_instance.to_instance()],
-            &mut rng,
-        )?;
-        Ok(Self {
-            compliance_proof,
-            compliance_instance,
+        measure_compliance(compliance_info, || {
+            let (compliance_instance, circuit) = compliance_info.build();
+            let params = DEFAULT_PARAMS_STORE
+                .get(COMPLIANCE_CIRCUIT_PARAMS_SIZE)
+                .unwrap();
+            let compliance_proof = Proof::create(
+                &COMPLIANCE_PROVING_KEY,
+                &params,
+                circuit,
+                &[&compliance_instance.to_instance()],
+                &mut rng,
+            )?;
+            Ok(Self {
+                compliance_proof,
+                compliance_instance,
+            })
         })
     }
 
+    // The precomputed COMPLIANCE_PROVING_KEY only matches the default params, so the keys of the
+    // given store come from the key cache.
+    #[cfg(feature = "prover")]
+    pub fn create_with_params_store<R: RngCore>(
+        compliance_info: &ComplianceInfo,
+        params_store: &ParamsStore,
+        mut rng: R,
+    ) -> Result<Self, TransactionError> {
+        measure_compliance(compliance_info, || {
+            let (compliance_instance, circuit) = compliance_info.build();
+            let params = params_store.get(COMPLIANCE_CIRCUIT_PARAMS_SIZE)?;
+            let pk = KEY_CACHE.proving_key(&params, &circuit, 0)?;
+            let compliance_proof = Proof::create(
+                &pk,
+                &params,
+                circuit,
+                &[&compliance_instance.to_instance()],
+                &mut rng,
+            )?;
+            Ok(Self {
+                compliance_proof,
+                compliance_instance,
+            })
+        })
+    }
+
+    pub fn get_compliance_proof(&self) -> &Proof {
+        &self.compliance_proof
+    }
+
     pub fn verify(&self) -> Result<(), Error> {
-        let params = SETUP_PARAMS_MAP
-            .get(&COMPLIANCE_CIRCUIT_PARAMS_SIZE)
+        let params = DEFAULT_PARAMS_STORE
+            .get(COMPLIANCE_CIRCUIT_PARAMS_SIZE)
             .unwrap();
         self.compliance_proof.verify(
             &COMPLIANCE_VERIFYING_KEY,
-            params,
+            &params,
             &[&self.compliance_instance.to_instance()],
         )
     }
+
+    /// Adds the proof to the batch instead of verifying it.
+    pub fn add_to_batch<'a>(&'a self, batch: &mut BatchVerifier<'a>) {
+        batch.add_proof(
+            COMPLIANCE_CIRCUIT_PARAMS_SIZE,
+            &COMPLIANCE_VERIFYING_KEY,
+            &self.compliance_proof,
+            &[&self.compliance_instance.to_instance()],
+        );
+    }
+
+    pub fn verify_with_params(&self, params: &Params) -> Result<(), Error> {
+        let vk = keygen_vk(params, &ComplianceCircuit::default())?;
+        self.compliance_proof
+            .verify(&vk, params, &[&self.compliance_instance.to_instance()])
+    }
 }
 
 impl ResourceLogicVerifyingInfoSet {
     pub fn new(
         app_resource_logic_verifying_info: ResourceLogicVerifyingInfo,
         app_dynamic_resource_logic_verifying_info: Vec<ResourceLogicVerifyingInfo>,
-    ) -> Self {
-        assert!(app_dynamic_resource_logic_verifying_info.len() <= MAX_DYNAMIC_RESOURCE_LOGIC_NUM);
-
-        Self {
+    ) -> Result<Self, TransactionError> {
+        let verifying_info_set = Self {
             app_resource_logic_verifying_info,
             app_dynamic_resource_logic_verifying_info,
-        }
+        };
+        verifying_info_set.check_dynamic_resource_logic_num()?;
+        Ok(verifying_info_set)
     }
 
     // TODO: remove it.
-    pub fn build(
+    #[cfg(feature = "prover")]
+    pub fn build<R: RngCore>(
         application_resource_logic: Box<ResourceLogic>,
         dynamic_resource_logics: Vec<Box<ResourceLogic>>,
-    ) -> Self {
-        assert!(dynamic_resource_logics.len() <= MAX_DYNAMIC_RESOURCE_LOGIC_NUM);
+        mut rng: R,
+    ) -> Result<Self, TransactionError> {
+        if dynamic_resource_logics.len() > MAX_DYNAMIC_RESOURCE_LOGIC_NUM {
+            return Err(TransactionError::TooManyDynamicResourceLogics(
+                dynamic_resource_logics.len(),
+            ));
+        }
 
-        let app_resource_logic_verifying_info = application_resource_logic.get_verifying_info();
+        let app_resource_logic_verifying_info =
+            application_resource_logic.get_verifying_info(&mut rng)?;
 
         let app_dynamic_resource_logic_verifying_info = dynamic_resource_logics
             .into_iter()
-            .map(|verifying_info| verifying_info.get_verifying_info())
-            .collect();
+            .map(|verifying_info| verifying_info.get_verifying_info(&mut rng))
+            .collect::<Result<_, _>>()?;
 
-        Self {
+        Self::new(
             app_resource_logic_verifying_info,
             app_dynamic_resource_logic_verifying_info,
-        }
+        )
     }
 
-    pub fn verify(&self) -> Result<(), Error> {
+    pub fn verify(&self) -> Result<(), TransactionError> {
+        self.verify_with_params_store(&DEFAULT_PARAMS_STORE)
+    }
+
+    pub fn verify_with_params(&self, params: &Params) -> Result<(), TransactionError> {
+        self.verify_proofs(|verify_info| verify_info.verify_with_logic_name(params))
+    }
+
+    /// Verifies every proof with the params of its circuit size, the resource logics with large
+    /// lookup tables are proved with larger params than the default ones.
+    pub fn verify_with_params_store(
+        &self,
+        params_store: &ParamsStore,
+    ) -> Result<(), TransactionError> {
+        self.verify_proofs(|verify_info| verify_info.verify_with_params_store(params_store))
+    }
+
+    fn verify_proofs(
+        &self,
+        verify: impl Fn(&ResourceLogicVerifyingInfo) -> Result<(), TransactionError>,
+    ) -> Result<(), TransactionError> {
+        self.check_bounds()?;
+
         // Verify the application resource logic proof
-        self.app_resource_logic_verifying_info.verify()?;
+        let app_info = &self.app_resource_logic_verifying_info;
+        trace_proof(
+            "application resource logic proof",
+            0,
+            || vk_digest(&ResourceLogicVerifyingKey::from_vk((*app_info.vk).clone())),
+            || verify(app_info),
+        )?;
 
         // Verify application dynamic resource logic proofs
-        for verify_info in self.app_dynamic_resource_logic_verifying_info.iter() {
-            verify_info.verify()?;
+        for (index, verify_info) in self
+            .app_dynamic_resource_logic_verifying_info
+            .iter()
+            .enumerate()
+        {
+            trace_proof(
+                "dynamic resource logic proof",
+                index,
+                || {
+                    vk_digest(&ResourceLogicVerifyingKey::from_vk(
+                        (*verify_info.vk).clone(),
+                    ))
+                },
+                || verify(verify_info),
+            )?;
         }
 
         // TODO function privacy: Verify resource logic verifier proofs
@@ -474,6 +1570,50 @@ impl ResourceLogicVerifyingInfoSet {
         Ok(())
     }
 
+    fn check_dynamic_resource_logic_num(&self) -> Result<(), TransactionError> {
+        let num = self.app_dynamic_resource_logic_verifying_info.len();
+        if num > MAX_DYNAMIC_RESOURCE_LOGIC_NUM {
+            return Err(TransactionError::TooManyDynamicResourceLogics(num));
+        }
+        Ok(())
+    }
+
+    // Bounds the verification work of the deserialized proofs, the too large circuits are
+    // rejected before verifying any proof.
+    fn check_bounds(&self) -> Result<(), TransactionError> {
+        self.check_dynamic_resource_logic_num()?;
+        self.app_resource_logic_verifying_info
+            .check_circuit_size()?;
+        for verify_info in self.app_dynamic_resource_logic_verifying_info.iter() {
+            verify_info.check_circuit_size()?;
+        }
+        Ok(())
+    }
+
+    /// Adds the resource logic proofs to the batch instead of verifying them, the bounds are
+    /// checked as in `verify_with_params`.
+    pub fn add_to_batch<'a>(
+        &'a self,
+        batch: &mut BatchVerifier<'a>,
+    ) -> Result<(), TransactionError> {
+        self.check_bounds()?;
+        self.app_resource_logic_verifying_info.add_to_batch(batch);
+        for verify_info in self.app_dynamic_resource_logic_verifying_info.iter() {
+            verify_info.add_to_batch(batch);
+        }
+        Ok(())
+    }
+
+    /// The verification weight of the resource logic proofs.
+    pub fn weight(&self) -> u64 {
+        self.app_resource_logic_verifying_info.weight()
+            + self
+                .app_dynamic_resource_logic_verifying_info
+                .iter()
+                .map(|verify_info| verify_info.weight())
+                .sum::<u64>()
+    }
+
     pub fn get_nullifiers(&self) -> Vec<[pallas::Base; NUM_RESOURCE]> {
         let mut nfs = vec![self.app_resource_logic_verifying_info.get_nullifiers()];
         self.app_dynamic_resource_logic_verifying_info
@@ -482,6 +1622,15 @@ impl ResourceLogicVerifyingInfoSet {
         nfs
     }
 
+    /// The public inputs of the application resource logic, then of the dynamic ones.
+    pub fn get_public_inputs(&self) -> Vec<&ResourceLogicPublicInputs> {
+        let mut public_inputs = vec![&self.app_resource_logic_verifying_info.public_inputs];
+        self.app_dynamic_resource_logic_verifying_info
+            .iter()
+            .for_each(|resource_logic_info| public_inputs.push(&resource_logic_info.public_inputs));
+        public_inputs
+    }
+
     pub fn get_resource_commitments(&self) -> Vec<[ResourceCommitment; NUM_RESOURCE]> {
         let mut cms = vec![self
             .app_resource_logic_verifying_info
@@ -499,12 +1648,15 @@ impl ResourceLogicVerifyingInfoSet {
 pub mod testing {
     use crate::{
         circuit::resource_logic_circuit::{ResourceLogic, ResourceLogicVerifyingInfoTrait},
-        circuit::resource_logic_examples::TrivialResourceLogicCircuit,
+        circuit::resource_logic_examples::{
+            TrivialResourceLogicCircuit, COMPRESSED_TRIVIAL_RESOURCE_LOGIC_VK,
+        },
         compliance::ComplianceInfo,
-        constant::TAIGA_COMMITMENT_TREE_DEPTH,
+        constant::{NUM_RESOURCE, TAIGA_COMMITMENT_TREE_DEPTH},
         merkle_tree::MerklePath,
         nullifier::Nullifier,
-        resource::{Resource, ResourceLogics},
+        prover_options::ProverOptions,
+        resource::{OwnedResourceId, Resource, ResourceLogics},
         shielded_ptx::ShieldedPartialTransaction,
         utils::poseidon_hash,
     };
@@ -513,11 +1665,29 @@ pub mod testing {
     use rand::rngs::OsRng;
 
     pub fn create_shielded_ptx() -> ShieldedPartialTransaction {
+        create_shielded_ptx_with_options(&ProverOptions::default())
+    }
+
+    pub fn create_shielded_ptx_with_options(options: &ProverOptions) -> ShieldedPartialTransaction {
+        create_shielded_ptx_with_witnesses(options).0
+    }
+
+    // Also returns the compliance infos and the resource logic circuit of the first input
+    // resource, to update the witnesses of the partial transaction.
+    pub fn create_shielded_ptx_with_witnesses(
+        options: &ProverOptions,
+    ) -> (
+        ShieldedPartialTransaction,
+        Vec<ComplianceInfo>,
+        TrivialResourceLogicCircuit,
+    ) {
         let mut rng = OsRng;
 
         // Create empty resource logic circuit without resource info
         let trivial_resource_logic_circuit = TrivialResourceLogicCircuit::default();
-        let trivial_resource_logic_vk = trivial_resource_logic_circuit.get_resource_logic_vk();
+        let trivial_resource_logic_vk = trivial_resource_logic_circuit
+            .get_resource_logic_vk()
+            .unwrap();
         let compressed_trivial_resource_logic_vk = trivial_resource_logic_vk.get_compressed();
 
         // Generate resources
@@ -536,7 +1706,7 @@ pub mod testing {
                 app_dynamic_resource_logic_vk[1],
             );
             let nonce = Nullifier::from(pallas::Base::random(&mut rng));
-            let quantity = 5000u64;
+            let quantity = 5000u128;
             let nk = pallas::Base::random(&mut rng);
             let rseed = pallas::Base::random(&mut rng);
             let is_ephemeral = false;
@@ -556,7 +1726,7 @@ pub mod testing {
             // TODO: add real application dynamic resource logics and encode them to value later.
             // If the dynamic resource logic is not used, set value pallas::Base::zero() by default.
             let value = pallas::Base::zero();
-            let quantity = 5000u64;
+            let quantity = 5000u128;
             let npk = pallas::Base::random(&mut rng);
             let rseed = pallas::Base::random(&mut rng);
             let is_ephemeral = false;
@@ -586,7 +1756,7 @@ pub mod testing {
             let label = pallas::Base::one();
             let value = pallas::Base::zero();
             let nonce = Nullifier::from(pallas::Base::random(&mut rng));
-            let quantity = 10u64;
+            let quantity = 10u128;
             let nk = pallas::Base::random(&mut rng);
             let rseed = pallas::Base::random(&mut rng);
             let is_ephemeral = false;
@@ -604,7 +1774,7 @@ pub mod testing {
         let mut output_resource_2 = {
             let label = pallas::Base::one();
             let value = pallas::Base::zero();
-            let quantity = 10u64;
+            let quantity = 10u128;
             let npk = pallas::Base::random(&mut rng);
             let rseed = pallas::Base::random(&mut rng);
             let is_ephemeral = false;
@@ -631,10 +1801,11 @@ pub mod testing {
 
         // Create resource logic circuit and fill the resource info
         let mut trivial_resource_logic_circuit = TrivialResourceLogicCircuit {
-            owned_resource_id: input_resource_1.get_nf().unwrap().inner(),
+            owned_resource_id: input_resource_1.get_nf().unwrap().into(),
             input_resources: [input_resource_1, input_resource_2],
             output_resources: [output_resource_1, output_resource_2],
         };
+        let input_resource_1_circuit = trivial_resource_logic_circuit.clone();
         let input_application_resource_logic_1 = Box::new(trivial_resource_logic_circuit.clone());
         let trivial_app_logic_1: Box<ResourceLogic> =
             Box::new(trivial_resource_logic_circuit.clone());
@@ -643,28 +1814,30 @@ pub mod testing {
         let input_resource_1_resource_logics = ResourceLogics::new(
             input_application_resource_logic_1,
             trivial_dynamic_resource_logics,
-        );
+        )
+        .unwrap();
 
         // The following resources use empty logic resource_logics and use value with pallas::Base::zero() by default.
         trivial_resource_logic_circuit.owned_resource_id =
-            input_resource_2.get_nf().unwrap().inner();
+            input_resource_2.get_nf().unwrap().into();
         let input_application_resource_logic_2 = Box::new(trivial_resource_logic_circuit.clone());
         let input_resource_2_resource_logics =
-            ResourceLogics::new(input_application_resource_logic_2, vec![]);
+            ResourceLogics::from_application(input_application_resource_logic_2);
 
-        trivial_resource_logic_circuit.owned_resource_id = output_resource_1.commitment().inner();
+        trivial_resource_logic_circuit.owned_resource_id = output_resource_1.commitment().into();
         let output_application_resource_logic_1 = Box::new(trivial_resource_logic_circuit.clone());
         let output_resource_1_resource_logics =
-            ResourceLogics::new(output_application_resource_logic_1, vec![]);
+            ResourceLogics::from_application(output_application_resource_logic_1);
 
-        trivial_resource_logic_circuit.owned_resource_id = output_resource_2.commitment().inner();
+        trivial_resource_logic_circuit.owned_resource_id = output_resource_2.commitment().into();
         let output_application_resource_logic_2 = Box::new(trivial_resource_logic_circuit);
         let output_resource_2_resource_logics =
-            ResourceLogics::new(output_application_resource_logic_2, vec![]);
+            ResourceLogics::from_application(output_application_resource_logic_2);
 
         // Create shielded partial tx
-        ShieldedPartialTransaction::build(
-            vec![compliance_1, compliance_2],
+        let compliances = vec![compliance_1, compliance_2];
+        let ptx = ShieldedPartialTransaction::build_with_options(
+            compliances.clone(),
             vec![
                 input_resource_1_resource_logics,
                 input_resource_2_resource_logics,
@@ -674,8 +1847,558 @@ pub mod testing {
                 output_resource_2_resource_logics,
             ],
             vec![],
+            options,
             &mut rng,
         )
-        .unwrap()
+        .unwrap();
+        (ptx, compliances, input_resource_1_circuit)
+    }
+
+    #[test]
+    fn test_shielded_ptx_prover_options() {
+        use crate::executable::Executable;
+
+        let params_file = concat!(env!("CARGO_MANIFEST_DIR"), "/params/params_15");
+        for options in [
+            ProverOptions::low_memory(),
+            ProverOptions::low_memory().with_mapped_params(params_file),
+            ProverOptions::parallel(4),
+        ] {
+            let ptx = create_shielded_ptx_with_options(&options);
+            ptx.execute().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_shielded_ptx_builder() {
+        use crate::{
+            error::TransactionError, executable::Executable, resource::tests::random_resource,
+            shielded_ptx::ShieldedPartialTransactionBuilder,
+        };
+
+        let mut rng = OsRng;
+        let mut input_resource = random_resource(&mut rng);
+        input_resource.kind.logic = *COMPRESSED_TRIVIAL_RESOURCE_LOGIC_VK;
+        let mut output_resource = random_resource(&mut rng);
+        output_resource.kind.logic = *COMPRESSED_TRIVIAL_RESOURCE_LOGIC_VK;
+        let trivial_resource_logics =
+            |owned_resource_id: OwnedResourceId,
+             input_resources: [Resource; NUM_RESOURCE],
+             output_resources: [Resource; NUM_RESOURCE]| {
+                ResourceLogics::from_application(Box::new(TrivialResourceLogicCircuit::new(
+                    owned_resource_id,
+                    input_resources,
+                    output_resources,
+                )))
+            };
+
+        // Only the real resources are given, the second compliance pair is padding
+        let ptx = ShieldedPartialTransactionBuilder::new()
+            .add_input(
+                input_resource,
+                MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH),
+                |_, resource, input_resources, output_resources| {
+                    trivial_resource_logics(
+                        resource.get_nf().unwrap().into(),
+                        input_resources,
+                        output_resources,
+                    )
+                },
+            )
+            .add_output(
+                output_resource,
+                |_, resource, input_resources, output_resources| {
+                    trivial_resource_logics(
+                        resource.commitment().into(),
+                        input_resources,
+                        output_resources,
+                    )
+                },
+            )
+            .build(&mut rng)
+            .unwrap();
+        ptx.execute().unwrap();
+        // The padding input resource shares the anchor of the real input resource
+        assert_eq!(
+            ptx.compliances[0].compliance_instance.anchor,
+            ptx.compliances[1].compliance_instance.anchor
+        );
+
+        let mut builder = ShieldedPartialTransactionBuilder::new();
+        for _ in 0..NUM_RESOURCE + 1 {
+            builder =
+                builder.add_output(random_resource(&mut rng), |_, resource, inputs, outputs| {
+                    trivial_resource_logics(resource.commitment().into(), inputs, outputs)
+                });
+        }
+        assert!(matches!(
+            builder.build(&mut rng),
+            Err(TransactionError::TooManyResources(num)) if num == NUM_RESOURCE + 1
+        ));
+    }
+
+    #[test]
+    fn test_shielded_ptx_builder_pure_intent() {
+        use crate::{
+            error::TransactionError, executable::Executable,
+            shielded_ptx::ShieldedPartialTransactionBuilder,
+        };
+        use rand::RngCore;
+
+        let mut rng = OsRng;
+        let create_intent = |rng: &mut OsRng| {
+            Resource::new_pure_intent_resource(
+                *COMPRESSED_TRIVIAL_RESOURCE_LOGIC_VK,
+                pallas::Base::random(&mut *rng),
+                pallas::Base::random(&mut *rng),
+                pallas::Base::random(&mut *rng),
+                Nullifier::random(&mut *rng),
+                pallas::Base::random(&mut *rng),
+            )
+        };
+        let trivial_resource_logics =
+            |_: &mut dyn RngCore,
+             resource: &Resource,
+             input_resources: [Resource; NUM_RESOURCE],
+             output_resources: [Resource; NUM_RESOURCE]| {
+                ResourceLogics::from_application(Box::new(TrivialResourceLogicCircuit::new(
+                    resource.commitment().into(),
+                    input_resources,
+                    output_resources,
+                )))
+            };
+
+        // The intent is paired with a padding input resource, no real resource is consumed
+        let intent = create_intent(&mut rng);
+        assert!(intent.is_pure_intent());
+        let ptx = ShieldedPartialTransactionBuilder::new()
+            .add_intent(intent, trivial_resource_logics)
+            .build(&mut rng)
+            .unwrap();
+        ptx.execute().unwrap();
+
+        // An intent backed by a quantity must be added as an output resource
+        let mut intent = create_intent(&mut rng);
+        intent.quantity = 1;
+        assert!(matches!(
+            ShieldedPartialTransactionBuilder::new()
+                .add_intent(intent, trivial_resource_logics)
+                .build(&mut rng),
+            Err(TransactionError::NonPureIntentResource)
+        ));
+    }
+
+    #[test]
+    fn test_shielded_ptx_builder_uniform_size() {
+        use crate::{
+            constant::MAX_DYNAMIC_RESOURCE_LOGIC_NUM, executable::Executable,
+            resource::tests::random_resource, shielded_ptx::ShieldedPartialTransactionBuilder,
+        };
+
+        let mut rng = OsRng;
+        let mut output_resource = random_resource(&mut rng);
+        output_resource.kind.logic = *COMPRESSED_TRIVIAL_RESOURCE_LOGIC_VK;
+
+        // A single real resource without dynamic resource logics
+        let ptx = ShieldedPartialTransactionBuilder::new()
+            .add_output(output_resource, |_, resource, inputs, outputs| {
+                ResourceLogics::from_application(Box::new(TrivialResourceLogicCircuit::new(
+                    resource.commitment().into(),
+                    inputs,
+                    outputs,
+                )))
+            })
+            .with_uniform_size()
+            .build(&mut rng)
+            .unwrap();
+        ptx.execute().unwrap();
+        for verifying_info_set in ptx.inputs.iter().chain(ptx.outputs.iter()) {
+            assert_eq!(
+                verifying_info_set
+                    .app_dynamic_resource_logic_verifying_info
+                    .len(),
+                MAX_DYNAMIC_RESOURCE_LOGIC_NUM
+            );
+        }
+
+        #[cfg(feature = "borsh")]
+        {
+            use crate::serialization::{
+                decode_padded_partial_transaction, encode_padded_partial_transaction,
+                encode_partial_transaction, padded_size,
+            };
+
+            let bytes = encode_padded_partial_transaction(&ptx);
+            assert_eq!(
+                bytes.len(),
+                padded_size(encode_partial_transaction(&ptx).len())
+            );
+            let de_ptx = decode_padded_partial_transaction(&bytes).unwrap();
+            assert_eq!(encode_padded_partial_transaction(&de_ptx), bytes);
+        }
+    }
+
+    #[test]
+    fn test_shielded_ptx_builder_output_encryption() {
+        use crate::{
+            constant::GENERATOR, executable::Executable, resource::tests::random_resource,
+            resource_detection::scan_batch_outputs,
+            shielded_ptx::ShieldedPartialTransactionBuilder,
+        };
+        use group::prime::PrimeCurveAffine;
+        use rand::RngCore;
+
+        let mut rng = OsRng;
+        let rcv_sk = pallas::Scalar::random(&mut rng);
+        let rcv_pk = GENERATOR.to_curve() * rcv_sk;
+        let trivial_resource_logics =
+            |_: &mut dyn RngCore,
+             resource: &Resource,
+             input_resources: [Resource; NUM_RESOURCE],
+             output_resources: [Resource; NUM_RESOURCE]| {
+                ResourceLogics::from_application(Box::new(TrivialResourceLogicCircuit::new(
+                    resource.commitment().into(),
+                    input_resources,
+                    output_resources,
+                )))
+            };
+        let random_output = |rng: &mut OsRng| {
+            let mut resource = random_resource(rng);
+            resource.kind.logic = *COMPRESSED_TRIVIAL_RESOURCE_LOGIC_VK;
+            resource
+        };
+
+        // Both outputs are encrypted to the receiver in one batch
+        let mut builder = ShieldedPartialTransactionBuilder::new();
+        for _ in 0..NUM_RESOURCE {
+            builder = builder.add_output(random_output(&mut rng), trivial_resource_logics);
+        }
+        let ptx = builder
+            .with_output_encryption(rcv_pk)
+            .build(&mut rng)
+            .unwrap();
+        ptx.execute().unwrap();
+        assert_eq!(ptx.get_output_ciphertext().unwrap().len(), NUM_RESOURCE);
+
+        // The receiver recovers the outputs with the nonces set by the compliances
+        let output_cms: Vec<_> = ptx
+            .decrypt_outputs(&rcv_sk)
+            .iter()
+            .map(Resource::commitment)
+            .collect();
+        assert_eq!(output_cms, ptx.get_output_cms());
+
+        // A ptx without the batch ciphertext is skipped by the scan
+        let other_ptx = ShieldedPartialTransactionBuilder::new()
+            .add_output(random_output(&mut rng), trivial_resource_logics)
+            .build(&mut rng)
+            .unwrap();
+        assert!(other_ptx.get_output_ciphertext().is_none());
+
+        #[cfg(feature = "borsh")]
+        let ptx = {
+            use crate::serialization::{decode_partial_transaction, encode_partial_transaction};
+
+            decode_partial_transaction(&encode_partial_transaction(&ptx)).unwrap()
+        };
+        let found = scan_batch_outputs([&other_ptx, &ptx], &rcv_sk);
+        assert_eq!(found.len(), NUM_RESOURCE);
+        assert!(found.iter().all(|(index, _)| *index == 1));
+        assert_eq!(
+            found
+                .iter()
+                .map(|(_, resource)| resource.commitment())
+                .collect::<Vec<_>>(),
+            ptx.get_output_cms()
+        );
+
+        // Another key finds nothing
+        assert!(scan_batch_outputs([&ptx], &pallas::Scalar::random(&mut rng)).is_empty());
+    }
+
+    #[test]
+    fn test_shielded_ptx_builder_output_detection() {
+        use crate::{
+            constant::FMD_GAMMA, fuzzy_detection::FmdSecretKey, resource::tests::random_resource,
+            shielded_ptx::ShieldedPartialTransactionBuilder,
+        };
+        use rand::RngCore;
+
+        let mut rng = OsRng;
+        let fmd_sk = FmdSecretKey::random(&mut rng);
+        let other_fmd_sk = FmdSecretKey::random(&mut rng);
+        let trivial_resource_logics =
+            |_: &mut dyn RngCore,
+             resource: &Resource,
+             input_resources: [Resource; NUM_RESOURCE],
+             output_resources: [Resource; NUM_RESOURCE]| {
+                ResourceLogics::from_application(Box::new(TrivialResourceLogicCircuit::new(
+                    resource.commitment().into(),
+                    input_resources,
+                    output_resources,
+                )))
+            };
+        let random_output = |rng: &mut OsRng| {
+            let mut resource = random_resource(rng);
+            resource.kind.logic = *COMPRESSED_TRIVIAL_RESOURCE_LOGIC_VK;
+            resource
+        };
+
+        // The second output of the first ptx is the receiver's, the other output is tagged for
+        // a random key
+        let ptx = ShieldedPartialTransactionBuilder::new()
+            .add_output(random_output(&mut rng), trivial_resource_logics)
+            .add_detectable_output(
+                random_output(&mut rng),
+                fmd_sk.public_key(),
+                trivial_resource_logics,
+            )
+            .build(&mut rng)
+            .unwrap();
+        assert_eq!(ptx.get_output_detection_tags().len(), NUM_RESOURCE);
+        let other_ptx = ShieldedPartialTransactionBuilder::new()
+            .add_detectable_output(
+                random_output(&mut rng),
+                other_fmd_sk.public_key(),
+                trivial_resource_logics,
+            )
+            .build(&mut rng)
+            .unwrap();
+        // A ptx without detectable outputs carries no tags
+        let untagged_ptx = ShieldedPartialTransactionBuilder::new()
+            .add_output(random_output(&mut rng), trivial_resource_logics)
+            .build(&mut rng)
+            .unwrap();
+        assert!(untagged_ptx.get_output_detection_tags().is_empty());
+
+        #[cfg(feature = "borsh")]
+        let ptx = {
+            use crate::serialization::{decode_partial_transaction, encode_partial_transaction};
+
+            let de_ptx = decode_partial_transaction(&encode_partial_transaction(&ptx)).unwrap();
+            assert_eq!(
+                de_ptx.get_output_detection_tags(),
+                ptx.get_output_detection_tags()
+            );
+            de_ptx
+        };
+
+        let ptxs = [&ptx, &other_ptx, &untagged_ptx];
+        let detection_key = fmd_sk.detection_key(FMD_GAMMA);
+        assert_eq!(detection_key.detect_outputs(ptxs), vec![(0, 1)]);
+        let detection_key = other_fmd_sk.detection_key(FMD_GAMMA);
+        assert_eq!(detection_key.detect_outputs(ptxs), vec![(1, 0)]);
+
+        // The precision 0 key matches every tagged output
+        assert_eq!(
+            fmd_sk.detection_key(0).detect_outputs(ptxs).len(),
+            2 * NUM_RESOURCE
+        );
+    }
+
+    #[cfg(feature = "borsh")]
+    #[test]
+    fn test_shielded_ptx_vk_deduplication() {
+        use crate::executable::Executable;
+        use borsh::BorshDeserialize;
+        use std::sync::Arc;
+
+        // The four resources of the ptx are of the trivial logic, the vk is written once
+        let ptx = create_shielded_ptx();
+        let (vk_table, vk_indexes) = ptx.resource_logic_vk_table();
+        assert_eq!(vk_table.len(), 1);
+        assert_eq!(vk_indexes, vec![0; 2 * NUM_RESOURCE]);
+
+        let bytes = borsh::to_vec(&ptx).unwrap();
+        let duplicated_vks_size = ptx
+            .inputs
+            .iter()
+            .chain(ptx.outputs.iter())
+            .map(|verifying_info_set| {
+                borsh::to_vec(verifying_info_set).unwrap().len()
+                    - borsh::to_vec(&verifying_info_set.app_dynamic_resource_logic_verifying_info)
+                        .unwrap()
+                        .len()
+            })
+            .sum::<usize>();
+        assert!(bytes.len() < duplicated_vks_size);
+
+        let de_ptx = ShieldedPartialTransaction::try_from_slice(&bytes).unwrap();
+        assert_eq!(borsh::to_vec(&de_ptx).unwrap(), bytes);
+        de_ptx.execute().unwrap();
+
+        // The decoded proofs share the vk read once, not a copy each
+        let shared_vk = &de_ptx.inputs[0].app_resource_logic_verifying_info.vk;
+        assert!(de_ptx
+            .inputs
+            .iter()
+            .chain(de_ptx.outputs.iter())
+            .all(|verifying_info_set| Arc::ptr_eq(
+                &verifying_info_set.app_resource_logic_verifying_info.vk,
+                shared_vk
+            )));
+
+        // The vk indexes must be in the vk table
+        let compliances_size = borsh::to_vec(&ptx.compliances).unwrap().len();
+        let mut out_of_table = bytes.clone();
+        out_of_table[compliances_size] = 0;
+        assert!(ShieldedPartialTransaction::try_from_slice(&out_of_table).is_err());
+    }
+
+    #[test]
+    fn test_shielded_ptx_stale_anchor() {
+        use crate::{error::TransactionError, executable::Executable, merkle_tree::Anchor};
+
+        let mut ptx = create_shielded_ptx();
+        // Claim a root the compliance proof was not created for
+        ptx.compliances[0].compliance_instance.anchor =
+            Anchor::from(pallas::Base::random(&mut OsRng));
+        assert!(matches!(ptx.execute(), Err(TransactionError::Proof(_))));
+    }
+
+    #[test]
+    fn test_shielded_ptx_too_many_dynamic_resource_logics() {
+        use crate::{
+            constant::MAX_DYNAMIC_RESOURCE_LOGIC_NUM, error::TransactionError,
+            executable::Executable, shielded_ptx::ResourceLogicVerifyingInfoSet,
+        };
+
+        let mut ptx = create_shielded_ptx();
+        // Attach more dynamic resource logic proofs than the cap
+        let app_info = ptx.inputs[0].app_resource_logic_verifying_info.clone();
+        ptx.inputs[0].app_dynamic_resource_logic_verifying_info =
+            vec![app_info; MAX_DYNAMIC_RESOURCE_LOGIC_NUM + 1];
+        assert!(matches!(
+            ptx.execute(),
+            Err(TransactionError::TooManyDynamicResourceLogics(num))
+                if num == MAX_DYNAMIC_RESOURCE_LOGIC_NUM + 1
+        ));
+
+        // The constructor rejects them as well
+        let app_info = ptx.inputs[0].app_resource_logic_verifying_info.clone();
+        assert!(matches!(
+            ResourceLogicVerifyingInfoSet::new(
+                app_info.clone(),
+                vec![app_info; MAX_DYNAMIC_RESOURCE_LOGIC_NUM + 1]
+            ),
+            Err(TransactionError::TooManyDynamicResourceLogics(_))
+        ));
+    }
+
+    #[test]
+    fn test_shielded_ptx_from_malformed_bytecode() {
+        use crate::{compliance::tests::random_compliance_info, error::TransactionError};
+
+        let mut rng = OsRng;
+        // The counts are checked before any proof is generated
+        let compliances = vec![random_compliance_info(&mut rng)];
+        assert!(matches!(
+            ShieldedPartialTransaction::from_bytecode(compliances, vec![], vec![], vec![], rng),
+            Err(TransactionError::TooManyResources(1))
+        ));
+        let compliances = vec![random_compliance_info(&mut rng); NUM_RESOURCE];
+        assert!(matches!(
+            ShieldedPartialTransaction::from_bytecode(compliances, vec![], vec![], vec![], rng),
+            Err(TransactionError::TooManyResources(0))
+        ));
+    }
+
+    #[test]
+    fn test_shielded_ptx_reprove() {
+        use crate::{
+            error::TransactionError, executable::Executable, merkle_tree::MerklePath,
+            shielded_ptx::ResourcePosition,
+        };
+
+        let (mut ptx, mut compliances, mut circuit) =
+            create_shielded_ptx_with_witnesses(&ProverOptions::default());
+        let untouched = ptx.compliances[1].compliance_proof.inner();
+
+        // The commitment tree grew, re-prove the compliance unit with the new merkle path
+        compliances[0]
+            .set_input_merkle_path(MerklePath::random(&mut OsRng, TAIGA_COMMITMENT_TREE_DEPTH));
+        ptx.reprove_compliance(0, &compliances[0], OsRng).unwrap();
+        assert_eq!(
+            ptx.compliances[0].compliance_instance.anchor,
+            compliances[0].calculate_root()
+        );
+        assert_eq!(ptx.compliances[1].compliance_proof.inner(), untouched);
+        ptx.execute().unwrap();
+
+        // The compliance unit of another resource can't replace it
+        assert!(matches!(
+            ptx.reprove_compliance(0, &compliances[1], OsRng),
+            Err(TransactionError::InvalidReproving(_))
+        ));
+        assert!(matches!(
+            ptx.reprove_compliance(2, &compliances[0], OsRng),
+            Err(TransactionError::InvalidReproving(_))
+        ));
+
+        // Re-prove a dynamic resource logic of the first input resource
+        ptx.reprove_resource_logic(ResourcePosition::Input(0), Some(1), &circuit, OsRng)
+            .unwrap();
+        ptx.execute().unwrap();
+        assert!(matches!(
+            ptx.reprove_resource_logic(ResourcePosition::Input(1), Some(0), &circuit, OsRng),
+            Err(TransactionError::InvalidReproving(_))
+        ));
+
+        // The resource logic must own the same resource
+        circuit.owned_resource_id = compliances[1].get_input_resource_nullifier().into();
+        assert!(matches!(
+            ptx.reprove_resource_logic(ResourcePosition::Input(0), None, &circuit, OsRng),
+            Err(TransactionError::InconsistentOwnedResourceID)
+        ));
+    }
+
+    #[test]
+    fn test_shielded_ptx_refresh_stale_anchors() {
+        use crate::{
+            error::TransactionError,
+            executable::Executable,
+            merkle_tree::{AnchorHistory, WitnessTracker},
+            resource::ResourceCommitment,
+        };
+
+        // Tracks every resource at the same path, enough for a single refresh
+        struct Tracker(Option<MerklePath>);
+        impl WitnessTracker for Tracker {
+            fn merkle_path(&self, _cm: &ResourceCommitment) -> Option<MerklePath> {
+                self.0.clone()
+            }
+        }
+
+        let (mut ptx, mut compliances, _) =
+            create_shielded_ptx_with_witnesses(&ProverOptions::default());
+        let mut anchor_history = AnchorHistory::new(2);
+        anchor_history.push(compliances[1].get_input_anchor());
+        assert_eq!(ptx.check_anchors(&anchor_history), vec![0]);
+
+        // The tree moved on, the new root of the first input resource is accepted
+        let new_path = MerklePath::random(&mut OsRng, TAIGA_COMMITMENT_TREE_DEPTH);
+        let mut refreshed = compliances[0].clone();
+        refreshed.set_input_merkle_path(new_path.clone());
+        anchor_history.push(refreshed.get_input_anchor());
+
+        assert!(matches!(
+            ptx.refresh_stale_anchors(&anchor_history, &Tracker(None), &mut compliances, OsRng),
+            Err(TransactionError::UntrackedResource)
+        ));
+        let reproved = ptx
+            .refresh_stale_anchors(
+                &anchor_history,
+                &Tracker(Some(new_path)),
+                &mut compliances,
+                OsRng,
+            )
+            .unwrap();
+        assert_eq!(reproved, vec![0]);
+        assert!(ptx.check_anchors(&anchor_history).is_empty());
+        ptx.execute().unwrap();
+
+        // The oldest root falls out of the window
+        anchor_history.push(refreshed.get_input_anchor());
+        assert_eq!(ptx.check_anchors(&anchor_history), vec![1]);
     }
 }
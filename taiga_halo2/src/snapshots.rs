@@ -0,0 +1,236 @@
+/// Snapshot tests of the example transactions.
+///
+/// The witnesses of an example partial transaction are built from a seeded rng, and the snapshot
+/// records everything its proofs commit to: the compliance instances, the resource logic public
+/// inputs and the bytecode encodings of the resource logics. A change in the serialization, the
+/// public input ordering or the padding policy shows up as an explicit snapshot diff. The proofs
+/// are randomized by the prover and are not recorded.
+///
+/// A missing snapshot fails the test like a changed one. Snapshots are only written with
+/// TAIGA_UPDATE_SNAPSHOTS=1: record them after an intended change and review the diff.
+use crate::{
+    circuit::{
+        resource_logic_circuit::ResourceLogicCircuit,
+        resource_logic_examples::{
+            signature_verification::COMPRESSED_TOKEN_AUTH_VK,
+            token::{Token, TokenAuthorization},
+            TrivialResourceLogicCircuit,
+        },
+    },
+    compliance::ComplianceInfo,
+    constant::TAIGA_COMMITMENT_TREE_DEPTH,
+    merkle_tree::{Anchor, MerklePath},
+    nullifier::NullifierKeyContainer,
+    resource::Resource,
+    utils::hex_encode,
+};
+use ff::{Field, PrimeField};
+use pasta_curves::pallas;
+use rand::{rngs::StdRng, SeedableRng};
+use std::path::PathBuf;
+
+const UPDATE_SNAPSHOTS_ENV: &str = "TAIGA_UPDATE_SNAPSHOTS";
+
+#[derive(Default)]
+struct Snapshot(Vec<String>);
+
+impl Snapshot {
+    // One line per field element, to keep the diffs readable
+    fn fields(&mut self, name: &str, fields: &[pallas::Base]) {
+        for (i, field) in fields.iter().enumerate() {
+            self.0
+                .push(format!("{name}[{i}]: {}", hex_encode(&field.to_repr())));
+        }
+    }
+
+    fn bytes(&mut self, name: &str, bytes: &[u8]) {
+        self.0.push(format!("{name}: {}", hex_encode(bytes)));
+    }
+
+    fn resource_logic<C: ResourceLogicCircuit>(
+        &mut self,
+        name: &str,
+        circuit: &C,
+        bytecode: &[u8],
+        rng: &mut StdRng,
+    ) {
+        self.fields(
+            &format!("{name} public inputs"),
+            circuit.get_public_inputs(rng).inner(),
+        );
+        self.bytes(&format!("{name} bytecode"), bytecode);
+    }
+}
+
+fn assert_snapshot(name: &str, snapshot: &Snapshot) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("snapshots")
+        .join(format!("{name}.snap"));
+    let actual = snapshot.0.join("\n") + "\n";
+    if std::env::var(UPDATE_SNAPSHOTS_ENV).as_deref() == Ok("1") {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    let expected = std::fs::read_to_string(&path).unwrap_or_else(|err| {
+        panic!(
+            "cannot read the snapshot {name} with {err}, run with {UPDATE_SNAPSHOTS_ENV}=1 to \
+             record it"
+        )
+    });
+    if let Some((line, (expected, actual))) = expected
+        .lines()
+        .zip(actual.lines())
+        .enumerate()
+        .find(|(_, (expected, actual))| expected != actual)
+    {
+        panic!(
+            "snapshot {name} differs at line {}:\n  expected: {expected}\n  actual:   {actual}\n\
+             run with {UPDATE_SNAPSHOTS_ENV}=1 to record the new snapshot",
+            line + 1
+        );
+    }
+    assert_eq!(
+        expected.lines().count(),
+        actual.lines().count(),
+        "snapshot {name} has a different number of lines, run with {UPDATE_SNAPSHOTS_ENV}=1 to \
+         record the new snapshot"
+    );
+}
+
+// The witnesses of the token swap partial transaction of the examples: 5 BTC for 10 ETH
+fn token_swap_ptx_snapshot(seed: u64) -> Snapshot {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut snapshot = Snapshot::default();
+
+    let input_token = Token::new("btc".to_string(), 5u64);
+    let input_auth_sk = pallas::Scalar::random(&mut rng);
+    let input_auth = TokenAuthorization::from_sk_vk(&input_auth_sk, &COMPRESSED_TOKEN_AUTH_VK);
+    let input_nk = NullifierKeyContainer::random_key(&mut rng);
+    let input_resource = input_token.create_random_input_token_resource(
+        &mut rng,
+        input_nk.get_nk().unwrap(),
+        &input_auth,
+    );
+
+    let output_token = Token::new("eth".to_string(), 10u64);
+    let output_auth_sk = pallas::Scalar::random(&mut rng);
+    let output_auth = TokenAuthorization::from_sk_vk(&output_auth_sk, &COMPRESSED_TOKEN_AUTH_VK);
+    let output_npk = NullifierKeyContainer::random_key(&mut rng).get_npk();
+    let mut output_resource =
+        output_token.create_random_output_token_resource(&mut rng, output_npk, &output_auth);
+
+    let padding_input_resource = Resource::random_padding_resource(&mut rng);
+    let mut padding_output_resource = Resource::random_padding_resource(&mut rng);
+
+    // Compliances
+    let merkle_path = MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH);
+    let compliance_1 = ComplianceInfo::new(
+        *input_resource.resource(),
+        merkle_path.clone(),
+        None,
+        &mut output_resource.resource,
+        &mut rng,
+    );
+    let anchor = Anchor::from(pallas::Base::random(&mut rng));
+    let compliance_2 = ComplianceInfo::new(
+        padding_input_resource,
+        merkle_path,
+        Some(anchor),
+        &mut padding_output_resource,
+        &mut rng,
+    );
+    for (i, compliance) in [compliance_1, compliance_2].iter().enumerate() {
+        snapshot.fields(
+            &format!("compliance {i} instance"),
+            &compliance.build().0.to_instance(),
+        );
+    }
+
+    let input_resources = [*input_resource.resource(), padding_input_resource];
+    let output_resources = [*output_resource.resource(), padding_output_resource];
+    for (i, resource) in input_resources.iter().enumerate() {
+        snapshot.bytes(
+            &format!("input resource {i}"),
+            &borsh::to_vec(resource).unwrap(),
+        );
+    }
+    for (i, resource) in output_resources.iter().enumerate() {
+        snapshot.bytes(
+            &format!("output resource {i}"),
+            &borsh::to_vec(resource).unwrap(),
+        );
+    }
+
+    // Resource logics
+    let (input_token_logic, input_auth_logic) = input_resource.input_token_circuits(
+        &mut rng,
+        input_auth,
+        input_auth_sk,
+        input_resources,
+        output_resources,
+    );
+    let (output_token_logic, output_receiver_logic) = output_resource.output_token_circuits(
+        &mut rng,
+        output_auth,
+        input_resources,
+        output_resources,
+    );
+    let padding_input_logic = TrivialResourceLogicCircuit::new(
+        padding_input_resource.get_nf().unwrap().into(),
+        input_resources,
+        output_resources,
+    );
+    let padding_output_logic = TrivialResourceLogicCircuit::new(
+        padding_output_resource.commitment().into(),
+        input_resources,
+        output_resources,
+    );
+
+    snapshot.resource_logic(
+        "input token logic",
+        &input_token_logic,
+        &borsh::to_vec(&input_token_logic.to_bytecode()).unwrap(),
+        &mut rng,
+    );
+    snapshot.resource_logic(
+        "input token auth logic",
+        &input_auth_logic,
+        &borsh::to_vec(&input_auth_logic.to_bytecode()).unwrap(),
+        &mut rng,
+    );
+    snapshot.resource_logic(
+        "output token logic",
+        &output_token_logic,
+        &borsh::to_vec(&output_token_logic.to_bytecode()).unwrap(),
+        &mut rng,
+    );
+    snapshot.resource_logic(
+        "output receiver logic",
+        &output_receiver_logic,
+        &borsh::to_vec(&output_receiver_logic.to_bytecode()).unwrap(),
+        &mut rng,
+    );
+    snapshot.resource_logic(
+        "padding input logic",
+        &padding_input_logic,
+        &borsh::to_vec(&padding_input_logic.to_bytecode()).unwrap(),
+        &mut rng,
+    );
+    snapshot.resource_logic(
+        "padding output logic",
+        &padding_output_logic,
+        &borsh::to_vec(&padding_output_logic.to_bytecode()).unwrap(),
+        &mut rng,
+    );
+
+    snapshot
+}
+
+#[test]
+fn test_token_swap_ptx_snapshot() {
+    // The snapshot only depends on the seed
+    assert_eq!(token_swap_ptx_snapshot(1).0, token_swap_ptx_snapshot(1).0);
+    assert_snapshot("token_swap_ptx", &token_swap_ptx_snapshot(1));
+}
@@ -0,0 +1,437 @@
+/// Settlement planning for solvers.
+///
+/// Given the open intents, the planner finds rings of intents that settle each other (a swap is a
+/// ring of two) in full with the or-relation intents or partially with the partial fulfillment
+/// intents, and returns the settlement plan: the fills of each ring and the solver ptxs to
+/// construct for them. The rings are chosen greedily by a pluggable objective until no ring of
+/// the remaining intents settles, so the plan is a maximal matching of the intents.
+use crate::circuit::resource_logic_examples::token::Token;
+
+/// The native description of an open intent.
+#[derive(Clone, Debug)]
+pub struct OpenIntent {
+    pub kind: IntentKind,
+    /// The fee paid to the solver when the intent is filled in full, partial fills pay pro rata.
+    pub fee: u128,
+}
+
+#[derive(Clone, Debug)]
+pub enum IntentKind {
+    /// Sells the whole `sell` token for either `token_1` or `token_2`, see
+    /// OrRelationIntentResourceLogicCircuit.
+    OrRelation {
+        sell: Token,
+        token_1: Token,
+        token_2: Token,
+    },
+    /// Sells up to `sell` at the price `buy`, see partial_fulfillment_intent::Swap.
+    PartialFulfillment { sell: Token, buy: Token },
+}
+
+impl OpenIntent {
+    pub fn new(kind: IntentKind) -> Self {
+        Self { kind, fee: 0 }
+    }
+
+    pub fn with_fee(mut self, fee: u128) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    pub fn sell(&self) -> &Token {
+        match &self.kind {
+            IntentKind::OrRelation { sell, .. } => sell,
+            IntentKind::PartialFulfillment { sell, .. } => sell,
+        }
+    }
+
+    // The ways the intent can take part in a ring
+    fn legs(&self) -> Vec<Want> {
+        match &self.kind {
+            IntentKind::OrRelation {
+                token_1, token_2, ..
+            } => vec![Want::Exact(token_1.clone()), Want::Exact(token_2.clone())],
+            IntentKind::PartialFulfillment { sell, buy } => {
+                // Swap::random enforces the buy quantity is a multiple of the sell quantity
+                if sell.quantity() == 0 || buy.quantity() % sell.quantity() != 0 {
+                    return vec![];
+                }
+                vec![Want::Ratio {
+                    buy: buy.clone(),
+                    ratio: buy.quantity() / sell.quantity(),
+                }]
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Want {
+    // The whole sell token for the token
+    Exact(Token),
+    // `ratio` units of the buy token per unit of the sell token
+    Ratio { buy: Token, ratio: u128 },
+}
+
+impl Want {
+    fn token(&self) -> &Token {
+        match self {
+            Want::Exact(token) => token,
+            Want::Ratio { buy, .. } => buy,
+        }
+    }
+}
+
+// The tokens are the same resource kind
+fn same_kind(lhs: &Token, rhs: &Token) -> bool {
+    lhs.name() == rhs.name()
+        && lhs.quantity_mode() == rhs.quantity_mode()
+        && lhs.auth_policy() == rhs.auth_policy()
+}
+
+fn with_quantity(token: &Token, quantity: u128) -> Token {
+    Token::new_with_quantity_mode(token.name().inner(), quantity, token.quantity_mode())
+        .with_auth_policy(*token.auth_policy())
+}
+
+/// The part an intent takes in a ring.
+#[derive(Clone, Debug)]
+pub struct Fill {
+    /// The index of the intent in the planned intents.
+    pub intent: usize,
+    /// The token the intent gives, to the previous fill of the ring.
+    pub given: Token,
+    /// The token the intent receives, from the next fill of the ring.
+    pub received: Token,
+    /// The rest of the sell token returned to the intent owner in a partial fill.
+    pub returned: Option<Token>,
+}
+
+/// Intents settling each other: each fill receives what the next fill gives.
+#[derive(Clone, Debug)]
+pub struct Ring {
+    pub fills: Vec<Fill>,
+}
+
+/// The ptxs the solver constructs to settle a fill.
+#[derive(Clone, Debug)]
+pub enum PlannedPtx {
+    /// Consumes the or-relation intent and sends the intent owner the `output` token.
+    ConsumeOrRelationIntent { intent: usize, output: Token },
+    /// Fills the partial fulfillment intent with `offer`, see Swap::fill.
+    FillPartialFulfillmentIntent { intent: usize, offer: Token },
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SettlementPlan {
+    pub rings: Vec<Ring>,
+}
+
+impl SettlementPlan {
+    /// The solver ptxs settling the rings, in the order of the rings and their fills.
+    pub fn ptxs(&self, intents: &[OpenIntent]) -> Vec<PlannedPtx> {
+        self.rings
+            .iter()
+            .flat_map(|ring| ring.fills.iter())
+            .map(|fill| match intents[fill.intent].kind {
+                IntentKind::OrRelation { .. } => PlannedPtx::ConsumeOrRelationIntent {
+                    intent: fill.intent,
+                    output: fill.received.clone(),
+                },
+                IntentKind::PartialFulfillment { .. } => PlannedPtx::FillPartialFulfillmentIntent {
+                    intent: fill.intent,
+                    offer: fill.received.clone(),
+                },
+            })
+            .collect()
+    }
+
+    /// The indexes of the intents the plan doesn't settle.
+    pub fn unmatched(&self, intents: &[OpenIntent]) -> Vec<usize> {
+        (0..intents.len())
+            .filter(|index| {
+                !self
+                    .rings
+                    .iter()
+                    .any(|ring| ring.fills.iter().any(|fill| fill.intent == *index))
+            })
+            .collect()
+    }
+}
+
+/// Scores a ring, the planner prefers the rings with the higher scores.
+pub trait Objective {
+    fn score(&self, intents: &[OpenIntent], ring: &Ring) -> u128;
+}
+
+impl<F: Fn(&[OpenIntent], &Ring) -> u128> Objective for F {
+    fn score(&self, intents: &[OpenIntent], ring: &Ring) -> u128 {
+        self(intents, ring)
+    }
+}
+
+/// The quantity settled by the ring.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Volume;
+
+impl Objective for Volume {
+    fn score(&self, _intents: &[OpenIntent], ring: &Ring) -> u128 {
+        ring.fills
+            .iter()
+            .fold(0u128, |sum, fill| sum.saturating_add(fill.given.quantity()))
+    }
+}
+
+/// The fees the intents of the ring pay to the solver.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Fees;
+
+impl Objective for Fees {
+    fn score(&self, intents: &[OpenIntent], ring: &Ring) -> u128 {
+        ring.fills.iter().fold(0u128, |sum, fill| {
+            let intent = &intents[fill.intent];
+            let fee = if fill.given.quantity() >= intent.sell().quantity() {
+                intent.fee
+            } else {
+                intent.fee.saturating_mul(fill.given.quantity()) / intent.sell().quantity()
+            };
+            sum.saturating_add(fee)
+        })
+    }
+}
+
+pub struct Planner<O: Objective> {
+    objective: O,
+    max_ring_len: usize,
+}
+
+impl<O: Objective> Planner<O> {
+    /// Plans rings of up to three intents.
+    pub fn new(objective: O) -> Self {
+        Self {
+            objective,
+            max_ring_len: 3,
+        }
+    }
+
+    /// The ring search is exponential in the ring length, keep it small.
+    pub fn with_max_ring_len(mut self, max_ring_len: usize) -> Self {
+        assert!(max_ring_len >= 2);
+        self.max_ring_len = max_ring_len;
+        self
+    }
+
+    pub fn plan(&self, intents: &[OpenIntent]) -> SettlementPlan {
+        let legs: Vec<Vec<Want>> = intents.iter().map(|intent| intent.legs()).collect();
+        let mut candidates = vec![];
+        for start in 0..intents.len() {
+            let mut path = vec![];
+            self.search(intents, &legs, start, &mut path, &mut candidates);
+        }
+
+        // Greedily take the best rings of the unused intents. The sort is stable, so ties keep the
+        // search order and the plan is deterministic.
+        let mut scored: Vec<(u128, Ring)> = candidates
+            .into_iter()
+            .map(|ring| (self.objective.score(intents, &ring), ring))
+            .collect();
+        scored.sort_by(|(lhs, _), (rhs, _)| rhs.cmp(lhs));
+        let mut used = vec![false; intents.len()];
+        let mut rings = vec![];
+        for (_, ring) in scored {
+            if ring.fills.iter().all(|fill| !used[fill.intent]) {
+                ring.fills.iter().for_each(|fill| used[fill.intent] = true);
+                rings.push(ring);
+            }
+        }
+        SettlementPlan { rings }
+    }
+
+    // Extends the path of (intent, leg) with intents after the start, each giving what the last
+    // one wants, and collects the paths closing a settling ring.
+    fn search(
+        &self,
+        intents: &[OpenIntent],
+        legs: &[Vec<Want>],
+        next: usize,
+        path: &mut Vec<(usize, usize)>,
+        candidates: &mut Vec<Ring>,
+    ) {
+        for (leg, want) in legs[next].iter().enumerate() {
+            path.push((next, leg));
+            let start = path[0].0;
+            if path.len() >= 2 && same_kind(want.token(), intents[start].sell()) {
+                if let Some(ring) = settle(intents, legs, path) {
+                    candidates.push(ring);
+                }
+            }
+            if path.len() < self.max_ring_len {
+                for candidate in (start + 1)..intents.len() {
+                    if !path.iter().any(|(intent, _)| *intent == candidate)
+                        && same_kind(intents[candidate].sell(), want.token())
+                    {
+                        self.search(intents, legs, candidate, path, candidates);
+                    }
+                }
+            }
+            path.pop();
+        }
+    }
+}
+
+// Solves the quantities of the ring: each fill receives what the next one gives. An exact leg
+// fixes the quantities of the whole ring, a ring of partial fills only settles with ratio 1.
+fn settle(intents: &[OpenIntent], legs: &[Vec<Want>], path: &[(usize, usize)]) -> Option<Ring> {
+    let len = path.len();
+    let want = |index: usize| &legs[path[index].0][path[index].1];
+    let sell = |index: usize| intents[path[index].0].sell();
+    let (first, initial) = match (0..len).find(|index| matches!(want(*index), Want::Exact(_))) {
+        Some(index) => (index, sell(index).quantity()),
+        None => (0, (0..len).map(|index| sell(index).quantity()).min()?),
+    };
+
+    let mut given = vec![0u128; len];
+    let mut received = vec![0u128; len];
+    given[first] = initial;
+    for step in 0..len {
+        let index = (first + step) % len;
+        received[index] = match want(index) {
+            Want::Exact(token) => {
+                if given[index] != sell(index).quantity() {
+                    return None;
+                }
+                token.quantity()
+            }
+            Want::Ratio { buy, ratio } => {
+                if given[index] == 0 || given[index] > sell(index).quantity() {
+                    return None;
+                }
+                let quantity = given[index].checked_mul(*ratio)?;
+                if quantity > buy.quantity() {
+                    return None;
+                }
+                quantity
+            }
+        };
+        let next = (index + 1) % len;
+        if next == first {
+            if received[index] != initial {
+                return None;
+            }
+        } else {
+            given[next] = received[index];
+        }
+    }
+
+    let fills = (0..len)
+        .map(|index| {
+            let sell = sell(index);
+            let returned = sell.quantity() - given[index];
+            Fill {
+                intent: path[index].0,
+                given: with_quantity(sell, given[index]),
+                received: with_quantity(want(index).token(), received[index]),
+                returned: (returned > 0).then(|| with_quantity(sell, returned)),
+            }
+        })
+        .collect();
+    Some(Ring { fills })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn or_relation(sell: (&str, u64), token_1: (&str, u64), token_2: (&str, u64)) -> OpenIntent {
+        OpenIntent::new(IntentKind::OrRelation {
+            sell: Token::new(sell.0.to_string(), sell.1),
+            token_1: Token::new(token_1.0.to_string(), token_1.1),
+            token_2: Token::new(token_2.0.to_string(), token_2.1),
+        })
+    }
+
+    fn partial(sell: (&str, u64), buy: (&str, u64)) -> OpenIntent {
+        OpenIntent::new(IntentKind::PartialFulfillment {
+            sell: Token::new(sell.0.to_string(), sell.1),
+            buy: Token::new(buy.0.to_string(), buy.1),
+        })
+    }
+
+    #[test]
+    fn plan_ring_trade() {
+        let intents = vec![
+            or_relation(("btc", 5), ("eth", 10), ("dolphin", 1)),
+            or_relation(("eth", 10), ("xan", 15), ("monkey", 2)),
+            or_relation(("xan", 15), ("btc", 5), ("penguin", 3)),
+            or_relation(("sol", 1), ("dot", 1), ("atom", 1)),
+        ];
+        let plan = Planner::new(Volume).plan(&intents);
+        assert_eq!(plan.rings.len(), 1);
+        assert_eq!(plan.rings[0].fills.len(), 3);
+        assert_eq!(plan.unmatched(&intents), vec![3]);
+        let ring = &plan.rings[0];
+        for (index, fill) in ring.fills.iter().enumerate() {
+            let next = &ring.fills[(index + 1) % ring.fills.len()];
+            assert_eq!(fill.received.name(), next.given.name());
+            assert_eq!(fill.received.quantity(), next.given.quantity());
+        }
+        assert_eq!(plan.ptxs(&intents).len(), 3);
+
+        // The ring is too long for the planner
+        let plan = Planner::new(Volume).with_max_ring_len(2).plan(&intents);
+        assert!(plan.rings.is_empty());
+    }
+
+    #[test]
+    fn plan_partial_fulfillment() {
+        // Alice sells up to 5 BTC for 2 ETH each, Bob gives 4 ETH for 2 BTC
+        let intents = vec![
+            partial(("btc", 5), ("eth", 10)),
+            or_relation(("eth", 4), ("btc", 2), ("dolphin", 1)),
+        ];
+        let plan = Planner::new(Volume).plan(&intents);
+        assert_eq!(plan.rings.len(), 1);
+        let alice = plan.rings[0]
+            .fills
+            .iter()
+            .find(|fill| fill.intent == 0)
+            .unwrap();
+        assert_eq!(alice.given.quantity(), 2);
+        assert_eq!(alice.received.quantity(), 4);
+        assert_eq!(alice.returned.as_ref().unwrap().quantity(), 3);
+        assert!(matches!(
+            plan.ptxs(&intents)
+                .into_iter()
+                .find(|ptx| matches!(ptx, PlannedPtx::FillPartialFulfillmentIntent { .. })),
+            Some(PlannedPtx::FillPartialFulfillmentIntent { intent: 0, offer }) if offer.quantity() == 4
+        ));
+
+        // Bob wants more BTC than Alice sells
+        let intents = vec![
+            partial(("btc", 5), ("eth", 10)),
+            or_relation(("eth", 12), ("btc", 6), ("dolphin", 1)),
+        ];
+        assert!(Planner::new(Volume).plan(&intents).rings.is_empty());
+    }
+
+    #[test]
+    fn plan_with_objectives() {
+        // Carol's intent settles with either Alice's or Bob's
+        let intents = vec![
+            or_relation(("eth", 10), ("btc", 5), ("dolphin", 1)).with_fee(1),
+            or_relation(("eth", 20), ("btc", 5), ("dolphin", 1)).with_fee(100),
+            or_relation(("btc", 5), ("eth", 10), ("eth", 20)),
+        ];
+        let plan = Planner::new(Volume).plan(&intents);
+        assert_eq!(plan.unmatched(&intents), vec![0]);
+
+        let plan = Planner::new(Fees).plan(&intents);
+        assert_eq!(plan.unmatched(&intents), vec![0]);
+
+        // Prefer the smaller trade with a custom objective
+        let plan =
+            Planner::new(|_: &[OpenIntent], ring: &Ring| u128::MAX - Volume.score(&[], ring))
+                .plan(&intents);
+        assert_eq!(plan.unmatched(&intents), vec![1]);
+    }
+}
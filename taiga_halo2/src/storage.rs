@@ -0,0 +1,315 @@
+/// The persistence of the ledger state: the resource commitments in the order of the commitment
+/// tree, the spent nullifiers and the anchors.
+///
+/// The state is written in batches, the writes of an ingested transaction, and a batch is
+/// applied atomically: a crash leaves the state before or after the transaction, never in
+/// between. `MemoryStorage` keeps the state in memory, for tests and light clients. With the
+/// `sled` feature, `SledStorage` keeps it in a sled database, one tree per kind of item. The
+/// values are stored in their canonical 32-byte encodings.
+use crate::{
+    merkle_tree::Anchor,
+    nullifier::{Nullifier, NullifierSet},
+    resource::ResourceCommitment,
+};
+use std::collections::HashSet;
+use std::io;
+
+/// The writes of an ingested transaction.
+#[derive(Debug, Clone, Default)]
+pub struct StorageBatch {
+    pub nullifiers: Vec<Nullifier>,
+    /// Appended to the commitments in order.
+    pub commitments: Vec<ResourceCommitment>,
+    /// The root of the commitment tree after the batch, it becomes the latest anchor.
+    pub anchor: Option<Anchor>,
+}
+
+pub trait Storage {
+    /// Applies all the writes of the batch, or none of them.
+    fn write_batch(&mut self, batch: &StorageBatch) -> io::Result<()>;
+
+    fn get_commitment(&self, position: u64) -> io::Result<Option<ResourceCommitment>>;
+
+    fn num_commitments(&self) -> io::Result<u64>;
+
+    fn contains_nullifier(&self, nf: &Nullifier) -> io::Result<bool>;
+
+    /// The spent nullifiers, in no particular order.
+    fn nullifiers(&self) -> io::Result<NullifierSet>;
+
+    fn contains_anchor(&self, anchor: &Anchor) -> io::Result<bool>;
+
+    fn latest_anchor(&self) -> io::Result<Option<Anchor>>;
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStorage {
+    commitments: Vec<ResourceCommitment>,
+    nullifiers: NullifierSet,
+    anchors: HashSet<Anchor>,
+    latest_anchor: Option<Anchor>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn write_batch(&mut self, batch: &StorageBatch) -> io::Result<()> {
+        self.nullifiers
+            .insert_batch(batch.nullifiers.iter().copied());
+        self.commitments.extend_from_slice(&batch.commitments);
+        if let Some(anchor) = batch.anchor {
+            self.anchors.insert(anchor);
+            self.latest_anchor = Some(anchor);
+        }
+        Ok(())
+    }
+
+    fn get_commitment(&self, position: u64) -> io::Result<Option<ResourceCommitment>> {
+        Ok(self.commitments.get(position as usize).copied())
+    }
+
+    fn num_commitments(&self) -> io::Result<u64> {
+        Ok(self.commitments.len() as u64)
+    }
+
+    fn contains_nullifier(&self, nf: &Nullifier) -> io::Result<bool> {
+        Ok(self.nullifiers.contains(nf))
+    }
+
+    fn nullifiers(&self) -> io::Result<NullifierSet> {
+        Ok(self.nullifiers.clone())
+    }
+
+    fn contains_anchor(&self, anchor: &Anchor) -> io::Result<bool> {
+        Ok(self.anchors.contains(anchor))
+    }
+
+    fn latest_anchor(&self) -> io::Result<Option<Anchor>> {
+        Ok(self.latest_anchor)
+    }
+}
+
+#[cfg(feature = "sled")]
+pub use sled_storage::SledStorage;
+
+#[cfg(feature = "sled")]
+mod sled_storage {
+    use super::*;
+    use sled::transaction::{
+        ConflictableTransactionError, TransactionError as SledTransactionError, Transactional,
+    };
+    use std::path::Path;
+
+    const COMMITMENTS_TREE: &str = "commitments";
+    const NULLIFIERS_TREE: &str = "nullifiers";
+    const ANCHORS_TREE: &str = "anchors";
+    const NUM_COMMITMENTS_KEY: &[u8] = b"num_commitments";
+    const LATEST_ANCHOR_KEY: &[u8] = b"latest_anchor";
+
+    /// The commitments are keyed by their big-endian position so the keys sort in the tree
+    /// order, the nullifiers and the anchors by their encoding. The number of commitments and
+    /// the latest anchor are kept in the default tree, and a batch is written in a transaction
+    /// over all the trees.
+    #[derive(Debug, Clone)]
+    pub struct SledStorage {
+        db: sled::Db,
+        commitments: sled::Tree,
+        nullifiers: sled::Tree,
+        anchors: sled::Tree,
+    }
+
+    impl SledStorage {
+        pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+            Self::from_db(sled::open(path)?)
+        }
+
+        pub fn from_db(db: sled::Db) -> io::Result<Self> {
+            Ok(Self {
+                commitments: db.open_tree(COMMITMENTS_TREE)?,
+                nullifiers: db.open_tree(NULLIFIERS_TREE)?,
+                anchors: db.open_tree(ANCHORS_TREE)?,
+                db,
+            })
+        }
+
+        /// Flushes the pending writes to the disk.
+        pub fn flush(&self) -> io::Result<()> {
+            self.db.flush()?;
+            Ok(())
+        }
+    }
+
+    fn to_32_bytes(value: &[u8]) -> io::Result<[u8; 32]> {
+        value
+            .try_into()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid stored value"))
+    }
+
+    fn invalid_data(what: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, format!("invalid stored {what}"))
+    }
+
+    fn decode_num_commitments(value: Option<sled::IVec>) -> io::Result<u64> {
+        value
+            .map(|value| {
+                let bytes = value
+                    .as_ref()
+                    .try_into()
+                    .map_err(|_| invalid_data("number of commitments"))?;
+                Ok(u64::from_be_bytes(bytes))
+            })
+            .unwrap_or(Ok(0))
+    }
+
+    impl Storage for SledStorage {
+        fn write_batch(&mut self, batch: &StorageBatch) -> io::Result<()> {
+            (
+                &*self.db,
+                &self.commitments,
+                &self.nullifiers,
+                &self.anchors,
+            )
+                .transaction(|(meta, commitments, nullifiers, anchors)| {
+                    let mut position = decode_num_commitments(meta.get(NUM_COMMITMENTS_KEY)?)
+                        .map_err(ConflictableTransactionError::Abort)?;
+                    for cm in batch.commitments.iter() {
+                        commitments.insert(&position.to_be_bytes()[..], &cm.to_bytes()[..])?;
+                        position += 1;
+                    }
+                    meta.insert(NUM_COMMITMENTS_KEY, &position.to_be_bytes()[..])?;
+                    for nf in batch.nullifiers.iter() {
+                        nullifiers.insert(&nf.to_bytes()[..], &b""[..])?;
+                    }
+                    if let Some(anchor) = batch.anchor {
+                        anchors.insert(&anchor.to_bytes()[..], &b""[..])?;
+                        meta.insert(LATEST_ANCHOR_KEY, &anchor.to_bytes()[..])?;
+                    }
+                    Ok(())
+                })
+                .map_err(|e| match e {
+                    SledTransactionError::Abort(e) => e,
+                    SledTransactionError::Storage(e) => e.into(),
+                })
+        }
+
+        fn get_commitment(&self, position: u64) -> io::Result<Option<ResourceCommitment>> {
+            self.commitments
+                .get(position.to_be_bytes())?
+                .map(|value| {
+                    Option::from(ResourceCommitment::from_bytes(to_32_bytes(&value)?))
+                        .ok_or_else(|| invalid_data("commitment"))
+                })
+                .transpose()
+        }
+
+        fn num_commitments(&self) -> io::Result<u64> {
+            decode_num_commitments(self.db.get(NUM_COMMITMENTS_KEY)?)
+        }
+
+        fn contains_nullifier(&self, nf: &Nullifier) -> io::Result<bool> {
+            Ok(self.nullifiers.contains_key(nf.to_bytes())?)
+        }
+
+        fn nullifiers(&self) -> io::Result<NullifierSet> {
+            let mut nullifiers = NullifierSet::new();
+            for key in self.nullifiers.iter().keys() {
+                let nf = Option::from(Nullifier::from_bytes(to_32_bytes(&key?)?))
+                    .ok_or_else(|| invalid_data("nullifier"))?;
+                nullifiers.insert_batch([nf]);
+            }
+            Ok(nullifiers)
+        }
+
+        fn contains_anchor(&self, anchor: &Anchor) -> io::Result<bool> {
+            Ok(self.anchors.contains_key(anchor.to_bytes())?)
+        }
+
+        fn latest_anchor(&self) -> io::Result<Option<Anchor>> {
+            self.db
+                .get(LATEST_ANCHOR_KEY)?
+                .map(|value| {
+                    Option::from(Anchor::from_bytes(to_32_bytes(&value)?))
+                        .ok_or_else(|| invalid_data("anchor"))
+                })
+                .transpose()
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use ff::Field;
+    use pasta_curves::pallas;
+    use rand::rngs::OsRng;
+
+    // The behaviour every storage backend has to implement
+    pub fn check_storage<S: Storage>(storage: &mut S) {
+        let mut rng = OsRng;
+        assert_eq!(storage.num_commitments().unwrap(), 0);
+        assert_eq!(storage.latest_anchor().unwrap(), None);
+
+        let cms: Vec<ResourceCommitment> = (0..3)
+            .map(|_| ResourceCommitment::from(pallas::Base::random(&mut rng)))
+            .collect();
+        let nf = Nullifier::random(&mut rng);
+        let anchors = [(); 2].map(|_| Anchor::from(pallas::Base::random(&mut rng)));
+        storage
+            .write_batch(&StorageBatch {
+                nullifiers: vec![nf],
+                commitments: cms[..2].to_vec(),
+                anchor: Some(anchors[0]),
+            })
+            .unwrap();
+        storage
+            .write_batch(&StorageBatch {
+                nullifiers: vec![],
+                commitments: cms[2..].to_vec(),
+                anchor: Some(anchors[1]),
+            })
+            .unwrap();
+
+        // The commitments of the batches are appended in order
+        assert_eq!(storage.num_commitments().unwrap(), 3);
+        for (i, cm) in cms.iter().enumerate() {
+            assert_eq!(storage.get_commitment(i as u64).unwrap(), Some(*cm));
+        }
+        assert_eq!(storage.get_commitment(3).unwrap(), None);
+
+        assert!(storage.contains_nullifier(&nf).unwrap());
+        assert!(!storage
+            .contains_nullifier(&Nullifier::random(&mut rng))
+            .unwrap());
+        let nullifiers = storage.nullifiers().unwrap();
+        assert_eq!(nullifiers.len(), 1);
+        assert!(nullifiers.contains(&nf));
+
+        assert!(anchors
+            .iter()
+            .all(|anchor| storage.contains_anchor(anchor).unwrap()));
+        assert!(!storage
+            .contains_anchor(&Anchor::from(pallas::Base::random(&mut rng)))
+            .unwrap());
+        assert_eq!(storage.latest_anchor().unwrap(), Some(anchors[1]));
+    }
+
+    #[test]
+    fn test_memory_storage() {
+        check_storage(&mut MemoryStorage::new());
+    }
+
+    #[cfg(feature = "sled")]
+    #[test]
+    fn test_sled_storage() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        check_storage(&mut SledStorage::from_db(db.clone()).unwrap());
+
+        // The number of commitments is read back from the database
+        let storage = SledStorage::from_db(db).unwrap();
+        assert_eq!(storage.num_commitments().unwrap(), 3);
+    }
+}
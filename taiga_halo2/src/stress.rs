@@ -0,0 +1,265 @@
+/// A stress harness building and verifying transactions with large bundles of partial
+/// transactions, to check the bundle-level data structures and cross-checks scale.
+///
+/// The bundle mixes the example logics: token transfers, bridge mints and token merges cascaded
+/// by intents. `run_stress` reports the proving and verification times, the size of the
+/// transaction and the peak memory of the process. The stress tests are ignored by default, run
+/// them with
+/// `cargo test --release --features example-bridge,example-intents stress -- --ignored --nocapture`.
+use crate::{
+    circuit::resource_logic_examples::{
+        bridge::{BridgeEvent, BridgeResourceLogicCircuit, EXTERNAL_EVENT_TREE_DEPTH},
+        signature_verification::COMPRESSED_TOKEN_AUTH_VK,
+        token::{Token, TokenAuthorization, TokenResource},
+        token_merge::{add_token_output, create_token_merge_ptxs},
+    },
+    constant::TAIGA_COMMITMENT_TREE_DEPTH,
+    error::TransactionError,
+    merkle_tree::{MerklePath, Node},
+    resource::ResourceLogics,
+    shielded_ptx::{ShieldedPartialTransaction, ShieldedPartialTransactionBuilder},
+    transaction::{ShieldedPartialTxBundle, Transaction, TransparentPartialTxBundle},
+};
+use ff::Field;
+use pasta_curves::pallas;
+use rand::{CryptoRng, RngCore};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// The ptxs of a round of the mixed bundle: a token transfer, a bridge mint and a token merge
+/// over two cascaded ptxs.
+const ROUND_PTXS: usize = 4;
+
+#[derive(Debug, Clone)]
+pub struct StressReport {
+    pub num_ptxs: usize,
+    /// The verification weight of the transaction, see `Transaction::weight`
+    pub weight: u64,
+    /// The length of the borsh encoding of the transaction
+    pub tx_size: usize,
+    pub proving_time: Duration,
+    pub verifying_time: Duration,
+    pub batched_verifying_time: Duration,
+    /// The time of `Transaction::check_cross_ptx`
+    pub cross_check_time: Duration,
+    /// The peak resident memory of the process in bytes, if the platform reports it
+    pub peak_memory: Option<u64>,
+}
+
+impl fmt::Display for StressReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} ptxs, weight {}, {} bytes",
+            self.num_ptxs, self.weight, self.tx_size
+        )?;
+        writeln!(f, "proving: {:?}", self.proving_time)?;
+        writeln!(f, "verifying: {:?}", self.verifying_time)?;
+        writeln!(f, "batched verifying: {:?}", self.batched_verifying_time)?;
+        writeln!(f, "cross-checks: {:?}", self.cross_check_time)?;
+        match self.peak_memory {
+            Some(peak_memory) => write!(f, "peak memory: {} MiB", peak_memory >> 20),
+            None => write!(f, "peak memory: unknown"),
+        }
+    }
+}
+
+/// The peak resident memory of the process in bytes, read from `/proc/self/status` on Linux.
+pub fn peak_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kilobytes: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// Creates a ptx transferring a token between random parties.
+pub fn create_token_transfer_ptx<R: RngCore>(
+    mut rng: R,
+) -> Result<ShieldedPartialTransaction, TransactionError> {
+    let sender_sk = pallas::Scalar::random(&mut rng);
+    let sender_auth = TokenAuthorization::from_sk_vk(&sender_sk, &COMPRESSED_TOKEN_AUTH_VK);
+    let sender_nk = pallas::Base::random(&mut rng);
+    let receiver_auth = TokenAuthorization::random(&mut rng);
+    let receiver_npk = pallas::Base::random(&mut rng);
+
+    let token = Token::new("BTC".to_string(), 5u64);
+    let input = token.create_random_input_token_resource(&mut rng, sender_nk, &sender_auth);
+    let output = token.create_random_output_token_resource(&mut rng, receiver_npk, &receiver_auth);
+    let merkle_path = MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH);
+    let builder = ShieldedPartialTransactionBuilder::new().add_input(
+        *input.resource(),
+        merkle_path,
+        move |rng, _, input_resources, output_resources| {
+            input.generate_input_token_resource_logics(
+                rng,
+                sender_auth,
+                sender_sk,
+                input_resources,
+                output_resources,
+            )
+        },
+    );
+    add_token_output(builder, output, receiver_auth).build(&mut rng)
+}
+
+/// Creates a ptx minting a bridge resource upon a random external event.
+pub fn create_bridge_mint_ptx<R: RngCore>(
+    mut rng: R,
+) -> Result<ShieldedPartialTransaction, TransactionError> {
+    let event = BridgeEvent {
+        asset: pallas::Base::random(&mut rng),
+        recipient_npk: pallas::Base::random(&mut rng),
+        quantity: 5u128,
+        event_nonce: pallas::Base::random(&mut rng),
+    };
+    let event_path = MerklePath::random(&mut rng, EXTERNAL_EVENT_TREE_DEPTH);
+    let external_root = event_path.root(Node::from(event.commitment())).inner();
+    let claim_resource = event.create_claim_resource();
+    let minted_resource = event.create_minted_resource(&mut rng);
+
+    ShieldedPartialTransactionBuilder::new()
+        .add_input(
+            claim_resource,
+            MerklePath::default(),
+            move |_, resource, input_resources, output_resources| {
+                let resource_logic = BridgeResourceLogicCircuit {
+                    owned_resource_id: resource.get_nf().unwrap().into(),
+                    input_resources,
+                    output_resources,
+                    external_root,
+                    event_path,
+                };
+                ResourceLogics::from_application(Box::new(resource_logic))
+            },
+        )
+        .add_output(
+            minted_resource,
+            |_, resource, input_resources, output_resources| {
+                let resource_logic = BridgeResourceLogicCircuit {
+                    owned_resource_id: resource.commitment().into(),
+                    input_resources,
+                    output_resources,
+                    ..Default::default()
+                };
+                ResourceLogics::from_application(Box::new(resource_logic))
+            },
+        )
+        .build(&mut rng)
+}
+
+/// Creates the ptxs merging three token resources of a random owner, cascaded by an intent.
+pub fn create_token_merge_ptx_pair<R: RngCore>(
+    mut rng: R,
+) -> Result<Vec<ShieldedPartialTransaction>, TransactionError> {
+    let sk = pallas::Scalar::random(&mut rng);
+    let auth = TokenAuthorization::from_sk_vk(&sk, &COMPRESSED_TOKEN_AUTH_VK);
+    let nk = pallas::Base::random(&mut rng);
+    let inputs: Vec<(TokenResource, MerklePath)> = [3u64, 2, 2]
+        .iter()
+        .map(|quantity| {
+            let input = Token::new("ETH".to_string(), *quantity)
+                .create_random_input_token_resource(&mut rng, nk, &auth);
+            let merkle_path = MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH);
+            (input, merkle_path)
+        })
+        .collect();
+    let receiver_auth = TokenAuthorization::random(&mut rng);
+    let receiver_npk = pallas::Base::random(&mut rng);
+    create_token_merge_ptxs(&mut rng, inputs, auth, sk, 6, receiver_npk, receiver_auth)
+}
+
+/// Creates a bundle of `num_ptxs` ptxs mixing the example logics.
+pub fn create_stress_bundle<R: RngCore>(
+    mut rng: R,
+    num_ptxs: usize,
+) -> Result<ShieldedPartialTxBundle, TransactionError> {
+    let mut ptxs = Vec::with_capacity(num_ptxs);
+    while num_ptxs - ptxs.len() >= ROUND_PTXS {
+        ptxs.push(create_token_transfer_ptx(&mut rng)?);
+        ptxs.push(create_bridge_mint_ptx(&mut rng)?);
+        ptxs.extend(create_token_merge_ptx_pair(&mut rng)?);
+    }
+    while ptxs.len() < num_ptxs {
+        ptxs.push(create_token_transfer_ptx(&mut rng)?);
+    }
+    Ok(ShieldedPartialTxBundle::new(ptxs))
+}
+
+/// Builds a transaction with a bundle of `num_ptxs` ptxs, verifies it one proof at a time and
+/// in a batch and reports the measurements.
+pub fn run_stress<R: RngCore + CryptoRng>(
+    mut rng: R,
+    num_ptxs: usize,
+) -> Result<StressReport, TransactionError> {
+    let start = Instant::now();
+    let bundle = create_stress_bundle(&mut rng, num_ptxs)?;
+    let tx = Transaction::build(&mut rng, bundle, TransparentPartialTxBundle::default())?;
+    let proving_time = start.elapsed();
+
+    let start = Instant::now();
+    let result = tx.execute()?;
+    let verifying_time = start.elapsed();
+
+    let start = Instant::now();
+    let batched_result = tx.execute_batched()?;
+    let batched_verifying_time = start.elapsed();
+    if result != batched_result {
+        return Err(TransactionError::InconsistentBatchedResult);
+    }
+
+    let start = Instant::now();
+    tx.check_cross_ptx()?;
+    let cross_check_time = start.elapsed();
+
+    Ok(StressReport {
+        num_ptxs,
+        weight: tx.weight(),
+        tx_size: borsh::to_vec(&tx)?.len(),
+        proving_time,
+        verifying_time,
+        batched_verifying_time,
+        cross_check_time,
+        peak_memory: peak_memory(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    #[test]
+    #[ignore]
+    fn test_stress_large_bundle() {
+        let report = run_stress(OsRng, 16).unwrap();
+        println!("{report}");
+        assert_eq!(report.num_ptxs, 16);
+    }
+
+    // The cross-checks are linear in the number of ptxs, a quadratic check would take about 64
+    // times longer on a bundle 8 times larger. The transparent ptxs carry no proofs, so large
+    // bundles are cheap to build.
+    #[test]
+    #[ignore]
+    fn test_cross_checks_scale() {
+        use crate::transaction::testing::create_transparent_ptx_bundle;
+
+        let time_cross_checks = |num_ptxs| {
+            let tx = Transaction::build(
+                OsRng,
+                ShieldedPartialTxBundle::default(),
+                create_transparent_ptx_bundle(num_ptxs),
+            )
+            .unwrap();
+            let start = Instant::now();
+            tx.check_cross_ptx().unwrap();
+            start.elapsed()
+        };
+        // Warm up
+        time_cross_checks(500);
+        let small = time_cross_checks(500);
+        let large = time_cross_checks(4000);
+        println!("cross-checks: 500 ptxs {small:?}, 4000 ptxs {large:?}");
+        assert!(large < small * 24);
+    }
+}
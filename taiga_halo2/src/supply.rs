@@ -0,0 +1,271 @@
+/// Per-kind supply accounting.
+///
+/// `SupplyTracker` aggregates the quantities created, consumed and burned per resource kind from
+/// the transactions it's fed, for explorers and other observers maintaining circulating-supply
+/// statistics. Transparent partial transactions reveal their resources and are counted
+/// resource by resource. Shielded partial transactions hide them; only the ones whose creator
+/// disclosed the per-kind delta and the delta blinding (see `disclose_delta_blinding`) can be
+/// counted, after the disclosure is checked against the delta commitments.
+use crate::{
+    burn::is_burned,
+    resource::{Resource, ResourceKind},
+    transaction::Transaction,
+    transparent_ptx::TransparentPartialTransaction,
+};
+use std::collections::{HashMap, HashSet};
+
+#[cfg(feature = "borsh")]
+use crate::{
+    delta_commitment::{net_delta, verify_disclosed_delta, DeltaCommitment},
+    error::TransactionError,
+};
+#[cfg(feature = "borsh")]
+use pasta_curves::pallas;
+
+/// The supply statistics of a resource kind.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KindSupply {
+    /// The total quantity of the created resources, not including the burned ones.
+    pub created: u128,
+    /// The total quantity of the consumed resources.
+    pub consumed: u128,
+    /// The total quantity of the resources sent to the burn address.
+    pub burned: u128,
+}
+
+impl KindSupply {
+    /// The quantity created and not consumed yet. It's negative when the tracker started after
+    /// resources of the kind had been created and those were consumed since.
+    pub fn circulating(&self) -> i128 {
+        i128::try_from(self.created)
+            .unwrap_or(i128::MAX)
+            .saturating_sub(i128::try_from(self.consumed).unwrap_or(i128::MAX))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SupplyTracker {
+    supplies: HashMap<ResourceKind, KindSupply>,
+    // The (txid, ptx index) of the recorded disclosures
+    disclosures: HashSet<([u8; 32], usize)>,
+}
+
+impl SupplyTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the transparent partial transactions of the transaction. The shielded ones are
+    /// skipped, their deltas have to be recorded with `record_disclosed_delta`.
+    pub fn record_transaction(&mut self, tx: &Transaction) {
+        for ptx in tx.get_transparent_ptx_bundle().partial_txs() {
+            self.record_transparent_ptx(ptx);
+        }
+    }
+
+    /// Records the resources of a transparent partial transaction. Ephemeral resources never
+    /// reach the commitment tree and are not counted.
+    pub fn record_transparent_ptx(&mut self, ptx: &TransparentPartialTransaction) {
+        for compliance in ptx.get_compliances() {
+            self.record_consumed(compliance.get_input_resource());
+            self.record_created(compliance.get_output_resource());
+        }
+    }
+
+    /// Records the disclosed per-kind delta (input quantity - output quantity) of the shielded
+    /// partial transaction `ptx_index` of the transaction, after checking it opens the delta
+    /// commitments of the ptx with the disclosed blinding. A positive delta is counted as consumed
+    /// and a negative one as created. Each kind is listed once, and a ptx is recorded once: the
+    /// disclosures are keyed by the txid, so the same disclosure fed twice is rejected.
+    ///
+    /// Unlike `record_transparent_ptx`, the disclosed delta covers the ephemeral resources as
+    /// well, and burns can't be told apart from other outputs.
+    ///
+    /// The deltas of the kinds the compliance proofs of the ptx disclose are checked against the
+    /// transparent kind points, so a kind can't be both transparent and shielded in the ptx.
+    #[cfg(feature = "borsh")]
+    pub fn record_disclosed_delta(
+        &mut self,
+        tx: &Transaction,
+        ptx_index: usize,
+        disclosed_r: &pallas::Scalar,
+        kind_deltas: &[(ResourceKind, i128)],
+    ) -> Result<(), TransactionError> {
+        use crate::executable::Executable;
+
+        let ptx = tx
+            .get_shielded_ptx_bundle()
+            .partial_txs()
+            .get(ptx_index)
+            .ok_or(TransactionError::UnknownPartialTransaction(ptx_index))?;
+        self.record_disclosure(
+            (tx.txid(), ptx_index),
+            &ptx.get_delta_commitments(),
+            &ptx.get_transparent_kinds(),
+            disclosed_r,
+            kind_deltas,
+        )
+    }
+
+    #[cfg(feature = "borsh")]
+    fn record_disclosure(
+        &mut self,
+        key: ([u8; 32], usize),
+        deltas: &[DeltaCommitment],
+        transparent_kinds: &[ResourceKind],
+        disclosed_r: &pallas::Scalar,
+        kind_deltas: &[(ResourceKind, i128)],
+    ) -> Result<(), TransactionError> {
+        if self.disclosures.contains(&key) {
+            return Err(TransactionError::DuplicateDisclosure(key.1));
+        }
+        let mut kinds = HashSet::new();
+        if !kind_deltas.iter().all(|(kind, _)| kinds.insert(*kind)) {
+            return Err(TransactionError::DuplicateDisclosedKind);
+        }
+        let expected = net_delta(kind_deltas.iter().map(|(kind, delta)| {
+            if transparent_kinds.contains(kind) {
+                (kind.derive_transparent_kind(), *delta)
+            } else {
+                (kind.derive_kind(), *delta)
+            }
+        }));
+        verify_disclosed_delta(deltas, disclosed_r, &expected)?;
+
+        self.disclosures.insert(key);
+        for (kind, delta) in kind_deltas {
+            let supply = self.supplies.entry(*kind).or_default();
+            if *delta < 0 {
+                supply.created = supply.created.saturating_add(delta.unsigned_abs());
+            } else {
+                supply.consumed = supply.consumed.saturating_add(delta.unsigned_abs());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn supply(&self, kind: &ResourceKind) -> KindSupply {
+        self.supplies.get(kind).copied().unwrap_or_default()
+    }
+
+    pub fn circulating_supply(&self, kind: &ResourceKind) -> i128 {
+        self.supply(kind).circulating()
+    }
+
+    /// All the kinds recorded so far with their supply statistics.
+    pub fn iter(&self) -> impl Iterator<Item = (&ResourceKind, &KindSupply)> {
+        self.supplies.iter()
+    }
+
+    fn record_consumed(&mut self, resource: &Resource) {
+        if resource.is_ephemeral {
+            return;
+        }
+        let supply = self.supplies.entry(resource.kind).or_default();
+        supply.consumed = supply.consumed.saturating_add(resource.quantity);
+    }
+
+    fn record_created(&mut self, resource: &Resource) {
+        if resource.is_ephemeral {
+            return;
+        }
+        let supply = self.supplies.entry(resource.kind).or_default();
+        if is_burned(resource) {
+            supply.burned = supply.burned.saturating_add(resource.quantity);
+        } else {
+            supply.created = supply.created.saturating_add(resource.quantity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SupplyTracker;
+
+    #[cfg(feature = "borsh")]
+    #[test]
+    fn test_transparent_ptx_supply() {
+        use crate::transparent_ptx::testing::create_transparent_ptx;
+
+        let ptx = create_transparent_ptx();
+        let mut tracker = SupplyTracker::new();
+        tracker.record_transparent_ptx(&ptx);
+
+        // The ptx is balanced, every kind is created as much as it's consumed
+        for compliance in ptx.get_compliances() {
+            let input = compliance.get_input_resource();
+            let supply = tracker.supply(&input.kind);
+            assert!(supply.consumed >= input.quantity);
+            assert_eq!(supply.created, supply.consumed);
+            assert_eq!(supply.burned, 0);
+            assert_eq!(tracker.circulating_supply(&input.kind), 0);
+        }
+    }
+
+    #[cfg(feature = "borsh")]
+    #[test]
+    fn test_disclosed_delta_supply() {
+        use crate::{
+            compliance::{disclose_delta_blinding, tests::random_compliance_info},
+            error::TransactionError,
+            resource::ResourceKind,
+        };
+        use halo2_proofs::arithmetic::Field;
+        use pasta_curves::pallas;
+        use rand::rngs::OsRng;
+
+        let mut rng = OsRng;
+        let mut compliances: Vec<_> = (0..2).map(|_| random_compliance_info(&mut rng)).collect();
+        let disclosed_r = pallas::Scalar::random(&mut rng);
+        disclose_delta_blinding(&mut compliances, disclosed_r);
+        let deltas: Vec<_> = compliances
+            .iter()
+            .map(|info| info.get_delta_commitment(&info.get_rcv()))
+            .collect();
+        let kind_deltas: Vec<(ResourceKind, i128)> = compliances
+            .iter()
+            .flat_map(|info| {
+                let input = info.get_input_resource();
+                let output = info.get_output_resource();
+                [
+                    (input.kind, input.quantity as i128),
+                    (output.kind, -(output.quantity as i128)),
+                ]
+            })
+            .collect();
+        let key = ([1u8; 32], 0);
+
+        let mut tracker = SupplyTracker::new();
+        // A wrong disclosure is rejected and not recorded
+        assert!(matches!(
+            tracker.record_disclosure(key, &deltas, &[], &disclosed_r, &kind_deltas[1..]),
+            Err(TransactionError::InvalidDisclosedDelta)
+        ));
+        assert_eq!(tracker.iter().count(), 0);
+
+        // A kind listed twice is rejected, even when the listed deltas add up
+        let (kind, delta) = kind_deltas[0];
+        let mut split_deltas = kind_deltas.clone();
+        split_deltas[0] = (kind, delta - 1);
+        split_deltas.push((kind, 1));
+        assert!(matches!(
+            tracker.record_disclosure(key, &deltas, &[], &disclosed_r, &split_deltas),
+            Err(TransactionError::DuplicateDisclosedKind)
+        ));
+        assert_eq!(tracker.iter().count(), 0);
+
+        tracker
+            .record_disclosure(key, &deltas, &[], &disclosed_r, &kind_deltas)
+            .unwrap();
+        let output = compliances[0].get_output_resource();
+        let supply = tracker.supply(&output.kind);
+        assert!(supply.created >= output.quantity);
+
+        // The same disclosure is recorded once
+        assert!(matches!(
+            tracker.record_disclosure(key, &deltas, &[], &disclosed_r, &kind_deltas),
+            Err(TransactionError::DuplicateDisclosure(0))
+        ));
+        assert_eq!(tracker.supply(&output.kind), supply);
+    }
+}
@@ -1,8 +1,5 @@
-#[cfg(feature = "borsh")]
-use crate::{
-    circuit::resource_logic_bytecode::ApplicationByteCode, compliance::ComplianceInfo,
-    transaction::TransactionResult,
-};
+#[cfg(all(feature = "borsh", feature = "prover"))]
+use crate::{circuit::resource_logic_bytecode::ApplicationByteCode, compliance::ComplianceInfo};
 use crate::{
     error::TransactionError,
     nullifier::Nullifier,
@@ -10,11 +7,13 @@ use crate::{
     shielded_ptx::ShieldedPartialTransaction,
     transaction::{ShieldedPartialTxBundle, Transaction, TransparentPartialTxBundle},
 };
+#[cfg(feature = "borsh")]
+use crate::{serialization, transaction::TransactionResult};
 use ff::Field;
 use pasta_curves::pallas;
-use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
 
-pub const RESOURCE_SIZE: usize = 202;
+pub const RESOURCE_SIZE: usize = 210;
 
 #[cfg(feature = "borsh")]
 use borsh::{BorshDeserialize, BorshSerialize};
@@ -29,15 +28,15 @@ use borsh::{BorshDeserialize, BorshSerialize};
 ///
 /// In practice, input resources are fetched and decrypted from blockchain storage.
 /// The create_input_resource API is only for test.
-pub fn create_input_resource(
+pub fn create_input_resource<R: RngCore>(
     logic: pallas::Base,
     label: pallas::Base,
     value: pallas::Base,
-    quantity: u64,
+    quantity: u128,
     nk: pallas::Base,
     is_ephemeral: bool,
+    mut rng: R,
 ) -> Resource {
-    let mut rng = OsRng;
     let nonce = Nullifier::random(&mut rng);
     let rseed = pallas::Base::random(&mut rng);
     Resource::new_input_resource(
@@ -52,23 +51,23 @@ pub fn create_input_resource(
     )
 }
 
-pub fn create_output_resource(
+pub fn create_output_resource<R: RngCore>(
     logic: pallas::Base,
     label: pallas::Base,
     value: pallas::Base,
-    quantity: u64,
+    quantity: u128,
     // The owner of output resource has the nullifier key and exposes the nullifier_key commitment to output creator.
     npk: pallas::Base,
     is_ephemeral: bool,
+    rng: R,
 ) -> Resource {
-    let mut rng = OsRng;
-    let rseed = pallas::Base::random(&mut rng);
+    let rseed = pallas::Base::random(rng);
     Resource::new_output_resource(logic, label, value, quantity, npk, is_ephemeral, rseed)
 }
 
 /// Resource borsh serialization
 ///
-/// Resource size: 202 bytes
+/// Resource size: 210 bytes
 ///
 /// Resource layout:
 /// |   Parameters          | type          |size(bytes)|
@@ -76,11 +75,11 @@ pub fn create_output_resource(
 /// |   logic               | pallas::Base  |   32      |
 /// |   label               | pallas::Base  |   32      |
 /// |   value               | pallas::Base  |   32      |
-/// |   quantity            | u64           |   8       |
+/// |   quantity            | u128          |   16      |
 /// |   nk_container type   | u8            |   1       |
 /// |   npk                 | pallas::Base  |   32      |
 /// |   nonce               | pallas::Base  |   32      |
-/// |   is_ephemeral        | u8            |   1       |
+/// |   flags               | u8            |   1       |
 /// |   rseed               | pallas::Base  |   32      |
 #[cfg(feature = "borsh")]
 pub fn resource_serialize(resource: &Resource) -> std::io::Result<Vec<u8>> {
@@ -101,73 +100,87 @@ pub fn resource_deserialize(bytes: Vec<u8>) -> std::io::Result<Resource> {
     BorshDeserialize::deserialize(&mut bytes.as_ref())
 }
 
-/// Shielded Partial Transaction borsh serialization
+/// Shielded Partial Transaction serialization, the versioned encoding of `serialization`
 ///
 /// Shielded Partial Transaction layout:
 /// | Parameters                        | type                  | size(bytes)   |
 /// |       -                           |       -               |   -           |
+/// | version                           | u8                    | 1             |
 /// | 2 compliance proofs               | ComplianceVerifyingInfo| 4676 * 2      |
-/// | input1 static resource_logic proof            | ResourceLogicVerifyingInfo       | 158216        |
+/// | resource_logic vk num             | u8                    | 1             |
+/// | resource_logic vks                | (ResourceLogicCircuitSize, compressed vk, VerifyingKey) | - * vk num |
+/// | input1 static resource_logic proof            | (u8 vk index, proof, public inputs) | -   |
 /// | input1 dynamic resource_logic num(by borsh)   | u32                   | 4             |
-/// | input1 dynamic resource_logic proof           | ResourceLogicVerifyingInfo       | 158216 * num  |
-/// | input2 static resource_logic proof            | ResourceLogicVerifyingInfo       | 158216        |
+/// | input1 dynamic resource_logic proof           | (u8 vk index, proof, public inputs) | - * num |
+/// | input2 static resource_logic proof            | (u8 vk index, proof, public inputs) | -   |
 /// | input2 dynamic resource_logic num(by borsh)   | u32                   | 4             |
-/// | input2 dynamic resource_logic proof           | ResourceLogicVerifyingInfo       | 158216 * num  |
-/// | output1 static resource_logic proof           | ResourceLogicVerifyingInfo       | 158216        |
+/// | input2 dynamic resource_logic proof           | (u8 vk index, proof, public inputs) | - * num |
+/// | output1 static resource_logic proof           | (u8 vk index, proof, public inputs) | -   |
 /// | output1 dynamic resource_logic num(by borsh)  | u32                   | 4             |
-/// | output1 dynamic resource_logic proofs         | ResourceLogicVerifyingInfo       | 158216 * num  |
-/// | output2 static resource_logic proof           | ResourceLogicVerifyingInfo       | 158216        |
+/// | output1 dynamic resource_logic proofs         | (u8 vk index, proof, public inputs) | - * num |
+/// | output2 static resource_logic proof           | (u8 vk index, proof, public inputs) | -   |
 /// | output2 dynamic resource_logic num(by borsh)  | u32                   | 4             |
-/// | output2 dynamic resource_logic proofs         | ResourceLogicVerifyingInfo       | 158216 * num  |
+/// | output2 dynamic resource_logic proofs         | (u8 vk index, proof, public inputs) | - * num |
 /// | binding_sig_r                     | Option<pallas::Scalar>| 1 or (1 + 32) |
 /// | hints                             | Vec<u8>               | -             |
+/// | output_ciphertext                 | Option<BatchResourceCiphertext> | 1 or (1 + -) |
+/// | output_detection_tags             | Vec<FuzzyDetectionTag> | 4 + 68 * (0 or 2) |
+///
+/// The vks are written once in the order of first use, the resource logics of the same logic
+/// share a vk, e.g. the token resources of a transfer. The compressed vk resolves the circuit
+/// type a vk is read with, see `register_circuit_type`.
 ///
 /// Resource: Ultimately, resource_logic proofs won't go to the ptx. It's verifier proofs instead.
 /// The verifier proof may have a much smaller size since the verifier verifying-key
 /// is a constant and can be cached.
 #[cfg(feature = "borsh")]
 pub fn partial_transaction_serialize(ptx: &ShieldedPartialTransaction) -> std::io::Result<Vec<u8>> {
-    borsh::to_vec(&ptx)
+    Ok(serialization::encode_partial_transaction(ptx))
 }
 
-/// Shielded Partial Transaction borsh deserialization
+/// Shielded Partial Transaction deserialization, the encodings of another version or not
+/// canonical are rejected
 #[cfg(feature = "borsh")]
 pub fn partial_transaction_deserialize(
     bytes: Vec<u8>,
 ) -> std::io::Result<ShieldedPartialTransaction> {
-    BorshDeserialize::deserialize(&mut bytes.as_ref())
+    serialization::decode_partial_transaction(&bytes)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
 }
 
-/// Transaction borsh serialization
+/// Transaction serialization, the versioned encoding of `serialization`
 ///
 /// Transaction layout:
 /// | Parameters                                                | type                          | size(bytes)|
 /// |                   -                                       |       -                       |   -   |
+/// | version                                                   | u8                            | 1     |
 /// | shielded_ptx_bundle(a list of shielded ptx)               | ShieldedPartialTxBundle       | -     |
 /// | TODO: transparent_ptx_bundle(a list of transparent ptx)   | TransparentPartialTxBundle    | -     |
 /// | signature                                                 | BindingSignature              | 32    |
 ///
 #[cfg(feature = "borsh")]
 pub fn transaction_serialize(tx: &Transaction) -> std::io::Result<Vec<u8>> {
-    borsh::to_vec(&tx)
+    Ok(serialization::encode_transaction(tx))
 }
 
-/// Transaction borsh deserialization
+/// Transaction deserialization, the encodings of another version or not canonical are
+/// rejected
 ///
 #[cfg(feature = "borsh")]
 pub fn transaction_deserialize(bytes: Vec<u8>) -> std::io::Result<Transaction> {
-    BorshDeserialize::deserialize(&mut bytes.as_ref())
+    serialization::decode_transaction(&bytes)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
 }
 
 /// Create a shielded partial transaction from resource_logic bytecode
-#[cfg(feature = "borsh")]
-pub fn create_shielded_partial_transaction(
+#[cfg(all(feature = "borsh", feature = "prover"))]
+pub fn create_shielded_partial_transaction<R: RngCore>(
     compliances: Vec<ComplianceInfo>,
     input_resource_app: Vec<ApplicationByteCode>,
     output_resource_app: Vec<ApplicationByteCode>,
     hints: Vec<u8>,
+    rng: R,
 ) -> Result<ShieldedPartialTransaction, TransactionError> {
-    let rng = OsRng;
     ShieldedPartialTransaction::from_bytecode(
         compliances,
         input_resource_app,
@@ -179,12 +192,12 @@ pub fn create_shielded_partial_transaction(
 
 /// Create a transaction from partial transactions
 ///
-pub fn create_transaction(
+pub fn create_transaction<R: RngCore + CryptoRng>(
     shielded_ptxs: Vec<ShieldedPartialTransaction>,
     // TODO: add transparent_ptxs
     // transparent_ptxs: Vec<TransparentPartialTransaction>,
+    rng: R,
 ) -> Result<Transaction, TransactionError> {
-    let rng = OsRng;
     let shielded_ptx_bundle = ShieldedPartialTxBundle::new(shielded_ptxs);
     // empty transparent_ptx_bundle
     let transparent_ptx_bundle = TransparentPartialTxBundle::default();
@@ -290,42 +303,42 @@ pub mod tests {
         // construct applications
         let input_resource_1_app = {
             let app_resource_logic = TrivialResourceLogicCircuit::new(
-                input_resource_1_nf.inner(),
+                input_resource_1_nf.into(),
                 [input_resource_1, input_resource_2],
                 [output_resource_1, output_resource_2],
             );
 
-            ApplicationByteCode::new(app_resource_logic.to_bytecode(), vec![])
+            ApplicationByteCode::from_application(app_resource_logic.to_bytecode())
         };
 
         let input_resource_2_app = {
             let app_resource_logic = TrivialResourceLogicCircuit::new(
-                input_resource_2_nf.inner(),
+                input_resource_2_nf.into(),
                 [input_resource_1, input_resource_2],
                 [output_resource_1, output_resource_2],
             );
 
-            ApplicationByteCode::new(app_resource_logic.to_bytecode(), vec![])
+            ApplicationByteCode::from_application(app_resource_logic.to_bytecode())
         };
 
         let output_resource_1_app = {
             let app_resource_logic = TrivialResourceLogicCircuit::new(
-                output_resource_1.commitment().inner(),
+                output_resource_1.commitment().into(),
                 [input_resource_1, input_resource_2],
                 [output_resource_1, output_resource_2],
             );
 
-            ApplicationByteCode::new(app_resource_logic.to_bytecode(), vec![])
+            ApplicationByteCode::from_application(app_resource_logic.to_bytecode())
         };
 
         let output_resource_2_app = {
             let app_resource_logic = TrivialResourceLogicCircuit::new(
-                output_resource_2.commitment().inner(),
+                output_resource_2.commitment().into(),
                 [input_resource_1, input_resource_2],
                 [output_resource_1, output_resource_2],
             );
 
-            ApplicationByteCode::new(app_resource_logic.to_bytecode(), vec![])
+            ApplicationByteCode::from_application(app_resource_logic.to_bytecode())
         };
 
         // construct ptx
@@ -334,10 +347,16 @@ pub mod tests {
             vec![input_resource_1_app, input_resource_2_app],
             vec![output_resource_1_app, output_resource_2_app],
             vec![],
+            &mut rng,
         )
         .unwrap();
 
         let ptx_bytes = partial_transaction_serialize(&ptx).unwrap();
+        assert_eq!(ptx_bytes, serialization::encode_partial_transaction(&ptx));
         verify_shielded_partial_transaction(ptx_bytes).unwrap();
+
+        // The unversioned borsh encoding is rejected
+        let raw_bytes = borsh::to_vec(&ptx).unwrap();
+        assert!(partial_transaction_deserialize(raw_bytes).is_err());
     }
 }
@@ -0,0 +1,202 @@
+/// Deterministic fixtures for the integration tests of downstream application crates.
+///
+/// Every fixture is derived from a seed, the same seed gives the same keys, resources and merkle
+/// paths across runs, platforms and rand versions: the rng is ChaCha20, whose stream is fixed,
+/// unlike the one of `StdRng`. The resources are owned by the trivial resource logic, so the
+/// prebuilt transactions are valid without any application logic. The proofs are randomized by
+/// the prover, only the witnesses of the shielded fixtures are deterministic.
+use crate::{
+    circuit::{
+        resource_logic_bytecode::ApplicationByteCode,
+        resource_logic_examples::{
+            TrivialResourceLogicCircuit, COMPRESSED_TRIVIAL_RESOURCE_LOGIC_VK,
+        },
+    },
+    compliance::ComplianceInfo,
+    constant::{NUM_RESOURCE, TAIGA_COMMITMENT_TREE_DEPTH},
+    merkle_tree::MerklePath,
+    nullifier::NullifierKeyContainer,
+    resource::{tests::random_resource, OwnedResourceId, Resource, ResourceLogics},
+    shielded_ptx::{ShieldedPartialTransaction, ShieldedPartialTransactionBuilder},
+    transaction::{ShieldedPartialTxBundle, Transaction, TransparentPartialTxBundle},
+    transparent_ptx::TransparentPartialTransaction,
+};
+use ff::Field;
+use group::{Curve, Group};
+use pasta_curves::pallas;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+/// The rng all the fixtures of a seed are derived from.
+pub fn seeded_rng(seed: u64) -> ChaCha20Rng {
+    ChaCha20Rng::seed_from_u64(seed)
+}
+
+/// A nullifier key, the npk is derived with `get_npk`.
+pub fn nullifier_key(seed: u64) -> NullifierKeyContainer {
+    NullifierKeyContainer::random_key(seeded_rng(seed))
+}
+
+/// An authorization key pair, as used by the signature verification resource logics.
+pub fn auth_key_pair(seed: u64) -> (pallas::Scalar, pallas::Affine) {
+    let sk = pallas::Scalar::random(seeded_rng(seed));
+    let pk = (pallas::Point::generator() * sk).to_affine();
+    (sk, pk)
+}
+
+/// A merkle path of the commitment tree depth.
+pub fn merkle_path(seed: u64) -> MerklePath {
+    MerklePath::random(&mut seeded_rng(seed), TAIGA_COMMITMENT_TREE_DEPTH)
+}
+
+/// A non-ephemeral resource of the trivial resource logic, with a random label and quantity.
+pub fn resource(seed: u64) -> Resource {
+    random_trivial_resource(&mut seeded_rng(seed))
+}
+
+/// Input and output resources of the same kinds and quantities, in the order of the
+/// compliance pairs.
+pub fn balanced_resources(seed: u64) -> ([Resource; NUM_RESOURCE], [Resource; NUM_RESOURCE]) {
+    balanced_trivial_resources(&mut seeded_rng(seed))
+}
+
+/// A transparent partial transaction consuming and creating balanced trivial resources.
+pub fn transparent_ptx(seed: u64) -> TransparentPartialTransaction {
+    let mut rng = seeded_rng(seed);
+    let (input_resources, mut output_resources) = balanced_trivial_resources(&mut rng);
+    let compliances = input_resources
+        .iter()
+        .zip(output_resources.iter_mut())
+        .map(|(input_resource, output_resource)| {
+            let merkle_path = MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH);
+            ComplianceInfo::new(
+                *input_resource,
+                merkle_path,
+                None,
+                output_resource,
+                &mut rng,
+            )
+        })
+        .collect();
+
+    let create_app = |owned_resource_id: OwnedResourceId| {
+        let resource_logic =
+            TrivialResourceLogicCircuit::new(owned_resource_id, input_resources, output_resources);
+        ApplicationByteCode::from_application(resource_logic.to_bytecode())
+    };
+    let input_resource_apps = input_resources
+        .iter()
+        .map(|resource| create_app(resource.get_nf().unwrap().into()))
+        .collect();
+    let output_resource_apps = output_resources
+        .iter()
+        .map(|resource| create_app(resource.commitment().into()))
+        .collect();
+    TransparentPartialTransaction::new(
+        compliances,
+        input_resource_apps,
+        output_resource_apps,
+        vec![],
+    )
+}
+
+/// A shielded partial transaction consuming and creating balanced trivial resources.
+pub fn shielded_ptx(seed: u64) -> ShieldedPartialTransaction {
+    let mut rng = seeded_rng(seed);
+    let (input_resources, output_resources) = balanced_trivial_resources(&mut rng);
+    let trivial_resource_logics =
+        |owned_resource_id: OwnedResourceId,
+         input_resources: [Resource; NUM_RESOURCE],
+         output_resources: [Resource; NUM_RESOURCE]| {
+            ResourceLogics::from_application(Box::new(TrivialResourceLogicCircuit::new(
+                owned_resource_id,
+                input_resources,
+                output_resources,
+            )))
+        };
+
+    let mut builder = ShieldedPartialTransactionBuilder::new();
+    for (input_resource, output_resource) in input_resources.into_iter().zip(output_resources) {
+        builder = builder
+            .add_input(
+                input_resource,
+                MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH),
+                move |_, resource, inputs, outputs| {
+                    trivial_resource_logics(resource.get_nf().unwrap().into(), inputs, outputs)
+                },
+            )
+            .add_output(output_resource, move |_, resource, inputs, outputs| {
+                trivial_resource_logics(resource.commitment().into(), inputs, outputs)
+            });
+    }
+    builder.build(&mut rng).unwrap()
+}
+
+/// A transaction with a single transparent partial transaction.
+pub fn transparent_transaction(seed: u64) -> Transaction {
+    Transaction::build(
+        seeded_rng(seed),
+        ShieldedPartialTxBundle::default(),
+        TransparentPartialTxBundle::new(vec![transparent_ptx(seed)]),
+    )
+    .unwrap()
+}
+
+/// A transaction with a single shielded partial transaction.
+pub fn shielded_transaction(seed: u64) -> Transaction {
+    Transaction::build(
+        seeded_rng(seed),
+        ShieldedPartialTxBundle::new(vec![shielded_ptx(seed)]),
+        TransparentPartialTxBundle::default(),
+    )
+    .unwrap()
+}
+
+fn random_trivial_resource<R: RngCore>(rng: R) -> Resource {
+    let mut resource = random_resource(rng);
+    resource.kind.logic = *COMPRESSED_TRIVIAL_RESOURCE_LOGIC_VK;
+    resource
+}
+
+fn balanced_trivial_resources<R: RngCore>(
+    mut rng: R,
+) -> ([Resource; NUM_RESOURCE], [Resource; NUM_RESOURCE]) {
+    let input_resources = [(); NUM_RESOURCE].map(|_| random_trivial_resource(&mut rng));
+    let output_resources = input_resources.map(|input_resource| {
+        let mut resource = random_trivial_resource(&mut rng);
+        resource.kind = input_resource.kind;
+        resource.quantity = input_resource.quantity;
+        resource
+    });
+    (input_resources, output_resources)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixtures_are_deterministic() {
+        assert_eq!(resource(1).commitment(), resource(1).commitment());
+        assert_ne!(resource(1).commitment(), resource(2).commitment());
+        assert_eq!(merkle_path(1), merkle_path(1));
+        assert_eq!(auth_key_pair(1), auth_key_pair(1));
+        assert_eq!(
+            transparent_transaction(1).execute().unwrap(),
+            transparent_transaction(1).execute().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_shielded_fixtures_execute() {
+        use crate::executable::Executable;
+
+        let ptx = shielded_ptx(1);
+        ptx.execute().unwrap();
+
+        // The proofs are randomized, the statement is the same for a seed
+        let result = shielded_transaction(1).execute().unwrap();
+        assert_eq!(result, shielded_transaction(1).execute().unwrap());
+        assert_eq!(result.nullifiers, ptx.get_nullifiers());
+    }
+}
@@ -1,16 +1,21 @@
 use crate::binding_signature::{BindingSignature, BindingSigningKey, BindingVerificationKey};
-use crate::constant::TRANSACTION_BINDING_HASH_PERSONALIZATION;
+use crate::constant::{
+    DEFAULT_PARAMS_STORE, TRANSACTION_BINDING_HASH_PERSONALIZATION, TRANSACTION_ID_PERSONALIZATION,
+};
 use crate::delta_commitment::DeltaCommitment;
 use crate::error::TransactionError;
 use crate::executable::Executable;
 use crate::merkle_tree::Anchor;
 use crate::nullifier::Nullifier;
+use crate::proof::BatchVerifier;
 use crate::resource::ResourceCommitment;
 use crate::shielded_ptx::ShieldedPartialTransaction;
 use crate::transparent_ptx::TransparentPartialTransaction;
+use crate::verification_trace::{enter_component, trace_check};
 use blake2b_simd::Params as Blake2bParams;
 use pasta_curves::{group::Group, pallas};
 use rand::{CryptoRng, RngCore};
+use std::collections::HashSet;
 
 #[cfg(feature = "nif")]
 use rustler::{atoms, types::atom, Decoder, Env, NifRecord, NifResult, NifStruct, Term};
@@ -18,6 +23,8 @@ use rustler::{atoms, types::atom, Decoder, Env, NifRecord, NifResult, NifStruct,
 #[cfg(feature = "serde")]
 use serde;
 
+#[cfg(feature = "borsh")]
+use crate::serialization::TRANSACTION_FORMAT_VERSION;
 #[cfg(feature = "borsh")]
 use borsh::{BorshDeserialize, BorshSerialize};
 
@@ -78,16 +85,105 @@ impl Transaction {
 
     #[allow(clippy::type_complexity)]
     pub fn execute(&self) -> Result<TransactionResult, TransactionError> {
+        trace_check("duplicate nullifiers", 0, || {
+            self.check_duplicate_nullifiers()
+        })?;
+        trace_check("duplicate output commitments", 0, || {
+            self.check_duplicate_output_cms()
+        })?;
+
         let mut result = self.shielded_ptx_bundle.execute()?;
         let mut transparent_result = self.transparent_ptx_bundle.execute()?;
         result.append(&mut transparent_result);
 
         // check balance
-        self.verify_binding_sig()?;
+        trace_check("binding signature", 0, || self.verify_binding_sig())?;
 
         Ok(result)
     }
 
+    /// Executes the transaction like `execute`, but checks all the proofs of the shielded
+    /// partial transactions together with a single multi-scalar multiplication.
+    pub fn execute_batched(&self) -> Result<TransactionResult, TransactionError> {
+        trace_check("duplicate nullifiers", 0, || {
+            self.check_duplicate_nullifiers()
+        })?;
+        trace_check("duplicate output commitments", 0, || {
+            self.check_duplicate_output_cms()
+        })?;
+
+        let mut result = self.shielded_ptx_bundle.execute_batched()?;
+        let mut transparent_result = self.transparent_ptx_bundle.execute()?;
+        result.append(&mut transparent_result);
+
+        // check balance
+        trace_check("binding signature", 0, || self.verify_binding_sig())?;
+
+        Ok(result)
+    }
+
+    /// Runs the checks across the partial transactions, without the proofs: the nullifiers and
+    /// the output commitments are unique and the binding signature is valid.
+    pub fn check_cross_ptx(&self) -> Result<(), TransactionError> {
+        self.check_duplicate_nullifiers()?;
+        self.check_duplicate_output_cms()?;
+        self.verify_binding_sig()
+    }
+
+    pub fn get_shielded_ptx_bundle(&self) -> &ShieldedPartialTxBundle {
+        &self.shielded_ptx_bundle
+    }
+
+    pub fn get_transparent_ptx_bundle(&self) -> &TransparentPartialTxBundle {
+        &self.transparent_ptx_bundle
+    }
+
+    /// The verification weight of the proofs, see `ShieldedPartialTransaction::weight`. The
+    /// transparent partial transactions carry no proofs.
+    pub fn weight(&self) -> u64 {
+        self.shielded_ptx_bundle
+            .0
+            .iter()
+            .map(|ptx| ptx.weight())
+            .sum()
+    }
+
+    // The nullifiers must be unique within the transaction, the nullifier set can only catch
+    // double spends across transactions.
+    fn check_duplicate_nullifiers(&self) -> Result<(), TransactionError> {
+        let mut nfs = HashSet::new();
+        for nf in self
+            .shielded_ptx_bundle
+            .get_nullifiers()
+            .iter()
+            .chain(self.transparent_ptx_bundle.get_nullifiers().iter())
+        {
+            if !nfs.insert(nf.to_bytes()) {
+                return Err(TransactionError::DuplicateNullifier);
+            }
+        }
+
+        Ok(())
+    }
+
+    // The output resource commitments must be unique within the transaction, as duplicated
+    // leaves break the commitment tree and wallet scanning.
+    fn check_duplicate_output_cms(&self) -> Result<(), TransactionError> {
+        let mut cms = HashSet::new();
+        for cm in self
+            .shielded_ptx_bundle
+            .get_output_cms()
+            .iter()
+            .chain(self.transparent_ptx_bundle.get_output_cms().iter())
+        {
+            if !cms.insert(cm.to_bytes()) {
+                return Err(TransactionError::DuplicateOutputResourceCommitment);
+            }
+        }
+
+        Ok(())
+    }
+
     fn verify_binding_sig(&self) -> Result<(), TransactionError> {
         let binding_vk = self.get_binding_vk();
         let sig_hash = Self::digest(&self.shielded_ptx_bundle, &self.transparent_ptx_bundle);
@@ -113,6 +209,31 @@ impl Transaction {
         BindingVerificationKey::from(vk)
     }
 
+    /// The stable id of the transaction, for mempools and indexers. It's a domain-separated
+    /// Blake2b hash of the compliances and the resource logic inputs of the partial
+    /// transactions, without the malleable parts: the proofs and the binding signature are
+    /// randomized and the hints are unchecked, so the same transaction proved or signed again,
+    /// or relayed with other hints, keeps its id.
+    #[cfg(feature = "borsh")]
+    pub fn txid(&self) -> [u8; 32] {
+        let mut h = Blake2bParams::new()
+            .hash_length(32)
+            .personal(TRANSACTION_ID_PERSONALIZATION)
+            .to_state();
+        h.update(&[TRANSACTION_FORMAT_VERSION]);
+        h.update(&(self.shielded_ptx_bundle.0.len() as u32).to_le_bytes());
+        self.shielded_ptx_bundle
+            .0
+            .iter()
+            .for_each(|ptx| ptx.update_txid_hash(&mut h));
+        h.update(&(self.transparent_ptx_bundle.0.len() as u32).to_le_bytes());
+        self.transparent_ptx_bundle
+            .0
+            .iter()
+            .for_each(|ptx| ptx.update_txid_hash(&mut h));
+        h.finalize().as_bytes().try_into().unwrap()
+    }
+
     fn digest(
         shielded_bundle: &ShieldedPartialTxBundle,
         transparent_bundle: &TransparentPartialTxBundle,
@@ -242,9 +363,14 @@ impl ShieldedPartialTxBundle {
         self.0.push(ptx);
     }
 
+    pub fn partial_txs(&self) -> &[ShieldedPartialTransaction] {
+        &self.0
+    }
+
     #[allow(clippy::type_complexity)]
     pub fn execute(&self) -> Result<TransactionResult, TransactionError> {
-        for partial_tx in self.0.iter() {
+        for (index, partial_tx) in self.0.iter().enumerate() {
+            let _span = enter_component("shielded ptx", index);
             partial_tx.execute()?;
         }
 
@@ -256,6 +382,28 @@ impl ShieldedPartialTxBundle {
         })
     }
 
+    /// Executes the partial transactions with their proofs checked in a batch. If the batch is
+    /// invalid, the proofs are verified one by one to report the invalid one.
+    pub fn execute_batched(&self) -> Result<TransactionResult, TransactionError> {
+        let mut batch = BatchVerifier::new();
+        for (index, partial_tx) in self.0.iter().enumerate() {
+            let _span = enter_component("shielded ptx", index);
+            partial_tx.execute_batched(&mut batch)?;
+        }
+        if !trace_check("batched proofs", 0, || {
+            batch.finalize(&DEFAULT_PARAMS_STORE)
+        })? {
+            self.execute()?;
+            return Err(TransactionError::InvalidBatchProof);
+        }
+
+        Ok(TransactionResult {
+            nullifiers: self.get_nullifiers(),
+            output_cms: self.get_output_cms(),
+            anchors: self.get_anchors(),
+        })
+    }
+
     pub fn get_delta_commitments(&self) -> Vec<DeltaCommitment> {
         self.0
             .iter()
@@ -289,8 +437,13 @@ impl TransparentPartialTxBundle {
         self.0.push(ptx);
     }
 
+    pub fn partial_txs(&self) -> &[TransparentPartialTransaction] {
+        &self.0
+    }
+
     pub fn execute(&self) -> Result<TransactionResult, TransactionError> {
-        for partial_tx in self.0.iter() {
+        for (index, partial_tx) in self.0.iter().enumerate() {
+            let _span = enter_component("transparent ptx", index);
             partial_tx.execute()?;
         }
 
@@ -347,6 +500,79 @@ pub mod testing {
         TransparentPartialTxBundle::new(bundle)
     }
 
+    #[cfg(feature = "borsh")]
+    #[test]
+    fn test_txid() {
+        use super::*;
+        use rand::rngs::OsRng;
+
+        // Signing the same bundles again gives another binding signature, but the same id
+        let transparent_ptx_bundle = create_transparent_ptx_bundle(1);
+        let tx = Transaction::build(
+            OsRng,
+            ShieldedPartialTxBundle::default(),
+            transparent_ptx_bundle.clone(),
+        )
+        .unwrap();
+        let resigned_tx = Transaction::build(
+            OsRng,
+            ShieldedPartialTxBundle::default(),
+            transparent_ptx_bundle,
+        )
+        .unwrap();
+        assert_eq!(tx.txid(), resigned_tx.txid());
+
+        let other_tx = Transaction::build(
+            OsRng,
+            ShieldedPartialTxBundle::default(),
+            create_transparent_ptx_bundle(1),
+        )
+        .unwrap();
+        assert_ne!(tx.txid(), other_tx.txid());
+    }
+
+    #[cfg(feature = "borsh")]
+    #[test]
+    fn test_txid_ignores_the_malleable_parts() {
+        use super::*;
+        use rand::rngs::OsRng;
+
+        let shielded_ptx_bundle = create_shielded_ptx_bundle(1);
+        let transparent_ptx_bundle = create_transparent_ptx_bundle(1);
+        let tx = Transaction::build(
+            OsRng,
+            shielded_ptx_bundle.clone(),
+            transparent_ptx_bundle.clone(),
+        )
+        .unwrap();
+
+        // Re-signing the shielded part and changing the hints of the transparent part keep the id
+        let rehinted_bundle = TransparentPartialTxBundle::new(
+            transparent_ptx_bundle
+                .partial_txs()
+                .iter()
+                .map(|ptx| {
+                    TransparentPartialTransaction::new(
+                        ptx.get_compliances().to_vec(),
+                        ptx.get_input_resource_apps().to_vec(),
+                        ptx.get_output_resource_apps().to_vec(),
+                        b"other hints".to_vec(),
+                    )
+                })
+                .collect(),
+        );
+        let resigned_tx = Transaction::build(OsRng, shielded_ptx_bundle, rehinted_bundle).unwrap();
+        assert_ne!(
+            borsh::to_vec(&tx.signature).unwrap(),
+            borsh::to_vec(&resigned_tx.signature).unwrap()
+        );
+        assert_ne!(
+            borsh::to_vec(&tx.transparent_ptx_bundle).unwrap(),
+            borsh::to_vec(&resigned_tx.transparent_ptx_bundle).unwrap()
+        );
+        assert_eq!(tx.txid(), resigned_tx.txid());
+    }
+
     #[test]
     fn test_halo2_transaction() {
         use super::*;
@@ -363,6 +589,7 @@ pub mod testing {
 
         let tx = Transaction::build(rng, shielded_ptx_bundle, transparent_ptx_bundle).unwrap();
         let _ret = tx.execute().unwrap();
+        assert_eq!(tx.execute_batched().unwrap(), _ret);
 
         #[cfg(feature = "borsh")]
         {
@@ -370,6 +597,198 @@ pub mod testing {
             let de_tx: Transaction = BorshDeserialize::deserialize(&mut borsh.as_ref()).unwrap();
             let de_ret = de_tx.execute().unwrap();
             assert_eq!(_ret, de_ret);
+            assert_eq!(tx.txid(), de_tx.txid());
+        }
+
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_string(&tx).unwrap();
+            let de_tx: Transaction = serde_json::from_str(&json).unwrap();
+            let de_ret = de_tx.execute().unwrap();
+            assert_eq!(_ret, de_ret);
         }
     }
 }
+
+// Deliberately invalid transactions, each must be rejected with the matching error.
+#[cfg(test)]
+#[cfg(feature = "borsh")]
+mod adversarial_tests {
+    use crate::{
+        compliance::tests::create_compliance_info_unchecked,
+        error::TransactionError,
+        executable::Executable,
+        transaction::{ShieldedPartialTxBundle, Transaction, TransparentPartialTxBundle},
+        transparent_ptx::{testing::*, TransparentPartialTransaction},
+    };
+    use rand::rngs::OsRng;
+
+    fn build_transparent_tx(ptx: TransparentPartialTransaction) -> Transaction {
+        Transaction::build(
+            OsRng,
+            ShieldedPartialTxBundle::default(),
+            TransparentPartialTxBundle::new(vec![ptx]),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_mismatched_owned_resource_id() {
+        let mut rng = OsRng;
+        let (input_resources, mut output_resources) = create_balanced_resources(&mut rng);
+        let compliances = create_compliances(&mut rng, &input_resources, &mut output_resources);
+        let (mut input_resource_apps, output_resource_apps) =
+            create_trivial_applications(&input_resources, &output_resources);
+        // Each application now checks the other input resource
+        input_resource_apps.swap(0, 1);
+
+        let ptx = TransparentPartialTransaction::new(
+            compliances,
+            input_resource_apps,
+            output_resource_apps,
+            vec![],
+        );
+        assert!(matches!(
+            ptx.execute(),
+            Err(TransactionError::InconsistentOwnedResourceID)
+        ));
+        assert!(matches!(
+            build_transparent_tx(ptx).execute(),
+            Err(TransactionError::InconsistentOwnedResourceID)
+        ));
+    }
+
+    #[test]
+    fn test_swapped_output_cms() {
+        let mut rng = OsRng;
+        let (input_resources, mut output_resources) = create_balanced_resources(&mut rng);
+        let compliances = create_compliances(&mut rng, &input_resources, &mut output_resources);
+        // The applications check the output resources of another partial transaction
+        let (other_input_resources, mut other_output_resources) =
+            create_balanced_resources(&mut rng);
+        create_compliances(
+            &mut rng,
+            &other_input_resources,
+            &mut other_output_resources,
+        );
+        let (input_resource_apps, output_resource_apps) =
+            create_trivial_applications(&input_resources, &other_output_resources);
+
+        let ptx = TransparentPartialTransaction::new(
+            compliances,
+            input_resource_apps,
+            output_resource_apps,
+            vec![],
+        );
+        assert!(matches!(
+            build_transparent_tx(ptx).execute(),
+            Err(TransactionError::InconsistentOutputResourceCommitment)
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_nullifier_in_partial_tx() {
+        let mut rng = OsRng;
+        let (mut input_resources, mut output_resources) = create_balanced_resources(&mut rng);
+        // Spend the same resource in both compliances
+        input_resources[1] = input_resources[0];
+        output_resources[1].kind = input_resources[0].kind;
+        output_resources[1].quantity = input_resources[0].quantity;
+        let compliances = create_compliances(&mut rng, &input_resources, &mut output_resources);
+        let (input_resource_apps, output_resource_apps) =
+            create_trivial_applications(&input_resources, &output_resources);
+
+        let ptx = TransparentPartialTransaction::new(
+            compliances,
+            input_resource_apps,
+            output_resource_apps,
+            vec![],
+        );
+        assert!(matches!(
+            build_transparent_tx(ptx).execute(),
+            Err(TransactionError::DuplicateNullifier)
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_nullifier_across_partial_txs() {
+        let ptx = create_transparent_ptx();
+        let tx = Transaction::build(
+            OsRng,
+            ShieldedPartialTxBundle::default(),
+            TransparentPartialTxBundle::new(vec![ptx.clone(), ptx]),
+        )
+        .unwrap();
+        assert!(matches!(
+            tx.execute(),
+            Err(TransactionError::DuplicateNullifier)
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_output_cm_across_partial_txs() {
+        let mut rng = OsRng;
+        let (input_resources, mut output_resources) = create_balanced_resources(&mut rng);
+        let compliances = create_compliances(&mut rng, &input_resources, &mut output_resources);
+        let (input_resource_apps, output_resource_apps) =
+            create_trivial_applications(&input_resources, &output_resources);
+        let ptx = TransparentPartialTransaction::new(
+            compliances,
+            input_resource_apps,
+            output_resource_apps,
+            vec![],
+        );
+
+        // Spend other resources but create the same output resources
+        let (other_input_resources, _) = create_balanced_resources(&mut rng);
+        let other_compliances = other_input_resources
+            .iter()
+            .zip(output_resources.iter())
+            .map(|(input_resource, output_resource)| {
+                create_compliance_info_unchecked(&mut rng, *input_resource, *output_resource)
+            })
+            .collect();
+        let (input_resource_apps, output_resource_apps) =
+            create_trivial_applications(&other_input_resources, &output_resources);
+        let other_ptx = TransparentPartialTransaction::new(
+            other_compliances,
+            input_resource_apps,
+            output_resource_apps,
+            vec![],
+        );
+
+        let tx = Transaction::build(
+            OsRng,
+            ShieldedPartialTxBundle::default(),
+            TransparentPartialTxBundle::new(vec![ptx, other_ptx]),
+        )
+        .unwrap();
+        assert!(matches!(
+            tx.execute(),
+            Err(TransactionError::DuplicateOutputResourceCommitment)
+        ));
+    }
+
+    #[test]
+    fn test_forged_quantity() {
+        let mut rng = OsRng;
+        let (input_resources, mut output_resources) = create_balanced_resources(&mut rng);
+        // Create one more unit than consumed, the resource logics are still satisfied
+        output_resources[0].quantity += 1;
+        let compliances = create_compliances(&mut rng, &input_resources, &mut output_resources);
+        let (input_resource_apps, output_resource_apps) =
+            create_trivial_applications(&input_resources, &output_resources);
+
+        let ptx = TransparentPartialTransaction::new(
+            compliances,
+            input_resource_apps,
+            output_resource_apps,
+            vec![],
+        );
+        ptx.execute().unwrap();
+        assert!(matches!(
+            build_transparent_tx(ptx).execute(),
+            Err(TransactionError::InvalidBindingSignature)
+        ));
+    }
+}
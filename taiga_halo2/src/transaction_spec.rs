@@ -0,0 +1,615 @@
+/// Declarative transaction specs.
+///
+/// A `TransactionSpec` describes a transaction as data: the partial transactions, the resources
+/// each of them spends and creates, and the resource logics of the resources by their names in
+/// the resource logic vk registry. `Transaction::from_spec` builds the witnesses, proves the
+/// partial transactions and assembles the transaction, so scenarios and test fixtures can be
+/// written in JSON or TOML instead of Rust.
+///
+/// The owners are named parties whose keys are drawn from the spec rng on first use, so a spec
+/// with a seed always builds the same witnesses.
+///
+/// The specs support the token logics, "Token" and "TokenU128", and the "OrRelationIntent"
+/// intent, whose owner receives either of the two tokens it wants. The other logics of the
+/// registry are rejected with `InvalidTransactionSpec`.
+use crate::{
+    circuit::{
+        resource_logic_bytecode::ApplicationByteCode,
+        resource_logic_examples::{
+            or_relation_intent::{create_intent_resource, OrRelationIntentResourceLogicCircuit},
+            signature_verification::COMPRESSED_TOKEN_AUTH_VK,
+            token::{Token, TokenAuthorization, TokenQuantityMode, TokenResource},
+        },
+    },
+    compliance::ComplianceInfo,
+    constant::{NUM_RESOURCE, TAIGA_COMMITMENT_TREE_DEPTH},
+    error::TransactionError,
+    merkle_tree::{Anchor, MerklePath},
+    nullifier::NullifierKeyContainer,
+    resource::{OwnedResourceId, Resource},
+    shielded_ptx::ShieldedPartialTransaction,
+    transaction::{ShieldedPartialTxBundle, Transaction, TransparentPartialTxBundle},
+};
+use ff::Field;
+use pasta_curves::pallas;
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TransactionSpec {
+    /// The seed of the rng drawing the keys and the witnesses, random if not set.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    pub partial_transactions: Vec<PartialTransactionSpec>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PartialTransactionSpec {
+    /// At most NUM_RESOURCE spent resources, the missing ones are padding resources.
+    #[serde(default)]
+    pub spends: Vec<ResourceSpec>,
+    /// At most NUM_RESOURCE created resources, the missing ones are padding resources.
+    #[serde(default)]
+    pub creates: Vec<ResourceSpec>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ResourceSpec {
+    /// The registered name of the resource logic, "Token", "TokenU128" or "OrRelationIntent".
+    pub logic: String,
+    /// The party owning the resource.
+    pub owner: String,
+    /// The token of a token resource, unused by the intents.
+    #[serde(default)]
+    pub token: String,
+    /// The quantity of a token resource, unused by the intents.
+    #[serde(default)]
+    pub quantity: u128,
+    /// The parameters of an intent resource, only used by the intents.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intent: Option<IntentSpec>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IntentSpec {
+    /// The two tokens of the "Token" logic the owner wants, either of them satisfies the intent.
+    pub wants: Vec<TokenSpec>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TokenSpec {
+    pub token: String,
+    pub quantity: u64,
+}
+
+impl TransactionSpec {
+    pub fn from_json(spec: &str) -> Result<Self, TransactionError> {
+        serde_json::from_str(spec)
+            .map_err(|e| TransactionError::InvalidTransactionSpec(e.to_string()))
+    }
+
+    pub fn from_toml(spec: &str) -> Result<Self, TransactionError> {
+        toml::from_str(spec).map_err(|e| TransactionError::InvalidTransactionSpec(e.to_string()))
+    }
+
+    // Checks the spec before drawing any witness, so a bad spec fails before the proving.
+    fn check(&self) -> Result<(), TransactionError> {
+        if self.partial_transactions.is_empty() {
+            return Err(TransactionError::InvalidTransactionSpec(
+                "the transaction has no partial transactions".to_string(),
+            ));
+        }
+        for (i, ptx) in self.partial_transactions.iter().enumerate() {
+            if ptx.spends.len() > NUM_RESOURCE || ptx.creates.len() > NUM_RESOURCE {
+                return Err(TransactionError::InvalidTransactionSpec(format!(
+                    "partial transaction {i} has more than {NUM_RESOURCE} spends or creates"
+                )));
+            }
+            for resource in ptx.spends.iter().chain(ptx.creates.iter()) {
+                resource.kind()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+enum ResourceKind {
+    Token(Token),
+    // The two tokens the owner wants
+    Intent(Token, Token),
+}
+
+impl ResourceSpec {
+    fn kind(&self) -> Result<ResourceKind, TransactionError> {
+        let quantity_mode = match self.logic.as_str() {
+            "Token" => TokenQuantityMode::U64,
+            "TokenU128" => TokenQuantityMode::U128,
+            "OrRelationIntent" => return self.intent(),
+            logic => {
+                return Err(TransactionError::InvalidTransactionSpec(format!(
+                    "resource logic {logic} is not supported by transaction specs"
+                )))
+            }
+        };
+        if self.intent.is_some() {
+            return Err(TransactionError::InvalidTransactionSpec(format!(
+                "the {} resource of {} has intent parameters",
+                self.logic, self.owner
+            )));
+        }
+        if quantity_mode == TokenQuantityMode::U64 && self.quantity > u64::MAX as u128 {
+            return Err(TransactionError::InvalidTransactionSpec(format!(
+                "quantity {} of {} doesn't fit the Token logic",
+                self.quantity, self.token
+            )));
+        }
+        Ok(ResourceKind::Token(Token::new_with_quantity_mode(
+            self.token.clone(),
+            self.quantity,
+            quantity_mode,
+        )))
+    }
+
+    fn intent(&self) -> Result<ResourceKind, TransactionError> {
+        if !self.token.is_empty() || self.quantity != 0 {
+            return Err(TransactionError::InvalidTransactionSpec(format!(
+                "the {} intent of {} has a token or a quantity",
+                self.logic, self.owner
+            )));
+        }
+        match self.intent.as_ref().map(|intent| intent.wants.as_slice()) {
+            Some([token_1, token_2]) => Ok(ResourceKind::Intent(
+                Token::new(token_1.token.clone(), token_1.quantity),
+                Token::new(token_2.token.clone(), token_2.quantity),
+            )),
+            _ => Err(TransactionError::InvalidTransactionSpec(format!(
+                "the {} intent of {} doesn't want exactly two tokens",
+                self.logic, self.owner
+            ))),
+        }
+    }
+}
+
+struct Party {
+    auth_sk: pallas::Scalar,
+    nk: NullifierKeyContainer,
+}
+
+impl Party {
+    fn auth(&self) -> TokenAuthorization {
+        TokenAuthorization::from_sk_vk(&self.auth_sk, &COMPRESSED_TOKEN_AUTH_VK)
+    }
+}
+
+#[derive(Default)]
+struct Parties(HashMap<String, Party>);
+
+impl Parties {
+    fn get<R: RngCore>(&mut self, name: &str, mut rng: R) -> &Party {
+        self.0.entry(name.to_string()).or_insert_with(|| Party {
+            auth_sk: pallas::Scalar::random(&mut rng),
+            nk: NullifierKeyContainer::random_key(&mut rng),
+        })
+    }
+}
+
+// A spent or created resource of a spec, with the witnesses of its application
+enum SpecResource {
+    Token {
+        token_resource: TokenResource,
+        auth: TokenAuthorization,
+        auth_sk: pallas::Scalar,
+    },
+    Intent {
+        resource: Resource,
+        token_1: Token,
+        token_2: Token,
+        receiver_npk: pallas::Base,
+        receiver_value: pallas::Base,
+    },
+}
+
+impl SpecResource {
+    fn new<R: RngCore>(
+        spec: &ResourceSpec,
+        is_input: bool,
+        parties: &mut Parties,
+        mut rng: R,
+    ) -> Result<Self, TransactionError> {
+        let kind = spec.kind()?;
+        let party = parties.get(&spec.owner, &mut rng);
+        let (auth, auth_sk) = (party.auth(), party.auth_sk);
+        let (nk, npk) = (party.nk.get_nk().unwrap(), party.nk.get_npk());
+        Ok(match kind {
+            ResourceKind::Token(token) => {
+                let token_resource = if is_input {
+                    token.create_random_input_token_resource(&mut rng, nk, &auth)
+                } else {
+                    token.create_random_output_token_resource(&mut rng, npk, &auth)
+                };
+                Self::Token {
+                    token_resource,
+                    auth,
+                    auth_sk,
+                }
+            }
+            // The owner of the intent receives the wanted token
+            ResourceKind::Intent(token_1, token_2) => {
+                let receiver_value = auth.to_value();
+                let resource =
+                    create_intent_resource(&mut rng, &token_1, &token_2, npk, receiver_value, nk);
+                Self::Intent {
+                    resource,
+                    token_1,
+                    token_2,
+                    receiver_npk: npk,
+                    receiver_value,
+                }
+            }
+        })
+    }
+
+    fn resource(&self) -> Resource {
+        match self {
+            Self::Token { token_resource, .. } => *token_resource.resource(),
+            Self::Intent { resource, .. } => *resource,
+        }
+    }
+
+    fn set_resource(&mut self, new_resource: Resource) {
+        match self {
+            Self::Token { token_resource, .. } => token_resource.resource = new_resource,
+            Self::Intent { resource, .. } => *resource = new_resource,
+        }
+    }
+
+    // The intents are ephemeral, their inputs don't need a real root
+    fn anchor<R: RngCore>(&self, rng: R) -> Option<Anchor> {
+        match self {
+            Self::Token { .. } => None,
+            Self::Intent { .. } => Some(Anchor::from(pallas::Base::random(rng))),
+        }
+    }
+
+    fn application<R: RngCore>(
+        &self,
+        is_input: bool,
+        rng: R,
+        input_resources: [Resource; NUM_RESOURCE],
+        output_resources: [Resource; NUM_RESOURCE],
+    ) -> ApplicationByteCode {
+        match self {
+            Self::Token {
+                token_resource,
+                auth,
+                auth_sk,
+            } => {
+                if is_input {
+                    token_resource.generate_input_token_application(
+                        rng,
+                        *auth,
+                        *auth_sk,
+                        input_resources,
+                        output_resources,
+                    )
+                } else {
+                    token_resource.generate_output_token_application(
+                        rng,
+                        *auth,
+                        input_resources,
+                        output_resources,
+                    )
+                }
+            }
+            Self::Intent {
+                resource,
+                token_1,
+                token_2,
+                receiver_npk,
+                receiver_value,
+            } => {
+                let owned_resource_id = if is_input {
+                    OwnedResourceId::Nf(resource.get_nf().unwrap())
+                } else {
+                    OwnedResourceId::Cm(resource.commitment())
+                };
+                let intent_resource_logic = OrRelationIntentResourceLogicCircuit {
+                    owned_resource_id,
+                    input_resources,
+                    output_resources,
+                    token_1: token_1.clone(),
+                    token_2: token_2.clone(),
+                    receiver_npk: *receiver_npk,
+                    receiver_value: *receiver_value,
+                };
+                ApplicationByteCode::from_application(intent_resource_logic.to_bytecode())
+            }
+        }
+    }
+}
+
+fn build_partial_transaction<R: RngCore>(
+    spec: &PartialTransactionSpec,
+    parties: &mut Parties,
+    mut rng: R,
+) -> Result<ShieldedPartialTransaction, TransactionError> {
+    let spends = spec
+        .spends
+        .iter()
+        .map(|resource| SpecResource::new(resource, true, parties, &mut rng))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut creates = spec
+        .creates
+        .iter()
+        .map(|resource| SpecResource::new(resource, false, parties, &mut rng))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Pair the resources in compliances, padding the missing ones
+    let mut input_resources = vec![];
+    let mut output_resources = vec![];
+    let mut compliances = vec![];
+    for i in 0..NUM_RESOURCE {
+        let (input_resource, anchor) = match spends.get(i) {
+            Some(spend) => (spend.resource(), spend.anchor(&mut rng)),
+            None => (
+                Resource::random_padding_resource(&mut rng),
+                Some(Anchor::from(pallas::Base::random(&mut rng))),
+            ),
+        };
+        let mut output_resource = match creates.get(i) {
+            Some(create) => create.resource(),
+            None => Resource::random_padding_resource(&mut rng),
+        };
+        let merkle_path = MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH);
+        compliances.push(ComplianceInfo::new(
+            input_resource,
+            merkle_path,
+            anchor,
+            &mut output_resource,
+            &mut rng,
+        ));
+        // The compliance sets the nonce of the output resource
+        if let Some(create) = creates.get_mut(i) {
+            create.set_resource(output_resource);
+        }
+        input_resources.push(input_resource);
+        output_resources.push(output_resource);
+    }
+    let input_resources: [Resource; NUM_RESOURCE] = input_resources.try_into().unwrap();
+    let output_resources: [Resource; NUM_RESOURCE] = output_resources.try_into().unwrap();
+
+    // Applications
+    let input_resource_apps = input_resources
+        .iter()
+        .enumerate()
+        .map(|(i, resource)| match spends.get(i) {
+            Some(spend) => spend.application(true, &mut rng, input_resources, output_resources),
+            None => ApplicationByteCode::create_input_padding_resource_application(
+                resource,
+                input_resources,
+                output_resources,
+            ),
+        })
+        .collect();
+    let output_resource_apps = output_resources
+        .iter()
+        .enumerate()
+        .map(|(i, resource)| match creates.get(i) {
+            Some(create) => create.application(false, &mut rng, input_resources, output_resources),
+            None => ApplicationByteCode::create_output_padding_resource_application(
+                resource,
+                input_resources,
+                output_resources,
+            ),
+        })
+        .collect();
+
+    ShieldedPartialTransaction::from_bytecode(
+        compliances,
+        input_resource_apps,
+        output_resource_apps,
+        vec![],
+        &mut rng,
+    )
+}
+
+impl Transaction {
+    /// Builds and proves the transaction described by the spec.
+    pub fn from_spec(spec: &TransactionSpec) -> Result<Self, TransactionError> {
+        spec.check()?;
+        let mut rng = match spec.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        let mut parties = Parties::default();
+        let ptxs = spec
+            .partial_transactions
+            .iter()
+            .map(|ptx| build_partial_transaction(ptx, &mut parties, &mut rng))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Transaction::build(
+            rng,
+            ShieldedPartialTxBundle::new(ptxs),
+            TransparentPartialTxBundle::default(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IntentSpec, PartialTransactionSpec, ResourceSpec, TokenSpec, TransactionSpec};
+    use crate::error::TransactionError;
+    use crate::transaction::Transaction;
+
+    // Alice, Bob and Carol swap tokens in a ring
+    const TOKEN_RING_SWAP_SPEC: &str = r#"
+seed = 7
+
+[[partial_transactions]]
+spends = [{ logic = "Token", owner = "alice", token = "btc", quantity = 5 }]
+creates = [{ logic = "Token", owner = "alice", token = "eth", quantity = 10 }]
+
+[[partial_transactions]]
+spends = [{ logic = "Token", owner = "bob", token = "eth", quantity = 10 }]
+creates = [{ logic = "Token", owner = "bob", token = "xan", quantity = 15 }]
+
+[[partial_transactions]]
+spends = [{ logic = "Token", owner = "carol", token = "xan", quantity = 15 }]
+creates = [{ logic = "Token", owner = "carol", token = "btc", quantity = 5 }]
+"#;
+
+    // Alice swaps 5 btc for 1 dolphin or 2 monkeys with an intent, Bob sells a dolphin for the
+    // btc and the solver settles the intent
+    const TOKEN_SWAP_INTENT_SPEC: &str = r#"
+seed = 11
+
+[[partial_transactions]]
+spends = [{ logic = "Token", owner = "alice", token = "btc", quantity = 5 }]
+
+[[partial_transactions.creates]]
+logic = "OrRelationIntent"
+owner = "alice"
+intent.wants = [{ token = "dolphin", quantity = 1 }, { token = "monkey", quantity = 2 }]
+
+[[partial_transactions]]
+spends = [{ logic = "Token", owner = "bob", token = "dolphin", quantity = 1 }]
+creates = [{ logic = "Token", owner = "bob", token = "btc", quantity = 5 }]
+
+[[partial_transactions]]
+creates = [{ logic = "Token", owner = "alice", token = "dolphin", quantity = 1 }]
+
+[[partial_transactions.spends]]
+logic = "OrRelationIntent"
+owner = "alice"
+intent.wants = [{ token = "dolphin", quantity = 1 }, { token = "monkey", quantity = 2 }]
+"#;
+
+    #[test]
+    fn test_transaction_spec_formats() {
+        let spec = TransactionSpec::from_toml(TOKEN_RING_SWAP_SPEC).unwrap();
+        assert_eq!(spec.seed, Some(7));
+        assert_eq!(spec.partial_transactions.len(), 3);
+
+        let json = serde_json::to_string(&spec).unwrap();
+        assert_eq!(TransactionSpec::from_json(&json).unwrap(), spec);
+
+        assert!(matches!(
+            TransactionSpec::from_json(r#"{"partial_transactions": [], "fee": 1}"#),
+            Err(TransactionError::InvalidTransactionSpec(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_transaction_spec() {
+        let resource = ResourceSpec {
+            logic: "Token".to_string(),
+            owner: "alice".to_string(),
+            token: "btc".to_string(),
+            quantity: 5,
+            intent: None,
+        };
+        let intent = ResourceSpec {
+            logic: "OrRelationIntent".to_string(),
+            owner: "alice".to_string(),
+            token: String::new(),
+            quantity: 0,
+            intent: Some(IntentSpec {
+                wants: vec![
+                    TokenSpec {
+                        token: "dolphin".to_string(),
+                        quantity: 1,
+                    },
+                    TokenSpec {
+                        token: "monkey".to_string(),
+                        quantity: 2,
+                    },
+                ],
+            }),
+        };
+        let invalid_specs = [
+            vec![],
+            vec![PartialTransactionSpec {
+                spends: vec![resource.clone(); 3],
+                creates: vec![],
+            }],
+            vec![PartialTransactionSpec {
+                spends: vec![ResourceSpec {
+                    logic: "Bridge".to_string(),
+                    ..resource.clone()
+                }],
+                creates: vec![],
+            }],
+            vec![PartialTransactionSpec {
+                spends: vec![],
+                creates: vec![ResourceSpec {
+                    quantity: u64::MAX as u128 + 1,
+                    ..resource.clone()
+                }],
+            }],
+            vec![PartialTransactionSpec {
+                spends: vec![ResourceSpec {
+                    intent: intent.intent.clone(),
+                    ..resource.clone()
+                }],
+                creates: vec![],
+            }],
+            vec![PartialTransactionSpec {
+                spends: vec![],
+                creates: vec![ResourceSpec {
+                    intent: None,
+                    ..intent.clone()
+                }],
+            }],
+            vec![PartialTransactionSpec {
+                spends: vec![],
+                creates: vec![ResourceSpec {
+                    intent: Some(IntentSpec {
+                        wants: intent.intent.clone().unwrap().wants[..1].to_vec(),
+                    }),
+                    ..intent.clone()
+                }],
+            }],
+            vec![PartialTransactionSpec {
+                spends: vec![],
+                creates: vec![ResourceSpec {
+                    token: resource.token,
+                    ..intent
+                }],
+            }],
+        ];
+        for partial_transactions in invalid_specs {
+            let spec = TransactionSpec {
+                seed: None,
+                partial_transactions,
+            };
+            assert!(matches!(
+                Transaction::from_spec(&spec),
+                Err(TransactionError::InvalidTransactionSpec(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_transaction_from_spec() {
+        let spec = TransactionSpec::from_toml(TOKEN_RING_SWAP_SPEC).unwrap();
+        let tx = Transaction::from_spec(&spec).unwrap();
+        tx.execute().unwrap();
+    }
+
+    #[test]
+    fn test_intent_transaction_from_spec() {
+        let spec = TransactionSpec::from_toml(TOKEN_SWAP_INTENT_SPEC).unwrap();
+        let json = serde_json::to_string(&spec).unwrap();
+        assert_eq!(TransactionSpec::from_json(&json).unwrap(), spec);
+
+        let tx = Transaction::from_spec(&spec).unwrap();
+        tx.execute().unwrap();
+    }
+}
@@ -1,8 +1,14 @@
 use crate::{
-    circuit::resource_logic_bytecode::ApplicationByteCode, compliance::ComplianceInfo,
-    constant::NUM_RESOURCE, delta_commitment::DeltaCommitment, error::TransactionError,
-    executable::Executable, merkle_tree::Anchor, nullifier::Nullifier,
-    resource::ResourceCommitment,
+    circuit::resource_logic_bytecode::ApplicationByteCode,
+    compliance::ComplianceInfo,
+    constant::NUM_RESOURCE,
+    delta_commitment::DeltaCommitment,
+    error::TransactionError,
+    executable::Executable,
+    merkle_tree::Anchor,
+    nullifier::Nullifier,
+    resource::{OwnedResourceId, ResourceCommitment},
+    verification_trace::{enter_component, trace_check},
 };
 
 use pasta_curves::pallas;
@@ -40,27 +46,68 @@ impl TransparentPartialTransaction {
             hints,
         }
     }
+
+    pub fn get_compliances(&self) -> &[ComplianceInfo] {
+        &self.compliances
+    }
+
+    pub fn get_input_resource_apps(&self) -> &[ApplicationByteCode] {
+        &self.input_resource_app
+    }
+
+    pub fn get_output_resource_apps(&self) -> &[ApplicationByteCode] {
+        &self.output_resource_app
+    }
+
+    // Hashes the compliances and the resource logic inputs of the ptx into the transaction id.
+    // The hints are left out: they're not checked, anyone relaying the ptx could change them.
+    #[cfg(feature = "borsh")]
+    pub(crate) fn update_txid_hash(&self, h: &mut blake2b_simd::State) {
+        h.update(&borsh::to_vec(&self.compliances).unwrap());
+        h.update(&borsh::to_vec(&self.input_resource_app).unwrap());
+        h.update(&borsh::to_vec(&self.output_resource_app).unwrap());
+    }
 }
 
 impl Executable for TransparentPartialTransaction {
     fn execute(&self) -> Result<(), TransactionError> {
+        for (index, compliance) in self.compliances.iter().enumerate() {
+            trace_check("transparent compliance", index, || {
+                compliance.verify_transparently()
+            })?;
+        }
+
         // check resource logics, nullifiers, and resource commitments
         let compliance_nfs = self.get_nullifiers();
         let compliance_cms = self.get_output_cms();
-        for (resource_logic, nf) in self.input_resource_app.iter().zip(compliance_nfs.iter()) {
-            let owned_resource_id =
-                resource_logic.verify_transparently(&compliance_nfs, &compliance_cms)?;
+        for (index, (resource_logic, nf)) in self
+            .input_resource_app
+            .iter()
+            .zip(compliance_nfs.iter())
+            .enumerate()
+        {
+            let _span = enter_component("input resource", index);
+            let owned_resource_id = trace_check("transparent resource logic", index, || {
+                resource_logic.verify_transparently(&compliance_nfs, &compliance_cms)
+            })?;
             // Make sure all resource logics are checked
-            if owned_resource_id != nf.inner() {
+            if owned_resource_id != OwnedResourceId::Nf(*nf) {
                 return Err(TransactionError::InconsistentOwnedResourceID);
             }
         }
 
-        for (resource_logic, cm) in self.output_resource_app.iter().zip(compliance_cms.iter()) {
-            let owned_resource_id =
-                resource_logic.verify_transparently(&compliance_nfs, &compliance_cms)?;
+        for (index, (resource_logic, cm)) in self
+            .output_resource_app
+            .iter()
+            .zip(compliance_cms.iter())
+            .enumerate()
+        {
+            let _span = enter_component("output resource", index);
+            let owned_resource_id = trace_check("transparent resource logic", index, || {
+                resource_logic.verify_transparently(&compliance_nfs, &compliance_cms)
+            })?;
             // Make sure all resource logics are checked
-            if owned_resource_id != cm.inner() {
+            if owned_resource_id != OwnedResourceId::Cm(*cm) {
                 return Err(TransactionError::InconsistentOwnedResourceID);
             }
         }
@@ -106,92 +153,86 @@ impl Executable for TransparentPartialTransaction {
 pub mod testing {
     use crate::{
         circuit::resource_logic_examples::TrivialResourceLogicCircuit,
-        constant::TAIGA_COMMITMENT_TREE_DEPTH, merkle_tree::MerklePath,
-        resource::tests::random_resource, transparent_ptx::*,
+        constant::TAIGA_COMMITMENT_TREE_DEPTH,
+        merkle_tree::MerklePath,
+        resource::{tests::random_resource, Resource},
+        transparent_ptx::*,
     };
-    use rand::rngs::OsRng;
+    use rand::{rngs::OsRng, RngCore};
 
     pub fn create_transparent_ptx() -> TransparentPartialTransaction {
         let mut rng = OsRng;
-        // construct resources
-        let input_resource_1 = random_resource(&mut rng);
-        let mut output_resource_1 = {
-            let mut resource = random_resource(&mut rng);
-            resource.kind = input_resource_1.kind;
-            resource.quantity = input_resource_1.quantity;
-            resource
-        };
-        let merkle_path_1 = MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH);
-        let compliance_1 = ComplianceInfo::new(
-            input_resource_1,
-            merkle_path_1,
-            None,
-            &mut output_resource_1,
-            &mut rng,
-        );
-
-        let input_resource_2 = random_resource(&mut rng);
-        let mut output_resource_2 = {
-            let mut resource = random_resource(&mut rng);
-            resource.kind = input_resource_2.kind;
-            resource.quantity = input_resource_2.quantity;
-            resource
-        };
-        let merkle_path_2 = MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH);
-        let compliance_2 = ComplianceInfo::new(
-            input_resource_2,
-            merkle_path_2,
-            None,
-            &mut output_resource_2,
-            &mut rng,
-        );
-
-        // construct applications
-        let input_resource_1_app = {
-            let app_resource_logic = TrivialResourceLogicCircuit::new(
-                input_resource_1.get_nf().unwrap().inner(),
-                [input_resource_1, input_resource_2],
-                [output_resource_1, output_resource_2],
-            );
-
-            ApplicationByteCode::new(app_resource_logic.to_bytecode(), vec![])
-        };
-
-        let input_resource_2_app = {
-            let app_resource_logic = TrivialResourceLogicCircuit::new(
-                input_resource_2.get_nf().unwrap().inner(),
-                [input_resource_1, input_resource_2],
-                [output_resource_1, output_resource_2],
-            );
+        let (input_resources, mut output_resources) = create_balanced_resources(&mut rng);
+        let compliances = create_compliances(&mut rng, &input_resources, &mut output_resources);
+        let (input_resource_apps, output_resource_apps) =
+            create_trivial_applications(&input_resources, &output_resources);
 
-            ApplicationByteCode::new(app_resource_logic.to_bytecode(), vec![])
-        };
+        TransparentPartialTransaction::new(
+            compliances,
+            input_resource_apps,
+            output_resource_apps,
+            vec![],
+        )
+    }
 
-        let output_resource_1_app = {
-            let app_resource_logic = TrivialResourceLogicCircuit::new(
-                output_resource_1.commitment().inner(),
-                [input_resource_1, input_resource_2],
-                [output_resource_1, output_resource_2],
-            );
+    // Random input resources and output resources of the same kinds and quantities
+    pub fn create_balanced_resources<R: RngCore>(
+        mut rng: R,
+    ) -> ([Resource; NUM_RESOURCE], [Resource; NUM_RESOURCE]) {
+        let input_resources = [(); NUM_RESOURCE].map(|_| random_resource(&mut rng));
+        let output_resources = input_resources.map(|input_resource| {
+            let mut resource = random_resource(&mut rng);
+            resource.kind = input_resource.kind;
+            resource.quantity = input_resource.quantity;
+            resource
+        });
+        (input_resources, output_resources)
+    }
 
-            ApplicationByteCode::new(app_resource_logic.to_bytecode(), vec![])
-        };
+    // Pair up the resources, the nonces of the output resources are set to the input nullifiers
+    pub fn create_compliances<R: RngCore>(
+        mut rng: R,
+        input_resources: &[Resource; NUM_RESOURCE],
+        output_resources: &mut [Resource; NUM_RESOURCE],
+    ) -> Vec<ComplianceInfo> {
+        input_resources
+            .iter()
+            .zip(output_resources.iter_mut())
+            .map(|(input_resource, output_resource)| {
+                let merkle_path = MerklePath::random(&mut rng, TAIGA_COMMITMENT_TREE_DEPTH);
+                ComplianceInfo::new(
+                    *input_resource,
+                    merkle_path,
+                    None,
+                    output_resource,
+                    &mut rng,
+                )
+            })
+            .collect()
+    }
 
-        let output_resource_2_app = {
+    // Trivial applications for all the resources
+    pub fn create_trivial_applications(
+        input_resources: &[Resource; NUM_RESOURCE],
+        output_resources: &[Resource; NUM_RESOURCE],
+    ) -> (Vec<ApplicationByteCode>, Vec<ApplicationByteCode>) {
+        let create_app = |owned_resource_id: OwnedResourceId| {
             let app_resource_logic = TrivialResourceLogicCircuit::new(
-                output_resource_2.commitment().inner(),
-                [input_resource_1, input_resource_2],
-                [output_resource_1, output_resource_2],
+                owned_resource_id,
+                *input_resources,
+                *output_resources,
             );
-
-            ApplicationByteCode::new(app_resource_logic.to_bytecode(), vec![])
+            ApplicationByteCode::from_application(app_resource_logic.to_bytecode())
         };
 
-        TransparentPartialTransaction::new(
-            vec![compliance_1, compliance_2],
-            vec![input_resource_1_app, input_resource_2_app],
-            vec![output_resource_1_app, output_resource_2_app],
-            vec![],
-        )
+        let input_resource_apps = input_resources
+            .iter()
+            .map(|resource| create_app(resource.get_nf().unwrap().into()))
+            .collect();
+        let output_resource_apps = output_resources
+            .iter()
+            .map(|resource| create_app(resource.commitment().into()))
+            .collect();
+        (input_resource_apps, output_resource_apps)
     }
 }
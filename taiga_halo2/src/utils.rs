@@ -1,9 +1,13 @@
-use crate::constant::{POSEIDON_TO_FIELD_U_0_POSTFIX, POSEIDON_TO_FIELD_U_1_POSTFIX};
+use crate::constant::{CURVE_ID, POSEIDON_TO_FIELD_U_0_POSTFIX, POSEIDON_TO_FIELD_U_1_POSTFIX};
+use blake2b_simd::Params as Blake2bParams;
 use halo2_gadgets::poseidon::primitives as poseidon;
 use halo2_proofs::arithmetic::CurveAffine;
 use pasta_curves::{
     arithmetic::CurveExt,
-    group::{ff::PrimeField, Curve, GroupEncoding},
+    group::{
+        ff::{FromUniformBytes, PrimeField},
+        Curve, GroupEncoding,
+    },
     hashtocurve, pallas,
 };
 
@@ -48,14 +52,71 @@ pub(crate) fn poseidon_hash_n<const L: usize>(message: [pallas::Base; L]) -> pal
 }
 
 pub fn poseidon_to_curve<const L: usize>(message: &[pallas::Base]) -> pallas::Point {
-    let us = poseidon_to_field::<L>(message);
+    let [u_0, u_1] = poseidon_to_field::<L>(message);
+    map_to_curve(u_0, u_1)
+}
+
+/// The longest domain of a `HashToCurveDomain`, its postfixes fit in a field element.
+pub const MAX_HASH_TO_CURVE_DOMAIN_LEN: usize = BYTES_PER_FIELD - CURVE_ID.len() - 4;
+
+/// The domain separation of `hash_to_curve`. The two field elements mapped to the curve are
+/// hashed with the postfixes `"{domain}-pallas-0"` and `"{domain}-pallas-1"`, each followed by the
+/// byte `domain.len() + 10` and packed into a field element in little endian.
+///
+/// The resource kinds are derived in the `RESOURCE_KIND_DOMAIN`, "Taiga-NoteType".
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HashToCurveDomain {
+    u_0_postfix: pallas::Base,
+    u_1_postfix: pallas::Base,
+}
+
+impl HashToCurveDomain {
+    /// Panics if the domain is longer than `MAX_HASH_TO_CURVE_DOMAIN_LEN` bytes.
+    pub fn new(domain: &str) -> Self {
+        assert!(
+            domain.len() <= MAX_HASH_TO_CURVE_DOMAIN_LEN,
+            "the hash-to-curve domain is longer than {MAX_HASH_TO_CURVE_DOMAIN_LEN} bytes"
+        );
+        let postfix = |index: u8| {
+            let mut postfix = format!("{domain}-{CURVE_ID}-{index}").into_bytes();
+            postfix.push((4 + CURVE_ID.len() + domain.len()) as u8);
+            to_field_elements(&postfix)[0]
+        };
+        Self {
+            u_0_postfix: postfix(0),
+            u_1_postfix: postfix(1),
+        }
+    }
+
+    pub fn u_0_postfix(&self) -> pallas::Base {
+        self.u_0_postfix
+    }
+
+    pub fn u_1_postfix(&self) -> pallas::Base {
+        self.u_1_postfix
+    }
+}
+
+/// Hashes two field elements to a pallas point in the domain:
+/// `u_i = Poseidon(message[0], message[1], postfix_i)` and the point is
+/// `iso_map(map_to_curve_simple_swu(u_0) + map_to_curve_simple_swu(u_1))`. The point is the
+/// one of `circuit::hash_to_curve::hash_to_curve_gadget`.
+///
+/// `ResourceKind::derive_kind` is `hash_to_curve(&RESOURCE_KIND_DOMAIN, [logic, label])`.
+pub fn hash_to_curve(domain: &HashToCurveDomain, message: [pallas::Base; 2]) -> pallas::Point {
+    let u_0 = poseidon_hash_n([message[0], message[1], domain.u_0_postfix]);
+    let u_1 = poseidon_hash_n([message[0], message[1], domain.u_1_postfix]);
+    map_to_curve(u_0, u_1)
+}
+
+fn map_to_curve(u_0: pallas::Base, u_1: pallas::Base) -> pallas::Point {
     let q0 = hashtocurve::map_to_curve_simple_swu::<pallas::Base, pallas::Point, pallas::Iso>(
-        &us[0],
+        &u_0,
         pallas::Point::THETA,
         pallas::Point::Z,
     );
     let q1 = hashtocurve::map_to_curve_simple_swu::<pallas::Base, pallas::Point, pallas::Iso>(
-        &us[1],
+        &u_1,
         pallas::Point::THETA,
         pallas::Point::Z,
     );
@@ -86,9 +147,8 @@ fn poseidon_to_field<const L: usize>(message: &[pallas::Base]) -> [pallas::Base;
 }
 
 pub fn to_field_elements(bytes: &[u8]) -> Vec<pallas::Base> {
-    let max_size = ((pallas::Base::NUM_BITS - 1) / 8) as usize;
     bytes
-        .chunks(max_size)
+        .chunks(BYTES_PER_FIELD)
         .map(|chunk| {
             let mut field_bytes = [0u8; 32];
             field_bytes.iter_mut().zip(chunk).for_each(|(a, b)| *a = *b);
@@ -97,6 +157,140 @@ pub fn to_field_elements(bytes: &[u8]) -> Vec<pallas::Base> {
         .collect::<Vec<pallas::Base>>()
 }
 
+/// The number of bytes packed into one field element by [`encode_bytes_to_fields`].
+pub const BYTES_PER_FIELD: usize = ((pallas::Base::NUM_BITS - 1) / 8) as usize;
+
+/// Encodes a byte string as field elements: the bytes packed 31 per element in little endian,
+/// followed by the length of the byte string.
+///
+/// Binding the length makes the encoding injective, e.g. "abc" and "abc\0" are encoded
+/// differently, and every element is canonical, so the fields can be hashed with Poseidon or
+/// witnessed in a circuit with `circuit::gadgets::bytes_encoding`.
+pub fn encode_bytes_to_fields(bytes: &[u8]) -> Vec<pallas::Base> {
+    let mut fields = to_field_elements(bytes);
+    fields.push(pallas::Base::from(bytes.len() as u64));
+    fields
+}
+
+/// The inverse of [`encode_bytes_to_fields`], None if the fields are not a valid encoding.
+pub fn decode_fields_to_bytes(fields: &[pallas::Base]) -> Option<Vec<u8>> {
+    let (len, chunks) = fields.split_last()?;
+    let len_repr = len.to_repr();
+    if len_repr[8..].iter().any(|b| *b != 0) {
+        return None;
+    }
+    let len = usize::try_from(u64::from_le_bytes(len_repr[..8].try_into().unwrap())).ok()?;
+    if chunks.len() != len.div_ceil(BYTES_PER_FIELD) {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(chunks.len() * BYTES_PER_FIELD);
+    for chunk in chunks {
+        let repr = chunk.to_repr();
+        if repr[BYTES_PER_FIELD..].iter().any(|b| *b != 0) {
+            return None;
+        }
+        bytes.extend_from_slice(&repr[..BYTES_PER_FIELD]);
+    }
+    // The padding of the last chunk must be zeros
+    if bytes[len..].iter().any(|b| *b != 0) {
+        return None;
+    }
+    bytes.truncate(len);
+    Some(bytes)
+}
+
+/// Hashes a byte string to one field element with a Poseidon chain, starting from zero, over
+/// its [`encode_bytes_to_fields`] encoding. The in-circuit counterpart is
+/// `circuit::gadgets::bytes_encoding::hash_encoded_bytes_gadget`.
+pub fn poseidon_hash_bytes(bytes: &[u8]) -> pallas::Base {
+    encode_bytes_to_fields(bytes)
+        .into_iter()
+        .fold(pallas::Base::zero(), poseidon_hash)
+}
+
+/// The hash used by [`hash_to_field`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HashToFieldMethod {
+    /// Blake2b-512 followed by a wide reduction into pallas::Base. Cheap natively, but
+    /// has no in-circuit counterpart.
+    #[default]
+    Blake2b,
+    /// A Poseidon chain over 31-byte chunks of the message, bound to the personalization
+    /// and the message length. Can be recomputed in-circuit with
+    /// `circuit::gadgets::hash_to_field::hash_to_field_gadget`.
+    Poseidon,
+}
+
+/// Maps an arbitrary byte string to pallas::Base canonically.
+///
+/// This is the standard way to bring external data (strings, serialized application
+/// state, foreign-chain data, etc.) into resource fields such as `label` or `value`.
+/// Unlike packing bytes with `from_repr`, it never fails and never truncates, and the
+/// personalization separates domains of different applications.
+pub fn hash_to_field(
+    method: HashToFieldMethod,
+    personalization: &[u8; 16],
+    message: &[u8],
+) -> pallas::Base {
+    match method {
+        HashToFieldMethod::Blake2b => {
+            let hash = Blake2bParams::new()
+                .hash_length(64)
+                .personal(personalization)
+                .hash(message);
+            pallas::Base::from_uniform_bytes(hash.as_array())
+        }
+        HashToFieldMethod::Poseidon => {
+            let init = poseidon_hash(
+                to_field_elements(personalization)[0],
+                pallas::Base::from(message.len() as u64),
+            );
+            to_field_elements(message)
+                .into_iter()
+                .fold(init, poseidon_hash)
+        }
+    }
+}
+
+pub(crate) fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub(crate) fn hex_decode(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// The serde encoding of the byte blobs (proofs, verifying keys): a hex string in the
+/// human-readable formats such as JSON, the raw bytes otherwise.
+#[cfg(feature = "serde")]
+pub(crate) mod serde_hex {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: impl AsRef<[u8]>, s: S) -> Result<S::Ok, S::Error> {
+        if s.is_human_readable() {
+            s.serialize_str(&super::hex_encode(bytes.as_ref()))
+        } else {
+            s.serialize_bytes(bytes.as_ref())
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+        if d.is_human_readable() {
+            let hex = String::deserialize(d)?;
+            super::hex_decode(&hex).ok_or_else(|| D::Error::custom("invalid hex string"))
+        } else {
+            Vec::<u8>::deserialize(d)
+        }
+    }
+}
+
 pub fn read_base_field<R: std::io::Read>(reader: &mut R) -> std::io::Result<pallas::Base> {
     let mut bytes = [0u8; 32];
     reader.read_exact(&mut bytes)?;
@@ -117,3 +311,219 @@ pub fn read_point<R: std::io::Read>(reader: &mut R) -> std::io::Result<pallas::P
     Option::from(pallas::Point::from_bytes(&bytes))
         .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid point"))
 }
+
+#[test]
+fn test_bytes_to_fields_encoding() {
+    use rand::{rngs::OsRng, RngCore};
+
+    for len in [0, 1, 30, 31, 32, 62, 63, 100] {
+        let mut bytes = vec![0u8; len];
+        OsRng.fill_bytes(&mut bytes);
+        let fields = encode_bytes_to_fields(&bytes);
+        assert_eq!(fields.len(), len.div_ceil(BYTES_PER_FIELD) + 1);
+        assert_eq!(decode_fields_to_bytes(&fields), Some(bytes));
+    }
+
+    // Trailing zeros are bound by the length
+    assert_ne!(
+        encode_bytes_to_fields(b"abc"),
+        encode_bytes_to_fields(b"abc\0")
+    );
+    assert_ne!(poseidon_hash_bytes(b"abc"), poseidon_hash_bytes(b"abc\0"));
+    assert_ne!(poseidon_hash_bytes(b""), poseidon_hash_bytes(b"\0"));
+
+    // Invalid encodings
+    assert_eq!(decode_fields_to_bytes(&[]), None);
+    let fields = encode_bytes_to_fields(b"abc");
+    assert_eq!(
+        decode_fields_to_bytes(&[fields[0], pallas::Base::from(2)]),
+        None
+    );
+    assert_eq!(
+        decode_fields_to_bytes(&[fields[0], pallas::Base::from(32)]),
+        None
+    );
+    assert_eq!(
+        decode_fields_to_bytes(&[-pallas::Base::one(), pallas::Base::from(31)]),
+        None
+    );
+    assert_eq!(decode_fields_to_bytes(&[-pallas::Base::one()]), None);
+}
+
+#[test]
+fn test_hash_to_curve() {
+    use crate::constant::{RESOURCE_KIND_DOMAIN, VALUE_BASE_DOMAIN_POSTFIX};
+    use crate::resource::ResourceKind;
+    use ff::Field;
+    use rand::rngs::OsRng;
+
+    // The kind domain matches the postfixes the kinds were derived with
+    let legacy_postfix = |index: u8| {
+        let mut postfix =
+            format!("{}-{}-{}", VALUE_BASE_DOMAIN_POSTFIX, CURVE_ID, index).into_bytes();
+        postfix.push((4 + CURVE_ID.len() + VALUE_BASE_DOMAIN_POSTFIX.len()) as u8);
+        to_field_elements(&postfix)
+    };
+    assert_eq!(vec![RESOURCE_KIND_DOMAIN.u_0_postfix()], legacy_postfix(0));
+    assert_eq!(vec![RESOURCE_KIND_DOMAIN.u_1_postfix()], legacy_postfix(1));
+
+    let message = [pallas::Base::random(OsRng), pallas::Base::random(OsRng)];
+    let kind = ResourceKind::new(message[0], message[1]);
+    assert_eq!(
+        kind.derive_kind(),
+        hash_to_curve(&RESOURCE_KIND_DOMAIN, message)
+    );
+    assert_eq!(kind.derive_kind(), poseidon_to_curve::<3>(&message));
+
+    // The domains and the message order are separated
+    let domain = HashToCurveDomain::new("MyApp-Kinds");
+    assert_ne!(domain, *RESOURCE_KIND_DOMAIN);
+    assert_ne!(
+        hash_to_curve(&domain, message),
+        hash_to_curve(&RESOURCE_KIND_DOMAIN, message)
+    );
+    assert_ne!(
+        hash_to_curve(&RESOURCE_KIND_DOMAIN, [message[1], message[0]]),
+        hash_to_curve(&RESOURCE_KIND_DOMAIN, message)
+    );
+
+    let longest = "x".repeat(MAX_HASH_TO_CURVE_DOMAIN_LEN);
+    HashToCurveDomain::new(&longest);
+    assert!(std::panic::catch_unwind(|| HashToCurveDomain::new(&format!("{longest}x"))).is_err());
+}
+
+#[test]
+fn test_hash_to_field() {
+    use HashToFieldMethod::{Blake2b, Poseidon};
+
+    let personalization = b"MyApp_TokenName_";
+
+    // Known answers, computed with an independent implementation of both methods
+    assert_eq!(
+        *crate::constant::BURN_NPK,
+        pallas::Base::from_raw([
+            0xd68a1c5e9aa85ef7,
+            0x28370b7bb12b3fbb,
+            0xc1e323e4a00a8265,
+            0x2ae1953b64c73fb9,
+        ])
+    );
+    assert_eq!(
+        hash_to_field(Blake2b, personalization, b"Taiga"),
+        pallas::Base::from_raw([
+            0xb7a0b0ce8a04031d,
+            0xaede0ca2c08eb267,
+            0xbebfe8e6dfc96bb3,
+            0x189ac04a8b3ae26e,
+        ])
+    );
+    assert_eq!(
+        hash_to_field(Poseidon, personalization, b""),
+        pallas::Base::from_raw([
+            0x7cc09ff62fb37528,
+            0x5bc4f8f26de25bac,
+            0x285d5f31d26b1143,
+            0x231c8ed050c7a858,
+        ])
+    );
+    assert_eq!(
+        hash_to_field(Poseidon, personalization, b"Taiga"),
+        pallas::Base::from_raw([
+            0xfc642b095bec9765,
+            0x2c429e45ad573b5d,
+            0x6de8d2507db32309,
+            0x3512cd5123b1d307,
+        ])
+    );
+    // Two chunks, the second one partial
+    let message: Vec<u8> = (0..40).collect();
+    assert_eq!(
+        hash_to_field(Poseidon, personalization, &message),
+        pallas::Base::from_raw([
+            0xc0a13f006f7c3e76,
+            0xa9a937bd5bb98038,
+            0x327e683127e33a19,
+            0x2a2a43fb58b2d037,
+        ])
+    );
+
+    // The personalizations, the methods and the messages are separated, the length binds the
+    // trailing zeros
+    for method in [Blake2b, Poseidon] {
+        let hash = hash_to_field(method, personalization, b"Taiga");
+        assert_ne!(hash, hash_to_field(method, b"MyApp_TokenName2", b"Taiga"));
+        assert_ne!(hash, hash_to_field(method, personalization, b"taiga"));
+        assert_ne!(hash, hash_to_field(method, personalization, b"Taiga\0"));
+        assert_ne!(
+            hash_to_field(method, personalization, b""),
+            hash_to_field(method, personalization, b"\0")
+        );
+    }
+    assert_ne!(
+        hash_to_field(Blake2b, personalization, b"Taiga"),
+        hash_to_field(Poseidon, personalization, b"Taiga")
+    );
+}
+
+#[cfg(test)]
+const HASH_TO_CURVE_VECTORS_PATH: &str = "./params/hash_to_curve_vectors.txt";
+
+// The test vectors of hash_to_curve for the other implementations of the kind derivation: the
+// postfixes of the domains, then the messages and the affine coordinates of their points.
+#[cfg(test)]
+fn hash_to_curve_vectors() -> String {
+    use std::fmt::Write;
+
+    let hex = |field: &pallas::Base| hex_encode(&field.to_repr());
+    let messages = [
+        [pallas::Base::zero(), pallas::Base::zero()],
+        [pallas::Base::one(), pallas::Base::from(2)],
+        [-pallas::Base::one(), pallas::Base::from_u128(1 << 64)],
+    ];
+    let mut vectors = String::new();
+    // Writing to a String never fails
+    for domain_name in ["Taiga-NoteType", "MyApp-Kinds", ""] {
+        let domain = HashToCurveDomain::new(domain_name);
+        let _ = writeln!(
+            vectors,
+            "domain {domain_name:?}: postfixes {} {}",
+            hex(&domain.u_0_postfix()),
+            hex(&domain.u_1_postfix())
+        );
+        for message in messages.iter() {
+            let point = hash_to_curve(&domain, *message).to_affine();
+            let coordinates = point.coordinates().unwrap();
+            let _ = writeln!(
+                vectors,
+                "  {} {} -> {} {}",
+                hex(&message[0]),
+                hex(&message[1]),
+                hex(coordinates.x()),
+                hex(coordinates.y())
+            );
+        }
+    }
+    vectors
+}
+
+#[test]
+fn test_hash_to_curve_vectors_are_pinned() {
+    let pinned = std::fs::read_to_string(HASH_TO_CURVE_VECTORS_PATH).unwrap_or_else(|err| {
+        panic!(
+            "cannot read {HASH_TO_CURVE_VECTORS_PATH} with {err}, run \
+             export_hash_to_curve_vectors to create it"
+        )
+    });
+    assert_eq!(
+        pinned,
+        hash_to_curve_vectors(),
+        "the kind derivation changed, the kinds of the existing resources no longer match"
+    );
+}
+
+#[ignore]
+#[test]
+fn export_hash_to_curve_vectors() {
+    std::fs::write(HASH_TO_CURVE_VECTORS_PATH, hash_to_curve_vectors())
+        .unwrap_or_else(|err| panic!("cannot write the hash-to-curve vectors with {}", err));
+}
@@ -0,0 +1,127 @@
+/// Structured events of the transaction verification, for node operators diagnosing rejected
+/// transactions.
+///
+/// With the `tracing` feature, every step of `Transaction::execute` emits an event with the
+/// target `taiga::verification`: the proofs of the compliance units and resource logics, with
+/// the digest of their vk, and the cross-checks between them. The events carry the step, the
+/// index of the verified item, the elapsed time in microseconds and, for the failed steps, the
+/// error. The partial transactions and resources are entered as spans, so a subscriber can tell
+/// which component of the transaction failed. Without the feature the steps are run as is.
+use crate::{
+    constant::COMPLIANCE_VERIFYING_KEY, resource_logic_vk::ResourceLogicVerifyingKey,
+    utils::hex_encode,
+};
+use ff::PrimeField;
+use std::fmt::Display;
+
+pub const VERIFICATION_TRACE_TARGET: &str = "taiga::verification";
+
+/// The hex encoded compressed vk, as in the vk registry digests.
+pub(crate) fn vk_digest(vk: &ResourceLogicVerifyingKey) -> String {
+    hex_encode(&vk.get_compressed().to_repr())
+}
+
+pub(crate) fn compliance_vk_digest() -> String {
+    vk_digest(&ResourceLogicVerifyingKey::from_vk(
+        COMPLIANCE_VERIFYING_KEY.clone(),
+    ))
+}
+
+/// Runs the verification of a proof. The vk digest is only computed when the event is emitted.
+pub(crate) fn trace_proof<T, E: Display>(
+    step: &'static str,
+    index: usize,
+    digest: impl FnOnce() -> String,
+    verify: impl FnOnce() -> Result<T, E>,
+) -> Result<T, E> {
+    #[cfg(feature = "tracing")]
+    {
+        let start = std::time::Instant::now();
+        let result = verify();
+        let elapsed_us = start.elapsed().as_micros() as u64;
+        match &result {
+            Ok(_) => tracing::debug!(
+                target: VERIFICATION_TRACE_TARGET,
+                step,
+                index,
+                vk = %digest(),
+                elapsed_us,
+                "verification step passed"
+            ),
+            Err(e) => tracing::warn!(
+                target: VERIFICATION_TRACE_TARGET,
+                step,
+                index,
+                vk = %digest(),
+                elapsed_us,
+                error = %e,
+                "verification step failed"
+            ),
+        }
+        result
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        let _ = (step, index, digest);
+        verify()
+    }
+}
+
+/// Runs a check that doesn't verify a proof, e.g. the consistency of the nullifiers.
+pub(crate) fn trace_check<T, E: Display>(
+    step: &'static str,
+    index: usize,
+    check: impl FnOnce() -> Result<T, E>,
+) -> Result<T, E> {
+    #[cfg(feature = "tracing")]
+    {
+        let start = std::time::Instant::now();
+        let result = check();
+        let elapsed_us = start.elapsed().as_micros() as u64;
+        match &result {
+            Ok(_) => tracing::debug!(
+                target: VERIFICATION_TRACE_TARGET,
+                step,
+                index,
+                elapsed_us,
+                "verification step passed"
+            ),
+            Err(e) => tracing::warn!(
+                target: VERIFICATION_TRACE_TARGET,
+                step,
+                index,
+                elapsed_us,
+                error = %e,
+                "verification step failed"
+            ),
+        }
+        result
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        let _ = (step, index);
+        check()
+    }
+}
+
+/// The span of a component of the transaction, exited when dropped.
+pub(crate) struct ComponentSpan {
+    #[cfg(feature = "tracing")]
+    _span: tracing::span::EnteredSpan,
+}
+
+/// Enters the span of the component, e.g. ("shielded ptx", 1) or ("input resource", 0).
+pub(crate) fn enter_component(component: &'static str, index: usize) -> ComponentSpan {
+    #[cfg(feature = "tracing")]
+    {
+        ComponentSpan {
+            _span: tracing::info_span!(target: VERIFICATION_TRACE_TARGET, "component", component, index)
+                .entered(),
+        }
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        let _ = (component, index);
+        ComponentSpan {}
+    }
+}
@@ -0,0 +1,106 @@
+/// wasm-bindgen bindings for browser wallets: building, (de)serializing and verifying partial
+/// transactions and transactions.
+///
+/// The values cross the boundary as their borsh encodings, the layouts are documented in
+/// `taiga_api`. The partial transactions and transactions are prefixed with the format
+/// version, see `serialization`. A browser package is a cdylib crate depending on taiga_halo2 with the `wasm`
+/// feature, the bindings are exported from it. The randomness of the proofs comes from the
+/// crypto API of the browser.
+use crate::{
+    circuit::resource_logic_bytecode::ApplicationByteCode,
+    compliance::ComplianceInfo,
+    shielded_ptx::ShieldedPartialTransaction,
+    taiga_api,
+    transaction::{ShieldedPartialTxBundle, Transaction, TransparentPartialTxBundle},
+};
+use borsh::BorshDeserialize;
+use rand::rngs::OsRng;
+use std::fmt::Display;
+use wasm_bindgen::prelude::*;
+
+fn js_error(e: impl Display) -> JsError {
+    JsError::new(&e.to_string())
+}
+
+fn decode<T: BorshDeserialize>(bytes: &[u8]) -> Result<T, JsError> {
+    T::try_from_slice(bytes).map_err(js_error)
+}
+
+/// A shielded partial transaction.
+#[wasm_bindgen]
+pub struct PartialTransaction(ShieldedPartialTransaction);
+
+#[wasm_bindgen]
+impl PartialTransaction {
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<PartialTransaction, JsError> {
+        taiga_api::partial_transaction_deserialize(bytes.to_vec())
+            .map(Self)
+            .map_err(js_error)
+    }
+
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, JsError> {
+        taiga_api::partial_transaction_serialize(&self.0).map_err(js_error)
+    }
+
+    /// Verifies the proofs of the partial transaction.
+    pub fn verify(&self) -> Result<(), JsError> {
+        self.0.verify_proof().map_err(js_error)
+    }
+}
+
+/// Creates a shielded partial transaction from the borsh encoded compliance infos
+/// (`Vec<ComplianceInfo>`) and applications of the input and output resources
+/// (`Vec<ApplicationByteCode>`), generating all the proofs. Malformed inputs, e.g. a wrong
+/// number of compliances or a witness the resource logic rejects, reject with a `JsError`.
+#[wasm_bindgen(js_name = createShieldedPartialTransaction)]
+pub fn create_shielded_partial_transaction(
+    compliances: &[u8],
+    input_resource_apps: &[u8],
+    output_resource_apps: &[u8],
+    hints: Vec<u8>,
+) -> Result<PartialTransaction, JsError> {
+    let compliances: Vec<ComplianceInfo> = decode(compliances)?;
+    let input_resource_apps: Vec<ApplicationByteCode> = decode(input_resource_apps)?;
+    let output_resource_apps: Vec<ApplicationByteCode> = decode(output_resource_apps)?;
+    ShieldedPartialTransaction::from_bytecode(
+        compliances,
+        input_resource_apps,
+        output_resource_apps,
+        hints,
+        OsRng,
+    )
+    .map(PartialTransaction)
+    .map_err(js_error)
+}
+
+/// Generates the proofs of a borsh encoded `ApplicationByteCode`, returns the borsh encoded
+/// `ResourceLogicVerifyingInfoSet`.
+#[wasm_bindgen(js_name = generateProofs)]
+pub fn generate_proofs(application: &[u8]) -> Result<Vec<u8>, JsError> {
+    let application: ApplicationByteCode = decode(application)?;
+    let verifying_info_set = application.generate_proofs(OsRng).map_err(js_error)?;
+    borsh::to_vec(&verifying_info_set).map_err(js_error)
+}
+
+/// Builds a transaction from a borsh encoded `ShieldedPartialTxBundle`, returns the versioned
+/// encoding of the transaction.
+#[wasm_bindgen(js_name = buildTransaction)]
+pub fn build_transaction(shielded_ptx_bundle: &[u8]) -> Result<Vec<u8>, JsError> {
+    let shielded_ptx_bundle: ShieldedPartialTxBundle = decode(shielded_ptx_bundle)?;
+    let tx = Transaction::build(
+        OsRng,
+        shielded_ptx_bundle,
+        TransparentPartialTxBundle::default(),
+    )
+    .map_err(js_error)?;
+    taiga_api::transaction_serialize(&tx).map_err(js_error)
+}
+
+/// Executes a versioned encoding of a transaction, returns the borsh encoded `TransactionResult`.
+#[wasm_bindgen(js_name = executeTransaction)]
+pub fn execute_transaction(tx: &[u8]) -> Result<Vec<u8>, JsError> {
+    let result = taiga_api::verify_transaction(tx.to_vec()).map_err(js_error)?;
+    borsh::to_vec(&result).map_err(js_error)
+}